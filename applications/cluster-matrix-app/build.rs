@@ -21,6 +21,17 @@ fn main() {
         .unwrap()
         .write_all(include_bytes!("memory.x"))
         .unwrap();
+    // memory.x includes the FLASH region, smaller when frame recordings
+    // need their part of the flash
+    let flash: &[u8] = if env::var_os("CARGO_FEATURE_FRAME_RECORDING").is_some() {
+        include_bytes!("flash-frame-recording.x")
+    } else {
+        include_bytes!("flash.x")
+    };
+    File::create(out.join("flash.x"))
+        .unwrap()
+        .write_all(flash)
+        .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
     // By default, Cargo will re-run a build script whenever
@@ -28,6 +39,8 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=flash.x");
+    println!("cargo:rerun-if-changed=flash-frame-recording.x");
 
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
@@ -0,0 +1,2 @@
+/* Program flash, see memory.x */
+FLASH : ORIGIN = 0x10000000, LENGTH = 2048K - 64K - 4K
@@ -3,8 +3,13 @@ MEMORY {
      * The RP2350 has either external or internal flash.
      *
      * 2 MiB is a safe default here, although a Pico 2 has 4 MiB.
+     * The last 64 KiB are reserved for the persisted layout (see layout_store.rs)
+     * and the 4 KiB before them for the device configuration (see
+     * device_config.rs). With the frame-recording feature, the 128 KiB before
+     * those hold the frame recordings (see recorder.rs): build.rs picks the
+     * FLASH region from flash.x or flash-frame-recording.x.
      */
-    FLASH : ORIGIN = 0x10000000, LENGTH = 2048K
+    INCLUDE flash.x
     /*
      * RAM consists of 8 banks, SRAM0-SRAM7, with a striped mapping.
      * This is usually good for performance, as it distributes load on
@@ -0,0 +1,55 @@
+//! Startup mode, picked with the wake button at power-up
+//!
+//! - not held: normal boot;
+//! - held, then released within [`SAFE_MODE_HOLD`]: self-test, see
+//!   [`crate::diagnostics`];
+//! - still held after [`SAFE_MODE_HOLD`]: safe mode, see
+//!   [`crate::safe_mode`].
+//!
+//! A bad device configuration or stored layout could make
+//! every boot fail, and reflashing would be the only way out. The steps of
+//! the boot that depend on such data ask the [`BootMode`] before running,
+//! and `main` hands over to safe mode before the stored layout, the network
+//! and the render loop, so it gets a panel up with nothing but the firmware.
+
+use defmt::info;
+use embassy_rp::gpio;
+use embassy_time::{Duration, with_timeout};
+
+/// How long the button must stay held at power-up to enter safe mode
+pub const SAFE_MODE_HOLD: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum BootMode {
+    Normal,
+    /// Run the self-test before the normal views
+    SelfTest,
+    /// Skip everything read from flash or the network, show diagnostics
+    /// and offer a configuration reset
+    Safe,
+}
+
+impl BootMode {
+    /// Read the mode from the wake button (active low) as the firmware starts
+    pub async fn detect(button: &mut gpio::Input<'_>) -> Self {
+        if button.is_high() {
+            return Self::Normal;
+        }
+        let mode = match with_timeout(SAFE_MODE_HOLD, button.wait_for_high()).await {
+            Ok(()) => Self::SelfTest,
+            Err(_) => Self::Safe,
+        };
+        info!("Boot mode: {}", mode);
+        mode
+    }
+
+    /// Load the device configuration from flash
+    pub const fn loads_config(self) -> bool {
+        !matches!(self, Self::Safe)
+    }
+
+    /// Bring the network up and talk to the server
+    pub const fn starts_network(self) -> bool {
+        !matches!(self, Self::Safe)
+    }
+}
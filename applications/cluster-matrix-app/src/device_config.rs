@@ -0,0 +1,225 @@
+//! Settings of this install, persisted in flash
+//!
+//! Unlike the layout, which comes from the server, these describe the
+//! hardware the firmware runs on and are set up on site, e.g. by the
+//! geometry probe of the self-test, or per deployment like the messages
+//! shown for closed clusters, or on first boot like the device ID stored
+//! once the server provisioned the panel. The last preferences received
+//! from the server are kept too, so the panel starts with them before the
+//! network is up. They live in their own flash sector, reserved in memory.x
+//! right before the layout:
+//!
+//! ```text
+//! [magic "DCF1"][panel address lines u8]
+//! then per attribute of MESSAGE_ATTRIBUTES:
+//! [message length u8][message, MAX_OVERRIDE_LENGTH bytes]
+//! then [device ID, DEVICE_ID_LENGTH bytes][show raw data u8, 1 = raw]
+//! [locale u8, index in Locale::ALL]
+//! [occupancy alert raise % u8][occupancy alert clear % u8]
+//! [theme u8, 0 = dark, 1 = light][default floor u8, index in Layout::FLOORS]
+//! ```
+//!
+//! An erased sector, or an address line count the driver can't scan, loads
+//! as the default configuration. A message slot that is erased or doesn't
+//! hold UTF-8 keeps the localized default. Fields are only ever appended,
+//! and read as erased in a record saved before they existed: an erased
+//! device ID means the panel wasn't provisioned yet, and seat changes are
+//! smoothed unless the raw data flag is 1. An unknown locale index loads as
+//! the default locale, and alert percentages above 100 as the default
+//! thresholds (raise at 95%, clear below 90%). Preferences with an unknown
+//! theme or floor load as none received yet.
+
+use crate::layout_store::{FLASH_SIZE, LAYOUT_STORE_SIZE, StoreError};
+use cluster_core::messages::{FallbackMessages, MAX_OVERRIDE_LENGTH, MESSAGE_ATTRIBUTES};
+use cluster_core::models::Layout;
+use cluster_core::preferences::{Preferences, Theme};
+use cluster_core::visualization::AlertThresholds;
+use cluster_net::DeviceId;
+use cluster_net::device::DEVICE_ID_LENGTH;
+use defmt::{info, warn};
+use embassy_rp::flash::{Blocking, ERASE_SIZE, Flash};
+use embassy_rp::peripherals::FLASH;
+use graphics_common::i18n::Locale;
+use hub75_rp2350_driver::PanelGeometry;
+
+/// Size of the sector reserved for the device configuration
+pub const DEVICE_CONFIG_SIZE: usize = ERASE_SIZE;
+
+/// Offset of the configuration sector from the start of flash
+const DEVICE_CONFIG_OFFSET: u32 = (FLASH_SIZE - LAYOUT_STORE_SIZE - DEVICE_CONFIG_SIZE) as u32;
+
+const DEVICE_CONFIG_MAGIC: u32 = 0x4443_4631; // "DCF1"
+/// Length byte and text of a message override
+const OVERRIDE_SLOT_SIZE: usize = 1 + MAX_OVERRIDE_LENGTH;
+const MESSAGES_OFFSET: usize = 5;
+const DEVICE_ID_OFFSET: usize = MESSAGES_OFFSET + MESSAGE_ATTRIBUTES.len() * OVERRIDE_SLOT_SIZE;
+const RAW_DATA_OFFSET: usize = DEVICE_ID_OFFSET + DEVICE_ID_LENGTH;
+const LOCALE_OFFSET: usize = RAW_DATA_OFFSET + 1;
+const ALERT_OFFSET: usize = LOCALE_OFFSET + 1;
+const PREFERENCES_OFFSET: usize = ALERT_OFFSET + 2;
+const RECORD_SIZE: usize = PREFERENCES_OFFSET + 2;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceConfig {
+    /// Scan geometry found by the probe, `None` to use the driver's
+    pub geometry: Option<PanelGeometry>,
+    /// Messages of clusters without one, with this deployment's wording
+    pub messages: FallbackMessages,
+    /// ID announced to the server, `None` until the panel was provisioned
+    pub device_id: Option<DeviceId>,
+    /// Show seat changes on the first poll instead of smoothing them, see
+    /// [`LayoutStore::apply`](crate::layout_store::LayoutStore::apply)
+    pub show_raw_data: bool,
+    /// Language of the on-screen strings
+    pub locale: Locale,
+    /// Occupancy at which a floor is flagged as full, and at which the flag
+    /// clears
+    pub occupancy_alert: AlertThresholds,
+    /// Preferences last received from the server, `None` until then
+    pub preferences: Option<Preferences>,
+}
+
+impl defmt::Format for DeviceConfig {
+    fn format(&self, f: defmt::Formatter) {
+        let overrides = MESSAGE_ATTRIBUTES
+            .iter()
+            .filter(|(attribute, _)| self.messages.override_for(*attribute).is_some())
+            .count();
+        defmt::write!(
+            f,
+            "DeviceConfig {{ geometry: {}, message overrides: {}, device ID: {}, raw data: {}, locale: {}, alert: {}%/{}%, preferences: {} }}",
+            self.geometry,
+            overrides,
+            self.device_id,
+            self.show_raw_data,
+            defmt::Debug2Format(&self.locale),
+            self.occupancy_alert.raise_percent,
+            self.occupancy_alert.clear_percent,
+            defmt::Debug2Format(&self.preferences)
+        )
+    }
+}
+
+impl DeviceConfig {
+    /// Load the configuration, or the default one if none was saved
+    pub fn load(flash: &mut Flash<'_, FLASH, Blocking, FLASH_SIZE>) -> Self {
+        let mut record = [0u8; RECORD_SIZE];
+        if flash
+            .blocking_read(DEVICE_CONFIG_OFFSET, &mut record)
+            .is_err()
+        {
+            warn!("Failed to read the device configuration");
+            return Self::default();
+        }
+
+        let [m0, m1, m2, m3, lines, ..] = record;
+        if u32::from_le_bytes([m0, m1, m2, m3]) != DEVICE_CONFIG_MAGIC {
+            info!("No device configuration in flash");
+            return Self::default();
+        }
+        let mut messages = FallbackMessages::new();
+        for (slot, (attribute, _)) in record[MESSAGES_OFFSET..DEVICE_ID_OFFSET]
+            .chunks_exact(OVERRIDE_SLOT_SIZE)
+            .zip(MESSAGE_ATTRIBUTES)
+        {
+            let text = slot[1..]
+                .get(..usize::from(slot[0]))
+                .and_then(|text| core::str::from_utf8(text).ok());
+            if let Some(text) = text {
+                // Cannot fail: the attribute has a default and the text fits
+                let _ = messages.set_override(attribute, text);
+            }
+        }
+        let device_id = core::str::from_utf8(&record[DEVICE_ID_OFFSET..][..DEVICE_ID_LENGTH])
+            .ok()
+            .and_then(DeviceId::parse);
+        let theme = match record[PREFERENCES_OFFSET] {
+            0 => Some(Theme::Dark),
+            1 => Some(Theme::Light),
+            _ => None,
+        };
+        let default_floor = Layout::FLOORS
+            .get(usize::from(record[PREFERENCES_OFFSET + 1]))
+            .copied();
+        let config = Self {
+            geometry: PanelGeometry::from_address_lines(lines),
+            messages,
+            device_id,
+            show_raw_data: record[RAW_DATA_OFFSET] == 1,
+            locale: Locale::ALL
+                .get(usize::from(record[LOCALE_OFFSET]))
+                .copied()
+                .unwrap_or_default(),
+            occupancy_alert: match record[ALERT_OFFSET..][..2] {
+                [raise, clear] if raise <= 100 && clear <= 100 => {
+                    AlertThresholds::new(raise, clear)
+                }
+                _ => AlertThresholds::DEFAULT,
+            },
+            preferences: theme
+                .zip(default_floor)
+                .map(|(theme, default_floor)| Preferences {
+                    theme,
+                    default_floor,
+                }),
+        };
+        info!("Loaded device configuration: {}", config);
+        config
+    }
+
+    /// Persist the configuration, replacing the previous one
+    pub fn save(
+        &self,
+        flash: &mut Flash<'_, FLASH, Blocking, FLASH_SIZE>,
+    ) -> Result<(), StoreError> {
+        let mut record = [0xFFu8; RECORD_SIZE];
+        record[..4].copy_from_slice(&DEVICE_CONFIG_MAGIC.to_le_bytes());
+        if let Some(geometry) = self.geometry {
+            record[4] = geometry.address_lines();
+        }
+        for (slot, (attribute, _)) in record[MESSAGES_OFFSET..DEVICE_ID_OFFSET]
+            .chunks_exact_mut(OVERRIDE_SLOT_SIZE)
+            .zip(MESSAGE_ATTRIBUTES)
+        {
+            if let Some(text) = self.messages.override_for(attribute) {
+                slot[0] = text.len() as u8;
+                slot[1..=text.len()].copy_from_slice(text.as_bytes());
+            }
+        }
+        if let Some(device_id) = &self.device_id {
+            record[DEVICE_ID_OFFSET..][..DEVICE_ID_LENGTH]
+                .copy_from_slice(device_id.as_str().as_bytes());
+        }
+        record[RAW_DATA_OFFSET] = u8::from(self.show_raw_data);
+        if let Some(index) = Locale::ALL.iter().position(|&locale| locale == self.locale) {
+            record[LOCALE_OFFSET] = index as u8;
+        }
+        record[ALERT_OFFSET] = self.occupancy_alert.raise_percent;
+        record[ALERT_OFFSET + 1] = self.occupancy_alert.clear_percent;
+        if let Some(preferences) = &self.preferences {
+            record[PREFERENCES_OFFSET] = match preferences.theme {
+                Theme::Dark => 0,
+                Theme::Light => 1,
+            };
+            if let Some(index) = Layout::FLOORS
+                .iter()
+                .position(|&floor| floor == preferences.default_floor)
+            {
+                record[PREFERENCES_OFFSET + 1] = index as u8;
+            }
+        }
+
+        flash
+            .blocking_erase(
+                DEVICE_CONFIG_OFFSET,
+                DEVICE_CONFIG_OFFSET + DEVICE_CONFIG_SIZE as u32,
+            )
+            .map_err(|_| StoreError::Flash)?;
+        flash
+            .blocking_write(DEVICE_CONFIG_OFFSET, &record)
+            .map_err(|_| StoreError::Flash)?;
+
+        info!("Persisted device configuration: {}", self);
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Self-test run by holding the wake button at boot, see [`crate::boot`], or
+//! from the menu, see [`crate::menu`]
+//!
+//! Chains the checks from [`graphics_common::diagnostics`] so an install can
+//! be validated on site in about a minute:
+//!
+//! - geometry: [`probe_geometry`] asks the installer which rows light up and
+//!   saves the panel scan geometry, run from `main` while the flash is free;
+//! - display: cycles the test patterns, checked by eye;
+//! - input: waits for a press of the wake button;
+//! - network: waits for the network task to fetch a layout from the server;
+//! - flash: reads back the persisted layout record;
+//! - plugins: always skipped, this firmware doesn't load plugins (they run
+//!   in the simulator, see `plugin-host`).
+//!
+//! Each outcome is logged and the summary stays on screen before the normal
+//! views start.
+
+use crate::events::{EVENTS, wake_button};
+use crate::layout_store::{LAYOUT_STORE_SIZE, LayoutStore};
+use crate::network::SERVER_REACHED;
+use cluster_core::persist::{PersistError, decode_layout};
+use defmt::{info, warn};
+use embassy_time::{Duration, Timer, with_timeout};
+use graphics_common::diagnostics::geometry::GeometryProbe;
+use graphics_common::diagnostics::{
+    Check, Outcome, Report, TestPattern, draw_prompt, draw_summary,
+};
+use graphics_common::i18n::{Locale, MessageId};
+use hub75_rp2350_driver::{ADDRESS_LINES, Hub75, PanelGeometry};
+use matrix_driver::MatrixDriver;
+
+/// How long each test pattern is shown
+const PATTERN_TIME: Duration = Duration::from_millis(1500);
+/// How long to wait for the button press
+const INPUT_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long to wait for the network task to reach the server
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the summary stays on screen
+const SUMMARY_TIME: Duration = Duration::from_secs(20);
+/// How long the installer has to answer each geometry question
+const PROBE_ANSWER_TIME: Duration = Duration::from_secs(8);
+
+/// Find out how many rows the panel addresses, with the installer's help
+///
+/// Each question shows a green row at the top and a red one below it. The
+/// installer presses the button if the two are lit apart and waits if only
+/// one yellow row is lit, see [`GeometryProbe`]. Returns `None` if no
+/// answer made sense, leaving the display in the default geometry.
+pub async fn probe_geometry(display: &mut Hub75<'_>) -> Option<PanelGeometry> {
+    info!("Probing the panel geometry: press the button when a red and a green row are lit apart");
+    let mut events = EVENTS.subscribe()?;
+    display.set_geometry(PanelGeometry::DEFAULT);
+
+    let mut probe = GeometryProbe::new(ADDRESS_LINES);
+    while let Some(line) = probe.line() {
+        let Ok(()) = probe.draw(display);
+        display.commit();
+        let pressed = with_timeout(PROBE_ANSWER_TIME, wake_button(&mut events));
+        let two_rows = pressed.await.is_ok();
+        info!(
+            "Address line {}: {}",
+            line,
+            if two_rows { "two rows" } else { "one row" }
+        );
+        probe.answer(two_rows);
+    }
+    display.clear();
+    display.commit();
+
+    let geometry = probe
+        .address_lines()
+        .and_then(PanelGeometry::from_address_lines);
+    match geometry {
+        Some(geometry) => info!(
+            "Panel is {} rows high, 1/{} scan",
+            geometry.height(),
+            geometry.scan_rows()
+        ),
+        None => warn!("Panel geometry not found, keeping the default"),
+    }
+    geometry
+}
+
+/// Check that the layout record in flash is readable
+///
+/// An erased region passes: it is what a fresh install looks like.
+pub fn check_flash(store: &mut LayoutStore<'_>, scratch: &mut [u8]) -> Outcome {
+    let len = scratch.len().min(LAYOUT_STORE_SIZE);
+    if store.read(&mut scratch[..len]).is_err() {
+        return Outcome::Failed("flash read error");
+    }
+    match decode_layout(&scratch[..len]) {
+        Ok(_) | Err(PersistError::NotFound) => Outcome::Passed,
+        Err(_) => Outcome::Failed("layout record corrupted"),
+    }
+}
+
+/// Check that the network task reaches the server
+///
+/// Waits up to [`NETWORK_TIMEOUT`] for a first fetch when the link isn't
+/// up yet, e.g. right after boot.
+async fn check_network() -> Outcome {
+    let Some(mut reached) = SERVER_REACHED.receiver() else {
+        return Outcome::Failed("network status busy");
+    };
+    match with_timeout(NETWORK_TIMEOUT, reached.get()).await {
+        Ok(true) => Outcome::Passed,
+        Ok(false) => Outcome::Failed("server unreachable"),
+        Err(_) => Outcome::Failed("no network link"),
+    }
+}
+
+/// Run the self-test on the display
+///
+/// `flash` is the outcome of [`check_flash`], done at boot while the flash
+/// was available. Screens are shown in `locale`, logs stay in English.
+pub async fn run(display: &mut impl MatrixDriver, flash: Outcome, locale: Locale) -> Report {
+    info!("Starting self-test");
+    let mut report = Report::new();
+
+    for pattern in TestPattern::ALL {
+        let Ok(()) = pattern.draw(display);
+        display.commit();
+        Timer::after(PATTERN_TIME).await;
+    }
+    record(&mut report, Check::Display, Outcome::Passed);
+
+    let input = match EVENTS.subscribe() {
+        Some(mut events) => {
+            let title = MessageId::InputTest.text(locale);
+            let prompt = MessageId::PressButton.text(locale);
+            let Ok(()) = draw_prompt(display, title, prompt);
+            display.commit();
+            let pressed = with_timeout(INPUT_TIMEOUT, wake_button(&mut events));
+            match pressed.await {
+                Ok(()) => Outcome::Passed,
+                Err(_) => Outcome::Failed("no button press"),
+            }
+        }
+        None => Outcome::Failed("event bus full"),
+    };
+    record(&mut report, Check::Input, input);
+
+    record(&mut report, Check::Network, check_network().await);
+    record(&mut report, Check::Flash, flash);
+    record(
+        &mut report,
+        Check::Plugins,
+        Outcome::Skipped("no plugins in firmware"),
+    );
+
+    let Ok(()) = draw_summary(display, &report, locale);
+    display.commit();
+    if report.passed() {
+        info!("Self-test passed");
+    } else {
+        warn!("Self-test failed: {} check(s)", report.failures());
+    }
+    Timer::after(SUMMARY_TIME).await;
+    report
+}
+
+/// Set the outcome of a check, and log it
+pub fn record(report: &mut Report, check: Check, outcome: Outcome) {
+    match outcome.reason() {
+        Some(reason) => info!("{}: {} ({})", check.name(), outcome.label(), reason),
+        None => info!("{}: {}", check.name(), outcome.label()),
+    }
+    report.set(check, outcome);
+}
@@ -0,0 +1,39 @@
+//! Firmware event bus
+//!
+//! Subsystems publish what happened on [`EVENTS`] and any task can subscribe
+//! to react to it, so new consumers don't need changes on the producer side.
+//! Commands that need a single owner (e.g. [`POWER`](crate::power::POWER))
+//! keep their own channel; the bus reports what happened.
+
+use cluster_core::events::{EventBus, EventSubscriber};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+
+/// Events pending per subscriber before the oldest are skipped
+const EVENT_CAPACITY: usize = 8;
+
+/// Maximum number of concurrent subscribers
+const MAX_SUBSCRIBERS: usize = 4;
+
+pub static EVENTS: EventBus<CriticalSectionRawMutex, Event, EVENT_CAPACITY, MAX_SUBSCRIBERS> =
+    EventBus::new();
+
+/// Subscription to [`EVENTS`]
+pub type Subscriber =
+    EventSubscriber<'static, CriticalSectionRawMutex, Event, EVENT_CAPACITY, MAX_SUBSCRIBERS>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Event {
+    /// Wake button pressed
+    WakeButton,
+    /// Wake-on-LAN magic packet received
+    WakeOnLan,
+    /// Menu button pressed while the menu is shown, `held` to pick the entry
+    MenuButton { held: bool },
+    /// Displayed frame stopped (`true`) or started (`false`) changing
+    DisplayIdle(bool),
+}
+
+/// Wait for the next press of the wake button
+pub async fn wake_button(events: &mut Subscriber) {
+    while events.next().await != Event::WakeButton {}
+}
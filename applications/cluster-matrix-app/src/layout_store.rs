@@ -0,0 +1,182 @@
+//! Last good layout persisted in flash
+//!
+//! The layout is stored in a reserved region at the end of flash (excluded
+//! from `FLASH` in memory.x) so the panel can show it at boot, marked as stale,
+//! while the network comes up. Layouts fetched from the server are saved at
+//! most once per [`SAVE_INTERVAL`], see [`LayoutStore::save_throttled`].
+//!
+//! Fetched layouts reach the panel through [`LayoutStore::apply`], which
+//! smooths seat changes across polls (see [`SeatSmoother`]) unless the
+//! device configuration asks for raw data.
+
+use cluster_core::models::{Layout, PartialLayout};
+use cluster_core::persist::{PersistError, decode_layout, encode_layout};
+use cluster_core::smoothing::{DEFAULT_CONFIRM_POLLS, SeatSmoother};
+use defmt::{info, warn};
+use embassy_rp::Peri;
+use embassy_rp::flash::{Blocking, ERASE_SIZE, Flash};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Instant};
+
+/// Total flash size, must match memory.x plus the reserved region
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Size of the region reserved for the layout at the end of flash
+pub const LAYOUT_STORE_SIZE: usize = 64 * 1024;
+
+/// Minimum time between two saves of the fetched layout
+///
+/// Each save erases the whole region: hourly saves stay within the flash
+/// endurance over the life of the panel.
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Offset of the layout region from the start of flash
+const LAYOUT_STORE_OFFSET: u32 = (FLASH_SIZE - LAYOUT_STORE_SIZE) as u32;
+
+const _: () = assert!(LAYOUT_STORE_SIZE % ERASE_SIZE == 0);
+
+#[derive(Debug, defmt::Format)]
+pub enum StoreError {
+    Flash,
+    Encode,
+}
+
+/// Store shared by the tasks writing to flash once booted
+pub type SharedStore = Mutex<CriticalSectionRawMutex, LayoutStore<'static>>;
+
+pub struct LayoutStore<'d> {
+    flash: Flash<'d, FLASH, Blocking, FLASH_SIZE>,
+    /// When the layout was last saved since boot
+    saved_at: Option<Instant>,
+    /// Seat changes held back until confirmed by the next polls
+    smoother: SeatSmoother,
+}
+
+impl<'d> LayoutStore<'d> {
+    pub fn new(flash: Peri<'d, FLASH>) -> Self {
+        Self {
+            flash: Flash::new_blocking(flash),
+            saved_at: None,
+            smoother: SeatSmoother::new(DEFAULT_CONFIRM_POLLS),
+        }
+    }
+
+    /// Show seat changes on their first poll, or smooth them
+    pub fn set_raw_data(&mut self, raw: bool) {
+        let confirm_polls = if raw {
+            SeatSmoother::RAW
+        } else {
+            DEFAULT_CONFIRM_POLLS
+        };
+        self.smoother = SeatSmoother::new(confirm_polls);
+    }
+
+    /// Replace the `shown` layout with one `fetched` at `now_ms`
+    ///
+    /// Seat changes only show once seen on consecutive polls, unless set to
+    /// raw data. A `stale` layout, loaded from flash, is replaced as is.
+    pub fn apply(&mut self, shown: &mut Layout, stale: bool, fetched: Layout, now_ms: u64) {
+        if stale {
+            *shown = fetched;
+            return;
+        }
+        let Layout {
+            f0,
+            f1,
+            f1b,
+            f2,
+            f4,
+            f6,
+        } = fetched;
+        let partial = PartialLayout {
+            f0: Some(f0),
+            f1: Some(f1),
+            f1b: Some(f1b),
+            f2: Some(f2),
+            f4: Some(f4),
+            f6: Some(f6),
+        };
+        self.smoother.apply(shown, partial, now_ms);
+    }
+
+    /// Load the last persisted layout, if any
+    ///
+    /// `scratch` should be `LAYOUT_STORE_SIZE` bytes long.
+    pub fn load(&mut self, scratch: &mut [u8]) -> Option<Layout> {
+        let len = scratch.len().min(LAYOUT_STORE_SIZE);
+        if self.read(&mut scratch[..len]).is_err() {
+            warn!("Failed to read layout from flash");
+            return None;
+        }
+
+        match decode_layout(&scratch[..len]) {
+            Ok(layout) => {
+                info!("Loaded persisted layout from flash");
+                Some(layout)
+            }
+            Err(PersistError::NotFound) => {
+                info!("No persisted layout in flash");
+                None
+            }
+            Err(_) => {
+                warn!("Persisted layout is corrupted, ignoring it");
+                None
+            }
+        }
+    }
+
+    /// Flash of the store, for the regions reserved next to the layout
+    pub fn flash(&mut self) -> &mut Flash<'d, FLASH, Blocking, FLASH_SIZE> {
+        &mut self.flash
+    }
+
+    /// Read the raw layout region into `buffer`
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), StoreError> {
+        self.flash
+            .blocking_read(LAYOUT_STORE_OFFSET, buffer)
+            .map_err(|_| StoreError::Flash)
+    }
+
+    /// Persist `layout` unless it was saved less than [`SAVE_INTERVAL`] ago
+    ///
+    /// A failed save is retried on the next call.
+    pub fn save_throttled(
+        &mut self,
+        layout: &Layout,
+        scratch: &mut [u8],
+    ) -> Result<(), StoreError> {
+        if self
+            .saved_at
+            .is_some_and(|saved_at| saved_at.elapsed() < SAVE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.save(layout, scratch)?;
+        self.saved_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Persist `layout`, replacing the previous one
+    ///
+    /// Erases the whole region: layout updates go through
+    /// [`save_throttled`](Self::save_throttled) to limit flash wear.
+    pub fn save(&mut self, layout: &Layout, scratch: &mut [u8]) -> Result<(), StoreError> {
+        let len = scratch.len().min(LAYOUT_STORE_SIZE);
+        let written = encode_layout(layout, &mut scratch[..len]).map_err(|_| StoreError::Encode)?;
+
+        self.flash
+            .blocking_erase(
+                LAYOUT_STORE_OFFSET,
+                LAYOUT_STORE_OFFSET + LAYOUT_STORE_SIZE as u32,
+            )
+            .map_err(|_| StoreError::Flash)?;
+        self.flash
+            .blocking_write(LAYOUT_STORE_OFFSET, &scratch[..written])
+            .map_err(|_| StoreError::Flash)?;
+
+        info!("Persisted layout to flash ({} bytes)", written);
+        Ok(())
+    }
+}
@@ -3,42 +3,95 @@
 #![no_std]
 #![no_main]
 
+mod boot;
+mod compat;
+mod device_config;
+mod diagnostics;
+mod events;
+mod layout_store;
+mod menu;
+mod network;
+mod power;
+#[cfg(feature = "frame-recording")]
+mod recorder;
+mod safe_mode;
+mod scheduler;
+mod settings;
+#[cfg(feature = "usb-display")]
+mod usb_display;
+
+use crate::boot::BootMode;
+use crate::device_config::DeviceConfig;
+use crate::layout_store::{LAYOUT_STORE_SIZE, LayoutStore, SharedStore};
+use crate::menu::MenuEntry;
+use crate::network::EthernetPins;
+use crate::power::{
+    IDLE_AFTER_COMMITS, IDLE_FRAME_DELAY, POWER, PowerCommand, button_task, wait_or_command,
+};
+use crate::settings::SETTINGS;
 use cluster_core::models::Layout;
-use defmt::info;
+use cluster_core::visualization::{
+    AnimationView, BackgroundCache, ClusterView, HistoryView, RenderCtx, Renderer,
+};
+use defmt::{info, warn};
 use embassy_executor::Spawner;
 use embassy_rp::peripherals::*;
 use embassy_rp::{Peri, gpio};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
 use embassy_sync::rwlock::RwLock;
 use embassy_time::{Duration, Timer};
 use graphics_common::animations;
-use hub75_rp2350_driver::{DisplayMemory, Hub75};
+use graphics_common::diagnostics::Outcome;
+use hub75_rp2350_driver::{DisplayMemory, FadeTarget, Hub75, LowPowerConfig};
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
 // Static memory for the display - required for the driver
 static DISPLAY_MEMORY: StaticCell<DisplayMemory> = StaticCell::new();
 
-// Pin grouping structures to reduce parameter count
-pub struct Hub75Pins {
-    // RGB data pins
-    pub r1_pin: Peri<'static, PIN_0>,
-    pub g1_pin: Peri<'static, PIN_1>,
-    pub b1_pin: Peri<'static, PIN_2>,
-    pub r2_pin: Peri<'static, PIN_3>,
-    pub g2_pin: Peri<'static, PIN_4>,
-    pub b2_pin: Peri<'static, PIN_5>,
-    // Address pins
-    pub a_pin: Peri<'static, PIN_6>,
-    pub b_pin: Peri<'static, PIN_7>,
-    pub c_pin: Peri<'static, PIN_8>,
-    pub d_pin: Peri<'static, PIN_9>,
-    pub e_pin: Peri<'static, PIN_10>,
-    // Control pins
-    pub clk_pin: Peri<'static, PIN_11>,
-    pub lat_pin: Peri<'static, PIN_12>,
-    pub oe_pin: Peri<'static, PIN_13>,
-}
+// Scratch buffer for encoding/decoding the persisted layout
+static LAYOUT_SCRATCH: StaticCell<[u8; LAYOUT_STORE_SIZE]> = StaticCell::new();
+
+// Pre-rendered static layers of the cluster view
+static BACKGROUND_CACHE: StaticCell<BackgroundCache> = StaticCell::new();
+
+// Flash, shared once booted
+static STORE: StaticCell<SharedStore> = StaticCell::new();
+
+/// Brightness while awake
+const AWAKE_BRIGHTNESS: u8 = 255;
+/// Duration of the fade to black at the start of quiet hours
+const SLEEP_FADE_MS: u32 = 10_000;
+/// Duration of the ramp up at the end of quiet hours
+const WAKE_FADE_MS: u32 = 5_000;
+/// Brightness of the dimmed view shown once the sleep fade is over
+const LOW_POWER_BRIGHTNESS: u8 = 64;
+/// Refresh gating while in low-power mode: the panel is refreshed for
+/// `ON` ms out of every `ON + OFF` ms, and redrawn once per `CYCLES` periods
+const LOW_POWER_ON_MS: u64 = 4;
+const LOW_POWER_OFF_MS: u64 = 6;
+const LOW_POWER_CYCLES: u32 = 100;
+
+/// Frames spent on the cluster view before showing the history page
+const CLUSTER_PAGE_FRAMES: u32 = 60 * 20;
+/// Frames spent on the history page
+const HISTORY_PAGE_FRAMES: u32 = 60 * 5;
+/// Duration of the fade in when switching pages
+const PAGE_FADE_MS: u32 = 400;
+/// Occupancy history: one sample every 10 minutes over 24 hours
+const HISTORY_SAMPLE_MS: u64 = 10 * 60 * 1000;
+const HISTORY_SAMPLES: usize = 144;
+
+/// Frames between two logs of the frame hash, for fleet health checks
+const FRAME_HASH_LOG_FRAMES: u32 = 60 * 60;
+
+/// Frames between two dumps of the frame capture
+#[cfg(feature = "frame-capture")]
+const CAPTURE_DUMP_FRAMES: u32 = 60 * 10;
+
+// Pins and panel size of the board
+cluster_macros::matrix_config!("board.toml");
 
 pub struct DmaChannels {
     pub dma_ch0: Peri<'static, DMA_CH0>,
@@ -52,24 +105,7 @@ async fn main(spawner: Spawner) {
     let p = embassy_rp::init(Default::default());
 
     // Group pins and DMA channels
-    let pins = Hub75Pins {
-        r1_pin: p.PIN_0,
-        g1_pin: p.PIN_1,
-        b1_pin: p.PIN_2,
-        r2_pin: p.PIN_3,
-        g2_pin: p.PIN_4,
-        b2_pin: p.PIN_5,
-
-        a_pin: p.PIN_6,  // Changed from PIN_9
-        b_pin: p.PIN_7,  // Changed from PIN_10
-        c_pin: p.PIN_8,  // Changed from PIN_11
-        d_pin: p.PIN_9,  // Changed from PIN_12
-        e_pin: p.PIN_10, // Changed from PIN_13
-
-        clk_pin: p.PIN_11, // Changed from PIN_6
-        lat_pin: p.PIN_12, // Changed from PIN_7
-        oe_pin: p.PIN_13,  // Changed from PIN_8
-    };
+    let pins = hub75_pins!(p);
 
     let dma_channels = DmaChannels {
         dma_ch0: p.DMA_CH0,
@@ -78,8 +114,108 @@ async fn main(spawner: Spawner) {
         dma_ch3: p.DMA_CH3,
     };
 
+    // Wake button between PIN_14 and GND; holding it at boot runs the
+    // self-test, or safe mode if held longer
+    let mut button = gpio::Input::new(p.PIN_14, gpio::Pull::Up);
+    let boot_mode = BootMode::detect(&mut button).await;
+    let self_test = boot_mode == BootMode::SelfTest;
+    spawner.spawn(button_task(button).unwrap());
+
+    // Menu button between PIN_15 and GND, see menu.rs
+    let menu_button = gpio::Input::new(p.PIN_15, gpio::Pull::Up);
+    spawner.spawn(menu::menu_button_task(menu_button).unwrap());
+
+    // Create the LED matrix driver with PIO + DMA
+    let mut display = pins.into_display(
+        p.PIO0,
+        (
+            dma_channels.dma_ch0,
+            dma_channels.dma_ch1,
+            dma_channels.dma_ch2,
+            dma_channels.dma_ch3,
+        ),
+        DISPLAY_MEMORY.init(DisplayMemory::new()),
+    );
+    info!("Hub75 driver initialized - display running continuously with zero CPU overhead");
+
+    // Scan the panel as probed on site; the self-test probes it again
+    let mut store = LayoutStore::new(p.FLASH);
+    let mut device_config = if boot_mode.loads_config() {
+        DeviceConfig::load(store.flash())
+    } else {
+        DeviceConfig::default()
+    };
+    if self_test && let Some(geometry) = diagnostics::probe_geometry(&mut display).await {
+        device_config.geometry = Some(geometry);
+        if let Err(e) = device_config.save(store.flash()) {
+            warn!("Failed to save the device configuration: {}", e);
+        }
+    }
+    display.set_geometry(device_config.geometry.unwrap_or_default());
+    store.set_raw_data(device_config.show_raw_data);
+    SETTINGS.apply_preferences(&device_config.preferences.unwrap_or_default());
+
+    // Nothing else from flash, nor the network, until power cycled
+    let scratch = LAYOUT_SCRATCH.init([0; LAYOUT_STORE_SIZE]);
+    if boot_mode == BootMode::Safe {
+        safe_mode::run(&mut display, &mut store, scratch, device_config.locale).await;
+    }
+
+    // Show the last good layout while the network comes up; the flash is
+    // checked now for the self-test, at boot or from the menu
+    let flash_check = diagnostics::check_flash(&mut store, scratch);
+    let initial_state = match store.load(scratch) {
+        Some(layout) => State::Running {
+            layout,
+            stale: true,
+        },
+        None => State::Init,
+    };
+    let state = CLUSTERS.init(RwLock::new(initial_state));
+
+    // The recorder and the network task write to flash once booted
+    let store = STORE.init(Mutex::new(store));
+    #[cfg(feature = "frame-recording")]
+    spawner.spawn(recorder::recorder_task(store).unwrap());
+
     // Core 0 handles Hub75 matrix with PIO + DMA
-    spawner.spawn(matrix_task(p.PIO0, dma_channels, pins).unwrap());
+    spawner.spawn(
+        matrix_task(
+            display,
+            state,
+            self_test,
+            flash_check,
+            device_config.clone(),
+        )
+        .unwrap(),
+    );
+
+    // W6100 on SPI0, see network.rs
+    if boot_mode.starts_network() {
+        let chip_id = embassy_rp::otp::get_chipid().unwrap_or_else(|_| {
+            warn!("Failed to read the chip ID from OTP");
+            0
+        });
+        let ethernet = EthernetPins {
+            spi: p.SPI0,
+            miso: p.PIN_16,
+            mosi: p.PIN_19,
+            clk: p.PIN_18,
+            cs: p.PIN_17,
+            reset: p.PIN_20,
+            int: p.PIN_21,
+            tx_dma: p.DMA_CH4,
+            rx_dma: p.DMA_CH5,
+        };
+        let stack = network::start(&spawner, ethernet, chip_id).await;
+        spawner.spawn(
+            network::network_task(stack, state, store, scratch, device_config, chip_id).unwrap(),
+        );
+    }
+
+    // Frames streamed from a PC take over the panel while they keep coming
+    #[cfg(feature = "usb-display")]
+    usb_display::start(&spawner, p.USB);
 }
 
 enum ErrorState {
@@ -87,7 +223,11 @@ enum ErrorState {
 }
 enum State {
     Init,
-    Running(Layout),
+    /// Displaying a layout; `stale` until the first fresh fetch
+    Running {
+        layout: Layout,
+        stale: bool,
+    },
     // Error states
     Error(ErrorState),
 }
@@ -95,44 +235,46 @@ enum State {
 static CLUSTERS: StaticCell<RwLock<CriticalSectionRawMutex, State>> = StaticCell::new();
 
 #[embassy_executor::task]
-async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins: Hub75Pins) {
+async fn matrix_task(
+    mut display: Hub75<'static>,
+    state: &'static RwLock<CriticalSectionRawMutex, State>,
+    self_test: bool,
+    flash_check: Outcome,
+    config: DeviceConfig,
+) {
     info!("Starting Hub75 LED matrix control with 3 PIO SMs + chained DMA");
 
-    // Create the LED matrix driver with PIO + DMA
-    let mut display = Hub75::new(
-        pio,
-        (
-            dma_channels.dma_ch0,
-            dma_channels.dma_ch1,
-            dma_channels.dma_ch2,
-            dma_channels.dma_ch3,
-        ),
-        DISPLAY_MEMORY.init(DisplayMemory::new()),
-        // RGB data pins
-        pins.r1_pin,
-        pins.g1_pin,
-        pins.b1_pin,
-        pins.r2_pin,
-        pins.g2_pin,
-        pins.b2_pin,
-        pins.clk_pin,
-        // Address pins (all 5 for 64x64 display)
-        pins.a_pin,
-        pins.b_pin,
-        pins.c_pin,
-        pins.d_pin,
-        pins.e_pin,
-        // Control pins
-        pins.lat_pin,
-        pins.oe_pin,
-    );
-    info!("Hub75 driver initialized - display running continuously with zero CPU overhead");
+    if self_test {
+        diagnostics::run(&mut display, flash_check, config.locale).await;
+    }
+    display.set_idle_callback(Some((IDLE_AFTER_COMMITS, power::display_idle)));
 
     // Animation frame counter and time tracking
     let mut frame_counter: u32 = 0;
     let mut last_time = embassy_time::Instant::now();
-
-    let state = CLUSTERS.init(RwLock::new(State::Init));
+    // Invalidated whenever a new layout is written to `state`
+    let mut cluster_view = ClusterView::new(
+        BACKGROUND_CACHE.init(BackgroundCache::new()),
+        config.occupancy_alert,
+    );
+    cluster_view
+        .renderer_mut()
+        .set_fallback_messages(config.messages);
+    let mut history_view: HistoryView<HISTORY_SAMPLES> = HistoryView::new(HISTORY_SAMPLE_MS);
+    let mut animation_view: AnimationView<Hub75<'_>> =
+        AnimationView::new(animations::fortytwo::draw_animation_frame);
+    #[cfg(feature = "usb-display")]
+    let mut usb_display_view = usb_display::UsbDisplayView;
+    let mut awake_brightness = AWAKE_BRIGHTNESS;
+    // Faded out and showing the dim low-power view
+    let mut asleep = false;
+    // Power command that cut the last frame's wait short
+    let mut pending_power = None;
+    let mut settings = SETTINGS.receiver().unwrap();
+    // Followed from the settings, the base of the changes made in the menu
+    let mut preferences = config.preferences.unwrap_or_default();
+    #[cfg(feature = "frame-capture")]
+    let mut dumped_capture = 0;
 
     // Main animation loop - no need to call update(), display runs automatically!
     loop {
@@ -146,29 +288,144 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
             info!("Animation FPS: {}", fps);
         }
 
-        // Measure animation frame drawing time
-        let anim_start = embassy_time::Instant::now();
+        if network::LAYOUT_UPDATED.try_take().is_some() {
+            cluster_view.invalidate();
+        }
+
+        // The menu, and what is picked in it, take the panel over at full
+        // brightness
+        if menu::OPEN.try_take().is_some() {
+            asleep = false;
+            display.set_low_power(None);
+            display.set_fade(u8::MAX);
+            display.set_brightness(awake_brightness);
+            match menu::run(&mut display, config.locale).await {
+                MenuEntry::Brightness => {
+                    SETTINGS.set_brightness(menu::next_brightness(awake_brightness));
+                }
+                entry @ (MenuEntry::Theme | MenuEntry::Floor) => {
+                    if let Some(picked) = menu::next_preferences(entry, &preferences) {
+                        SETTINGS.apply_preferences(&picked);
+                        network::LOCAL_PREFERENCES.signal(picked);
+                    }
+                }
+                MenuEntry::SelfTest => {
+                    diagnostics::run(&mut display, flash_check, config.locale).await;
+                }
+                MenuEntry::Close => {}
+            }
+            cluster_view.invalidate();
+            last_time = embassy_time::Instant::now();
+            continue;
+        }
 
-        match &*state.read().await {
-            State::Init => animations::fortytwo::draw_animation_frame(&mut display, frame_counter),
-            State::Running(layout) => {
-                cluster_core::visualization::draw_cluster_frame(&mut display, layout, frame_counter)
+        // Pick up settings changed since the last frame
+        let changes = settings.changes();
+        if let Some(level) = changes.brightness {
+            awake_brightness = level;
+        }
+        if let Some(theme) = changes.theme {
+            preferences.theme = theme;
+            cluster_view.set_theme(theme);
+            history_view.renderer_mut().set_theme(theme);
+        }
+        if let Some(cluster) = changes.assignment {
+            preferences.default_floor = cluster;
+            cluster_view.renderer_mut().set_selected_cluster(cluster);
+            history_view.renderer_mut().set_selected_cluster(cluster);
+        }
+
+        // Brightness and fade are applied as pixels are drawn, so set them
+        // before rendering
+        let now_ms = current_time.as_millis();
+        match pending_power.take().or_else(|| POWER.try_take()) {
+            Some(PowerCommand::Sleep) if !asleep => {
+                display.fade_to(FadeTarget::Black, SLEEP_FADE_MS, now_ms);
+            }
+            Some(PowerCommand::Sleep) | None => {}
+            Some(PowerCommand::Wake) => wake(&mut display, &mut asleep, now_ms),
+            Some(PowerCommand::WakeNow) => {
+                asleep = false;
+                display.set_fade(u8::MAX);
             }
-            State::Error(_) => {
-                // Draw error state animation
-                animations::fortytwo::draw_animation_frame(&mut display, frame_counter)
+            Some(PowerCommand::PauseDisplay) => {
+                // Blocks until woken; show the panel at the requested level
+                match power::pause_display(&mut display).await {
+                    PowerCommand::WakeNow => {
+                        asleep = false;
+                        display.set_fade(u8::MAX);
+                    }
+                    _ => wake(
+                        &mut display,
+                        &mut asleep,
+                        embassy_time::Instant::now().as_millis(),
+                    ),
+                }
+                last_time = embassy_time::Instant::now();
+                continue;
             }
         }
-        .unwrap();
+
+        // Fade each page in, unless a sleep or wake fade is running
+        let page = frame_counter % (CLUSTER_PAGE_FRAMES + HISTORY_PAGE_FRAMES);
+        if (page == 0 || page == CLUSTER_PAGE_FRAMES) && !asleep && !display.is_fading() {
+            display.set_fade(0);
+            display.fade_to(FadeTarget::Frame, PAGE_FADE_MS, now_ms);
+        }
+
+        // Once faded out, keep a dim view up in low-power refresh mode
+        if !display.update_fade(now_ms) && display.fade_level() == 0 && !asleep {
+            asleep = true;
+            display.set_fade(u8::MAX);
+        }
+        if asleep != display.low_power().is_some() {
+            display.set_low_power(asleep.then_some(LowPowerConfig::DEFAULT));
+        }
+        display.set_brightness(if asleep {
+            LOW_POWER_BRIGHTNESS
+        } else {
+            awake_brightness
+        });
+
+        // Measure animation frame drawing time
+        let anim_start = embassy_time::Instant::now();
+
+        let current = state.read().await;
+        let mut ctx = RenderCtx::new(frame_counter, now_ms).with_locale(config.locale);
+        let view: &mut dyn Renderer<Hub75<'_>> = match &*current {
+            #[cfg(feature = "usb-display")]
+            _ if usb_display_view.is_active(current_time) => &mut usb_display_view,
+            State::Running { layout, stale } => {
+                history_view.record(layout, now_ms);
+                ctx = ctx.with_layout(layout, *stale);
+
+                if page < CLUSTER_PAGE_FRAMES {
+                    &mut cluster_view
+                } else {
+                    &mut history_view
+                }
+            }
+            // Error states show the animation too for now
+            State::Init | State::Error(_) => &mut animation_view,
+        };
+        let Ok(()) = view.render(&mut display, &ctx);
+        drop(current);
 
         let anim_time = anim_start.elapsed();
 
         // Commit the buffer - this makes it visible on the display
-        // This is very fast (just a pointer swap) and non-blocking
+        // This is fast (a hash and a pointer swap) and non-blocking; identical
+        // frames are dropped
         let commit_start = embassy_time::Instant::now();
         display.commit();
         let commit_time = commit_start.elapsed();
 
+        // Keep the frame for the recorder if it changed
+        #[cfg(feature = "frame-recording")]
+        if display.unchanged_commits() == 0 {
+            recorder::record(now_ms, display.shown_frame());
+        }
+
         if frame_counter % 60 == 0 {
             info!(
                 "Animation draw time: {}us, Buffer commit time: {}us",
@@ -177,9 +434,55 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
             );
         }
 
+        // A hash that never changes or matches the blank frame flags a
+        // frozen or dark panel
+        if frame_counter.is_multiple_of(FRAME_HASH_LOG_FRAMES) {
+            info!(
+                "Frame hash: {=u32:#x}, blank: {}, unchanged for {} commits",
+                display.frame_hash(),
+                display.is_blank(),
+                display.unchanged_commits()
+            );
+        }
+
+        // Hex dump of what the DMA feeds the data state machine, when it changed
+        #[cfg(feature = "frame-capture")]
+        if frame_counter.is_multiple_of(CAPTURE_DUMP_FRAMES)
+            && display.capture().frames() != dumped_capture
+        {
+            dumped_capture = display.capture().frames();
+            info!("Frame capture #{}", dumped_capture);
+            for line in display.capture().hex_dump() {
+                info!("{}", defmt::Display2Format(&line));
+            }
+        }
+
+        // In low-power mode, only refresh the panel part of the time until the
+        // next redraw, or until a power command comes in
+        if display.low_power().is_some() {
+            for _ in 0..LOW_POWER_CYCLES {
+                display.resume_refresh();
+                pending_power = wait_or_command(Duration::from_millis(LOW_POWER_ON_MS)).await;
+                if pending_power.is_some() {
+                    break;
+                }
+                display.pause_refresh();
+                pending_power = wait_or_command(Duration::from_millis(LOW_POWER_OFF_MS)).await;
+                if pending_power.is_some() {
+                    break;
+                }
+            }
+            display.resume_refresh();
+        }
+
         // Control animation frame rate (optional - you can go as fast as you want)
         // Timer::after(Duration::from_millis(16)).await; // ~60 FPS animation
 
+        // Nothing changed on screen for a while, no need to redraw at full speed
+        if pending_power.is_none() && power::is_display_idle() {
+            pending_power = wait_or_command(IDLE_FRAME_DELAY).await;
+        }
+
         // Increment frame counter
         frame_counter = frame_counter.wrapping_add(1);
     }
@@ -196,3 +499,11 @@ async fn core1_task(mut led: gpio::Output<'static>) {
         Timer::after(Duration::from_secs(1)).await;
     }
 }
+
+/// Fade the panel back in, from black if it was showing the low-power view
+fn wake(display: &mut Hub75<'_>, asleep: &mut bool, now_ms: u64) {
+    if core::mem::take(asleep) {
+        display.set_fade(0);
+    }
+    display.fade_to(FadeTarget::Frame, WAKE_FADE_MS, now_ms);
+}
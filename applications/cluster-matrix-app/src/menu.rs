@@ -0,0 +1,174 @@
+//! On-device menu, opened with the menu button
+//!
+//! The menu button sits between PIN_15 and GND. While the menu is closed, a
+//! press opens it over the current view; then a press moves to the next
+//! entry and holding the button for [`SELECT_HOLD`] picks the shown one. The
+//! menu closes by itself after [`MENU_TIMEOUT`] without a press.
+//!
+//! - Brightness: steps the awake brightness down, wrapping back to full;
+//! - Theme: switches between the dark and light themes;
+//! - Floor: shows the next floor, also at boot from then on;
+//! - Self-test: runs [`crate::diagnostics`];
+//! - Close.
+//!
+//! Theme and floor are the site's preferences: the render loop hands them
+//! to [`crate::network`], which saves them and pushes them to the server.
+//!
+//! The render loop owns the display, so [`menu_button_task`] only raises
+//! [`OPEN`] and reports presses on the event bus; [`run`] draws the menu and
+//! returns the picked entry for the render loop to act on.
+
+use crate::events::{EVENTS, Event, Subscriber};
+use cluster_core::models::Layout;
+use cluster_core::preferences::{Preferences, Theme};
+use core::sync::atomic::{AtomicBool, Ordering};
+use defmt::{info, warn};
+use embassy_rp::gpio;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer, with_timeout};
+use graphics_common::diagnostics::draw_prompt;
+use graphics_common::i18n::{Locale, MessageId};
+use matrix_driver::MatrixDriver;
+
+/// Raised by a press of the menu button while the menu is closed
+pub static OPEN: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Set while [`run`] shows the menu
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// How long the button must stay held to pick an entry
+const SELECT_HOLD: Duration = Duration::from_secs(1);
+
+/// How long the menu stays up without a press
+const MENU_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Awake brightness levels the menu steps through, from the brightest
+const BRIGHTNESS_LEVELS: [u8; 4] = [255, 160, 96, 32];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum MenuEntry {
+    /// Step the brightness, see [`next_brightness`]
+    Brightness,
+    /// Switch the theme, see [`next_preferences`]
+    Theme,
+    /// Show the next floor, see [`next_preferences`]
+    Floor,
+    /// Run the self-test, see [`crate::diagnostics`]
+    SelfTest,
+    Close,
+}
+
+impl MenuEntry {
+    /// Entries in the order the button goes through them
+    pub const ALL: [Self; 5] = [
+        Self::Brightness,
+        Self::Theme,
+        Self::Floor,
+        Self::SelfTest,
+        Self::Close,
+    ];
+
+    const fn label(self) -> MessageId {
+        match self {
+            Self::Brightness => MessageId::MenuBrightness,
+            Self::Theme => MessageId::MenuTheme,
+            Self::Floor => MessageId::MenuFloor,
+            Self::SelfTest => MessageId::SelfTest,
+            Self::Close => MessageId::MenuClose,
+        }
+    }
+}
+
+/// Brightness level after `level`, back to full after the dimmest
+pub fn next_brightness(level: u8) -> u8 {
+    BRIGHTNESS_LEVELS
+        .into_iter()
+        .find(|&next| next < level)
+        .unwrap_or(BRIGHTNESS_LEVELS[0])
+}
+
+/// Preferences after picking `entry`, `None` for entries not about them
+///
+/// The floor goes up through [`Layout::FLOORS`], back to the lowest after
+/// the highest.
+pub fn next_preferences(entry: MenuEntry, current: &Preferences) -> Option<Preferences> {
+    match entry {
+        MenuEntry::Theme => Some(Preferences {
+            theme: match current.theme {
+                Theme::Dark => Theme::Light,
+                Theme::Light => Theme::Dark,
+            },
+            ..*current
+        }),
+        MenuEntry::Floor => {
+            let floors = Layout::FLOORS;
+            let next = floors
+                .iter()
+                .position(|&floor| floor == current.default_floor)
+                .map_or(0, |index| (index + 1) % floors.len());
+            Some(Preferences {
+                default_floor: floors[next],
+                ..*current
+            })
+        }
+        MenuEntry::Brightness | MenuEntry::SelfTest | MenuEntry::Close => None,
+    }
+}
+
+/// Show the menu until an entry is picked
+///
+/// Returns [`MenuEntry::Close`] when the menu timed out.
+pub async fn run(display: &mut impl MatrixDriver, locale: Locale) -> MenuEntry {
+    let Some(mut events) = EVENTS.subscribe() else {
+        warn!("Event bus full, menu unavailable");
+        return MenuEntry::Close;
+    };
+    SHOWN.store(true, Ordering::Relaxed);
+    info!("Menu opened");
+
+    let mut shown = 0;
+    let picked = loop {
+        let entry = MenuEntry::ALL[shown];
+        let title = MessageId::Menu.text(locale);
+        let Ok(()) = draw_prompt(display, title, entry.label().text(locale));
+        display.commit();
+        match with_timeout(MENU_TIMEOUT, menu_press(&mut events)).await {
+            Ok(false) => shown = (shown + 1) % MenuEntry::ALL.len(),
+            Ok(true) => break entry,
+            Err(_) => break MenuEntry::Close,
+        }
+    };
+
+    info!("Menu closed: {}", picked);
+    SHOWN.store(false, Ordering::Relaxed);
+    picked
+}
+
+/// Wait for the next press of the menu button, `true` if held
+async fn menu_press(events: &mut Subscriber) -> bool {
+    loop {
+        if let Event::MenuButton { held } = events.next().await {
+            return held;
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn menu_button_task(mut button: gpio::Input<'static>) {
+    loop {
+        button.wait_for_falling_edge().await;
+        // Debounce
+        Timer::after(Duration::from_millis(50)).await;
+        let held = with_timeout(SELECT_HOLD, button.wait_for_high())
+            .await
+            .is_err();
+
+        if SHOWN.load(Ordering::Relaxed) {
+            EVENTS.publish(Event::MenuButton { held });
+        } else {
+            OPEN.signal(());
+        }
+        button.wait_for_high().await;
+    }
+}
@@ -0,0 +1,340 @@
+//! Ethernet link to the cluster server
+//!
+//! The panel reaches the server through a WIZnet W6100 on SPI0, wired as in
+//! the eth-test hardware test: MISO=16, MOSI=19, SCLK=18, CSn=17, RSTn=20,
+//! INTn=21. [`start`] brings the chip and a DHCP stack up, then
+//! [`network_task`] talks to the server:
+//!
+//! - on first boot, a panel without a device ID in its configuration
+//!   announces itself with [`Endpoints::provision`] and stores the ID, so
+//!   later boots skip the handshake;
+//! - every [`POLL_INTERVAL`] the layout is fetched and shown, its seat
+//!   changes smoothed (see [`LayoutStore::apply`]), and saved to flash for
+//!   the next boot (see [`LayoutStore::save_throttled`]);
+//! - the layout also drives quiet hours, see [`crate::scheduler`];
+//! - the site's preferences are synced both ways: changes made from the
+//!   menu (see [`LOCAL_PREFERENCES`]) are saved and pushed to the server
+//!   right away, then the server's are fetched with each layout, applied and
+//!   saved when they changed. The server wins over a change it did not take.
+//!
+//! INTn belongs to the chip driver, so wake-on-LAN magic packets are read
+//! from a UDP socket by [`wake_on_lan_task`] rather than from the pin.
+//!
+//! [`LayoutStore::apply`]: crate::layout_store::LayoutStore::apply
+//! [`LayoutStore::save_throttled`]: crate::layout_store::LayoutStore::save_throttled
+
+use crate::State;
+use crate::compat::StackAdapter;
+use crate::device_config::DeviceConfig;
+use crate::events::{EVENTS, Event};
+use crate::layout_store::SharedStore;
+use crate::power::{POWER, PowerCommand};
+use crate::scheduler::Scheduler;
+use crate::settings::SETTINGS;
+use cluster_core::models::Layout;
+use cluster_core::preferences::{PreferenceSync, Preferences};
+use cluster_net::DeviceId;
+use cluster_net::client::{Client, ClientConfig};
+use cluster_net::endpoints::Endpoints;
+use defmt::{info, unwrap, warn};
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{Stack, StackResources};
+use embassy_net_wiznet::chip::W6100;
+use embassy_net_wiznet::{Device, Runner, State as ChipState};
+use embassy_rp::Peri;
+use embassy_rp::clocks::RoscRng;
+use embassy_rp::gpio::{Input, Level, Output, Pull};
+use embassy_rp::peripherals::*;
+use embassy_rp::spi::{Async, Config as SpiConfig, Spi};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::rwlock::RwLock;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::Watch;
+use embassy_time::{Delay, Duration, Instant, Timer};
+use embedded_hal_bus::spi::ExclusiveDevice;
+use static_cell::StaticCell;
+
+/// Cluster server of the deployment
+const SERVER_URL: &str = "http://clusters.local:8080";
+
+/// Firmware version reported when provisioning
+const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Delay between two layout fetches, and provisioning attempts
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Response buffer, large enough for the complete layout
+const RESPONSE_BUFFER_SIZE: usize = 16 * 1024;
+
+/// UDP port wake-on-LAN magic packets are sent to
+const WAKE_ON_LAN_PORT: u16 = 9;
+
+/// Length of a magic packet: 6 bytes of 0xFF, then the MAC address 16 times
+const MAGIC_PACKET_LENGTH: usize = 6 + 16 * 6;
+
+/// Raised when a new layout was written to the shown state
+pub static LAYOUT_UPDATED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Preferences changed on the device, to save and push to the server
+pub static LOCAL_PREFERENCES: Signal<CriticalSectionRawMutex, Preferences> = Signal::new();
+
+/// Whether the last layout fetch succeeded, unset until the link is up
+pub static SERVER_REACHED: Watch<CriticalSectionRawMutex, bool, 1> = Watch::new();
+
+type W6100Spi = ExclusiveDevice<Spi<'static, SPI0, Async>, Output<'static>, Delay>;
+
+/// Pins and peripherals of the W6100
+pub struct EthernetPins {
+    pub spi: Peri<'static, SPI0>,
+    pub miso: Peri<'static, PIN_16>,
+    pub mosi: Peri<'static, PIN_19>,
+    pub clk: Peri<'static, PIN_18>,
+    pub cs: Peri<'static, PIN_17>,
+    pub reset: Peri<'static, PIN_20>,
+    pub int: Peri<'static, PIN_21>,
+    pub tx_dma: Peri<'static, DMA_CH4>,
+    pub rx_dma: Peri<'static, DMA_CH5>,
+}
+
+/// Bring the W6100 and the network stack up
+///
+/// The MAC address is a locally administered one derived from `chip_id`, so
+/// it stays the same across boots.
+pub async fn start(spawner: &Spawner, pins: EthernetPins, chip_id: u64) -> Stack<'static> {
+    let mut spi_cfg = SpiConfig::default();
+    spi_cfg.frequency = 50_000_000;
+    let spi = Spi::new(
+        pins.spi,
+        pins.clk,
+        pins.mosi,
+        pins.miso,
+        pins.tx_dma,
+        pins.rx_dma,
+        spi_cfg,
+    );
+    let cs = Output::new(pins.cs, Level::High);
+    let int = Input::new(pins.int, Pull::Up);
+    let reset = Output::new(pins.reset, Level::High);
+    let spi_dev = ExclusiveDevice::new(spi, cs, Delay).unwrap();
+
+    let [a, b, c, d, e, ..] = chip_id.to_le_bytes();
+    let mac = [0x02, a, b, c, d, e];
+    static CHIP_STATE: StaticCell<ChipState<8, 8>> = StaticCell::new();
+    let (device, runner) =
+        embassy_net_wiznet::new(mac, CHIP_STATE.init(ChipState::new()), spi_dev, int, reset)
+            .await
+            .unwrap();
+    spawner.spawn(ethernet_task(runner).unwrap());
+
+    // Requests to the server, DNS and the wake-on-LAN socket
+    static RESOURCES: StaticCell<StackResources<4>> = StaticCell::new();
+    let (stack, runner) = embassy_net::new(
+        device,
+        embassy_net::Config::dhcpv4(Default::default()),
+        RESOURCES.init(StackResources::new()),
+        RoscRng.next_u64(),
+    );
+    spawner.spawn(net_task(runner).unwrap());
+    spawner.spawn(wake_on_lan_task(stack, mac).unwrap());
+    info!("Ethernet started, MAC {:02x}", mac);
+    stack
+}
+
+#[embassy_executor::task]
+async fn ethernet_task(
+    runner: Runner<'static, W6100, W6100Spi, Input<'static>, Output<'static>>,
+) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn net_task(mut runner: embassy_net::Runner<'static, Device<'static>>) -> ! {
+    runner.run().await
+}
+
+/// Talk to the cluster server once the link is configured
+///
+/// `config` is the device configuration loaded at boot, saved again with
+/// the device ID once provisioned and with new preferences, local or from
+/// the server. Fetched layouts
+/// replace the one in `state`; `scratch` encodes them for flash.
+#[embassy_executor::task]
+pub async fn network_task(
+    stack: Stack<'static>,
+    state: &'static RwLock<CriticalSectionRawMutex, State>,
+    store: &'static SharedStore,
+    scratch: &'static mut [u8],
+    mut config: DeviceConfig,
+    chip_id: u64,
+) {
+    stack.wait_config_up().await;
+    if let Some(ip) = stack.config_v4() {
+        info!("Network configured, address {}", ip.address);
+    }
+
+    let adapter = StackAdapter::new(&stack);
+    let client_config = unwrap!(ClientConfig::new(SERVER_URL));
+    let mut device_id = match &config.device_id {
+        Some(device_id) => device_id.clone(),
+        None => DeviceId::from_unique_id(chip_id),
+    };
+    info!("Device ID: {}", device_id);
+    let mut buffer = [0u8; RESPONSE_BUFFER_SIZE];
+    let mut scheduler = Scheduler::new();
+    let mut preferences = PreferenceSync::new(config.preferences);
+    // Local preferences that cut the last wait short
+    let mut local_preferences = None;
+
+    loop {
+        // First boot: announce the panel, then remember it was provisioned
+        if config.device_id.is_none() {
+            let mut client: Client<StackAdapter, StackAdapter> =
+                Client::new(client_config.clone(), &adapter, &adapter);
+            match Endpoints::provision(&mut client, &device_id, FIRMWARE_VERSION, &mut buffer).await
+            {
+                Ok(provisioning) => {
+                    info!("Provisioned at {}", provisioning.location.as_str());
+                    config.device_id = Some(device_id.clone());
+                    if let Err(e) = config.save(store.lock().await.flash()) {
+                        warn!("Failed to save the device ID: {}", e);
+                    }
+                }
+                Err(e) => warn!("Provisioning failed: {}", e),
+            }
+        }
+
+        // Changed from the menu: saved now, pushed until the server takes it
+        if let Some(local) = local_preferences
+            .take()
+            .or_else(|| LOCAL_PREFERENCES.try_take())
+            && preferences.set(local)
+        {
+            config.preferences = Some(local);
+            if let Err(e) = config.save(store.lock().await.flash()) {
+                warn!("Failed to save the preferences: {}", e);
+            }
+        }
+
+        let (fetched, fetched_preferences) = {
+            let mut client: Client<StackAdapter, StackAdapter> =
+                Client::new(client_config.clone(), &adapter, &adapter)
+                    .with_middleware(&mut device_id);
+            if let Some(pending) = preferences.pending().copied() {
+                match Endpoints::push_preferences(&mut client, &pending, &mut buffer).await {
+                    Ok(()) => preferences.push_succeeded(&pending),
+                    Err(e) => warn!("Failed to push the preferences: {}", e),
+                }
+            }
+            (
+                Endpoints::get_layout(&mut client, &mut buffer).await,
+                Endpoints::get_preferences(&mut client, &mut buffer).await,
+            )
+        };
+        SERVER_REACHED.sender().send(fetched.is_ok());
+        match fetched {
+            Ok(layout) => {
+                let command = scheduler.update(&layout, Instant::now());
+                show_layout(state, store, scratch, layout).await;
+                if let Some(command) = command {
+                    POWER.signal(command);
+                }
+            }
+            Err(e) => warn!("Failed to fetch the layout: {}", e),
+        }
+        match fetched_preferences {
+            Ok(fetched) if preferences.apply_server(fetched) => {
+                info!("Preferences changed on the server");
+                SETTINGS.apply_preferences(preferences.current());
+                config.preferences = Some(*preferences.current());
+                if let Err(e) = config.save(store.lock().await.flash()) {
+                    warn!("Failed to save the preferences: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to fetch the preferences: {}", e),
+        }
+
+        if let Either::Second(local) =
+            select(Timer::after(POLL_INTERVAL), LOCAL_PREFERENCES.wait()).await
+        {
+            local_preferences = Some(local);
+        }
+    }
+}
+
+/// Show a layout fetched from the server, and persist it now and then
+async fn show_layout(
+    state: &RwLock<CriticalSectionRawMutex, State>,
+    store: &SharedStore,
+    scratch: &mut [u8],
+    layout: Layout,
+) {
+    let mut store = store.lock().await;
+    let mut current = state.write().await;
+    match &mut *current {
+        State::Running {
+            layout: shown,
+            stale,
+        } => {
+            store.apply(shown, *stale, layout, Instant::now().as_millis());
+            *stale = false;
+        }
+        other => {
+            *other = State::Running {
+                layout,
+                stale: false,
+            }
+        }
+    }
+    drop(current);
+    LAYOUT_UPDATED.signal(());
+
+    let current = state.read().await;
+    if let State::Running { layout, .. } = &*current
+        && let Err(e) = store.save_throttled(layout, scratch)
+    {
+        warn!("Failed to save the layout: {}", e);
+    }
+}
+
+/// Wake the panel when a magic packet for `mac` arrives
+#[embassy_executor::task]
+async fn wake_on_lan_task(stack: Stack<'static>, mac: [u8; 6]) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 2];
+    let mut rx_buffer = [0u8; 256];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0u8; 16];
+    // Room for a SecureOn password after the MAC addresses
+    let mut packet = [0u8; 128];
+
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    unwrap!(socket.bind(WAKE_ON_LAN_PORT));
+
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut packet).await else {
+            continue;
+        };
+        if is_magic_packet(&packet[..len], &mac) {
+            info!("Wake-on-LAN packet");
+            EVENTS.publish(Event::WakeOnLan);
+            POWER.signal(PowerCommand::WakeNow);
+        }
+    }
+}
+
+/// Check for a magic packet addressed to `mac`
+fn is_magic_packet(packet: &[u8], mac: &[u8; 6]) -> bool {
+    packet.len() >= MAGIC_PACKET_LENGTH
+        && packet[..6].iter().all(|&byte| byte == 0xFF)
+        && packet[6..MAGIC_PACKET_LENGTH]
+            .chunks_exact(6)
+            .all(|chunk| chunk == mac)
+}
@@ -0,0 +1,125 @@
+//! Power management
+//!
+//! Dimming and low-power refresh cover quiet hours, but the panel still
+//! draws current while it scans. For longer periods (nights, weekends) the
+//! scheduler can pause the display: the PIO state machines and DMA are
+//! stopped with OE held high, so the LEDs draw nothing, and the render loop
+//! stops until a wake source fires. Wake sources are the wake button and
+//! wake-on-LAN magic packets, received by the network stack (see
+//! [`crate::network`]).
+//!
+//! This is not a low-power state of the chip: the network stack must keep
+//! running to receive magic packets, so clocks stay up and the core only
+//! idles in the executor (WFE) as usual. The board has no switch on the
+//! panel supply either, so the panel's drivers stay powered.
+//!
+//! Display memory is left untouched while paused, so the last committed
+//! frame is shown again as soon as refresh resumes. The low-power refresh
+//! loop waits on [`POWER`] too, so a wake request cuts it short.
+//!
+//! Separately, the driver reports when the content stops changing
+//! ([`display_idle`]); the render loop then redraws less often.
+
+use crate::events::{EVENTS, Event};
+use core::sync::atomic::{AtomicBool, Ordering};
+use defmt::info;
+use embassy_futures::select::{Either, select};
+use embassy_rp::gpio;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use hub75_rp2350_driver::Hub75;
+
+/// Sleep/wake requests for the display, from the scheduler or a wake source
+pub static POWER: Signal<CriticalSectionRawMutex, PowerCommand> = Signal::new();
+
+/// Set while the displayed frame hasn't changed for a while
+static DISPLAY_IDLE: AtomicBool = AtomicBool::new(false);
+
+/// Commits without change before the display counts as idle
+pub const IDLE_AFTER_COMMITS: u32 = 120;
+
+/// Delay between redraws while the display is idle
+pub const IDLE_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+pub enum PowerCommand {
+    /// Fade out for quiet hours
+    Sleep,
+    /// Ramp back up after quiet hours
+    Wake,
+    /// Wake at full brightness immediately
+    WakeNow,
+    /// Stop refreshing the panel until a wake source fires, see
+    /// [`pause_display`]
+    PauseDisplay,
+}
+
+/// Stop refreshing the panel and wait for a wake request
+///
+/// Refresh and the low-power mode in effect before the pause are restored
+/// on wake. Returns the command that ended the sleep: [`PowerCommand::WakeNow`]
+/// from a wake source or a scheduled [`PowerCommand::Wake`].
+pub async fn pause_display(display: &mut Hub75<'_>) -> PowerCommand {
+    let low_power = display.low_power();
+    display.pause_refresh();
+    info!("Pausing the display");
+    let paused_at = Instant::now();
+
+    let command = loop {
+        match POWER.wait().await {
+            command @ (PowerCommand::Wake | PowerCommand::WakeNow) => break command,
+            // Already off
+            PowerCommand::Sleep | PowerCommand::PauseDisplay => {}
+        }
+    };
+
+    info!(
+        "Resuming the display after {}s",
+        paused_at.elapsed().as_secs()
+    );
+    display.set_low_power(low_power);
+    display.resume_refresh();
+    command
+}
+
+/// Wait for `duration`, or until a power command arrives
+pub async fn wait_or_command(duration: Duration) -> Option<PowerCommand> {
+    match select(Timer::after(duration), POWER.wait()).await {
+        Either::First(()) => None,
+        Either::Second(command) => Some(command),
+    }
+}
+
+/// Idle callback for `Hub75::set_idle_callback`
+pub fn display_idle(idle: bool) {
+    info!("Display {}", if idle { "idle" } else { "active" });
+    DISPLAY_IDLE.store(idle, Ordering::Relaxed);
+    EVENTS.publish(Event::DisplayIdle(idle));
+}
+
+/// Check if the displayed frame has stopped changing
+pub fn is_display_idle() -> bool {
+    DISPLAY_IDLE.load(Ordering::Relaxed)
+}
+
+#[embassy_executor::task]
+pub async fn button_task(mut button: gpio::Input<'static>) {
+    loop {
+        button.wait_for_falling_edge().await;
+        info!("Wake button pressed");
+        EVENTS.publish(Event::WakeButton);
+        POWER.signal(PowerCommand::WakeNow);
+
+        // Debounce
+        Timer::after(Duration::from_millis(50)).await;
+        #[cfg(feature = "frame-recording")]
+        if embassy_time::with_timeout(crate::recorder::HOLD_TO_SAVE, button.wait_for_high())
+            .await
+            .is_err()
+        {
+            info!("Wake button held, saving the frame recording");
+            crate::recorder::request_save();
+        }
+        button.wait_for_high().await;
+    }
+}
@@ -0,0 +1,172 @@
+//! Frame recorder for postmortems of visual glitches
+//!
+//! With the `frame-recording` feature, every frame committed to the panel
+//! is kept, run-length encoded, in a RAM ring of about [`RECORDING_SLOT_SIZE`]
+//! bytes. With `frame-recording-heatshrink` the frames are compressed with
+//! heatshrink instead, see [`CODEC`]. Holding the wake button for
+//! [`HOLD_TO_SAVE`], or a command calling [`request_save`], copies the ring
+//! to flash: recordings go to [`RECORDING_SLOTS`] slots in turn, in a region
+//! reserved before the device configuration (see flash-frame-recording.x),
+//! so a new recording doesn't overwrite the previous one.
+//!
+//! Frames are encoded before the ring is locked, and the ring is copied out
+//! before the flash is written, so the lock is only held for memory copies.
+//! The render loop and [`recorder_task`] both run on the thread-mode
+//! executor, and the lock leaves interrupts on.
+//!
+//! Read the region back with picotool while the board is in BOOTSEL mode
+//! and play the latest recording in the simulator:
+//!
+//! ```text
+//! picotool save -r 0x101CF000 0x101EF000 recordings.bin
+//! sim replay recordings.bin
+//! ```
+
+use crate::device_config::DEVICE_CONFIG_SIZE;
+use crate::layout_store::{FLASH_SIZE, LAYOUT_STORE_SIZE, SharedStore};
+use cluster_core::codec::Codec;
+use cluster_core::recording::{FrameRing, HEADER_SIZE, RecordingHeader};
+use core::cell::RefCell;
+use defmt::{info, warn};
+use embassy_rp::flash::{Blocking, ERASE_SIZE, Error, Flash};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_sync::signal::Signal;
+use embassy_time::Duration;
+use heapless::Vec;
+use hub75_rp2350_driver::{RECORDED_HEIGHT, RECORDED_PIXELS, RECORDED_WIDTH};
+use static_cell::StaticCell;
+
+/// Size of a recording slot in flash
+pub const RECORDING_SLOT_SIZE: usize = 64 * 1024;
+
+/// Recordings kept in flash
+pub const RECORDING_SLOTS: usize = 2;
+
+/// How long the wake button must be held to save a recording
+pub const HOLD_TO_SAVE: Duration = Duration::from_secs(3);
+
+/// Offset of the recording region from the start of flash, right before
+/// the device configuration
+const RECORDING_OFFSET: u32 =
+    (FLASH_SIZE - LAYOUT_STORE_SIZE - DEVICE_CONFIG_SIZE - RECORDING_SLOTS * RECORDING_SLOT_SIZE)
+        as u32;
+
+/// Size of the RAM ring, which fills a slot along with the header
+const RING_BYTES: usize = RECORDING_SLOT_SIZE - HEADER_SIZE;
+
+/// Largest encoding of a recorded frame
+const FRAME_BYTES: usize = Codec::max_encoded_size(RECORDED_PIXELS);
+
+const _: () = assert!(RECORDING_SLOT_SIZE % ERASE_SIZE == 0);
+// The 128K reserved in flash-frame-recording.x
+const _: () = assert!(RECORDING_SLOTS * RECORDING_SLOT_SIZE == 128 * 1024);
+
+/// Codec of the recorded frames
+///
+/// Heatshrink keeps text, gradients and seat grids several times smaller
+/// than runs, so a recording goes further back, but encoding a frame takes
+/// tens of milliseconds where runs take well under one (see the
+/// `codec_bench` example of cluster-core). The render loop pays it on
+/// every commit.
+pub const CODEC: Codec = if cfg!(feature = "frame-recording-heatshrink") {
+    Codec::Heatshrink
+} else {
+    Codec::Rle
+};
+
+static RING: Mutex<ThreadModeRawMutex, RefCell<FrameRing<RING_BYTES>>> =
+    Mutex::new(RefCell::new(FrameRing::with_codec(CODEC)));
+
+/// Frame being encoded by [`record`]
+static ENCODED: Mutex<ThreadModeRawMutex, RefCell<Vec<u8, FRAME_BYTES>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// Copy of the ring that [`recorder_task`] writes to flash
+static SAVED: StaticCell<[u8; RING_BYTES]> = StaticCell::new();
+
+static SAVE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Record a frame committed at `now_ms`
+pub fn record(now_ms: u64, pixels: &[u16]) {
+    ENCODED.lock(|encoded| {
+        let mut encoded = encoded.borrow_mut();
+        encoded.clear();
+        CODEC.encode(pixels, |byte| {
+            // Never full, see FRAME_BYTES
+            let _ = encoded.push(byte);
+        });
+        RING.lock(|ring| ring.borrow_mut().push_encoded(now_ms as u32, &encoded));
+    });
+}
+
+/// Copy the recorded frames to flash
+pub fn request_save() {
+    SAVE.signal(());
+}
+
+/// Save the recorded frames to flash when requested
+///
+/// Borrows the flash from the layout store, shared with the network task.
+#[embassy_executor::task]
+pub async fn recorder_task(store: &'static SharedStore) {
+    let saved = SAVED.init([0; RING_BYTES]);
+    let mut sequence = latest_sequence(store.lock().await.flash()).unwrap_or(0);
+
+    loop {
+        SAVE.wait().await;
+        sequence = sequence.wrapping_add(1);
+        let slot = sequence as usize % RECORDING_SLOTS;
+
+        // Frames keep being recorded while the copy is written
+        let (header, frames, len) = RING.lock(|ring| {
+            let ring = ring.borrow();
+            let (first, second) = ring.as_slices();
+            saved[..first.len()].copy_from_slice(first);
+            saved[first.len()..ring.len()].copy_from_slice(second);
+            let header = ring.header(sequence, RECORDED_WIDTH as u16, RECORDED_HEIGHT as u16);
+            (header, ring.frames(), ring.len())
+        });
+
+        let mut store = store.lock().await;
+        match save(store.flash(), &header, &saved[..len], slot) {
+            Ok(()) => info!(
+                "Saved recording {} ({} frames) to slot {}",
+                sequence, frames, slot
+            ),
+            Err(_) => warn!("Failed to save recording {}", sequence),
+        }
+    }
+}
+
+/// Sequence number of the latest recording in flash
+fn latest_sequence(flash: &mut Flash<'_, FLASH, Blocking, FLASH_SIZE>) -> Option<u32> {
+    (0..RECORDING_SLOTS)
+        .filter_map(|slot| {
+            let mut header = [0; HEADER_SIZE];
+            flash.blocking_read(slot_offset(slot), &mut header).ok()?;
+            RecordingHeader::parse(&header).ok()
+        })
+        .map(|header| header.sequence)
+        .max()
+}
+
+/// Write a recording, its header and the frames copied from the ring, to
+/// a slot
+fn save(
+    flash: &mut Flash<'_, FLASH, Blocking, FLASH_SIZE>,
+    header: &[u8; HEADER_SIZE],
+    frames: &[u8],
+    slot: usize,
+) -> Result<(), Error> {
+    let offset = slot_offset(slot);
+    flash.blocking_erase(offset, offset + RECORDING_SLOT_SIZE as u32)?;
+    flash.blocking_write(offset + HEADER_SIZE as u32, frames)?;
+    // Last, so a torn write leaves no recording rather than a broken one
+    flash.blocking_write(offset, header)
+}
+
+const fn slot_offset(slot: usize) -> u32 {
+    RECORDING_OFFSET + (slot * RECORDING_SLOT_SIZE) as u32
+}
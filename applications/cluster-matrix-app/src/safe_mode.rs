@@ -0,0 +1,92 @@
+//! Safe mode, entered by holding the wake button at power-up
+//!
+//! The panel comes up in the driver's default geometry, without the device
+//! configuration, the stored layout, the network or plugins. It cycles
+//! through a diagnostics summary and a reset prompt: pressing the button on
+//! the prompt, then again to confirm, erases the device configuration. Safe
+//! mode lasts until the panel is power cycled.
+
+use crate::device_config::DeviceConfig;
+use crate::diagnostics::{check_flash, record};
+use crate::events::{EVENTS, wake_button};
+use crate::layout_store::LayoutStore;
+use defmt::{info, warn};
+use embassy_time::{Duration, with_timeout};
+use graphics_common::diagnostics::{Check, Outcome, Report, draw_prompt, draw_summary};
+use graphics_common::i18n::{Locale, MessageId};
+use hub75_rp2350_driver::Hub75;
+
+/// How long each screen stays up without a button press
+const SCREEN_TIME: Duration = Duration::from_secs(10);
+
+/// Show diagnostics and offer a configuration reset, forever
+pub async fn run(
+    display: &mut Hub75<'_>,
+    store: &mut LayoutStore<'_>,
+    scratch: &mut [u8],
+    locale: Locale,
+) -> ! {
+    warn!("Safe mode: no device configuration, stored layout, network or plugins");
+
+    let mut report = Report::new();
+    let skipped = Outcome::Skipped("safe mode");
+    record(&mut report, Check::Display, skipped);
+    record(&mut report, Check::Input, skipped);
+    record(&mut report, Check::Network, skipped);
+    record(&mut report, Check::Flash, check_flash(store, scratch));
+    record(&mut report, Check::Plugins, skipped);
+
+    let Some(mut events) = EVENTS.subscribe() else {
+        warn!("Event bus full, configuration reset unavailable");
+        let Ok(()) = draw_summary(display, &report, locale);
+        display.commit();
+        loop {
+            core::future::pending::<()>().await;
+        }
+    };
+
+    let title = MessageId::SafeMode.text(locale);
+    loop {
+        let Ok(()) = draw_summary(display, &report, locale);
+        display.commit();
+        let _ = with_timeout(SCREEN_TIME, wake_button(&mut events)).await;
+
+        let Ok(()) = draw_prompt(display, title, MessageId::ResetConfig.text(locale));
+        display.commit();
+        if with_timeout(SCREEN_TIME, wake_button(&mut events))
+            .await
+            .is_err()
+        {
+            continue;
+        }
+
+        let Ok(()) = draw_prompt(display, title, MessageId::ConfirmReset.text(locale));
+        display.commit();
+        if with_timeout(SCREEN_TIME, wake_button(&mut events))
+            .await
+            .is_err()
+        {
+            continue;
+        }
+
+        match DeviceConfig::default().save(store.flash()) {
+            Ok(()) => {
+                info!("Device configuration reset");
+                let title = MessageId::ConfigReset.text(locale);
+                let Ok(()) = draw_prompt(display, title, MessageId::PowerCycle.text(locale));
+                display.commit();
+                loop {
+                    core::future::pending::<()>().await;
+                }
+            }
+            Err(e) => {
+                warn!("Failed to reset the device configuration: {}", e);
+                record(
+                    &mut report,
+                    Check::Flash,
+                    Outcome::Failed("config reset failed"),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Quiet hours scheduler
+//!
+//! The panel has no wall clock, so quiet hours follow the server: once every
+//! floor of a fetched layout is marked [`Attribute::Closed`], the display
+//! fades out ([`PowerCommand::Sleep`]), and the first layout with an open
+//! floor ramps it back up ([`PowerCommand::Wake`]). Closed for
+//! [`PAUSE_AFTER`] (a night, a weekend), the panel stops refreshing
+//! altogether ([`PowerCommand::PauseDisplay`]). The wake button and wake-on-LAN
+//! still bring it back at once in between.
+
+use crate::power::PowerCommand;
+use cluster_core::models::Layout;
+use cluster_core::types::Attribute;
+use defmt::info;
+use embassy_time::{Duration, Instant};
+
+/// Time closed before the panel goes from dimmed to paused
+const PAUSE_AFTER: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Follows the opening state of the campus from the fetched layouts
+pub struct Scheduler {
+    /// When every floor was first seen closed
+    closed_since: Option<Instant>,
+    paused: bool,
+}
+
+impl Scheduler {
+    pub const fn new() -> Self {
+        Self {
+            closed_since: None,
+            paused: false,
+        }
+    }
+
+    /// Power command for a layout fetched at `now`, if the state changed
+    pub fn update(&mut self, layout: &Layout, now: Instant) -> Option<PowerCommand> {
+        let closed = layout
+            .clusters()
+            .iter()
+            .all(|(_, cluster)| cluster.attributes.contains(&Attribute::Closed));
+        match (closed, self.closed_since) {
+            (true, None) => {
+                info!("Every floor is closed, quiet hours");
+                self.closed_since = Some(now);
+                Some(PowerCommand::Sleep)
+            }
+            (true, Some(since)) if !self.paused && now - since >= PAUSE_AFTER => {
+                info!("Closed for a while, pausing the display");
+                self.paused = true;
+                Some(PowerCommand::PauseDisplay)
+            }
+            (false, Some(_)) => {
+                info!("Floors open again");
+                self.closed_since = None;
+                self.paused = false;
+                Some(PowerCommand::Wake)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Settings that can change while the panel runs
+//!
+//! Brightness, theme and floor assignment used to be fixed at boot. They are
+//! now sent to [`SETTINGS`] (see [`cluster_core::settings`]): the brightness
+//! from the menu, the theme and floor from the preferences stored in flash
+//! and fetched from the server. The render task picks up the latest values
+//! at the start of each frame.
+
+use cluster_core::settings::Settings;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+
+/// Tasks that can follow the settings
+const RECEIVERS: usize = 2;
+
+pub static SETTINGS: Settings<CriticalSectionRawMutex, RECEIVERS> = Settings::new();
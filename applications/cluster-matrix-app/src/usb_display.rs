@@ -0,0 +1,172 @@
+//! USB display mode
+//!
+//! The device enumerates as a USB CDC ACM serial port. A desktop app can
+//! stream whole frames to it (`sim usb-display` in the simulator is a sample
+//! sender), and while they keep coming the panel shows them instead of the
+//! usual views: a generic PC-driven display for events. Frames use the
+//! framing of `cluster_core::framing`, with kind
+//! [`KIND_DISPLAY_FRAME`] and the panel pixels as payload.
+//!
+//! Frames are decoded as packets arrive and the last complete one is kept
+//! for the render loop. The receive task and the render loop run on the same
+//! executor, so handing the frame over never waits.
+
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use cluster_core::framing::{FrameDecoder, KIND_DISPLAY_FRAME};
+use cluster_core::visualization::{RenderCtx, Renderer};
+use core::cell::RefCell;
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_rp::{Peri, bind_interrupts};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::{Builder, Config, UsbDevice};
+use embedded_graphics::image::{Image, ImageRawLE};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+type UsbDriver = Driver<'static, USB>;
+
+/// Size of a display frame payload: the whole panel in RGB565
+pub const FRAME_BYTES: usize = (DISPLAY_WIDTH * DISPLAY_HEIGHT * 2) as usize;
+
+/// Time without frames after which the panel goes back to its views
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Full-speed bulk packet size
+const MAX_PACKET_SIZE: u16 = 64;
+
+/// Test VID/PID from pid.codes, to be replaced before shipping devices
+const USB_VID: u16 = 0x1209;
+const USB_PID: u16 = 0x0001;
+
+/// Last complete frame and when it arrived
+struct Received {
+    pixels: [u8; FRAME_BYTES],
+    at: Option<Instant>,
+}
+
+static RECEIVED: Mutex<ThreadModeRawMutex, RefCell<Received>> =
+    Mutex::new(RefCell::new(Received {
+        pixels: [0; FRAME_BYTES],
+        at: None,
+    }));
+
+static DECODER: StaticCell<FrameDecoder<FRAME_BYTES>> = StaticCell::new();
+
+/// Start the USB device and the frame receiver
+///
+/// Must be called with the spawner of the executor running the render loop.
+pub fn start(spawner: &Spawner, usb: Peri<'static, USB>) {
+    let driver = Driver::new(usb, Irqs);
+
+    let mut config = Config::new(USB_VID, USB_PID);
+    config.manufacturer = Some("42");
+    config.product = Some("Cluster matrix display");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static CDC_STATE: StaticCell<State> = StaticCell::new();
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(&mut builder, CDC_STATE.init(State::new()), MAX_PACKET_SIZE);
+    let usb = builder.build();
+
+    spawner.spawn(usb_task(usb).unwrap());
+    spawner.spawn(receive_task(class).unwrap());
+}
+
+#[embassy_executor::task]
+async fn usb_task(mut usb: UsbDevice<'static, UsbDriver>) -> ! {
+    usb.run().await
+}
+
+#[embassy_executor::task]
+async fn receive_task(mut class: CdcAcmClass<'static, UsbDriver>) -> ! {
+    let decoder = DECODER.init(FrameDecoder::new());
+    let mut packet = [0; MAX_PACKET_SIZE as usize];
+    let mut next_sequence: Option<u8> = None;
+
+    loop {
+        class.wait_connection().await;
+        info!("USB display host connected");
+
+        while let Ok(len) = class.read_packet(&mut packet).await {
+            for &byte in &packet[..len] {
+                match decoder.push(byte) {
+                    Some(Ok(frame))
+                        if frame.kind == KIND_DISPLAY_FRAME
+                            && frame.payload.len() == FRAME_BYTES =>
+                    {
+                        if next_sequence.is_some_and(|expected| expected != frame.sequence) {
+                            warn!("USB display frames lost before #{}", frame.sequence);
+                        }
+                        next_sequence = Some(frame.sequence.wrapping_add(1));
+                        RECEIVED.lock(|received| {
+                            let mut received = received.borrow_mut();
+                            received.pixels.copy_from_slice(frame.payload);
+                            received.at = Some(Instant::now());
+                        });
+                    }
+                    Some(Ok(frame)) => warn!(
+                        "Ignoring USB frame of kind {} with {} bytes",
+                        frame.kind,
+                        frame.payload.len()
+                    ),
+                    Some(Err(error)) => {
+                        warn!("Dropped USB frame: {}", defmt::Display2Format(&error))
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        info!("USB display host disconnected");
+        decoder.reset();
+        next_sequence = None;
+    }
+}
+
+/// View of the frames streamed over USB
+pub struct UsbDisplayView;
+
+impl UsbDisplayView {
+    /// Check if a frame arrived recently enough to take over the panel
+    pub fn is_active(&self, now: Instant) -> bool {
+        RECEIVED.lock(|received| {
+            received
+                .borrow()
+                .at
+                .is_some_and(|at| now.saturating_duration_since(at) < IDLE_TIMEOUT)
+        })
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Renderer<D> for UsbDisplayView {
+    fn render(&mut self, target: &mut D, _ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        RECEIVED.lock(|received| {
+            let received = received.borrow();
+            let raw = ImageRawLE::<Rgb565>::new(&received.pixels, DISPLAY_WIDTH);
+            Image::new(&raw, Point::zero()).draw(target)
+        })
+    }
+}
@@ -7,7 +7,7 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-const C_PLUGINS: &[&str] = &["plasma", "quadrant"];
+const C_PLUGINS: &[&str] = &["marquee", "plasma", "quadrant"];
 const RUST_PLUGINS: &[&str] = &["bouncing_ball", "quadrant_rust"];
 
 fn main() {
@@ -22,6 +22,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 0,
+            label: None,
         },
         Seat {
             id: "f0r1s2".to_string(),
@@ -29,6 +30,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 1,
+            label: None,
         },
         Seat {
             id: "f0r1s3".to_string(),
@@ -36,6 +38,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 0,
+            label: None,
         },
         Seat {
             id: "f0r1s4".to_string(),
@@ -43,6 +46,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 1,
+            label: None,
         },
         Seat {
             id: "f0r1s5".to_string(),
@@ -50,6 +54,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 0,
+            label: None,
         },
         Seat {
             id: "f0r1s6".to_string(),
@@ -57,6 +62,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 1,
+            label: None,
         },
         // Row 2
         Seat {
@@ -65,6 +71,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 5,
+            label: None,
         },
         Seat {
             id: "f0r2s2".to_string(),
@@ -72,6 +79,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 6,
+            label: None,
         },
         Seat {
             id: "f0r2s3".to_string(),
@@ -79,6 +87,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Broken,
             x: 6,
             y: 5,
+            label: None,
         },
         Seat {
             id: "f0r2s4".to_string(),
@@ -86,6 +95,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 6,
+            label: None,
         },
         Seat {
             id: "f0r2s5".to_string(),
@@ -93,6 +103,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 5,
+            label: None,
         },
         Seat {
             id: "f0r2s6".to_string(),
@@ -100,6 +111,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 6,
+            label: None,
         },
         Seat {
             id: "f0r2s7".to_string(),
@@ -107,6 +119,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 5,
+            label: None,
         },
         // Row 3
         Seat {
@@ -115,6 +128,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 10,
+            label: None,
         },
         Seat {
             id: "f0r3s2".to_string(),
@@ -122,6 +136,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 11,
+            label: None,
         },
         Seat {
             id: "f0r3s3".to_string(),
@@ -129,6 +144,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Broken,
             x: 6,
             y: 10,
+            label: None,
         },
         Seat {
             id: "f0r3s4".to_string(),
@@ -136,6 +152,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 11,
+            label: None,
         },
         Seat {
             id: "f0r3s5".to_string(),
@@ -143,6 +160,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 10,
+            label: None,
         },
         Seat {
             id: "f0r3s6".to_string(),
@@ -150,6 +168,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 11,
+            label: None,
         },
         Seat {
             id: "f0r3s7".to_string(),
@@ -157,6 +176,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 10,
+            label: None,
         },
         // Row 4
         Seat {
@@ -165,6 +185,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 15,
+            label: None,
         },
         Seat {
             id: "f0r4s2".to_string(),
@@ -172,6 +193,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 16,
+            label: None,
         },
         Seat {
             id: "f0r4s3".to_string(),
@@ -179,6 +201,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 15,
+            label: None,
         },
         Seat {
             id: "f0r4s4".to_string(),
@@ -186,6 +209,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 16,
+            label: None,
         },
         Seat {
             id: "f0r4s5".to_string(),
@@ -193,6 +217,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 15,
+            label: None,
         },
         Seat {
             id: "f0r4s6".to_string(),
@@ -200,6 +225,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Broken,
             x: 15,
             y: 16,
+            label: None,
         },
         Seat {
             id: "f0r4s7".to_string(),
@@ -207,6 +233,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 15,
+            label: None,
         },
         // Row 5
         Seat {
@@ -215,6 +242,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 20,
+            label: None,
         },
         Seat {
             id: "f0r5s2".to_string(),
@@ -222,6 +250,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 21,
+            label: None,
         },
         Seat {
             id: "f0r5s3".to_string(),
@@ -229,6 +258,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 20,
+            label: None,
         },
         Seat {
             id: "f0r5s4".to_string(),
@@ -236,6 +266,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 21,
+            label: None,
         },
         Seat {
             id: "f0r5s5".to_string(),
@@ -243,6 +274,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 20,
+            label: None,
         },
         Seat {
             id: "f0r5s6".to_string(),
@@ -250,6 +282,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 21,
+            label: None,
         },
         Seat {
             id: "f0r5s7".to_string(),
@@ -257,6 +290,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 20,
+            label: None,
         },
         // Row 6
         Seat {
@@ -265,6 +299,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 25,
+            label: None,
         },
         Seat {
             id: "f0r6s2".to_string(),
@@ -272,6 +307,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 26,
+            label: None,
         },
         Seat {
             id: "f0r6s3".to_string(),
@@ -279,6 +315,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 25,
+            label: None,
         },
         Seat {
             id: "f0r6s4".to_string(),
@@ -286,6 +323,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 26,
+            label: None,
         },
         Seat {
             id: "f0r6s5".to_string(),
@@ -293,6 +331,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 25,
+            label: None,
         },
         Seat {
             id: "f0r6s6".to_string(),
@@ -300,6 +339,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 26,
+            label: None,
         },
         // Row 7
         Seat {
@@ -308,6 +348,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 30,
+            label: None,
         },
         Seat {
             id: "f0r7s2".to_string(),
@@ -315,6 +356,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 31,
+            label: None,
         },
         Seat {
             id: "f0r7s3".to_string(),
@@ -322,6 +364,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 30,
+            label: None,
         },
         Seat {
             id: "f0r7s4".to_string(),
@@ -329,6 +372,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 31,
+            label: None,
         },
         Seat {
             id: "f0r7s5".to_string(),
@@ -336,6 +380,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 30,
+            label: None,
         },
         Seat {
             id: "f0r7s6".to_string(),
@@ -343,6 +388,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 31,
+            label: None,
         },
         // Row 8
         Seat {
@@ -351,6 +397,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 35,
+            label: None,
         },
         Seat {
             id: "f0r8s2".to_string(),
@@ -358,6 +405,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 36,
+            label: None,
         },
         Seat {
             id: "f0r8s3".to_string(),
@@ -365,6 +413,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 35,
+            label: None,
         },
         Seat {
             id: "f0r8s4".to_string(),
@@ -372,6 +421,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 36,
+            label: None,
         },
         Seat {
             id: "f0r8s5".to_string(),
@@ -379,6 +429,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 35,
+            label: None,
         },
         Seat {
             id: "f0r8s6".to_string(),
@@ -386,6 +437,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 36,
+            label: None,
         },
         Seat {
             id: "f0r8s7".to_string(),
@@ -393,6 +445,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 35,
+            label: None,
         },
         // Row 9
         Seat {
@@ -401,6 +454,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 40,
+            label: None,
         },
         Seat {
             id: "f0r9s2".to_string(),
@@ -408,6 +462,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 41,
+            label: None,
         },
         Seat {
             id: "f0r9s3".to_string(),
@@ -415,6 +470,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 40,
+            label: None,
         },
         Seat {
             id: "f0r9s4".to_string(),
@@ -422,6 +478,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 41,
+            label: None,
         },
         Seat {
             id: "f0r9s5".to_string(),
@@ -429,6 +486,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 40,
+            label: None,
         },
         Seat {
             id: "f0r9s6".to_string(),
@@ -436,6 +494,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 41,
+            label: None,
         },
         Seat {
             id: "f0r9s7".to_string(),
@@ -443,6 +502,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 40,
+            label: None,
         },
         // Row 10
         Seat {
@@ -451,6 +511,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 45,
+            label: None,
         },
         Seat {
             id: "f0r10s2".to_string(),
@@ -458,6 +519,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 46,
+            label: None,
         },
         Seat {
             id: "f0r10s3".to_string(),
@@ -465,6 +527,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 45,
+            label: None,
         },
         Seat {
             id: "f0r10s4".to_string(),
@@ -472,6 +535,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 46,
+            label: None,
         },
         Seat {
             id: "f0r10s5".to_string(),
@@ -479,6 +543,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 45,
+            label: None,
         },
         Seat {
             id: "f0r10s6".to_string(),
@@ -486,6 +551,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 46,
+            label: None,
         },
         Seat {
             id: "f0r10s7".to_string(),
@@ -493,6 +559,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 45,
+            label: None,
         },
         // Row 11
         Seat {
@@ -501,6 +568,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 50,
+            label: None,
         },
         Seat {
             id: "f0r11s2".to_string(),
@@ -508,6 +576,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 51,
+            label: None,
         },
         Seat {
             id: "f0r11s3".to_string(),
@@ -515,6 +584,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 50,
+            label: None,
         },
         Seat {
             id: "f0r11s4".to_string(),
@@ -522,6 +592,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 51,
+            label: None,
         },
         Seat {
             id: "f0r11s5".to_string(),
@@ -529,6 +600,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 50,
+            label: None,
         },
         Seat {
             id: "f0r11s6".to_string(),
@@ -536,6 +608,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 51,
+            label: None,
         },
         Seat {
             id: "f0r11s7".to_string(),
@@ -543,6 +616,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 50,
+            label: None,
         },
         // Row 12
         Seat {
@@ -551,6 +625,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 55,
+            label: None,
         },
         Seat {
             id: "f0r12s2".to_string(),
@@ -558,6 +633,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 56,
+            label: None,
         },
         Seat {
             id: "f0r12s3".to_string(),
@@ -565,6 +641,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 55,
+            label: None,
         },
         Seat {
             id: "f0r12s4".to_string(),
@@ -572,6 +649,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 56,
+            label: None,
         },
         Seat {
             id: "f0r12s5".to_string(),
@@ -579,6 +657,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 55,
+            label: None,
         },
         Seat {
             id: "f0r12s6".to_string(),
@@ -586,6 +665,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 56,
+            label: None,
         },
         Seat {
             id: "f0r12s7".to_string(),
@@ -593,6 +673,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 55,
+            label: None,
         },
         // Row 13
         Seat {
@@ -601,6 +682,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 0,
             y: 60,
+            label: None,
         },
         Seat {
             id: "f0r13s2".to_string(),
@@ -608,6 +690,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 3,
             y: 61,
+            label: None,
         },
         Seat {
             id: "f0r13s3".to_string(),
@@ -615,6 +698,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Free,
             x: 6,
             y: 60,
+            label: None,
         },
         Seat {
             id: "f0r13s4".to_string(),
@@ -622,6 +706,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 9,
             y: 61,
+            label: None,
         },
         Seat {
             id: "f0r13s5".to_string(),
@@ -629,6 +714,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 12,
             y: 60,
+            label: None,
         },
         Seat {
             id: "f0r13s6".to_string(),
@@ -636,6 +722,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 15,
             y: 61,
+            label: None,
         },
         Seat {
             id: "f0r13s7".to_string(),
@@ -643,6 +730,7 @@ fn create_sample_seats() -> Vec<Seat> {
             status: Status::Taken,
             x: 18,
             y: 60,
+            label: None,
         },
     ]
 }
@@ -8,8 +8,9 @@
 //! - Z: A button
 //! - X: B button
 //! - Enter: Start
-//! - Backspace: Select
+//! - Right Shift or Backspace: Select
 //! - Tab: Switch to next plugin
+//! - P: Pause/resume the plugin
 //! - Escape: Quit
 
 use embedded_graphics::pixelcolor::Rgb565;
@@ -17,10 +18,7 @@ use embedded_graphics::prelude::*;
 use embedded_graphics_simulator::{
     OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window, sdl2::Keycode,
 };
-use plugin_api::{
-    INPUT_A, INPUT_B, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_SELECT, INPUT_START, INPUT_UP,
-};
-use simulator::{NativePlugin, Plugin, SimulatorPluginRuntime};
+use simulator::{Keypad, NativePlugin, Plugin, SimulatorPluginRuntime};
 use std::time::{Duration, Instant};
 
 /// Plugin entry with its type info
@@ -37,8 +35,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Z: A button");
     println!("  X: B button");
     println!("  Enter: Start");
-    println!("  Backspace: Select");
+    println!("  Right Shift/Backspace: Select");
     println!("  Tab: Switch plugin");
+    println!("  P: Pause/resume");
     println!("  Escape: Quit");
     println!();
 
@@ -86,7 +85,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     runtime.init_plugin(&mut current_plugin);
 
     // Input state
-    let mut inputs: u32 = 0;
+    let mut keypad = Keypad::new();
+    let mut paused = false;
 
     // Frame timing
     let target_frame_duration = Duration::from_millis(16); // ~60 FPS
@@ -101,17 +101,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // Handle events
         for event in window.events() {
+            keypad.handle(&event);
             match event {
                 SimulatorEvent::Quit => break 'running,
                 SimulatorEvent::KeyDown { keycode, .. } => match keycode {
-                    Keycode::Up => inputs |= INPUT_UP,
-                    Keycode::Down => inputs |= INPUT_DOWN,
-                    Keycode::Left => inputs |= INPUT_LEFT,
-                    Keycode::Right => inputs |= INPUT_RIGHT,
-                    Keycode::Z => inputs |= INPUT_A,
-                    Keycode::X => inputs |= INPUT_B,
-                    Keycode::Return => inputs |= INPUT_START,
-                    Keycode::Backspace => inputs |= INPUT_SELECT,
                     Keycode::Tab => {
                         // Cleanup current plugin
                         current_plugin.cleanup();
@@ -130,27 +123,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         runtime = SimulatorPluginRuntime::new();
                         current_plugin = load_plugin(entry).expect("Failed to load plugin");
                         runtime.init_plugin(&mut current_plugin);
+                        paused = false;
+                    }
+                    Keycode::P => {
+                        paused = !paused;
+                        if paused {
+                            current_plugin.pause();
+                        } else {
+                            current_plugin.resume();
+                        }
+                        println!("{}", if paused { "Paused" } else { "Resumed" });
                     }
                     Keycode::Escape => break 'running,
                     _ => {}
                 },
-                SimulatorEvent::KeyUp { keycode, .. } => match keycode {
-                    Keycode::Up => inputs &= !INPUT_UP,
-                    Keycode::Down => inputs &= !INPUT_DOWN,
-                    Keycode::Left => inputs &= !INPUT_LEFT,
-                    Keycode::Right => inputs &= !INPUT_RIGHT,
-                    Keycode::Z => inputs &= !INPUT_A,
-                    Keycode::X => inputs &= !INPUT_B,
-                    Keycode::Return => inputs &= !INPUT_START,
-                    Keycode::Backspace => inputs &= !INPUT_SELECT,
-                    _ => {}
-                },
                 _ => {}
             }
         }
 
-        // Update current plugin
-        runtime.update(&mut current_plugin, inputs);
+        // Update current plugin, the last frame stays on screen while paused
+        if !paused {
+            runtime.update(&mut current_plugin, keypad.buttons());
+        }
 
         // Render to display
         runtime.render_to_display(&mut display);
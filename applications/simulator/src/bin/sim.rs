@@ -0,0 +1,478 @@
+//! Simulator command line
+//!
+//! ```text
+//! sim animation fortytwo
+//! sim plugin path/to/libplugin.so        (needs the `plugin` feature)
+//! sim plugin path/to/libplugin.so --seed 42
+//! sim plugin path/to/libsnake.so --storage snake.bin
+//! sim plugin path/to/libheatmap.so --layout layout.json
+//! sim plugin path/to/libtetris.so --asset tiles.bin --asset levels.bin
+//! sim cluster layout.json --poll URL
+//! sim cluster layout.json --poll URL --latency-ms 500 --drop-rate 20
+//! sim cluster layout.json --poll URL --confirm-polls 1   (raw seat data)
+//! sim serve layout.json
+//! sim serve layout.json --port 9000
+//! sim mirror 192.168.1.42
+//! sim mirror 192.168.1.42 --codec heatshrink
+//! sim usb-display /dev/ttyACM0 stars
+//! sim upload-plugin /dev/ttyACM0 plugin.bin
+//! sim replay recordings.bin
+//! ```
+//!
+//! `--scale`, `--spacing` and `--fps` apply to every subcommand. In the
+//! window, Space pauses, N steps a frame while paused, S toggles slow motion
+//! and hovering a pixel shows its position and color. G shows a graph of the
+//! frame time against `--budget-us`, and F12 saves a screenshot to
+//! `--screenshot-dir`. `--record out.png` saves the first
+//! `--record-frames` frames as an animated PNG.
+//!
+//! `sim plugin` plays the plugin's buttons from the keyboard: arrows, Z/X for
+//! A/B, Enter for Start and Right Shift for Select.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cluster_core::codec::Codec;
+use cluster_core::models::{Layout, PartialLayout};
+use cluster_core::smoothing::{DEFAULT_CONFIRM_POLLS, SeatSmoother};
+use cluster_core::visualization::draw_cluster_frame;
+use embedded_graphics::prelude::*;
+use graphics_common::animations;
+use simulator::mirror::{MIRROR_PORT, MirrorClient};
+use simulator::network::{FlakyNetwork, NetworkConditions};
+use simulator::plugin_upload;
+use simulator::replay::Replay;
+use simulator::server::{LayoutServer, SERVER_PORT};
+use simulator::usb_display::UsbDisplaySender;
+use simulator::{AnimationFn, Simulator, SimulatorConfig};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(name = "sim", about = "Hub75 matrix simulator")]
+struct Cli {
+    /// Window pixels per panel pixel
+    #[arg(long, global = true, default_value_t = 6)]
+    scale: u32,
+    /// Gap between panel pixels, in window pixels
+    #[arg(long, global = true, default_value_t = 1)]
+    spacing: u32,
+    /// Target frame rate, 0 for unlimited
+    #[arg(long, global = true, default_value_t = 60)]
+    fps: u32,
+    /// Directory F12 screenshots are saved to
+    #[arg(long, global = true, default_value = ".")]
+    screenshot_dir: PathBuf,
+    /// Callback time in microseconds matching a full frame on the RP2350
+    #[arg(long, global = true, default_value_t = 1000)]
+    budget_us: u64,
+    /// Record the first frames to this animated PNG
+    #[arg(long, global = true)]
+    record: Option<PathBuf>,
+    /// Frames to record with `--record`
+    #[arg(long, global = true, default_value_t = 120)]
+    record_frames: u32,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play one of the built-in animations
+    Animation {
+        #[arg(value_enum)]
+        name: Animation,
+    },
+    /// Run a plugin compiled as a native shared library
+    #[cfg(feature = "plugin")]
+    Plugin {
+        path: PathBuf,
+        /// Seed random() with this and derive millis() from the frame
+        /// counter, so each frame is the same on every run
+        #[arg(long)]
+        seed: Option<u32>,
+        /// Keep the plugin's saved values (high scores, ...) in this file
+        #[arg(long)]
+        storage: Option<PathBuf>,
+        /// Cluster layout JSON the plugin reads its cluster data from
+        #[arg(long)]
+        layout: Option<PathBuf>,
+        /// Asset files the plugin reads with read_asset_fn, numbered from 0
+        /// in order, as `cargo xtask plugin-pack` packs them
+        #[arg(long = "asset")]
+        assets: Vec<PathBuf>,
+    },
+    /// Render a cluster layout from a JSON file
+    Cluster {
+        layout: PathBuf,
+        /// Refresh the layout from this URL (full or partial layout JSON)
+        #[arg(long)]
+        poll: Option<String>,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+        /// Delay each poll by this many milliseconds
+        #[arg(long, default_value_t = 0)]
+        latency_ms: u64,
+        /// Random extra delay of each poll, up to this many milliseconds
+        #[arg(long, default_value_t = 0)]
+        jitter_ms: u64,
+        /// Percentage of polls failing as if the connection dropped
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+        drop_rate: u8,
+        /// Percentage of polls answered with a 500 instead of reaching the server
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+        error_rate: u8,
+        /// Polls a seat's new status must be seen on before it shows; 1
+        /// shows the raw data
+        #[arg(long, default_value_t = DEFAULT_CONFIRM_POLLS, value_parser = clap::value_parser!(u8).range(1..))]
+        confirm_polls: u8,
+    },
+    /// Serve a layout file like the cluster server, including partial
+    /// layouts for `/clusters?ids=...`
+    Serve {
+        layout: PathBuf,
+        #[arg(long, default_value_t = SERVER_PORT)]
+        port: u16,
+    },
+    /// Show the framebuffer streamed by a device
+    Mirror {
+        device: IpAddr,
+        #[arg(long, default_value_t = MIRROR_PORT)]
+        port: u16,
+        /// Compression of the streamed frames
+        #[arg(long, value_enum, default_value_t = MirrorCodec::Raw)]
+        codec: MirrorCodec,
+    },
+    /// Play an animation and stream it to a device in USB display mode
+    UsbDisplay {
+        /// Serial port of the device, e.g. /dev/ttyACM0
+        port: PathBuf,
+        #[arg(value_enum)]
+        animation: Animation,
+    },
+    /// Load a plugin binary into a device running `plugin_test` with the
+    /// `usb-loader` feature, in place of its main plugin
+    UploadPlugin {
+        /// Serial port of the device, e.g. /dev/ttyACM0
+        port: PathBuf,
+        binary: PathBuf,
+    },
+    /// Play the frames recorded by a device, from a dump of its flash
+    Replay { recording: PathBuf },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MirrorCodec {
+    Raw,
+    Rle,
+    Heatshrink,
+}
+
+impl From<MirrorCodec> for Option<Codec> {
+    fn from(codec: MirrorCodec) -> Self {
+        match codec {
+            MirrorCodec::Raw => None,
+            MirrorCodec::Rle => Some(Codec::Rle),
+            MirrorCodec::Heatshrink => Some(Codec::Heatshrink),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Animation {
+    Fortytwo,
+    Stars,
+    Arrow,
+    Quadrant,
+}
+
+impl Animation {
+    fn draw_fn(self) -> AnimationFn {
+        match self {
+            Self::Fortytwo => animations::fortytwo::draw_animation_frame,
+            Self::Stars => animations::stars::draw_animation_frame,
+            Self::Arrow => animations::arrow::draw_animation_frame,
+            Self::Quadrant => animations::quadrant::draw_animation_frame,
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let config = SimulatorConfig {
+        scale: cli.scale,
+        pixel_spacing: cli.spacing,
+        target_fps: (cli.fps > 0).then_some(cli.fps),
+        screenshot_dir: cli.screenshot_dir,
+        record_path: cli.record,
+        record_frames: cli.record_frames,
+        hardware_budget: Duration::from_micros(cli.budget_us),
+        ..Default::default()
+    };
+
+    match cli.command {
+        Command::Animation { name } => Simulator::new(config)?.run_animation(name.draw_fn()),
+        #[cfg(feature = "plugin")]
+        Command::Plugin {
+            path,
+            seed,
+            storage,
+            layout,
+            assets,
+        } => run_plugin(
+            config,
+            &path,
+            seed,
+            storage.as_deref(),
+            layout.as_deref(),
+            &assets,
+        ),
+        Command::Cluster {
+            layout,
+            poll,
+            interval,
+            latency_ms,
+            jitter_ms,
+            drop_rate,
+            error_rate,
+            confirm_polls,
+        } => {
+            let network = NetworkConditions {
+                latency: Duration::from_millis(latency_ms),
+                jitter: Duration::from_millis(jitter_ms),
+                drop_percent: drop_rate,
+                server_error_percent: error_rate,
+            };
+            run_cluster(
+                config,
+                &layout,
+                poll,
+                Duration::from_secs(interval),
+                network,
+                SeatSmoother::new(confirm_polls),
+            )
+        }
+        Command::Serve { layout, port } => {
+            let layout: Layout = serde_json::from_str(&std::fs::read_to_string(layout)?)?;
+            let server = LayoutServer::bind(("0.0.0.0", port), layout)?;
+            println!("Serving the layout on {}", server.local_addr()?);
+            Ok(server.run()?)
+        }
+        Command::Mirror {
+            device,
+            port,
+            codec,
+        } => {
+            let size = config.size;
+            let client = MirrorClient::connect(SocketAddr::new(device, port), size, codec.into());
+            Simulator::new(config)?.run_with_events(|display, _, _| {
+                if !client.is_connected() {
+                    display.clear(embedded_graphics::pixelcolor::Rgb565::BLACK)?;
+                }
+                Ok(client.draw(display)?)
+            })
+        }
+        Command::UsbDisplay { port, animation } => {
+            let mut sender = UsbDisplaySender::open(&port)?;
+            let draw = animation.draw_fn();
+            Simulator::new(config)?.run_with_events(|display, frame, _| {
+                draw(display, frame)?;
+                Ok(sender.send(display)?)
+            })
+        }
+        Command::UploadPlugin { port, binary } => {
+            let message = plugin_upload::upload(&port, &std::fs::read(binary)?)?;
+            println!("{message}");
+            Ok(())
+        }
+        Command::Replay { recording } => {
+            let replay = Replay::load(&recording)?;
+            let frames = replay.frames();
+            let duration = frames[frames.len() - 1]
+                .timestamp_ms
+                .wrapping_sub(frames[0].timestamp_ms);
+            println!("{} frames over {} ms", frames.len(), duration);
+
+            let config = SimulatorConfig {
+                size: replay.size(),
+                ..config
+            };
+            Simulator::new(config)?
+                .run_with_events(|display, frame, _| Ok(replay.draw(frame as usize, display)?))
+        }
+    }
+}
+
+fn run_cluster(
+    config: SimulatorConfig,
+    path: &Path,
+    poll: Option<String>,
+    interval: Duration,
+    network: NetworkConditions,
+    mut smoother: SeatSmoother,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let json = std::fs::read_to_string(path)?;
+    let mut layout: Layout = serde_json::from_str(&json)?;
+
+    // Fetch in the background so a slow server doesn't stall the window
+    let (tx, rx) = mpsc::channel::<PartialLayout>();
+    if let Some(url) = poll {
+        std::thread::spawn(move || {
+            let mut network = FlakyNetwork::from_clock(network);
+            loop {
+                match network.send(|| fetch_layout(&url)) {
+                    Ok(partial) => {
+                        if tx.send(partial).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to poll {url}: {e}"),
+                }
+                std::thread::sleep(interval);
+            }
+        });
+    }
+
+    let start = Instant::now();
+    Simulator::new(config)?.run_with_events(|display, frame, _| {
+        // A full layout also parses as a partial one with every floor set
+        for partial in rx.try_iter() {
+            smoother.apply(&mut layout, partial, start.elapsed().as_millis() as u64);
+        }
+        Ok(draw_cluster_frame(display, &layout, frame)?)
+    })
+}
+
+fn fetch_layout(url: &str) -> Result<PartialLayout, Box<dyn std::error::Error>> {
+    let body = ureq::get(url)
+        .timeout(Duration::from_secs(10))
+        .call()?
+        .into_string()?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+#[cfg(feature = "plugin")]
+fn run_plugin(
+    config: SimulatorConfig,
+    path: &Path,
+    seed: Option<u32>,
+    storage: Option<&Path>,
+    layout: Option<&Path>,
+    assets: &[PathBuf],
+) -> Result<(), Box<dyn std::error::Error>> {
+    use embedded_graphics_simulator::SimulatorEvent;
+    use plugin_api::{INPUT_AXIS_MAX, InputState};
+    use simulator::native_plugin::SymbolConvention;
+    use simulator::{Keypad, NativePlugin, Plugin, SimulatorPluginRuntime};
+
+    // `libplasma.so` is the C plugin `plasma`, with `plasma_*` symbols
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("invalid plugin path")?;
+    let name: &'static str = Box::leak(stem.trim_start_matches("lib").to_owned().into_boxed_str());
+    let mut plugin = NativePlugin::load(path, name, SymbolConvention::Generic)
+        .or_else(|_| NativePlugin::load(path, name, SymbolConvention::NamePrefixed))?;
+
+    let mut runtime = seed.map_or_else(
+        SimulatorPluginRuntime::new,
+        SimulatorPluginRuntime::new_deterministic,
+    );
+    if let Some(storage) = storage
+        && storage.exists()
+    {
+        runtime.load_storage(&std::fs::read(storage)?);
+    }
+    if let Some(layout) = layout {
+        let layout: Layout = serde_json::from_str(&std::fs::read_to_string(layout)?)?;
+        runtime.set_layout(Some(layout));
+    }
+    if !assets.is_empty() {
+        let assets = assets
+            .iter()
+            .map(std::fs::read)
+            .collect::<Result<Vec<_>, _>>()?;
+        let assets: Vec<&[u8]> = assets.iter().map(Vec::as_slice).collect();
+        runtime.set_assets(plugin_api::assets::build_table(&assets));
+    }
+    // The window shows true color at `--fps`, 0 being as fast as it goes
+    runtime.set_display_info(plugin_api::DisplayInfo {
+        refresh_hz: config.target_fps.unwrap_or(0),
+        color_depth: 8,
+        ..plugin_api::DisplayInfo::UNKNOWN
+    });
+    runtime.init_plugin(&mut plugin);
+    println!("Running plugin {name}");
+    let permissions: Vec<_> = plugin_api::permissions::names(plugin.permissions()).collect();
+    if !permissions.is_empty() {
+        println!("Permissions: {}", permissions.join(", "));
+    }
+
+    // Desktop timings don't predict the device's, so a plugin going over
+    // the budget is reported rather than stopped as on the device
+    let budget = std::time::Duration::from_micros(plugin_api::UPDATE_BUDGET_US.into());
+    let mut over_budget = false;
+
+    // Dragging with the mouse held moves the stick, from the center of the
+    // display to its edges, and the wheel turns the encoder
+    let center = config.size / 2;
+    let stick = |point: Point| {
+        let axis = |offset: i32, half: u32| {
+            let half = half.max(1) as i32;
+            (offset * i32::from(INPUT_AXIS_MAX) / half)
+                .clamp(-i32::from(INPUT_AXIS_MAX), i32::from(INPUT_AXIS_MAX)) as i16
+        };
+        let offset = point - center;
+        (axis(offset.x, center.width), axis(offset.y, center.height))
+    };
+
+    let mut input = InputState::default();
+    let mut keypad = Keypad::new();
+    let mut dragging = false;
+    let result = Simulator::new(config)?.run_with_events(|display, _, events| {
+        for event in events {
+            keypad.handle(event);
+            match *event {
+                SimulatorEvent::MouseButtonDown { point, .. } => {
+                    dragging = true;
+                    (input.axis_x, input.axis_y) = stick(point);
+                }
+                SimulatorEvent::MouseMove { point } if dragging => {
+                    (input.axis_x, input.axis_y) = stick(point);
+                }
+                SimulatorEvent::MouseButtonUp { .. } => {
+                    dragging = false;
+                    (input.axis_x, input.axis_y) = (0, 0);
+                }
+                SimulatorEvent::MouseWheel { scroll_delta, .. } => {
+                    input.rotary_delta += scroll_delta.y;
+                }
+                _ => {}
+            }
+        }
+
+        input.buttons = keypad.buttons();
+        let start = std::time::Instant::now();
+        runtime.update(&mut plugin, input);
+        input.rotary_delta = 0;
+        let elapsed = start.elapsed();
+        if elapsed > budget && !over_budget {
+            over_budget = true;
+            eprintln!(
+                "Update took {} ms, over the {} ms budget: the device would stop {name}",
+                elapsed.as_millis(),
+                budget.as_millis()
+            );
+        }
+        runtime.render_to_display(display);
+        Ok(())
+    });
+
+    plugin.cleanup();
+    if let (Some(storage), Some(image)) = (storage, runtime.storage_to_persist()) {
+        std::fs::write(storage, image)?;
+    }
+    result
+}
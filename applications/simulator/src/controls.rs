@@ -0,0 +1,122 @@
+//! Playback controls and pixel inspector for the simulator window
+//!
+//! - Space: pause/resume
+//! - N: advance a single frame while paused
+//! - S: toggle slow motion
+//! - Mouse hover: show the pixel position and color in an overlay
+//!
+//! The overlay is drawn on a copy of the display, so it never shows up in
+//! the inspected pixels or in the rendered frames.
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_4X6},
+    pixelcolor::{Rgb565, Rgb888, raw::RawU16},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use embedded_graphics_simulator::{SimulatorDisplay, SimulatorEvent, sdl2::Keycode};
+
+/// Frame time multiplier in slow motion
+const SLOW_MOTION_FACTOR: u32 = 8;
+
+/// Height of the inspector bar
+const BAR_HEIGHT: u32 = 7;
+
+#[derive(Debug, Default)]
+pub(crate) struct Controls {
+    paused: bool,
+    step: bool,
+    slow_motion: bool,
+    hover: Option<Point>,
+}
+
+impl Controls {
+    /// Update from a window event
+    pub(crate) fn handle(&mut self, event: &SimulatorEvent) {
+        match *event {
+            SimulatorEvent::KeyDown {
+                keycode, repeat, ..
+            } => match keycode {
+                Keycode::Space if !repeat => self.paused = !self.paused,
+                Keycode::N if self.paused => self.step = true,
+                Keycode::S if !repeat => self.slow_motion = !self.slow_motion,
+                _ => {}
+            },
+            SimulatorEvent::MouseMove { point } => self.hover = Some(point),
+            _ => {}
+        }
+    }
+
+    /// Check if the next frame should be rendered
+    pub(crate) fn advance(&mut self) -> bool {
+        !self.paused || core::mem::take(&mut self.step)
+    }
+
+    /// Check if the loop should be paced even without a target frame rate
+    pub(crate) const fn is_throttled(&self) -> bool {
+        self.paused || self.slow_motion
+    }
+
+    /// Multiplier applied to the frame time
+    pub(crate) const fn frame_time_factor(&self) -> u32 {
+        if self.slow_motion {
+            SLOW_MOTION_FACTOR
+        } else {
+            1
+        }
+    }
+
+    /// Check if an overlay needs to be drawn over the frame
+    pub(crate) const fn has_overlay(&self) -> bool {
+        self.paused || self.slow_motion || self.hover.is_some()
+    }
+
+    /// Draw the status and inspector overlay of `frame` onto `target`
+    pub(crate) fn draw_overlay(
+        &self,
+        frame: &SimulatorDisplay<Rgb565>,
+        target: &mut SimulatorDisplay<Rgb565>,
+    ) {
+        let size = frame.size();
+        let mut line = String::new();
+        if self.paused {
+            line.push_str("|| ");
+        } else if self.slow_motion {
+            line.push_str(">> ");
+        }
+
+        let hovered = self
+            .hover
+            .filter(|&point| frame.bounding_box().contains(point));
+        if let Some(point) = hovered {
+            let color = frame.get_pixel(point);
+            let raw = RawU16::from(color).into_inner();
+            let rgb = Rgb888::from(color);
+            line.push_str(&format!(
+                "{},{} {raw:04X} {:02X}{:02X}{:02X}",
+                point.x,
+                point.y,
+                rgb.r(),
+                rgb.g(),
+                rgb.b()
+            ));
+        }
+        if line.is_empty() {
+            return;
+        }
+
+        // Keep the bar away from the inspected pixel
+        let y = match hovered {
+            Some(point) if point.y >= (size.height / 2) as i32 => 0,
+            _ => (size.height - BAR_HEIGHT) as i32,
+        };
+        let bar = Rectangle::new(Point::new(0, y), Size::new(size.width, BAR_HEIGHT));
+        let Ok(()) = bar
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(target);
+        let style = MonoTextStyle::new(&FONT_4X6, Rgb565::YELLOW);
+        let Ok(_) =
+            Text::with_baseline(&line, Point::new(1, y + 1), style, Baseline::Top).draw(target);
+    }
+}
@@ -0,0 +1,115 @@
+//! Rolling graph of the callback execution time
+//!
+//! Toggled with G. Each column is one frame, newest on the right, scaled so
+//! the top of the graph is twice the 60 FPS frame time. Two lines mark the
+//! 60 FPS frame time and [`SimulatorConfig::hardware_budget`]: bars above the
+//! budget line are drawn red, as the effect is unlikely to keep up on the
+//! RP2350.
+//!
+//! [`SimulatorConfig::hardware_budget`]: crate::SimulatorConfig::hardware_budget
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+};
+use embedded_graphics_simulator::{SimulatorDisplay, SimulatorEvent, sdl2::Keycode};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frame time at 60 FPS
+const FRAME_TIME_60FPS: Duration = Duration::from_micros(16_667);
+
+/// Height of the graph in panel pixels
+const GRAPH_HEIGHT: u32 = 32;
+
+#[derive(Debug)]
+pub(crate) struct FrameGraph {
+    visible: bool,
+    budget: Duration,
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl FrameGraph {
+    pub(crate) fn new(width: u32, budget: Duration) -> Self {
+        Self {
+            visible: false,
+            budget,
+            samples: VecDeque::with_capacity(width as usize),
+            capacity: width as usize,
+        }
+    }
+
+    /// Update from a window event
+    pub(crate) fn handle(&mut self, event: &SimulatorEvent) {
+        if let SimulatorEvent::KeyDown {
+            keycode: Keycode::G,
+            repeat: false,
+            ..
+        } = *event
+        {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Record the execution time of one callback
+    pub(crate) fn record(&mut self, elapsed: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(elapsed);
+    }
+
+    pub(crate) const fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Draw the graph along the top of `target`
+    pub(crate) fn draw(&self, target: &mut SimulatorDisplay<Rgb565>) {
+        let width = target.size().width;
+        let Ok(()) = Rectangle::new(Point::zero(), Size::new(width, GRAPH_HEIGHT))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(target);
+
+        let full_scale = FRAME_TIME_60FPS * 2;
+        let height_of = |time: Duration| {
+            let ratio = time.as_secs_f32() / full_scale.as_secs_f32();
+            ((ratio * GRAPH_HEIGHT as f32) as u32).min(GRAPH_HEIGHT)
+        };
+        let bottom = GRAPH_HEIGHT as i32 - 1;
+
+        let start = width as usize - self.samples.len().min(width as usize);
+        for (i, &sample) in self.samples.iter().enumerate() {
+            let height = height_of(sample);
+            if height == 0 {
+                continue;
+            }
+            let color = if sample > self.budget {
+                Rgb565::RED
+            } else {
+                Rgb565::GREEN
+            };
+            let x = (start + i) as i32;
+            let Ok(()) = Line::new(
+                Point::new(x, bottom),
+                Point::new(x, bottom + 1 - height as i32),
+            )
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+            .draw(target);
+        }
+
+        for (time, color) in [
+            (FRAME_TIME_60FPS, Rgb565::WHITE),
+            (self.budget, Rgb565::YELLOW),
+        ] {
+            let y = bottom - height_of(time) as i32;
+            if y < 0 {
+                continue;
+            }
+            let Ok(()) = Line::new(Point::new(0, y), Point::new(width as i32 - 1, y))
+                .into_styled(PrimitiveStyle::with_stroke(color, 1))
+                .draw(target);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Golden-image checks of rendered frames
+//!
+//! A frame is compared pixel for pixel, at panel resolution, with a PNG
+//! committed next to the test. When the golden image is missing or
+//! `UPDATE_GOLDEN=1` is set, the frame is written as the new golden image
+//! instead, so a deliberate change in the output is accepted by rerunning the
+//! test and reviewing the PNG diff.
+//!
+//! ```no_run
+//! use simulator::{Simulator, SimulatorConfig};
+//!
+//! let config = SimulatorConfig {
+//!     headless: true,
+//!     ..Default::default()
+//! };
+//! let mut sim = Simulator::new(config).unwrap();
+//! sim.run_frames(30, graphics_common::animations::stars::draw_animation_frame)
+//!     .unwrap();
+//! sim.assert_frame_matches("tests/golden/stars-30.png");
+//! ```
+
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics_simulator::{OutputSettings, SimulatorDisplay};
+use std::path::Path;
+
+/// Environment variable that rewrites the golden images
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// Panic unless `display` matches the golden PNG at `path`
+///
+/// On a mismatch the frame is saved next to the golden image with an
+/// `.actual.png` extension, to compare the two.
+pub fn assert_display_matches(display: &SimulatorDisplay<Rgb565>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = display
+        .to_rgb_output_image(&OutputSettings::default())
+        .as_image_buffer();
+
+    let update = std::env::var_os(UPDATE_ENV).is_some_and(|value| value != "0");
+    if update || !path.exists() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("create the golden image directory");
+        }
+        actual.save(path).expect("write the golden image");
+        println!("Wrote golden image {}", path.display());
+        return;
+    }
+
+    let expected = match image::open(path) {
+        Ok(image) => image.to_rgb8(),
+        Err(e) => panic!("Failed to read golden image {}: {e}", path.display()),
+    };
+    if expected == actual {
+        return;
+    }
+
+    let actual_path = path.with_extension("actual.png");
+    let _ = actual.save(&actual_path);
+    if expected.dimensions() != actual.dimensions() {
+        panic!(
+            "Frame is {:?} but golden image {} is {:?}, see {}",
+            actual.dimensions(),
+            path.display(),
+            expected.dimensions(),
+            actual_path.display()
+        );
+    }
+    let mut differing = expected
+        .enumerate_pixels()
+        .zip(actual.pixels())
+        .filter(|((_, _, expected), actual)| expected != actual);
+    let ((x, y, expected_pixel), actual_pixel) = differing.next().expect("images differ");
+    panic!(
+        "Frame differs from golden image {} in {} pixels, first at ({x}, {y}): \
+         expected {:?}, got {:?}. See {}, or rerun with {UPDATE_ENV}=1 to accept it",
+        path.display(),
+        differing.count() + 1,
+        expected_pixel.0,
+        actual_pixel.0,
+        actual_path.display()
+    );
+}
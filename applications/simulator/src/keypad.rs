@@ -0,0 +1,51 @@
+//! Keyboard stand-in for the panel's buttons
+//!
+//! Arrows are the D-pad, Z and X are A and B, Enter is Start and Right Shift
+//! (or Backspace) is Select, so plugins can be played in the window without
+//! hardware.
+
+use embedded_graphics_simulator::{SimulatorEvent, sdl2::Keycode};
+use plugin_api::{
+    INPUT_A, INPUT_B, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_SELECT, INPUT_START, INPUT_UP,
+};
+
+/// `INPUT_*` bit of a key, 0 for keys that aren't buttons
+pub fn key_input(keycode: Keycode) -> u32 {
+    match keycode {
+        Keycode::Up => INPUT_UP,
+        Keycode::Down => INPUT_DOWN,
+        Keycode::Left => INPUT_LEFT,
+        Keycode::Right => INPUT_RIGHT,
+        Keycode::Z => INPUT_A,
+        Keycode::X => INPUT_B,
+        Keycode::Return => INPUT_START,
+        Keycode::RShift | Keycode::Backspace => INPUT_SELECT,
+        _ => 0,
+    }
+}
+
+/// Buttons held on the keyboard
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Keypad {
+    buttons: u32,
+}
+
+impl Keypad {
+    pub const fn new() -> Self {
+        Self { buttons: 0 }
+    }
+
+    /// Press or release the button of a key event, ignoring other events
+    pub fn handle(&mut self, event: &SimulatorEvent) {
+        match *event {
+            SimulatorEvent::KeyDown { keycode, .. } => self.buttons |= key_input(keycode),
+            SimulatorEvent::KeyUp { keycode, .. } => self.buttons &= !key_input(keycode),
+            _ => {}
+        }
+    }
+
+    /// `INPUT_*` flags of the buttons held
+    pub const fn buttons(&self) -> u32 {
+        self.buttons
+    }
+}
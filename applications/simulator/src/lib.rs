@@ -1,18 +1,45 @@
-use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use embedded_graphics::{
+    pixelcolor::{Rgb565, raw::RawU16},
+    prelude::*,
+};
 use embedded_graphics_simulator::{
-    OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+    OutputSettings, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window, sdl2::Keycode,
 };
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::controls::Controls;
+use crate::frame_graph::FrameGraph;
+use crate::recording::Recording;
 
+mod controls;
+mod frame_graph;
+pub mod golden;
+#[cfg(feature = "plugin")]
+pub mod keypad;
+pub mod mirror;
 #[cfg(feature = "plugin")]
 pub mod native_plugin;
+pub mod network;
 #[cfg(feature = "plugin")]
 pub mod plugin_host;
+pub mod plugin_upload;
+mod recording;
+pub mod replay;
+mod screenshot;
+pub mod server;
+pub mod usb_display;
 
+#[cfg(feature = "plugin")]
+pub use keypad::Keypad;
 #[cfg(feature = "plugin")]
 pub use native_plugin::NativePlugin;
 #[cfg(feature = "plugin")]
 pub use plugin_host::{Plugin, SimulatorPluginRuntime};
 
+/// Frame time while paused or in slow motion without a target frame rate
+const THROTTLED_FRAME_TIME: Duration = Duration::from_millis(16);
+
 pub type AnimationFn =
     fn(&mut SimulatorDisplay<Rgb565>, u32) -> Result<(), core::convert::Infallible>;
 
@@ -23,6 +50,31 @@ pub struct SimulatorConfig {
     pub pixel_spacing: u32,
     pub title: String,
     pub target_fps: Option<u32>,
+    /// Directory the F12 screenshots are saved to
+    pub screenshot_dir: PathBuf,
+    /// Animated PNG the first `record_frames` frames are saved to, see
+    /// [`Simulator::record`]
+    pub record_path: Option<PathBuf>,
+    pub record_frames: u32,
+    /// Run without a window, e.g. on CI
+    ///
+    /// Frames are rendered with [`Simulator::run_frames`] and checked with
+    /// [`Simulator::assert_frame_matches`] or [`Simulator::framebuffer`].
+    pub headless: bool,
+    /// Callback time on this machine matching a full frame on the RP2350
+    ///
+    /// Marked on the frame-time graph. Calibrate it by comparing the FPS an
+    /// effect logs on hardware with its callback time here.
+    pub hardware_budget: Duration,
+}
+
+impl SimulatorConfig {
+    fn output_settings(&self) -> OutputSettings {
+        OutputSettingsBuilder::new()
+            .scale(self.scale)
+            .pixel_spacing(self.pixel_spacing)
+            .build()
+    }
 }
 
 impl Default for SimulatorConfig {
@@ -33,72 +85,77 @@ impl Default for SimulatorConfig {
             pixel_spacing: 1,
             title: "Hub75 Matrix Simulator".to_string(),
             target_fps: Some(60),
+            screenshot_dir: PathBuf::from("."),
+            record_path: None,
+            record_frames: 120,
+            headless: false,
+            hardware_budget: Duration::from_millis(1),
         }
     }
 }
 
 pub struct Simulator {
     display: SimulatorDisplay<Rgb565>,
-    window: Window,
+    /// `None` when headless
+    window: Option<Window>,
     config: SimulatorConfig,
+    recording: Option<Recording>,
 }
 
 impl Simulator {
     pub fn new(config: SimulatorConfig) -> Result<Self, String> {
         let display = SimulatorDisplay::<Rgb565>::new(config.size);
-
-        let output_settings = OutputSettingsBuilder::new()
-            .scale(config.scale)
-            .pixel_spacing(config.pixel_spacing)
-            .build();
-
-        let window = Window::new(&config.title, &output_settings);
+        let window =
+            (!config.headless).then(|| Window::new(&config.title, &config.output_settings()));
+        let recording = config
+            .record_path
+            .clone()
+            .map(|path| Recording::new(path, config.record_frames));
 
         Ok(Self {
             display,
             window,
             config,
+            recording,
         })
     }
 
+    /// Record the next `n_frames` rendered frames to an animated PNG
+    ///
+    /// Frames are scaled like the window, without overlays, and play at the
+    /// target frame rate. The file is written once the frames are captured,
+    /// or with the frames so far when the window is closed first.
+    pub fn record(&mut self, path: impl Into<PathBuf>, n_frames: u32) -> &mut Self {
+        self.recording = Some(Recording::new(path.into(), n_frames));
+        self
+    }
+
+    /// Render `n_frames` frames without a window or pacing
+    ///
+    /// This is how a headless simulator runs; the callback gets the frame
+    /// number like in [`run_with_callback`](Self::run_with_callback). A
+    /// recording in progress is saved at the end.
+    pub fn run_frames<F>(
+        &mut self,
+        n_frames: u32,
+        mut callback: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(&mut SimulatorDisplay<Rgb565>, u32) -> Result<(), core::convert::Infallible>,
+    {
+        for frame in 0..n_frames {
+            callback(&mut self.display, frame)?;
+            self.capture_frame();
+        }
+        self.save_recording();
+        Ok(())
+    }
+
     pub fn run_animation(
         &mut self,
         animation_fn: AnimationFn,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut frame: u32 = 0;
-        let frame_duration = self
-            .config
-            .target_fps
-            .map(|fps| std::time::Duration::from_millis(1000 / fps as u64));
-
-        'running: loop {
-            let frame_start = std::time::Instant::now();
-
-            // Draw the animation frame
-            animation_fn(&mut self.display, frame)?;
-
-            // Update the window
-            self.window.update(&self.display);
-
-            // Handle events
-            for event in self.window.events() {
-                if event == SimulatorEvent::Quit {
-                    break 'running;
-                }
-            }
-
-            // Control frame rate if specified
-            if let Some(duration) = frame_duration {
-                let elapsed = frame_start.elapsed();
-                if elapsed < duration {
-                    std::thread::sleep(duration - elapsed);
-                }
-            }
-
-            frame = frame.wrapping_add(1);
-        }
-
-        Ok(())
+        self.run_with_callback(animation_fn)
     }
 
     pub fn run_with_callback<F>(
@@ -107,49 +164,204 @@ impl Simulator {
     ) -> Result<(), Box<dyn std::error::Error>>
     where
         F: FnMut(&mut SimulatorDisplay<Rgb565>, u32) -> Result<(), core::convert::Infallible>,
+    {
+        self.run_with_events(|display, frame, _| Ok(callback(display, frame)?))
+    }
+
+    /// Run a callback every frame with the window events received since the
+    /// previous frame
+    ///
+    /// The loop stops when the window is closed or the callback fails. The
+    /// playback controls and pixel inspector from [`controls`] and the
+    /// [`frame_graph`] are handled here; the callback isn't called while
+    /// paused, so the frame counter only advances with rendered frames.
+    ///
+    /// Fails right away when headless, see [`run_frames`](Self::run_frames).
+    ///
+    /// [`controls`]: crate::controls
+    /// [`frame_graph`]: crate::frame_graph
+    pub fn run_with_events<F>(&mut self, callback: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(
+            &mut SimulatorDisplay<Rgb565>,
+            u32,
+            &[SimulatorEvent],
+        ) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        // Out of `self` while the loop runs, so frames can be captured
+        let mut window = self
+            .window
+            .take()
+            .ok_or("a headless simulator has no window, use run_frames")?;
+        let result = self.run_window(&mut window, callback);
+        self.window = Some(window);
+        result
+    }
+
+    fn run_window<F>(
+        &mut self,
+        window: &mut Window,
+        mut callback: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(
+            &mut SimulatorDisplay<Rgb565>,
+            u32,
+            &[SimulatorEvent],
+        ) -> Result<(), Box<dyn std::error::Error>>,
     {
         let mut frame: u32 = 0;
         let frame_duration = self
             .config
             .target_fps
-            .map(|fps| std::time::Duration::from_millis(1000 / fps as u64));
+            .map(|fps| Duration::from_millis(1000 / fps as u64));
+        let mut events = Vec::new();
+        let mut controls = Controls::default();
+        let mut graph = FrameGraph::new(self.config.size.width, self.config.hardware_budget);
 
-        'running: loop {
-            let frame_start = std::time::Instant::now();
+        loop {
+            let frame_start = Instant::now();
 
             // Run the callback
-            callback(&mut self.display, frame)?;
+            if controls.advance() {
+                let callback_start = Instant::now();
+                callback(&mut self.display, frame, &events)?;
+                graph.record(callback_start.elapsed());
+                frame = frame.wrapping_add(1);
+                self.capture_frame();
+            }
 
-            // Update the window
-            self.window.update(&self.display);
+            // Update the window, with the overlays on a copy of the frame
+            if controls.has_overlay() || graph.is_visible() {
+                let mut shown = self.display.clone();
+                if graph.is_visible() {
+                    graph.draw(&mut shown);
+                }
+                controls.draw_overlay(&self.display, &mut shown);
+                window.update(&shown);
+            } else {
+                window.update(&self.display);
+            }
 
             // Handle events
-            for event in self.window.events() {
-                if event == SimulatorEvent::Quit {
-                    break 'running;
+            events.clear();
+            events.extend(window.events());
+            if events.contains(&SimulatorEvent::Quit) {
+                self.save_recording();
+                break;
+            }
+            for event in &events {
+                controls.handle(event);
+                graph.handle(event);
+                if let SimulatorEvent::KeyDown {
+                    keycode: Keycode::F12,
+                    repeat: false,
+                    ..
+                } = event
+                {
+                    self.save_screenshot();
                 }
             }
 
-            // Control frame rate if specified
-            if let Some(duration) = frame_duration {
+            // Control frame rate if specified; without a target, still pace
+            // the loop while paused or in slow motion
+            let duration = match frame_duration {
+                Some(duration) => Some(duration * controls.frame_time_factor()),
+                None if controls.is_throttled() => {
+                    Some(THROTTLED_FRAME_TIME * controls.frame_time_factor())
+                }
+                None => None,
+            };
+            if let Some(duration) = duration {
                 let elapsed = frame_start.elapsed();
                 if elapsed < duration {
                     std::thread::sleep(duration - elapsed);
                 }
             }
-
-            frame = frame.wrapping_add(1);
         }
 
         Ok(())
     }
 
+    /// Save the current frame to a PNG file, scaled like the window
+    ///
+    /// Overlays are not included.
+    pub fn screenshot(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let image = self
+            .display
+            .to_rgb_output_image(&self.config.output_settings());
+        image.save_png(path)?;
+        Ok(())
+    }
+
+    /// Save a timestamped screenshot and copy it to the clipboard if enabled
+    fn save_screenshot(&self) {
+        let path = screenshot::timestamped_path(&self.config.screenshot_dir);
+        let image = self
+            .display
+            .to_rgb_output_image(&self.config.output_settings());
+        match image.save_png(&path) {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(e) => eprintln!("Failed to save screenshot to {}: {e}", path.display()),
+        }
+        if let Err(e) = screenshot::copy_to_clipboard(&image) {
+            eprintln!("Failed to copy screenshot to the clipboard: {e}");
+        }
+    }
+
+    /// Add the current frame to the recording, saving it when complete
+    fn capture_frame(&mut self) {
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        let image = self
+            .display
+            .to_rgb_output_image(&self.config.output_settings());
+        if recording.capture(&image) {
+            self.save_recording();
+        }
+    }
+
+    /// Write the recording in progress, if any
+    fn save_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        let path = recording.path().to_path_buf();
+        match recording.save(self.config.target_fps.unwrap_or(60)) {
+            Ok(frames) => println!("Saved {frames} frames to {}", path.display()),
+            Err(e) => eprintln!("Failed to save recording to {}: {e}", path.display()),
+        }
+    }
+
     pub const fn display_mut(&mut self) -> &mut SimulatorDisplay<Rgb565> {
         &mut self.display
     }
 
-    pub const fn window_mut(&mut self) -> &mut Window {
-        &mut self.window
+    /// The rendered frame
+    pub const fn display(&self) -> &SimulatorDisplay<Rgb565> {
+        &self.display
+    }
+
+    /// Raw RGB565 pixels of the rendered frame, row by row
+    pub fn framebuffer(&self) -> Vec<u16> {
+        self.display
+            .bounding_box()
+            .points()
+            .map(|point| RawU16::from(self.display.get_pixel(point)).into_inner())
+            .collect()
+    }
+
+    /// Panic unless the rendered frame matches the golden PNG at `path`
+    ///
+    /// See [`golden`] for how golden images are created and updated.
+    pub fn assert_frame_matches(&self, path: impl AsRef<Path>) {
+        golden::assert_display_matches(&self.display, path);
+    }
+
+    /// The window, `None` when headless
+    pub const fn window_mut(&mut self) -> Option<&mut Window> {
+        self.window.as_mut()
     }
 }
 
@@ -0,0 +1,134 @@
+//! Mirror a panel's framebuffer in the simulator
+//!
+//! The device streams raw frames over TCP on [`MIRROR_PORT`]: each frame is
+//! `width * height` RGB565 pixels, row-major, little-endian, with no header.
+//! On a slow link a device may compress them with a [`Codec`] instead, sending
+//! each frame as `[size: u32 LE][encoded frame]`; heatshrink brings a
+//! cluster view from 32 KiB down to under 1 KiB.
+//! [`MirrorClient`] reads frames on a background thread, reconnecting when
+//! the connection drops, and keeps the latest one for drawing.
+
+use cluster_core::codec::Codec;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::prelude::*;
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// TCP port the device streams frames on
+pub const MIRROR_PORT: u16 = 4242;
+
+/// Delay before reconnecting after the stream drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Shared {
+    frame: Option<Vec<u16>>,
+    connected: bool,
+}
+
+/// Background reader of a device's frame stream
+pub struct MirrorClient {
+    size: Size,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MirrorClient {
+    /// Start reading frames of `size` pixels from `addr`, encoded with
+    /// `codec` or raw
+    pub fn connect(addr: SocketAddr, size: Size, codec: Option<Codec>) -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let pixels = (size.width * size.height) as usize;
+
+        let reader = Arc::clone(&shared);
+        thread::spawn(move || {
+            loop {
+                if let Err(e) = stream_frames(addr, pixels, codec, &reader) {
+                    eprintln!("Mirror connection to {addr} lost: {e}");
+                }
+                reader.lock().unwrap().connected = false;
+                thread::sleep(RECONNECT_DELAY);
+            }
+        });
+
+        Self { size, shared }
+    }
+
+    /// Check if the stream is currently connected
+    pub fn is_connected(&self) -> bool {
+        self.shared.lock().unwrap().connected
+    }
+
+    /// Draw the latest frame, if one was received
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let shared = self.shared.lock().unwrap();
+        let Some(frame) = &shared.frame else {
+            return Ok(());
+        };
+
+        let width = self.size.width as usize;
+        display.draw_iter(frame.iter().enumerate().map(|(i, &raw)| {
+            let point = Point::new((i % width) as i32, (i / width) as i32);
+            Pixel(point, Rgb565::from(RawU16::new(raw)))
+        }))
+    }
+}
+
+fn stream_frames(
+    addr: SocketAddr,
+    pixels: usize,
+    codec: Option<Codec>,
+    shared: &Mutex<Shared>,
+) -> io::Result<()> {
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))?;
+    println!("Mirroring {addr}");
+    shared.lock().unwrap().connected = true;
+
+    let mut buffer = vec![0u8; pixels * 2];
+    loop {
+        let frame = match codec {
+            None => {
+                stream.read_exact(&mut buffer)?;
+                buffer
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                    .collect()
+            }
+            Some(codec) => read_encoded(&mut stream, codec, pixels, &mut buffer)?,
+        };
+        shared.lock().unwrap().frame = Some(frame);
+    }
+}
+
+/// Read a frame sent as `[size: u32 LE][encoded frame]`
+fn read_encoded(
+    stream: &mut impl Read,
+    codec: Codec,
+    pixels: usize,
+    buffer: &mut Vec<u8>,
+) -> io::Result<Vec<u16>> {
+    let mut size = [0; 4];
+    stream.read_exact(&mut size)?;
+    let size = u32::from_le_bytes(size) as usize;
+    // Runs of single pixels are the largest encoding
+    if size > pixels * 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {size} bytes is too large"),
+        ));
+    }
+    buffer.resize(size, 0);
+    stream.read_exact(buffer)?;
+
+    let mut frame = vec![0; pixels];
+    codec
+        .decode(buffer, &mut frame)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(frame)
+}
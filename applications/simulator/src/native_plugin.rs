@@ -5,10 +5,14 @@
 //!
 //! C plugins use name-prefixed symbols: `{name}_init`, `{name}_update`, `{name}_cleanup`
 //! Rust plugins use generic symbols: `__plugin_init`, `__plugin_update`, `__plugin_cleanup`
+//!
+//! The `pause` and `resume` symbols are optional, like the entry points of the
+//! `PLUGIN_CAP_PAUSE` capability on the device. Permissions are read from the
+//! `PLUGIN_HEADER` both kinds of plugins export.
 
 use crate::plugin_host::Plugin;
 use libloading::{Library, Symbol};
-use plugin_api::{Inputs, PluginAPI};
+use plugin_api::{InputState, Inputs, PLUGIN_API_VERSION, PLUGIN_MAGIC, PluginAPI, PluginHeader};
 use std::path::Path;
 
 // Include the list of compiled native plugins from build.rs
@@ -28,8 +32,11 @@ pub struct NativePlugin {
     _lib: Library,
     name: &'static str,
     init_fn: Symbol<'static, unsafe extern "C" fn(*const PluginAPI) -> i32>,
-    update_fn: Symbol<'static, unsafe extern "C" fn(*const PluginAPI, u32)>,
+    update_fn: Symbol<'static, unsafe extern "C" fn(*const PluginAPI, *const InputState)>,
     cleanup_fn: Symbol<'static, unsafe extern "C" fn()>,
+    pause_fn: Option<Symbol<'static, unsafe extern "C" fn()>>,
+    resume_fn: Option<Symbol<'static, unsafe extern "C" fn()>>,
+    permissions: u32,
 }
 
 impl NativePlugin {
@@ -43,16 +50,20 @@ impl NativePlugin {
             let lib = Library::new(path).map_err(|e| format!("Failed to load library: {}", e))?;
 
             // Build symbol names based on convention
-            let (init_name, update_name, cleanup_name) = match convention {
+            let (init_name, update_name, cleanup_name, pause_name, resume_name) = match convention {
                 SymbolConvention::NamePrefixed => (
                     format!("{}_init\0", name),
                     format!("{}_update\0", name),
                     format!("{}_cleanup\0", name),
+                    format!("{}_pause\0", name),
+                    format!("{}_resume\0", name),
                 ),
                 SymbolConvention::Generic => (
                     "__plugin_init\0".to_string(),
                     "__plugin_update\0".to_string(),
                     "__plugin_cleanup\0".to_string(),
+                    "__plugin_pause\0".to_string(),
+                    "__plugin_resume\0".to_string(),
                 ),
             };
 
@@ -65,11 +76,13 @@ impl NativePlugin {
             let init_fn: Symbol<'static, unsafe extern "C" fn(*const PluginAPI) -> i32> =
                 std::mem::transmute(init_fn);
 
-            let update_fn: Symbol<unsafe extern "C" fn(*const PluginAPI, u32)> = lib
+            let update_fn: Symbol<unsafe extern "C" fn(*const PluginAPI, *const InputState)> = lib
                 .get(update_name.as_bytes())
                 .map_err(|e| format!("Failed to find update symbol: {}", e))?;
-            let update_fn: Symbol<'static, unsafe extern "C" fn(*const PluginAPI, u32)> =
-                std::mem::transmute(update_fn);
+            let update_fn: Symbol<
+                'static,
+                unsafe extern "C" fn(*const PluginAPI, *const InputState),
+            > = std::mem::transmute(update_fn);
 
             let cleanup_fn: Symbol<unsafe extern "C" fn()> = lib
                 .get(cleanup_name.as_bytes())
@@ -77,12 +90,35 @@ impl NativePlugin {
             let cleanup_fn: Symbol<'static, unsafe extern "C" fn()> =
                 std::mem::transmute(cleanup_fn);
 
+            // Optional lifecycle entry points
+            let pause_fn = lib
+                .get::<unsafe extern "C" fn()>(pause_name.as_bytes())
+                .ok()
+                .map(|symbol| std::mem::transmute(symbol));
+            let resume_fn = lib
+                .get::<unsafe extern "C" fn()>(resume_name.as_bytes())
+                .ok()
+                .map(|symbol| std::mem::transmute(symbol));
+
+            let header = lib
+                .get::<*const PluginHeader>(b"PLUGIN_HEADER\0")
+                .ok()
+                .map(|header| &**header)
+                .filter(|header| header.magic == PLUGIN_MAGIC);
+            if header.is_some_and(|header| header.api_version != PLUGIN_API_VERSION) {
+                return Err("Plugin API version mismatch".to_string());
+            }
+            let permissions = header.map_or(0, |header| header.permissions);
+
             Ok(Self {
                 _lib: lib,
                 name,
                 init_fn,
                 update_fn,
                 cleanup_fn,
+                pause_fn,
+                resume_fn,
+                permissions,
             })
         }
     }
@@ -143,14 +179,31 @@ impl Plugin for NativePlugin {
     }
 
     fn update(&mut self, api: &mut PluginAPI, inputs: Inputs) {
-        unsafe { (self.update_fn)(api as *const PluginAPI, inputs.raw()) }
+        let api = api as *const PluginAPI;
+        unsafe { (self.update_fn)(api, &inputs.state()) }
     }
 
     fn cleanup(&mut self) {
         unsafe { (self.cleanup_fn)() }
     }
 
+    fn pause(&mut self) {
+        if let Some(pause_fn) = &self.pause_fn {
+            unsafe { pause_fn() }
+        }
+    }
+
+    fn resume(&mut self) {
+        if let Some(resume_fn) = &self.resume_fn {
+            unsafe { resume_fn() }
+        }
+    }
+
     fn name(&self) -> &'static str {
         self.name
     }
+
+    fn permissions(&self) -> u32 {
+        self.permissions
+    }
 }
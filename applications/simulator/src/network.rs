@@ -0,0 +1,83 @@
+//! Simulated network conditions for the requests of the simulator
+//!
+//! `sim cluster --poll` goes through a [`FlakyNetwork`] to see how the
+//! display copes with a slow or unreliable server: requests are delayed,
+//! fail as if the connection dropped, or fail with a `500` before reaching
+//! the server. Firmware tests get the same faults from `cluster_net::faults`.
+
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Network conditions to simulate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkConditions {
+    /// Delay before each request
+    pub latency: Duration,
+    /// Extra delay drawn for each request, up to this much
+    pub jitter: Duration,
+    /// Share of requests failing as if the connection dropped, in percent
+    pub drop_percent: u8,
+    /// Share of requests answered with a `500`, in percent
+    pub server_error_percent: u8,
+}
+
+/// Applies [`NetworkConditions`] to requests
+pub struct FlakyNetwork {
+    conditions: NetworkConditions,
+    state: u32,
+}
+
+impl FlakyNetwork {
+    /// Draw faults from `seed`, the same for every run with the same seed
+    pub fn new(conditions: NetworkConditions, seed: u32) -> Self {
+        Self {
+            conditions,
+            // Xorshift never leaves 0
+            state: seed.max(1),
+        }
+    }
+
+    /// Draw faults from the clock
+    pub fn from_clock(conditions: NetworkConditions) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        Self::new(conditions, nanos)
+    }
+
+    /// Run `request` over the simulated network
+    ///
+    /// Injected faults fail with an [`io::Error`] without calling `request`.
+    pub fn send<T, E: From<io::Error>>(
+        &mut self,
+        request: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let roll = self.next_u32() % 100;
+        let drop_percent = u32::from(self.conditions.drop_percent);
+        if roll < drop_percent {
+            let error = io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "simulated dropped connection",
+            );
+            return Err(error.into());
+        }
+        if roll < drop_percent + u32::from(self.conditions.server_error_percent) {
+            return Err(io::Error::other("simulated 500 Internal Server Error").into());
+        }
+
+        let jitter_ms = self.conditions.jitter.as_millis() as u32;
+        let jitter = match jitter_ms {
+            0 => 0,
+            _ => self.next_u32() % (jitter_ms + 1),
+        };
+        std::thread::sleep(self.conditions.latency + Duration::from_millis(jitter.into()));
+        request()
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+}
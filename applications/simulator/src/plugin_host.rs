@@ -4,14 +4,26 @@
 //! compiled for the host platform, bridging between the plugin API
 //! and the embedded-graphics simulator.
 
+use cluster_core::models::{Cluster, Layout};
+use cluster_core::types::{Attribute, Kind, Status};
 use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::pixelcolor::raw::RawU16;
 use embedded_graphics::prelude::*;
 use embedded_graphics_simulator::SimulatorDisplay;
+use plugin_api::assets::{ASSET_NOT_FOUND, AssetTable};
+use plugin_api::data::*;
+use plugin_api::permissions::{
+    PLUGIN_ERR_DENIED, PLUGIN_PERM_ALL, PLUGIN_PERM_SOUND, PLUGIN_PERM_STORAGE,
+};
+use plugin_api::storage::{self, STORAGE_SIZE, Storage};
 use plugin_api::*;
+use plugin_api::{font, raster};
 use std::cell::RefCell;
 use std::time::Instant;
 
+/// Seed of `random()` when none is given
+const DEFAULT_SEED: u32 = 0xDEADBEEF;
+
 // Thread-local storage for the runtime pointer (used by C-style callbacks)
 thread_local! {
     static RUNTIME_PTR: RefCell<Option<*mut SimulatorPluginRuntime>> = const { RefCell::new(None) };
@@ -33,8 +45,19 @@ pub trait Plugin: Send {
     /// Clean up plugin resources
     fn cleanup(&mut self);
 
+    /// Updates stop until `resume`, for plugins that implement it
+    fn pause(&mut self) {}
+
+    /// Updates start again after `pause`
+    fn resume(&mut self) {}
+
     /// Get the plugin name
     fn name(&self) -> &'static str;
+
+    /// `PLUGIN_PERM_*` flags of the gated functions the plugin uses
+    fn permissions(&self) -> u32 {
+        0
+    }
 }
 
 /// Plugin runtime for the simulator
@@ -42,9 +65,23 @@ pub struct SimulatorPluginRuntime {
     framebuffer: FrameBuffer,
     graphics_ctx: GraphicsContext,
     system_ctx: SystemContext,
+    data_ctx: DataContext,
     api: PluginAPI,
     start_time: Instant,
     rng_state: u32,
+    storage: Storage,
+    /// Name of the plugin, the namespace of its storage keys
+    plugin_name: &'static str,
+    /// Permissions requested by the plugin
+    plugin_permissions: u32,
+    /// Permissions granted to the plugin, see `set_granted_permissions`
+    granted: u32,
+    /// Cluster data of the plugin, see `set_layout`
+    layout: Option<Layout>,
+    /// Display reported to the plugin, see `set_display_info`
+    display_info: DisplayInfo,
+    /// Asset table and data of the plugin, see `set_assets`
+    assets: Vec<u8>,
 }
 
 impl SimulatorPluginRuntime {
@@ -65,6 +102,8 @@ impl SimulatorPluginRuntime {
                 draw_line_fn: gfx_draw_line,
                 draw_circle_fn: gfx_draw_circle,
                 blit_fn: gfx_blit,
+                draw_text_fn: gfx_draw_text,
+                text_width_fn: gfx_text_width,
             },
             system_ctx: SystemContext {
                 random_fn: sys_random,
@@ -78,30 +117,66 @@ impl SimulatorPluginRuntime {
                 color_yellow: 0xFFE0,
                 color_cyan: 0x07FF,
                 color_magenta: 0xF81F,
+                deterministic: false,
+                seed: DEFAULT_SEED,
+                storage_read_fn: sys_storage_read,
+                storage_write_fn: sys_storage_write,
+                tone_fn: sys_tone,
+                display_info_fn: sys_display_info,
+                read_asset_fn: sys_read_asset,
+            },
+            data_ctx: DataContext {
+                get_cluster_seat_count_fn: data_cluster_seat_count,
+                get_seat_fn: data_seat,
+                get_cluster_attr_mask_fn: data_cluster_attr_mask,
             },
             api: PluginAPI {
                 framebuffer: std::ptr::null_mut(),
                 gfx: std::ptr::null(),
                 sys: std::ptr::null(),
+                data: std::ptr::null(),
             },
             start_time: Instant::now(),
-            rng_state: 0xDEADBEEF,
+            rng_state: DEFAULT_SEED,
+            storage: Storage::new(),
+            plugin_name: "",
+            plugin_permissions: 0,
+            granted: PLUGIN_PERM_ALL,
+            layout: None,
+            display_info: DisplayInfo::UNKNOWN,
+            assets: Vec::new(),
         };
 
         // Set up API pointers
         runtime.api.framebuffer = &mut runtime.framebuffer as *mut _;
         runtime.api.gfx = &runtime.graphics_ctx as *const _;
         runtime.api.sys = &runtime.system_ctx as *const _;
+        runtime.api.data = &runtime.data_ctx as *const _;
 
         runtime
     }
 
+    /// Create a runtime whose plugin output only depends on the frame
+    ///
+    /// `random()` starts from `seed` and `millis()` follows the frame
+    /// counter instead of the clock, so the same frame index always yields
+    /// the same image, as needed by golden-image tests.
+    pub fn new_deterministic(seed: u32) -> Self {
+        let mut runtime = Self::new();
+        // Xorshift never leaves zero
+        runtime.rng_state = if seed == 0 { DEFAULT_SEED } else { seed };
+        runtime.system_ctx.deterministic = true;
+        runtime.system_ctx.seed = seed;
+        runtime
+    }
+
     /// Update API pointers to current memory location
     /// Required because the struct may have moved since new()
     fn refresh_api_pointers(&mut self) {
         self.api.framebuffer = &mut self.framebuffer as *mut _;
         self.api.gfx = &self.graphics_ctx as *const _;
         self.api.sys = &self.system_ctx as *const _;
+        self.api.data = &self.data_ctx as *const _;
     }
 
     /// Initialize a plugin
@@ -114,11 +189,63 @@ impl SimulatorPluginRuntime {
             *ptr.borrow_mut() = Some(self as *mut _);
         });
 
+        self.plugin_name = plugin.name();
+        self.plugin_permissions = plugin.permissions();
         plugin.init(&mut self.api)
     }
 
-    /// Run one update cycle
-    pub fn update<P: Plugin>(&mut self, plugin: &mut P, inputs: u32) {
+    /// Set the `PLUGIN_PERM_*` flags granted to the plugin
+    ///
+    /// The simulator grants everything by default; the plugin still only
+    /// gets what it requested.
+    pub fn set_granted_permissions(&mut self, granted: u32) {
+        self.granted = granted;
+    }
+
+    /// Check that the plugin may use `permission`
+    fn allowed(&self, permission: u32) -> bool {
+        self.plugin_permissions & self.granted & permission != 0
+    }
+
+    /// Restore the plugin storage from an image saved by
+    /// [`storage_to_persist`](Self::storage_to_persist)
+    pub fn load_storage(&mut self, image: &[u8]) -> bool {
+        self.storage.load(image)
+    }
+
+    /// Storage image to save, if the plugin changed it since the last call
+    pub fn storage_to_persist(&mut self) -> Option<&[u8; STORAGE_SIZE]> {
+        self.storage.take_dirty().then(|| self.storage.image())
+    }
+
+    /// Give the plugin cluster data, e.g. a layout loaded from a file
+    ///
+    /// Without one the plugin sees clusters without seats.
+    pub fn set_layout(&mut self, layout: Option<Layout>) {
+        self.layout = layout;
+    }
+
+    /// Describe the display the plugin runs on, e.g. to try its effects at
+    /// the refresh rate of slower hardware
+    pub fn set_display_info(&mut self, info: DisplayInfo) {
+        self.display_info = info;
+    }
+
+    /// Give the plugin the assets of a segmented binary, as an asset table
+    /// followed by the data (see `plugin_api::assets::build_table`)
+    ///
+    /// Native plugins are not packed, so the assets come separately.
+    pub fn set_assets(&mut self, table: Vec<u8>) {
+        self.assets = table;
+    }
+
+    fn cluster(&self, cluster: u32) -> Option<&Cluster> {
+        let id = *Layout::FLOORS.get(cluster as usize)?;
+        self.layout.as_ref()?.get(id)
+    }
+
+    /// Run one update cycle, a bare `u32` input being the button bitmask
+    pub fn update<P: Plugin>(&mut self, plugin: &mut P, input: impl Into<InputState>) {
         // Refresh API pointers in case struct was moved
         self.refresh_api_pointers();
 
@@ -127,13 +254,18 @@ impl SimulatorPluginRuntime {
             *ptr.borrow_mut() = Some(self as *mut _);
         });
 
-        plugin.update(&mut self.api, Inputs::from_raw(inputs));
+        plugin.update(&mut self.api, Inputs::from_state(input.into()));
         self.framebuffer.frame_counter = self.framebuffer.frame_counter.wrapping_add(1);
     }
 
-    /// Get elapsed milliseconds since runtime creation
+    /// Get elapsed milliseconds since runtime creation, or the time of the
+    /// current frame in deterministic mode
     pub fn millis(&self) -> u32 {
-        self.start_time.elapsed().as_millis() as u32
+        if self.system_ctx.deterministic {
+            self.framebuffer.frame_counter.saturating_mul(FRAME_TIME_MS)
+        } else {
+            self.start_time.elapsed().as_millis() as u32
+        }
     }
 
     /// Get a random number using xorshift
@@ -192,19 +324,11 @@ where
 }
 
 fn set_pixel_internal(runtime: &mut SimulatorPluginRuntime, x: i32, y: i32, color: u16) {
-    if x >= 0 && x < DISPLAY_WIDTH as i32 && y >= 0 && y < DISPLAY_HEIGHT as i32 {
-        let idx = (y as usize) * DISPLAY_WIDTH + (x as usize);
-        runtime.framebuffer.pixels[idx] = color;
-    }
+    runtime.framebuffer.checked_set_pixel(x, y, color);
 }
 
 fn get_pixel_internal(runtime: &SimulatorPluginRuntime, x: i32, y: i32) -> u16 {
-    if x >= 0 && x < DISPLAY_WIDTH as i32 && y >= 0 && y < DISPLAY_HEIGHT as i32 {
-        let idx = (y as usize) * DISPLAY_WIDTH + (x as usize);
-        runtime.framebuffer.pixels[idx]
-    } else {
-        0
-    }
+    runtime.framebuffer.checked_get_pixel(x, y).unwrap_or(0)
 }
 
 fn clear_internal(runtime: &mut SimulatorPluginRuntime, color: u16) {
@@ -219,20 +343,7 @@ fn fill_rect_internal(
     h: i32,
     color: u16,
 ) {
-    let x_start = x.max(0) as usize;
-    let y_start = y.max(0) as usize;
-    let x_end = ((x + w).min(DISPLAY_WIDTH as i32) as usize).min(DISPLAY_WIDTH);
-    let y_end = ((y + h).min(DISPLAY_HEIGHT as i32) as usize).min(DISPLAY_HEIGHT);
-
-    if x_start >= x_end || y_start >= y_end {
-        return;
-    }
-
-    for py in y_start..y_end {
-        for px in x_start..x_end {
-            runtime.framebuffer.pixels[py * DISPLAY_WIDTH + px] = color;
-        }
-    }
+    runtime.framebuffer.fill_rect(x, y, w, h, color);
 }
 
 fn draw_line_internal(
@@ -243,33 +354,7 @@ fn draw_line_internal(
     y1: i32,
     color: u16,
 ) {
-    // Bresenham's line algorithm
-    let mut x = x0;
-    let mut y = y0;
-
-    let dx = (x1 - x0).abs();
-    let dy = (y1 - y0).abs();
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
-    let mut err = dx - dy;
-
-    loop {
-        set_pixel_internal(runtime, x, y, color);
-
-        if x == x1 && y == y1 {
-            break;
-        }
-
-        let e2 = 2 * err;
-        if e2 > -dy {
-            err -= dy;
-            x += sx;
-        }
-        if e2 < dx {
-            err += dx;
-            y += sy;
-        }
-    }
+    runtime.framebuffer.draw_line(x0, y0, x1, y1, color);
 }
 
 fn draw_circle_internal(
@@ -279,34 +364,7 @@ fn draw_circle_internal(
     radius: i32,
     color: u16,
 ) {
-    if radius < 0 {
-        return;
-    }
-
-    // Midpoint circle algorithm
-    let mut x = radius;
-    let mut y = 0;
-    let mut decision = 1 - radius;
-
-    while x >= y {
-        set_pixel_internal(runtime, cx + x, cy + y, color);
-        set_pixel_internal(runtime, cx - x, cy + y, color);
-        set_pixel_internal(runtime, cx + x, cy - y, color);
-        set_pixel_internal(runtime, cx - x, cy - y, color);
-        set_pixel_internal(runtime, cx + y, cy + x, color);
-        set_pixel_internal(runtime, cx - y, cy + x, color);
-        set_pixel_internal(runtime, cx + y, cy - x, color);
-        set_pixel_internal(runtime, cx - y, cy - x, color);
-
-        y += 1;
-
-        if decision <= 0 {
-            decision += 2 * y + 1;
-        } else {
-            x -= 1;
-            decision += 2 * (y - x) + 1;
-        }
-    }
+    runtime.framebuffer.draw_circle(cx, cy, radius, color);
 }
 
 fn blit_internal(
@@ -317,24 +375,45 @@ fn blit_internal(
     h: i32,
     data: *const u16,
 ) {
-    if data.is_null() || w <= 0 || h <= 0 || w > 1024 || h > 1024 {
+    let max = 1..=raster::MAX_BLIT_SIZE;
+    if data.is_null() || !max.contains(&w) || !max.contains(&h) {
         return;
     }
 
-    unsafe {
-        for dy in 0..h {
-            for dx in 0..w {
-                let px = x + dx;
-                let py = y + dy;
-
-                if px >= 0 && px < DISPLAY_WIDTH as i32 && py >= 0 && py < DISPLAY_HEIGHT as i32 {
-                    let src_idx = (dy * w + dx) as usize;
-                    let dst_idx = (py as usize) * DISPLAY_WIDTH + (px as usize);
-                    runtime.framebuffer.pixels[dst_idx] = *data.add(src_idx);
-                }
-            }
-        }
+    // SAFETY: the API requires `data` to hold `w * h` pixels
+    let data = unsafe { std::slice::from_raw_parts(data, (w * h) as usize) };
+    runtime.framebuffer.blit(x, y, w, h, data);
+}
+
+/// Text passed to the text callbacks, cut to `font::MAX_TEXT_LEN` bytes
+///
+/// # Safety
+///
+/// `text` must be null or point to `len` readable bytes.
+unsafe fn text_bytes<'a>(text: *const u8, len: u32) -> &'a [u8] {
+    if text.is_null() {
+        return &[];
     }
+    let len = (len as usize).min(font::MAX_TEXT_LEN);
+    // SAFETY: guaranteed by the caller
+    unsafe { std::slice::from_raw_parts(text, len) }
+}
+
+/// Bytes passed to the storage callbacks, `None` if more than `max`
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+unsafe fn storage_bytes<'a>(data: *const u8, len: u32, max: usize) -> Option<&'a [u8]> {
+    let len = len as usize;
+    if len > max {
+        return None;
+    }
+    if data.is_null() {
+        return (len == 0).then_some(&[]);
+    }
+    // SAFETY: guaranteed by the caller
+    Some(unsafe { std::slice::from_raw_parts(data, len) })
 }
 
 // ============================================================================
@@ -369,6 +448,70 @@ unsafe extern "C" fn gfx_blit(x: i32, y: i32, w: i32, h: i32, data: *const u16)
     with_runtime(|runtime| blit_internal(runtime, x, y, w, h, data));
 }
 
+unsafe extern "C" fn gfx_draw_text(x: i32, y: i32, text: *const u8, len: u32, color: u16) {
+    let text = unsafe { text_bytes(text, len) };
+    with_runtime(|runtime| runtime.framebuffer.draw_text(x, y, text, color));
+}
+
+unsafe extern "C" fn gfx_text_width(text: *const u8, len: u32) -> i32 {
+    font::text_width(unsafe { text_bytes(text, len) })
+}
+
+unsafe extern "C" fn data_cluster_seat_count(cluster: u32) -> u32 {
+    with_runtime(|runtime| {
+        runtime
+            .cluster(cluster)
+            .map_or(0, |cluster| cluster.seats.len() as u32)
+    })
+}
+
+unsafe extern "C" fn data_seat(cluster: u32, index: u32, out: *mut SeatInfo) -> i32 {
+    let seat = with_runtime(|runtime| {
+        let seat = runtime.cluster(cluster)?.seats.get(index as usize)?;
+        Some(SeatInfo {
+            x: u16::try_from(seat.x).unwrap_or(u16::MAX),
+            y: u16::try_from(seat.y).unwrap_or(u16::MAX),
+            status: match seat.status {
+                Status::Free => SEAT_STATUS_FREE,
+                Status::Taken => SEAT_STATUS_TAKEN,
+                Status::Reported => SEAT_STATUS_REPORTED,
+                Status::Broken => SEAT_STATUS_BROKEN,
+            },
+            kind: match seat.kind {
+                Kind::Mac => SEAT_KIND_MAC,
+                Kind::Lenovo => SEAT_KIND_LENOVO,
+                Kind::Dell => SEAT_KIND_DELL,
+                Kind::Flex => SEAT_KIND_FLEX,
+            },
+        })
+    });
+    match seat {
+        Some(seat) if !out.is_null() => {
+            unsafe { out.write_unaligned(seat) };
+            0
+        }
+        _ => DATA_NOT_FOUND,
+    }
+}
+
+unsafe extern "C" fn data_cluster_attr_mask(cluster: u32) -> u32 {
+    with_runtime(|runtime| {
+        let Some(cluster) = runtime.cluster(cluster) else {
+            return 0;
+        };
+        cluster
+            .attributes
+            .iter()
+            .fold(0, |mask, attribute| match attribute {
+                Attribute::Piscine => mask | CLUSTER_ATTR_PISCINE,
+                Attribute::Exam => mask | CLUSTER_ATTR_EXAM,
+                Attribute::Silent => mask | CLUSTER_ATTR_SILENT,
+                Attribute::Event => mask | CLUSTER_ATTR_EVENT,
+                Attribute::Closed => mask | CLUSTER_ATTR_CLOSED,
+            })
+    })
+}
+
 unsafe extern "C" fn sys_random() -> u32 {
     with_runtime(|runtime| runtime.random())
 }
@@ -377,6 +520,92 @@ unsafe extern "C" fn sys_millis() -> u32 {
     with_runtime(|runtime| runtime.millis())
 }
 
+unsafe extern "C" fn sys_storage_read(
+    key: *const u8,
+    key_len: u32,
+    buf: *mut u8,
+    buf_len: u32,
+) -> i32 {
+    let Some(key) = (unsafe { storage_bytes(key, key_len, storage::MAX_KEY_LEN) }) else {
+        return storage::STORAGE_NOT_FOUND;
+    };
+    // Values are never longer, so neither is what gets copied
+    let buf_len = (buf_len as usize).min(storage::MAX_VALUE_LEN);
+    let buf: &mut [u8] = if buf.is_null() {
+        &mut []
+    } else {
+        // SAFETY: the API requires `buf` to hold `buf_len` bytes
+        unsafe { std::slice::from_raw_parts_mut(buf, buf_len) }
+    };
+    with_runtime(|runtime| {
+        if !runtime.allowed(PLUGIN_PERM_STORAGE) {
+            return PLUGIN_ERR_DENIED;
+        }
+        let namespace = runtime.plugin_name.as_bytes();
+        runtime
+            .storage
+            .read(namespace, key, buf)
+            .map_or(storage::STORAGE_NOT_FOUND, |len| len as i32)
+    })
+}
+
+unsafe extern "C" fn sys_storage_write(
+    key: *const u8,
+    key_len: u32,
+    data: *const u8,
+    len: u32,
+) -> i32 {
+    let Some(key) = (unsafe { storage_bytes(key, key_len, storage::MAX_KEY_LEN) }) else {
+        return storage::STORAGE_ERR_INVALID_KEY;
+    };
+    let Some(value) = (unsafe { storage_bytes(data, len, storage::MAX_VALUE_LEN) }) else {
+        return storage::STORAGE_ERR_TOO_LARGE;
+    };
+    with_runtime(|runtime| {
+        if !runtime.allowed(PLUGIN_PERM_STORAGE) {
+            return PLUGIN_ERR_DENIED;
+        }
+        let namespace = runtime.plugin_name.as_bytes();
+        match runtime.storage.write(namespace, key, value) {
+            Ok(()) => 0,
+            Err(error) => error.code(),
+        }
+    })
+}
+
+/// The simulator has no sound output, tones are dropped once allowed
+unsafe extern "C" fn sys_tone(_freq_hz: u32, _duration_ms: u32) -> i32 {
+    with_runtime(|runtime| {
+        if runtime.allowed(PLUGIN_PERM_SOUND) {
+            0
+        } else {
+            PLUGIN_ERR_DENIED
+        }
+    })
+}
+
+unsafe extern "C" fn sys_display_info(out: *mut DisplayInfo) {
+    with_runtime(|runtime| {
+        if !out.is_null() {
+            unsafe { out.write(runtime.display_info) }
+        }
+    })
+}
+
+unsafe extern "C" fn sys_read_asset(asset: u32, offset: u32, buf: *mut u8, buf_len: u32) -> i32 {
+    with_runtime(|runtime| {
+        let Some(assets) = AssetTable::parse(&runtime.assets) else {
+            return ASSET_NOT_FOUND;
+        };
+        let buf = if buf.is_null() {
+            &mut []
+        } else {
+            unsafe { std::slice::from_raw_parts_mut(buf, buf_len as usize) }
+        };
+        assets.read(asset as usize, offset as usize, buf)
+    })
+}
+
 unsafe extern "C" fn sys_rgb(r: u8, g: u8, b: u8) -> u16 {
     ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | ((b as u16 & 0xF8) >> 3)
 }
@@ -0,0 +1,66 @@
+//! Upload a plugin binary to a device running `plugin_test` with the
+//! `usb-loader` feature
+//!
+//! The binary goes in the upload frames of `cluster_core::framing`: its size
+//! and CRC, chunks in order, then an end frame the device answers with a
+//! status once it has swapped its main plugin. As for
+//! [`usb_display`](crate::usb_display), the port is opened as a plain file.
+
+use cluster_core::framing::{
+    FrameDecoder, KIND_PLUGIN_BEGIN, KIND_PLUGIN_CHUNK, KIND_PLUGIN_END, KIND_PLUGIN_STATUS,
+    PLUGIN_CHUNK_SIZE, crc32, encode, frame_size,
+};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Send `binary` to the device on the serial port at `port`
+///
+/// Returns the device's status message, or its error as
+/// [`io::ErrorKind::Other`].
+pub fn upload(port: &Path, binary: &[u8]) -> io::Result<String> {
+    let mut port = OpenOptions::new().read(true).write(true).open(port)?;
+    let size = u32::try_from(binary.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "plugin too large"))?;
+
+    let mut stream = Vec::new();
+    let mut sequence = 0u8;
+    let mut push = |kind, payload: &[u8]| {
+        let start = stream.len();
+        stream.resize(start + frame_size(payload.len()), 0);
+        encode(kind, sequence, payload, &mut stream[start..]);
+        sequence = sequence.wrapping_add(1);
+    };
+
+    let mut begin = size.to_le_bytes().to_vec();
+    begin.extend_from_slice(&crc32(binary).to_le_bytes());
+    push(KIND_PLUGIN_BEGIN, &begin);
+    for (i, chunk) in binary.chunks(PLUGIN_CHUNK_SIZE).enumerate() {
+        let mut payload = ((i * PLUGIN_CHUNK_SIZE) as u32).to_le_bytes().to_vec();
+        payload.extend_from_slice(chunk);
+        push(KIND_PLUGIN_CHUNK, &payload);
+    }
+    push(KIND_PLUGIN_END, &[]);
+    port.write_all(&stream)?;
+    port.flush()?;
+
+    // The device answers the end, or the first frame it rejected
+    let mut decoder = FrameDecoder::<128>::new();
+    let mut byte = [0];
+    loop {
+        if port.read(&mut byte)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let Some(Ok(frame)) = decoder.push(byte[0]) else {
+            continue;
+        };
+        if frame.kind != KIND_PLUGIN_STATUS || frame.payload.is_empty() {
+            continue;
+        }
+        let message = String::from_utf8_lossy(&frame.payload[1..]).into_owned();
+        return match frame.payload[0] {
+            0 => Ok(message),
+            _ => Err(io::Error::other(message)),
+        };
+    }
+}
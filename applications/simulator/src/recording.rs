@@ -0,0 +1,72 @@
+//! Animated PNG recordings of the simulated panel
+//!
+//! [`Simulator::record`] captures the next rendered frames, scaled like the
+//! window, and writes them as a looping APNG once enough frames are in or
+//! the window is closed. Browsers and GitHub play APNGs inline, so an
+//! animation can be previewed in a pull request without filming the panel.
+//!
+//! [`Simulator::record`]: crate::Simulator::record
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics_simulator::OutputImage;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Frames captured so far for one recording
+pub(crate) struct Recording {
+    path: PathBuf,
+    frames_wanted: u32,
+    width: u32,
+    height: u32,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recording {
+    pub(crate) fn new(path: PathBuf, frames_wanted: u32) -> Self {
+        Self {
+            path,
+            frames_wanted,
+            width: 0,
+            height: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add a frame, returning whether the recording is complete
+    pub(crate) fn capture(&mut self, image: &OutputImage<Rgb888>) -> bool {
+        let buffer = image.as_image_buffer();
+        self.width = buffer.width();
+        self.height = buffer.height();
+        self.frames.push(buffer.into_raw());
+        self.frames.len() as u32 >= self.frames_wanted
+    }
+
+    /// Write the captured frames, each shown for `1 / fps` seconds
+    ///
+    /// Returns the number of frames written.
+    pub(crate) fn save(self, fps: u32) -> Result<usize, Box<dyn std::error::Error>> {
+        if self.frames.is_empty() {
+            return Err("no frames were captured".into());
+        }
+
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        // 0 plays loops forever
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        encoder.set_frame_delay(1, fps.clamp(1, u32::from(u16::MAX)) as u16)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(frame)?;
+        }
+        writer.finish()?;
+        Ok(self.frames.len())
+    }
+}
@@ -0,0 +1,102 @@
+//! Playback of the frame recordings saved by a device
+//!
+//! With the `frame-recording` feature, the firmware saves the last frames
+//! it showed to flash (see `cluster_core::recording`). A dump of the
+//! recording region read back with picotool holds a recording per slot,
+//! each starting on a flash sector; [`Replay`] loads the latest one.
+//! `sim replay` shows one recorded frame per window frame, so Space and N
+//! go through a glitch frame by frame.
+
+use cluster_core::recording::{Recording, RecordingError};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::prelude::*;
+use std::io;
+use std::path::Path;
+
+/// Alignment of the recordings in a dump, the flash erase size
+const SECTOR_SIZE: usize = 4096;
+
+/// Frame of a recording, decoded
+pub struct ReplayFrame {
+    /// When the device showed the frame, in milliseconds since boot
+    pub timestamp_ms: u32,
+    pixels: Vec<u16>,
+}
+
+/// Decoded frames of the latest recording of a dump
+pub struct Replay {
+    size: Size,
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// Load the latest recording of a flash dump
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// Decode the latest recording of a flash dump
+    pub fn parse(dump: &[u8]) -> io::Result<Self> {
+        let recording = (0..dump.len())
+            .step_by(SECTOR_SIZE)
+            .filter_map(|offset| Recording::parse(&dump[offset..]).ok())
+            .max_by_key(|recording| recording.header.sequence)
+            .ok_or_else(|| invalid(RecordingError::NotFound))?;
+
+        let header = recording.header;
+        let frames = recording
+            .frames()
+            .map(|frame| {
+                let frame = frame.map_err(invalid)?;
+                let mut pixels = vec![0; header.pixels()];
+                frame.decode(&mut pixels).map_err(invalid)?;
+                Ok(ReplayFrame {
+                    timestamp_ms: frame.timestamp_ms,
+                    pixels,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if frames.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the recording holds no frame",
+            ));
+        }
+        Ok(Self {
+            size: Size::new(header.width.into(), header.height.into()),
+            frames,
+        })
+    }
+
+    /// Size of the recorded panel
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Recorded frames, oldest first
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    /// Draw frame `index`, looping over the recording
+    pub fn draw<D>(&self, index: usize, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let frame = &self.frames[index % self.frames.len()];
+        let width = self.size.width as usize;
+        display.draw_iter(frame.pixels.iter().enumerate().map(|(i, &raw)| {
+            let point = Point::new((i % width) as i32, (i / width) as i32);
+            Pixel(point, Rgb565::from(RawU16::new(raw)))
+        }))
+    }
+}
+
+fn invalid(error: RecordingError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid recording: {error:?}"),
+    )
+}
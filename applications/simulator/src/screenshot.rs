@@ -0,0 +1,51 @@
+//! Screenshots of the simulated panel
+//!
+//! F12 saves the current frame to a timestamped PNG in
+//! [`SimulatorConfig::screenshot_dir`]. With the `clipboard` feature the
+//! image is also copied to the clipboard, ready to paste in a review thread.
+//!
+//! [`SimulatorConfig::screenshot_dir`]: crate::SimulatorConfig::screenshot_dir
+
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics_simulator::OutputImage;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Path of a new screenshot in `dir`, named after the current time
+pub(crate) fn timestamped_path(dir: &Path) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    dir.join(format!(
+        "screenshot-{}-{:03}.png",
+        now.as_secs(),
+        now.subsec_millis()
+    ))
+}
+
+/// Copy an image to the system clipboard
+#[cfg(feature = "clipboard")]
+pub(crate) fn copy_to_clipboard(
+    image: &OutputImage<Rgb888>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = image.as_image_buffer();
+    let rgba: Vec<u8> = buffer
+        .as_raw()
+        .chunks_exact(3)
+        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+        .collect();
+
+    arboard::Clipboard::new()?.set_image(arboard::ImageData {
+        width: buffer.width() as usize,
+        height: buffer.height() as usize,
+        bytes: rgba.into(),
+    })?;
+    Ok(())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn copy_to_clipboard(
+    _image: &OutputImage<Rgb888>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Local stand-in for the cluster server
+//!
+//! Serves a layout file with the same endpoints as the real server, so a
+//! device or `sim cluster --poll` can be tried without it:
+//! - `GET /layout`: every floor
+//! - `GET /cluster/<id>`: a single floor
+//! - `GET /clusters?ids=f0,f2`: a [`PartialLayout`] with only the floors asked
+//!   for, like `Endpoints::get_clusters` requests
+//!
+//! Each connection gets one response and is closed.
+
+use cluster_core::models::{Layout, PartialLayout};
+use cluster_core::types::ClusterId;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+/// Default TCP port of [`LayoutServer`]
+pub const SERVER_PORT: u16 = 8080;
+
+/// Status and JSON body of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message }).to_string(),
+        }
+    }
+
+    const fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// HTTP server answering requests for a layout
+pub struct LayoutServer {
+    listener: TcpListener,
+    layout: Layout,
+}
+
+impl LayoutServer {
+    /// Listen on `addr`; port 0 picks a free one, see
+    /// [`local_addr`](Self::local_addr)
+    pub fn bind(addr: impl ToSocketAddrs, layout: Layout) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            layout,
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answer requests until the listener fails
+    ///
+    /// A failed connection is logged and doesn't stop the server.
+    pub fn run(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            if let Err(e) = self.handle(stream?) {
+                eprintln!("Failed to answer a request: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers, the response doesn't depend on them
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(target)) => respond(&self.layout, target),
+            (Some(_), Some(_)) => Response::error(405, "only GET is supported"),
+            _ => Response::error(400, "malformed request"),
+        };
+
+        let mut stream = &stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body,
+        )?;
+        stream.flush()
+    }
+}
+
+/// Answer a GET of `target` (path and query) from `layout`
+pub fn respond(layout: &Layout, target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let json = match path {
+        "/layout" => serde_json::to_string(layout),
+        "/clusters" => match partial_layout(layout, query) {
+            Ok(partial) => serde_json::to_string(&partial),
+            Err(response) => return response,
+        },
+        _ => match path.strip_prefix("/cluster/") {
+            Some(id) => match id.parse().ok().and_then(|id| layout.get(id)) {
+                Some(cluster) => serde_json::to_string(cluster),
+                None => return Response::error(404, "unknown cluster"),
+            },
+            None => return Response::error(404, "unknown endpoint"),
+        },
+    };
+    match json {
+        Ok(body) => Response::ok(body),
+        Err(_) => Response::error(500, "failed to serialize the layout"),
+    }
+}
+
+/// Floors of `layout` listed in the `ids` parameter of `query`
+fn partial_layout(layout: &Layout, query: &str) -> Result<PartialLayout, Response> {
+    let ids = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("ids="))
+        .ok_or_else(|| Response::error(400, "missing ids parameter"))?;
+
+    let mut partial = PartialLayout::default();
+    for id in ids.split(',').filter(|id| !id.is_empty()) {
+        let id: ClusterId = id
+            .parse()
+            .map_err(|_| Response::error(400, "invalid cluster id"))?;
+        let cluster = layout
+            .get(id)
+            .ok_or_else(|| Response::error(400, "invalid cluster id"))?;
+        let slot = match id {
+            ClusterId::F0 => &mut partial.f0,
+            ClusterId::F1 => &mut partial.f1,
+            ClusterId::F1b => &mut partial.f1b,
+            ClusterId::F2 => &mut partial.f2,
+            ClusterId::F4 => &mut partial.f4,
+            ClusterId::F6 => &mut partial.f6,
+            // Not displayed, so `layout.get` already rejected it
+            ClusterId::Hidden => continue,
+        };
+        *slot = Some(cluster.clone());
+    }
+    Ok(partial)
+}
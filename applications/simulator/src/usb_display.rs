@@ -0,0 +1,60 @@
+//! Stream the simulator's frames to a device in USB display mode
+//!
+//! With the `usb-display` feature the device shows up as a USB serial port
+//! (`/dev/ttyACM0` on Linux, `COMx` on Windows) and displays the frames
+//! written to it. [`UsbDisplaySender`] is a sample sender: it frames the
+//! window's pixels with `cluster_core::framing` and writes them to the port.
+//! CDC ACM ignores the line settings, so the port is opened as a plain file.
+
+use cluster_core::framing::{KIND_DISPLAY_FRAME, encode, frame_size};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::prelude::*;
+use embedded_graphics_simulator::SimulatorDisplay;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writer of display frames to a device's serial port
+pub struct UsbDisplaySender {
+    port: File,
+    sequence: u8,
+    payload: Vec<u8>,
+    frame: Vec<u8>,
+}
+
+impl UsbDisplaySender {
+    /// Open the serial port of the device
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let port = OpenOptions::new().write(true).open(path)?;
+        Ok(Self {
+            port,
+            sequence: 0,
+            payload: Vec::new(),
+            frame: Vec::new(),
+        })
+    }
+
+    /// Send the pixels of `display`, which must match the panel size
+    pub fn send(&mut self, display: &SimulatorDisplay<Rgb565>) -> io::Result<()> {
+        self.payload.clear();
+        for point in display.bounding_box().points() {
+            let raw = RawU16::from(display.get_pixel(point)).into_inner();
+            self.payload.extend_from_slice(&raw.to_le_bytes());
+        }
+
+        self.frame.resize(frame_size(self.payload.len()), 0);
+        let len = encode(
+            KIND_DISPLAY_FRAME,
+            self.sequence,
+            &self.payload,
+            &mut self.frame,
+        )
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+        self.port.write_all(&self.frame[..len])?;
+        self.port.flush()?;
+
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! Golden-image regression tests, run headless
+//!
+//! Rerun with `UPDATE_GOLDEN=1` after a deliberate change in the output and
+//! review the PNGs in `tests/golden`.
+
+use cluster_core::models::Layout;
+use cluster_core::visualization::draw_cluster_frame;
+use graphics_common::animations;
+use simulator::{Simulator, SimulatorConfig};
+
+fn headless() -> Simulator {
+    let config = SimulatorConfig {
+        headless: true,
+        ..Default::default()
+    };
+    Simulator::new(config).unwrap()
+}
+
+#[test]
+fn test_fortytwo_animation() {
+    let mut sim = headless();
+    sim.run_frames(60, animations::fortytwo::draw_animation_frame)
+        .unwrap();
+    sim.assert_frame_matches("tests/golden/fortytwo-60.png");
+}
+
+#[test]
+fn test_cluster_frame() {
+    let json = include_str!("../assets/layout.json");
+    let layout: Layout = serde_json::from_str(json).unwrap();
+
+    let mut sim = headless();
+    sim.run_frames(1, |display, frame| {
+        draw_cluster_frame(display, &layout, frame)
+    })
+    .unwrap();
+    sim.assert_frame_matches("tests/golden/cluster-layout.png");
+}
@@ -0,0 +1,60 @@
+//! Layout server, over a real socket and through `respond`
+
+use cluster_core::models::{Cluster, Layout, PartialLayout};
+use cluster_core::types::ClusterId;
+use simulator::server::{LayoutServer, respond};
+
+fn layout() -> Layout {
+    serde_json::from_str(include_str!("../assets/layout.json")).unwrap()
+}
+
+#[test]
+fn test_serves_partial_layout() {
+    let server = LayoutServer::bind("127.0.0.1:0", layout()).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+
+    let body = ureq::get(&format!("http://{addr}/clusters?ids=f0,f2"))
+        .call()
+        .unwrap()
+        .into_string()
+        .unwrap();
+    let partial: PartialLayout = serde_json::from_str(&body).unwrap();
+
+    let expected = layout();
+    assert_eq!(partial.get(ClusterId::F0).unwrap().name, expected.f0.name);
+    assert_eq!(partial.get(ClusterId::F2).unwrap().name, expected.f2.name);
+    for id in [ClusterId::F1, ClusterId::F1b, ClusterId::F4, ClusterId::F6] {
+        assert!(partial.get(id).is_none(), "{id} wasn't asked for");
+    }
+}
+
+#[test]
+fn test_serves_full_layout_and_single_cluster() {
+    let layout = layout();
+
+    let response = respond(&layout, "/layout");
+    assert_eq!(response.status, 200);
+    let full: Layout = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(full.f6.seats.len(), layout.f6.seats.len());
+
+    let response = respond(&layout, "/cluster/f1b");
+    assert_eq!(response.status, 200);
+    let cluster: Cluster = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(cluster.name, layout.f1b.name);
+}
+
+#[test]
+fn test_rejects_bad_requests() {
+    let layout = layout();
+
+    assert_eq!(respond(&layout, "/clusters?ids=f0,f9").status, 400);
+    assert_eq!(respond(&layout, "/clusters?ids=hidden").status, 400);
+    assert_eq!(respond(&layout, "/clusters").status, 400);
+    assert_eq!(respond(&layout, "/cluster/f9").status, 404);
+    assert_eq!(respond(&layout, "/seats").status, 404);
+
+    let empty = respond(&layout, "/clusters?ids=");
+    assert_eq!(empty.status, 200);
+    assert_eq!(empty.body, "{}");
+}
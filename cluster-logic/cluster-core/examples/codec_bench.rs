@@ -0,0 +1,127 @@
+//! Compression ratio and encoding time of the frame codecs
+//!
+//! Encodes typical 128x128 frames with each codec and prints the size
+//! against the raw frame and the time per frame. Host timings only give the
+//! relative cost: scale them by about 20 for the RP2350.
+//!
+//! ```text
+//! cargo run --release -p cluster-core --example codec_bench --features codec
+//! ```
+
+use cluster_core::codec::Codec;
+use cluster_core::models::Layout;
+use cluster_core::types::{Kind, Status};
+use cluster_core::visualization::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use cluster_core::visualization::{BackgroundSurface, ClusterRenderer};
+use cluster_core::{cluster, layout, seats};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::prelude::*;
+use graphics_common::animations::{fortytwo, stars};
+use std::time::Instant;
+
+const PIXELS: usize = (DISPLAY_WIDTH * DISPLAY_HEIGHT) as usize;
+
+/// Encodings per frame and codec, timed together
+const ROUNDS: u32 = 50;
+
+fn main() {
+    let frames = [
+        ("cluster view", render(cluster_view)),
+        (
+            "forty-two",
+            render(|surface| fortytwo::draw_animation_frame(surface, 30)),
+        ),
+        (
+            "stars",
+            render(|surface| stars::draw_animation_frame(surface, 30)),
+        ),
+        ("vertical gradient", gradient(|_, y| y)),
+        ("horizontal gradient", gradient(|x, _| x)),
+    ];
+
+    println!(
+        "{:<20} {:>8} {:>8} {:>10} {:>8} {:>10}",
+        "frame", "raw", "rle", "rle time", "heatshr.", "heat. time"
+    );
+    for (name, pixels) in &frames {
+        let (rle, rle_time) = bench(Codec::Rle, pixels);
+        let (heatshrink, heatshrink_time) = bench(Codec::Heatshrink, pixels);
+        println!(
+            "{name:<20} {:>8} {rle:>8} {rle_time:>8}us {heatshrink:>8} {heatshrink_time:>8}us",
+            PIXELS * 2
+        );
+    }
+}
+
+/// Encoded size and encoding time in microseconds
+fn bench(codec: Codec, pixels: &[u16]) -> (usize, u128) {
+    let mut data = Vec::with_capacity(PIXELS * 3);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        data.clear();
+        codec.encode(pixels, |byte| data.push(byte));
+    }
+    let time = start.elapsed().as_micros() / u128::from(ROUNDS);
+
+    let mut decoded = vec![0; PIXELS];
+    codec.decode(&data, &mut decoded).unwrap();
+    assert_eq!(decoded, pixels, "{codec:?} round trip");
+    (data.len(), time)
+}
+
+/// Pixels drawn by `draw`, row-major
+fn render(
+    draw: impl FnOnce(&mut BackgroundSurface) -> Result<(), core::convert::Infallible>,
+) -> Vec<u16> {
+    let mut surface = Box::new(BackgroundSurface::new());
+    let Ok(()) = draw(&mut surface);
+    (0..PIXELS as i32)
+        .map(|i| {
+            let point = Point::new(i % DISPLAY_WIDTH as i32, i / DISPLAY_WIDTH as i32);
+            RawU16::from(surface.pixel(point).unwrap()).into_inner()
+        })
+        .collect()
+}
+
+/// Gradient from black to white along `axis(x, y)`
+fn gradient(axis: fn(u32, u32) -> u32) -> Vec<u16> {
+    (0..PIXELS as u32)
+        .map(|i| {
+            let level = axis(i % DISPLAY_WIDTH, i / DISPLAY_WIDTH) * 32 / DISPLAY_WIDTH;
+            let color = Rgb565::new(level as u8, (level * 2) as u8, level as u8);
+            RawU16::from(color).into_inner()
+        })
+        .collect()
+}
+
+/// A cluster with a few rows of seats in every status
+fn cluster_view(surface: &mut BackgroundSurface) -> Result<(), core::convert::Infallible> {
+    let statuses = [Status::Free, Status::Taken, Status::Free, Status::Broken];
+    let mut layout: Box<Layout> = Box::new(layout! {
+        f0: cluster! {
+            message: "Welcome",
+            name: "F0",
+            attributes: [],
+            seats: [],
+            zones: []
+        }
+    });
+    for row in 0..6 {
+        let seats = seats! {
+            pattern: "f0r1s{}", 1..=8;
+            kind: Kind::Mac;
+            status: [Status::Free, Status::Taken];
+            positions: (0, 0), (4, 0), (8, 0), (12, 0), (16, 0), (20, 0), (24, 0), (28, 0)
+        };
+        layout
+            .f0
+            .seats
+            .extend(seats.into_iter().enumerate().map(|(i, mut seat)| {
+                seat.y = row * 6;
+                seat.status = statuses[(row + i) % statuses.len()];
+                seat
+            }));
+    }
+    ClusterRenderer::new().render_frame(surface, &layout, 0)
+}
@@ -0,0 +1,423 @@
+//! Content-addressed cache of downloaded assets
+//!
+//! The server can push images (e.g. event banners) too big to download on
+//! every boot. Downloaded assets are kept in a region of flash split into
+//! fixed-size slots, one asset per slot, and looked up by [`AssetHash`], the
+//! hash of their content: a cached copy can't go stale, and a download can be
+//! checked against the hash the server announced. When every slot is taken,
+//! the least recently used asset is evicted.
+//!
+//! A slot holds a header followed by the asset:
+//!
+//! ```text
+//! [magic: u32 LE][length: u32 LE][hash: u64 LE][asset]
+//! ```
+//!
+//! The header is written last, so a torn write leaves an erased header and a
+//! free slot. The index is rebuilt from the headers by [`AssetCache::mount`],
+//! and assets are checked against their hash when read. Recency isn't
+//! persisted: after a reboot, slots are evicted in slot order until used.
+
+use core::fmt;
+
+/// Slot magic ("AST1")
+pub const ASSET_MAGIC: u32 = 0x4153_5431;
+
+/// Size of the slot header in bytes
+pub const HEADER_SIZE: usize = 16;
+
+/// Hash identifying an asset by its content (64-bit FNV-1a)
+///
+/// Written as 16 lowercase hex digits in URLs and ETags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssetHash(pub u64);
+
+impl AssetHash {
+    /// Hash of an asset's content
+    pub fn of(data: &[u8]) -> Self {
+        Self(data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+        }))
+    }
+
+    /// Parse 16 hex digits
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 16 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        u64::from_str_radix(hex, 16).ok().map(Self)
+    }
+
+    /// Parse an HTTP ETag holding the hash, e.g. `"0123456789abcdef"`
+    ///
+    /// Weak ETags (`W/"..."`) are accepted: the hash identifies the content
+    /// either way.
+    pub fn from_etag(etag: &str) -> Option<Self> {
+        let etag = etag.trim();
+        let etag = etag.strip_prefix("W/").unwrap_or(etag);
+        Self::from_hex(etag.strip_prefix('"')?.strip_suffix('"')?)
+    }
+}
+
+impl fmt::Display for AssetHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Flash region holding the cache
+///
+/// Offsets are relative to the start of the region. Like NOR flash, a write
+/// can only clear bits, so slots are erased before being written.
+pub trait AssetStorage {
+    type Error;
+
+    /// Size of an erase unit, slots are made of whole units
+    const ERASE_SIZE: u32;
+
+    /// Read `buffer.len()` bytes at `offset`
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Erase `from..to`, both multiples of [`Self::ERASE_SIZE`]
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error>;
+
+    /// Write `data` at `offset`, in an erased area
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Errors that can occur while using the asset cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetError<E> {
+    /// The storage failed
+    Storage(E),
+    /// The asset doesn't fit in a slot
+    TooLarge,
+    /// The buffer is too small for the asset
+    BufferTooSmall,
+    /// No asset with this hash is cached
+    NotFound,
+    /// The cached asset doesn't match its hash, it was dropped
+    Corrupted,
+}
+
+/// Asset stored in a slot
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    hash: AssetHash,
+    len: u32,
+    /// Value of the use counter when the asset was last used
+    last_used: u32,
+}
+
+/// Asset cache over `SLOTS` slots of an [`AssetStorage`]
+pub struct AssetCache<S, const SLOTS: usize> {
+    storage: S,
+    slot_size: u32,
+    slots: [Option<Entry>; SLOTS],
+    /// Use counter, ordering the uses of every asset
+    uses: u32,
+}
+
+impl<S: AssetStorage, const SLOTS: usize> AssetCache<S, SLOTS> {
+    /// Open the cache in `storage`, made of `SLOTS` slots of `slot_size`
+    /// bytes, and index the assets already stored
+    ///
+    /// # Panics
+    /// If `slot_size` isn't a non-zero multiple of the erase size.
+    pub fn mount(mut storage: S, slot_size: u32) -> Result<Self, AssetError<S::Error>> {
+        assert!(
+            slot_size > HEADER_SIZE as u32 && slot_size.is_multiple_of(S::ERASE_SIZE),
+            "asset slots must be made of whole erase units"
+        );
+
+        let mut slots = [None; SLOTS];
+        for (index, slot) in slots.iter_mut().enumerate() {
+            let mut header = [0; HEADER_SIZE];
+            storage
+                .read(index as u32 * slot_size, &mut header)
+                .map_err(AssetError::Storage)?;
+            *slot = Self::decode_header(&header, slot_size);
+        }
+
+        Ok(Self {
+            storage,
+            slot_size,
+            slots,
+            uses: 0,
+        })
+    }
+
+    /// Largest asset a slot can hold
+    pub const fn max_asset_size(&self) -> usize {
+        self.slot_size as usize - HEADER_SIZE
+    }
+
+    /// Check if an asset is cached
+    pub fn contains(&self, hash: AssetHash) -> bool {
+        self.find(hash).is_some()
+    }
+
+    /// Number of cached assets
+    pub fn len(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    /// Check if the cache holds no asset
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read a cached asset into `buffer`
+    pub fn get<'b>(
+        &mut self,
+        hash: AssetHash,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b [u8], AssetError<S::Error>> {
+        let index = self.find(hash).ok_or(AssetError::NotFound)?;
+        let len = self.slots[index].map_or(0, |entry| entry.len as usize);
+        let data = buffer.get_mut(..len).ok_or(AssetError::BufferTooSmall)?;
+        self.storage
+            .read(self.slot_offset(index) + HEADER_SIZE as u32, data)
+            .map_err(AssetError::Storage)?;
+
+        if AssetHash::of(data) != hash {
+            // Left for the next insertion to erase
+            self.slots[index] = None;
+            return Err(AssetError::Corrupted);
+        }
+
+        self.touch(index);
+        Ok(data)
+    }
+
+    /// Store an asset, evicting the least recently used one if needed
+    ///
+    /// Returns the hash to get it back with. Storing an asset already cached
+    /// only marks it as used.
+    pub fn insert(&mut self, data: &[u8]) -> Result<AssetHash, AssetError<S::Error>> {
+        if data.len() > self.max_asset_size() {
+            return Err(AssetError::TooLarge);
+        }
+
+        let hash = AssetHash::of(data);
+        if let Some(index) = self.find(hash) {
+            self.touch(index);
+            return Ok(hash);
+        }
+
+        // A free slot, or the least recently used
+        let index = self
+            .slots
+            .iter()
+            .position(Option::is_none)
+            .or_else(|| {
+                (0..SLOTS).min_by_key(|&index| self.slots[index].map_or(0, |e| e.last_used))
+            })
+            .ok_or(AssetError::TooLarge)?;
+
+        // Forget the slot first: if writing fails it holds nothing usable
+        self.slots[index] = None;
+        let offset = self.slot_offset(index);
+        self.storage
+            .erase(offset, offset + self.slot_size)
+            .map_err(AssetError::Storage)?;
+        self.storage
+            .write(offset + HEADER_SIZE as u32, data)
+            .map_err(AssetError::Storage)?;
+
+        let mut header = [0; HEADER_SIZE];
+        header[0..4].copy_from_slice(&ASSET_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        header[8..16].copy_from_slice(&hash.0.to_le_bytes());
+        self.storage
+            .write(offset, &header)
+            .map_err(AssetError::Storage)?;
+
+        self.slots[index] = Some(Entry {
+            hash,
+            len: data.len() as u32,
+            last_used: 0,
+        });
+        self.touch(index);
+        Ok(hash)
+    }
+
+    /// Give the storage back
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    fn find(&self, hash: AssetHash) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.is_some_and(|entry| entry.hash == hash))
+    }
+
+    fn touch(&mut self, index: usize) {
+        self.uses = self.uses.wrapping_add(1);
+        if let Some(entry) = &mut self.slots[index] {
+            entry.last_used = self.uses;
+        }
+    }
+
+    const fn slot_offset(&self, index: usize) -> u32 {
+        index as u32 * self.slot_size
+    }
+
+    fn decode_header(header: &[u8; HEADER_SIZE], slot_size: u32) -> Option<Entry> {
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+
+        let len = read_u32(4);
+        if read_u32(0) != ASSET_MAGIC || len > slot_size - HEADER_SIZE as u32 {
+            return None;
+        }
+
+        let mut hash = [0; 8];
+        hash.copy_from_slice(&header[8..16]);
+        Some(Entry {
+            hash: AssetHash(u64::from_le_bytes(hash)),
+            len,
+            last_used: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERASE: usize = 64;
+    const SLOTS: usize = 3;
+
+    /// NOR-like flash in RAM: writes can only clear bits
+    struct RamFlash {
+        data: [u8; ERASE * 2 * SLOTS],
+    }
+
+    impl RamFlash {
+        fn new() -> Self {
+            Self {
+                data: [0xFF; ERASE * 2 * SLOTS],
+            }
+        }
+    }
+
+    impl AssetStorage for RamFlash {
+        type Error = ();
+        const ERASE_SIZE: u32 = ERASE as u32;
+
+        fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), ()> {
+            let offset = offset as usize;
+            buffer.copy_from_slice(&self.data[offset..offset + buffer.len()]);
+            Ok(())
+        }
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), ()> {
+            assert!(from.is_multiple_of(Self::ERASE_SIZE) && to.is_multiple_of(Self::ERASE_SIZE));
+            self.data[from as usize..to as usize].fill(0xFF);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), ()> {
+            let offset = offset as usize;
+            for (dst, src) in self.data[offset..offset + data.len()].iter_mut().zip(data) {
+                *dst &= src;
+            }
+            Ok(())
+        }
+    }
+
+    fn cache(flash: RamFlash) -> AssetCache<RamFlash, SLOTS> {
+        AssetCache::mount(flash, 2 * ERASE as u32).unwrap()
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut cache = cache(RamFlash::new());
+        assert!(cache.is_empty());
+
+        let hash = cache.insert(b"banner").unwrap();
+        assert_eq!(hash, AssetHash::of(b"banner"));
+        assert_eq!(cache.insert(b"banner"), Ok(hash));
+        assert_eq!(cache.len(), 1);
+
+        let mut buffer = [0; 16];
+        assert_eq!(cache.get(hash, &mut buffer), Ok(&b"banner"[..]));
+        assert_eq!(
+            cache.get(AssetHash::of(b"other"), &mut buffer),
+            Err(AssetError::NotFound)
+        );
+        assert_eq!(
+            cache.get(hash, &mut buffer[..3]),
+            Err(AssetError::BufferTooSmall)
+        );
+        assert_eq!(
+            cache.insert(&[0; 2 * ERASE - HEADER_SIZE + 1]),
+            Err(AssetError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = cache(RamFlash::new());
+        let a = cache.insert(b"a").unwrap();
+        let b = cache.insert(b"b").unwrap();
+        let c = cache.insert(b"c").unwrap();
+
+        let mut buffer = [0; 16];
+        cache.get(a, &mut buffer).unwrap();
+        let d = cache.insert(b"d").unwrap();
+
+        assert!(cache.contains(a) && cache.contains(c) && cache.contains(d));
+        assert!(!cache.contains(b));
+        assert_eq!(cache.get(d, &mut buffer), Ok(&b"d"[..]));
+    }
+
+    #[test]
+    fn test_mount_finds_stored_assets() {
+        let mut cache = cache(RamFlash::new());
+        let hash = cache.insert(b"kept across reboots").unwrap();
+
+        let mut cache = self::cache(cache.into_storage());
+        let mut buffer = [0; 32];
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.get(hash, &mut buffer),
+            Ok(&b"kept across reboots"[..])
+        );
+    }
+
+    #[test]
+    fn test_corrupted_asset_is_dropped() {
+        let mut cache = cache(RamFlash::new());
+        let hash = cache.insert(b"banner").unwrap();
+
+        let mut flash = cache.into_storage();
+        flash.data[HEADER_SIZE] = 0;
+        let mut cache = self::cache(flash);
+
+        let mut buffer = [0; 16];
+        assert_eq!(cache.get(hash, &mut buffer), Err(AssetError::Corrupted));
+        assert!(!cache.contains(hash));
+    }
+
+    #[test]
+    fn test_hash_from_etag() {
+        let hash = AssetHash(0x0123_4567_89ab_cdef);
+        assert_eq!(AssetHash::from_etag("\"0123456789abcdef\""), Some(hash));
+        assert_eq!(AssetHash::from_etag("W/\"0123456789abcdef\""), Some(hash));
+        assert_eq!(AssetHash::from_etag("0123456789abcdef"), None);
+        assert_eq!(AssetHash::from_etag("\"1234\""), None);
+
+        let mut hex = heapless::String::<16>::new();
+        core::fmt::write(&mut hex, format_args!("{hash}")).unwrap();
+        assert_eq!(hex.as_str(), "0123456789abcdef");
+    }
+}
@@ -0,0 +1,205 @@
+//! Seat reservations from an external booking system
+//!
+//! Campuses with a booking tool publish [`Bookings`]: the seats reserved over
+//! a time range. A seat booked right now but not taken is shown in its own
+//! color, so people can tell it will be claimed shortly:
+//!
+//! - [`Bookings::booked_seats`] resolves the bookings active at a time to the
+//!   seat indices of a cluster;
+//! - [`SeatState::merge`] combines a booking with the live status, the
+//!   booking winning unless someone is sitting there;
+//! - the renderer draws booked seats with `visual::SEAT_BOOKED`, see
+//!   `ClusterRenderer::set_booked_seats`.
+//!
+//! The panel has no wall clock, so the response carries the server time and
+//! the caller advances it with its monotonic timer, see [`Bookings::now`].
+
+use crate::constants::MAX_SEATS_PER_CLUSTER;
+use crate::models::Cluster;
+use crate::types::{SeatId, Status};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+pub type BookingVec = std::vec::Vec<Booking>;
+#[cfg(not(feature = "std"))]
+pub type BookingVec = heapless::Vec<Booking, { crate::constants::MAX_BOOKINGS }>;
+
+/// Reservation of a seat over a time range
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Booking {
+    pub seat_id: SeatId,
+    /// Start of the booking, in seconds since the Unix epoch
+    pub start: u64,
+    /// End of the booking (exclusive), in seconds since the Unix epoch
+    pub end: u64,
+}
+
+impl Booking {
+    /// Check if the booking covers `now`
+    pub const fn is_active(&self, now: u64) -> bool {
+        self.start <= now && now < self.end
+    }
+}
+
+/// Bookings returned by the booking endpoint
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Bookings {
+    /// Server time when the response was produced, in seconds since the
+    /// Unix epoch
+    pub server_time: u64,
+    pub bookings: BookingVec,
+}
+
+impl Bookings {
+    /// Current time, `elapsed_secs` after the response was received
+    pub const fn now(&self, elapsed_secs: u64) -> u64 {
+        self.server_time.saturating_add(elapsed_secs)
+    }
+
+    /// Check if a seat has a booking covering `now`
+    pub fn is_booked(&self, seat_id: &str, now: u64) -> bool {
+        self.bookings
+            .iter()
+            .any(|booking| booking.seat_id.as_str() == seat_id && booking.is_active(now))
+    }
+
+    /// Seats of `cluster` with a booking covering `now`
+    pub fn booked_seats(&self, cluster: &Cluster, now: u64) -> BookedSeats {
+        let mut booked = BookedSeats::NONE;
+        for (index, seat) in cluster.seats.iter().enumerate() {
+            if self.is_booked(&seat.id, now) {
+                booked.insert(index);
+            }
+        }
+        booked
+    }
+}
+
+/// Set of booked seats of a cluster, by index in `Cluster::seats`
+///
+/// Indices past [`MAX_SEATS_PER_CLUSTER`] are never booked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookedSeats {
+    bits: [u32; MAX_SEATS_PER_CLUSTER.div_ceil(32)],
+}
+
+impl BookedSeats {
+    /// No seat booked
+    pub const NONE: Self = Self {
+        bits: [0; MAX_SEATS_PER_CLUSTER.div_ceil(32)],
+    };
+
+    /// Mark the seat at `index` as booked
+    pub const fn insert(&mut self, index: usize) {
+        if index < MAX_SEATS_PER_CLUSTER {
+            self.bits[index / 32] |= 1 << (index % 32);
+        }
+    }
+
+    /// Check if the seat at `index` is booked
+    pub const fn contains(&self, index: usize) -> bool {
+        index < MAX_SEATS_PER_CLUSTER && self.bits[index / 32] & (1 << (index % 32)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+}
+
+impl Default for BookedSeats {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// What a seat shows once bookings are merged in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeatState {
+    /// Live status reported by the cluster
+    Live(Status),
+    /// Booked and nobody is sitting there
+    Booked,
+}
+
+impl SeatState {
+    /// Merge a booking into the live status
+    ///
+    /// A booking takes priority over the live status, unless the seat is
+    /// taken: then the booker (or someone else) is already there.
+    pub const fn merge(status: Status, booked: bool) -> Self {
+        match status {
+            Status::Taken => Self::Live(status),
+            _ if booked => Self::Booked,
+            _ => Self::Live(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOKINGS: &str = r#"{
+        "server_time": 1000,
+        "bookings": [
+            {"seat_id": "f0r1s1", "start": 900, "end": 1100},
+            {"seat_id": "f0r1s2", "start": 1100, "end": 1200},
+            {"seat_id": "f0r1s3", "start": 0, "end": 2000}
+        ]
+    }"#;
+
+    const CLUSTER: &str = r#"{
+        "message": "", "attributes": [], "name": "f0", "zones": [],
+        "seats": [
+            {"id": "f0r1s1", "kind": "mac", "status": "free", "x": 0, "y": 0},
+            {"id": "f0r1s2", "kind": "mac", "status": "free", "x": 1, "y": 0},
+            {"id": "f0r1s3", "kind": "mac", "status": "taken", "x": 2, "y": 0},
+            {"id": "f0r1s4", "kind": "mac", "status": "free", "x": 3, "y": 0}
+        ]
+    }"#;
+
+    #[test]
+    fn test_booked_seats_follow_time() {
+        let bookings: Bookings = serde_json::from_str(BOOKINGS).unwrap();
+        let cluster: Cluster = serde_json::from_str(CLUSTER).unwrap();
+
+        let booked = bookings.booked_seats(&cluster, bookings.now(0));
+        assert!(booked.contains(0));
+        assert!(!booked.contains(1));
+        assert!(booked.contains(2));
+        assert!(!booked.contains(3));
+
+        // The first booking ended, the second started
+        let booked = bookings.booked_seats(&cluster, bookings.now(150));
+        assert!(!booked.contains(0));
+        assert!(booked.contains(1));
+
+        assert!(bookings.booked_seats(&cluster, 5000).is_empty());
+    }
+
+    #[test]
+    fn test_merge_priority() {
+        assert_eq!(SeatState::merge(Status::Free, true), SeatState::Booked);
+        assert_eq!(SeatState::merge(Status::Broken, true), SeatState::Booked);
+        assert_eq!(
+            SeatState::merge(Status::Taken, true),
+            SeatState::Live(Status::Taken)
+        );
+        assert_eq!(
+            SeatState::merge(Status::Free, false),
+            SeatState::Live(Status::Free)
+        );
+    }
+
+    #[test]
+    fn test_booked_seats_bounds() {
+        let mut booked = BookedSeats::NONE;
+        booked.insert(MAX_SEATS_PER_CLUSTER - 1);
+        booked.insert(MAX_SEATS_PER_CLUSTER);
+        assert!(booked.contains(MAX_SEATS_PER_CLUSTER - 1));
+        assert!(!booked.contains(MAX_SEATS_PER_CLUSTER));
+        assert!(!booked.is_empty());
+    }
+}
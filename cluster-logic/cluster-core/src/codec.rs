@@ -0,0 +1,475 @@
+//! Compression of RGB565 frames sent or stored off the panel
+//!
+//! Two codecs, picked per stream with [`Codec`]:
+//!
+//! - [`Codec::Rle`]: runs `[count: u8][pixel: u16 LE]` of identical pixels.
+//!   Nearly free to encode and great on flat backgrounds, but a gradient or
+//!   an antialiased edge costs 3 bytes per pixel, more than the raw frame.
+//! - [`Codec::Heatshrink`]: LZSS over the little-endian pixel bytes, in the
+//!   bitstream of [heatshrink] with a window of [`WINDOW_BITS`] and a
+//!   lookahead of [`LOOKAHEAD_BITS`] (`heatshrink -d -w 9 -l 8` decodes
+//!   it). The window holds two 128-pixel rows, so a row repeating one
+//!   above, as in a vertical gradient, is a single back-reference, and a
+//!   long match keeps flat areas close to RLE.
+//!   Encoding searches the whole window at each byte and costs an order of
+//!   magnitude more CPU than RLE.
+//!
+//! `cargo run --release -p cluster-core --example codec_bench --features
+//! codec` compares both on typical cluster frames.
+//!
+//! Every output goes through an `emit` callback, so frames are encoded
+//! straight into a ring, a socket buffer or a `Vec`, without a scratch
+//! buffer the size of the panel.
+//!
+//! ```
+//! # use cluster_core::codec::Codec;
+//! let pixels: [u16; 64] = core::array::from_fn(|i| (i / 8) as u16 * 0x0841);
+//! let mut data = [0; 64 * 3];
+//! let mut len = 0;
+//! Codec::Heatshrink.encode(&pixels, |byte| {
+//!     data[len] = byte;
+//!     len += 1;
+//! });
+//!
+//! let mut decoded = [0; 64];
+//! Codec::Heatshrink.decode(&data[..len], &mut decoded).unwrap();
+//! assert_eq!(decoded, pixels);
+//! ```
+//!
+//! [heatshrink]: https://github.com/atomicobject/heatshrink
+
+use core::fmt;
+
+/// Bits of a back-reference offset: matches reach 512 bytes back
+pub const WINDOW_BITS: u32 = 9;
+
+/// Bits of a back-reference length: matches are at most 256 bytes long
+pub const LOOKAHEAD_BITS: u32 = 8;
+
+const WINDOW: usize = 1 << WINDOW_BITS;
+const LOOKAHEAD: usize = 1 << LOOKAHEAD_BITS;
+
+/// Shortest match worth a back-reference: a back-reference takes 18 bits,
+/// a literal 9
+const MIN_MATCH: usize = 3;
+
+/// Size of a run: count and pixel
+pub(crate) const RUN_SIZE: usize = 3;
+
+/// Compression of a frame of RGB565 pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// Run-length encoding
+    #[default]
+    Rle,
+    /// LZSS, heatshrink bitstream
+    Heatshrink,
+}
+
+/// Errors that can occur while decoding a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    /// The data ends in the middle of a run
+    Truncated,
+    /// The data doesn't cover the pixels exactly
+    SizeMismatch,
+    /// A back-reference points before the start of the frame
+    Corrupt,
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "data ends in the middle of a run"),
+            Self::SizeMismatch => write!(f, "data doesn't match the frame size"),
+            Self::Corrupt => write!(f, "back-reference before the start of the frame"),
+        }
+    }
+}
+
+impl Codec {
+    /// Encode `pixels`, handing each byte to `emit`
+    pub fn encode(self, pixels: &[u16], mut emit: impl FnMut(u8)) {
+        match self {
+            Self::Rle => {
+                for (count, pixel) in runs(pixels) {
+                    let [low, high] = pixel.to_le_bytes();
+                    emit(count);
+                    emit(low);
+                    emit(high);
+                }
+            }
+            Self::Heatshrink => compress(&Pixels(pixels), emit),
+        }
+    }
+
+    /// Upper bound of the encoding of `pixels` pixels, for either codec
+    ///
+    /// A run per pixel for [`Codec::Rle`]; heatshrink stays below, at 9
+    /// bits per byte and a padding byte.
+    pub const fn max_encoded_size(pixels: usize) -> usize {
+        pixels * RUN_SIZE
+    }
+
+    /// Size of the encoding of `pixels`
+    ///
+    /// For [`Codec::Heatshrink`] this costs as much as encoding.
+    pub fn encoded_size(self, pixels: &[u16]) -> usize {
+        match self {
+            Self::Rle => runs(pixels).count() * RUN_SIZE,
+            Self::Heatshrink => {
+                let mut size = 0;
+                compress(&Pixels(pixels), |_| size += 1);
+                size
+            }
+        }
+    }
+
+    /// Decode a frame into `pixels`, which it must fill exactly
+    pub fn decode(self, data: &[u8], pixels: &mut [u16]) -> Result<(), CodecError> {
+        let filled = match self {
+            Self::Rle => decode_runs(data, pixels)?,
+            Self::Heatshrink => decompress(data, &mut PixelsMut(pixels))? / 2,
+        };
+        match filled == pixels.len() {
+            true => Ok(()),
+            false => Err(CodecError::SizeMismatch),
+        }
+    }
+}
+
+/// Runs of identical pixels, at most 255 long
+pub(crate) fn runs(pixels: &[u16]) -> impl Iterator<Item = (u8, u16)> + '_ {
+    let mut rest = pixels;
+    core::iter::from_fn(move || {
+        let &first = rest.first()?;
+        let count = rest
+            .iter()
+            .take(u8::MAX as usize)
+            .take_while(|&&pixel| pixel == first)
+            .count();
+        rest = &rest[count..];
+        Some((count as u8, first))
+    })
+}
+
+/// Decode runs into `pixels`, returning the number of pixels filled
+fn decode_runs(data: &[u8], pixels: &mut [u16]) -> Result<usize, CodecError> {
+    let (runs, rest) = data.as_chunks::<RUN_SIZE>();
+    if !rest.is_empty() {
+        return Err(CodecError::Truncated);
+    }
+
+    let mut filled = 0;
+    for run in runs {
+        let end = filled + run[0] as usize;
+        pixels
+            .get_mut(filled..end)
+            .ok_or(CodecError::SizeMismatch)?
+            .fill(u16::from_le_bytes([run[1], run[2]]));
+        filled = end;
+    }
+    Ok(filled)
+}
+
+/// Bytes compressed or decompressed in place
+trait Bytes {
+    fn len(&self) -> usize;
+    fn get(&self, index: usize) -> u8;
+}
+
+trait BytesMut: Bytes {
+    fn set(&mut self, index: usize, byte: u8);
+}
+
+impl Bytes for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        self[index]
+    }
+}
+
+impl BytesMut for [u8] {
+    fn set(&mut self, index: usize, byte: u8) {
+        self[index] = byte;
+    }
+}
+
+/// Pixels seen as their little-endian bytes
+struct Pixels<'a>(&'a [u16]);
+
+struct PixelsMut<'a>(&'a mut [u16]);
+
+impl Bytes for Pixels<'_> {
+    fn len(&self) -> usize {
+        self.0.len() * 2
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        self.0[index / 2].to_le_bytes()[index % 2]
+    }
+}
+
+impl Bytes for PixelsMut<'_> {
+    fn len(&self) -> usize {
+        self.0.len() * 2
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        self.0[index / 2].to_le_bytes()[index % 2]
+    }
+}
+
+impl BytesMut for PixelsMut<'_> {
+    fn set(&mut self, index: usize, byte: u8) {
+        let mut bytes = self.0[index / 2].to_le_bytes();
+        bytes[index % 2] = byte;
+        self.0[index / 2] = u16::from_le_bytes(bytes);
+    }
+}
+
+/// Bits written most significant first, the last byte padded with zeros
+struct BitWriter<F> {
+    emit: F,
+    byte: u8,
+    bits: u32,
+}
+
+impl<F: FnMut(u8)> BitWriter<F> {
+    fn push(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            self.byte = self.byte << 1 | (value >> bit & 1) as u8;
+            self.bits += 1;
+            if self.bits == 8 {
+                (self.emit)(self.byte);
+                self.byte = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) {
+        if self.bits > 0 {
+            (self.emit)(self.byte << (8 - self.bits));
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    /// Next `bits` bits, `None` if fewer are left, i.e. the padding
+    fn take(&mut self, bits: u32) -> Option<u32> {
+        if self.data.len() * 8 - self.position < bits as usize {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = value << 1 | bit as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Longest match for the bytes at `position` in the window before it, as
+/// (offset, length), nearest first
+fn longest_match(input: &(impl Bytes + ?Sized), position: usize) -> (usize, usize) {
+    let max_len = LOOKAHEAD.min(input.len() - position);
+    let first = input.get(position);
+    let mut best = (0, 0);
+    for offset in 1..=WINDOW.min(position) {
+        let start = position - offset;
+        if input.get(start) != first {
+            continue;
+        }
+        // Matches may run into the bytes they copy, as runs do
+        let len = (1..max_len)
+            .take_while(|&i| input.get(start + i) == input.get(position + i))
+            .count()
+            + 1;
+        if len > best.1 {
+            best = (offset, len);
+            if len == max_len {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Compress `input`, handing each byte to `emit`
+fn compress(input: &(impl Bytes + ?Sized), emit: impl FnMut(u8)) {
+    let mut writer = BitWriter {
+        emit,
+        byte: 0,
+        bits: 0,
+    };
+    let mut position = 0;
+    while position < input.len() {
+        let (offset, len) = longest_match(input, position);
+        if len >= MIN_MATCH {
+            writer.push(0, 1);
+            writer.push(offset as u32 - 1, WINDOW_BITS);
+            writer.push(len as u32 - 1, LOOKAHEAD_BITS);
+            position += len;
+        } else {
+            writer.push(1, 1);
+            writer.push(input.get(position).into(), 8);
+            position += 1;
+        }
+    }
+    writer.finish();
+}
+
+/// Decompress `data` into `output`, returning the number of bytes written
+fn decompress(data: &[u8], output: &mut (impl BytesMut + ?Sized)) -> Result<usize, CodecError> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut filled = 0;
+    while let Some(tag) = reader.take(1) {
+        if tag == 1 {
+            let Some(byte) = reader.take(8) else { break };
+            if filled == output.len() {
+                return Err(CodecError::SizeMismatch);
+            }
+            output.set(filled, byte as u8);
+            filled += 1;
+        } else {
+            let Some(offset) = reader.take(WINDOW_BITS) else {
+                break;
+            };
+            let Some(len) = reader.take(LOOKAHEAD_BITS) else {
+                break;
+            };
+            let (offset, len) = (offset as usize + 1, len as usize + 1);
+            if offset > filled {
+                return Err(CodecError::Corrupt);
+            }
+            if filled + len > output.len() {
+                return Err(CodecError::SizeMismatch);
+            }
+            for _ in 0..len {
+                output.set(filled, output.get(filled - offset));
+                filled += 1;
+            }
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: usize = 256;
+
+    /// Frames the codecs deal with: flat, a gradient, noise
+    fn frames() -> [[u16; PIXELS]; 3] {
+        let mut seed = 0x1234_5678u32;
+        [
+            [0x001F; PIXELS],
+            // 16 rows of a horizontal gradient
+            core::array::from_fn(|i| (i % 16) as u16 * 0x0841),
+            core::array::from_fn(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u16
+            }),
+        ]
+    }
+
+    fn encode(codec: Codec, pixels: &[u16], data: &mut [u8]) -> usize {
+        let mut len = 0;
+        codec.encode(pixels, |byte| {
+            data[len] = byte;
+            len += 1;
+        });
+        assert_eq!(len, codec.encoded_size(pixels));
+        len
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = [0; PIXELS * 3];
+        for codec in [Codec::Rle, Codec::Heatshrink] {
+            for pixels in frames() {
+                let len = encode(codec, &pixels, &mut data);
+                let mut decoded = [0; PIXELS];
+                codec.decode(&data[..len], &mut decoded).unwrap();
+                assert_eq!(decoded, pixels, "{codec:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_heatshrink_beats_rle_on_gradients() {
+        let [flat, gradient, noise] = frames();
+        let size = |codec: Codec, pixels: &[u16]| codec.encoded_size(pixels);
+
+        assert!(size(Codec::Heatshrink, &flat) < 2 * size(Codec::Rle, &flat));
+        assert_eq!(size(Codec::Rle, &gradient), PIXELS * RUN_SIZE);
+        assert!(size(Codec::Heatshrink, &gradient) < PIXELS / 2);
+        // 9 bits per byte at worst
+        assert!(size(Codec::Heatshrink, &noise) <= (PIXELS * 2 * 9).div_ceil(8));
+        for codec in [Codec::Rle, Codec::Heatshrink] {
+            for pixels in [flat, gradient, noise] {
+                assert!(size(codec, &pixels) <= Codec::max_encoded_size(PIXELS));
+            }
+        }
+        assert!(Codec::Heatshrink.encoded_size(&[0xFFFF]) <= Codec::max_encoded_size(1));
+    }
+
+    #[test]
+    fn test_heatshrink_bitstream() {
+        // Two literals, then a match of 6 bytes 2 back that runs into the
+        // bytes it copies, as heatshrink encodes it
+        let mut data = [0; 8];
+        let mut len = 0;
+        compress(&b"abababab"[..], |byte| {
+            data[len] = byte;
+            len += 1;
+        });
+        // 1 01100001, 1 01100010, 0 000000001 00000101, padding
+        assert_eq!(&data[..len], &[0xB0, 0xD8, 0x80, 0x10, 0x50]);
+
+        let mut out = [0; 8];
+        assert_eq!(decompress(&data[..len], &mut out[..]), Ok(8));
+        assert_eq!(&out, b"abababab");
+    }
+
+    #[test]
+    fn test_bad_data() {
+        let mut pixels = [0; 4];
+        // Back-reference into nothing
+        assert_eq!(
+            Codec::Heatshrink.decode(&[0x00, 0x00, 0x00], &mut pixels),
+            Err(CodecError::Corrupt)
+        );
+
+        let mut data = [0; PIXELS * 3];
+        let [_, gradient, _] = frames();
+        let len = encode(Codec::Heatshrink, &gradient, &mut data);
+        let mut short = [0; PIXELS - 1];
+        assert_eq!(
+            Codec::Heatshrink.decode(&data[..len], &mut short),
+            Err(CodecError::SizeMismatch)
+        );
+        let mut decoded = [0; PIXELS];
+        assert_eq!(
+            Codec::Heatshrink.decode(&data[..len / 2], &mut decoded),
+            Err(CodecError::SizeMismatch)
+        );
+        assert_eq!(
+            Codec::Rle.decode(&[1, 2], &mut decoded),
+            Err(CodecError::Truncated)
+        );
+    }
+}
@@ -5,6 +5,11 @@ pub const MAX_MESSAGE_LENGTH: usize = 128;
 /// Maximum seats per cluster
 pub const MAX_SEATS_PER_CLUSTER: usize = 270;
 pub const MAX_SEAT_ID_LENGTH: usize = 8;
+/// Seat label length in bytes, room for two emojis
+pub const MAX_SEAT_LABEL_LENGTH: usize = 8;
 
 pub const MAX_ATTRIBUTES: usize = 3;
 pub const MAX_ZONES: usize = 4;
+
+/// Maximum bookings in a booking response
+pub const MAX_BOOKINGS: usize = 64;
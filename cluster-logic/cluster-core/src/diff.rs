@@ -0,0 +1,214 @@
+//! Changes between two snapshots of a cluster
+//!
+//! Each poll brings a whole new [`Cluster`]. [`Cluster::diff`] tells what
+//! changed since the previous one, so the panel can animate the seats that
+//! changed status instead of redrawing the floor. Seats are matched by ID
+//! and zones by name; a zone that moved or changed attributes counts as
+//! removed and added, as both places need a redraw.
+//!
+//! The diff refers to seats and zones by index into the snapshots rather
+//! than copying them, in vectors bounded like the snapshots, to stay small
+//! on the stack.
+//!
+//! ```
+//! # use cluster_core::{cluster, seat, types::{Kind, Status}};
+//! let older = cluster! {
+//!     message: "",
+//!     name: "F0",
+//!     attributes: [],
+//!     seats: [seat!("f0r1s1", Kind::Mac, Status::Free, 0, 0)],
+//!     zones: []
+//! };
+//! let mut newer = older.clone();
+//! newer.seats[0].status = Status::Taken;
+//!
+//! let diff = older.diff(&newer);
+//! let change = diff.seats[0];
+//! assert_eq!(&newer.seats[change.index as usize].id, "f0r1s1");
+//! assert_eq!((change.from, change.to), (Some(Status::Free), Status::Taken));
+//! ```
+
+use crate::models::{Cluster, Zone};
+use crate::types::Status;
+
+pub type SeatChangeVec = heapless::Vec<SeatChange, { crate::constants::MAX_SEATS_PER_CLUSTER }>;
+
+pub type SeatIndexVec = heapless::Vec<u16, { crate::constants::MAX_SEATS_PER_CLUSTER }>;
+
+pub type ZoneIndexVec = heapless::Vec<u16, { crate::constants::MAX_ZONES }>;
+
+/// Seat whose status changed, or that is new
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeatChange {
+    /// Index of the seat in the newer cluster
+    pub index: u16,
+    /// Status in the older cluster, `None` for a new seat
+    pub from: Option<Status>,
+    pub to: Status,
+}
+
+/// What changed from one snapshot of a cluster to a newer one
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClusterDiff {
+    /// Seats that changed status or are new, in the newer cluster's order
+    pub seats: SeatChangeVec,
+    /// Indices in the older cluster of the seats that are gone
+    pub removed_seats: SeatIndexVec,
+    /// Indices in the newer cluster of the zones that are new
+    pub added_zones: ZoneIndexVec,
+    /// Indices in the older cluster of the zones that are gone
+    pub removed_zones: ZoneIndexVec,
+    pub message_changed: bool,
+}
+
+impl ClusterDiff {
+    /// Check if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.seats.is_empty()
+            && self.removed_seats.is_empty()
+            && self.added_zones.is_empty()
+            && self.removed_zones.is_empty()
+            && !self.message_changed
+    }
+}
+
+impl Cluster {
+    /// Changes from this snapshot to `newer`
+    pub fn diff(&self, newer: &Cluster) -> ClusterDiff {
+        let mut diff = ClusterDiff {
+            message_changed: self.message != newer.message,
+            ..ClusterDiff::default()
+        };
+
+        // Both vectors are bounded by the same capacities as the snapshots,
+        // so no push fails
+        for (index, seat) in newer.seats.iter().enumerate() {
+            let from = self
+                .find_seat(index, &seat.id)
+                .map(|i| self.seats[i].status);
+            if from != Some(seat.status) {
+                let _ = diff.seats.push(SeatChange {
+                    index: index as u16,
+                    from,
+                    to: seat.status,
+                });
+            }
+        }
+        for (index, seat) in self.seats.iter().enumerate() {
+            if newer.find_seat(index, &seat.id).is_none() {
+                let _ = diff.removed_seats.push(index as u16);
+            }
+        }
+
+        for (index, zone) in newer.zones.iter().enumerate() {
+            if !self.zones.iter().any(|old| same_zone(old, zone)) {
+                let _ = diff.added_zones.push(index as u16);
+            }
+        }
+        for (index, zone) in self.zones.iter().enumerate() {
+            if !newer.zones.iter().any(|new| same_zone(zone, new)) {
+                let _ = diff.removed_zones.push(index as u16);
+            }
+        }
+        diff
+    }
+
+    /// Index of the seat `id`, looked up at `hint` first: polls rarely
+    /// reorder seats
+    pub(crate) fn find_seat(&self, hint: usize, id: &str) -> Option<usize> {
+        match self.seats.get(hint) {
+            Some(seat) if seat.id == id => Some(hint),
+            _ => self.seat_index_by_id(id),
+        }
+    }
+}
+
+fn same_zone(a: &Zone, b: &Zone) -> bool {
+    a.name == b.name && a.x == b.x && a.y == b.y && a.attributes == b.attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Attribute, Kind};
+    use crate::{cluster, seat, zone};
+
+    fn snapshot() -> Cluster {
+        cluster! {
+            message: "Welcome",
+            name: "F0",
+            attributes: [],
+            seats: [
+                seat!("f0r1s1", Kind::Mac, Status::Free, 0, 0),
+                seat!("f0r1s2", Kind::Mac, Status::Taken, 4, 0),
+                seat!("f0r1s3", Kind::Dell, Status::Free, 8, 0)
+            ],
+            zones: [zone!("Z1", [], 0, 0), zone!("Z2", [], 10, 0)]
+        }
+    }
+
+    #[test]
+    fn test_same_snapshot() {
+        assert!(snapshot().diff(&snapshot()).is_empty());
+    }
+
+    #[test]
+    fn test_seat_changes() {
+        let older = snapshot();
+        let mut newer = snapshot();
+        newer.seats[1].status = Status::Free;
+        newer.seats[2].status = Status::Broken;
+        // Moved to the front: matched by ID whatever the order
+        newer.seats.swap(0, 2);
+        newer.seats.remove(2);
+        #[allow(unused_must_use)]
+        {
+            newer
+                .seats
+                .push(seat!("f0r2s1", Kind::Flex, Status::Taken, 0, 6));
+        }
+
+        let diff = older.diff(&newer);
+        assert_eq!(
+            &diff.seats[..],
+            &[
+                SeatChange {
+                    index: 0,
+                    from: Some(Status::Free),
+                    to: Status::Broken,
+                },
+                SeatChange {
+                    index: 1,
+                    from: Some(Status::Taken),
+                    to: Status::Free,
+                },
+                SeatChange {
+                    index: 2,
+                    from: None,
+                    to: Status::Taken,
+                },
+            ]
+        );
+        assert_eq!(&older.seats[diff.removed_seats[0] as usize].id, "f0r1s1");
+        assert_eq!(diff.removed_seats.len(), 1);
+        assert!(diff.added_zones.is_empty() && !diff.message_changed);
+    }
+
+    #[test]
+    fn test_zone_and_message_changes() {
+        let older = snapshot();
+        let mut newer = snapshot();
+        #[allow(unused_must_use)]
+        {
+            newer.message.push_str(", closed at 6pm");
+        }
+        newer.zones[0].x = 2;
+        newer.zones[1] = zone!("Z2", [Attribute::Silent], 10, 0);
+
+        let diff = older.diff(&newer);
+        assert!(diff.message_changed);
+        assert_eq!(&diff.added_zones[..], &[0, 1]);
+        assert_eq!(&diff.removed_zones[..], &[0, 1]);
+        assert!(diff.seats.is_empty() && diff.removed_seats.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+//! Typed publish/subscribe bus between firmware subsystems
+//!
+//! Producers (buttons, network, commands, the scheduler) publish events
+//! without knowing who listens, and each consumer reads every event from its
+//! own [`EventSubscriber`]. The bus is a bounded ring shared by all
+//! subscribers: publishing never waits, and a subscriber that falls more than
+//! `CAP` events behind skips the oldest ones instead of stalling producers.
+//!
+//! ```ignore
+//! static EVENTS: EventBus<CriticalSectionRawMutex, Event, 8, 4> = EventBus::new();
+//!
+//! EVENTS.publish(Event::ButtonPressed);
+//!
+//! let mut events = EVENTS.subscribe().unwrap();
+//! loop {
+//!     match events.next().await { ... }
+//! }
+//! ```
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::pubsub::{PubSubBehavior, PubSubChannel, Subscriber, WaitResult};
+
+/// Bus carrying events of type `E`
+///
+/// Holds up to `CAP` events not yet read by every subscriber, and up to
+/// `SUBS` subscribers at once. Use `CriticalSectionRawMutex` when producers
+/// run in interrupts or on another core, `NoopRawMutex` otherwise.
+pub struct EventBus<M: RawMutex, E: Clone, const CAP: usize, const SUBS: usize> {
+    channel: PubSubChannel<M, E, CAP, SUBS, 0>,
+}
+
+impl<M: RawMutex, E: Clone, const CAP: usize, const SUBS: usize> EventBus<M, E, CAP, SUBS> {
+    /// Create an empty bus, usable in a `static`
+    pub const fn new() -> Self {
+        Self {
+            channel: PubSubChannel::new(),
+        }
+    }
+
+    /// Publish an event to every current subscriber
+    ///
+    /// Never waits: when the bus is full, the oldest event is dropped for
+    /// the subscribers that haven't read it yet.
+    pub fn publish(&self, event: E) {
+        self.channel.publish_immediate(event);
+    }
+
+    /// Subscribe to events published from now on
+    ///
+    /// Returns `None` if `SUBS` subscribers already exist. Dropping a
+    /// subscriber frees its slot.
+    pub fn subscribe(&self) -> Option<EventSubscriber<'_, M, E, CAP, SUBS>> {
+        let subscriber = self.channel.subscriber().ok()?;
+        Some(EventSubscriber {
+            subscriber,
+            missed: 0,
+        })
+    }
+}
+
+impl<M: RawMutex, E: Clone, const CAP: usize, const SUBS: usize> Default
+    for EventBus<M, E, CAP, SUBS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One consumer's view of an [`EventBus`]
+pub struct EventSubscriber<'a, M: RawMutex, E: Clone, const CAP: usize, const SUBS: usize> {
+    subscriber: Subscriber<'a, M, E, CAP, SUBS, 0>,
+    missed: u64,
+}
+
+impl<M: RawMutex, E: Clone, const CAP: usize, const SUBS: usize>
+    EventSubscriber<'_, M, E, CAP, SUBS>
+{
+    /// Wait for the next event
+    pub async fn next(&mut self) -> E {
+        loop {
+            match self.subscriber.next_message().await {
+                WaitResult::Message(event) => return event,
+                WaitResult::Lagged(count) => self.missed += count,
+            }
+        }
+    }
+
+    /// Get the next event if one is pending
+    pub fn try_next(&mut self) -> Option<E> {
+        loop {
+            match self.subscriber.try_next_message()? {
+                WaitResult::Message(event) => return Some(event),
+                WaitResult::Lagged(count) => self.missed += count,
+            }
+        }
+    }
+
+    /// Number of events skipped because this subscriber fell behind
+    pub const fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Event {
+        Button,
+        Network(bool),
+    }
+
+    #[test]
+    fn test_every_subscriber_gets_every_event() {
+        let bus = EventBus::<NoopRawMutex, Event, 4, 2>::new();
+        bus.publish(Event::Button);
+
+        let mut first = bus.subscribe().unwrap();
+        let mut second = bus.subscribe().unwrap();
+        assert!(bus.subscribe().is_none());
+
+        // Events published before subscribing are not seen
+        bus.publish(Event::Network(true));
+        assert_eq!(first.try_next(), Some(Event::Network(true)));
+        assert_eq!(first.try_next(), None);
+        assert_eq!(second.try_next(), Some(Event::Network(true)));
+
+        drop(second);
+        assert!(bus.subscribe().is_some());
+    }
+
+    #[test]
+    fn test_lagging_subscriber_skips_oldest() {
+        let bus = EventBus::<NoopRawMutex, u32, 2, 1>::new();
+        let mut subscriber = bus.subscribe().unwrap();
+        for event in 0..5 {
+            bus.publish(event);
+        }
+
+        assert_eq!(subscriber.try_next(), Some(3));
+        assert_eq!(subscriber.missed(), 3);
+        assert_eq!(subscriber.try_next(), Some(4));
+    }
+
+    #[test]
+    fn test_next_waits_for_event() {
+        let bus = EventBus::<NoopRawMutex, Event, 4, 1>::new();
+        let mut subscriber = bus.subscribe().unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut next = pin!(subscriber.next());
+        assert!(next.as_mut().poll(&mut cx).is_pending());
+        bus.publish(Event::Button);
+        assert_eq!(next.as_mut().poll(&mut cx), Poll::Ready(Event::Button));
+    }
+}
@@ -0,0 +1,357 @@
+//! Framing of data streamed to the device over a serial link
+//!
+//! A serial link such as USB CDC is a plain byte stream: it has no message
+//! boundaries, and the host may start writing halfway through a frame or
+//! drop bytes when it falls behind. Each frame is therefore wrapped with a
+//! sync marker, a header and a CRC, all numbers little-endian:
+//!
+//! ```text
+//! [sync: 0xA5 0x5A][kind: u8][sequence: u8][length: u32][payload][crc: u32]
+//! ```
+//!
+//! The CRC-32 (IEEE) covers the kind, sequence, length and payload. A
+//! [`FrameDecoder`] is fed the stream byte by byte and hands out each frame
+//! whose CRC matches; after a bad frame it hunts for the next sync marker.
+//!
+//! ```
+//! # use cluster_core::framing::{FrameDecoder, encode, frame_size};
+//! let mut stream = [0; frame_size(3)];
+//! let len = encode(7, 0, b"abc", &mut stream).unwrap();
+//!
+//! let mut decoder = FrameDecoder::<16>::new();
+//! let (last, bytes) = stream[..len].split_last().unwrap();
+//! assert!(bytes.iter().all(|&byte| decoder.push(byte).is_none()));
+//! let frame = decoder.push(*last).unwrap().unwrap();
+//! assert_eq!((frame.kind, frame.payload), (7, &b"abc"[..]));
+//! ```
+
+use core::fmt;
+
+/// Marker starting every frame
+pub const SYNC: [u8; 2] = [0xA5, 0x5A];
+
+/// Size of the sync marker and header in bytes
+pub const HEADER_SIZE: usize = SYNC.len() + 6;
+
+/// Size of the CRC trailer in bytes
+pub const TRAILER_SIZE: usize = 4;
+
+/// Kind of the frames of the USB display mode: a whole panel of RGB565
+/// pixels, row by row, little-endian
+pub const KIND_DISPLAY_FRAME: u8 = 0x01;
+
+/// Kinds of the frames uploading a plugin binary to a device's load buffer
+///
+/// The sender announces the binary with [`KIND_PLUGIN_BEGIN`] (size then
+/// CRC-32, both `u32`), sends it in order in [`KIND_PLUGIN_CHUNK`] frames
+/// (offset as `u32`, then up to [`PLUGIN_CHUNK_SIZE`] bytes) and finishes
+/// with an empty [`KIND_PLUGIN_END`]. The device answers the end, or the
+/// first frame it rejects, with [`KIND_PLUGIN_STATUS`]: a status byte, 0 if
+/// the plugin was loaded, followed by a UTF-8 message.
+pub const KIND_PLUGIN_BEGIN: u8 = 0x10;
+pub const KIND_PLUGIN_CHUNK: u8 = 0x11;
+pub const KIND_PLUGIN_END: u8 = 0x12;
+pub const KIND_PLUGIN_STATUS: u8 = 0x13;
+
+/// Largest plugin binary chunk in a [`KIND_PLUGIN_CHUNK`] frame
+pub const PLUGIN_CHUNK_SIZE: usize = 1024;
+
+/// Size on the wire of a frame with `payload_len` bytes of payload
+pub const fn frame_size(payload_len: usize) -> usize {
+    HEADER_SIZE + payload_len + TRAILER_SIZE
+}
+
+/// Lookup table of the reflected CRC-32 (IEEE) polynomial
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < table.len() {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE) computed over several pieces of data
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(u32::MAX)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = CRC_TABLE[usize::from(self.0 as u8 ^ byte)] ^ (self.0 >> 8);
+        }
+    }
+
+    pub const fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CRC-32 (IEEE) of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// Write a frame to `out`, returning its size, or `None` if `out` is too
+/// small (see [`frame_size`])
+pub fn encode(kind: u8, sequence: u8, payload: &[u8], out: &mut [u8]) -> Option<usize> {
+    let size = frame_size(payload.len());
+    let length = u32::try_from(payload.len()).ok()?;
+    let out = out.get_mut(..size)?;
+
+    out[..SYNC.len()].copy_from_slice(&SYNC);
+    out[2] = kind;
+    out[3] = sequence;
+    out[4..HEADER_SIZE].copy_from_slice(&length.to_le_bytes());
+    out[HEADER_SIZE..size - TRAILER_SIZE].copy_from_slice(payload);
+    let crc = crc32(&out[SYNC.len()..size - TRAILER_SIZE]);
+    out[size - TRAILER_SIZE..].copy_from_slice(&crc.to_le_bytes());
+    Some(size)
+}
+
+/// Frame received in full with a matching CRC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub kind: u8,
+    /// Incremented by the sender for each frame, to spot dropped frames
+    pub sequence: u8,
+    pub payload: &'a [u8],
+}
+
+/// Errors that can occur while decoding a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// The header announces more payload than the decoder can hold
+    TooLarge(u32),
+    /// The CRC doesn't match the received bytes
+    BadChecksum,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(length) => write!(f, "frame of {length} bytes is too large"),
+            Self::BadChecksum => f.write_str("frame checksum mismatch"),
+        }
+    }
+}
+
+/// Part of the frame the decoder is waiting for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Number of sync bytes matched so far
+    Sync(usize),
+    Header(usize),
+    Payload(usize),
+    Crc(usize),
+}
+
+/// Decoder of a byte stream into frames of up to `N` bytes of payload
+pub struct FrameDecoder<const N: usize> {
+    state: State,
+    /// Kind, sequence and length
+    header: [u8; HEADER_SIZE - SYNC.len()],
+    length: usize,
+    payload: [u8; N],
+    crc: [u8; TRAILER_SIZE],
+}
+
+impl<const N: usize> FrameDecoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Sync(0),
+            header: [0; HEADER_SIZE - SYNC.len()],
+            length: 0,
+            payload: [0; N],
+            crc: [0; TRAILER_SIZE],
+        }
+    }
+
+    /// Drop the frame being received and wait for the next sync marker
+    pub fn reset(&mut self) {
+        self.state = State::Sync(0);
+    }
+
+    /// Feed the next byte of the stream
+    ///
+    /// Returns the frame this byte completes, or the reason it was dropped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Frame<'_>, FrameError>> {
+        match self.state {
+            State::Sync(matched) => {
+                self.state = if byte == SYNC[matched] {
+                    if matched + 1 == SYNC.len() {
+                        State::Header(0)
+                    } else {
+                        State::Sync(matched + 1)
+                    }
+                } else {
+                    State::Sync(usize::from(byte == SYNC[0]))
+                };
+            }
+            State::Header(received) => {
+                self.header[received] = byte;
+                if received + 1 < self.header.len() {
+                    self.state = State::Header(received + 1);
+                    return None;
+                }
+
+                let length = u32::from_le_bytes([
+                    self.header[2],
+                    self.header[3],
+                    self.header[4],
+                    self.header[5],
+                ]);
+                match usize::try_from(length) {
+                    Ok(length) if length <= N => {
+                        self.length = length;
+                        self.state = if length == 0 {
+                            State::Crc(0)
+                        } else {
+                            State::Payload(0)
+                        };
+                    }
+                    _ => {
+                        self.reset();
+                        return Some(Err(FrameError::TooLarge(length)));
+                    }
+                }
+            }
+            State::Payload(received) => {
+                self.payload[received] = byte;
+                self.state = if received + 1 < self.length {
+                    State::Payload(received + 1)
+                } else {
+                    State::Crc(0)
+                };
+            }
+            State::Crc(received) => {
+                self.crc[received] = byte;
+                if received + 1 < self.crc.len() {
+                    self.state = State::Crc(received + 1);
+                    return None;
+                }
+
+                self.reset();
+                let payload = &self.payload[..self.length];
+                let mut crc = Crc32::new();
+                crc.update(&self.header);
+                crc.update(payload);
+                if crc.finish() != u32::from_le_bytes(self.crc) {
+                    return Some(Err(FrameError::BadChecksum));
+                }
+                return Some(Ok(Frame {
+                    kind: self.header[0],
+                    sequence: self.header[1],
+                    payload,
+                }));
+            }
+        }
+        None
+    }
+}
+
+impl<const N: usize> Default for FrameDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `bytes` and collect the outcome of each completed or dropped frame
+    fn decode<const N: usize>(
+        decoder: &mut FrameDecoder<N>,
+        bytes: &[u8],
+        mut outcome: impl FnMut(Result<Frame<'_>, FrameError>),
+    ) {
+        for &byte in bytes {
+            if let Some(result) = decoder.push(byte) {
+                outcome(result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_round_trip_after_garbage() {
+        let mut stream = [0; 5 + 2 * frame_size(4)];
+        // Noise including a lone sync byte, then two frames
+        stream[..5].copy_from_slice(&[0x00, 0xA5, 0xA5, 0x13, 0xFF]);
+        let first = encode(KIND_DISPLAY_FRAME, 41, b"ping", &mut stream[5..]).unwrap();
+        encode(2, 42, b"pong", &mut stream[5 + first..]).unwrap();
+
+        let mut decoder = FrameDecoder::<8>::new();
+        let mut frames = 0;
+        decode(&mut decoder, &stream, |result| {
+            let frame = result.unwrap();
+            let expected = [(KIND_DISPLAY_FRAME, 41, b"ping"), (2, 42, b"pong")][frames];
+            assert_eq!(
+                (frame.kind, frame.sequence, frame.payload),
+                (expected.0, expected.1, &expected.2[..])
+            );
+            frames += 1;
+        });
+        assert_eq!(frames, 2);
+    }
+
+    #[test]
+    fn test_corrupted_frame_is_dropped() {
+        let mut stream = [0; 2 * frame_size(4)];
+        let first = encode(1, 0, b"abcd", &mut stream).unwrap();
+        encode(1, 1, b"efgh", &mut stream[first..]).unwrap();
+        stream[HEADER_SIZE + 1] ^= 0x20;
+
+        let mut decoder = FrameDecoder::<4>::new();
+        let mut outcomes = [None; 2];
+        let mut i = 0;
+        decode(&mut decoder, &stream, |result| {
+            outcomes[i] = Some(result.map(|frame| frame.sequence));
+            i += 1;
+        });
+        assert_eq!(outcomes, [Some(Err(FrameError::BadChecksum)), Some(Ok(1))]);
+    }
+
+    #[test]
+    fn test_oversized_frame() {
+        let mut stream = [0; frame_size(5)];
+        encode(1, 0, b"large", &mut stream).unwrap();
+        assert_eq!(encode(1, 0, b"large", &mut [0; 8]), None);
+
+        let mut decoder = FrameDecoder::<4>::new();
+        let mut errors = 0;
+        decode(&mut decoder, &stream, |result| {
+            assert_eq!(result, Err(FrameError::TooLarge(5)));
+            errors += 1;
+        });
+        assert_eq!(errors, 1);
+    }
+}
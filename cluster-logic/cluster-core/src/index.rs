@@ -0,0 +1,283 @@
+//! Seat lookups by ID and by position
+//!
+//! [`Cluster::seat_by_id`] and [`Cluster::seat_at`] scan the seats, which is
+//! fine for a lookup now and then. Code looking seats up on every frame,
+//! e.g. bookings or a cursor over the grid, builds a [`ClusterIndex`] once
+//! per poll instead: two sorted tables of seat indices searched in
+//! O(log n), about 1 KB for a full cluster without `std`. Both find the
+//! first seat matching, and have `seat_index_*` variants returning its
+//! index in `seats`.
+//!
+//! ```
+//! # use cluster_core::{cluster, seat, types::{Kind, Status}};
+//! # use cluster_core::index::ClusterIndex;
+//! let cluster = cluster! {
+//!     message: "",
+//!     name: "F0",
+//!     attributes: [],
+//!     seats: [
+//!         seat!("f0r1s2", Kind::Mac, Status::Free, 4, 0),
+//!         seat!("f0r1s1", Kind::Mac, Status::Taken, 0, 0)
+//!     ],
+//!     zones: []
+//! };
+//! let index = ClusterIndex::new(&cluster);
+//!
+//! let seat = index.seat_by_id(&cluster, "f0r1s1").unwrap();
+//! assert_eq!(seat.status, Status::Taken);
+//! let seat = index.seat_at(&cluster, 4, 0).unwrap();
+//! assert_eq!(&seat.id, "f0r1s2");
+//! ```
+
+use crate::diff::SeatIndexVec;
+use crate::models::{Cluster, Seat};
+
+impl Cluster {
+    /// Seat with the ID `id`
+    pub fn seat_by_id(&self, id: &str) -> Option<&Seat> {
+        self.seat_index_by_id(id).map(|index| &self.seats[index])
+    }
+
+    /// Seat at (`x`, `y`) of the seat grid
+    pub fn seat_at(&self, x: usize, y: usize) -> Option<&Seat> {
+        self.seat_index_at(x, y).map(|index| &self.seats[index])
+    }
+
+    /// Index in `seats` of the seat with the ID `id`
+    pub fn seat_index_by_id(&self, id: &str) -> Option<usize> {
+        self.seats.iter().position(|seat| seat.id == id)
+    }
+
+    /// Index in `seats` of the seat at (`x`, `y`) of the seat grid
+    pub fn seat_index_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.seats.iter().position(|seat| position(seat) == (y, x))
+    }
+}
+
+/// Seats of a cluster sorted by ID and by position, for lookups without a
+/// scan
+///
+/// The index holds seat indices, not seats: lookups take the cluster it was
+/// built from. Rebuild it when a poll replaces the cluster, e.g. with
+/// [`refresh`](Self::refresh). The index keeps a key of the seat IDs and
+/// positions it was built from, so lookups into a cluster whose seats were
+/// added, removed, renamed or moved since still find them, by scanning.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClusterIndex {
+    /// Seat indices by ID
+    by_id: SeatIndexVec,
+    /// Seat indices by row, then column
+    by_position: SeatIndexVec,
+    /// [`seats_key`] of the cluster indexed
+    key: u32,
+}
+
+impl ClusterIndex {
+    /// Index the seats of `cluster`
+    pub fn new(cluster: &Cluster) -> Self {
+        let seats = &cluster.seats;
+        // Bounded by the same capacity as the seats
+        let mut by_id: SeatIndexVec = (0..seats.len() as u16).collect();
+        let mut by_position = by_id.clone();
+
+        // Seats sharing an ID or position stay in order, first one first
+        by_id.sort_unstable_by_key(|&i| (seats[i as usize].id.as_str(), i));
+        by_position.sort_unstable_by_key(|&i| (position(&seats[i as usize]), i));
+        Self {
+            by_id,
+            by_position,
+            key: seats_key(cluster),
+        }
+    }
+
+    /// Rebuild the index if the seats of `cluster` aren't the ones indexed
+    ///
+    /// Cheaper than [`new`](Self::new) after a poll that only changed
+    /// statuses. Returns `true` if the index was rebuilt.
+    pub fn refresh(&mut self, cluster: &Cluster) -> bool {
+        if self.is_current(cluster) {
+            return false;
+        }
+        *self = Self::new(cluster);
+        true
+    }
+
+    /// Seat of `cluster` with the ID `id`
+    pub fn seat_by_id<'c>(&self, cluster: &'c Cluster, id: &str) -> Option<&'c Seat> {
+        self.seat_index_by_id(cluster, id)
+            .map(|index| &cluster.seats[index])
+    }
+
+    /// Seat of `cluster` at (`x`, `y`) of the seat grid
+    pub fn seat_at<'c>(&self, cluster: &'c Cluster, x: usize, y: usize) -> Option<&'c Seat> {
+        self.seat_index_at(cluster, x, y)
+            .map(|index| &cluster.seats[index])
+    }
+
+    /// Index in the seats of `cluster` of the seat with the ID `id`
+    pub fn seat_index_by_id(&self, cluster: &Cluster, id: &str) -> Option<usize> {
+        let seat_id = |i: u16| cluster.seats.get(i as usize).map(|seat| seat.id.as_str());
+        let first = self.by_id.partition_point(|&i| seat_id(i) < Some(id));
+        match self.by_id.get(first) {
+            Some(&i) if seat_id(i) == Some(id) => Some(i as usize),
+            _ => self.miss(cluster, || cluster.seat_index_by_id(id)),
+        }
+    }
+
+    /// Index in the seats of `cluster` of the seat at (`x`, `y`) of the seat
+    /// grid
+    pub fn seat_index_at(&self, cluster: &Cluster, x: usize, y: usize) -> Option<usize> {
+        let seat_position = |i: u16| cluster.seats.get(i as usize).map(position);
+        let first = self
+            .by_position
+            .partition_point(|&i| seat_position(i) < Some((y, x)));
+        match self.by_position.get(first) {
+            Some(&i) if seat_position(i) == Some((y, x)) => Some(i as usize),
+            _ => self.miss(cluster, || cluster.seat_index_at(x, y)),
+        }
+    }
+
+    /// Number of seats indexed
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// Check if the index was built from the seats of `cluster`
+    ///
+    /// Goes over every seat ID, so check it once per poll rather than per
+    /// lookup.
+    pub fn is_current(&self, cluster: &Cluster) -> bool {
+        self.len() == cluster.seats.len() && self.key == seats_key(cluster)
+    }
+
+    /// Confirm a lookup that found nothing, scanning with `scan` if the
+    /// index is stale
+    ///
+    /// A hit is checked against the seat found, so only misses need the key.
+    fn miss(&self, cluster: &Cluster, scan: impl FnOnce() -> Option<usize>) -> Option<usize> {
+        if self.is_current(cluster) {
+            None
+        } else {
+            scan()
+        }
+    }
+}
+
+/// Sort key of a seat in reading order
+fn position(seat: &Seat) -> (usize, usize) {
+    (seat.y, seat.x)
+}
+
+/// FNV-1a hash of the seat IDs and positions of `cluster`, in order
+fn seats_key(cluster: &Cluster) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut add = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193);
+        }
+    };
+    for seat in &cluster.seats {
+        add(seat.id.as_bytes());
+        // Not part of any ID, so "ab" + "c" hashes apart from "a" + "bc"
+        add(&[0xff]);
+        add(&(seat.x as u32).to_le_bytes());
+        add(&(seat.y as u32).to_le_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Kind, Status};
+    use crate::{cluster, empty_cluster, seat};
+
+    fn floor() -> Cluster {
+        cluster! {
+            message: "",
+            name: "F0",
+            attributes: [],
+            seats: [
+                seat!("f0r2s1", Kind::Mac, Status::Free, 0, 6),
+                seat!("f0r1s2", Kind::Dell, Status::Taken, 4, 0),
+                seat!("f0r1s1", Kind::Mac, Status::Broken, 0, 0),
+                seat!("f0r2s2", Kind::Flex, Status::Free, 4, 6)
+            ],
+            zones: []
+        }
+    }
+
+    fn id_of(seat: Option<&Seat>) -> Option<&str> {
+        seat.map(|seat| seat.id.as_str())
+    }
+
+    #[test]
+    fn test_index_matches_scan() {
+        let cluster = floor();
+        let index = ClusterIndex::new(&cluster);
+        assert_eq!(index.len(), 4);
+
+        for seat in &cluster.seats {
+            let id = Some(seat.id.as_str());
+            assert_eq!(id_of(index.seat_by_id(&cluster, &seat.id)), id);
+            assert_eq!(id_of(cluster.seat_by_id(&seat.id)), id);
+            assert_eq!(id_of(index.seat_at(&cluster, seat.x, seat.y)), id);
+            assert_eq!(id_of(cluster.seat_at(seat.x, seat.y)), id);
+        }
+        assert!(index.seat_by_id(&cluster, "f0r3s1").is_none());
+        assert!(index.seat_at(&cluster, 4, 3).is_none());
+        assert!(cluster.seat_at(4, 3).is_none());
+    }
+
+    #[test]
+    fn test_stale_index_scans() {
+        let mut cluster = floor();
+        let index = ClusterIndex::new(&cluster);
+        cluster.seats.remove(0);
+
+        assert_eq!(
+            index.seat_by_id(&cluster, "f0r2s2").map(|seat| seat.x),
+            Some(4)
+        );
+        assert!(index.seat_at(&cluster, 0, 6).is_none());
+        assert!(ClusterIndex::new(&empty_cluster!("F1")).is_empty());
+    }
+
+    #[test]
+    fn test_renamed_and_moved_seats_found() {
+        let mut cluster = floor();
+        let mut index = ClusterIndex::new(&cluster);
+
+        // Same number of seats, out of the index's order
+        cluster.seats[1] = seat!("f0r0s9", Kind::Dell, Status::Taken, 4, 0);
+        cluster.seats[3].x = 9;
+        assert!(!index.is_current(&cluster));
+        assert_eq!(index.seat_index_by_id(&cluster, "f0r0s9"), Some(1));
+        assert_eq!(index.seat_index_at(&cluster, 9, 6), Some(3));
+        assert!(index.seat_at(&cluster, 4, 6).is_none());
+
+        assert!(index.refresh(&cluster));
+        assert!(index.is_current(&cluster));
+        assert_eq!(index.seat_index_by_id(&cluster, "f0r0s9"), Some(1));
+
+        // Statuses aren't part of the key
+        cluster.seats[0].status = Status::Taken;
+        assert!(!index.refresh(&cluster));
+    }
+
+    #[test]
+    fn test_first_of_duplicates() {
+        let mut cluster = floor();
+        cluster.seats[3] = seat!("f0r1s1", Kind::Flex, Status::Free, 0, 0);
+        let index = ClusterIndex::new(&cluster);
+
+        assert_eq!(index.seat_index_by_id(&cluster, "f0r1s1"), Some(2));
+        assert_eq!(cluster.seat_index_by_id("f0r1s1"), Some(2));
+        assert_eq!(index.seat_index_at(&cluster, 0, 0), Some(2));
+        assert_eq!(cluster.seat_index_at(0, 0), Some(2));
+    }
+}
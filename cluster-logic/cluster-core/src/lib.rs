@@ -3,8 +3,34 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "assets")]
+pub mod assets;
+#[cfg(feature = "bookings")]
+pub mod bookings;
+#[cfg(feature = "codec")]
+pub mod codec;
 pub mod constants;
+pub mod diff;
+#[cfg(feature = "events")]
+pub mod events;
+#[cfg(feature = "framing")]
+pub mod framing;
+pub mod index;
+pub mod messages;
 pub mod models;
+#[cfg(feature = "persist")]
+pub mod persist;
+pub mod preferences;
+#[cfg(feature = "recording")]
+pub mod recording;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "events")]
+pub mod settings;
+pub mod smoothing;
+pub mod stats;
+pub mod trend;
 pub mod types;
 pub mod utils;
+pub mod validate;
 pub mod visualization;
@@ -0,0 +1,153 @@
+//! Default messages for clusters without one
+//!
+//! A closed cluster, or one booked for an exam, has no seat worth walking
+//! to, but unless staff wrote a message the header stays empty and nothing
+//! tells users why. [`FallbackMessages`] picks a localized default from the
+//! cluster's attributes in that case. A deployment can replace a default
+//! with its own wording, e.g. "Closed for maintenance until 14:00", which
+//! the firmware keeps in its device configuration.
+
+use crate::models::Cluster;
+use crate::types::Attribute;
+use graphics_common::i18n::{Locale, MessageId};
+use heapless::{String, Vec};
+
+/// Maximum length of a deployment's own message
+pub const MAX_OVERRIDE_LENGTH: usize = 64;
+
+/// Attributes with a default message; a cluster with several shows the
+/// first one's
+pub const MESSAGE_ATTRIBUTES: [(Attribute, MessageId); 2] = [
+    (Attribute::Closed, MessageId::ClusterClosed),
+    (Attribute::Exam, MessageId::ClusterExam),
+];
+
+/// Message replacing a default one
+pub type OverrideString = String<MAX_OVERRIDE_LENGTH>;
+
+/// Why an override was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverrideError {
+    /// The attribute has no default message, see [`MESSAGE_ATTRIBUTES`]
+    NoDefault,
+    /// The message is longer than [`MAX_OVERRIDE_LENGTH`]
+    TooLong,
+}
+
+/// Messages shown for clusters without one, with the deployment's overrides
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FallbackMessages {
+    overrides: Vec<(Attribute, OverrideString), { MESSAGE_ATTRIBUTES.len() }>,
+}
+
+impl FallbackMessages {
+    /// The localized defaults, without overrides
+    pub const fn new() -> Self {
+        Self {
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Show `text` instead of the default message of `attribute`
+    ///
+    /// An empty `text` restores the default.
+    pub fn set_override(&mut self, attribute: Attribute, text: &str) -> Result<(), OverrideError> {
+        if !MESSAGE_ATTRIBUTES.iter().any(|(a, _)| *a == attribute) {
+            return Err(OverrideError::NoDefault);
+        }
+        let text = OverrideString::try_from(text).map_err(|_| OverrideError::TooLong)?;
+
+        self.overrides.retain(|(a, _)| *a != attribute);
+        if !text.is_empty() {
+            // Cannot fail: one slot per attribute with a default
+            let _ = self.overrides.push((attribute, text));
+        }
+        Ok(())
+    }
+
+    /// The deployment's message for `attribute`, if it replaced the default
+    pub fn override_for(&self, attribute: Attribute) -> Option<&str> {
+        self.overrides
+            .iter()
+            .find(|(a, _)| *a == attribute)
+            .map(|(_, text)| text.as_str())
+    }
+
+    /// Message of `cluster`: its own, or else the one of its attributes
+    ///
+    /// Empty when the cluster has neither.
+    pub fn message<'m>(&'m self, cluster: &'m Cluster, locale: Locale) -> &'m str {
+        if !cluster.message.is_empty() {
+            return &cluster.message;
+        }
+        MESSAGE_ATTRIBUTES
+            .iter()
+            .find(|(attribute, _)| cluster.attributes.contains(attribute))
+            .map_or("", |&(attribute, id)| {
+                self.override_for(attribute)
+                    .unwrap_or_else(|| id.text(locale))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(message: &str, attributes: &[Attribute]) -> Cluster {
+        let mut cluster = crate::cluster! {
+            message: message,
+            name: "F0",
+            attributes: [],
+            seats: [],
+            zones: []
+        };
+        cluster.attributes.extend(attributes.iter().copied());
+        cluster
+    }
+
+    #[test]
+    fn test_message_from_attributes() {
+        let messages = FallbackMessages::new();
+
+        let own = cluster("Back at 2pm", &[Attribute::Closed]);
+        assert_eq!(messages.message(&own, Locale::En), "Back at 2pm");
+
+        let closed = cluster("", &[Attribute::Exam, Attribute::Closed]);
+        assert_eq!(messages.message(&closed, Locale::En), "Cluster closed");
+        assert_eq!(messages.message(&closed, Locale::Fr), "Cluster fermé");
+
+        let exam = cluster("", &[Attribute::Silent, Attribute::Exam]);
+        assert_eq!(messages.message(&exam, Locale::En), "Exam in progress");
+
+        let open = cluster("", &[Attribute::Silent]);
+        assert_eq!(messages.message(&open, Locale::En), "");
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut messages = FallbackMessages::new();
+        let closed = cluster("", &[Attribute::Closed]);
+
+        messages
+            .set_override(Attribute::Closed, "Maintenance until 14:00")
+            .unwrap();
+        assert_eq!(
+            messages.message(&closed, Locale::Fr),
+            "Maintenance until 14:00"
+        );
+
+        messages.set_override(Attribute::Closed, "").unwrap();
+        assert_eq!(messages.message(&closed, Locale::En), "Cluster closed");
+
+        assert_eq!(
+            messages.set_override(Attribute::Event, "Party"),
+            Err(OverrideError::NoDefault)
+        );
+        let long = [b'x'; MAX_OVERRIDE_LENGTH + 1];
+        assert_eq!(
+            messages.set_override(Attribute::Exam, core::str::from_utf8(&long).unwrap()),
+            Err(OverrideError::TooLong)
+        );
+    }
+}
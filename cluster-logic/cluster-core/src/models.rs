@@ -1,7 +1,7 @@
 //! Main data models for cluster representation
 
 use crate::types::AttributeVec;
-use crate::types::{ClusterId, ClusterString, Kind, MessageString, SeatId, Status};
+use crate::types::{ClusterId, ClusterString, Kind, MessageString, SeatId, SeatLabel, Status};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
@@ -16,15 +16,21 @@ pub type ZoneVec = heapless::Vec<Zone, { crate::constants::MAX_ZONES }>;
 
 #[doc = "`ClusterUpdate`"]
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ClusterUpdate {
     pub attributes: AttributeVec,
     pub id: ClusterId,
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "crate::types::lossy::deserialize")
+    )]
     pub name: ClusterString,
     pub zones: ZoneVec,
 }
 
 #[doc = "`Layout`"]
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Layout {
     pub f0: Cluster,
     pub f1: Cluster,
@@ -34,13 +40,188 @@ pub struct Layout {
     pub f6: Cluster,
 }
 
+impl Layout {
+    /// Ids of the displayed clusters, from the lowest floor up
+    pub const FLOORS: [ClusterId; 6] = [
+        ClusterId::F0,
+        ClusterId::F1,
+        ClusterId::F1b,
+        ClusterId::F2,
+        ClusterId::F4,
+        ClusterId::F6,
+    ];
+
+    /// Get a displayed cluster by ID
+    pub const fn get(&self, id: ClusterId) -> Option<&Cluster> {
+        match id {
+            ClusterId::Hidden => None,
+            ClusterId::F0 => Some(&self.f0),
+            ClusterId::F1 => Some(&self.f1),
+            ClusterId::F1b => Some(&self.f1b),
+            ClusterId::F2 => Some(&self.f2),
+            ClusterId::F4 => Some(&self.f4),
+            ClusterId::F6 => Some(&self.f6),
+        }
+    }
+
+    /// Get a displayed cluster by ID, to update it in place
+    pub const fn get_mut(&mut self, id: ClusterId) -> Option<&mut Cluster> {
+        match id {
+            ClusterId::Hidden => None,
+            ClusterId::F0 => Some(&mut self.f0),
+            ClusterId::F1 => Some(&mut self.f1),
+            ClusterId::F1b => Some(&mut self.f1b),
+            ClusterId::F2 => Some(&mut self.f2),
+            ClusterId::F4 => Some(&mut self.f4),
+            ClusterId::F6 => Some(&mut self.f6),
+        }
+    }
+
+    /// Get every displayed cluster with its id, from the lowest floor up
+    pub const fn clusters(&self) -> [(ClusterId, &Cluster); 6] {
+        [
+            (ClusterId::F0, &self.f0),
+            (ClusterId::F1, &self.f1),
+            (ClusterId::F1b, &self.f1b),
+            (ClusterId::F2, &self.f2),
+            (ClusterId::F4, &self.f4),
+            (ClusterId::F6, &self.f6),
+        ]
+    }
+
+    /// Compute the static statistics of every floor
+    ///
+    /// Same values as `layout_stats_from_json!` generates at compile time.
+    pub fn stats(&self) -> LayoutStats {
+        let mut stats = LayoutStats::default();
+        for (floor, (_, cluster)) in stats.floors.iter_mut().zip(self.clusters()) {
+            *floor = FloorStats {
+                total_seats: cluster.seats.len() as u16,
+                bounds: cluster.seat_bounds(),
+            };
+        }
+        stats
+    }
+
+    /// Replace the clusters present in `partial`, keeping the others untouched
+    pub fn apply(&mut self, partial: PartialLayout) {
+        let PartialLayout {
+            f0,
+            f1,
+            f1b,
+            f2,
+            f4,
+            f6,
+        } = partial;
+        let slots = [
+            (&mut self.f0, f0),
+            (&mut self.f1, f1),
+            (&mut self.f1b, f1b),
+            (&mut self.f2, f2),
+            (&mut self.f4, f4),
+            (&mut self.f6, f6),
+        ];
+        for (slot, cluster) in slots {
+            if let Some(cluster) = cluster {
+                *slot = cluster;
+            }
+        }
+    }
+}
+
+/// Subset of a [`Layout`] holding only the requested clusters
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PartialLayout {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f0: Option<Cluster>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f1: Option<Cluster>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f1b: Option<Cluster>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f2: Option<Cluster>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f4: Option<Cluster>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub f6: Option<Cluster>,
+}
+
+impl PartialLayout {
+    /// Get a cluster by ID, if it was part of the response
+    pub fn get(&self, id: ClusterId) -> Option<&Cluster> {
+        match id {
+            ClusterId::Hidden => None,
+            ClusterId::F0 => self.f0.as_ref(),
+            ClusterId::F1 => self.f1.as_ref(),
+            ClusterId::F1b => self.f1b.as_ref(),
+            ClusterId::F2 => self.f2.as_ref(),
+            ClusterId::F4 => self.f4.as_ref(),
+            ClusterId::F6 => self.f6.as_ref(),
+        }
+    }
+
+    /// Take a cluster out by ID, if it was part of the response
+    pub fn take(&mut self, id: ClusterId) -> Option<Cluster> {
+        match id {
+            ClusterId::Hidden => None,
+            ClusterId::F0 => self.f0.take(),
+            ClusterId::F1 => self.f1.take(),
+            ClusterId::F1b => self.f1b.take(),
+            ClusterId::F2 => self.f2.take(),
+            ClusterId::F4 => self.f4.take(),
+            ClusterId::F6 => self.f6.take(),
+        }
+    }
+}
+
+/// Named layouts embedded at build time, see `layouts_from_dir!`
+///
+/// Layouts are only built when requested, so embedding several campuses
+/// doesn't keep them all in memory.
+#[derive(Clone, Copy)]
+pub struct LayoutSet {
+    names: &'static [&'static str],
+    build: fn(&str) -> Option<Layout>,
+}
+
+impl LayoutSet {
+    /// Create a set from its sorted layout names and a builder
+    pub const fn new(names: &'static [&'static str], build: fn(&str) -> Option<Layout>) -> Self {
+        Self { names, build }
+    }
+
+    /// Names of the embedded layouts, sorted
+    pub const fn names(&self) -> &'static [&'static str] {
+        self.names
+    }
+
+    /// Check if a layout with this name is embedded
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name)
+    }
+
+    /// Build the layout with this name
+    pub fn get(&self, name: &str) -> Option<Layout> {
+        (self.build)(name)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Seat {
     pub id: SeatId,
     pub kind: Kind,
     pub status: Status,
     pub x: usize,
     pub y: usize,
+    /// Short tag shown over the seat when zoomed in, e.g. staff initials
+    ///
+    /// Older servers don't send it.
+    // Serialized even when empty: the persisted encoding has no field names
+    // to skip it by
+    #[serde(default)]
+    pub label: Option<SeatLabel>,
 }
 
 impl Seat {
@@ -52,12 +233,55 @@ impl Seat {
             Status::Broken | Status::Reported => self.status.color(),
         }
     }
+
+    /// Cluster, row and number of the seat, if its ID has the usual form
+    pub fn id_parts(&self) -> Option<SeatIdParts<'_>> {
+        SeatIdParts::parse(&self.id)
+    }
+}
+
+/// Parts of a seat ID of the usual `<cluster>r<row>s<seat>` form, e.g.
+/// `f1br2s14`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeatIdParts<'a> {
+    pub cluster: &'a str,
+    pub row: u16,
+    pub seat: u16,
+}
+
+impl<'a> SeatIdParts<'a> {
+    /// Split a seat ID, `None` if it doesn't have the usual form
+    pub fn parse(id: &'a str) -> Option<Self> {
+        // Digits only, `parse` alone would take a sign
+        let number = |digits: &str| {
+            digits
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+                .then(|| digits.parse().ok())
+                .flatten()
+        };
+        let (rest, seat) = id.rsplit_once('s')?;
+        let (cluster, row) = rest.rsplit_once('r')?;
+        if cluster.is_empty() {
+            return None;
+        }
+        Some(Self {
+            cluster,
+            row: number(row)?,
+            seat: number(seat)?,
+        })
+    }
 }
 
 #[doc = "`Zone`"]
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Zone {
     pub attributes: AttributeVec,
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "crate::types::lossy::deserialize")
+    )]
     pub name: ClusterString,
     pub x: usize,
     pub y: usize,
@@ -65,9 +289,18 @@ pub struct Zone {
 
 #[doc = "`Cluster`"]
 #[derive(Deserialize, Serialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Cluster {
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "crate::types::lossy::deserialize")
+    )]
     pub message: MessageString,
     pub attributes: AttributeVec,
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "crate::types::lossy::deserialize")
+    )]
     pub name: ClusterString,
     pub seats: SeatVec,
     pub zones: ZoneVec,
@@ -76,8 +309,14 @@ pub struct Cluster {
 impl Cluster {
     /// Get the grid dimensions based on seat positions
     pub fn grid_size(&self) -> (usize, usize) {
+        let bounds = self.seat_bounds();
+        (bounds.width, bounds.height)
+    }
+
+    /// Get the bounding box of the seat positions
+    pub fn seat_bounds(&self) -> SeatBounds {
         if self.seats.is_empty() {
-            return (0, 0);
+            return SeatBounds::default();
         }
 
         let min_x = self.seats.iter().map(|p| p.x).min().unwrap_or(0);
@@ -85,7 +324,12 @@ impl Cluster {
         let min_y = self.seats.iter().map(|p| p.y).min().unwrap_or(0);
         let max_y = self.seats.iter().map(|p| p.y).max().unwrap_or(0);
 
-        (max_x - min_x + 1, max_y - min_y + 1)
+        SeatBounds {
+            min_x,
+            min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        }
     }
 
     /// Calculate overall occupancy percentage
@@ -140,3 +384,70 @@ impl ClusterStats {
         }
     }
 }
+
+/// Bounding box of a cluster's seat positions, in grid coordinates
+///
+/// Empty clusters have a zero-sized box at the origin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeatBounds {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Seat statistics of a floor that don't change with seat status
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FloorStats {
+    pub total_seats: u16,
+    pub bounds: SeatBounds,
+}
+
+/// Static statistics of every floor of a layout
+///
+/// Generated as a constant by `layout_stats_from_json!` for embedded
+/// layouts, so buffers and projections can be sized at compile time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayoutStats {
+    /// Per-floor statistics, in [`Layout::FLOORS`] order
+    pub floors: [FloorStats; 6],
+}
+
+impl LayoutStats {
+    /// Get the statistics of a displayed cluster
+    pub const fn get(&self, id: ClusterId) -> Option<&FloorStats> {
+        match id {
+            ClusterId::Hidden => None,
+            ClusterId::F0 => Some(&self.floors[0]),
+            ClusterId::F1 => Some(&self.floors[1]),
+            ClusterId::F1b => Some(&self.floors[2]),
+            ClusterId::F2 => Some(&self.floors[3]),
+            ClusterId::F4 => Some(&self.floors[4]),
+            ClusterId::F6 => Some(&self.floors[5]),
+        }
+    }
+
+    /// Total number of seats over all floors
+    pub const fn total_seats(&self) -> u16 {
+        let mut total = 0;
+        let mut i = 0;
+        while i < self.floors.len() {
+            total += self.floors[i].total_seats;
+            i += 1;
+        }
+        total
+    }
+
+    /// Largest seat count of a single floor
+    pub const fn max_floor_seats(&self) -> u16 {
+        let mut max = 0;
+        let mut i = 0;
+        while i < self.floors.len() {
+            if self.floors[i].total_seats > max {
+                max = self.floors[i].total_seats;
+            }
+            i += 1;
+        }
+        max
+    }
+}
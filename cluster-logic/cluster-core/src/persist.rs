@@ -0,0 +1,209 @@
+//! Binary encoding of a [`Layout`] for persistent storage
+//!
+//! The last good layout is written to flash so the panel can show slightly
+//! stale data at boot while the network comes up. The [`Preferences`] use the
+//! same record format with their own magic. A record is laid out as:
+//!
+//! ```text
+//! [magic: u32 LE][payload length: u32 LE][checksum: u32 LE][postcard payload]
+//! ```
+//!
+//! Erased flash (all `0xFF`) or a torn write is rejected by the magic and
+//! checksum before the payload is decoded.
+
+use crate::models::Layout;
+use crate::preferences::Preferences;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Record magic ("LYT2"), bumped whenever the encoded layout changes shape
+pub const LAYOUT_MAGIC: u32 = 0x4C59_5432;
+
+/// Preferences record magic ("PRF1")
+pub const PREFERENCES_MAGIC: u32 = 0x5052_4631;
+
+/// Size of the record header in bytes
+pub const HEADER_SIZE: usize = 12;
+
+/// Errors that can occur while encoding or decoding a persisted layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistError {
+    /// Buffer is too small for the encoded layout
+    BufferTooSmall,
+    /// No layout record found (bad magic, e.g. erased flash)
+    NotFound,
+    /// Record is truncated or its checksum does not match
+    Corrupted,
+    /// Payload could not be encoded or decoded
+    Encoding,
+}
+
+/// Encode a layout into `buffer`, returning the number of bytes written
+pub fn encode_layout(layout: &Layout, buffer: &mut [u8]) -> Result<usize, PersistError> {
+    encode_record(LAYOUT_MAGIC, layout, buffer)
+}
+
+/// Decode a layout previously written by [`encode_layout`]
+pub fn decode_layout(buffer: &[u8]) -> Result<Layout, PersistError> {
+    decode_record(LAYOUT_MAGIC, buffer)
+}
+
+/// Encode preferences into `buffer`, returning the number of bytes written
+pub fn encode_preferences(
+    preferences: &Preferences,
+    buffer: &mut [u8],
+) -> Result<usize, PersistError> {
+    encode_record(PREFERENCES_MAGIC, preferences, buffer)
+}
+
+/// Decode preferences previously written by [`encode_preferences`]
+pub fn decode_preferences(buffer: &[u8]) -> Result<Preferences, PersistError> {
+    decode_record(PREFERENCES_MAGIC, buffer)
+}
+
+fn encode_record<T: Serialize>(
+    magic: u32,
+    value: &T,
+    buffer: &mut [u8],
+) -> Result<usize, PersistError> {
+    if buffer.len() < HEADER_SIZE {
+        return Err(PersistError::BufferTooSmall);
+    }
+
+    let (header, payload) = buffer.split_at_mut(HEADER_SIZE);
+    let payload_len = postcard::to_slice(value, payload)
+        .map_err(|e| match e {
+            postcard::Error::SerializeBufferFull => PersistError::BufferTooSmall,
+            _ => PersistError::Encoding,
+        })?
+        .len();
+
+    header[0..4].copy_from_slice(&magic.to_le_bytes());
+    header[4..8].copy_from_slice(&(payload_len as u32).to_le_bytes());
+    header[8..12].copy_from_slice(&checksum(&payload[..payload_len]).to_le_bytes());
+
+    Ok(HEADER_SIZE + payload_len)
+}
+
+fn decode_record<T: DeserializeOwned>(magic: u32, buffer: &[u8]) -> Result<T, PersistError> {
+    if buffer.len() < HEADER_SIZE {
+        return Err(PersistError::NotFound);
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            buffer[offset],
+            buffer[offset + 1],
+            buffer[offset + 2],
+            buffer[offset + 3],
+        ])
+    };
+
+    if read_u32(0) != magic {
+        return Err(PersistError::NotFound);
+    }
+
+    let payload_end = HEADER_SIZE
+        .checked_add(read_u32(4) as usize)
+        .ok_or(PersistError::Corrupted)?;
+    let payload = buffer
+        .get(HEADER_SIZE..payload_end)
+        .ok_or(PersistError::Corrupted)?;
+    if checksum(payload) != read_u32(8) {
+        return Err(PersistError::Corrupted);
+    }
+
+    postcard::from_bytes(payload).map_err(|_| PersistError::Encoding)
+}
+
+/// FNV-1a hash of the payload
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811C_9DC5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::types::{Kind, Status};
+    use crate::{cluster, layout, seat};
+    use std::vec;
+
+    fn sample_layout() -> Layout {
+        layout! {
+            f0: cluster! {
+                message: "Hello",
+                name: "F0",
+                attributes: [],
+                seats: [
+                    seat!("f0r1s1", Kind::Mac, Status::Free, 0, 0),
+                    seat!("f0r1s2", Kind::Dell, Status::Taken, 3, 1)
+                ],
+                zones: []
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout_round_trip() {
+        let layout = sample_layout();
+        let mut buffer = vec![0u8; 1024];
+
+        let len = encode_layout(&layout, &mut buffer).unwrap();
+        let decoded = decode_layout(&buffer[..len]).unwrap();
+
+        assert_eq!(decoded.f0.seats.len(), 2);
+        assert_eq!(decoded.f0.seats[1].id, "f0r1s2");
+        assert_eq!(decoded.f6.name, "F6");
+    }
+
+    #[test]
+    fn test_rejects_erased_and_corrupted_records() {
+        let erased = [0xFFu8; 64];
+        assert_eq!(decode_layout(&erased).unwrap_err(), PersistError::NotFound);
+
+        let mut buffer = vec![0u8; 1024];
+        let len = encode_layout(&sample_layout(), &mut buffer).unwrap();
+        buffer[len - 1] ^= 0xFF;
+        assert_eq!(
+            decode_layout(&buffer[..len]).unwrap_err(),
+            PersistError::Corrupted
+        );
+        assert_eq!(
+            decode_layout(&buffer[..len - 1]).unwrap_err(),
+            PersistError::Corrupted
+        );
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let mut buffer = [0u8; 16];
+        assert_eq!(
+            encode_layout(&sample_layout(), &mut buffer).unwrap_err(),
+            PersistError::BufferTooSmall
+        );
+    }
+
+    #[test]
+    fn test_preferences_round_trip() {
+        use crate::preferences::Theme;
+        use crate::types::ClusterId;
+
+        let preferences = Preferences {
+            theme: Theme::Light,
+            default_floor: ClusterId::F4,
+        };
+        let mut buffer = [0u8; 32];
+        let len = encode_preferences(&preferences, &mut buffer).unwrap();
+        assert_eq!(decode_preferences(&buffer[..len]).unwrap(), preferences);
+
+        // Records of another kind are not mistaken for preferences
+        let mut buffer = vec![0u8; 1024];
+        let len = encode_layout(&sample_layout(), &mut buffer).unwrap();
+        assert_eq!(
+            decode_preferences(&buffer[..len]).unwrap_err(),
+            PersistError::NotFound
+        );
+    }
+}
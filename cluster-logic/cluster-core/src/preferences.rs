@@ -0,0 +1,204 @@
+//! User preferences synced with the server
+//!
+//! A small [`Preferences`] blob (theme, floor shown at boot) can be changed
+//! on the device and on the server. [`PreferenceSync`] tracks which copy is
+//! current:
+//!
+//! - at boot the copy persisted in flash is used, or the defaults;
+//! - preferences fetched from the server always replace the local ones,
+//!   including a local change not pushed yet (the server wins);
+//! - local changes are queued until the server accepted them.
+//!
+//! The caller does the I/O: fetch and push through `cluster-net`, and write
+//! the current preferences to flash whenever [`PreferenceSync::apply_server`]
+//! or [`PreferenceSync::set`] report a change.
+
+use crate::types::ClusterId;
+use serde::{Deserialize, Serialize};
+
+/// Color scheme of the panel
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// Preferences of a site
+///
+/// Missing fields take their default value, so the server can send a subset.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Preferences {
+    pub theme: Theme,
+    /// Floor shown when the panel starts
+    pub default_floor: ClusterId,
+}
+
+impl Preferences {
+    pub const DEFAULT: Self = Self {
+        theme: Theme::Dark,
+        default_floor: ClusterId::F0,
+    };
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Where the current preferences come from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreferenceSource {
+    /// Nothing persisted or received yet
+    Default,
+    /// Loaded from flash at boot
+    Flash,
+    /// Received from the server
+    Server,
+    /// Changed on the device
+    Local,
+}
+
+/// Tracks the current preferences and the local change to push
+#[derive(Clone, Copy, Debug)]
+pub struct PreferenceSync {
+    current: Preferences,
+    source: PreferenceSource,
+    pending: bool,
+}
+
+impl PreferenceSync {
+    /// Start from the preferences persisted in flash, if any
+    pub const fn new(stored: Option<Preferences>) -> Self {
+        match stored {
+            Some(current) => Self {
+                current,
+                source: PreferenceSource::Flash,
+                pending: false,
+            },
+            None => Self {
+                current: Preferences::DEFAULT,
+                source: PreferenceSource::Default,
+                pending: false,
+            },
+        }
+    }
+
+    pub const fn current(&self) -> &Preferences {
+        &self.current
+    }
+
+    pub const fn source(&self) -> PreferenceSource {
+        self.source
+    }
+
+    /// Change the preferences on the device and queue them for the server
+    ///
+    /// Returns `true` if they changed and should be persisted.
+    pub fn set(&mut self, preferences: Preferences) -> bool {
+        if preferences == self.current {
+            return false;
+        }
+        self.current = preferences;
+        self.source = PreferenceSource::Local;
+        self.pending = true;
+        true
+    }
+
+    /// Preferences to push to the server, if a local change is queued
+    pub const fn pending(&self) -> Option<&Preferences> {
+        if self.pending {
+            Some(&self.current)
+        } else {
+            None
+        }
+    }
+
+    /// Record that the server accepted `pushed`
+    ///
+    /// Only clears the queue if nothing changed locally since the push.
+    pub fn push_succeeded(&mut self, pushed: &Preferences) {
+        if self.pending && *pushed == self.current {
+            self.pending = false;
+        }
+    }
+
+    /// Adopt the preferences fetched from the server
+    ///
+    /// The server wins over local changes not pushed yet. Returns `true` if
+    /// the preferences changed and should be persisted.
+    pub fn apply_server(&mut self, preferences: Preferences) -> bool {
+        let changed = preferences != self.current;
+        self.current = preferences;
+        self.source = PreferenceSource::Server;
+        self.pending = false;
+        changed
+    }
+}
+
+impl Default for PreferenceSync {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHT_F2: Preferences = Preferences {
+        theme: Theme::Light,
+        default_floor: ClusterId::F2,
+    };
+
+    #[test]
+    fn test_boot_sources() {
+        let sync = PreferenceSync::new(None);
+        assert_eq!(sync.source(), PreferenceSource::Default);
+        assert_eq!(*sync.current(), Preferences::DEFAULT);
+
+        let sync = PreferenceSync::new(Some(LIGHT_F2));
+        assert_eq!(sync.source(), PreferenceSource::Flash);
+        assert_eq!(*sync.current(), LIGHT_F2);
+        assert!(sync.pending().is_none());
+    }
+
+    #[test]
+    fn test_local_change_pushed() {
+        let mut sync = PreferenceSync::default();
+        assert!(sync.set(LIGHT_F2));
+        assert!(!sync.set(LIGHT_F2));
+        let pushed = *sync.pending().unwrap();
+
+        // A change made while the push was in flight stays queued
+        let dark_f2 = Preferences {
+            theme: Theme::Dark,
+            ..LIGHT_F2
+        };
+        assert!(sync.set(dark_f2));
+        sync.push_succeeded(&pushed);
+        assert_eq!(sync.pending(), Some(&dark_f2));
+
+        sync.push_succeeded(&dark_f2);
+        assert!(sync.pending().is_none());
+        assert_eq!(sync.source(), PreferenceSource::Local);
+    }
+
+    #[test]
+    fn test_server_wins() {
+        let mut sync = PreferenceSync::new(Some(Preferences::DEFAULT));
+        sync.set(LIGHT_F2);
+
+        assert!(sync.apply_server(Preferences::DEFAULT));
+        assert!(sync.pending().is_none());
+        assert_eq!(*sync.current(), Preferences::DEFAULT);
+        assert_eq!(sync.source(), PreferenceSource::Server);
+
+        assert!(!sync.apply_server(Preferences::DEFAULT));
+    }
+}
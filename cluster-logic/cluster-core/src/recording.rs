@@ -0,0 +1,501 @@
+//! Recording of the last frames shown, for postmortems of visual glitches
+//!
+//! A glitch seen once a day is hard to catch on camera. The firmware keeps
+//! the last committed frames in a [`FrameRing`] in RAM, compressed with a
+//! [`Codec`], and copies them to flash when asked to, e.g. with a held
+//! button. The recording is then read back from flash and played frame by
+//! frame in the simulator (`sim replay`).
+//!
+//! A recording is a header followed by the frames, oldest first:
+//!
+//! ```text
+//! [magic: u32 LE][sequence: u32 LE][width: u16 LE][height: u16 LE][length: u32 LE]
+//! [timestamp ms: u32 LE][size: u32 LE][frame] ...
+//! ```
+//!
+//! `length` is the size of the frames after the header, and each frame has
+//! `size` bytes covering the panel row-major in RGB565, encoded with the
+//! codec given by the magic: [`RECORDING_MAGIC`] for runs, see
+//! [`Codec::Rle`], [`HEATSHRINK_MAGIC`] for [`Codec::Heatshrink`]. As in
+//! `assets`, the header is written last, so a torn write leaves an erased
+//! header rather than a truncated recording.
+
+use crate::codec::{Codec, CodecError};
+use heapless::Deque;
+
+/// Magic of run-length encoded recordings ("FRC1")
+pub const RECORDING_MAGIC: u32 = 0x4652_4331;
+
+/// Magic of heatshrink compressed recordings ("FRC2")
+pub const HEATSHRINK_MAGIC: u32 = 0x4652_4332;
+
+/// Size of the recording header in bytes
+pub const HEADER_SIZE: usize = 16;
+
+/// Size of the timestamp and size preceding each frame
+const FRAME_HEADER_SIZE: usize = 8;
+
+/// Errors that can occur while reading a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingError {
+    /// No recording header, e.g. erased flash
+    NotFound,
+    /// The data ends in the middle of a frame
+    Truncated,
+    /// A frame doesn't cover the panel exactly
+    SizeMismatch,
+    /// A frame doesn't decode, see [`CodecError::Corrupt`]
+    Corrupt,
+}
+
+impl From<CodecError> for RecordingError {
+    fn from(error: CodecError) -> Self {
+        match error {
+            CodecError::Truncated => Self::Truncated,
+            CodecError::SizeMismatch => Self::SizeMismatch,
+            CodecError::Corrupt => Self::Corrupt,
+        }
+    }
+}
+
+/// Size of the runs encoding `pixels`
+pub fn encoded_size(pixels: &[u16]) -> usize {
+    Codec::Rle.encoded_size(pixels)
+}
+
+/// Decode the runs of a frame into `pixels`, which they must fill exactly
+pub fn decode_runs(data: &[u8], pixels: &mut [u16]) -> Result<(), RecordingError> {
+    Codec::Rle
+        .decode(data, pixels)
+        .map_err(RecordingError::from)
+}
+
+/// Last frames shown, compressed in `BYTES` bytes of RAM
+///
+/// Pushing a frame drops the oldest ones until it fits, so the ring always
+/// holds the latest frames.
+pub struct FrameRing<const BYTES: usize> {
+    bytes: Deque<u8, BYTES>,
+    frames: usize,
+    codec: Codec,
+}
+
+impl<const BYTES: usize> Default for FrameRing<BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BYTES: usize> FrameRing<BYTES> {
+    /// Ring of run-length encoded frames
+    pub const fn new() -> Self {
+        Self::with_codec(Codec::Rle)
+    }
+
+    /// Ring of frames encoded with `codec`
+    ///
+    /// [`Codec::Heatshrink`] holds several times more frames with gradients
+    /// or text, but encodes each frame twice, once to size it.
+    pub const fn with_codec(codec: Codec) -> Self {
+        Self {
+            bytes: Deque::new(),
+            frames: 0,
+            codec,
+        }
+    }
+
+    /// Codec of the frames
+    pub const fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Record a frame shown at `timestamp_ms`
+    ///
+    /// Returns `false` if the frame alone doesn't fit in the ring.
+    pub fn push(&mut self, timestamp_ms: u32, pixels: &[u16]) -> bool {
+        let size = self.codec.encoded_size(pixels);
+        if !self.make_room(timestamp_ms, size) {
+            return false;
+        }
+        let codec = self.codec;
+        codec.encode(pixels, |byte| {
+            let _ = self.bytes.push_back(byte);
+        });
+        true
+    }
+
+    /// Record a frame shown at `timestamp_ms`, already encoded with
+    /// [`Self::codec`]
+    ///
+    /// Lets the caller encode outside of the lock guarding a shared ring.
+    /// Returns `false` if the frame alone doesn't fit in the ring.
+    pub fn push_encoded(&mut self, timestamp_ms: u32, data: &[u8]) -> bool {
+        if !self.make_room(timestamp_ms, data.len()) {
+            return false;
+        }
+        for &byte in data {
+            let _ = self.bytes.push_back(byte);
+        }
+        true
+    }
+
+    /// Drop the oldest frames until one of `size` bytes fits, and write its
+    /// header
+    fn make_room(&mut self, timestamp_ms: u32, size: usize) -> bool {
+        if FRAME_HEADER_SIZE + size > BYTES {
+            return false;
+        }
+        while BYTES - self.bytes.len() < FRAME_HEADER_SIZE + size {
+            self.drop_oldest();
+        }
+
+        let header = timestamp_ms
+            .to_le_bytes()
+            .into_iter()
+            .chain((size as u32).to_le_bytes());
+        // Room was made above, for the frame too
+        for byte in header {
+            let _ = self.bytes.push_back(byte);
+        }
+        self.frames += 1;
+        true
+    }
+
+    fn drop_oldest(&mut self) {
+        let mut header = [0; FRAME_HEADER_SIZE];
+        for byte in &mut header {
+            *byte = self.bytes.pop_front().unwrap_or(0);
+        }
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        for _ in 0..size {
+            self.bytes.pop_front();
+        }
+        self.frames -= 1;
+    }
+
+    /// Number of frames held
+    pub const fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Size of the frames held, in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Check if no frame was recorded
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Drop every frame
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.frames = 0;
+    }
+
+    /// Frames held, oldest first, to write after the header
+    ///
+    /// The ring wraps around, so the frames come in up to two parts.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        self.bytes.as_slices()
+    }
+
+    /// Header of a recording of the frames held, for a `width` x `height`
+    /// panel
+    ///
+    /// `sequence` numbers the recordings, so the latest can be told apart
+    /// from older ones kept in flash.
+    pub fn header(&self, sequence: u32, width: u16, height: u16) -> [u8; HEADER_SIZE] {
+        RecordingHeader {
+            sequence,
+            width,
+            height,
+            len: self.len() as u32,
+            codec: self.codec,
+        }
+        .encode()
+    }
+}
+
+/// Header of a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub sequence: u32,
+    pub width: u16,
+    pub height: u16,
+    /// Size of the frames after the header
+    pub len: u32,
+    pub codec: Codec,
+}
+
+impl RecordingHeader {
+    /// Parse the header at the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self, RecordingError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(RecordingError::NotFound)?;
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let codec = match read_u32(0) {
+            RECORDING_MAGIC => Codec::Rle,
+            HEATSHRINK_MAGIC => Codec::Heatshrink,
+            _ => return Err(RecordingError::NotFound),
+        };
+
+        Ok(Self {
+            sequence: read_u32(4),
+            width: u16::from_le_bytes([header[8], header[9]]),
+            height: u16::from_le_bytes([header[10], header[11]]),
+            len: read_u32(12),
+            codec,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        let magic = match self.codec {
+            Codec::Rle => RECORDING_MAGIC,
+            Codec::Heatshrink => HEATSHRINK_MAGIC,
+        };
+        header[0..4].copy_from_slice(&magic.to_le_bytes());
+        header[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+        header[8..10].copy_from_slice(&self.width.to_le_bytes());
+        header[10..12].copy_from_slice(&self.height.to_le_bytes());
+        header[12..16].copy_from_slice(&self.len.to_le_bytes());
+        header
+    }
+
+    /// Pixels of each frame
+    pub const fn pixels(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// Recording read back from flash
+pub struct Recording<'a> {
+    pub header: RecordingHeader,
+    data: &'a [u8],
+}
+
+impl<'a> Recording<'a> {
+    /// Parse a recording; `bytes` may go on past its end, e.g. a whole slot
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, RecordingError> {
+        let header = RecordingHeader::parse(bytes)?;
+        let data = bytes
+            .get(HEADER_SIZE..HEADER_SIZE + header.len as usize)
+            .ok_or(RecordingError::Truncated)?;
+        Ok(Self { header, data })
+    }
+
+    /// Frames of the recording, oldest first, stopping after an error
+    pub fn frames(&self) -> impl Iterator<Item = Result<RecordedFrame<'a>, RecordingError>> {
+        let mut rest = self.data;
+        let codec = self.header.codec;
+        core::iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            let frame = RecordedFrame::split(rest, codec);
+            rest = match frame {
+                Ok((_, tail)) => tail,
+                Err(_) => &[],
+            };
+            Some(frame.map(|(frame, _)| frame))
+        })
+    }
+}
+
+/// Frame of a [`Recording`]
+#[derive(Debug, Clone, Copy)]
+pub struct RecordedFrame<'a> {
+    /// When the frame was shown, in milliseconds since boot (wrapping)
+    pub timestamp_ms: u32,
+    data: &'a [u8],
+    codec: Codec,
+}
+
+impl<'a> RecordedFrame<'a> {
+    /// Split the first frame off `data`
+    fn split(data: &'a [u8], codec: Codec) -> Result<(Self, &'a [u8]), RecordingError> {
+        if data.len() < FRAME_HEADER_SIZE {
+            return Err(RecordingError::Truncated);
+        }
+        let (header, rest) = data.split_at(FRAME_HEADER_SIZE);
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if rest.len() < size {
+            return Err(RecordingError::Truncated);
+        }
+        let (frame, rest) = rest.split_at(size);
+        let frame = Self {
+            timestamp_ms: u32::from_le_bytes([header[0], header[1], header[2], header[3]]),
+            data: frame,
+            codec,
+        };
+        Ok((frame, rest))
+    }
+
+    /// Decode the frame into `pixels`, row-major RGB565
+    pub fn decode(&self, pixels: &mut [u16]) -> Result<(), RecordingError> {
+        Ok(self.codec.decode(self.data, pixels)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{RUN_SIZE, runs};
+
+    const PIXELS: usize = 64;
+
+    fn frame(seed: u16) -> [u16; PIXELS] {
+        // Long runs and single pixels
+        core::array::from_fn(|i| if i < 40 { seed } else { i as u16 * seed })
+    }
+
+    /// Recording of the ring, as it is laid out in flash
+    fn flatten<const BYTES: usize>(ring: &FrameRing<BYTES>, out: &mut [u8]) -> usize {
+        let (first, second) = ring.as_slices();
+        let len = HEADER_SIZE + first.len() + second.len();
+        out[..HEADER_SIZE].copy_from_slice(&ring.header(7, 8, 8));
+        out[HEADER_SIZE..HEADER_SIZE + first.len()].copy_from_slice(first);
+        out[HEADER_SIZE + first.len()..len].copy_from_slice(second);
+        len
+    }
+
+    #[test]
+    fn test_runs_round_trip() {
+        let pixels = frame(3);
+        let mut data = [0; PIXELS * RUN_SIZE];
+        let mut len = 0;
+        for (count, pixel) in runs(&pixels) {
+            let [low, high] = pixel.to_le_bytes();
+            data[len..len + RUN_SIZE].copy_from_slice(&[count, low, high]);
+            len += RUN_SIZE;
+        }
+        assert_eq!(len, encoded_size(&pixels));
+        assert_eq!(len, (1 + 24) * RUN_SIZE);
+
+        let mut decoded = [0; PIXELS];
+        decode_runs(&data[..len], &mut decoded).unwrap();
+        assert_eq!(decoded, pixels);
+
+        // Runs longer than a count are split
+        assert_eq!(encoded_size(&[0; 600]), 3 * RUN_SIZE);
+        let mut short = [0; PIXELS - 1];
+        assert_eq!(
+            decode_runs(&data[..len], &mut short),
+            Err(RecordingError::SizeMismatch)
+        );
+    }
+
+    #[test]
+    fn test_ring_keeps_latest_frames() {
+        let size = FRAME_HEADER_SIZE + encoded_size(&frame(1));
+        let mut ring: FrameRing<256> = FrameRing::new();
+        for (i, seed) in (1..=10).enumerate() {
+            assert!(ring.push(i as u32 * 16, &frame(seed)));
+        }
+        assert_eq!(ring.frames(), 256 / size);
+        assert_eq!(ring.len(), ring.frames() * size);
+
+        let mut bytes = [0; 512];
+        let len = flatten(&ring, &mut bytes);
+        let recording = Recording::parse(&bytes[..len]).unwrap();
+        assert_eq!(recording.header.sequence, 7);
+        assert_eq!(recording.header.pixels(), PIXELS);
+
+        let mut pixels = [0; PIXELS];
+        let mut timestamps = [0; 8];
+        let mut count = 0;
+        for recorded in recording.frames() {
+            let recorded = recorded.unwrap();
+            recorded.decode(&mut pixels).unwrap();
+            let seed = 11 - ring.frames() as u16 + count as u16;
+            assert_eq!(pixels, frame(seed));
+            timestamps[count] = recorded.timestamp_ms;
+            count += 1;
+        }
+        assert_eq!(count, ring.frames());
+        assert_eq!(timestamps[count - 1], 9 * 16);
+    }
+
+    #[test]
+    fn test_push_encoded_matches_push() {
+        let mut pushed: FrameRing<256> = FrameRing::with_codec(Codec::Heatshrink);
+        let mut encoded: FrameRing<256> = FrameRing::with_codec(Codec::Heatshrink);
+        let mut data = [0; PIXELS * RUN_SIZE];
+        for seed in 1..=6 {
+            let pixels = frame(seed);
+            let mut len = 0;
+            encoded.codec().encode(&pixels, |byte| {
+                data[len] = byte;
+                len += 1;
+            });
+            assert!(pushed.push(seed as u32, &pixels));
+            assert!(encoded.push_encoded(seed as u32, &data[..len]));
+        }
+        assert_eq!(encoded.frames(), pushed.frames());
+        assert_eq!(encoded.as_slices(), pushed.as_slices());
+
+        // Too large for the ring on its own
+        assert!(!encoded.push_encoded(0, &[0; 256]));
+        assert_eq!(encoded.frames(), pushed.frames());
+    }
+
+    #[test]
+    fn test_heatshrink_ring() {
+        let mut ring: FrameRing<256> = FrameRing::with_codec(Codec::Heatshrink);
+        for seed in 1..=3 {
+            assert!(ring.push(seed as u32, &frame(seed)));
+        }
+
+        let mut bytes = [0; 512];
+        let len = flatten(&ring, &mut bytes);
+        let recording = Recording::parse(&bytes[..len]).unwrap();
+        assert_eq!(recording.header.codec, Codec::Heatshrink);
+
+        let mut pixels = [0; PIXELS];
+        for (recorded, seed) in recording.frames().zip(1..) {
+            recorded.unwrap().decode(&mut pixels).unwrap();
+            assert_eq!(pixels, frame(seed));
+        }
+        assert_eq!(recording.frames().count(), ring.frames());
+    }
+
+    #[test]
+    fn test_bad_recordings() {
+        assert_eq!(
+            Recording::parse(&[0xFF; 64]).err(),
+            Some(RecordingError::NotFound)
+        );
+
+        let mut ring: FrameRing<256> = FrameRing::new();
+        // No run to merge, bigger than the whole ring
+        let noise: [u16; 128] = core::array::from_fn(|i| i as u16);
+        assert!(!ring.push(0, &noise));
+        assert!(ring.is_empty());
+
+        ring.push(0, &frame(1));
+        let mut bytes = [0; 512];
+        let len = flatten(&ring, &mut bytes);
+        assert_eq!(
+            Recording::parse(&bytes[..len - 1]).err(),
+            Some(RecordingError::Truncated)
+        );
+
+        // Header claiming more frame data than there is
+        bytes[HEADER_SIZE + 4] = 0xFF;
+        let recording = Recording::parse(&bytes[..len]).unwrap();
+        let mut frames = recording.frames();
+        assert_eq!(
+            frames.next().unwrap().err(),
+            Some(RecordingError::Truncated)
+        );
+        assert!(frames.next().is_none());
+    }
+}
@@ -0,0 +1,86 @@
+//! JSON Schema of the layout format
+//!
+//! Generated from the types the firmware deserializes, so the server and
+//! frontend can validate their payloads against what the panel actually
+//! accepts. The generated files are checked in under `schema/` at the root of
+//! the repository; run `cargo xtask schema` after changing a model to refresh
+//! them.
+
+#[cfg(feature = "bookings")]
+use crate::bookings::Bookings;
+use crate::models::{ClusterUpdate, Layout, PartialLayout};
+use crate::preferences::Preferences;
+use schemars::{Schema, schema_for};
+
+/// Builds one of the published schemas
+pub type SchemaFn = fn() -> Schema;
+
+/// Every published schema with the name of the file it is written to
+pub const SCHEMAS: &[(&str, SchemaFn)] = &[
+    ("layout.schema.json", layout_schema),
+    ("partial-layout.schema.json", partial_layout_schema),
+    ("cluster-update.schema.json", cluster_update_schema),
+    ("preferences.schema.json", preferences_schema),
+    #[cfg(feature = "bookings")]
+    ("bookings.schema.json", bookings_schema),
+];
+
+/// Schema of a full [`Layout`]
+pub fn layout_schema() -> Schema {
+    schema_for!(Layout)
+}
+
+/// Schema of a [`PartialLayout`], as returned for a subset of clusters
+pub fn partial_layout_schema() -> Schema {
+    schema_for!(PartialLayout)
+}
+
+/// Schema of a [`ClusterUpdate`] pushed for a single cluster
+pub fn cluster_update_schema() -> Schema {
+    schema_for!(ClusterUpdate)
+}
+
+/// Schema of the [`Preferences`] exchanged with the server
+pub fn preferences_schema() -> Schema {
+    schema_for!(Preferences)
+}
+
+/// Schema of the [`Bookings`] returned by the booking system
+#[cfg(feature = "bookings")]
+pub fn bookings_schema() -> Schema {
+    schema_for!(Bookings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::string::String;
+
+    #[test]
+    fn test_layout_schema_shape() {
+        let schema = layout_schema().to_value();
+        let required = schema["required"].as_array().unwrap();
+        for floor in ["f0", "f1", "f1b", "f2", "f4", "f6"] {
+            assert!(required.iter().any(|r| r == floor), "{floor} not required");
+        }
+
+        let seat = &schema["$defs"]["Seat"]["properties"];
+        assert!(seat["x"].is_object());
+        assert!(seat["status"].is_object());
+    }
+
+    #[test]
+    fn test_checked_in_schemas_up_to_date() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../schema");
+        for &(file, schema) in SCHEMAS {
+            let mut expected = serde_json::to_string_pretty(&schema()).unwrap();
+            expected.push('\n');
+            let actual = std::fs::read_to_string(dir.join(file)).unwrap_or_else(|_| String::new());
+            assert!(
+                actual == expected,
+                "schema/{file} is out of date, run `cargo xtask schema`"
+            );
+        }
+    }
+}
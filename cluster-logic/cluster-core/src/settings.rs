@@ -0,0 +1,159 @@
+//! Settings that can change while the panel runs
+//!
+//! Brightness, theme and floor assignment are each kept in a [`Watch`]:
+//! sources (the menu, preferences from the server) send a new value through
+//! [`Settings`], and the render loop picks up the latest ones at the start of
+//! each frame with [`SettingsReceiver::changes`]. Values sent before the
+//! render loop subscribed are not lost, a receiver sees the latest one on its
+//! first poll; values overwritten before a poll are.
+//!
+//! ```ignore
+//! static SETTINGS: Settings<CriticalSectionRawMutex, 2> = Settings::new();
+//!
+//! SETTINGS.set_brightness(128);
+//!
+//! let mut settings = SETTINGS.receiver().unwrap();
+//! loop {
+//!     if let Some(level) = settings.changes().brightness { ... }
+//! }
+//! ```
+
+use crate::preferences::{Preferences, Theme};
+use crate::types::ClusterId;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::watch::{Receiver, Watch};
+
+/// Every runtime setting, followed by up to `N` receivers
+pub struct Settings<M: RawMutex, const N: usize> {
+    /// Brightness while awake
+    brightness: Watch<M, u8, N>,
+    /// Color scheme
+    theme: Watch<M, Theme, N>,
+    /// Floor the panel shows
+    assignment: Watch<M, ClusterId, N>,
+}
+
+impl<M: RawMutex, const N: usize> Settings<M, N> {
+    /// Create settings with no value sent yet, usable in a `static`
+    pub const fn new() -> Self {
+        Self {
+            brightness: Watch::new(),
+            theme: Watch::new(),
+            assignment: Watch::new(),
+        }
+    }
+
+    pub fn set_brightness(&self, level: u8) {
+        self.brightness.sender().send(level);
+    }
+
+    pub fn set_theme(&self, theme: Theme) {
+        self.theme.sender().send(theme);
+    }
+
+    pub fn set_assignment(&self, cluster: ClusterId) {
+        self.assignment.sender().send(cluster);
+    }
+
+    /// Show the theme and floor of `preferences`
+    pub fn apply_preferences(&self, preferences: &Preferences) {
+        self.set_theme(preferences.theme);
+        self.set_assignment(preferences.default_floor);
+    }
+
+    /// Subscribe to every setting, `None` once `N` receivers exist
+    pub fn receiver(&self) -> Option<SettingsReceiver<'_, M, N>> {
+        Some(SettingsReceiver {
+            brightness: self.brightness.receiver()?,
+            theme: self.theme.receiver()?,
+            assignment: self.assignment.receiver()?,
+        })
+    }
+}
+
+impl<M: RawMutex, const N: usize> Default for Settings<M, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Settings sent since the last [`SettingsReceiver::changes`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SettingsChanges {
+    pub brightness: Option<u8>,
+    pub theme: Option<Theme>,
+    pub assignment: Option<ClusterId>,
+}
+
+/// One subscriber's view of [`Settings`]
+pub struct SettingsReceiver<'a, M: RawMutex, const N: usize> {
+    brightness: Receiver<'a, M, u8, N>,
+    theme: Receiver<'a, M, Theme, N>,
+    assignment: Receiver<'a, M, ClusterId, N>,
+}
+
+impl<M: RawMutex, const N: usize> SettingsReceiver<'_, M, N> {
+    /// Take the settings changed since the last call, without waiting
+    pub fn changes(&mut self) -> SettingsChanges {
+        SettingsChanges {
+            brightness: self.brightness.try_changed(),
+            theme: self.theme.try_changed(),
+            assignment: self.assignment.try_changed(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    #[test]
+    fn test_change_reaches_receiver_once() {
+        let settings = Settings::<NoopRawMutex, 2>::new();
+        let mut receiver = settings.receiver().unwrap();
+        assert_eq!(receiver.changes(), SettingsChanges::default());
+
+        settings.set_brightness(64);
+        settings.set_brightness(128);
+        assert_eq!(
+            receiver.changes(),
+            SettingsChanges {
+                brightness: Some(128),
+                ..SettingsChanges::default()
+            }
+        );
+        assert_eq!(receiver.changes(), SettingsChanges::default());
+    }
+
+    #[test]
+    fn test_value_sent_before_subscribing_is_seen() {
+        let settings = Settings::<NoopRawMutex, 1>::new();
+        settings.apply_preferences(&Preferences {
+            theme: Theme::Light,
+            default_floor: ClusterId::F2,
+        });
+
+        let mut receiver = settings.receiver().unwrap();
+        assert!(settings.receiver().is_none());
+        assert_eq!(
+            receiver.changes(),
+            SettingsChanges {
+                brightness: None,
+                theme: Some(Theme::Light),
+                assignment: Some(ClusterId::F2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_every_receiver_sees_the_change() {
+        let settings = Settings::<NoopRawMutex, 2>::new();
+        let mut render = settings.receiver().unwrap();
+        let mut other = settings.receiver().unwrap();
+
+        settings.set_theme(Theme::Light);
+        assert_eq!(render.changes().theme, Some(Theme::Light));
+        assert_eq!(other.changes().theme, Some(Theme::Light));
+    }
+}
@@ -0,0 +1,229 @@
+//! Smoothing of seat status changes between polls
+//!
+//! After a network gap the first polls often disagree: a cached response,
+//! then fresh data, then a seat that was briefly released is taken again.
+//! Applied as they come, seats flap between states for a poll or two.
+//! [`SeatSmoother`] sits between the poller and the shown [`Layout`]: a seat
+//! only shows a new status once it was seen on [`confirm_polls`] polls in a
+//! row. New seats, zones and messages show right away.
+//!
+//! Installations that prefer raw data use [`SeatSmoother::RAW`], which
+//! confirms every change on its first poll.
+//!
+//! [`confirm_polls`]: SeatSmoother::new
+
+use crate::models::{Cluster, Layout, PartialLayout};
+use crate::types::Status;
+
+type SeatStateVec = heapless::Vec<SeatState, { crate::constants::MAX_SEATS_PER_CLUSTER }>;
+
+/// Polls a change must be seen on by default
+pub const DEFAULT_CONFIRM_POLLS: u8 = 2;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct SeatState {
+    /// Status polled but not shown yet, with the polls it was seen on
+    pending: Option<(Status, u8)>,
+    /// When the shown status last changed, in milliseconds (wrapping)
+    changed_ms: u32,
+}
+
+/// State of the seats of every floor, in [`Layout::FLOORS`] order, each in
+/// the order of the shown cluster's seats
+pub struct SeatSmoother {
+    confirm_polls: u8,
+    floors: [SeatStateVec; 6],
+}
+
+impl Default for SeatSmoother {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONFIRM_POLLS)
+    }
+}
+
+impl SeatSmoother {
+    /// Every change shows on its first poll
+    pub const RAW: u8 = 1;
+
+    /// Show a seat's new status once polled `confirm_polls` times in a row
+    pub const fn new(confirm_polls: u8) -> Self {
+        Self {
+            confirm_polls: if confirm_polls == 0 { 1 } else { confirm_polls },
+            floors: [const { SeatStateVec::new() }; 6],
+        }
+    }
+
+    /// Polls a change must be seen on before it shows
+    pub const fn confirm_polls(&self) -> u8 {
+        self.confirm_polls
+    }
+
+    /// Replace the clusters present in `partial`, polled at `now_ms`,
+    /// holding back the seat changes not confirmed yet
+    ///
+    /// Takes the place of [`Layout::apply`].
+    pub fn apply(&mut self, layout: &mut Layout, mut partial: PartialLayout, now_ms: u64) {
+        for (states, id) in self.floors.iter_mut().zip(Layout::FLOORS) {
+            let (Some(mut cluster), Some(shown)) = (partial.take(id), layout.get_mut(id)) else {
+                continue;
+            };
+            *states = smooth(
+                states,
+                shown,
+                &mut cluster,
+                self.confirm_polls,
+                now_ms as u32,
+            );
+            *shown = cluster;
+        }
+    }
+
+    /// When the shown status of the seat at `index` of a floor last
+    /// changed, in milliseconds since boot (wrapping)
+    ///
+    /// Seats of the layout shown before the first poll count from 0.
+    pub fn changed_ms(&self, floor: usize, index: usize) -> Option<u32> {
+        Some(self.floors.get(floor)?.get(index)?.changed_ms)
+    }
+
+    /// Check if a polled change of the seat at `index` of a floor is still
+    /// held back
+    pub fn is_pending(&self, floor: usize, index: usize) -> bool {
+        self.floors
+            .get(floor)
+            .and_then(|states| states.get(index))
+            .is_some_and(|state| state.pending.is_some())
+    }
+}
+
+/// Hold back the unconfirmed changes of `polled` against `shown`, returning
+/// the seat states in `polled` order
+fn smooth(
+    states: &SeatStateVec,
+    shown: &Cluster,
+    polled: &mut Cluster,
+    confirm_polls: u8,
+    now_ms: u32,
+) -> SeatStateVec {
+    let mut next = SeatStateVec::new();
+    for (index, seat) in polled.seats.iter_mut().enumerate() {
+        let state = match shown.find_seat(index, &seat.id) {
+            // New seats have nothing to flap from
+            None => SeatState {
+                pending: None,
+                changed_ms: now_ms,
+            },
+            Some(old) => {
+                let current = shown.seats[old].status;
+                let mut state = states.get(old).copied().unwrap_or_default();
+                if seat.status == current {
+                    state.pending = None;
+                } else {
+                    let seen = match state.pending {
+                        Some((status, seen)) if status == seat.status => seen.saturating_add(1),
+                        _ => 1,
+                    };
+                    if seen >= confirm_polls {
+                        state = SeatState {
+                            pending: None,
+                            changed_ms: now_ms,
+                        };
+                    } else {
+                        state.pending = Some((seat.status, seen));
+                        seat.status = current;
+                    }
+                }
+                state
+            }
+        };
+        // Bounded by the same capacity as the seats
+        let _ = next.push(state);
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ClusterId, Kind};
+    use crate::{cluster, layout, seat};
+
+    const F0: usize = 0;
+
+    fn floor(statuses: [Status; 2]) -> Cluster {
+        cluster! {
+            message: "",
+            name: "F0",
+            attributes: [],
+            seats: [
+                seat!("f0r1s1", Kind::Mac, statuses[0], 0, 0),
+                seat!("f0r1s2", Kind::Mac, statuses[1], 4, 0)
+            ],
+            zones: []
+        }
+    }
+
+    fn poll(statuses: [Status; 2]) -> PartialLayout {
+        PartialLayout {
+            f0: Some(floor(statuses)),
+            ..PartialLayout::default()
+        }
+    }
+
+    fn layout() -> Layout {
+        layout! { f0: floor([Status::Free, Status::Free]) }
+    }
+
+    fn shown(layout: &Layout) -> [Status; 2] {
+        let seats = &layout.get(ClusterId::F0).unwrap().seats;
+        [seats[0].status, seats[1].status]
+    }
+
+    #[test]
+    fn test_flapping_is_suppressed() {
+        use Status::{Free, Taken};
+        let mut layout = layout();
+        let mut smoother = SeatSmoother::default();
+
+        // A one-poll blip never shows
+        smoother.apply(&mut layout, poll([Taken, Free]), 1000);
+        assert_eq!(shown(&layout), [Free, Free]);
+        assert!(smoother.is_pending(F0, 0));
+        smoother.apply(&mut layout, poll([Free, Free]), 2000);
+        assert_eq!(shown(&layout), [Free, Free]);
+        assert!(!smoother.is_pending(F0, 0));
+
+        // A change seen twice does
+        smoother.apply(&mut layout, poll([Free, Taken]), 3000);
+        smoother.apply(&mut layout, poll([Free, Taken]), 4000);
+        assert_eq!(shown(&layout), [Free, Taken]);
+        assert_eq!(smoother.changed_ms(F0, 1), Some(4000));
+        assert_eq!(smoother.changed_ms(F0, 0), Some(0));
+    }
+
+    #[test]
+    fn test_seats_followed_by_id() {
+        let mut layout = layout();
+        let mut smoother = SeatSmoother::default();
+
+        smoother.apply(&mut layout, poll([Status::Taken, Status::Free]), 1000);
+        // Same seats, reordered
+        let mut partial = poll([Status::Taken, Status::Free]);
+        partial.f0.as_mut().unwrap().seats.reverse();
+        smoother.apply(&mut layout, partial, 2000);
+        let seats = &layout.f0.seats;
+        assert_eq!(
+            (&seats[1].id[..], seats[1].status),
+            ("f0r1s1", Status::Taken)
+        );
+        assert_eq!(smoother.changed_ms(F0, 1), Some(2000));
+    }
+
+    #[test]
+    fn test_raw_data() {
+        let mut layout = layout();
+        let mut smoother = SeatSmoother::new(SeatSmoother::RAW);
+        smoother.apply(&mut layout, poll([Status::Broken, Status::Free]), 1000);
+        assert_eq!(shown(&layout), [Status::Broken, Status::Free]);
+    }
+}
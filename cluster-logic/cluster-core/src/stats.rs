@@ -0,0 +1,200 @@
+//! Occupancy statistics over the last polls
+//!
+//! [`OccupancyHistory`] keeps the occupancy percentage of every displayed
+//! cluster at each of the last `N` polls, oldest first. Unlike
+//! [`TrendTracker`](crate::trend::TrendTracker), which samples the selected
+//! cluster on a clock, it records whatever the poller brings, so a floor
+//! that wasn't part of a partial response keeps its history untouched.
+//!
+//! ```
+//! # use cluster_core::stats::OccupancyHistory;
+//! # use cluster_core::types::ClusterId;
+//! let mut history: OccupancyHistory<8> = OccupancyHistory::new();
+//! for occupancy in [20, 40, 30] {
+//!     history.record_cluster(ClusterId::F1, occupancy);
+//! }
+//!
+//! let summary = history.summary(ClusterId::F1).unwrap();
+//! assert_eq!((summary.min, summary.max, summary.avg), (20, 40, 30));
+//! assert!(history.sparkline(ClusterId::F1, 3).eq([0, 2, 1]));
+//! ```
+//!
+//! [`samples`](OccupancyHistory::samples) feeds a
+//! [`HistoryGraph`](crate::visualization::history::HistoryGraph) directly.
+
+use crate::models::{Layout, PartialLayout};
+use crate::types::ClusterId;
+use heapless::HistoryBuf;
+
+/// Summary of a cluster's occupancy over the recorded polls, in percent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OccupancySummary {
+    pub min: u8,
+    pub max: u8,
+    /// Mean, rounded to the nearest percent
+    pub avg: u8,
+    pub latest: u8,
+    /// Number of polls recorded
+    pub polls: usize,
+}
+
+/// Occupancy percentages of every displayed cluster over the last `N` polls
+pub struct OccupancyHistory<const N: usize> {
+    /// In [`Layout::FLOORS`] order
+    floors: [HistoryBuf<u8, N>; 6],
+}
+
+impl<const N: usize> Default for OccupancyHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> OccupancyHistory<N> {
+    pub const fn new() -> Self {
+        Self {
+            floors: [const { HistoryBuf::new() }; 6],
+        }
+    }
+
+    /// Record the occupancy of every cluster of a polled layout
+    pub fn record(&mut self, layout: &Layout) {
+        for (id, cluster) in layout.clusters() {
+            self.record_cluster(id, cluster.get_stats().occupancy_percentage());
+        }
+    }
+
+    /// Record the occupancy of the clusters present in a partial response
+    pub fn record_partial(&mut self, partial: &PartialLayout) {
+        for id in Layout::FLOORS {
+            if let Some(cluster) = partial.get(id) {
+                self.record_cluster(id, cluster.get_stats().occupancy_percentage());
+            }
+        }
+    }
+
+    /// Record a poll of one cluster, clamped to 100%
+    pub fn record_cluster(&mut self, id: ClusterId, occupancy: u8) {
+        if let Some(floor) = self.floor_mut(id) {
+            floor.write(occupancy.min(100));
+        }
+    }
+
+    /// Occupancy of a cluster at each recorded poll, oldest first
+    pub fn samples(&self, id: ClusterId) -> impl Iterator<Item = u8> + Clone + '_ {
+        self.floor(id)
+            .into_iter()
+            .flat_map(|floor| floor.oldest_ordered().copied())
+    }
+
+    /// Min, max and average occupancy of a cluster, `None` before its
+    /// first poll
+    pub fn summary(&self, id: ClusterId) -> Option<OccupancySummary> {
+        let floor = self.floor(id)?;
+        let latest = *floor.recent()?;
+        let (min, max, sum) = floor
+            .as_slice()
+            .iter()
+            .fold((u8::MAX, 0, 0u32), |(min, max, sum), &x| {
+                (min.min(x), max.max(x), sum + u32::from(x))
+            });
+        let polls = floor.len();
+        Some(OccupancySummary {
+            min,
+            max,
+            avg: ((sum + polls as u32 / 2) / polls as u32) as u8,
+            latest,
+            polls,
+        })
+    }
+
+    /// Samples of a cluster scaled between its min and max to levels
+    /// `0..levels`, oldest first, e.g. to pick one of 8 block characters
+    ///
+    /// A flat history sits on level 0.
+    pub fn sparkline(&self, id: ClusterId, levels: u8) -> impl Iterator<Item = u8> + Clone + '_ {
+        let (min, max) = self
+            .summary(id)
+            .map_or((0, 0), |summary| (summary.min, summary.max));
+        let top = u16::from(levels.saturating_sub(1));
+        let range = u16::from(max - min).max(1);
+        self.samples(id).map(move |sample| {
+            let level = (u16::from(sample - min) * top + range / 2) / range;
+            level as u8
+        })
+    }
+
+    /// Drop every recorded poll
+    pub fn clear(&mut self) {
+        self.floors.iter_mut().for_each(|floor| floor.clear());
+    }
+
+    fn floor(&self, id: ClusterId) -> Option<&HistoryBuf<u8, N>> {
+        let index = Layout::FLOORS.iter().position(|&floor| floor == id)?;
+        Some(&self.floors[index])
+    }
+
+    fn floor_mut(&mut self, id: ClusterId) -> Option<&mut HistoryBuf<u8, N>> {
+        let index = Layout::FLOORS.iter().position(|&floor| floor == id)?;
+        Some(&mut self.floors[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Kind, Status};
+    use crate::{cluster, seat};
+
+    #[test]
+    fn test_ring_keeps_last_polls() {
+        let mut history: OccupancyHistory<3> = OccupancyHistory::new();
+        for occupancy in [10, 50, 20, 150] {
+            history.record_cluster(ClusterId::F2, occupancy);
+        }
+        history.record_cluster(ClusterId::Hidden, 40);
+
+        assert!(history.samples(ClusterId::F2).eq([50, 20, 100]));
+        assert_eq!(
+            history.summary(ClusterId::F2),
+            Some(OccupancySummary {
+                min: 20,
+                max: 100,
+                avg: 57,
+                latest: 100,
+                polls: 3,
+            })
+        );
+        assert_eq!(history.summary(ClusterId::F0), None);
+        assert_eq!(history.samples(ClusterId::Hidden).count(), 0);
+
+        assert!(history.sparkline(ClusterId::F2, 8).eq([3, 0, 7]));
+        history.clear();
+        assert_eq!(history.sparkline(ClusterId::F2, 8).count(), 0);
+    }
+
+    #[test]
+    fn test_partial_polls() {
+        let mut history: OccupancyHistory<4> = OccupancyHistory::new();
+        let f1 = cluster! {
+            message: "",
+            name: "F1",
+            attributes: [],
+            seats: [
+                seat!("f1r1s1", Kind::Mac, Status::Taken, 0, 0),
+                seat!("f1r1s2", Kind::Mac, Status::Free, 4, 0)
+            ],
+            zones: []
+        };
+        let partial = PartialLayout {
+            f1: Some(f1),
+            ..PartialLayout::default()
+        };
+        history.record_partial(&partial);
+        history.record_partial(&partial);
+
+        assert!(history.samples(ClusterId::F1).eq([50, 50]));
+        assert!(history.sparkline(ClusterId::F1, 8).eq([0, 0]));
+        assert_eq!(history.samples(ClusterId::F0).count(), 0);
+    }
+}
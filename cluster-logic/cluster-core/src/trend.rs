@@ -0,0 +1,98 @@
+//! Occupancy trend sampling
+//!
+//! [`TrendTracker`] keeps a fixed number of occupancy samples taken at a
+//! regular interval, oldest first, for history graphs and trend indicators.
+
+use heapless::HistoryBuf;
+
+/// Ring buffer of occupancy samples taken every `interval_ms`
+pub struct TrendTracker<const N: usize> {
+    samples: HistoryBuf<u8, N>,
+    interval_ms: u64,
+    last_sample_ms: Option<u64>,
+}
+
+impl<const N: usize> TrendTracker<N> {
+    /// Create an empty tracker sampling at most once per `interval_ms`
+    pub const fn new(interval_ms: u64) -> Self {
+        Self {
+            samples: HistoryBuf::new(),
+            interval_ms,
+            last_sample_ms: None,
+        }
+    }
+
+    /// Record an occupancy percentage if the sampling interval has elapsed
+    ///
+    /// Returns `true` if the sample was stored.
+    pub fn record(&mut self, now_ms: u64, occupancy: u8) -> bool {
+        if let Some(last) = self.last_sample_ms
+            && now_ms.saturating_sub(last) < self.interval_ms
+        {
+            return false;
+        }
+
+        self.samples.write(occupancy.min(100));
+        self.last_sample_ms = Some(now_ms);
+        true
+    }
+
+    /// Iterate over the samples, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = u8> + Clone + '_ {
+        self.samples.oldest_ordered().copied()
+    }
+
+    /// Get the most recent sample
+    pub fn latest(&self) -> Option<u8> {
+        self.samples.recent().copied()
+    }
+
+    /// Get the change between the oldest and the most recent sample
+    pub fn delta(&self) -> i16 {
+        match (self.samples.oldest(), self.samples.recent()) {
+            (Some(&oldest), Some(&recent)) => i16::from(recent) - i16::from(oldest),
+            _ => 0,
+        }
+    }
+
+    /// Get the number of stored samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Check if no sample has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Get the sampling interval in milliseconds
+    pub const fn interval_ms(&self) -> u64 {
+        self.interval_ms
+    }
+
+    /// Drop all samples
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.last_sample_ms = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_interval_and_order() {
+        let mut trend: TrendTracker<3> = TrendTracker::new(1_000);
+        assert!(trend.record(0, 10));
+        assert!(!trend.record(500, 99));
+        assert!(trend.record(1_000, 20));
+        assert!(trend.record(2_000, 30));
+        assert!(trend.record(3_000, 150));
+
+        // Oldest sample dropped, values clamped to 100%
+        assert!(trend.samples().eq([20, 30, 100]));
+        assert_eq!(trend.latest(), Some(100));
+        assert_eq!(trend.delta(), 80);
+    }
+}
@@ -20,6 +20,11 @@ pub type SeatId = std::string::String;
 #[cfg(not(feature = "std"))]
 pub type SeatId = heapless::String<{ crate::constants::MAX_SEAT_ID_LENGTH }>;
 
+#[cfg(feature = "std")]
+pub type SeatLabel = std::string::String;
+#[cfg(not(feature = "std"))]
+pub type SeatLabel = heapless::String<{ crate::constants::MAX_SEAT_LABEL_LENGTH }>;
+
 #[doc = r" Error types."]
 pub mod error {
     #[cfg(feature = "std")]
@@ -82,8 +87,94 @@ pub mod error {
     }
 }
 
+/// Lossy conversion of server text into the bounded string types
+///
+/// Without `std`, a name or message longer than its `heapless` capacity
+/// fails deserialization, and with it the whole cluster. The
+/// `lossy-strings` feature uses [`lossy::deserialize`] on those fields
+/// instead, which cuts the text at a char boundary and ends it with
+/// [`lossy::ELLIPSIS`].
+pub mod lossy {
+    use core::fmt;
+    use serde::de::{Deserializer, Error, Visitor};
+
+    /// Appended to truncated text, ASCII as the panel fonts are
+    pub const ELLIPSIS: &str = "...";
+
+    /// Longest prefix of `s` to keep so it fits in `max_len` bytes once
+    /// ended with [`ELLIPSIS`], or `s` itself if it already fits
+    ///
+    /// Returns whether the text was cut. Below the length of the ellipsis,
+    /// the text is cut without one.
+    #[must_use]
+    pub fn truncate(s: &str, max_len: usize) -> (&str, bool) {
+        if s.len() <= max_len {
+            return (s, false);
+        }
+        let budget = if max_len > ELLIPSIS.len() {
+            max_len - ELLIPSIS.len()
+        } else {
+            max_len
+        };
+        let end = (0..=budget)
+            .rev()
+            .find(|&i| s.is_char_boundary(i))
+            .unwrap_or(0);
+        (&s[..end], true)
+    }
+
+    /// String built from text of any length, truncating what doesn't fit
+    pub trait FromStrLossy: Sized {
+        fn from_str_lossy(s: &str) -> Self;
+    }
+
+    impl<const N: usize> FromStrLossy for heapless::String<N> {
+        fn from_str_lossy(s: &str) -> Self {
+            let (kept, truncated) = truncate(s, N);
+            let mut out = Self::new();
+            // Both fit by construction of `truncate`
+            let _ = out.push_str(kept);
+            if truncated && N > ELLIPSIS.len() {
+                let _ = out.push_str(ELLIPSIS);
+            }
+            out
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl FromStrLossy for std::string::String {
+        fn from_str_lossy(s: &str) -> Self {
+            s.into()
+        }
+    }
+
+    /// `deserialize_with` target for [`FromStrLossy`] fields
+    pub fn deserialize<'de, D, S>(deserializer: D) -> Result<S, D::Error>
+    where
+        D: Deserializer<'de>,
+        S: FromStrLossy,
+    {
+        struct LossyVisitor<S>(core::marker::PhantomData<S>);
+
+        impl<S: FromStrLossy> Visitor<'_> for LossyVisitor<S> {
+            type Value = S;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<S, E> {
+                Ok(S::from_str_lossy(v))
+            }
+        }
+
+        deserializer.deserialize_str(LossyVisitor(core::marker::PhantomData))
+    }
+}
+
 #[doc = "`Attribute`"]
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Attribute {
     Piscine,
@@ -148,6 +239,7 @@ impl_enum_conversions!(
 
 #[doc = "`Kind`"]
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Kind {
     Mac,
@@ -166,6 +258,7 @@ impl_enum_conversions!(
 
 #[doc = "`Status`"]
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
     Free,
@@ -197,6 +290,7 @@ impl_enum_conversions!(
 
 #[doc = "`ClusterId`"]
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum ClusterId {
     Hidden,
@@ -254,3 +348,47 @@ impl Kind {
 pub type AttributeVec = heapless::Vec<Attribute, { crate::constants::MAX_ATTRIBUTES }>;
 #[cfg(feature = "std")]
 pub type AttributeVec = std::vec::Vec<Attribute>;
+
+#[cfg(test)]
+mod tests {
+    use super::lossy::{FromStrLossy, truncate};
+
+    #[test]
+    fn test_truncate_at_char_boundary() {
+        assert_eq!(truncate("hello", 5), ("hello", false));
+        assert_eq!(truncate("hello world", 8), ("hello", true));
+        // "é" is two bytes, cutting after its first one would split it
+        assert_eq!(truncate("caféine", 7), ("caf", true));
+        assert_eq!(truncate("🚀🚀", 6), ("", true));
+        assert_eq!(truncate("abcdef", 2), ("ab", true));
+    }
+
+    #[test]
+    fn test_heapless_from_str_lossy() {
+        let s = heapless::String::<8>::from_str_lossy("hello world");
+        assert_eq!(s.as_str(), "hello...");
+        let s = heapless::String::<8>::from_str_lossy("hi");
+        assert_eq!(s.as_str(), "hi");
+        let s = heapless::String::<2>::from_str_lossy("hello");
+        assert_eq!(s.as_str(), "he");
+    }
+
+    #[cfg(all(feature = "lossy-strings", not(feature = "std")))]
+    #[test]
+    fn test_long_message_degrades() {
+        use crate::constants::MAX_MESSAGE_LENGTH;
+        use crate::models::Cluster;
+
+        let mut json = heapless::String::<512>::new();
+        json.push_str(r#"{"message":""#).unwrap();
+        for _ in 0..MAX_MESSAGE_LENGTH {
+            json.push('é').unwrap();
+        }
+        json.push_str(r#"","attributes":[],"name":"f1b2c3","seats":[],"zones":[]}"#)
+            .unwrap();
+        let cluster: Cluster = serde_json::from_str(&json).unwrap();
+        assert!(cluster.message.ends_with("..."));
+        assert!(cluster.message.len() <= MAX_MESSAGE_LENGTH);
+        assert_eq!(cluster.name.as_str(), "f...");
+    }
+}
@@ -11,9 +11,15 @@
 /// use cluster_core::{seat, types::{Kind, Status}};
 ///
 /// let s = seat!("f0r1s1", Kind::Mac, Status::Free, 0, 0);
+/// let staff = seat!("f0r1s2", Kind::Mac, Status::Taken, 3, 0, label: "JD");
 /// ```
 #[macro_export]
 macro_rules! seat {
+    ($id:expr, $kind:expr, $status:expr, $x:expr, $y:expr, label: $label:expr) => {{
+        let mut seat = $crate::seat!($id, $kind, $status, $x, $y);
+        seat.label = Some($label.try_into().expect("Invalid seat label"));
+        seat
+    }};
     ($id:expr, $kind:expr, $status:expr, $x:expr, $y:expr) => {
         $crate::models::Seat {
             id: $id.try_into().expect("Invalid seat ID"),
@@ -21,6 +27,7 @@ macro_rules! seat {
             status: $status,
             x: $x,
             y: $y,
+            label: None,
         }
     };
 }
@@ -103,6 +110,7 @@ macro_rules! cluster {
             },
             seats: {
                 use $crate::models::SeatVec;
+                #[allow(unused_mut)]
                 let mut seats = SeatVec::new();
                 $(
                     #[allow(unused_must_use)]
@@ -195,6 +203,9 @@ macro_rules! empty_cluster {
 
 /// Create a layout from the given clusters
 ///
+/// With only `f0` given, the other floors are empty, which is what most
+/// tests and examples need.
+///
 /// # Example
 /// ```no_run
 /// use cluster_core::{layout, empty_cluster, cluster, seat, types::{Kind, Status}};
@@ -213,6 +224,7 @@ macro_rules! empty_cluster {
 ///     f4: empty_cluster!("F4"),
 ///     f6: empty_cluster!("F6")
 /// };
+/// let ground_floor_only = layout! { f0: empty_cluster!("F0") };
 /// ```
 #[macro_export]
 macro_rules! layout {
@@ -233,6 +245,16 @@ macro_rules! layout {
             f6: $f6,
         }
     };
+    { f0: $f0:expr $(,)? } => {
+        $crate::layout! {
+            f0: $f0,
+            f1: $crate::empty_cluster!("F1"),
+            f1b: $crate::empty_cluster!("F1B"),
+            f2: $crate::empty_cluster!("F2"),
+            f4: $crate::empty_cluster!("F4"),
+            f6: $crate::empty_cluster!("F6")
+        }
+    };
 }
 
 /// Generate multiple seats with a pattern
@@ -289,6 +311,7 @@ macro_rules! seats {
                     status,
                     x: *x,
                     y: *y,
+                    label: None,
                 };
 
                 // Use the appropriate push method based on the vector type
@@ -328,6 +351,7 @@ macro_rules! seats {
                     status: $status,
                     x: *x,
                     y: *y,
+                    label: None,
                 };
 
                 // Use the appropriate push method based on the vector type
@@ -456,6 +480,10 @@ mod tests {
         assert_eq!(l.f1.name, "F1");
         assert_eq!(l.f0.seats.len(), 1);
         assert_eq!(l.f1.seats.len(), 0);
+
+        let l = layout! { f0: empty_cluster!("F0") };
+        assert_eq!(l.f1b.name, "F1B");
+        assert_eq!(l.f6.name, "F6");
     }
 
     #[test]
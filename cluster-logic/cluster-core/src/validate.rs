@@ -0,0 +1,248 @@
+//! Semantic layout checks
+//!
+//! Deserializing a layout only checks its shape. [`validate_layout`] catches
+//! the mistakes that otherwise only show up on the panel: duplicate seat IDs,
+//! two seats on the same position, positions outside the grid and unnamed
+//! zones. `layout_from_json!` runs it at compile time.
+
+use crate::index::ClusterIndex;
+use crate::models::{Cluster, Layout};
+use crate::types::ClusterId;
+
+/// Largest seat or zone position accepted, exclusive
+///
+/// Defaults to one grid cell per panel pixel; layouts beyond that can't be
+/// told apart on the panel anyway.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridBounds {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GridBounds {
+    pub const DEFAULT: Self = Self::new(
+        crate::visualization::display::DISPLAY_WIDTH as usize,
+        crate::visualization::display::DISPLAY_HEIGHT as usize,
+    );
+
+    pub const fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+}
+
+impl Default for GridBounds {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A problem found in a layout
+///
+/// Entries are referred to by cluster and index in the cluster's `seats` or
+/// `zones` list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutIssue {
+    /// Seat ID already used by an earlier seat, possibly in another cluster
+    DuplicateSeatId {
+        cluster: ClusterId,
+        seat: usize,
+        first: (ClusterId, usize),
+    },
+    /// Seat placed on the same position as an earlier seat of the cluster
+    OverlappingSeats {
+        cluster: ClusterId,
+        seat: usize,
+        other: usize,
+    },
+    /// Seat position outside the grid
+    SeatOutOfGrid { cluster: ClusterId, seat: usize },
+    /// Zone position outside the grid
+    ZoneOutOfGrid { cluster: ClusterId, zone: usize },
+    /// Zone with an empty name and no attributes to show instead
+    UnnamedZone { cluster: ClusterId, zone: usize },
+}
+
+/// Check a layout, reporting each issue to `report`
+///
+/// Returns the number of issues found.
+pub fn validate_layout(
+    layout: &Layout,
+    bounds: GridBounds,
+    mut report: impl FnMut(LayoutIssue),
+) -> usize {
+    let clusters = layout.clusters();
+    let mut count = 0;
+    let mut report = |issue| {
+        count += 1;
+        report(issue);
+    };
+
+    for (c, &(id, cluster)) in clusters.iter().enumerate() {
+        validate_cluster(id, cluster, bounds, &mut report);
+
+        // Seat IDs must be unique across the whole layout
+        let index = ClusterIndex::new(cluster);
+        for (s, seat) in cluster.seats.iter().enumerate() {
+            let earlier = clusters[..c].iter().find_map(|&(other_id, other)| {
+                other
+                    .seat_index_by_id(&seat.id)
+                    .map(|index| (other_id, index))
+            });
+            let first = earlier.or_else(|| {
+                index
+                    .seat_index_by_id(cluster, &seat.id)
+                    .filter(|&first| first < s)
+                    .map(|first| (id, first))
+            });
+            if let Some(first) = first {
+                report(LayoutIssue::DuplicateSeatId {
+                    cluster: id,
+                    seat: s,
+                    first,
+                });
+            }
+        }
+    }
+
+    count
+}
+
+/// Check the positions and zones of a single cluster
+pub fn validate_cluster(
+    id: ClusterId,
+    cluster: &Cluster,
+    bounds: GridBounds,
+    mut report: impl FnMut(LayoutIssue),
+) {
+    let index = ClusterIndex::new(cluster);
+    for (s, seat) in cluster.seats.iter().enumerate() {
+        if !bounds.contains(seat.x, seat.y) {
+            report(LayoutIssue::SeatOutOfGrid {
+                cluster: id,
+                seat: s,
+            });
+        }
+        if let Some(other) = index
+            .seat_index_at(cluster, seat.x, seat.y)
+            .filter(|&other| other < s)
+        {
+            report(LayoutIssue::OverlappingSeats {
+                cluster: id,
+                seat: s,
+                other,
+            });
+        }
+    }
+
+    for (z, zone) in cluster.zones.iter().enumerate() {
+        if !bounds.contains(zone.x, zone.y) {
+            report(LayoutIssue::ZoneOutOfGrid {
+                cluster: id,
+                zone: z,
+            });
+        }
+        if zone.name.is_empty() && zone.attributes.is_empty() {
+            report(LayoutIssue::UnnamedZone {
+                cluster: id,
+                zone: z,
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::types::{Attribute, Kind, Status};
+    use crate::{cluster, empty_cluster, layout, seat, zone};
+    use std::vec::Vec;
+
+    fn layout(f0: Cluster, f1: Cluster) -> Layout {
+        Layout {
+            f1,
+            ..layout! { f0: f0 }
+        }
+    }
+
+    fn issues(layout: &Layout, bounds: GridBounds) -> Vec<LayoutIssue> {
+        let mut issues = Vec::new();
+        let count = validate_layout(layout, bounds, |issue| issues.push(issue));
+        assert_eq!(count, issues.len());
+        issues
+    }
+
+    #[test]
+    fn test_valid_layout() {
+        let f0 = cluster! {
+            message: "",
+            name: "F0",
+            attributes: [],
+            seats: [
+                seat!("f0r1s1", Kind::Mac, Status::Free, 0, 0),
+                seat!("f0r1s2", Kind::Mac, Status::Free, 3, 1)
+            ],
+            zones: [
+                zone!("Z1", [], 0, 0),
+                zone!("", [Attribute::Silent], 3, 0)
+            ]
+        };
+        assert!(issues(&layout(f0, empty_cluster!("F1")), GridBounds::DEFAULT).is_empty());
+    }
+
+    #[test]
+    fn test_reported_issues() {
+        let f0 = cluster! {
+            message: "",
+            name: "F0",
+            attributes: [],
+            seats: [
+                seat!("s1", Kind::Mac, Status::Free, 0, 0),
+                seat!("s2", Kind::Mac, Status::Free, 0, 0),
+                seat!("s3", Kind::Mac, Status::Free, 10, 0)
+            ],
+            zones: [
+                zone!("", [], 1, 1),
+                zone!("Z2", [], 1, 8)
+            ]
+        };
+        let f1 = cluster! {
+            message: "",
+            name: "F1",
+            attributes: [],
+            seats: [seat!("s2", Kind::Dell, Status::Free, 0, 0)],
+            zones: []
+        };
+
+        assert_eq!(
+            issues(&layout(f0, f1), GridBounds::new(8, 8)),
+            [
+                LayoutIssue::OverlappingSeats {
+                    cluster: ClusterId::F0,
+                    seat: 1,
+                    other: 0,
+                },
+                LayoutIssue::SeatOutOfGrid {
+                    cluster: ClusterId::F0,
+                    seat: 2,
+                },
+                LayoutIssue::UnnamedZone {
+                    cluster: ClusterId::F0,
+                    zone: 0,
+                },
+                LayoutIssue::ZoneOutOfGrid {
+                    cluster: ClusterId::F0,
+                    zone: 1,
+                },
+                LayoutIssue::DuplicateSeatId {
+                    cluster: ClusterId::F1,
+                    seat: 0,
+                    first: (ClusterId::F0, 1),
+                },
+            ]
+        );
+    }
+}
@@ -1,13 +1,29 @@
 //! Cluster visualization system
 
+pub mod alert;
+pub mod cache;
 pub mod display;
+pub mod grid;
+pub mod history;
+pub mod regions;
 pub mod renderer;
+pub mod rows;
+pub mod view;
+pub mod zoom;
 
 // Re-export commonly used types for convenience
 use crate::models::Layout;
+pub use alert::{AlertThresholds, OccupancyAlerts};
+pub use cache::{BackgroundCache, BackgroundSurface, Surface};
 pub use display::{DEFAULT_LAYOUT, DisplayLayout};
 use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+pub use grid::{CellScale, GridSpace, Orientation};
+pub use history::{GraphStyle, HistoryGraph};
+pub use regions::{ClaimError, ProducerId, RegionMap};
 pub use renderer::ClusterRenderer;
+pub use rows::{RowSummary, SeatDensity};
+pub use view::{AnimationView, ClusterView, HistoryView, RenderCtx, Renderer};
+pub use zoom::{MapZoom, Zoom};
 
 /// Draw a cluster visualization frame
 pub fn draw_cluster_frame<D>(display: &mut D, layout: &Layout, frame: u32) -> Result<(), D::Error>
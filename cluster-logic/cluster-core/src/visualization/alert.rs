@@ -0,0 +1,147 @@
+//! Occupancy alerts for nearly full floors
+//!
+//! [`OccupancyAlerts`] watches the occupancy of every floor and raises an
+//! alert once it reaches a threshold. The alert only clears after occupancy
+//! drops below a lower threshold, so a floor hovering around the limit does
+//! not make the overlay flap.
+
+use crate::models::Layout;
+use crate::types::ClusterId;
+
+/// Thresholds at which an occupancy alert is raised and cleared
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlertThresholds {
+    /// Occupancy percentage at or above which the alert is raised
+    pub raise_percent: u8,
+    /// Occupancy percentage below which a raised alert clears
+    pub clear_percent: u8,
+}
+
+impl AlertThresholds {
+    /// Raise at 95%, clear below 90%
+    pub const DEFAULT: Self = Self::new(95, 90);
+
+    /// Create thresholds; `clear_percent` is capped to `raise_percent`
+    pub const fn new(raise_percent: u8, clear_percent: u8) -> Self {
+        Self {
+            raise_percent,
+            clear_percent: if clear_percent > raise_percent {
+                raise_percent
+            } else {
+                clear_percent
+            },
+        }
+    }
+
+    /// Compute the next alert state for an occupancy percentage
+    pub const fn next_state(&self, active: bool, occupancy: u8) -> bool {
+        if active {
+            occupancy >= self.clear_percent
+        } else {
+            occupancy >= self.raise_percent
+        }
+    }
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Alert state of every floor, with hysteresis
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OccupancyAlerts {
+    thresholds: AlertThresholds,
+    active: [bool; 6],
+}
+
+impl OccupancyAlerts {
+    /// Create alerts with no floor alerting
+    pub const fn new(thresholds: AlertThresholds) -> Self {
+        Self {
+            thresholds,
+            active: [false; 6],
+        }
+    }
+
+    /// Change the thresholds, keeping the current alert states
+    pub const fn set_thresholds(&mut self, thresholds: AlertThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Update every floor from fresh layout data
+    ///
+    /// Returns the highest alerting floor, if any.
+    pub fn update(&mut self, layout: &Layout) -> Option<ClusterId> {
+        let mut alerting = None;
+        for (active, (id, cluster)) in self.active.iter_mut().zip(layout.clusters()) {
+            let occupancy = cluster.get_stats().occupancy_percentage();
+            let next = self.thresholds.next_state(*active, occupancy);
+
+            *active = next;
+            if next {
+                alerting = Some(id);
+            }
+        }
+        alerting
+    }
+
+    /// Check if `cluster` is currently alerting
+    pub fn is_active(&self, cluster: ClusterId) -> bool {
+        Layout::FLOORS
+            .iter()
+            .position(|&id| id == cluster)
+            .is_some_and(|index| self.active[index])
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::types::{Kind, Status};
+    use crate::{empty_cluster, layout, seat};
+
+    fn layout_with_taken(taken: usize) -> Layout {
+        let mut layout = layout! { f0: empty_cluster!("F0") };
+        for i in 0..20 {
+            let status = if i < taken {
+                Status::Taken
+            } else {
+                Status::Free
+            };
+            layout
+                .f2
+                .seats
+                .push(seat!("f2r1s1", Kind::Mac, status, i, 0));
+        }
+        layout
+    }
+
+    #[test]
+    fn test_thresholds_hysteresis() {
+        let thresholds = AlertThresholds::new(95, 90);
+        assert!(!thresholds.next_state(false, 94));
+        assert!(thresholds.next_state(false, 95));
+        assert!(thresholds.next_state(true, 90));
+        assert!(!thresholds.next_state(true, 89));
+
+        // Clear threshold never exceeds the raise threshold
+        assert_eq!(AlertThresholds::new(80, 90).clear_percent, 80);
+    }
+
+    #[test]
+    fn test_floor_alert_does_not_flap() {
+        let mut alerts = OccupancyAlerts::new(AlertThresholds::DEFAULT);
+        assert_eq!(alerts.update(&layout_with_taken(18)), None);
+
+        // 19/20 = 95%
+        assert_eq!(alerts.update(&layout_with_taken(19)), Some(ClusterId::F2));
+        assert!(alerts.is_active(ClusterId::F2));
+        assert!(!alerts.is_active(ClusterId::F0));
+
+        // 18/20 = 90% keeps the alert, 17/20 = 85% clears it
+        assert_eq!(alerts.update(&layout_with_taken(18)), Some(ClusterId::F2));
+        assert_eq!(alerts.update(&layout_with_taken(17)), None);
+    }
+}
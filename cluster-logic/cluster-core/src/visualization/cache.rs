@@ -0,0 +1,195 @@
+//! Off-screen surfaces for caching static render layers
+//!
+//! Zone names, floor labels and panel backgrounds only change with the layout
+//! or the selected cluster, yet a full redraw repaints them every frame.
+//! [`BackgroundCache`] keeps them pre-rendered in a [`Surface`] so a frame
+//! reduces to one blit plus the dynamic content.
+
+use crate::types::ClusterId;
+use crate::visualization::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use core::convert::Infallible;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+/// In-memory RGB565 draw target
+pub struct Surface<const W: usize, const H: usize> {
+    pixels: [[Rgb565; W]; H],
+}
+
+impl<const W: usize, const H: usize> Surface<W, H> {
+    /// Create a surface filled with black
+    pub const fn new() -> Self {
+        Self {
+            pixels: [[Rgb565::BLACK; W]; H],
+        }
+    }
+
+    /// Get the color of a pixel, if it lies on the surface
+    pub fn pixel(&self, point: Point) -> Option<Rgb565> {
+        let x = usize::try_from(point.x).ok()?;
+        let y = usize::try_from(point.y).ok()?;
+        self.pixels.get(y)?.get(x).copied()
+    }
+
+    /// Copy the whole surface onto `target` with its top-left corner at `origin`
+    pub fn blit<D>(&self, target: &mut D, origin: Point) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        target.fill_contiguous(
+            &Rectangle::new(origin, self.size()),
+            self.pixels.iter().flatten().copied(),
+        )
+    }
+}
+
+impl<const W: usize, const H: usize> Default for Surface<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for Surface<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for Surface<W, H> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y))
+                && x < W
+                && y < H
+            {
+                self.pixels[y][x] = color;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.pixels = [[color; W]; H];
+        Ok(())
+    }
+}
+
+/// Full-screen surface holding the static background of a frame
+pub type BackgroundSurface = Surface<{ DISPLAY_WIDTH as usize }, { DISPLAY_HEIGHT as usize }>;
+
+/// Cached background layers, keyed by the selected cluster
+///
+/// Used with [`ClusterRenderer::render_frame_cached`](super::ClusterRenderer::render_frame_cached).
+pub struct BackgroundCache {
+    surface: BackgroundSurface,
+    rendered_for: Option<ClusterId>,
+}
+
+impl BackgroundCache {
+    /// Create an empty cache, rendered on first use
+    pub const fn new() -> Self {
+        Self {
+            surface: Surface::new(),
+            rendered_for: None,
+        }
+    }
+
+    /// Force the background to be redrawn on the next frame
+    ///
+    /// Call this when the layout (zones, cluster names) or the theme changes.
+    pub const fn invalidate(&mut self) {
+        self.rendered_for = None;
+    }
+
+    /// Check if the cached background was rendered for `cluster`
+    pub fn is_valid_for(&self, cluster: ClusterId) -> bool {
+        self.rendered_for == Some(cluster)
+    }
+
+    pub(crate) const fn validate(&mut self, cluster: ClusterId) {
+        self.rendered_for = Some(cluster);
+    }
+
+    pub(crate) const fn surface(&self) -> &BackgroundSurface {
+        &self.surface
+    }
+
+    pub(crate) const fn surface_mut(&mut self) -> &mut BackgroundSurface {
+        &mut self.surface
+    }
+}
+
+impl Default for BackgroundCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::models::Layout;
+    use crate::types::{Kind, Status};
+    use crate::visualization::ClusterRenderer;
+    use crate::{cluster, layout, seat, zone};
+    use std::boxed::Box;
+
+    fn sample_layout(status: Status) -> Layout {
+        layout! {
+            f0: cluster! {
+                message: "Welcome",
+                name: "F0",
+                attributes: [],
+                seats: [
+                    seat!("f0r1s1", Kind::Mac, status, 0, 0),
+                    seat!("f0r1s2", Kind::Dell, Status::Free, 4, 0),
+                    seat!("f0r2s1", Kind::Lenovo, Status::Broken, 0, 6)
+                ],
+                zones: [zone!("Z1", [], 0, 0)]
+            }
+        }
+    }
+
+    fn assert_same(a: &BackgroundSurface, b: &BackgroundSurface) {
+        assert!(
+            a.pixels == b.pixels,
+            "cached frame differs from full render"
+        );
+    }
+
+    #[test]
+    fn test_cached_frame_matches_full_render() {
+        let mut renderer = ClusterRenderer::new();
+        let mut cache = Box::new(BackgroundCache::new());
+        let mut full = Box::new(BackgroundSurface::new());
+        let mut cached = Box::new(BackgroundSurface::new());
+
+        for (frame, status) in [(0, Status::Free), (40, Status::Taken)] {
+            let layout = sample_layout(status);
+            renderer.render_frame(&mut *full, &layout, frame).unwrap();
+            renderer
+                .render_frame_cached(&mut *cached, &layout, frame, &mut cache)
+                .unwrap();
+            assert_same(&full, &cached);
+            assert!(cache.is_valid_for(ClusterId::F0));
+        }
+
+        // Changing the selection re-renders the background
+        renderer.set_selected_cluster(ClusterId::F1);
+        let layout = sample_layout(Status::Free);
+        renderer.render_frame(&mut *full, &layout, 3).unwrap();
+        renderer
+            .render_frame_cached(&mut *cached, &layout, 3, &mut cache)
+            .unwrap();
+        assert_same(&full, &cached);
+        assert!(cache.is_valid_for(ClusterId::F1));
+
+        cache.invalidate();
+        assert!(!cache.is_valid_for(ClusterId::F1));
+    }
+}
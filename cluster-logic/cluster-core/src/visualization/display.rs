@@ -1,7 +1,10 @@
 //! Display layout constants and structures
 
+use crate::preferences::Theme;
 use embedded_graphics::{
     geometry::{Point, Size},
+    pixelcolor::Rgb565,
+    prelude::{RgbColor, WebColors},
     primitives::Rectangle,
 };
 
@@ -128,7 +131,67 @@ pub mod visual {
     pub const OCCUPANCY_MEDIUM: Rgb565 = Rgb565::YELLOW;
     pub const OCCUPANCY_HIGH: Rgb565 = Rgb565::RED;
 
+    /// Marker shown while displaying stale (persisted) data
+    pub const STALE_INDICATOR: Rgb565 = Rgb565::CSS_ORANGE;
+    pub const STALE_INDICATOR_SIZE: u32 = 4;
+
+    /// Occupancy alert overlay (pulsing border and banner)
+    pub const ALERT_BANNER_BG: Rgb565 = Rgb565::RED;
+    pub const ALERT_TEXT: Rgb565 = Rgb565::WHITE;
+    pub const ALERT_BANNER_HEIGHT: u32 = 11;
+    /// Frames per border pulse
+    pub const ALERT_PULSE_PERIOD: u32 = 32;
+
+    /// History graph colors
+    pub const GRAPH_COLOR: Rgb565 = Rgb565::CSS_DEEP_SKY_BLUE;
+    pub const GRAPH_AXIS: Rgb565 = Rgb565::CSS_DARK_GRAY;
+
+    /// Seat booked in the booking system but not taken
+    #[cfg(feature = "bookings")]
+    pub const SEAT_BOOKED: Rgb565 = Rgb565::CSS_GOLD;
+
+    /// Seat label drawn over the seat color when zoomed in
+    pub const SEAT_LABEL: Rgb565 = Rgb565::WHITE;
+
     /// Seat rendering constants
     pub const SEAT_SIZE: u32 = 2;
     pub const ZONE_GAP: u32 = 4;
 }
+
+/// Colors that follow the [`Theme`]
+///
+/// The dark palette is the one from [`visual`]; seat and occupancy colors
+/// stay the same in every theme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub background: Rgb565,
+    pub text: Rgb565,
+    pub floor_indicator_bg: Rgb565,
+    pub floor_selected: Rgb565,
+    pub status_bar_bg: Rgb565,
+}
+
+impl Palette {
+    pub const DARK: Self = Self {
+        background: visual::BACKGROUND,
+        text: visual::TEXT_COLOR,
+        floor_indicator_bg: visual::FLOOR_INDICATOR_BG,
+        floor_selected: visual::FLOOR_SELECTED,
+        status_bar_bg: visual::STATUS_BAR_BG,
+    };
+
+    pub const LIGHT: Self = Self {
+        background: Rgb565::WHITE,
+        text: Rgb565::BLACK,
+        floor_indicator_bg: Rgb565::CSS_LIGHT_GRAY,
+        floor_selected: Rgb565::BLACK,
+        status_bar_bg: Rgb565::CSS_DARK_GRAY,
+    };
+
+    pub const fn for_theme(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Self::DARK,
+            Theme::Light => Self::LIGHT,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! World-to-pixel transforms for seat coordinates
+//!
+//! Seat and zone positions are expressed in layout units ("world" space).
+//! A [`GridSpace`] maps them onto a pixel area with a cell scale, margins and
+//! an orientation, so the same layout can be rendered on 64x64, 128x128 or
+//! larger targets without touching the seat data. The area can also be
+//! scrolled, to pan a zoomed map.
+
+use embedded_graphics::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Rotation applied to world coordinates, clockwise
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Orientation {
+    /// Check if this orientation swaps width and height
+    pub const fn is_transposed(self) -> bool {
+        matches!(self, Orientation::Rotate90 | Orientation::Rotate270)
+    }
+}
+
+/// Size of one world unit in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellScale {
+    /// Fixed ratio: one world unit is `num / den` pixels
+    Fixed { num: u32, den: u32 },
+    /// Largest ratio that fits the world into the area, keeping aspect ratio
+    Fit,
+}
+
+/// Mapping from world coordinates to a pixel area
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridSpace {
+    area: Rectangle,
+    world: Size,
+    margin: u32,
+    orientation: Orientation,
+    scale: CellScale,
+    scroll: Point,
+}
+
+impl GridSpace {
+    /// Map a world of `world` units 1:1 onto `area`
+    pub const fn new(area: Rectangle, world: Size) -> Self {
+        Self {
+            area,
+            world,
+            margin: 0,
+            orientation: Orientation::Normal,
+            scale: CellScale::Fixed { num: 1, den: 1 },
+            scroll: Point::zero(),
+        }
+    }
+
+    /// Set the world size (extent of the seat coordinates)
+    pub const fn with_world(mut self, world: Size) -> Self {
+        self.world = world;
+        self
+    }
+
+    /// Leave `margin` pixels empty on each side of the area
+    pub const fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Rotate the world before mapping it to pixels
+    pub const fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Set how many pixels one world unit covers
+    pub const fn with_scale(mut self, scale: CellScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Shift the mapped world up and left by `scroll` pixels, e.g. to show
+    /// another part of a zoomed map
+    ///
+    /// Cells scrolled out of the area are not clipped here; draw them through
+    /// a target clipped to [`area`](Self::area).
+    pub const fn with_scroll(mut self, scroll: Point) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Get the pixel area the world is mapped onto
+    pub const fn area(&self) -> Rectangle {
+        self.area
+    }
+
+    /// Get the world size, before orientation
+    pub const fn world(&self) -> Size {
+        self.world
+    }
+
+    /// Get the empty margin on each side of the area, in pixels
+    pub const fn margin(&self) -> u32 {
+        self.margin
+    }
+
+    /// Get the scroll offset in pixels
+    pub const fn scroll(&self) -> Point {
+        self.scroll
+    }
+
+    /// Get the world size after orientation
+    pub const fn oriented_world(&self) -> Size {
+        if self.orientation.is_transposed() {
+            Size::new(self.world.height, self.world.width)
+        } else {
+            self.world
+        }
+    }
+
+    /// Get the effective scale as a `(num, den)` ratio
+    pub fn ratio(&self) -> (u32, u32) {
+        match self.scale {
+            CellScale::Fixed { num, den } => (num, den.max(1)),
+            CellScale::Fit => {
+                let world = self.oriented_world();
+                let avail_w = self.area.size.width.saturating_sub(2 * self.margin);
+                let avail_h = self.area.size.height.saturating_sub(2 * self.margin);
+                if world.width == 0 || world.height == 0 {
+                    return (1, 1);
+                }
+                // Compare avail_w / world.w and avail_h / world.h without division
+                if avail_w * world.height <= avail_h * world.width {
+                    (avail_w, world.width)
+                } else {
+                    (avail_h, world.height)
+                }
+            }
+        }
+    }
+
+    /// Scale a world length to pixels
+    pub fn scale_length(&self, length: u32) -> u32 {
+        let (num, den) = self.ratio();
+        length * num / den
+    }
+
+    /// Map a world point to a pixel point
+    pub fn to_pixel(&self, point: Point) -> Point {
+        let (num, den) = self.ratio();
+        let oriented = self.orient(point);
+        let scale = |v: i32| v * num as i32 / den as i32;
+
+        self.area.top_left
+            + Point::new(self.margin as i32, self.margin as i32)
+            + Point::new(scale(oriented.x), scale(oriented.y))
+            - self.scroll
+    }
+
+    /// Map a world rectangle (e.g. a seat) to a pixel rectangle
+    ///
+    /// The result is at least one pixel wide and high so tiny cells stay
+    /// visible on small targets.
+    pub fn cell_rect(&self, top_left: Point, size: Size) -> Rectangle {
+        let a = self.to_pixel(top_left);
+        let b = self.to_pixel(top_left + size);
+
+        let corner = Point::new(a.x.min(b.x), a.y.min(b.y));
+        let width = a.x.abs_diff(b.x).max(1);
+        let height = a.y.abs_diff(b.y).max(1);
+        Rectangle::new(corner, Size::new(width, height))
+    }
+
+    /// Rotate a world point, treating coordinates as continuous
+    fn orient(&self, point: Point) -> Point {
+        let w = self.world.width as i32;
+        let h = self.world.height as i32;
+        match self.orientation {
+            Orientation::Normal => point,
+            Orientation::Rotate90 => Point::new(h - point.y, point.x),
+            Orientation::Rotate180 => Point::new(w - point.x, h - point.y),
+            Orientation::Rotate270 => Point::new(point.y, w - point.x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rectangle = Rectangle::new(Point::new(10, 20), Size::new(80, 60));
+
+    #[test]
+    fn test_identity_mapping() {
+        let grid = GridSpace::new(AREA, Size::new(40, 30));
+        assert_eq!(grid.to_pixel(Point::new(3, 4)), Point::new(13, 24));
+        assert_eq!(
+            grid.cell_rect(Point::new(3, 4), Size::new(2, 2)),
+            Rectangle::new(Point::new(13, 24), Size::new(2, 2))
+        );
+    }
+
+    #[test]
+    fn test_fit_scales_to_area() {
+        // 160x120 world into 80x60 pixels: half size
+        let grid = GridSpace::new(AREA, Size::new(160, 120)).with_scale(CellScale::Fit);
+        assert_eq!(grid.scale_length(10), 5);
+        assert_eq!(grid.to_pixel(Point::new(160, 120)), Point::new(90, 80));
+
+        // Cells never collapse below one pixel
+        let cell = grid.cell_rect(Point::new(0, 0), Size::new(1, 1));
+        assert_eq!(cell.size, Size::new(1, 1));
+
+        // Margins shrink the available area
+        let grid = grid.with_margin(5);
+        assert_eq!(grid.to_pixel(Point::zero()), Point::new(15, 25));
+        assert_eq!(grid.scale_length(120), 50);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let grid = GridSpace::new(AREA, Size::new(40, 30))
+            .with_scale(CellScale::Fixed { num: 2, den: 1 })
+            .with_scroll(Point::new(6, 4));
+        assert_eq!(grid.to_pixel(Point::new(3, 2)), Point::new(10, 20));
+        assert_eq!(
+            grid.cell_rect(Point::new(0, 0), Size::new(2, 2)),
+            Rectangle::new(Point::new(4, 16), Size::new(4, 4))
+        );
+    }
+
+    #[test]
+    fn test_rotation() {
+        let grid = GridSpace::new(AREA, Size::new(40, 30)).with_orientation(Orientation::Rotate90);
+        assert_eq!(grid.oriented_world(), Size::new(30, 40));
+
+        // Top-left cell ends up in the top-right corner
+        let cell = grid.cell_rect(Point::new(0, 0), Size::new(2, 2));
+        assert_eq!(
+            cell,
+            Rectangle::new(Point::new(10 + 28, 20), Size::new(2, 2))
+        );
+
+        let grid = grid.with_orientation(Orientation::Rotate180);
+        let cell = grid.cell_rect(Point::new(0, 0), Size::new(2, 2));
+        assert_eq!(
+            cell,
+            Rectangle::new(Point::new(10 + 38, 20 + 28), Size::new(2, 2))
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Occupancy history graph widget
+//!
+//! Draws a series of occupancy percentages (oldest first) as a bar or line
+//! chart. The y axis is scaled to the largest sample, rounded up to the next
+//! 10%, so quiet days still show some shape.
+
+use crate::visualization::display::visual;
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+};
+
+/// How samples are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GraphStyle {
+    /// One filled column per sample
+    #[default]
+    Bars,
+    /// Samples joined by a polyline
+    Line,
+}
+
+/// Chart of occupancy samples over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryGraph {
+    area: Rectangle,
+    style: GraphStyle,
+    color: Rgb565,
+}
+
+impl HistoryGraph {
+    /// Create a bar graph filling `area`, axes included
+    pub const fn new(area: Rectangle) -> Self {
+        Self {
+            area,
+            style: GraphStyle::Bars,
+            color: visual::GRAPH_COLOR,
+        }
+    }
+
+    /// Set how samples are drawn
+    pub const fn with_style(mut self, style: GraphStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the color of the bars or line
+    pub const fn with_color(mut self, color: Rgb565) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Get the top of the y axis for a set of samples, in percent
+    pub fn scale_max<I>(samples: I) -> u8
+    where
+        I: Iterator<Item = u8>,
+    {
+        let max = samples.max().unwrap_or(0).min(100);
+        max.div_ceil(10).max(1) * 10
+    }
+
+    /// Draw the axes and the samples
+    ///
+    /// When there are more samples than horizontal pixels only the most
+    /// recent ones are shown.
+    pub fn draw<D, I>(&self, display: &mut D, samples: I) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+        I: Iterator<Item = u8> + Clone,
+    {
+        let Some(bottom_right) = self.area.bottom_right() else {
+            return Ok(());
+        };
+        let top_left = self.area.top_left;
+        let axis_style = PrimitiveStyle::with_stroke(visual::GRAPH_AXIS, 1);
+
+        // Y axis on the left, X axis at the bottom
+        Line::new(top_left, Point::new(top_left.x, bottom_right.y))
+            .into_styled(axis_style)
+            .draw(display)?;
+        Line::new(Point::new(top_left.x, bottom_right.y), bottom_right)
+            .into_styled(axis_style)
+            .draw(display)?;
+
+        let plot_width = self.area.size.width.saturating_sub(1) as usize;
+        let plot_height = self.area.size.height.saturating_sub(1) as i32;
+        let count = samples.clone().count();
+        if count == 0 || plot_width == 0 || plot_height == 0 {
+            return Ok(());
+        }
+
+        let skip = count.saturating_sub(plot_width);
+        let shown = count - skip;
+        let max = i32::from(Self::scale_max(samples.clone()));
+        let origin = Point::new(top_left.x + 1, bottom_right.y);
+
+        let column = |index: usize| (index * plot_width / shown) as i32;
+        let height = |value: u8| i32::from(value.min(100)) * plot_height / max;
+
+        let mut previous: Option<Point> = None;
+        for (index, value) in samples.skip(skip).enumerate() {
+            let x0 = column(index);
+            let x1 = column(index + 1);
+            let h = height(value);
+
+            match self.style {
+                GraphStyle::Bars => {
+                    if h > 0 {
+                        Rectangle::new(
+                            Point::new(origin.x + x0, origin.y - h),
+                            Size::new((x1 - x0).max(1) as u32, h as u32),
+                        )
+                        .into_styled(PrimitiveStyle::with_fill(self.color))
+                        .draw(display)?;
+                    }
+                }
+                GraphStyle::Line => {
+                    let point = Point::new(origin.x + (x0 + x1) / 2, origin.y - h);
+                    Line::new(previous.unwrap_or(point), point)
+                        .into_styled(PrimitiveStyle::with_stroke(self.color, 1))
+                        .draw(display)?;
+                    previous = Some(point);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::Surface;
+
+    #[test]
+    fn test_scale_max() {
+        assert_eq!(HistoryGraph::scale_max([].into_iter()), 10);
+        assert_eq!(HistoryGraph::scale_max([3, 41, 12].into_iter()), 50);
+        assert_eq!(HistoryGraph::scale_max([100, 250].into_iter()), 100);
+    }
+
+    #[test]
+    fn test_bars_scaled_to_max() {
+        let mut surface: Surface<11, 11> = Surface::new();
+        let graph = HistoryGraph::new(Rectangle::new(Point::zero(), Size::new(11, 11)));
+        graph.draw(&mut surface, [50, 100].into_iter()).unwrap();
+
+        let color = visual::GRAPH_COLOR;
+        // 100% reaches the top of the plot, 50% half of it
+        assert_eq!(surface.pixel(Point::new(6, 0)), Some(color));
+        assert_eq!(surface.pixel(Point::new(1, 5)), Some(color));
+        assert_ne!(surface.pixel(Point::new(1, 4)), Some(color));
+        // Axes
+        assert_eq!(surface.pixel(Point::new(0, 3)), Some(visual::GRAPH_AXIS));
+        assert_eq!(surface.pixel(Point::new(7, 10)), Some(visual::GRAPH_AXIS));
+    }
+}
@@ -0,0 +1,264 @@
+//! Framebuffer region ownership
+//!
+//! Overlays, plugins and banners drawing into the same frame tear each other
+//! when they race. Each producer claims the rectangles it draws into in a
+//! [`RegionMap`]; a claim that overlaps another producer's is rejected and
+//! counted. Drawing through [`RegionMap::target`] only reaches pixels the
+//! producer owns, so a producer can't overwrite anyone else's area.
+
+use embedded_graphics::{prelude::*, primitives::Rectangle};
+use heapless::Vec;
+
+/// Identifies something that draws into the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProducerId(pub u8);
+
+impl ProducerId {
+    pub const CLUSTER_VIEW: Self = Self(0);
+    pub const ALERT_OVERLAY: Self = Self(1);
+    pub const NETWORK_BANNER: Self = Self(2);
+    pub const PLUGIN: Self = Self(3);
+}
+
+/// Why a claim was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimError {
+    /// The area overlaps a region owned by another producer
+    Conflict {
+        owner: ProducerId,
+        region: Rectangle,
+    },
+    /// No room left for another claim
+    Full,
+    /// The area has no pixels
+    Empty,
+}
+
+/// A rectangle owned by a producer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Claim {
+    pub producer: ProducerId,
+    pub area: Rectangle,
+}
+
+/// Set of non-conflicting region claims, at most `N` at a time
+#[derive(Clone, Debug, Default)]
+pub struct RegionMap<const N: usize> {
+    claims: Vec<Claim, N>,
+    rejected: u32,
+}
+
+impl<const N: usize> RegionMap<N> {
+    pub const fn new() -> Self {
+        Self {
+            claims: Vec::new(),
+            rejected: 0,
+        }
+    }
+
+    /// Claim `area` for `producer`
+    ///
+    /// A producer may hold overlapping claims of its own; overlapping a
+    /// region owned by another producer is rejected.
+    pub fn claim(&mut self, producer: ProducerId, area: Rectangle) -> Result<(), ClaimError> {
+        if area.is_zero_sized() {
+            return Err(ClaimError::Empty);
+        }
+
+        if let Some(other) = self
+            .claims
+            .iter()
+            .find(|c| c.producer != producer && !c.area.intersection(&area).is_zero_sized())
+        {
+            self.rejected = self.rejected.wrapping_add(1);
+            return Err(ClaimError::Conflict {
+                owner: other.producer,
+                region: other.area,
+            });
+        }
+
+        self.claims
+            .push(Claim { producer, area })
+            .map_err(|_| ClaimError::Full)
+    }
+
+    /// Release a claim made with exactly this area
+    ///
+    /// Returns `true` if a claim was released.
+    pub fn release(&mut self, producer: ProducerId, area: Rectangle) -> bool {
+        match self
+            .claims
+            .iter()
+            .position(|c| c.producer == producer && c.area == area)
+        {
+            Some(index) => {
+                self.claims.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Release every claim held by `producer`
+    pub fn release_all(&mut self, producer: ProducerId) {
+        self.claims.retain(|c| c.producer != producer);
+    }
+
+    /// Get the producer owning a pixel
+    pub fn owner_at(&self, point: Point) -> Option<ProducerId> {
+        self.claims
+            .iter()
+            .find(|c| c.area.contains(point))
+            .map(|c| c.producer)
+    }
+
+    /// Check if `producer` owns a pixel
+    pub fn owns(&self, producer: ProducerId, point: Point) -> bool {
+        self.claims_of(producer).any(|area| area.contains(point))
+    }
+
+    /// Iterate over the areas claimed by `producer`
+    pub fn claims_of(&self, producer: ProducerId) -> impl Iterator<Item = Rectangle> + '_ {
+        self.claims
+            .iter()
+            .filter(move |c| c.producer == producer)
+            .map(|c| c.area)
+    }
+
+    /// Take the number of claims rejected since the last call
+    pub fn take_rejected(&mut self) -> u32 {
+        core::mem::take(&mut self.rejected)
+    }
+
+    /// Draw target that only passes pixels owned by `producer`
+    pub fn target<'a, D>(
+        &'a self,
+        producer: ProducerId,
+        display: &'a mut D,
+    ) -> ClaimedTarget<'a, D, N>
+    where
+        D: DrawTarget,
+    {
+        ClaimedTarget {
+            map: self,
+            producer,
+            display,
+        }
+    }
+}
+
+/// Draw target clipped to the regions claimed by one producer
+pub struct ClaimedTarget<'a, D, const N: usize> {
+    map: &'a RegionMap<N>,
+    producer: ProducerId,
+    display: &'a mut D,
+}
+
+impl<D, const N: usize> Dimensions for ClaimedTarget<'_, D, N>
+where
+    D: DrawTarget,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.display.bounding_box()
+    }
+}
+
+impl<D, const N: usize> DrawTarget for ClaimedTarget<'_, D, N>
+where
+    D: DrawTarget,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let map = self.map;
+        let producer = self.producer;
+        self.display.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| map.owns(producer, *point)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        // Overlapping claims of the same producer may fill a pixel twice,
+        // which is harmless for a solid color
+        for claimed in self.map.claims_of(self.producer) {
+            let clipped = claimed.intersection(area);
+            if !clipped.is_zero_sized() {
+                self.display.fill_solid(&clipped, color)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::Surface;
+    use embedded_graphics::pixelcolor::Rgb565;
+
+    fn rect(x: i32, y: i32, w: u32, h: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(w, h))
+    }
+
+    #[test]
+    fn test_conflicting_claims_rejected() {
+        let mut map: RegionMap<4> = RegionMap::new();
+        let banner = rect(0, 0, 16, 4);
+
+        assert!(map.claim(ProducerId::NETWORK_BANNER, banner).is_ok());
+        // Same producer may overlap itself
+        assert!(
+            map.claim(ProducerId::NETWORK_BANNER, rect(8, 0, 8, 8))
+                .is_ok()
+        );
+        assert_eq!(
+            map.claim(ProducerId::PLUGIN, rect(4, 2, 4, 4)),
+            Err(ClaimError::Conflict {
+                owner: ProducerId::NETWORK_BANNER,
+                region: banner,
+            })
+        );
+        assert_eq!(map.take_rejected(), 1);
+        assert_eq!(
+            map.owner_at(Point::new(1, 1)),
+            Some(ProducerId::NETWORK_BANNER)
+        );
+
+        map.release_all(ProducerId::NETWORK_BANNER);
+        assert!(map.claim(ProducerId::PLUGIN, rect(4, 2, 4, 4)).is_ok());
+        assert_eq!(
+            map.claim(ProducerId::PLUGIN, rect(0, 0, 0, 4)),
+            Err(ClaimError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_target_clipped_to_claims() {
+        let mut map: RegionMap<2> = RegionMap::new();
+        map.claim(ProducerId::ALERT_OVERLAY, rect(2, 2, 2, 2))
+            .unwrap();
+
+        let mut surface: Surface<8, 8> = Surface::new();
+        {
+            let mut target = map.target(ProducerId::ALERT_OVERLAY, &mut surface);
+            target.clear(Rgb565::RED).unwrap();
+            Pixel(Point::new(0, 0), Rgb565::RED)
+                .draw(&mut target)
+                .unwrap();
+            Pixel(Point::new(3, 3), Rgb565::GREEN)
+                .draw(&mut target)
+                .unwrap();
+        }
+
+        assert_eq!(surface.pixel(Point::new(2, 2)), Some(Rgb565::RED));
+        assert_eq!(surface.pixel(Point::new(3, 3)), Some(Rgb565::GREEN));
+        assert_eq!(surface.pixel(Point::new(0, 0)), Some(Rgb565::BLACK));
+        assert_eq!(surface.pixel(Point::new(4, 4)), Some(Rgb565::BLACK));
+    }
+}
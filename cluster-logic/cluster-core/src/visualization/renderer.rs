@@ -1,26 +1,57 @@
 //! Cluster visualization renderer
 
-use crate::models::{Cluster, Layout, Seat};
+#[cfg(feature = "bookings")]
+use crate::bookings::{BookedSeats, SeatState};
+use crate::messages::FallbackMessages;
+use crate::models::{Cluster, Layout, LayoutStats, Seat, SeatBounds};
+use crate::preferences::Theme;
 use crate::types::{ClusterId, Kind, Status};
+use crate::visualization::cache::BackgroundCache;
 use crate::visualization::display::{
-    DEFAULT_LAYOUT, DISPLAY_WIDTH, DisplayLayout, FLOOR_BAR_SPACING, FLOOR_BARS_Y,
+    DEFAULT_LAYOUT, DISPLAY_HEIGHT, DISPLAY_WIDTH, DisplayLayout, FLOOR_BAR_SPACING, FLOOR_BARS_Y,
     FLOOR_INFO_LEFT_MARGIN, FLOOR_INFO_WIDTH, FLOOR_TEXT_BASELINE_Y, FLOOR_TEXT_X,
-    MOTD_LINE_HEIGHT, MOTD_TEXT_Y, SPLIT_FLOOR_GAP, STATUS_BAR_HEIGHT, STATUS_BAR_SIDE_MARGIN,
-    ZONE_TEXT_Y_OFFSET, visual,
+    MOTD_LINE_HEIGHT, MOTD_TEXT_Y, Palette, SPLIT_FLOOR_GAP, STATUS_BAR_HEIGHT,
+    STATUS_BAR_SIDE_MARGIN, ZONE_TEXT_Y_OFFSET, visual,
 };
+use crate::visualization::grid::GridSpace;
+use crate::visualization::history::HistoryGraph;
+use crate::visualization::rows::{self, SeatDensity};
+use crate::visualization::zoom::Zoom;
 use embedded_graphics::{
-    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    mono_font::{
+        MonoTextStyle,
+        iso_8859_1::{FONT_4X6, FONT_6X10},
+    },
     pixelcolor::Rgb565,
     prelude::*,
     primitives::{PrimitiveStyle, Rectangle},
-    text::Text,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
 };
+use graphics_common::i18n::{Locale, MessageId, write_message};
+use graphics_common::utilities::scroll::{SUBPIXELS, SubpixelScroll};
 use heapless::String;
 
+/// Speed of the MOTD marquee, in 256ths of a pixel per frame
+const MOTD_SCROLL_SPEED: u32 = SUBPIXELS / 2;
+
+/// Top of the graph on the history page
+const HISTORY_GRAPH_Y: i32 = 14;
+
 /// Main cluster renderer
 pub struct ClusterRenderer {
     layout: DisplayLayout,
     selected_cluster: ClusterId,
+    stale: bool,
+    alert: Option<ClusterId>,
+    grid: GridSpace,
+    density: SeatDensity,
+    zoom: Zoom,
+    static_stats: Option<LayoutStats>,
+    locale: Locale,
+    palette: Palette,
+    fallback_messages: FallbackMessages,
+    #[cfg(feature = "bookings")]
+    booked: BookedSeats,
 }
 
 impl ClusterRenderer {
@@ -29,6 +60,17 @@ impl ClusterRenderer {
         Self {
             layout: DEFAULT_LAYOUT,
             selected_cluster: ClusterId::F0,
+            stale: false,
+            alert: None,
+            grid: GridSpace::new(DEFAULT_LAYOUT.cluster_area, Size::zero()),
+            density: SeatDensity::Auto,
+            zoom: Zoom::NONE,
+            static_stats: None,
+            locale: Locale::En,
+            palette: Palette::DARK,
+            fallback_messages: FallbackMessages::new(),
+            #[cfg(feature = "bookings")]
+            booked: BookedSeats::NONE,
         }
     }
 
@@ -36,6 +78,101 @@ impl ClusterRenderer {
         self.selected_cluster = selected_cluster;
     }
 
+    /// Get the cluster shown by the renderer
+    pub const fn selected_cluster(&self) -> ClusterId {
+        self.selected_cluster
+    }
+
+    /// Set how seat coordinates are mapped to pixels
+    ///
+    /// The world size is filled in from each cluster's seat extent at render
+    /// time. Defaults to a 1:1 mapping onto the cluster area.
+    pub const fn set_grid_space(&mut self, grid: GridSpace) {
+        self.grid = grid;
+    }
+
+    /// Set when seats are summarized as row bars
+    ///
+    /// With [`SeatDensity::Auto`], dense clusters like F2 show a bar per row
+    /// until the grid space zooms in enough for their seats, or the map is
+    /// zoomed with [`set_zoom`](Self::set_zoom); switch to
+    /// [`SeatDensity::Seats`] to always expand the selected floor.
+    pub const fn set_seat_density(&mut self, density: SeatDensity) {
+        self.density = density;
+    }
+
+    /// Zoom into and pan the seat map, e.g. from a [`MapZoom`](super::MapZoom)
+    ///
+    /// Seats and zone names outside the cluster area are clipped.
+    pub const fn set_zoom(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+    }
+
+    /// Get the zoom of the seat map
+    pub const fn zoom(&self) -> Zoom {
+        self.zoom
+    }
+
+    /// Grid mapping of the selected cluster, before zoom
+    pub fn cluster_grid_space(&self, layout: &Layout) -> GridSpace {
+        let cluster = self.selected(layout);
+        self.grid
+            .with_world(cluster_world(self.seat_bounds(cluster)))
+    }
+
+    /// Use precomputed seat extents instead of scanning seats every frame
+    ///
+    /// Meant for layouts embedded at build time, with stats from
+    /// `layout_stats_from_json!`. A floor whose seat count no longer matches
+    /// its stats falls back to computing the extent.
+    pub const fn set_layout_stats(&mut self, stats: Option<LayoutStats>) {
+        self.static_stats = stats;
+    }
+
+    /// Mark the rendered data as stale (e.g. loaded from flash at boot)
+    ///
+    /// A blinking marker is drawn in the status bar until cleared.
+    pub const fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
+
+    /// Set the language of the strings drawn by the renderer
+    pub const fn set_locale(&mut self, locale: Locale) {
+        self.locale = locale;
+    }
+
+    /// Set the messages shown for clusters without one
+    ///
+    /// Defaults to the localized messages of [`MESSAGE_ATTRIBUTES`](crate::messages::MESSAGE_ATTRIBUTES).
+    pub fn set_fallback_messages(&mut self, messages: FallbackMessages) {
+        self.fallback_messages = messages;
+    }
+
+    /// Set the color scheme
+    ///
+    /// The cached background keeps the previous colors: invalidate the
+    /// [`BackgroundCache`] after a change.
+    pub const fn set_theme(&mut self, theme: Theme) {
+        self.palette = Palette::for_theme(theme);
+    }
+
+    /// Show the occupancy alert overlay for `cluster`, or hide it
+    ///
+    /// Typically fed from [`OccupancyAlerts::update`](super::OccupancyAlerts::update).
+    pub const fn set_alert(&mut self, cluster: Option<ClusterId>) {
+        self.alert = cluster;
+    }
+
+    /// Set the booked seats of the selected cluster
+    ///
+    /// Typically from [`Bookings::booked_seats`](crate::bookings::Bookings::booked_seats);
+    /// recompute it when the selection or the time changes. Booked seats
+    /// nobody sits on are drawn with [`visual::SEAT_BOOKED`].
+    #[cfg(feature = "bookings")]
+    pub const fn set_booked_seats(&mut self, booked: BookedSeats) {
+        self.booked = booked;
+    }
+
     /// Render a complete frame
     pub fn render_frame<D>(
         &self,
@@ -46,10 +183,134 @@ impl ClusterRenderer {
     where
         D: DrawTarget<Color = Rgb565>,
     {
-        // Clear display
-        display.clear(visual::BACKGROUND)?;
+        self.render_background(display, layout)?;
+        self.render_foreground(display, layout, frame)
+    }
+
+    /// Render a complete frame, reusing a cached background
+    ///
+    /// The static layers (panel backgrounds, floor labels, zone names) are only
+    /// redrawn into `cache` when it was invalidated or the selected cluster
+    /// changed. Each frame then blits the cache and draws the dynamic content
+    /// on top. Call [`BackgroundCache::invalidate`] when the layout or the theme
+    /// changes.
+    pub fn render_frame_cached<D>(
+        &self,
+        display: &mut D,
+        layout: &Layout,
+        frame: u32,
+        cache: &mut BackgroundCache,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if !cache.is_valid_for(self.selected_cluster) {
+            let Ok(()) = self.render_background(cache.surface_mut(), layout);
+            cache.validate(self.selected_cluster);
+        }
+
+        cache.surface().blit(display, Point::zero())?;
+        self.render_foreground(display, layout, frame)
+    }
+
+    /// Render the occupancy history page of the selected cluster
+    ///
+    /// `samples` are occupancy percentages, oldest first, e.g. from a
+    /// [`TrendTracker`](crate::trend::TrendTracker).
+    pub fn render_history_page<D, I>(
+        &self,
+        display: &mut D,
+        layout: &Layout,
+        samples: I,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+        I: Iterator<Item = u8> + Clone,
+    {
+        display.clear(self.palette.background)?;
+
+        let cluster = self.selected(layout);
+        let style = MonoTextStyle::new(&FONT_6X10, self.palette.text);
+        let mut title: String<24> = String::new();
+        let name: &str = &cluster.name;
+        let _ = write_message(&mut title, MessageId::HistoryTitle, self.locale, &[&name]);
+        Text::new(&title, Point::new(2, MOTD_TEXT_Y), style).draw(display)?;
+
+        let area = Rectangle::new(
+            Point::new(2, HISTORY_GRAPH_Y),
+            Size::new(
+                DISPLAY_WIDTH - 4,
+                DISPLAY_HEIGHT - HISTORY_GRAPH_Y as u32 - 14,
+            ),
+        );
+        HistoryGraph::new(area).draw(display, samples.clone())?;
+
+        // Axis scale and current value below the graph
+        let mut footer: String<24> = String::new();
+        let _ = write_message(
+            &mut footer,
+            MessageId::HistoryFooter,
+            self.locale,
+            &[
+                &HistoryGraph::scale_max(samples),
+                &cluster.get_stats().occupancy_percentage(),
+            ],
+        );
+        Text::new(&footer, Point::new(2, DISPLAY_HEIGHT as i32 - 4), style).draw(display)?;
+
+        Ok(())
+    }
+
+    /// Render the layers that only change with the layout or the selection
+    fn render_background<D>(&self, display: &mut D, layout: &Layout) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        display.clear(self.palette.background)?;
+
+        self.render_floors_background(display)?;
+        self.render_zone_labels(display, self.selected(layout))?;
+
+        // Background for status bar
+        self.layout
+            .status_bar
+            .into_styled(PrimitiveStyle::with_fill(self.palette.status_bar_bg))
+            .draw(display)
+    }
+
+    /// Render the layers that change with seat data or the frame counter
+    fn render_foreground<D>(
+        &self,
+        display: &mut D,
+        layout: &Layout,
+        frame: u32,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let selected_cluster = self.selected(layout);
+
+        let message = self
+            .fallback_messages
+            .message(selected_cluster, self.locale);
+        self.render_header(display, message, frame)?;
+        self.render_floor_bars(display, layout)?;
+        self.render_seats(display, selected_cluster)?;
+        let stats = selected_cluster.get_stats();
+        let occupancy = stats.occupancy_percentage();
+        self.render_status_bar(display, occupancy)?;
+        if self.stale {
+            self.render_stale_marker(display, frame)?;
+        }
+        if let Some(cluster) = self.alert {
+            self.render_alert(display, layout, cluster, frame)?;
+        }
 
-        let selected_cluster = match self.selected_cluster {
+        Ok(())
+    }
+
+    const fn selected<'l>(&self, layout: &'l Layout) -> &'l Cluster {
+        match self.selected_cluster {
             ClusterId::Hidden => &layout.f0,
             ClusterId::F0 => &layout.f0,
             ClusterId::F1 => &layout.f1,
@@ -57,30 +318,22 @@ impl ClusterRenderer {
             ClusterId::F2 => &layout.f2,
             ClusterId::F4 => &layout.f4,
             ClusterId::F6 => &layout.f6,
-        };
-
-        // Render each component
-        Self::render_header(display, &selected_cluster.message, frame)?;
-        self.render_floors_info(display, layout)?;
-        self.render_cluster::<D>(display, selected_cluster)?;
-        let stats = selected_cluster.get_stats();
-        let occupancy = stats.occupancy_percentage();
-        self.render_status_bar(display, occupancy)?;
-
-        Ok(())
+        }
     }
 
-    fn render_header<D>(display: &mut D, motd: &str, frame: u32) -> Result<(), D::Error>
+    fn render_header<D>(&self, display: &mut D, motd: &str, frame: u32) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = Rgb565>,
     {
         // Scrolling text for MOTD
         let text_width = motd.len() * 6; // Approximate width with FONT_6X10
         let total_scroll_width = text_width + DISPLAY_WIDTH as usize;
-        let scroll_pos = ((frame / 2) as usize) % total_scroll_width;
-        let x_offset = DISPLAY_WIDTH as i32 - scroll_pos as i32;
+        // Dithered between pixels, which looks smoother than a step every
+        // other frame
+        let scroll = SubpixelScroll::new(MOTD_SCROLL_SPEED, total_scroll_width as u32);
+        let x_offset = DISPLAY_WIDTH as i32 - scroll.offset(frame) as i32;
 
-        let style = MonoTextStyle::new(&FONT_6X10, visual::TEXT_COLOR);
+        let style = MonoTextStyle::new(&FONT_6X10, self.palette.text);
         Text::new(motd, Point::new(x_offset, MOTD_TEXT_Y), style).draw(display)?;
 
         // Draw the message again for seamless scrolling
@@ -111,7 +364,7 @@ impl ClusterRenderer {
         let occupancy = (stats.available as f32 / stats.total as f32) * 100.0;
 
         let bar_color = if is_selected {
-            visual::FLOOR_SELECTED
+            self.palette.floor_selected
         } else {
             visual::FLOOR_UNSELECTED
         };
@@ -132,14 +385,14 @@ impl ClusterRenderer {
         Ok(())
     }
 
-    fn render_floors_info<D>(&self, display: &mut D, layout: &Layout) -> Result<(), D::Error>
+    fn render_floors_background<D>(&self, display: &mut D) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = Rgb565>,
     {
         // Background for floor indicator area
         self.layout
             .floor_info
-            .into_styled(PrimitiveStyle::with_fill(visual::FLOOR_INDICATOR_BG))
+            .into_styled(PrimitiveStyle::with_fill(self.palette.floor_indicator_bg))
             .draw(display)?;
 
         // Draw current floor text
@@ -152,7 +405,7 @@ impl ClusterRenderer {
             ClusterId::F4 => String::try_from("F4").unwrap(),
             ClusterId::F6 => String::try_from("F6").unwrap(),
         };
-        let text_style = MonoTextStyle::new(&FONT_6X10, visual::TEXT_COLOR);
+        let text_style = MonoTextStyle::new(&FONT_6X10, self.palette.text);
         Text::new(
             &floor_num,
             Point::new(FLOOR_TEXT_X, FLOOR_TEXT_BASELINE_Y),
@@ -160,6 +413,26 @@ impl ClusterRenderer {
         )
         .draw(display)?;
 
+        // Inactive floors - grey filled rectangles
+        for row in [3i32, 1] {
+            Rectangle::new(
+                Point::new(
+                    FLOOR_INFO_LEFT_MARGIN as i32,
+                    FLOOR_BARS_Y as i32 + (row * (MOTD_LINE_HEIGHT + FLOOR_BAR_SPACING) as i32),
+                ),
+                Size::new(FLOOR_INFO_WIDTH, MOTD_LINE_HEIGHT),
+            )
+            .into_styled(PrimitiveStyle::with_fill(visual::FLOOR_INACTIVE))
+            .draw(display)?;
+        }
+
+        Ok(())
+    }
+
+    fn render_floor_bars<D>(&self, display: &mut D, layout: &Layout) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
         self.render_floor_info(
             display,
             &layout.f0,
@@ -205,17 +478,6 @@ impl ClusterRenderer {
             self.selected_cluster == ClusterId::F2,
         )?;
 
-        // Inactive floor - grey filled rectangle
-        Rectangle::new(
-            Point::new(
-                FLOOR_INFO_LEFT_MARGIN as i32,
-                FLOOR_BARS_Y as i32 + (3i32 * (MOTD_LINE_HEIGHT + FLOOR_BAR_SPACING) as i32),
-            ),
-            Size::new(FLOOR_INFO_WIDTH, MOTD_LINE_HEIGHT),
-        )
-        .into_styled(PrimitiveStyle::with_fill(visual::FLOOR_INACTIVE))
-        .draw(display)?;
-
         self.render_floor_info(
             display,
             &layout.f4,
@@ -227,17 +489,6 @@ impl ClusterRenderer {
             self.selected_cluster == ClusterId::F4,
         )?;
 
-        // Inactive floor - grey filled rectangle
-        Rectangle::new(
-            Point::new(
-                FLOOR_INFO_LEFT_MARGIN as i32,
-                FLOOR_BARS_Y as i32 + ((MOTD_LINE_HEIGHT + FLOOR_BAR_SPACING) as i32),
-            ),
-            Size::new(FLOOR_INFO_WIDTH, MOTD_LINE_HEIGHT),
-        )
-        .into_styled(PrimitiveStyle::with_fill(visual::FLOOR_INACTIVE))
-        .draw(display)?;
-
         self.render_floor_info(
             display,
             &layout.f6,
@@ -257,11 +508,6 @@ impl ClusterRenderer {
         D: DrawTarget<Color = Rgb565>,
     {
         occupancy = occupancy.clamp(0, 100);
-        // Background for status bar
-        self.layout
-            .status_bar
-            .into_styled(PrimitiveStyle::with_fill(visual::STATUS_BAR_BG))
-            .draw(display)?;
 
         // Calculate bar width based on occupancy, accounting for side margins
         let bar_area_width = self.layout.status_bar.size.width - (2 * STATUS_BAR_SIDE_MARGIN);
@@ -289,51 +535,232 @@ impl ClusterRenderer {
         Ok(())
     }
 
-    fn render_cluster<D>(&self, display: &mut D, cluster: &Cluster) -> Result<(), D::Error>
+    fn render_stale_marker<D>(&self, display: &mut D, frame: u32) -> Result<(), D::Error>
     where
         D: DrawTarget<Color = Rgb565>,
     {
-        if cluster.seats.is_empty() {
+        // Blink at roughly 1Hz at 60 FPS
+        if (frame / 30) % 2 == 1 {
             return Ok(());
         }
 
-        // Find the minimum coordinates to normalize the cluster position
-        let min_x = cluster.seats.iter().map(|s| s.x).min().unwrap_or(0);
-        let min_y = cluster.seats.iter().map(|s| s.y).min().unwrap_or(0);
+        let size = visual::STALE_INDICATOR_SIZE;
+        let bar = self.layout.status_bar;
+        Rectangle::new(
+            Point::new(
+                bar.top_left.x + (bar.size.width - size) as i32,
+                bar.top_left.y + ((bar.size.height - size) / 2) as i32,
+            ),
+            Size::new(size, size),
+        )
+        .into_styled(PrimitiveStyle::with_fill(visual::STALE_INDICATOR))
+        .draw(display)
+    }
+
+    fn render_alert<D>(
+        &self,
+        display: &mut D,
+        layout: &Layout,
+        cluster: ClusterId,
+        frame: u32,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let Some(alerting) = layout.get(cluster) else {
+            return Ok(());
+        };
+
+        // Pulsing border: red channel follows a triangle wave
+        let half = visual::ALERT_PULSE_PERIOD / 2;
+        let phase = frame % visual::ALERT_PULSE_PERIOD;
+        let level = if phase < half {
+            phase
+        } else {
+            visual::ALERT_PULSE_PERIOD - 1 - phase
+        };
+        let red = (Rgb565::MAX_R as u32 / 2 + level * (Rgb565::MAX_R as u32 / 2) / half) as u8;
+        Rectangle::new(Point::zero(), Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb565::new(red, 0, 0), 1))
+            .draw(display)?;
+
+        // Banner over the MOTD
+        Rectangle::new(
+            Point::zero(),
+            Size::new(DISPLAY_WIDTH, visual::ALERT_BANNER_HEIGHT),
+        )
+        .into_styled(PrimitiveStyle::with_fill(visual::ALERT_BANNER_BG))
+        .draw(display)?;
+
+        let mut banner: String<24> = String::new();
+        let name: &str = &alerting.name;
+        let _ = write_message(
+            &mut banner,
+            MessageId::AlertFull,
+            self.locale,
+            &[&name, &alerting.get_stats().occupancy_percentage()],
+        );
+        let style = MonoTextStyle::new(&FONT_6X10, visual::ALERT_TEXT);
+        Text::new(&banner, Point::new(2, MOTD_TEXT_Y), style).draw(display)?;
+
+        Ok(())
+    }
+
+    /// Seat extent of the selected cluster, from the static stats if they match
+    fn seat_bounds(&self, cluster: &Cluster) -> SeatBounds {
+        match self
+            .static_stats
+            .as_ref()
+            .and_then(|stats| stats.get(self.selected_cluster))
+        {
+            Some(floor) if usize::from(floor.total_seats) == cluster.seats.len() => floor.bounds,
+            _ => cluster.seat_bounds(),
+        }
+    }
+
+    /// Grid mapping for a cluster, sized to its seat extent and zoomed
+    fn cluster_grid(&self, bounds: SeatBounds) -> GridSpace {
+        self.zoom.apply(self.grid.with_world(cluster_world(bounds)))
+    }
+
+    /// Clip of the seat layers: the cluster area while zoomed, so seats and
+    /// zone names scrolled out don't spill onto the rest of the screen
+    fn zoom_clip(&self, grid: &GridSpace) -> Rectangle {
+        if self.zoom.is_zoomed() {
+            grid.area()
+        } else {
+            Rectangle::new(Point::zero(), Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT))
+        }
+    }
+
+    /// Check whether a cluster mapped by `grid` is drawn as row bars
+    fn draws_rows(&self, grid: &GridSpace) -> bool {
+        match self.density {
+            SeatDensity::Seats => false,
+            SeatDensity::Rows => true,
+            // Zooming in is asking for the seats
+            SeatDensity::Auto if self.zoom.is_zoomed() => false,
+            SeatDensity::Auto => {
+                // Seats under a pixel would merge, seats past the area are cut
+                let extent = grid.cell_rect(Point::zero(), grid.world());
+                let area = grid.area();
+                grid.scale_length(visual::SEAT_SIZE) == 0
+                    || !extent
+                        .bottom_right()
+                        .is_some_and(|corner| area.contains(corner))
+            }
+        }
+    }
 
-        // Position cluster at the start of the cluster area (left-aligned, top-aligned)
-        let offset_x = self.layout.cluster_area.top_left.x - min_x as i32;
-        let offset_y = self.layout.cluster_area.top_left.y - min_y as i32;
+    fn render_zone_labels<D>(&self, display: &mut D, cluster: &Cluster) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if cluster.seats.is_empty() {
+            return Ok(());
+        }
 
-        // Draw zone labels at the top of cluster area
-        let zones = &cluster.zones;
-        let text_style = MonoTextStyle::new(&FONT_6X10, visual::TEXT_COLOR);
+        // Draw zone labels at the top of cluster area, where row bars go
+        let grid = self.cluster_grid(self.seat_bounds(cluster));
+        if self.draws_rows(&grid) {
+            return Ok(());
+        }
+        let display = &mut display.clipped(&self.zoom_clip(&grid));
+        let text_style = MonoTextStyle::new(&FONT_6X10, self.palette.text);
 
-        for zone in zones {
+        for zone in &cluster.zones {
+            let anchor = grid.to_pixel(Point::new(zone.x as i32, zone.y as i32));
             Text::new(
                 &zone.name,
-                Point::new(
-                    self.layout.cluster_area.top_left.x + zone.x as i32,
-                    self.layout.cluster_area.top_left.y + zone.y as i32 - ZONE_TEXT_Y_OFFSET,
-                ),
+                Point::new(anchor.x, anchor.y - ZONE_TEXT_Y_OFFSET),
                 text_style,
             )
             .draw(display)?;
         }
 
-        // Render each seat at its exact coordinates (no centering, just offset to cluster area)
-        for seat in &cluster.seats {
-            Rectangle::new(
-                Point::new(seat.x as i32 + offset_x, seat.y as i32 + offset_y),
+        Ok(())
+    }
+
+    fn render_seats<D>(&self, display: &mut D, cluster: &Cluster) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        // The minimum coordinates normalize the cluster position
+        let bounds = self.seat_bounds(cluster);
+        let grid = self.cluster_grid(bounds);
+        if self.draws_rows(&grid) {
+            return rows::draw_row_bars(display, &rows::summarize_rows(cluster), grid.area());
+        }
+        let display = &mut display.clipped(&self.zoom_clip(&grid));
+
+        // Render each seat at its grid position, normalized to the cluster origin
+        for (index, seat) in cluster.seats.iter().enumerate() {
+            let cell = grid.cell_rect(
+                Point::new(
+                    seat.x.saturating_sub(bounds.min_x) as i32,
+                    seat.y.saturating_sub(bounds.min_y) as i32,
+                ),
                 Size::new(visual::SEAT_SIZE, visual::SEAT_SIZE),
-            )
-            .into_styled(PrimitiveStyle::with_fill(Self::seat_to_color(seat)))
-            .draw(display)?;
+            );
+            cell.into_styled(PrimitiveStyle::with_fill(self.seat_color(index, seat)))
+                .draw(display)?;
+            if let Some(label) = &seat.label {
+                Self::render_seat_label(display, cell, label)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Draw a seat label centered on its cell
+    ///
+    /// Only as many characters as fit in the cell are drawn, so labels show
+    /// up once the grid is zoomed in enough (see [`Self::set_grid_space`]).
+    fn render_seat_label<D>(display: &mut D, cell: Rectangle, label: &str) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let glyph = FONT_4X6.character_size;
+        if cell.size.height < glyph.height {
+            return Ok(());
+        }
+        let fit = (cell.size.width / glyph.width) as usize;
+        let end = label
+            .char_indices()
+            .nth(fit)
+            .map_or(label.len(), |(i, _)| i);
+        if end == 0 {
+            return Ok(());
+        }
+
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        Text::with_text_style(
+            &label[..end],
+            cell.center(),
+            MonoTextStyle::new(&FONT_4X6, visual::SEAT_LABEL),
+            text_style,
+        )
+        .draw(display)?;
+        Ok(())
+    }
+
+    /// Color of the seat at `index`, with its booking merged in
+    #[cfg(feature = "bookings")]
+    const fn seat_color(&self, index: usize, seat: &Seat) -> Rgb565 {
+        match SeatState::merge(seat.status, self.booked.contains(index)) {
+            SeatState::Booked => visual::SEAT_BOOKED,
+            SeatState::Live(_) => Self::seat_to_color(seat),
+        }
+    }
+
+    #[cfg(not(feature = "bookings"))]
+    const fn seat_color(&self, _index: usize, seat: &Seat) -> Rgb565 {
+        Self::seat_to_color(seat)
+    }
+
     const fn seat_to_color(seat: &Seat) -> Rgb565 {
         match (seat.kind, seat.status) {
             (Kind::Dell | Kind::Lenovo | Kind::Mac, Status::Free) => Rgb565::GREEN,
@@ -350,3 +777,11 @@ impl Default for ClusterRenderer {
         Self::new()
     }
 }
+
+/// World extent of a cluster, covering the last seat's full cell
+const fn cluster_world(bounds: SeatBounds) -> Size {
+    Size::new(
+        bounds.width as u32 + visual::SEAT_SIZE - 1,
+        bounds.height as u32 + visual::SEAT_SIZE - 1,
+    )
+}
@@ -0,0 +1,234 @@
+//! Row summaries for clusters too dense to draw seat by seat
+//!
+//! Seats are grouped by the row of their ID (see
+//! [`SeatIdParts`](crate::models::SeatIdParts)) and each row is drawn as one
+//! bar split into its taken, broken and free seats, in the seat colors.
+
+use crate::models::Cluster;
+use crate::types::Status;
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use heapless::Vec;
+
+/// Most rows summarized per cluster
+pub const MAX_ROWS: usize = 32;
+
+/// Height of a row bar, and gap below it
+pub const ROW_BAR_HEIGHT: u32 = 3;
+pub const ROW_BAR_GAP: u32 = 1;
+
+/// When the renderer draws row bars instead of seats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeatDensity {
+    /// Row bars when the seats don't fit the cluster area, at least one
+    /// pixel each
+    #[default]
+    Auto,
+    /// Always seats, e.g. while a floor is zoomed in on
+    Seats,
+    /// Always row bars
+    Rows,
+}
+
+/// Seat counts of one row
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RowSummary {
+    pub row: u16,
+    pub total: u16,
+    pub taken: u16,
+    pub broken: u16,
+    pub free: u16,
+}
+
+/// Rows of a cluster, in row order
+///
+/// Seats whose ID doesn't name a row, and rows past [`MAX_ROWS`], are left
+/// out.
+pub fn summarize_rows(cluster: &Cluster) -> Vec<RowSummary, MAX_ROWS> {
+    let mut rows: Vec<RowSummary, MAX_ROWS> = Vec::new();
+    for seat in &cluster.seats {
+        let Some(parts) = seat.id_parts() else {
+            continue;
+        };
+        let index = match rows.binary_search_by_key(&parts.row, |row| row.row) {
+            Ok(index) => index,
+            Err(index) => {
+                let row = RowSummary {
+                    row: parts.row,
+                    ..RowSummary::default()
+                };
+                if rows.insert(index, row).is_err() {
+                    continue;
+                }
+                index
+            }
+        };
+        let row = &mut rows[index];
+        row.total += 1;
+        match seat.status {
+            Status::Taken => row.taken += 1,
+            Status::Broken => row.broken += 1,
+            Status::Free => row.free += 1,
+            Status::Reported => {}
+        }
+    }
+    rows
+}
+
+/// Draw one bar per row from the top of `area`, as many as fit
+///
+/// Each bar spans the width of the area: taken seats first, then broken,
+/// then free, and reported seats in gray at the end.
+pub fn draw_row_bars<D>(
+    display: &mut D,
+    rows: &[RowSummary],
+    area: Rectangle,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let pitch = ROW_BAR_HEIGHT + ROW_BAR_GAP;
+    let fitting = (area.size.height / pitch) as usize;
+    for (i, row) in rows.iter().take(fitting).enumerate() {
+        let y = area.top_left.y + (i as u32 * pitch) as i32;
+        let total = u32::from(row.total.max(1));
+        let mut x = 0;
+        for (count, color) in [
+            (row.taken, Rgb565::BLUE),
+            (row.broken, Rgb565::RED),
+            (row.free, Rgb565::GREEN),
+        ] {
+            let width = area.size.width * u32::from(count) / total;
+            Rectangle::new(
+                Point::new(area.top_left.x + x as i32, y),
+                Size::new(width, ROW_BAR_HEIGHT),
+            )
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)?;
+            x += width;
+        }
+        // Reported seats and rounding
+        Rectangle::new(
+            Point::new(area.top_left.x + x as i32, y),
+            Size::new(area.size.width - x, ROW_BAR_HEIGHT),
+        )
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_GRAY))
+        .draw(display)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::models::SeatIdParts;
+    use crate::types::Kind;
+    use crate::visualization::cache::BackgroundSurface;
+    use crate::{cluster, seat};
+    use std::boxed::Box;
+
+    #[test]
+    fn test_seat_id_parts() {
+        assert_eq!(
+            SeatIdParts::parse("f1br2s14"),
+            Some(SeatIdParts {
+                cluster: "f1b",
+                row: 2,
+                seat: 14
+            })
+        );
+        assert_eq!(SeatIdParts::parse("s1"), None);
+        assert_eq!(SeatIdParts::parse("f0r+1s1"), None);
+        assert_eq!(SeatIdParts::parse("f0r1s"), None);
+    }
+
+    #[test]
+    fn test_rows_summarized_in_order() {
+        let cluster = cluster! {
+            message: "",
+            name: "F2",
+            attributes: [],
+            seats: [
+                seat!("f2r3s1", Kind::Mac, Status::Taken, 0, 6),
+                seat!("f2r1s1", Kind::Mac, Status::Free, 0, 0),
+                seat!("f2r1s2", Kind::Mac, Status::Taken, 3, 0),
+                seat!("f2r3s2", Kind::Mac, Status::Broken, 3, 6),
+                seat!("kiosk", Kind::Flex, Status::Taken, 9, 9)
+            ],
+            zones: []
+        };
+
+        let rows = summarize_rows(&cluster);
+        assert_eq!(
+            rows.as_slice(),
+            [
+                RowSummary {
+                    row: 1,
+                    total: 2,
+                    taken: 1,
+                    broken: 0,
+                    free: 1
+                },
+                RowSummary {
+                    row: 3,
+                    total: 2,
+                    taken: 1,
+                    broken: 1,
+                    free: 0
+                },
+            ]
+        );
+
+        let mut surface = Box::new(BackgroundSurface::new());
+        let area = Rectangle::new(Point::new(10, 20), Size::new(40, 20));
+        let Ok(()) = draw_row_bars(&mut *surface, &rows, area);
+        // Half taken, half free
+        assert_eq!(surface.pixel(Point::new(10, 20)), Some(Rgb565::BLUE));
+        assert_eq!(surface.pixel(Point::new(49, 22)), Some(Rgb565::GREEN));
+        // Second bar below the gap: half taken, half broken
+        assert_eq!(surface.pixel(Point::new(10, 23)), Some(Rgb565::BLACK));
+        assert_eq!(surface.pixel(Point::new(49, 24)), Some(Rgb565::RED));
+    }
+
+    #[test]
+    fn test_dense_cluster_drawn_as_rows() {
+        use crate::layout;
+        use crate::visualization::{ClusterRenderer, DEFAULT_LAYOUT};
+
+        // Too wide for the 81 pixel cluster area at 1:1
+        let layout = layout! {
+            f0: cluster! {
+                message: "",
+                name: "F0",
+                attributes: [],
+                seats: [
+                    seat!("f0r1s1", Kind::Mac, Status::Taken, 0, 0),
+                    seat!("f0r1s2", Kind::Mac, Status::Free, 200, 0)
+                ],
+                zones: []
+            }
+        };
+        let area = DEFAULT_LAYOUT.cluster_area;
+        // Right half of the bar
+        let right = area.top_left + Point::new(60, 0);
+        let render = |density| {
+            let mut renderer = ClusterRenderer::new();
+            renderer.set_seat_density(density);
+            let mut surface = Box::new(BackgroundSurface::new());
+            let Ok(()) = renderer.render_frame(&mut *surface, &layout, 0);
+            surface
+        };
+
+        let rows = render(SeatDensity::Auto);
+        assert_eq!(rows.pixel(area.top_left), Some(Rgb565::BLUE));
+        assert_eq!(rows.pixel(right), Some(Rgb565::GREEN));
+
+        // Expanded, the second seat is past the edge
+        let seats = render(SeatDensity::Seats);
+        assert_eq!(seats.pixel(area.top_left), Some(Rgb565::BLUE));
+        assert_ne!(seats.pixel(right), Some(Rgb565::GREEN));
+    }
+}
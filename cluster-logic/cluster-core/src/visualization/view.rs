@@ -0,0 +1,341 @@
+//! Interchangeable full-screen views
+//!
+//! The main loop shows one view per frame: the cluster map, the occupancy
+//! history, an animation or a plugin. Each implements [`Renderer`], so the
+//! scheduler can switch between them uniformly and tests can run any view
+//! against an in-memory target.
+//!
+//! `DrawTarget` has generic methods and can't be used as a trait object, so
+//! renderers are generic over the target instead: the firmware picks a
+//! `&mut dyn Renderer<Hub75>` each frame.
+
+use crate::models::Layout;
+use crate::preferences::Theme;
+use crate::trend::TrendTracker;
+use crate::visualization::alert::{AlertThresholds, OccupancyAlerts};
+use crate::visualization::cache::BackgroundCache;
+use crate::visualization::display::visual;
+use crate::visualization::renderer::ClusterRenderer;
+use crate::visualization::zoom::MapZoom;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use graphics_common::i18n::Locale;
+
+/// Per-frame state shared with every renderer
+#[derive(Clone, Copy, Debug)]
+pub struct RenderCtx<'a> {
+    /// Frame counter, for animations
+    pub frame: u32,
+    /// Monotonic time in milliseconds
+    pub now_ms: u64,
+    /// Layout to show, once one was loaded
+    pub layout: Option<&'a Layout>,
+    /// Whether `layout` predates the last successful fetch
+    pub stale: bool,
+    /// Inputs held during this frame, as `plugin_api::INPUT_*` bits
+    pub inputs: u32,
+    /// Language of the drawn strings
+    pub locale: Locale,
+}
+
+impl<'a> RenderCtx<'a> {
+    /// Context without a layout or inputs, in English
+    pub const fn new(frame: u32, now_ms: u64) -> Self {
+        Self {
+            frame,
+            now_ms,
+            layout: None,
+            stale: false,
+            inputs: 0,
+            locale: Locale::En,
+        }
+    }
+
+    /// Set the layout to show
+    pub const fn with_layout(self, layout: &'a Layout, stale: bool) -> Self {
+        Self {
+            layout: Some(layout),
+            stale,
+            ..self
+        }
+    }
+
+    /// Set the language of the drawn strings
+    pub const fn with_locale(self, locale: Locale) -> Self {
+        Self { locale, ..self }
+    }
+}
+
+/// A full-screen view drawn into `D`
+pub trait Renderer<D: DrawTarget<Color = Rgb565>> {
+    /// Draw a complete frame
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error>;
+}
+
+/// Draw the cluster map without caching; blank until a layout is loaded
+impl<D: DrawTarget<Color = Rgb565>> Renderer<D> for ClusterRenderer {
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        let Some(layout) = ctx.layout else {
+            return target.clear(visual::BACKGROUND);
+        };
+        self.set_stale(ctx.stale);
+        self.set_locale(ctx.locale);
+        self.render_frame(target, layout, ctx.frame)
+    }
+}
+
+/// Cluster map with a cached background and occupancy alerts
+///
+/// The inputs of each frame zoom and pan the seat map, see [`MapZoom`].
+pub struct ClusterView<'a> {
+    renderer: ClusterRenderer,
+    cache: &'a mut BackgroundCache,
+    alerts: OccupancyAlerts,
+    zoom: MapZoom,
+}
+
+impl<'a> ClusterView<'a> {
+    pub const fn new(cache: &'a mut BackgroundCache, thresholds: AlertThresholds) -> Self {
+        Self {
+            renderer: ClusterRenderer::new(),
+            cache,
+            alerts: OccupancyAlerts::new(thresholds),
+            zoom: MapZoom::new(),
+        }
+    }
+
+    /// Renderer settings (selected cluster, grid, ...)
+    pub const fn renderer_mut(&mut self) -> &mut ClusterRenderer {
+        &mut self.renderer
+    }
+
+    /// Redraw the background on the next frame, e.g. after a layout change
+    pub const fn invalidate(&mut self) {
+        self.cache.invalidate();
+    }
+
+    /// Set the color scheme, redrawing the background on the next frame
+    pub const fn set_theme(&mut self, theme: Theme) {
+        self.renderer.set_theme(theme);
+        self.cache.invalidate();
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Renderer<D> for ClusterView<'_> {
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        let Some(layout) = ctx.layout else {
+            return target.clear(visual::BACKGROUND);
+        };
+        self.renderer.set_stale(ctx.stale);
+        self.renderer.set_locale(ctx.locale);
+        self.renderer.set_alert(self.alerts.update(layout));
+
+        let grid = self.renderer.cluster_grid_space(layout);
+        self.zoom.update(ctx.inputs, grid);
+        let zoom = self.zoom.zoom(grid);
+        if zoom != self.renderer.zoom() {
+            // Zone names are part of the cached background
+            self.renderer.set_zoom(zoom);
+            self.cache.invalidate();
+        }
+        self.renderer
+            .render_frame_cached(target, layout, ctx.frame, self.cache)
+    }
+}
+
+/// Occupancy history of the selected cluster
+pub struct HistoryView<const N: usize> {
+    renderer: ClusterRenderer,
+    trend: TrendTracker<N>,
+}
+
+impl<const N: usize> HistoryView<N> {
+    /// Create a view sampling occupancy at most once per `interval_ms`
+    pub const fn new(interval_ms: u64) -> Self {
+        Self {
+            renderer: ClusterRenderer::new(),
+            trend: TrendTracker::new(interval_ms),
+        }
+    }
+
+    /// Renderer settings (selected cluster, ...)
+    pub const fn renderer_mut(&mut self) -> &mut ClusterRenderer {
+        &mut self.renderer
+    }
+
+    /// Sample the occupancy of the selected cluster
+    ///
+    /// Call every frame, whether the view is shown or not, so the history
+    /// has no gaps.
+    pub fn record(&mut self, layout: &Layout, now_ms: u64) {
+        if let Some(cluster) = layout.get(self.renderer.selected_cluster()) {
+            self.trend
+                .record(now_ms, cluster.get_stats().occupancy_percentage());
+        }
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb565>, const N: usize> Renderer<D> for HistoryView<N> {
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        let Some(layout) = ctx.layout else {
+            return target.clear(visual::BACKGROUND);
+        };
+        self.renderer.set_locale(ctx.locale);
+        self.renderer
+            .render_history_page(target, layout, self.trend.samples())
+    }
+}
+
+/// Frame-based animation, e.g. from `graphics_common::animations`
+pub struct AnimationView<D: DrawTarget> {
+    draw: fn(&mut D, u32) -> Result<(), D::Error>,
+}
+
+impl<D: DrawTarget> AnimationView<D> {
+    pub const fn new(draw: fn(&mut D, u32) -> Result<(), D::Error>) -> Self {
+        Self { draw }
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Renderer<D> for AnimationView<D> {
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        (self.draw)(target, ctx.frame)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::types::{Kind, Status};
+    use crate::visualization::cache::BackgroundSurface;
+    use crate::visualization::display::{DEFAULT_LAYOUT, DISPLAY_HEIGHT, Palette};
+    use crate::{cluster, layout, seat};
+    use core::convert::Infallible;
+    use std::boxed::Box;
+
+    fn sample_layout() -> Layout {
+        layout! {
+            f0: cluster! {
+                message: "",
+                name: "F0",
+                attributes: [],
+                seats: [
+                    seat!("f0r1s1", Kind::Mac, Status::Taken, 0, 0),
+                    seat!("f0r1s2", Kind::Mac, Status::Free, 1, 0)
+                ],
+                zones: []
+            }
+        }
+    }
+
+    fn pixels(surface: &BackgroundSurface) -> impl Iterator<Item = Rgb565> + '_ {
+        surface
+            .bounding_box()
+            .points()
+            .filter_map(|point| surface.pixel(point))
+    }
+
+    fn fill_red(target: &mut BackgroundSurface, _frame: u32) -> Result<(), Infallible> {
+        target.clear(Rgb565::RED)
+    }
+
+    #[test]
+    fn test_views_are_interchangeable() {
+        let layout = sample_layout();
+        let mut cache = Box::new(BackgroundCache::new());
+        let mut cluster = ClusterView::new(&mut cache, AlertThresholds::DEFAULT);
+        let mut history = HistoryView::<8>::new(0);
+        let mut animation = AnimationView::new(fill_red);
+
+        history.record(&layout, 0);
+        let ctx = RenderCtx::new(0, 0).with_layout(&layout, false);
+        let mut expected = Box::new(BackgroundSurface::new());
+        let mut actual = Box::new(BackgroundSurface::new());
+
+        // Each view draws what its underlying renderer draws
+        let Ok(()) = ClusterRenderer::new().render_frame(&mut *expected, &layout, 0);
+        let views: [&mut dyn Renderer<BackgroundSurface>; 3] =
+            [&mut cluster, &mut history, &mut animation];
+        for view in views {
+            let Ok(()) = view.render(&mut actual, &ctx);
+        }
+        assert!(pixels(&actual).all(|pixel| pixel == Rgb565::RED));
+
+        let Ok(()) = Renderer::render(&mut ClusterRenderer::new(), &mut *actual, &ctx);
+        assert!(pixels(&actual).eq(pixels(&expected)));
+    }
+
+    #[test]
+    fn test_theme_change_redraws_background() {
+        let layout = sample_layout();
+        let mut cache = Box::new(BackgroundCache::new());
+        let mut view = ClusterView::new(&mut cache, AlertThresholds::DEFAULT);
+        let mut surface = Box::new(BackgroundSurface::new());
+        let ctx = RenderCtx::new(0, 0).with_layout(&layout, false);
+        let corner = Point::new(0, DISPLAY_HEIGHT as i32 - 1);
+
+        let Ok(()) = view.render(&mut *surface, &ctx);
+        assert_eq!(surface.pixel(corner), Some(Palette::DARK.background));
+
+        view.set_theme(Theme::Light);
+        let Ok(()) = view.render(&mut *surface, &ctx);
+        assert_eq!(surface.pixel(corner), Some(Palette::LIGHT.background));
+    }
+
+    #[test]
+    fn test_seat_labels_only_when_zoomed() {
+        use crate::visualization::grid::{CellScale, GridSpace};
+
+        let plain = sample_layout();
+        let mut labeled = sample_layout();
+        labeled.f0.seats[0].label = Some("JD".into());
+        let render = |layout: &Layout, scale: u32| {
+            let mut renderer = ClusterRenderer::new();
+            renderer.set_grid_space(
+                GridSpace::new(DEFAULT_LAYOUT.cluster_area, Size::zero())
+                    .with_scale(CellScale::Fixed { num: scale, den: 1 }),
+            );
+            let mut surface = Box::new(BackgroundSurface::new());
+            let Ok(()) = renderer.render_frame(&mut *surface, layout, 0);
+            surface
+        };
+
+        // 2x2 pixel seats leave no room for text
+        assert!(pixels(&render(&labeled, 1)).eq(pixels(&render(&plain, 1))));
+        // 8x8 pixel seats fit both characters
+        assert!(!pixels(&render(&labeled, 4)).eq(pixels(&render(&plain, 4))));
+    }
+
+    #[test]
+    fn test_buttons_zoom_the_map() {
+        let layout = sample_layout();
+        let mut cache = Box::new(BackgroundCache::new());
+        let mut view = ClusterView::new(&mut cache, AlertThresholds::DEFAULT);
+        let mut surface = Box::new(BackgroundSurface::new());
+        let inside_first_seat = DEFAULT_LAYOUT.cluster_area.top_left + Point::new(1, 3);
+
+        let Ok(()) = view.render(
+            &mut *surface,
+            &RenderCtx::new(0, 0).with_layout(&layout, false),
+        );
+        assert_eq!(surface.pixel(inside_first_seat), Some(visual::BACKGROUND));
+
+        // Holding A (`INPUT_A`) zooms in one level, over a few frames
+        let mut ctx = RenderCtx::new(0, 0).with_layout(&layout, false);
+        ctx.inputs = 1 << 4;
+        for frame in 1..30 {
+            ctx.frame = frame;
+            let Ok(()) = view.render(&mut *surface, &ctx);
+        }
+        assert_eq!(surface.pixel(inside_first_seat), Some(Rgb565::BLUE));
+    }
+
+    #[test]
+    fn test_blank_without_layout() {
+        let mut surface = Box::new(BackgroundSurface::new());
+        let Ok(()) = surface.clear(Rgb565::RED);
+
+        let mut history = HistoryView::<8>::new(0);
+        let Ok(()) = history.render(&mut *surface, &RenderCtx::new(0, 0));
+        assert!(pixels(&surface).all(|pixel| pixel == visual::BACKGROUND));
+    }
+}
@@ -0,0 +1,304 @@
+//! Zoom and pan of the cluster map
+//!
+//! [`MapZoom`] turns the panel's buttons into a [`Zoom`] of the seat map: A
+//! zooms in and B zooms out one level per press, and the D-pad pans while
+//! held. Zoom and position ease towards their target over a few frames
+//! instead of jumping, and the view is clamped to the seat extent so the map
+//! never scrolls off. [`ClusterView`](super::ClusterView) drives it from
+//! [`RenderCtx::inputs`](super::RenderCtx::inputs).
+
+use crate::visualization::grid::{CellScale, GridSpace};
+use embedded_graphics::prelude::*;
+
+// Same bits as `plugin_api::INPUT_*`, which `RenderCtx::inputs` carries
+const INPUT_UP: u32 = 1 << 0;
+const INPUT_DOWN: u32 = 1 << 1;
+const INPUT_LEFT: u32 = 1 << 2;
+const INPUT_RIGHT: u32 = 1 << 3;
+const INPUT_A: u32 = 1 << 4;
+const INPUT_B: u32 = 1 << 5;
+
+/// Highest zoom level, in multiples of the grid space's own scale
+pub const MAX_ZOOM: u32 = 4;
+
+/// Pixels panned per frame while the D-pad is held
+pub const PAN_STEP: i32 = 2;
+
+/// Fixed-point one for zoom factors
+const ZOOM_ONE: u32 = 256;
+
+/// Fixed-point one for world coordinates of the view center
+const CENTER_ONE: i64 = 256;
+
+/// Share of the remaining distance covered each frame, as a divisor
+const EASE: i64 = 4;
+
+/// Zoom factor and scroll applied on top of a grid space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Zoom {
+    /// Zoom factor, [`Zoom::NONE`] being 1
+    factor: u32,
+    /// Pixels scrolled, in the zoomed grid
+    scroll: Point,
+}
+
+impl Zoom {
+    /// The grid space as configured
+    pub const NONE: Self = Self {
+        factor: ZOOM_ONE,
+        scroll: Point::zero(),
+    };
+
+    /// Apply the zoom to a grid space with its world set
+    pub fn apply(self, grid: GridSpace) -> GridSpace {
+        let (num, den) = grid.ratio();
+        grid.with_scale(CellScale::Fixed {
+            num: num * self.factor,
+            den: den * ZOOM_ONE,
+        })
+        .with_scroll(self.scroll)
+    }
+
+    /// Check whether the map is magnified
+    pub const fn is_zoomed(self) -> bool {
+        self.factor > ZOOM_ONE
+    }
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Zoom and pan state driven by the buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapZoom {
+    /// Zoom level pressed to, 1 to [`MAX_ZOOM`]
+    level: u32,
+    /// Zoom factor shown, easing towards `level`
+    factor: u32,
+    /// View center aimed at, in oriented world units times `CENTER_ONE`
+    target: Point,
+    /// View center shown, easing towards `target`
+    center: Point,
+    /// Inputs of the previous update, to zoom once per press
+    held: u32,
+}
+
+impl MapZoom {
+    pub const fn new() -> Self {
+        Self {
+            level: 1,
+            factor: ZOOM_ONE,
+            target: Point::zero(),
+            center: Point::zero(),
+            held: 0,
+        }
+    }
+
+    /// Zoom level pressed to, 1 when not zoomed
+    pub const fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Check whether the view is still easing towards its target
+    pub fn is_moving(&self) -> bool {
+        self.factor != self.level * ZOOM_ONE || self.center != self.target
+    }
+
+    /// Handle this frame's inputs and advance the transition
+    ///
+    /// `grid` maps the shown cluster without zoom, e.g. from
+    /// [`ClusterRenderer::cluster_grid_space`](super::ClusterRenderer::cluster_grid_space).
+    /// Returns whether [`zoom`](Self::zoom) changed.
+    pub fn update(&mut self, inputs: u32, grid: GridSpace) -> bool {
+        let before = self.zoom(grid);
+        let pressed = inputs & !self.held;
+        self.held = inputs;
+
+        let level = if pressed & INPUT_A != 0 {
+            (self.level + 1).min(MAX_ZOOM)
+        } else if pressed & INPUT_B != 0 {
+            self.level.saturating_sub(1).max(1)
+        } else {
+            self.level
+        };
+        if level != self.level {
+            // Zoom around what is shown, or back to the whole map
+            self.target = match level {
+                1 => Point::zero(),
+                _ => {
+                    let shown = self.visible_center(self.center, self.factor, grid);
+                    self.visible_center(shown, level * ZOOM_ONE, grid)
+                }
+            };
+            self.level = level;
+        }
+
+        let pan = Point::new(
+            i32::from(inputs & INPUT_RIGHT != 0) - i32::from(inputs & INPUT_LEFT != 0),
+            i32::from(inputs & INPUT_DOWN != 0) - i32::from(inputs & INPUT_UP != 0),
+        );
+        if pan != Point::zero() && self.level > 1 {
+            // The target stays on the map, so panning back reacts at once
+            let factor = self.level * ZOOM_ONE;
+            let step = to_world(i64::from(PAN_STEP), factor, grid) as i32;
+            self.target = self.visible_center(self.target + pan * step.max(1), factor, grid);
+        }
+
+        self.factor = ease(i64::from(self.factor), i64::from(self.level * ZOOM_ONE)) as u32;
+        self.center = Point::new(
+            ease(self.center.x.into(), self.target.x.into()) as i32,
+            ease(self.center.y.into(), self.target.y.into()) as i32,
+        );
+        self.zoom(grid) != before
+    }
+
+    /// Zoom to apply to `grid` this frame
+    pub fn zoom(&self, grid: GridSpace) -> Zoom {
+        Zoom {
+            factor: self.factor,
+            scroll: scroll_for(self.center, self.factor, grid),
+        }
+    }
+
+    /// World center of the area once `center` is clamped to the map
+    fn visible_center(&self, center: Point, factor: u32, grid: GridSpace) -> Point {
+        let half = available(grid) / 2;
+        let scroll = scroll_for(center, factor, grid);
+        Point::new(
+            to_world(i64::from(scroll.x) + i64::from(half.width), factor, grid) as i32,
+            to_world(i64::from(scroll.y) + i64::from(half.height), factor, grid) as i32,
+        )
+    }
+}
+
+impl Default for MapZoom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Move `current` part of the way to `target`, landing on it when close
+fn ease(current: i64, target: i64) -> i64 {
+    let step = (target - current) / EASE;
+    if step == 0 { target } else { current + step }
+}
+
+/// Pixels left for the world inside the grid's margins
+fn available(grid: GridSpace) -> Size {
+    let area = grid.area().size;
+    Size::new(
+        area.width.saturating_sub(2 * grid.margin()),
+        area.height.saturating_sub(2 * grid.margin()),
+    )
+}
+
+/// Convert zoomed pixels to world units times `CENTER_ONE`
+fn to_world(pixels: i64, factor: u32, grid: GridSpace) -> i64 {
+    let (num, den) = grid.ratio();
+    pixels * CENTER_ONE * i64::from(den) * i64::from(ZOOM_ONE)
+        / (i64::from(num) * i64::from(factor)).max(1)
+}
+
+/// Convert world units times `CENTER_ONE` to zoomed pixels, rounded
+fn to_pixels(world: i64, factor: u32, grid: GridSpace) -> i64 {
+    let (num, den) = grid.ratio();
+    let divisor = CENTER_ONE * i64::from(den) * i64::from(ZOOM_ONE);
+    (world * i64::from(num) * i64::from(factor) + divisor / 2).div_euclid(divisor)
+}
+
+/// Scroll centering the area on `center`, without leaving the map
+fn scroll_for(center: Point, factor: u32, grid: GridSpace) -> Point {
+    let world = grid.oriented_world();
+    let avail = available(grid);
+    let axis = |center: i32, world: u32, avail: u32| {
+        let extent = to_pixels(i64::from(world) * CENTER_ONE, factor, grid);
+        let max = (extent - i64::from(avail)).max(0);
+        let scroll = to_pixels(center.into(), factor, grid) - i64::from(avail / 2);
+        scroll.clamp(0, max) as i32
+    };
+    Point::new(
+        axis(center.x, world.width, avail.width),
+        axis(center.y, world.height, avail.height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::primitives::Rectangle;
+
+    const AREA: Rectangle = Rectangle::new(Point::new(10, 20), Size::new(80, 60));
+
+    /// Update until the transition is over, returning the frames it took
+    fn settle(zoom: &mut MapZoom, inputs: u32, grid: GridSpace) -> u32 {
+        let mut frames = 0;
+        while zoom.is_moving() {
+            zoom.update(inputs, grid);
+            frames += 1;
+            assert!(frames < 100, "never settles");
+        }
+        frames
+    }
+
+    #[test]
+    fn test_zoom_eases_around_the_center() {
+        let grid = GridSpace::new(AREA, Size::new(80, 60));
+        let mut zoom = MapZoom::new();
+        assert_eq!(zoom.zoom(grid), Zoom::NONE);
+
+        // One level per press, however long A is held
+        assert!(zoom.update(INPUT_A, grid));
+        let halfway = zoom.zoom(grid);
+        assert!(halfway.is_zoomed() && halfway.factor < 2 * ZOOM_ONE);
+        assert!(settle(&mut zoom, INPUT_A, grid) > 1);
+        assert_eq!(zoom.level(), 2);
+        assert!(!zoom.is_moving());
+
+        // The middle of the map stays in the middle of the area
+        let zoomed = zoom.zoom(grid).apply(grid);
+        assert_eq!(zoomed.to_pixel(Point::new(40, 30)), Point::new(50, 50));
+
+        zoom.update(0, grid);
+        zoom.update(INPUT_B, grid);
+        settle(&mut zoom, 0, grid);
+        assert_eq!(zoom.level(), 1);
+        assert_eq!(zoom.zoom(grid), Zoom::NONE);
+    }
+
+    #[test]
+    fn test_pan_is_clamped_to_the_map() {
+        let grid = GridSpace::new(AREA, Size::new(80, 60));
+        let mut zoom = MapZoom::new();
+
+        // No panning at the whole-map level
+        assert!(!zoom.update(INPUT_RIGHT, grid));
+
+        for inputs in [INPUT_A, 0, INPUT_A] {
+            zoom.update(inputs, grid);
+        }
+        settle(&mut zoom, 0, grid);
+        assert_eq!(zoom.level(), 3);
+
+        // Far past the bottom-right corner: the map's corner is the area's
+        for _ in 0..200 {
+            zoom.update(INPUT_RIGHT | INPUT_DOWN, grid);
+        }
+        settle(&mut zoom, 0, grid);
+        let zoomed = zoom.zoom(grid).apply(grid);
+        assert_eq!(zoomed.to_pixel(Point::new(80, 60)), Point::new(90, 80));
+
+        // Panning back starts right away, easing in
+        assert!(zoom.update(INPUT_LEFT, grid) | zoom.update(INPUT_LEFT, grid));
+        for _ in 0..200 {
+            zoom.update(INPUT_LEFT | INPUT_UP, grid);
+        }
+        settle(&mut zoom, 0, grid);
+        assert_eq!(
+            zoom.zoom(grid).apply(grid).to_pixel(Point::zero()),
+            AREA.top_left
+        );
+    }
+}
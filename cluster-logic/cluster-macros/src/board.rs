@@ -0,0 +1,176 @@
+//! Board description files for `matrix_config!`
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use serde::Deserialize;
+use syn::LitStr;
+
+/// Highest GPIO number of the RP2350 (48 on the B package)
+const MAX_GPIO: u8 = 47;
+
+/// Contents of a `board.toml`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Board {
+    pub panel: Panel,
+    pub pins: Pins,
+}
+
+/// Logical size of the panel (or chain of panels)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Panel {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// GPIO numbers of the Hub75 signals
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pins {
+    pub r1: u8,
+    pub g1: u8,
+    pub b1: u8,
+    pub r2: u8,
+    pub g2: u8,
+    pub b2: u8,
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub clk: u8,
+    pub lat: u8,
+    pub oe: u8,
+}
+
+impl Pins {
+    /// Field of the generated struct and GPIO of each signal, in the order
+    /// of the driver constructor
+    fn fields(&self) -> [(&'static str, u8); 14] {
+        [
+            ("r1_pin", self.r1),
+            ("g1_pin", self.g1),
+            ("b1_pin", self.b1),
+            ("r2_pin", self.r2),
+            ("g2_pin", self.g2),
+            ("b2_pin", self.b2),
+            ("clk_pin", self.clk),
+            ("a_pin", self.a),
+            ("b_pin", self.b),
+            ("c_pin", self.c),
+            ("d_pin", self.d),
+            ("e_pin", self.e),
+            ("lat_pin", self.lat),
+            ("oe_pin", self.oe),
+        ]
+    }
+}
+
+impl Board {
+    /// Check the pins and the panel size, reporting every problem at `source`
+    pub fn validate(&self, file_path: &str, source: &LitStr) -> syn::Result<()> {
+        let mut errors: Option<syn::Error> = None;
+        let mut report = |message: String| {
+            let error = syn::Error::new(source.span(), format!("{file_path}: {message}"));
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        };
+
+        if self.panel.width == 0 || self.panel.height == 0 {
+            report("panel width and height must not be zero".to_owned());
+        }
+
+        let fields = self.pins.fields();
+        // Name the signals as in the file
+        let key = |name: &str| name.trim_end_matches("_pin").to_owned();
+        for (i, &(name, gpio)) in fields.iter().enumerate() {
+            if gpio > MAX_GPIO {
+                report(format!(
+                    "{} uses GPIO {gpio}, past the last GPIO ({MAX_GPIO})",
+                    key(name)
+                ));
+            }
+            if let Some((other, _)) = fields[..i].iter().find(|&&(_, other)| other == gpio) {
+                report(format!(
+                    "{} and {} both use GPIO {gpio}",
+                    key(name),
+                    key(other)
+                ));
+            }
+        }
+
+        match errors {
+            Some(errors) => Err(errors),
+            None => Ok(()),
+        }
+    }
+
+    /// Items generated for the board
+    pub fn generate(&self) -> TokenStream {
+        let Panel { width, height } = self.panel;
+        let fields = self.pins.fields();
+        let names: Vec<_> = fields
+            .iter()
+            .map(|&(name, _)| format_ident!("{name}"))
+            .collect();
+        let gpios: Vec<_> = fields
+            .iter()
+            .map(|&(_, gpio)| format_ident!("PIN_{gpio}"))
+            .collect();
+        quote! {
+            /// Logical display size from the board description
+            #[allow(dead_code)]
+            pub const DISPLAY_WIDTH: u32 = #width;
+            #[allow(dead_code)]
+            pub const DISPLAY_HEIGHT: u32 = #height;
+
+            // The driver geometry comes from its features, make sure it
+            // covers the same area as the board description
+            const _: () = assert!(
+                hub75_rp2350_driver::DISPLAY_WIDTH * hub75_rp2350_driver::DISPLAY_HEIGHT
+                    == (#width * #height) as usize,
+                "the board panel size doesn't match the hub75-rp2350-driver size feature"
+            );
+
+            /// Hub75 pins from the board description
+            pub struct Hub75Pins {
+                #(pub #names: embassy_rp::Peri<'static, embassy_rp::peripherals::#gpios>,)*
+            }
+
+            impl Hub75Pins {
+                /// Start the driver on these pins
+                pub fn into_display(
+                    self,
+                    pio: embassy_rp::Peri<'static, embassy_rp::peripherals::PIO0>,
+                    dma_channels: (
+                        embassy_rp::Peri<'static, embassy_rp::peripherals::DMA_CH0>,
+                        embassy_rp::Peri<'static, embassy_rp::peripherals::DMA_CH1>,
+                        embassy_rp::Peri<'static, embassy_rp::peripherals::DMA_CH2>,
+                        embassy_rp::Peri<'static, embassy_rp::peripherals::DMA_CH3>,
+                    ),
+                    memory: &'static mut hub75_rp2350_driver::DisplayMemory,
+                ) -> hub75_rp2350_driver::Hub75<'static> {
+                    hub75_rp2350_driver::Hub75::new(
+                        pio,
+                        dma_channels,
+                        memory,
+                        #(self.#names,)*
+                    )
+                }
+            }
+
+            /// Move the board pins out of the `embassy_rp::init` peripherals
+            #[allow(unused_macros)]
+            macro_rules! hub75_pins {
+                ($p:ident) => {
+                    Hub75Pins {
+                        #(#names: $p.#gpios,)*
+                    }
+                };
+            }
+        }
+    }
+}
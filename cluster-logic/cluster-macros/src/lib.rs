@@ -1,47 +1,407 @@
+mod board;
+
+use board::Board;
+use cluster_core::models::Layout;
+use cluster_core::validate::{GridBounds, LayoutIssue, validate_layout};
 use proc_macro::TokenStream;
 use quote::quote;
 use std::fs;
-use std::path::Path;
-use syn::{LitStr, parse_macro_input};
+use std::path::{Path, PathBuf};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitInt, LitStr, Token, parenthesized, parse_macro_input};
+
+/// Macro arguments: a string and an optional grid size
+///
+/// The string is a file path, an environment variable or a directory
+/// depending on the macro.
+struct LayoutArgs {
+    source: LitStr,
+    grid: GridBounds,
+}
+
+impl Parse for LayoutArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let source = input.parse()?;
+        let mut grid = GridBounds::DEFAULT;
+
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "grid" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected `grid = (width, height)`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let content;
+            parenthesized!(content in input);
+            let width: LitInt = content.parse()?;
+            content.parse::<Token![,]>()?;
+            let height: LitInt = content.parse()?;
+            grid = GridBounds::new(width.base10_parse()?, height.base10_parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(Self { source, grid })
+    }
+}
 
 /// Compile-time JSON to Layout conversion macro
 ///
-/// Usage: `layout_from_json!("path/to/layout.json")`
+/// Usage: `layout_from_json!("path/to/layout.json")` or
+/// `layout_from_json!("path/to/layout.json", grid = (96, 64))`
 ///
 /// This macro reads a JSON file at compile time and generates
 /// the corresponding Layout struct initialization code.
 /// It automatically recompiles when the JSON file changes.
+///
+/// Besides the JSON shape, the layout is checked for duplicate seat IDs,
+/// seats sharing a position, seats and zones outside the grid (the panel
+/// resolution unless `grid` is given) and zones with neither a name nor
+/// attributes. Each problem is reported as a compile error naming the entry.
 #[proc_macro]
 pub fn layout_from_json(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as LitStr);
-    let file_path = input.value();
+    let LayoutArgs { source, grid } = parse_macro_input!(input as LayoutArgs);
 
-    // Validate at compile time
-    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
-    let full_path = Path::new(&manifest_dir).join(&file_path);
+    expand_layout(&source.value(), &source, grid)
+        .unwrap_or_else(|errors| errors_expr(&errors))
+        .into()
+}
 
-    let json_content = fs::read_to_string(&full_path)
-        .unwrap_or_else(|e| panic!("Failed to read JSON file {file_path}: {e}"));
+/// Compile-time layout selected by an environment variable
+///
+/// Usage: `layout_from_json_env!("LAYOUT_PATH")`, optionally with the same
+/// `grid = (width, height)` argument as `layout_from_json!`
+///
+/// The variable holds the path of the JSON file, relative to the crate root,
+/// so CI can build one firmware per campus from the same code:
+/// `LAYOUT_PATH=layouts/paris.json cargo build --release`. Changing the
+/// variable triggers a rebuild.
+#[proc_macro]
+pub fn layout_from_json_env(input: TokenStream) -> TokenStream {
+    let LayoutArgs { source, grid } = parse_macro_input!(input as LayoutArgs);
+    let var = source.value();
 
-    // Validate JSON structure at compile time
-    let layout: cluster_core::models::Layout = serde_json::from_str(&json_content)
-        .unwrap_or_else(|e| panic!("Failed to parse JSON in {file_path}: {e}"));
+    let Ok(file_path) = std::env::var(&var) else {
+        let message =
+            format!("environment variable {var} is not set, it must hold the layout path");
+        return errors_expr(&syn::Error::new(source.span(), message)).into();
+    };
+
+    match expand_layout(&file_path, &source, grid) {
+        Ok(layout_code) => quote! {
+            {
+                // Rebuild when the variable changes
+                const _: &str = env!(#var);
+                #layout_code
+            }
+        }
+        .into(),
+        Err(errors) => errors_expr(&errors).into(),
+    }
+}
+
+/// Compile-time map of every layout in a directory
+///
+/// Usage: `layouts_from_dir!("layouts/")`, optionally with the same
+/// `grid = (width, height)` argument as `layout_from_json!`
+///
+/// Every `*.json` file in the directory (relative to the crate root) is
+/// parsed and validated, and embedded under its file stem
+/// (`layouts/paris.json` becomes `"paris"`). The macro evaluates to a
+/// `cluster_core::models::LayoutSet`; layouts are built on request.
+///
+/// Edits to the embedded files trigger a rebuild, but adding or removing a
+/// file doesn't: touch the invoking source file in that case.
+#[proc_macro]
+pub fn layouts_from_dir(input: TokenStream) -> TokenStream {
+    let LayoutArgs { source, grid } = parse_macro_input!(input as LayoutArgs);
+    let dir_path = source.value();
+
+    let full_path = manifest_dir().join(&dir_path);
+    let entries = fs::read_dir(&full_path)
+        .unwrap_or_else(|e| panic!("Failed to read layout directory {dir_path}: {e}"));
+    let mut files: Vec<(String, String)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_owned();
+            let file = Path::new(&dir_path).join(path.file_name()?);
+            Some((name, file.to_str()?.to_owned()))
+        })
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        let message = format!("no .json layouts found in {dir_path}");
+        return errors_expr(&syn::Error::new(source.span(), message)).into();
+    }
+
+    // Report the issues of every file before giving up
+    let mut errors: Option<syn::Error> = None;
+    let mut arms = Vec::new();
+    for (name, file) in &files {
+        match expand_layout(file, &source, grid) {
+            Ok(layout_code) => arms.push(quote! { #name => Some(#layout_code), }),
+            Err(error) => match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return errors_expr(&errors).into();
+    }
+
+    let names = files.iter().map(|(name, _)| name);
+    quote! {
+        {
+            fn build(name: &str) -> Option<cluster_core::models::Layout> {
+                match name {
+                    #(#arms)*
+                    _ => None,
+                }
+            }
+            cluster_core::models::LayoutSet::new(&[#(#names),*], build)
+        }
+    }
+    .into()
+}
+
+/// Compile-time seat statistics of a layout file
+///
+/// Usage: `const STATS: LayoutStats = layout_stats_from_json!("layout.json");`,
+/// optionally with the same `grid = (width, height)` argument as
+/// `layout_from_json!`
+///
+/// Evaluates to a constant `cluster_core::models::LayoutStats` holding the
+/// seat count and seat bounding box of each floor, so buffers can be sized
+/// at compile time and `ClusterRenderer::set_layout_stats` can skip
+/// recomputing the extent every frame.
+#[proc_macro]
+pub fn layout_stats_from_json(input: TokenStream) -> TokenStream {
+    let LayoutArgs { source, grid } = parse_macro_input!(input as LayoutArgs);
+    let file_path = source.value();
+
+    let layout = match load_layout(&file_path, &source, grid) {
+        Ok(layout) => layout,
+        Err(errors) => return errors_expr(&errors).into(),
+    };
+
+    let tracking = track_file(&file_path);
+    let floors = layout.stats().floors.map(|floor| {
+        let total_seats = floor.total_seats;
+        let cluster_core::models::SeatBounds {
+            min_x,
+            min_y,
+            width,
+            height,
+        } = floor.bounds;
+        quote! {
+            cluster_core::models::FloorStats {
+                total_seats: #total_seats,
+                bounds: cluster_core::models::SeatBounds {
+                    min_x: #min_x,
+                    min_y: #min_y,
+                    width: #width,
+                    height: #height,
+                },
+            }
+        }
+    });
+
+    quote! {
+        {
+            #tracking
+
+            cluster_core::models::LayoutStats {
+                floors: [#(#floors),*],
+            }
+        }
+    }
+    .into()
+}
+
+/// Board support generated from a board description file
+///
+/// Usage: `matrix_config!("board.toml")`, at module level
+///
+/// The TOML file (relative to the crate root) has a `[panel]` table with the
+/// logical `width` and `height`, and a `[pins]` table with the GPIO number
+/// of each Hub75 signal: `r1`, `g1`, `b1`, `r2`, `g2`, `b2`, `a` to `e`,
+/// `clk`, `lat` and `oe`. The macro expands to:
+///
+/// - `DISPLAY_WIDTH` and `DISPLAY_HEIGHT`, checked at compile time against
+///   the size feature of `hub75-rp2350-driver`;
+/// - a `Hub75Pins` struct with one `Peri` per signal, and
+///   `Hub75Pins::into_display` starting the driver on them;
+/// - a `hub75_pins!(p)` macro moving the pins out of the peripherals
+///   returned by `embassy_rp::init`.
+///
+/// Porting to a new board then only takes a new description file. Unknown
+/// keys, GPIOs used twice or out of range are compile errors.
+#[proc_macro]
+pub fn matrix_config(input: TokenStream) -> TokenStream {
+    let source = parse_macro_input!(input as LitStr);
+    let file_path = source.value();
+
+    let full_path = manifest_dir().join(&file_path);
+    let content = fs::read_to_string(&full_path)
+        .unwrap_or_else(|e| panic!("Failed to read board file {file_path}: {e}"));
+    let board: Board = toml::from_str(&content)
+        .unwrap_or_else(|e| panic!("Failed to parse TOML in {file_path}: {e}"));
+
+    if let Err(errors) = board.validate(&file_path, &source) {
+        return errors.to_compile_error().into();
+    }
+
+    let tracking = track_file(&file_path);
+    let items = board.generate();
+    quote! {
+        #tracking
+        #items
+    }
+    .into()
+}
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set"))
+}
+
+/// Read, validate and generate the code of a layout file
+///
+/// Validation errors are reported at `source`.
+fn expand_layout(
+    file_path: &str,
+    source: &LitStr,
+    grid: GridBounds,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let layout = load_layout(file_path, source, grid)?;
 
     // Generate initialization code
     let layout_code = generate_layout_code(&layout);
+    let tracking = track_file(file_path);
 
-    // Generate code that includes the file for change tracking
-    let code = quote! {
+    Ok(quote! {
         {
-            // This ensures Cargo tracks the file but we don't actually use it
-            const _: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #file_path));
+            #tracking
 
             // Return the pre-validated layout
             #layout_code
         }
+    })
+}
+
+/// Read and validate a layout file relative to the crate root
+fn load_layout(file_path: &str, source: &LitStr, grid: GridBounds) -> syn::Result<Layout> {
+    // Validate at compile time
+    let full_path = manifest_dir().join(file_path);
+
+    let json_content = fs::read_to_string(&full_path)
+        .unwrap_or_else(|e| panic!("Failed to read JSON file {file_path}: {e}"));
+
+    // Validate JSON structure at compile time
+    let layout: Layout = serde_json::from_str(&json_content)
+        .unwrap_or_else(|e| panic!("Failed to parse JSON in {file_path}: {e}"));
+
+    // Validate layout semantics, reporting every issue at once
+    let mut errors: Option<syn::Error> = None;
+    validate_layout(&layout, grid, |issue| {
+        let message = format!("{file_path}: {}", describe_issue(&layout, grid, issue));
+        let error = syn::Error::new(source.span(), message);
+        match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        }
+    });
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(layout),
+    }
+}
+
+/// Item including a file so Cargo rebuilds when it changes
+fn track_file(file_path: &str) -> proc_macro2::TokenStream {
+    let full_path = manifest_dir().join(file_path);
+    let full_path = full_path
+        .to_str()
+        .unwrap_or_else(|| panic!("Path {file_path} is not valid UTF-8"));
+
+    // This ensures Cargo tracks the file but we don't actually use it
+    quote! {
+        const _: &str = include_str!(#full_path);
+    }
+}
+
+/// Compile errors usable where an expression is expected
+fn errors_expr(errors: &syn::Error) -> proc_macro2::TokenStream {
+    // Wrapped in a block so several errors still form an expression
+    let errors = errors.to_compile_error();
+    quote! {{ #errors }}
+}
+
+/// Describe an issue, naming entries by their JSON path
+fn describe_issue(layout: &Layout, grid: GridBounds, issue: LayoutIssue) -> String {
+    let seat = |cluster, index: usize| {
+        let id = layout
+            .get(cluster)
+            .map_or("", |c| c.seats[index].id.as_str());
+        format!("{cluster}.seats[{index}] (id \"{id}\")")
+    };
+    let position = |cluster, index: usize| {
+        layout
+            .get(cluster)
+            .map_or((0, 0), |c| (c.seats[index].x, c.seats[index].y))
     };
 
-    code.into()
+    match issue {
+        LayoutIssue::DuplicateSeatId {
+            cluster,
+            seat: index,
+            first: (first_cluster, first),
+        } => format!(
+            "{} reuses the ID of {}",
+            seat(cluster, index),
+            seat(first_cluster, first)
+        ),
+        LayoutIssue::OverlappingSeats {
+            cluster,
+            seat: index,
+            other,
+        } => format!(
+            "{} shares position {:?} with {}",
+            seat(cluster, index),
+            position(cluster, index),
+            seat(cluster, other)
+        ),
+        LayoutIssue::SeatOutOfGrid {
+            cluster,
+            seat: index,
+        } => {
+            let (x, y) = position(cluster, index);
+            format!(
+                "{} at ({x}, {y}) is outside the {}x{} grid",
+                seat(cluster, index),
+                grid.width,
+                grid.height
+            )
+        }
+        LayoutIssue::ZoneOutOfGrid { cluster, zone } => {
+            let (x, y) = layout
+                .get(cluster)
+                .map_or((0, 0), |c| (c.zones[zone].x, c.zones[zone].y));
+            format!(
+                "{cluster}.zones[{zone}] at ({x}, {y}) is outside the {}x{} grid",
+                grid.width, grid.height
+            )
+        }
+        LayoutIssue::UnnamedZone { cluster, zone } => format!(
+            "{cluster}.zones[{zone}] has an empty name; only attribute-only zones may be unnamed"
+        ),
+    }
 }
 
 fn generate_layout_code(layout: &cluster_core::models::Layout) -> proc_macro2::TokenStream {
@@ -98,6 +458,10 @@ fn generate_cluster_code(cluster: &cluster_core::models::Cluster) -> proc_macro2
         };
         let x = seat.x;
         let y = seat.y;
+        let label = match &seat.label {
+            Some(label) => quote! { Some(#label.try_into().expect("Invalid seat label")) },
+            None => quote! { None },
+        };
 
         quote! {
             cluster_core::models::Seat {
@@ -106,6 +470,7 @@ fn generate_cluster_code(cluster: &cluster_core::models::Cluster) -> proc_macro2
                 status: #status,
                 x: #x,
                 y: #y,
+                label: #label,
             }
         }
     });
@@ -0,0 +1,173 @@
+//! Credentials sent with every request
+//!
+//! The cluster API wants an `Authorization: Bearer` token. Set one on the
+//! [`ClientConfig`](crate::client::ClientConfig) with
+//! [`with_auth`](crate::client::ClientConfig::with_auth), either as a fixed
+//! [`Auth::Bearer`] token or [`Auth::ApiKey`], or through a [`TokenSource`]
+//! for tokens that expire. Requests rejected with `401` or `403` fail with
+//! [`Error::Unauthorized`].
+//!
+//! Credentials follow redirects to the same host only.
+
+use crate::error::{Error, Result};
+use core::fmt;
+use heapless::String;
+
+/// Maximum length of a token or API key
+pub const MAX_TOKEN_LENGTH: usize = 512;
+
+/// Token or API key
+pub type Token = String<MAX_TOKEN_LENGTH>;
+
+/// Value of the credentials header, room for the `Bearer ` scheme included
+pub type Credentials = String<{ MAX_TOKEN_LENGTH + 7 }>;
+
+/// Source of a bearer token that changes over time
+///
+/// Fetching a token usually takes a request of its own, so the source only
+/// hands out the token it holds: a task of the application renews it, e.g.
+/// before it expires or when [`rejected`](Self::rejected) is called.
+pub trait TokenSource: Sync {
+    /// Current token, `None` while there is none
+    fn token(&self) -> Option<Token>;
+
+    /// The server answered `401 Unauthorized` to `token`
+    ///
+    /// The request fails with [`Error::Unauthorized`] all the same; renew
+    /// the token for the following ones.
+    fn rejected(&self, _token: &str) {}
+}
+
+/// Credentials of a client
+#[derive(Clone)]
+pub enum Auth {
+    /// Token sent as `Authorization: Bearer <token>`
+    Bearer(Token),
+    /// Key sent as the value of `header`, e.g. `X-Api-Key`
+    ApiKey { header: &'static str, key: Token },
+    /// Bearer token read from a source before each request
+    Source(&'static dyn TokenSource),
+}
+
+impl Auth {
+    /// Fixed bearer token
+    pub fn bearer(token: &str) -> Result<Self> {
+        Ok(Self::Bearer(
+            Token::try_from(token).map_err(|_| Error::BufferTooSmall)?,
+        ))
+    }
+
+    /// Fixed API key, sent in `header`
+    pub fn api_key(header: &'static str, key: &str) -> Result<Self> {
+        Ok(Self::ApiKey {
+            header,
+            key: Token::try_from(key).map_err(|_| Error::BufferTooSmall)?,
+        })
+    }
+
+    /// Name and value of the header carrying the credentials
+    ///
+    /// Fails with [`Error::Unauthorized`] when the source has no token yet,
+    /// without sending the request.
+    pub fn header(&self) -> Result<(&'static str, Credentials)> {
+        let mut value = Credentials::new();
+        let name = match self {
+            Self::Bearer(token) => {
+                push_bearer(&mut value, token)?;
+                "Authorization"
+            }
+            Self::ApiKey { header, key } => {
+                value.push_str(key).map_err(|_| Error::BufferTooSmall)?;
+                header
+            }
+            Self::Source(source) => {
+                let token = source.token().ok_or(Error::Unauthorized(401))?;
+                push_bearer(&mut value, &token)?;
+                "Authorization"
+            }
+        };
+        Ok((name, value))
+    }
+
+    /// Tell the source its token was rejected with `status`
+    pub(crate) fn rejected(&self, credentials: &str, status: u16) {
+        if let Self::Source(source) = self
+            && status == 401
+        {
+            source.rejected(credentials.strip_prefix("Bearer ").unwrap_or(credentials));
+        }
+    }
+}
+
+/// Tokens stay out of logs
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bearer(_) => write!(f, "Bearer(..)"),
+            Self::ApiKey { header, .. } => write!(f, "ApiKey({}: ..)", header),
+            Self::Source(_) => write!(f, "Source(..)"),
+        }
+    }
+}
+
+fn push_bearer(value: &mut Credentials, token: &str) -> Result<()> {
+    value
+        .push_str("Bearer ")
+        .and_then(|()| value.push_str(token))
+        .map_err(|_| Error::BufferTooSmall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    /// Hands out a token until it is rejected
+    struct Expiring {
+        expired: AtomicBool,
+    }
+
+    impl TokenSource for Expiring {
+        fn token(&self) -> Option<Token> {
+            match self.expired.load(Ordering::Relaxed) {
+                true => None,
+                false => Token::try_from("t0k3n").ok(),
+            }
+        }
+
+        fn rejected(&self, token: &str) {
+            assert_eq!(token, "t0k3n");
+            self.expired.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_headers() {
+        let (name, value) = Auth::bearer("abc").unwrap().header().unwrap();
+        assert_eq!((name, value.as_str()), ("Authorization", "Bearer abc"));
+
+        let (name, value) = Auth::api_key("X-Api-Key", "abc").unwrap().header().unwrap();
+        assert_eq!((name, value.as_str()), ("X-Api-Key", "abc"));
+
+        let long = [b'a'; MAX_TOKEN_LENGTH + 1];
+        let long = core::str::from_utf8(&long).unwrap();
+        assert!(matches!(Auth::bearer(long), Err(Error::BufferTooSmall)));
+    }
+
+    #[test]
+    fn test_token_source() {
+        static SOURCE: Expiring = Expiring {
+            expired: AtomicBool::new(false),
+        };
+        let auth = Auth::Source(&SOURCE);
+        let (_, value) = auth.header().unwrap();
+        assert_eq!(value, "Bearer t0k3n");
+
+        // Forbidden isn't about the token
+        auth.rejected(&value, 403);
+        assert!(auth.header().is_ok());
+
+        auth.rejected(&value, 401);
+        assert_eq!(auth.header().err(), Some(Error::Unauthorized(401)));
+    }
+}
@@ -0,0 +1,92 @@
+//! Cancelling requests in flight
+//!
+//! A [`CancelToken`] is shared between the task making requests and whoever
+//! decides they are no longer needed, e.g. the scheduler switching the panel
+//! to a mode that doesn't show cluster data. Once cancelled, the request in
+//! flight and every later one on a client holding the token fail with
+//! [`Error::Cancelled`](crate::Error::Cancelled) until the token is reset.
+
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+use embassy_sync::waitqueue::AtomicWaker;
+
+/// Flag aborting the requests of the clients holding it
+///
+/// Usable in a `static`. Only one request at a time should wait on a token,
+/// which is the case for the requests of a single client or
+/// [`SharedClient`](crate::SharedClient).
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    /// Create a token that is not cancelled
+    pub const fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    /// Abort the request in flight, and the following ones until [`reset`](Self::reset)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+
+    /// Let requests run again
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+
+    /// Check whether the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Wait until the token is cancelled
+    pub async fn cancelled(&self) {
+        poll_fn(|cx| {
+            // Register first so a cancel between the two can't be missed
+            self.waker.register(cx.waker());
+            if self.is_cancelled() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::pin::pin;
+    use core::task::{Context, Waker};
+
+    #[test]
+    fn test_cancel_wakes_the_waiting_request() {
+        let token = CancelToken::new();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut cancelled = pin!(token.cancelled());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+        token.cancel();
+        assert!(cancelled.as_mut().poll(&mut cx).is_ready());
+
+        // Stays cancelled until reset
+        assert!(pin!(token.cancelled()).poll(&mut cx).is_ready());
+        token.reset();
+        assert!(!token.is_cancelled());
+        assert!(pin!(token.cancelled()).poll(&mut cx).is_pending());
+    }
+}
@@ -1,21 +1,76 @@
 //! HTTP client implementation
 
+use crate::auth::Auth;
+use crate::cancel::CancelToken;
 use crate::error::{Error, Result};
+use crate::middleware::{
+    MAX_HEADER_VALUE_LENGTH, Method, Middleware, RequestContext, ResponseInfo,
+};
+use crate::validators::{HttpDate, ValidatorCache, Validators};
+use core::ops::Range;
+use embassy_futures::select::{Either3, select3};
+use embassy_time::Timer;
 use embedded_nal_async::{Dns, TcpConnect};
-use heapless::String;
+use heapless::{String, Vec};
 use reqwless::client::HttpClient;
-use reqwless::request::{Method, RequestBuilder};
+use reqwless::headers::ContentType;
+use reqwless::request::RequestBuilder;
 
 #[cfg(feature = "tls")]
 use reqwless::client::TlsConfig;
 
+/// Time budgets of the steps of a request, in milliseconds
+///
+/// Each step fails with [`Error::Timeout`] when it runs over its budget, so
+/// a server that accepts the connection and then hangs can't hold the
+/// client forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// DNS lookup and TCP connection
+    pub connect_ms: u32,
+    /// TLS handshake, for `https://` URLs
+    ///
+    /// reqwless opens the connection and runs the handshake in one call, so
+    /// this budget is added to `connect_ms` rather than timed on its own.
+    pub tls_handshake_ms: u32,
+    /// Sending the request and receiving the response headers
+    pub header_ms: u32,
+    /// Reading the response body
+    pub body_ms: u32,
+}
+
+impl Timeouts {
+    /// Same budget for every step
+    pub const fn uniform(timeout_ms: u32) -> Self {
+        Self {
+            connect_ms: timeout_ms,
+            tls_handshake_ms: timeout_ms,
+            header_ms: timeout_ms,
+            body_ms: timeout_ms,
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect_ms: 5000,
+            tls_handshake_ms: 10000, // Key exchange is slow on the RP2350
+            header_ms: 5000,
+            body_ms: 10000,
+        }
+    }
+}
+
 /// Configuration for the cluster API client
 #[derive(Debug, Clone)]
 pub struct ClientConfig<const URL_LEN: usize = 128> {
     /// Base URL of the cluster API server
     pub base_url: String<URL_LEN>,
-    /// Request timeout in milliseconds
-    pub timeout_ms: u32,
+    /// Budgets of the steps of each request
+    pub timeouts: Timeouts,
+    /// Credentials sent with every request
+    pub auth: Option<Auth>,
 }
 
 impl<const URL_LEN: usize> ClientConfig<URL_LEN> {
@@ -23,21 +78,138 @@ impl<const URL_LEN: usize> ClientConfig<URL_LEN> {
     pub fn new(base_url: &str) -> Result<Self> {
         Ok(Self {
             base_url: String::try_from(base_url).map_err(|_| Error::InvalidUrl)?,
-            timeout_ms: 5000, // 5 second default timeout
+            timeouts: Timeouts::default(),
+            auth: None,
         })
     }
 
-    /// Set the request timeout
+    /// Set the same timeout for every step of a request
     pub fn with_timeout(mut self, timeout_ms: u32) -> Self {
-        self.timeout_ms = timeout_ms;
+        self.timeouts = Timeouts::uniform(timeout_ms);
+        self
+    }
+
+    /// Set the budget of each step of a request
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Send `auth` with every request
+    ///
+    /// # Example
+    /// ```no_run
+    /// use cluster_net::auth::Auth;
+    /// use cluster_net::client::ClientConfig;
+    ///
+    /// let config: ClientConfig = ClientConfig::new("https://api.example.com")
+    ///     .unwrap()
+    ///     .with_auth(Auth::bearer("t0k3n").unwrap());
+    /// ```
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
         self
     }
 }
 
+/// Status of a `304 Not Modified` response
+const NOT_MODIFIED: u16 = 304;
+
+/// Maximum number of redirects followed by a request
+pub const MAX_REDIRECTS: usize = 3;
+
+/// Statuses sending the request on to their `Location` header
+const REDIRECTS: [u16; 5] = [301, 302, 303, 307, 308];
+
+/// Status of a `303 See Other` response, followed with a GET
+const SEE_OTHER: u16 = 303;
+
+/// Full URL of a request
+type Url = String<{ crate::MAX_URL_LENGTH }>;
+
+/// Entity tag of a response, sent back to only download a resource again if
+/// it changed
+pub type ETag = String<MAX_HEADER_VALUE_LENGTH>;
+
+/// Response to a conditional GET
+#[derive(Debug, PartialEq, Eq)]
+pub enum Conditional<'buf> {
+    /// The resource changed, or no ETag was sent
+    Modified {
+        body: &'buf [u8],
+        /// ETag of the new version, if the server sent one
+        etag: Option<ETag>,
+    },
+    /// The copy matching the ETag sent is still current
+    NotModified,
+}
+
+/// Response read by the client
+struct Response {
+    status: u16,
+    etag: Option<ETag>,
+    last_modified: Option<HttpDate>,
+    /// Where the body lies in the buffer, empty if it was streamed
+    body: Range<usize>,
+    /// Length of the body, streamed or not
+    body_len: usize,
+    /// Target of a redirect, whose body is left unread
+    location: Option<Url>,
+}
+
+/// Validators of a conditional request, answered with `304 Not Modified`
+/// if the resource didn't change
+#[derive(Debug, Clone, Copy, Default)]
+struct Preconditions<'r> {
+    /// ETag sent as `If-None-Match`
+    if_none_match: Option<&'r str>,
+    /// Date sent as `If-Modified-Since`
+    if_modified_since: Option<&'r str>,
+}
+
+impl Preconditions<'_> {
+    /// Whether a `304 Not Modified` answers the request
+    fn is_conditional(&self) -> bool {
+        self.if_none_match.is_some() || self.if_modified_since.is_some()
+    }
+}
+
+/// Consumer of a body read chunk by chunk, see
+/// [`Client::get_streamed`]
+#[cfg_attr(not(feature = "stream-parse"), allow(dead_code))]
+struct BodySink<'s> {
+    /// Buffer each chunk is read into
+    chunk: &'s mut [u8],
+    /// Called with each chunk, in order
+    sink: &'s mut dyn FnMut(&[u8]) -> Result<()>,
+    /// Media type asked for, sent as `Accept`
+    accept: &'static str,
+    /// The body stays open: bound the wait for each chunk with the body
+    /// timeout, instead of the whole body
+    open_ended: bool,
+}
+
+impl BodySink<'_> {
+    /// Borrow the sink again for one hop of a redirected request
+    fn reborrow(&mut self) -> BodySink<'_> {
+        BodySink {
+            chunk: &mut *self.chunk,
+            sink: &mut *self.sink,
+            accept: self.accept,
+            open_ended: self.open_ended,
+        }
+    }
+}
+
 /// HTTP client for cluster API
 pub struct Client<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize = 8192> {
     config: ClientConfig,
     http_client: HttpClient<'a, T, D>,
+    middleware: Option<&'a mut dyn Middleware>,
+    cancel: Option<&'a CancelToken>,
+    validators: ValidatorCache,
+    /// Created with TLS, able to follow redirects to `https://`
+    tls: bool,
 }
 
 impl<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize> Client<'a, T, D, BUF_SIZE> {
@@ -51,6 +223,10 @@ impl<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize> Client<'a, T, D, BUF_SIZE
         Self {
             config,
             http_client: HttpClient::new(tcp, dns),
+            middleware: None,
+            cancel: None,
+            validators: ValidatorCache::new(),
+            tls: false,
         }
     }
 
@@ -89,9 +265,83 @@ impl<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize> Client<'a, T, D, BUF_SIZE
         Self {
             config,
             http_client: HttpClient::new_with_tls(tcp, dns, tls_config),
+            middleware: None,
+            cancel: None,
+            validators: ValidatorCache::new(),
+            tls: true,
         }
     }
 
+    /// Attach middleware called around every request
+    ///
+    /// Use a tuple `(A, B)` to chain several middlewares.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use cluster_net::client::{Client, ClientConfig};
+    /// use cluster_net::middleware::{Middleware, RequestContext};
+    /// use cluster_net::Result;
+    ///
+    /// struct DeviceId;
+    ///
+    /// impl Middleware for DeviceId {
+    ///     fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> Result<()> {
+    ///         ctx.add_header("X-Device-Id", "matrix-01")
+    ///     }
+    /// }
+    ///
+    /// # fn example<'a, T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(
+    /// #     client: Client<'a, T, D>, device_id: &'a mut DeviceId
+    /// # ) {
+    /// let client = client.with_middleware(device_id);
+    /// # }
+    /// ```
+    pub fn with_middleware(mut self, middleware: &'a mut dyn Middleware) -> Self {
+        self.middleware = Some(middleware);
+        self
+    }
+
+    /// Replace or remove the attached middleware
+    pub fn set_middleware(&mut self, middleware: Option<&'a mut dyn Middleware>) {
+        self.middleware = middleware;
+    }
+
+    /// Abort requests with [`Error::Cancelled`] when `token` is cancelled
+    ///
+    /// # Example
+    /// ```no_run
+    /// use cluster_net::cancel::CancelToken;
+    /// use cluster_net::client::Client;
+    ///
+    /// static POLL_CANCEL: CancelToken = CancelToken::new();
+    ///
+    /// # fn example<'a, T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(
+    /// #     client: Client<'a, T, D>
+    /// # ) {
+    /// let client = client.with_cancel_token(&POLL_CANCEL);
+    ///
+    /// // From the task switching modes
+    /// POLL_CANCEL.cancel();
+    /// # }
+    /// ```
+    pub fn with_cancel_token(mut self, token: &'a CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Replace or remove the cancellation token
+    pub fn set_cancel_token(&mut self, token: Option<&'a CancelToken>) {
+        self.cancel = token;
+    }
+
+    /// Change the timeouts of the following requests
+    ///
+    /// Useful around a request that needs more time than the others, like
+    /// downloading the full layout.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.config.timeouts = timeouts;
+    }
+
     /// Perform a GET request to the specified path
     ///
     /// # Arguments
@@ -101,51 +351,605 @@ impl<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize> Client<'a, T, D, BUF_SIZE
     /// # Returns
     /// The number of bytes read into the buffer
     pub async fn get<'buf>(&mut self, path: &str, buffer: &'buf mut [u8]) -> Result<&'buf [u8]> {
+        let body = self.get_range(path, buffer).await?;
+        Ok(&buffer[body])
+    }
+
+    /// Perform a GET request, returning where the body lies in `buffer`
+    pub(crate) async fn get_range(
+        &mut self,
+        path: &str,
+        buffer: &mut [u8],
+    ) -> Result<Range<usize>> {
+        let response = self
+            .execute(Method::Get, path, None, None, None, None, buffer)
+            .await?;
+        Ok(response.body)
+    }
+
+    /// Perform a GET request, skipping the download if the resource still
+    /// matches `etag`
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/assets/banner")
+    /// * `etag` - ETag of the copy already held, sent as `If-None-Match`
+    /// * `buffer` - Buffer to store the response body
+    pub async fn get_conditional<'buf>(
+        &mut self,
+        path: &str,
+        etag: Option<&str>,
+        buffer: &'buf mut [u8],
+    ) -> Result<Conditional<'buf>> {
+        let response = self
+            .execute(Method::Get, path, None, etag, None, None, buffer)
+            .await?;
+        Ok(match response.status {
+            NOT_MODIFIED => Conditional::NotModified,
+            _ => Conditional::Modified {
+                body: &buffer[response.body],
+                etag: response.etag,
+            },
+        })
+    }
+
+    /// Perform a GET request, sending back the validators of the previous
+    /// response to `path`
+    ///
+    /// The `ETag` and `Last-Modified` headers of each successful response
+    /// are remembered and sent as `If-None-Match` and `If-Modified-Since` on
+    /// the next call for the same path, see [`validators`](crate::validators).
+    /// Returns [`Conditional::NotModified`] when the resource didn't change.
+    /// If the body can't be used, call
+    /// [`forget_validators`](Self::forget_validators) so the next call
+    /// downloads it again.
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/cluster/f0")
+    /// * `buffer` - Buffer to store the response body
+    pub async fn get_cached<'buf>(
+        &mut self,
+        path: &str,
+        buffer: &'buf mut [u8],
+    ) -> Result<Conditional<'buf>> {
+        let validators = self.validators.get(path).cloned().unwrap_or_default();
+        let response = self
+            .execute(
+                Method::Get,
+                path,
+                None,
+                validators.etag.as_deref(),
+                validators.last_modified.as_deref(),
+                None,
+                buffer,
+            )
+            .await?;
+        if response.status == NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+
+        self.validators.store(
+            path,
+            Validators {
+                etag: response.etag.clone(),
+                last_modified: response.last_modified,
+            },
+        );
+        Ok(Conditional::Modified {
+            body: &buffer[response.body],
+            etag: response.etag,
+        })
+    }
+
+    /// Forget the validators of `path`, so the next
+    /// [`get_cached`](Self::get_cached) downloads it in full
+    pub fn forget_validators(&mut self, path: &str) {
+        self.validators.forget(path);
+    }
+
+    /// Forget the validators of every path, e.g. after the data they
+    /// describe was dropped
+    pub fn clear_validators(&mut self) {
+        self.validators.clear();
+    }
+
+    /// Perform a GET request, handing the body to `sink` chunk by chunk
+    ///
+    /// Only the response headers have to fit in `buffer`: the body is read
+    /// into `chunk` and passed on as it arrives, so it can be larger than
+    /// both. An error returned by `sink` aborts the request. Used by
+    /// [`Endpoints::get_layout_streamed`](crate::endpoints::Endpoints::get_layout_streamed).
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/layout")
+    /// * `buffer` - Buffer to store the response headers
+    /// * `chunk` - Buffer each piece of the body is read into
+    /// * `sink` - Called with each piece of the body, in order
+    ///
+    /// # Returns
+    /// The length of the body
+    #[cfg(feature = "stream-parse")]
+    pub async fn get_streamed(
+        &mut self,
+        path: &str,
+        buffer: &mut [u8],
+        chunk: &mut [u8],
+        sink: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let stream = BodySink {
+            chunk,
+            sink,
+            accept: "application/json",
+            open_ended: false,
+        };
+        let response = self
+            .execute(Method::Get, path, None, None, None, Some(stream), buffer)
+            .await?;
+        Ok(response.body_len)
+    }
+
+    /// Perform a GET request on a Server-Sent Events stream, handing the
+    /// events to `sink` chunk by chunk as the server writes them
+    ///
+    /// The server keeps the response open, so only the wait for each chunk
+    /// is bounded, by [`Timeouts::body_ms`]: the server must write more often
+    /// than that, keep-alive comments included. Returns when the server
+    /// closes the stream, see [`events`](crate::events) to parse it.
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/cluster/f0/events")
+    /// * `buffer` - Buffer to store the response headers
+    /// * `chunk` - Buffer each piece of the stream is read into
+    /// * `sink` - Called with each piece of the stream, in order
+    #[cfg(feature = "live-updates")]
+    pub async fn get_event_stream(
+        &mut self,
+        path: &str,
+        buffer: &mut [u8],
+        chunk: &mut [u8],
+        sink: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let stream = BodySink {
+            chunk,
+            sink,
+            accept: "text/event-stream",
+            open_ended: true,
+        };
+        let response = self
+            .execute(Method::Get, path, None, None, None, Some(stream), buffer)
+            .await?;
+        Ok(response.body_len)
+    }
+
+    /// Perform a POST request with a JSON body to the specified path
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/devices/provision")
+    /// * `body` - Serialized JSON request body
+    /// * `buffer` - Buffer to store the response body
+    pub async fn post<'buf>(
+        &mut self,
+        path: &str,
+        body: &[u8],
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf [u8]> {
+        let response = self
+            .execute(Method::Post, path, Some(body), None, None, None, buffer)
+            .await?;
+        Ok(&buffer[response.body])
+    }
+
+    /// Perform a PUT request with a JSON body to the specified path
+    ///
+    /// # Arguments
+    /// * `path` - The API path to request (e.g., "/preferences")
+    /// * `body` - Serialized JSON request body
+    /// * `buffer` - Buffer to store the response body
+    pub async fn put<'buf>(
+        &mut self,
+        path: &str,
+        body: &[u8],
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf [u8]> {
+        let response = self
+            .execute(Method::Put, path, Some(body), None, None, None, buffer)
+            .await?;
+        Ok(&buffer[response.body])
+    }
+
+    /// Build the URL, run the middleware hooks and send the request
+    ///
+    /// With `if_none_match` or `if_modified_since`, a `304 Not Modified`
+    /// response is a success. With `stream`, the body is handed to it
+    /// instead of being read into `buffer`. Redirects are followed, and the
+    /// middleware only sees the request as first built.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute(
+        &mut self,
+        method: Method,
+        path: &str,
+        body: Option<&[u8]>,
+        if_none_match: Option<&str>,
+        if_modified_since: Option<&str>,
+        stream: Option<BodySink<'_>>,
+        buffer: &mut [u8],
+    ) -> Result<Response> {
         // Construct full URL
-        let mut url: String<{ crate::MAX_URL_LENGTH }> = String::new();
+        let mut url = Url::new();
         url.push_str(self.config.base_url.as_str())
             .map_err(|_| Error::InvalidUrl)?;
         url.push_str(path).map_err(|_| Error::InvalidUrl)?;
 
         #[cfg(feature = "defmt")]
-        defmt::debug!("GET {}", url.as_str());
+        defmt::debug!("{} {}", method.as_str(), url.as_str());
 
-        // Create request
-        let request = self
-            .http_client
-            .request(Method::GET, url.as_str())
-            .await
-            .map_err(|_| Error::HttpError)?;
+        let mut ctx = RequestContext::new(method, path, url.as_str());
+        let preconditions = Preconditions {
+            if_none_match,
+            if_modified_since,
+        };
 
-        // Add common headers
-        let headers = [("Accept", "application/json")];
-        let mut request_with_headers = request.headers(&headers);
+        let result = match self.middleware.as_deref_mut() {
+            Some(middleware) => middleware.before_request(&mut ctx),
+            None => Ok(()),
+        };
+        let result = match result {
+            Ok(()) => {
+                self.follow_redirects(&ctx, body, preconditions, stream, buffer)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
 
-        // Send request and get response
-        let response = request_with_headers
-            .send(buffer)
-            .await
-            .map_err(|_| Error::ConnectionError)?;
+        if let Some(middleware) = self.middleware.as_deref_mut() {
+            let info = match &result {
+                Ok(response) => ResponseInfo {
+                    status: Some(response.status),
+                    body_len: response.body_len,
+                    error: None,
+                },
+                Err(e) => ResponseInfo {
+                    status: match e {
+                        Error::InvalidStatus(status) | Error::Unauthorized(status) => Some(*status),
+                        _ => None,
+                    },
+                    body_len: 0,
+                    error: Some(*e),
+                },
+            };
+            middleware.after_response(&ctx, &info);
+        }
+
+        result
+    }
+
+    /// Send the request described by `ctx`, following redirects
+    ///
+    /// Up to [`MAX_REDIRECTS`] redirects are followed, each with the full
+    /// timeouts. `303 See Other` turns the request into a GET without body,
+    /// the other redirects repeat it as is. A redirect from `https://` to
+    /// `http://` is refused, and one to `https://` needs a client created
+    /// with TLS. The credentials, the `preconditions` and the headers of
+    /// `ctx` are only sent to the host of the original request, checked
+    /// again on every hop.
+    async fn follow_redirects(
+        &mut self,
+        ctx: &RequestContext<'_>,
+        mut body: Option<&[u8]>,
+        preconditions: Preconditions<'_>,
+        mut stream: Option<BodySink<'_>>,
+        buffer: &mut [u8],
+    ) -> Result<Response> {
+        let mut method = ctx.method;
+        let mut url = Url::try_from(ctx.url).map_err(|_| Error::InvalidUrl)?;
+        let mut visited: Vec<Url, MAX_REDIRECTS> = Vec::new();
+        let auth = self.config.auth.as_ref();
+        let header = auth.map(Auth::header).transpose()?;
+        let credentials = header.as_ref().map(|(name, value)| (*name, value.as_str()));
+
+        loop {
+            let same_origin = authority(&url) == authority(ctx.url);
+            let credentials = credentials.filter(|_| same_origin);
+            let response = Self::send(
+                &mut self.http_client,
+                same_origin.then_some(ctx),
+                method,
+                &url,
+                credentials,
+                body,
+                if same_origin {
+                    preconditions
+                } else {
+                    Preconditions::default()
+                },
+                self.config.timeouts,
+                self.cancel,
+                stream.as_mut().map(BodySink::reborrow),
+                &mut *buffer,
+            )
+            .await;
+            let response = match (response, auth, credentials) {
+                (Err(Error::Unauthorized(status)), Some(auth), Some((_, value))) => {
+                    auth.rejected(value, status);
+                    return Err(Error::Unauthorized(status));
+                }
+                (response, _, _) => response?,
+            };
+            let Some(location) = &response.location else {
+                return Ok(response);
+            };
+
+            let next = redirect_target(&url, location)?;
+            if url.starts_with("https://") && next.starts_with("http://") {
+                return Err(Error::InsecureRedirect);
+            }
+            if next.starts_with("https://") && !self.tls {
+                return Err(Error::TlsUnavailable);
+            }
+            if next == url || visited.contains(&next) {
+                return Err(Error::RedirectLoop);
+            }
 
-        // Check status code
-        let status = response.status;
-        if !(200..300).contains(&(status.0)) {
             #[cfg(feature = "defmt")]
-            defmt::error!("HTTP error: status {}", status.0);
-            return Err(Error::InvalidStatus(status.0));
+            defmt::debug!("Redirected ({}) to {}", response.status, next.as_str());
+
+            visited
+                .push(core::mem::replace(&mut url, next))
+                .map_err(|_| Error::TooManyRedirects)?;
+            if response.status == SEE_OTHER {
+                method = Method::Get;
+                body = None;
+            }
+        }
+    }
+
+    /// Send a request to `url`, with the headers of `ctx` if given, the
+    /// `credentials` header and the `preconditions`, and read the response
+    /// body into `buffer`
+    ///
+    /// A `304 Not Modified` response is accepted, with an empty body, if the
+    /// request has preconditions. A redirect is accepted if it has a
+    /// `Location`, its body left unread. The body of a request without one
+    /// is handed to `stream` if given. Each step is bounded by its timeout
+    /// and aborted when `cancel` is cancelled.
+    #[allow(clippy::too_many_arguments)]
+    async fn send(
+        http_client: &mut HttpClient<'a, T, D>,
+        ctx: Option<&RequestContext<'_>>,
+        method: Method,
+        url: &str,
+        credentials: Option<(&str, &str)>,
+        body: Option<&[u8]>,
+        preconditions: Preconditions<'_>,
+        timeouts: Timeouts,
+        cancel: Option<&CancelToken>,
+        stream: Option<BodySink<'_>>,
+        buffer: &mut [u8],
+    ) -> Result<Response> {
+        if cancel.is_some_and(CancelToken::is_cancelled) {
+            return Err(Error::Cancelled);
         }
 
-        // Read response body
-        let body = response
-            .body()
-            .read_to_end()
-            .await
+        let method = match method {
+            Method::Get => reqwless::request::Method::GET,
+            Method::Post => reqwless::request::Method::POST,
+            Method::Put => reqwless::request::Method::PUT,
+        };
+
+        // Connect, and run the TLS handshake for https URLs
+        let connect_ms = if url.starts_with("https://") {
+            timeouts
+                .connect_ms
+                .saturating_add(timeouts.tls_handshake_ms)
+        } else {
+            timeouts.connect_ms
+        };
+        let request = Self::step(http_client.request(method, url), connect_ms, cancel)
+            .await?
             .map_err(|_| Error::HttpError)?;
 
+        // Add common headers, followed by the ones added by middleware. The
+        // built-in ones have slots of their own, see `MAX_HEADERS`.
+        let mut headers: Vec<(&str, &str), { crate::MAX_HEADERS }> = Vec::new();
+        let accept = stream
+            .as_ref()
+            .map_or("application/json", |stream| stream.accept);
+        let builtin = [
+            Some(("Accept", accept)),
+            credentials,
+            preconditions
+                .if_none_match
+                .map(|etag| ("If-None-Match", etag)),
+            preconditions
+                .if_modified_since
+                .map(|date| ("If-Modified-Since", date)),
+        ];
+        for header in builtin
+            .into_iter()
+            .flatten()
+            .chain(ctx.into_iter().flat_map(RequestContext::headers))
+        {
+            headers.push(header).map_err(|_| Error::BufferTooSmall)?;
+        }
+        let conditional = preconditions.is_conditional();
+        let request = request.headers(&headers);
+
+        // Send request and read the response body. Attaching a body changes
+        // the request type, so each case drives its own request.
+        let buffer_start = buffer.as_ptr() as usize;
+        let buffer_len = buffer.len();
+        let response = match body {
+            Some(body) => {
+                let mut request = request
+                    .body(body)
+                    .content_type(ContentType::ApplicationJson);
+                let response = Self::step(request.send(buffer), timeouts.header_ms, cancel)
+                    .await?
+                    .map_err(|_| Error::ConnectionError)?;
+                let location = Self::location(response.status.0, response.headers());
+                let status = Self::check_status(response.status.0, conditional, &location)?;
+                let etag = Self::header(response.headers(), "ETag");
+                let last_modified = Self::header(response.headers(), "Last-Modified");
+                let body = match location {
+                    Some(_) => 0..0,
+                    None => {
+                        let body =
+                            Self::step(response.body().read_to_end(), timeouts.body_ms, cancel)
+                                .await?
+                                .map_err(|_| Error::HttpError)?;
+                        range_in(buffer_start, buffer_len, body)?
+                    }
+                };
+                Response {
+                    status,
+                    etag,
+                    last_modified,
+                    body_len: body.len(),
+                    body,
+                    location,
+                }
+            }
+            None => {
+                let mut request = request;
+                let response = Self::step(request.send(buffer), timeouts.header_ms, cancel)
+                    .await?
+                    .map_err(|_| Error::ConnectionError)?;
+                let location = Self::location(response.status.0, response.headers());
+                let status = Self::check_status(response.status.0, conditional, &location)?;
+                let etag = Self::header(response.headers(), "ETag");
+                let last_modified = Self::header(response.headers(), "Last-Modified");
+                let (body, body_len) = match (status, stream) {
+                    // A 304 has no body to read, nor is a redirect's needed
+                    (NOT_MODIFIED, _) => (0..0, 0),
+                    _ if location.is_some() => (0..0, 0),
+                    #[cfg(feature = "stream-parse")]
+                    (_, Some(stream)) => {
+                        let reader = response.body().reader();
+                        let len = if stream.open_ended {
+                            Self::stream_body(reader, stream, Some(timeouts.body_ms), cancel)
+                                .await?
+                        } else {
+                            let body = Self::stream_body(reader, stream, None, cancel);
+                            Self::step(body, timeouts.body_ms, cancel).await??
+                        };
+                        (0..0, len)
+                    }
+                    _ => {
+                        let body =
+                            Self::step(response.body().read_to_end(), timeouts.body_ms, cancel)
+                                .await?
+                                .map_err(|_| Error::HttpError)?;
+                        let body = range_in(buffer_start, buffer_len, body)?;
+                        let body_len = body.len();
+                        (body, body_len)
+                    }
+                };
+                Response {
+                    status,
+                    etag,
+                    last_modified,
+                    body,
+                    body_len,
+                    location,
+                }
+            }
+        };
+
         #[cfg(feature = "defmt")]
-        defmt::debug!("Response: {} bytes", body.len());
+        defmt::debug!("Response: {} bytes", response.body_len);
+
+        Ok(response)
+    }
 
-        Ok(body)
+    /// Read a body to its end, handing each chunk to `stream`
+    ///
+    /// Each read is bounded by `chunk_ms`, if given.
+    #[cfg(feature = "stream-parse")]
+    async fn stream_body(
+        mut reader: impl embedded_io_async::Read,
+        stream: BodySink<'_>,
+        chunk_ms: Option<u32>,
+        cancel: Option<&CancelToken>,
+    ) -> Result<usize> {
+        let BodySink { chunk, sink, .. } = stream;
+        let mut len = 0;
+        loop {
+            let read = match chunk_ms {
+                Some(chunk_ms) => Self::step(reader.read(chunk), chunk_ms, cancel).await?,
+                None => reader.read(chunk).await,
+            }
+            .map_err(|_| Error::HttpError)?;
+            if read == 0 {
+                return Ok(len);
+            }
+            sink(&chunk[..read])?;
+            len += read;
+        }
+    }
+
+    /// Run one step of a request, failing if it takes longer than
+    /// `budget_ms` or `cancel` is cancelled first
+    async fn step<F: Future>(
+        future: F,
+        budget_ms: u32,
+        cancel: Option<&CancelToken>,
+    ) -> Result<F::Output> {
+        let cancelled = async {
+            match cancel {
+                Some(token) => token.cancelled().await,
+                None => core::future::pending().await,
+            }
+        };
+        match select3(future, Timer::after_millis(budget_ms.into()), cancelled).await {
+            Either3::First(output) => Ok(output),
+            Either3::Second(()) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("Request step timed out after {}ms", budget_ms);
+                Err(Error::Timeout)
+            }
+            Either3::Third(()) => Err(Error::Cancelled),
+        }
+    }
+
+    /// Find a header among the response headers, if its value fits
+    fn header<'h, const N: usize>(
+        mut headers: impl Iterator<Item = (&'h str, &'h [u8])>,
+        name: &str,
+    ) -> Option<String<N>> {
+        let (_, value) = headers.find(|(header, _)| header.eq_ignore_ascii_case(name))?;
+        String::try_from(core::str::from_utf8(value).ok()?).ok()
+    }
+
+    /// Target of a redirect response, `None` for other statuses
+    fn location<'h>(
+        status: u16,
+        headers: impl Iterator<Item = (&'h str, &'h [u8])>,
+    ) -> Option<Url> {
+        if !REDIRECTS.contains(&status) {
+            return None;
+        }
+        Self::header(headers, "Location")
+    }
+
+    /// Check that a status code is a 2xx success, a 304 answering a
+    /// conditional request, or a redirect with its `location`
+    ///
+    /// `401` and `403` fail with [`Error::Unauthorized`], other statuses
+    /// with [`Error::InvalidStatus`].
+    fn check_status(status: u16, conditional: bool, location: &Option<Url>) -> Result<u16> {
+        if (conditional && status == NOT_MODIFIED) || location.is_some() {
+            return Ok(status);
+        }
+        if matches!(status, 401 | 403) {
+            #[cfg(feature = "defmt")]
+            defmt::error!("HTTP error: status {}, check the credentials", status);
+            return Err(Error::Unauthorized(status));
+        }
+        if !(200..300).contains(&status) {
+            #[cfg(feature = "defmt")]
+            defmt::error!("HTTP error: status {}", status);
+            return Err(Error::InvalidStatus(status));
+        }
+        Ok(status)
     }
 
     /// Get the client configuration
@@ -153,3 +957,114 @@ impl<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize> Client<'a, T, D, BUF_SIZE
         &self.config
     }
 }
+
+/// URL a redirect's `Location` header points to, from the request `url`
+///
+/// Absolute URLs are taken as they are, `//host/path` keeps the scheme of
+/// `url`, and paths are resolved against `url`. Dot segments are left in.
+fn redirect_target(url: &str, location: &str) -> Result<Url> {
+    let (scheme, rest) = url.split_once("://").ok_or(Error::InvalidUrl)?;
+    let origin_len = scheme.len() + "://".len() + rest.find('/').unwrap_or(rest.len());
+
+    let mut target = Url::new();
+    let mut push = |part: &str| target.push_str(part).map_err(|_| Error::InvalidUrl);
+    if location.starts_with("http://") || location.starts_with("https://") {
+        push(location)?;
+    } else if location.contains("://") || location.is_empty() {
+        return Err(Error::InvalidUrl);
+    } else if location.starts_with("//") {
+        push(scheme)?;
+        push(":")?;
+        push(location)?;
+    } else if location.starts_with('/') {
+        push(&url[..origin_len])?;
+        push(location)?;
+    } else {
+        // Relative to the directory of the request path
+        let path = &url[origin_len..];
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        push(&url[..origin_len])?;
+        match path.rfind('/') {
+            Some(slash) => push(&path[..=slash])?,
+            None => push("/")?,
+        }
+        push(location)?;
+    }
+    Ok(target)
+}
+
+/// Where `part` lies in the buffer starting at address `buffer_start`
+///
+/// reqwless reads the body into the buffer it was given for the response,
+/// after the headers. A slice outside of it fails with
+/// [`Error::HttpError`] rather than producing a range that would panic.
+fn range_in(buffer_start: usize, buffer_len: usize, part: &[u8]) -> Result<Range<usize>> {
+    let start = (part.as_ptr() as usize)
+        .checked_sub(buffer_start)
+        .ok_or(Error::HttpError)?;
+    let end = start.checked_add(part.len()).ok_or(Error::HttpError)?;
+    if end > buffer_len {
+        return Err(Error::HttpError);
+    }
+    Ok(start..end)
+}
+
+/// Host and port of a URL
+fn authority(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_target() {
+        let url = "http://api.example.com/cluster/f0?full=1";
+        for (location, target) in [
+            (
+                "https://api.example.com/cluster/f0",
+                "https://api.example.com/cluster/f0",
+            ),
+            ("//cdn.example.com/f0", "http://cdn.example.com/f0"),
+            ("/v2/cluster/f0", "http://api.example.com/v2/cluster/f0"),
+            ("f1", "http://api.example.com/cluster/f1"),
+        ] {
+            assert_eq!(redirect_target(url, location).unwrap(), target);
+        }
+        assert_eq!(
+            redirect_target("http://api.example.com", "layout").unwrap(),
+            "http://api.example.com/layout"
+        );
+        assert_eq!(
+            redirect_target(url, "ftp://api.example.com/f0"),
+            Err(Error::InvalidUrl)
+        );
+    }
+
+    #[test]
+    fn test_range_in() {
+        let buffer = [0u8; 16];
+        let start = buffer.as_ptr() as usize;
+        assert_eq!(range_in(start, buffer.len(), &buffer[4..10]), Ok(4..10));
+        assert_eq!(range_in(start, buffer.len(), &buffer[16..]), Ok(16..16));
+
+        let other = [0u8; 16];
+        assert_eq!(range_in(start, buffer.len(), &other), Err(Error::HttpError));
+        assert_eq!(range_in(start, 4, &buffer[2..8]), Err(Error::HttpError));
+    }
+
+    #[test]
+    fn test_authority() {
+        assert_eq!(
+            authority("http://api.example.com/cluster"),
+            "api.example.com"
+        );
+        assert_eq!(
+            authority("https://api.example.com:8443?q"),
+            "api.example.com:8443"
+        );
+        assert_eq!(authority("https://api.example.com"), "api.example.com");
+    }
+}
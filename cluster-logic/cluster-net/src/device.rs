@@ -0,0 +1,139 @@
+//! Device identity and provisioning
+//!
+//! Every panel is identified by a [`DeviceId`] derived from the chip's unique
+//! ID. Attached to a [`Client`](crate::Client) as middleware it adds the
+//! `X-Device-Id` header to every request. On first boot the panel announces
+//! itself through [`Endpoints::provision`](crate::endpoints::Endpoints::provision)
+//! so the server can associate it with a location, then stores the ID in its
+//! configuration: a panel with a stored ID (see [`DeviceId::parse`]) is
+//! already provisioned and skips the handshake.
+
+use crate::error::Result;
+use crate::middleware::{Middleware, RequestContext};
+use cluster_core::types::ClusterId;
+use core::fmt;
+use heapless::String;
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the device ID on every request
+pub const DEVICE_ID_HEADER: &str = "X-Device-Id";
+
+/// Length of a device ID (16 hex digits of a 64-bit unique ID)
+pub const DEVICE_ID_LENGTH: usize = 16;
+
+/// Maximum length of a location name returned by the server
+pub const MAX_LOCATION_LENGTH: usize = 32;
+
+/// Unique identifier of a panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceId(String<DEVICE_ID_LENGTH>);
+
+impl DeviceId {
+    /// Build a device ID from a 64-bit chip unique ID
+    ///
+    /// The ID is rendered as 16 lowercase hex digits, so the same chip always
+    /// gets the same ID.
+    pub fn from_unique_id(unique_id: u64) -> Self {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+
+        let mut id = String::new();
+        for shift in (0..DEVICE_ID_LENGTH).rev() {
+            let nibble = (unique_id >> (shift * 4)) & 0xF;
+            // Cannot overflow: exactly DEVICE_ID_LENGTH digits are pushed
+            let _ = id.push(HEX[nibble as usize] as char);
+        }
+        Self(id)
+    }
+
+    /// Read back a device ID stored as text
+    ///
+    /// Returns `None` unless `id` is 16 lowercase hex digits, as built by
+    /// [`from_unique_id`](Self::from_unique_id).
+    pub fn parse(id: &str) -> Option<Self> {
+        if !id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return None;
+        }
+        match String::try_from(id) {
+            Ok(id) if id.len() == DEVICE_ID_LENGTH => Some(Self(id)),
+            _ => None,
+        }
+    }
+
+    /// Get the device ID as a string slice
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DeviceId {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.as_str())
+    }
+}
+
+impl Middleware for DeviceId {
+    fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> Result<()> {
+        ctx.add_header(DEVICE_ID_HEADER, self.as_str())
+    }
+}
+
+/// Body of the first-boot provisioning handshake
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ProvisionRequest<'r> {
+    pub device_id: &'r str,
+    pub firmware_version: &'r str,
+}
+
+/// Server answer to the provisioning handshake
+#[derive(Deserialize, Debug, Clone)]
+pub struct Provisioning {
+    /// Cluster the panel should display, if the server assigned one
+    pub cluster: Option<ClusterId>,
+    /// Human readable location of the panel
+    pub location: String<MAX_LOCATION_LENGTH>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_id_from_unique_id() {
+        let id = DeviceId::from_unique_id(0x0123_4567_89ab_cdef);
+        assert_eq!(id.as_str(), "0123456789abcdef");
+
+        let id = DeviceId::from_unique_id(0x2a);
+        assert_eq!(id.as_str(), "000000000000002a");
+    }
+
+    #[test]
+    fn test_device_id_parse() {
+        let id = DeviceId::from_unique_id(0x0123_4567_89ab_cdef);
+        assert_eq!(DeviceId::parse(id.as_str()), Some(id));
+
+        assert_eq!(DeviceId::parse("0123456789abcde"), None);
+        assert_eq!(DeviceId::parse("0123456789ABCDEF"), None);
+        assert_eq!(DeviceId::parse("0123456789abcdeg"), None);
+        // Erased flash
+        assert_eq!(DeviceId::parse("\u{ff}"), None);
+    }
+
+    #[test]
+    fn test_provisioning_parse() {
+        let json = br#"{"cluster":"f1b","location":"Paris"}"#;
+        let (provisioning, _) = serde_json_core::from_slice::<Provisioning>(json).unwrap();
+        assert_eq!(provisioning.cluster, Some(ClusterId::F1b));
+        assert_eq!(provisioning.location.as_str(), "Paris");
+
+        let json = br#"{"location":"Unassigned"}"#;
+        let (provisioning, _) = serde_json_core::from_slice::<Provisioning>(json).unwrap();
+        assert_eq!(provisioning.cluster, None);
+    }
+}
@@ -1,11 +1,62 @@
 //! REST API endpoints for cluster data
 
-use crate::client::Client;
-use crate::error::{Error, Result};
-use cluster_core::models::{Cluster, Layout};
-use cluster_core::types::ClusterId;
+use crate::client::{Client, Conditional};
+use crate::device::{DeviceId, ProvisionRequest, Provisioning};
+use crate::error::{Error, Result, from_json};
+#[cfg(feature = "live-updates")]
+use crate::events::{CLUSTER_UPDATE_EVENT, EventParser};
+#[cfg(feature = "stream-parse")]
+use crate::stream::LayoutParser;
+#[cfg(feature = "assets")]
+use cluster_core::assets::AssetHash;
+#[cfg(feature = "bookings")]
+use cluster_core::bookings::Bookings;
+#[cfg(feature = "live-updates")]
+use cluster_core::models::ClusterUpdate;
+use cluster_core::models::{Cluster, Layout, PartialLayout};
+use cluster_core::preferences::Preferences;
+use cluster_core::types::{ClusterId, Status};
 use embedded_nal_async::{Dns, TcpConnect};
 use heapless::String;
+use serde::Serialize;
+
+/// Path of the preferences of the site the requesting device belongs to
+const PREFERENCES_PATH: &str = "/preferences";
+
+/// Path of the current and upcoming seat bookings
+#[cfg(feature = "bookings")]
+const BOOKINGS_PATH: &str = "/bookings";
+
+/// Path of the downloadable assets, followed by the asset name
+#[cfg(feature = "assets")]
+const ASSETS_PATH: &str = "/assets/";
+
+/// Body of a seat status report
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct SeatReport<'r> {
+    pub seat_id: &'r str,
+    pub status: Status,
+}
+
+/// Outcome of polling a resource
+#[derive(Debug, Clone)]
+pub enum Polled<T> {
+    /// The resource changed since the last poll, or was fetched for the
+    /// first time
+    Modified(T),
+    /// The resource didn't change since the last poll, nothing to re-render
+    NotModified,
+}
+
+impl<T> Polled<T> {
+    /// The new version of the resource, if it changed
+    pub fn modified(self) -> Option<T> {
+        match self {
+            Self::Modified(value) => Some(value),
+            Self::NotModified => None,
+        }
+    }
+}
 
 /// API endpoints namespace
 pub struct Endpoints;
@@ -33,19 +84,14 @@ impl Endpoints {
         cluster_id: ClusterId,
         buffer: &mut [u8],
     ) -> Result<Cluster> {
-        use core::fmt::Write;
-
         // Construct path
-        let mut path: String<64> = String::new();
-        path.push_str("/cluster/").map_err(|_| Error::InvalidUrl)?;
-        write!(&mut path, "{}", cluster_id).map_err(|_| Error::InvalidUrl)?;
+        let path = Self::cluster_path(cluster_id)?;
 
         // Make request
         let response_body = client.get(path.as_str(), buffer).await?;
 
         // Parse JSON response
-        let (cluster, _) = serde_json_core::from_slice::<Cluster>(response_body)
-            .map_err(|_| Error::DeserializationError)?;
+        let cluster = from_json::<Cluster>(response_body)?;
 
         #[cfg(feature = "defmt")]
         defmt::debug!(
@@ -80,8 +126,7 @@ impl Endpoints {
         let response_body = client.get("/layout", buffer).await?;
 
         // Parse JSON response
-        let (layout, _) = serde_json_core::from_slice::<Layout>(response_body)
-            .map_err(|_| Error::DeserializationError)?;
+        let layout = from_json::<Layout>(response_body)?;
 
         #[cfg(feature = "defmt")]
         defmt::debug!("Fetched complete layout");
@@ -89,21 +134,469 @@ impl Endpoints {
         Ok(layout)
     }
 
+    /// Get complete layout, parsing it as it arrives
+    ///
+    /// Unlike [`get_layout`](Self::get_layout), the body doesn't have to fit
+    /// in `buffer`: it is read in [`CHUNK_SIZE`](crate::stream::CHUNK_SIZE)
+    /// chunks and fed to a [`LayoutParser`], which fills `layout` seat by
+    /// seat. `buffer` only holds the response headers.
+    ///
+    /// On error `layout` is left partially filled, so fetch into a copy
+    /// that isn't on display.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `buffer` - Buffer for the response headers
+    /// * `layout` - Layout to fill
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::Endpoints;
+    /// # use cluster_net::client::{Client, ClientConfig};
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>, layout: &mut cluster_core::models::Layout) {
+    /// let mut buffer = [0u8; 1024];
+    /// Endpoints::get_layout_streamed(client, &mut buffer, layout).await.unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "stream-parse")]
+    pub async fn get_layout_streamed<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        buffer: &mut [u8],
+        layout: &mut Layout,
+    ) -> Result<()> {
+        let mut chunk = [0u8; crate::stream::CHUNK_SIZE];
+        let mut parser = LayoutParser::new(layout);
+        client
+            .get_streamed("/layout", buffer, &mut chunk, &mut |bytes: &[u8]| {
+                parser.feed(bytes)
+            })
+            .await?;
+        parser.finish()?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Streamed complete layout");
+
+        Ok(())
+    }
+
+    /// Get only the selected clusters
+    ///
+    /// Hits `/clusters?ids=f0,f2`, which returns a layout object containing
+    /// just the requested clusters. This is much smaller than the complete
+    /// layout when a panel only displays a few clusters.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `cluster_ids` - The clusters to fetch
+    /// * `buffer` - Buffer for HTTP response
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::Endpoints;
+    /// # use cluster_net::client::Client;
+    /// # use cluster_core::types::ClusterId;
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>) {
+    /// let mut buffer = [0u8; 8192];
+    /// let clusters = Endpoints::get_clusters(client, &[ClusterId::F0, ClusterId::F2], &mut buffer)
+    ///     .await
+    ///     .unwrap();
+    /// let f2 = clusters.get(ClusterId::F2);
+    /// # }
+    /// ```
+    pub async fn get_clusters<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        cluster_ids: &[ClusterId],
+        buffer: &mut [u8],
+    ) -> Result<PartialLayout> {
+        if cluster_ids.is_empty() {
+            return Ok(PartialLayout::default());
+        }
+
+        // Construct path
+        let path = Self::clusters_path(cluster_ids)?;
+
+        // Make request
+        let response_body = client.get(path.as_str(), buffer).await?;
+
+        // Parse JSON response
+        let clusters = from_json::<PartialLayout>(response_body)?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Fetched {} selected clusters", cluster_ids.len());
+
+        Ok(clusters)
+    }
+
+    /// Build the `/cluster/<id>` path of a cluster
+    fn cluster_path(cluster_id: ClusterId) -> Result<String<64>> {
+        use core::fmt::Write;
+
+        let mut path: String<64> = String::new();
+        write!(&mut path, "/cluster/{}", cluster_id).map_err(|_| Error::InvalidUrl)?;
+        Ok(path)
+    }
+
+    /// Build the `/clusters?ids=...` path for a set of clusters
+    fn clusters_path(cluster_ids: &[ClusterId]) -> Result<String<64>> {
+        use core::fmt::Write;
+
+        let mut path: String<64> = String::new();
+        path.push_str("/clusters?ids=")
+            .map_err(|_| Error::InvalidUrl)?;
+        for (i, cluster_id) in cluster_ids.iter().enumerate() {
+            if i > 0 {
+                path.push(',').map_err(|_| Error::InvalidUrl)?;
+            }
+            write!(&mut path, "{}", cluster_id).map_err(|_| Error::InvalidUrl)?;
+        }
+        Ok(path)
+    }
+
     /// Poll for cluster updates
     ///
     /// This endpoint can be called periodically to fetch updated cluster data.
+    /// The client remembers the `ETag` and `Last-Modified` of the last
+    /// response and sends them back (see [`Client::get_cached`]), so an
+    /// unchanged cluster costs an empty `304` response and no parsing: the
+    /// result is then [`Polled::NotModified`] and the cluster shown can stay.
+    /// Keep the same client between polls for this to work.
     ///
     /// # Arguments
     /// * `client` - HTTP client instance
     /// * `cluster_id` - The cluster ID to poll
     /// * `buffer` - Buffer for HTTP response
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::{Endpoints, Polled};
+    /// # use cluster_net::client::Client;
+    /// # use cluster_core::types::ClusterId;
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>) {
+    /// let mut buffer = [0u8; 8192];
+    /// match Endpoints::poll_cluster(client, ClusterId::F0, &mut buffer).await {
+    ///     Ok(Polled::Modified(cluster)) => { /* render the new data */ }
+    ///     Ok(Polled::NotModified) => { /* keep the current frame */ }
+    ///     Err(e) => { /* retry later */ }
+    /// }
+    /// # }
+    /// ```
     pub async fn poll_cluster<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
         client: &'c mut Client<'a, T, D, BUF_SIZE>,
         cluster_id: ClusterId,
         buffer: &mut [u8],
-    ) -> Result<Cluster> {
-        // Reuse get_cluster for polling
-        Self::get_cluster(client, cluster_id, buffer).await
+    ) -> Result<Polled<Cluster>> {
+        let path = Self::cluster_path(cluster_id)?;
+
+        let response_body = match client.get_cached(path.as_str(), buffer).await? {
+            Conditional::NotModified => {
+                #[cfg(feature = "defmt")]
+                defmt::debug!("Cluster {} not modified", path.as_str());
+
+                return Ok(Polled::NotModified);
+            }
+            Conditional::Modified { body, .. } => body,
+        };
+
+        // Download it again next time if this version can't be used
+        let cluster = from_json::<Cluster>(response_body).inspect_err(|_| {
+            client.forget_validators(path.as_str());
+        })?;
+
+        Ok(Polled::Modified(cluster))
+    }
+
+    /// Follow the changes of one cluster as the server pushes them
+    ///
+    /// Subscribes to the Server-Sent Events of `/cluster/{id}/events` and
+    /// calls `on_update` with each update event, as soon as the server
+    /// writes it rather than at the next poll. Other events are ignored.
+    ///
+    /// Returns when the server closes the stream, or with the first error,
+    /// such as [`Error::Timeout`] when the server stays silent for longer
+    /// than the body timeout. Subscribe again then, after a full
+    /// [`poll_cluster`](Self::poll_cluster) to catch up on missed changes.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `cluster_id` - The cluster to follow
+    /// * `buffer` - Buffer for the response headers
+    /// * `on_update` - Called with each update
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::Endpoints;
+    /// # use cluster_net::client::Client;
+    /// # use cluster_core::types::ClusterId;
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>) {
+    /// let mut buffer = [0u8; 1024];
+    /// loop {
+    ///     let result = Endpoints::subscribe_cluster(client, ClusterId::F0, &mut buffer, &mut |update| {
+    ///         // Redraw with the new attributes and zones
+    ///     })
+    ///     .await;
+    ///     // Poll, then subscribe again
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "live-updates")]
+    pub async fn subscribe_cluster<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        cluster_id: ClusterId,
+        buffer: &mut [u8],
+        on_update: &mut dyn FnMut(ClusterUpdate),
+    ) -> Result<()> {
+        let mut path = Self::cluster_path(cluster_id)?;
+        path.push_str("/events").map_err(|_| Error::InvalidUrl)?;
+
+        let mut chunk = [0u8; crate::stream::CHUNK_SIZE];
+        let mut parser = EventParser::new();
+        client
+            .get_event_stream(path.as_str(), buffer, &mut chunk, &mut |bytes: &[u8]| {
+                parser.feed(bytes, |event| {
+                    if event.name == CLUSTER_UPDATE_EVENT {
+                        on_update(from_json::<ClusterUpdate>(event.data)?);
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Subscription to {} closed", path.as_str());
+
+        Ok(())
+    }
+
+    /// Announce the device to the server on first boot
+    ///
+    /// The server answers with the location (and optionally the cluster) the
+    /// panel is associated with.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `device_id` - Unique ID of this panel
+    /// * `firmware_version` - Firmware version string reported to the server
+    /// * `buffer` - Buffer for HTTP response
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::Endpoints;
+    /// # use cluster_net::client::Client;
+    /// # use cluster_net::device::DeviceId;
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>) {
+    /// let device_id = DeviceId::from_unique_id(0x0123_4567_89ab_cdef);
+    /// let mut buffer = [0u8; 512];
+    /// let provisioning = Endpoints::provision(client, &device_id, "0.1.0", &mut buffer)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn provision<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        device_id: &DeviceId,
+        firmware_version: &str,
+        buffer: &mut [u8],
+    ) -> Result<Provisioning> {
+        // Serialize request body
+        let request = ProvisionRequest {
+            device_id: device_id.as_str(),
+            firmware_version,
+        };
+        let mut body = [0u8; 128];
+        let body_len =
+            serde_json_core::to_slice(&request, &mut body).map_err(|_| Error::BufferTooSmall)?;
+
+        // Make request
+        let response_body = client
+            .post("/devices/provision", &body[..body_len], buffer)
+            .await?;
+
+        // Parse JSON response
+        let provisioning = from_json::<Provisioning>(response_body)?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!(
+            "Provisioned device {} at {}",
+            device_id,
+            provisioning.location.as_str()
+        );
+
+        Ok(provisioning)
+    }
+
+    /// Get the preferences of the panel's site
+    ///
+    /// The server identifies the site from the device ID header, so attach a
+    /// [`DeviceId`] middleware to the client. Feed the result to
+    /// `PreferenceSync::apply_server`.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `buffer` - Buffer for HTTP response
+    pub async fn get_preferences<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        buffer: &mut [u8],
+    ) -> Result<Preferences> {
+        let response_body = client.get(PREFERENCES_PATH, buffer).await?;
+        let preferences = from_json::<Preferences>(response_body)?;
+        Ok(preferences)
+    }
+
+    /// Send preferences changed on the device to the server
+    ///
+    /// On success, report it with `PreferenceSync::push_succeeded`; on
+    /// failure the change stays queued and can be retried later.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `preferences` - Preferences to store for the panel's site
+    /// * `buffer` - Buffer for HTTP response
+    pub async fn push_preferences<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        preferences: &Preferences,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        let mut body = [0u8; 64];
+        let body_len =
+            serde_json_core::to_slice(preferences, &mut body).map_err(|_| Error::BufferTooSmall)?;
+        client
+            .post(PREFERENCES_PATH, &body[..body_len], buffer)
+            .await?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Pushed preferences");
+
+        Ok(())
+    }
+
+    /// Report the status of a seat, e.g. a broken seat seen from the panel
+    ///
+    /// POSTs `{"seat_id", "status"}` to `/cluster/<id>/reports`. The server
+    /// decides what to do with the report; the new status shows up in the
+    /// cluster data on a later poll.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `cluster_id` - The cluster the seat belongs to
+    /// * `seat_id` - The seat to report, e.g. "f0r1s1"
+    /// * `status` - The status seen
+    /// * `buffer` - Buffer for HTTP response
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use cluster_net::endpoints::Endpoints;
+    /// # use cluster_net::client::Client;
+    /// # use cluster_core::types::{ClusterId, Status};
+    /// # async fn example<T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(client: &mut Client<'_, T, D>) {
+    /// let mut buffer = [0u8; 256];
+    /// Endpoints::report_seat(client, ClusterId::F0, "f0r1s1", Status::Broken, &mut buffer)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn report_seat<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        cluster_id: ClusterId,
+        seat_id: &str,
+        status: Status,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        use core::fmt::Write;
+
+        // Construct path
+        let mut path: String<64> = String::new();
+        write!(&mut path, "/cluster/{}/reports", cluster_id).map_err(|_| Error::InvalidUrl)?;
+
+        // Serialize request body
+        let request = SeatReport { seat_id, status };
+        let mut body = [0u8; 64];
+        let body_len =
+            serde_json_core::to_slice(&request, &mut body).map_err(|_| Error::BufferTooSmall)?;
+
+        // Make request
+        client
+            .post(path.as_str(), &body[..body_len], buffer)
+            .await?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Reported seat {}", seat_id);
+
+        Ok(())
+    }
+
+    /// Get the seat bookings from the campus booking system
+    ///
+    /// Only bookings still to come or in progress are returned. Note the
+    /// time the response arrived: the booked seats are resolved against
+    /// `Bookings::now` with the time elapsed since.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `buffer` - Buffer for HTTP response
+    #[cfg(feature = "bookings")]
+    pub async fn get_bookings<'c, 'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        buffer: &mut [u8],
+    ) -> Result<Bookings> {
+        let response_body = client.get(BOOKINGS_PATH, buffer).await?;
+        let bookings = from_json::<Bookings>(response_body)?;
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Received {} bookings", bookings.bookings.len());
+
+        Ok(bookings)
+    }
+
+    /// Download an asset, such as an event banner, unless the cached copy
+    /// is current
+    ///
+    /// The server sends the hash of the asset as its ETag: pass the hash of
+    /// the cached copy, if any, so an unchanged asset isn't downloaded again.
+    /// Returns `None` when the cached copy is current, otherwise the asset,
+    /// checked against its ETag, to store in an `AssetCache`.
+    ///
+    /// # Arguments
+    /// * `client` - HTTP client instance
+    /// * `name` - Name of the asset, as referenced by the server
+    /// * `cached` - Hash of the cached copy of the asset
+    /// * `buffer` - Buffer for HTTP response, large enough for the asset
+    #[cfg(feature = "assets")]
+    pub async fn get_asset<'c, 'a, 'buf, T: TcpConnect, D: Dns, const BUF_SIZE: usize>(
+        client: &'c mut Client<'a, T, D, BUF_SIZE>,
+        name: &str,
+        cached: Option<AssetHash>,
+        buffer: &'buf mut [u8],
+    ) -> Result<Option<&'buf [u8]>> {
+        use core::fmt::Write;
+
+        let mut path: String<64> = String::new();
+        path.push_str(ASSETS_PATH).map_err(|_| Error::InvalidUrl)?;
+        path.push_str(name).map_err(|_| Error::InvalidUrl)?;
+
+        let mut etag: String<20> = String::new();
+        if let Some(hash) = cached {
+            write!(&mut etag, "\"{}\"", hash).map_err(|_| Error::BufferTooSmall)?;
+        }
+        let if_none_match = cached.map(|_| etag.as_str());
+
+        let (body, etag) = match client
+            .get_conditional(path.as_str(), if_none_match, buffer)
+            .await?
+        {
+            Conditional::NotModified => return Ok(None),
+            Conditional::Modified { body, etag } => (body, etag),
+        };
+
+        // Other ETags than a hash can't be checked
+        let announced = etag.as_deref().and_then(AssetHash::from_etag);
+        if announced.is_some_and(|hash| hash != AssetHash::of(body)) {
+            return Err(Error::HashMismatch);
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Downloaded asset {} ({} bytes)", name, body.len());
+
+        Ok(Some(body))
     }
 }
 
@@ -118,4 +611,53 @@ mod tests {
         path.push_str("f0").unwrap();
         assert_eq!(path.as_str(), "/cluster/f0");
     }
+
+    #[test]
+    fn test_cluster_path_construction() {
+        let path = Endpoints::cluster_path(ClusterId::F1b).unwrap();
+        assert_eq!(path.as_str(), "/cluster/f1b");
+    }
+
+    #[test]
+    fn test_clusters_path_construction() {
+        let path =
+            Endpoints::clusters_path(&[ClusterId::F0, ClusterId::F1b, ClusterId::F2]).unwrap();
+        assert_eq!(path.as_str(), "/clusters?ids=f0,f1b,f2");
+    }
+
+    #[test]
+    fn test_preferences_json() {
+        use cluster_core::preferences::Theme;
+
+        let (preferences, _) =
+            serde_json_core::from_slice::<Preferences>(br#"{"default_floor":"f1b"}"#).unwrap();
+        assert_eq!(preferences.theme, Theme::Dark);
+        assert_eq!(preferences.default_floor, ClusterId::F1b);
+
+        let mut body = [0u8; 64];
+        let len = serde_json_core::to_slice(&preferences, &mut body).unwrap();
+        assert_eq!(&body[..len], br#"{"theme":"dark","default_floor":"f1b"}"#);
+    }
+
+    #[test]
+    fn test_seat_report_json() {
+        let report = SeatReport {
+            seat_id: "f0r1s1",
+            status: Status::Broken,
+        };
+        let mut body = [0u8; 64];
+        let len = serde_json_core::to_slice(&report, &mut body).unwrap();
+        assert_eq!(&body[..len], br#"{"seat_id":"f0r1s1","status":"broken"}"#);
+    }
+
+    #[cfg(feature = "bookings")]
+    #[test]
+    fn test_bookings_json() {
+        let json =
+            br#"{"server_time":1000,"bookings":[{"seat_id":"f0r1s1","start":900,"end":1100}]}"#;
+        let (bookings, _) = serde_json_core::from_slice::<Bookings>(json).unwrap();
+        assert_eq!(bookings.server_time, 1000);
+        assert!(bookings.is_booked("f0r1s1", bookings.now(50)));
+        assert!(!bookings.is_booked("f0r1s1", bookings.now(100)));
+    }
 }
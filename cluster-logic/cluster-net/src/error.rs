@@ -1,6 +1,8 @@
 //! Error types for network operations
 
-use core::fmt;
+use core::{fmt, str};
+use serde::Deserialize;
+use serde_json_core::de;
 
 /// Errors that can occur during network operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -11,16 +13,30 @@ pub enum Error {
     ParseError,
     /// Invalid response status code
     InvalidStatus(u16),
-    /// Deserialization failed
-    DeserializationError,
+    /// Credentials missing or rejected, with the `401` or `403` status
+    Unauthorized(u16),
+    /// Deserialization failed, with where the body went wrong
+    DeserializationError(JsonError),
     /// Buffer too small for operation
     BufferTooSmall,
     /// Network connection error
     ConnectionError,
     /// Request timeout
     Timeout,
+    /// Request aborted through its [`CancelToken`](crate::cancel::CancelToken)
+    Cancelled,
     /// Invalid URL format
     InvalidUrl,
+    /// Downloaded content doesn't match the hash it was announced with
+    HashMismatch,
+    /// Redirected more than [`MAX_REDIRECTS`](crate::client::MAX_REDIRECTS) times
+    TooManyRedirects,
+    /// Redirected back to a URL already requested
+    RedirectLoop,
+    /// Redirected from `https://` to `http://`
+    InsecureRedirect,
+    /// Redirected to `https://` by a client created without TLS
+    TlsUnavailable,
 }
 
 impl fmt::Display for Error {
@@ -29,11 +45,20 @@ impl fmt::Display for Error {
             Error::HttpError => write!(f, "HTTP request failed"),
             Error::ParseError => write!(f, "Response parsing failed"),
             Error::InvalidStatus(code) => write!(f, "Invalid HTTP status: {}", code),
-            Error::DeserializationError => write!(f, "JSON deserialization failed"),
+            Error::Unauthorized(code) => write!(f, "Unauthorized: status {}", code),
+            Error::DeserializationError(error) => {
+                write!(f, "JSON deserialization failed: {}", error)
+            }
             Error::BufferTooSmall => write!(f, "Buffer too small"),
             Error::ConnectionError => write!(f, "Network connection error"),
             Error::Timeout => write!(f, "Request timeout"),
+            Error::Cancelled => write!(f, "Request cancelled"),
             Error::InvalidUrl => write!(f, "Invalid URL format"),
+            Error::HashMismatch => write!(f, "Content does not match its hash"),
+            Error::TooManyRedirects => write!(f, "Too many redirects"),
+            Error::RedirectLoop => write!(f, "Redirect loop"),
+            Error::InsecureRedirect => write!(f, "Redirect from HTTPS to HTTP"),
+            Error::TlsUnavailable => write!(f, "Redirect to HTTPS without TLS"),
         }
     }
 }
@@ -48,14 +73,211 @@ impl defmt::Format for Error {
             Error::HttpError => defmt::write!(f, "HTTP request failed"),
             Error::ParseError => defmt::write!(f, "Response parsing failed"),
             Error::InvalidStatus(code) => defmt::write!(f, "Invalid HTTP status: {}", code),
-            Error::DeserializationError => defmt::write!(f, "JSON deserialization failed"),
+            Error::Unauthorized(code) => defmt::write!(f, "Unauthorized: status {}", code),
+            Error::DeserializationError(error) => {
+                defmt::write!(f, "JSON deserialization failed: {}", error)
+            }
             Error::BufferTooSmall => defmt::write!(f, "Buffer too small"),
             Error::ConnectionError => defmt::write!(f, "Network connection error"),
             Error::Timeout => defmt::write!(f, "Request timeout"),
+            Error::Cancelled => defmt::write!(f, "Request cancelled"),
             Error::InvalidUrl => defmt::write!(f, "Invalid URL format"),
+            Error::HashMismatch => defmt::write!(f, "Content does not match its hash"),
+            Error::TooManyRedirects => defmt::write!(f, "Too many redirects"),
+            Error::RedirectLoop => defmt::write!(f, "Redirect loop"),
+            Error::InsecureRedirect => defmt::write!(f, "Redirect from HTTPS to HTTP"),
+            Error::TlsUnavailable => defmt::write!(f, "Redirect to HTTPS without TLS"),
         }
     }
 }
 
+/// Bytes of the body kept around the failing byte
+const SNIPPET_LEN: usize = 32;
+
+/// Location of a JSON deserialization error in a response body
+///
+/// Device logs are often the only trace of a bad response, so the error
+/// carries where the parser stopped and the bytes around it, enough to find
+/// the offending field on the server side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonError {
+    /// What the parser complained about
+    pub kind: de::Error,
+    /// Byte offset of the failing byte in the body
+    pub offset: usize,
+    /// Line of the failing byte, from 1
+    pub line: usize,
+    /// Column of the failing byte, from 1
+    pub column: usize,
+    /// Printable ASCII copy of the body around the failing byte
+    snippet: [u8; SNIPPET_LEN],
+    snippet_len: u8,
+}
+
+impl JsonError {
+    /// Locate the error `kind` that parsing `body` as `T` failed with
+    ///
+    /// serde-json-core doesn't report where it stopped. Its parser fails at
+    /// the first byte it can't make sense of, so any prefix of the body
+    /// ending past that byte fails the same way, and shorter prefixes run
+    /// out of input or parse: the failing byte ends the shortest prefix
+    /// failing with `kind`, found by bisection. That costs a dozen parses
+    /// of an 8KB body, fine on the error path.
+    pub fn locate<'a, T: Deserialize<'a>>(body: &'a [u8], kind: de::Error) -> Self {
+        let offset = match kind {
+            // The body ended early, nothing to search
+            de::Error::EofWhileParsingList
+            | de::Error::EofWhileParsingObject
+            | de::Error::EofWhileParsingString
+            | de::Error::EofWhileParsingNumber
+            | de::Error::EofWhileParsingValue => body.len(),
+            _ => {
+                // `body[..low]` doesn't fail with `kind`, `body[..high]` does
+                let mut low = 0;
+                let mut high = body.len();
+                while high - low > 1 {
+                    let mid = low + (high - low) / 2;
+                    match serde_json_core::from_slice::<T>(&body[..mid]) {
+                        Err(error) if error == kind => high = mid,
+                        _ => low = mid,
+                    }
+                }
+                high.saturating_sub(1)
+            }
+        };
+        Self::at(body, kind, offset)
+    }
+
+    /// Error `kind` at byte `offset` of `body`
+    pub fn at(body: &[u8], kind: de::Error, offset: usize) -> Self {
+        let offset = offset.min(body.len());
+        let before = &body[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+
+        // Half the snippet before the failing byte, the rest from it
+        let start = offset
+            .saturating_sub(SNIPPET_LEN / 2)
+            .min(body.len().saturating_sub(SNIPPET_LEN));
+        let end = (start + SNIPPET_LEN).min(body.len());
+        let mut snippet = [0; SNIPPET_LEN];
+        for (dst, &src) in snippet.iter_mut().zip(&body[start..end]) {
+            // Keep the snippet on one log line
+            *dst = if src.is_ascii_graphic() || src == b' ' {
+                src
+            } else {
+                b'.'
+            };
+        }
+
+        Self {
+            kind,
+            offset,
+            line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            column: offset - line_start + 1,
+            snippet,
+            snippet_len: (end - start) as u8,
+        }
+    }
+
+    /// Body around the failing byte, other bytes than printable ASCII
+    /// replaced with `.`
+    pub fn snippet(&self) -> &str {
+        str::from_utf8(&self.snippet[..self.snippet_len as usize]).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {} (byte {}) near `{}`",
+            self.kind,
+            self.line,
+            self.column,
+            self.offset,
+            self.snippet()
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for JsonError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{} at line {}, column {} (byte {}) near `{}`",
+            self.kind,
+            self.line,
+            self.column,
+            self.offset,
+            self.snippet()
+        )
+    }
+}
+
+/// Parse a response body, locating the error on failure
+pub fn from_json<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T> {
+    match serde_json_core::from_slice::<T>(body) {
+        Ok((value, _)) => Ok(value),
+        Err(kind) => Err(Error::DeserializationError(JsonError::locate::<T>(
+            body, kind,
+        ))),
+    }
+}
+
 /// Result type for network operations
 pub type Result<T> = core::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Seat<'a> {
+        id: &'a str,
+        status: u8,
+    }
+
+    fn locate(body: &[u8]) -> JsonError {
+        match from_json::<Seat>(body) {
+            Err(Error::DeserializationError(error)) => error,
+            other => panic!("expected a deserialization error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_locates_the_failing_byte() {
+        let body = b"{\n  \"id\": \"f0r1s1\",\n  \"status\": \"free\"\n}";
+        let error = locate(body);
+        assert_eq!(error.kind, de::Error::InvalidType);
+        assert_eq!(body[error.offset], b'"');
+        assert_eq!((error.line, error.column), (3, 13));
+        assert_eq!(error.snippet(), ": \"f0r1s1\",.  \"status\": \"free\".}");
+    }
+
+    #[test]
+    fn test_locates_syntax_errors() {
+        let body = br#"{"id":"f0r1s1" "status":0}"#;
+        let error = locate(body);
+        assert_eq!(error.kind, de::Error::ExpectedObjectCommaOrEnd);
+        assert_eq!((error.line, error.column), (1, 16));
+
+        let body = br#"{"id":"f0r1s1","status":0}]"#;
+        let error = locate(body);
+        assert_eq!(error.kind, de::Error::TrailingCharacters);
+        assert_eq!(error.offset, body.len() - 1);
+    }
+
+    #[test]
+    fn test_truncated_body() {
+        let body = br#"{"id":"f0r1s1","sta"#;
+        let error = locate(body);
+        assert_eq!(error.kind, de::Error::EofWhileParsingString);
+        assert_eq!(error.offset, body.len());
+        assert_eq!(error.snippet(), r#"{"id":"f0r1s1","sta"#);
+    }
+}
@@ -0,0 +1,237 @@
+//! Server-Sent Events parsing
+//!
+//! Polling leaves a panel up to a poll interval behind the server. With a
+//! subscription the server keeps the response open and writes an event each
+//! time a cluster changes, in the `text/event-stream` format:
+//!
+//! ```text
+//! : keep-alive
+//!
+//! event: update
+//! data: {"id":"f0","name":"F0","attributes":["exam"],"zones":[]}
+//!
+//! ```
+//!
+//! [`EventParser`] cuts the stream into events as chunks arrive. `id` and
+//! `retry` fields are ignored: a dropped subscription is simply opened again,
+//! see [`Endpoints::subscribe_cluster`](crate::endpoints::Endpoints::subscribe_cluster).
+
+use crate::error::{Error, Result};
+use heapless::{String, Vec};
+
+/// Maximum size of the data of one event
+pub const MAX_EVENT_SIZE: usize = 512;
+
+/// Maximum length of an event name
+pub const MAX_EVENT_NAME_LENGTH: usize = 16;
+
+/// Name of events sent without an `event` field
+pub const DEFAULT_EVENT_NAME: &str = "message";
+
+/// Name of the events carrying a
+/// [`ClusterUpdate`](cluster_core::models::ClusterUpdate)
+pub const CLUSTER_UPDATE_EVENT: &str = "update";
+
+/// Event received on a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event<'e> {
+    /// Name of the event, [`DEFAULT_EVENT_NAME`] if the server gave none
+    pub name: &'e str,
+    /// Data lines of the event, joined with `\n`
+    pub data: &'e [u8],
+}
+
+/// Field of the line being read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// Reading the field name, up to the `:`
+    Name,
+    Data,
+    Event,
+    /// Unknown fields, `id`, `retry` and comments
+    Ignored,
+}
+
+/// Push parser cutting an event stream into [`Event`]s
+#[derive(Debug)]
+pub struct EventParser {
+    field: Field,
+    /// Field name of the line being read
+    field_name: String<MAX_EVENT_NAME_LENGTH>,
+    /// The space following the `:` is yet to be skipped
+    skip_space: bool,
+    /// The previous byte ended a line with `\r`, a `\n` may follow
+    after_cr: bool,
+    /// Nothing was read on the current line yet
+    line_empty: bool,
+    name: String<MAX_EVENT_NAME_LENGTH>,
+    data: Vec<u8, MAX_EVENT_SIZE>,
+    has_data: bool,
+}
+
+impl Default for EventParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventParser {
+    /// Create a parser at the start of a stream
+    pub const fn new() -> Self {
+        Self {
+            field: Field::Name,
+            field_name: String::new(),
+            skip_space: false,
+            after_cr: false,
+            line_empty: true,
+            name: String::new(),
+            data: Vec::new(),
+            has_data: false,
+        }
+    }
+
+    /// Parse the next chunk of the stream, calling `on_event` for each
+    /// event it completes
+    ///
+    /// Fails with [`Error::BufferTooSmall`] when the data of an event is
+    /// larger than [`MAX_EVENT_SIZE`], and with the error of `on_event`.
+    pub fn feed(
+        &mut self,
+        chunk: &[u8],
+        mut on_event: impl FnMut(Event<'_>) -> Result<()>,
+    ) -> Result<()> {
+        for &byte in chunk {
+            let after_cr = core::mem::take(&mut self.after_cr);
+            match byte {
+                // Second half of a `\r\n`
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    self.end_line(&mut on_event)?;
+                }
+                _ => self.push(byte)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Read one byte of a line
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.line_empty = false;
+        if core::mem::take(&mut self.skip_space) && byte == b' ' {
+            return Ok(());
+        }
+        match self.field {
+            Field::Name if byte == b':' => self.start_value()?,
+            Field::Name => {
+                // Too long for a known field: ignore the line
+                if self.field_name.push(byte as char).is_err() || !byte.is_ascii() {
+                    self.field = Field::Ignored;
+                }
+            }
+            Field::Data => {
+                self.data.push(byte).map_err(|_| Error::BufferTooSmall)?;
+            }
+            Field::Event => {
+                // Unknown names are only compared, a cut one never matches
+                let _ = self.name.push(byte as char);
+            }
+            Field::Ignored => {}
+        }
+        Ok(())
+    }
+
+    /// Start the value of the field named so far
+    fn start_value(&mut self) -> Result<()> {
+        self.skip_space = true;
+        self.field = match self.field_name.as_str() {
+            "data" => {
+                if self.has_data {
+                    self.data.push(b'\n').map_err(|_| Error::BufferTooSmall)?;
+                }
+                self.has_data = true;
+                Field::Data
+            }
+            "event" => {
+                self.name.clear();
+                Field::Event
+            }
+            _ => Field::Ignored,
+        };
+        Ok(())
+    }
+
+    /// Finish a line, dispatching the event on an empty one
+    fn end_line(&mut self, on_event: &mut impl FnMut(Event<'_>) -> Result<()>) -> Result<()> {
+        if self.field == Field::Name && !self.line_empty {
+            // A field without a value
+            self.start_value()?;
+        }
+        let dispatch = core::mem::replace(&mut self.line_empty, true);
+        self.field = Field::Name;
+        self.field_name.clear();
+        self.skip_space = false;
+        if !dispatch {
+            return Ok(());
+        }
+
+        let result = if self.has_data {
+            let name = match self.name.as_str() {
+                "" => DEFAULT_EVENT_NAME,
+                name => name,
+            };
+            on_event(Event {
+                name,
+                data: &self.data,
+            })
+        } else {
+            Ok(())
+        };
+        self.name.clear();
+        self.data.clear();
+        self.has_data = false;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `stream` in chunks of `chunk_size`, counting the events and
+    /// checking them against `expected`
+    fn parse(stream: &[u8], chunk_size: usize, expected: &[(&str, &[u8])]) {
+        let mut parser = EventParser::new();
+        let mut count = 0;
+        for chunk in stream.chunks(chunk_size) {
+            parser
+                .feed(chunk, |event| {
+                    assert_eq!((event.name, event.data), expected[count]);
+                    count += 1;
+                    Ok(())
+                })
+                .unwrap();
+        }
+        assert_eq!(count, expected.len());
+    }
+
+    #[test]
+    fn test_events() {
+        let stream = b": keep-alive\n\nevent: update\ndata: {\"id\":\"f0\"}\n\ndata:first\r\ndata: second\r\n\r\nretry: 1000\nid: 7\n\nevent: cut";
+        let expected: [(&str, &[u8]); 2] = [
+            ("update", b"{\"id\":\"f0\"}"),
+            (DEFAULT_EVENT_NAME, b"first\nsecond"),
+        ];
+        for chunk_size in [1, 3, stream.len()] {
+            parse(stream, chunk_size, &expected);
+        }
+    }
+
+    #[test]
+    fn test_event_too_large() {
+        let mut parser = EventParser::new();
+        parser.feed(b"data: ", |_| Ok(())).unwrap();
+        let data = [b'x'; MAX_EVENT_SIZE + 1];
+        assert_eq!(parser.feed(&data, |_| Ok(())), Err(Error::BufferTooSmall));
+    }
+}
@@ -0,0 +1,354 @@
+//! Fault injection, to test the client on a bad network
+//!
+//! [`FaultyTcp`] wraps the [`TcpConnect`] given to a [`Client`](crate::Client)
+//! and, for each connection, delays the response, drops the connection or
+//! answers `500 Internal Server Error` in place of the server. Timeouts,
+//! retries and the handling of stale data then get exercised in tests rather
+//! than only in the field. Faults are drawn from a seeded generator, so a
+//! test sees the same sequence on every run.
+//!
+//! Only available with the `faults` feature.
+
+use core::cell::Cell;
+use core::net::SocketAddr;
+use embassy_time::Timer;
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use embedded_nal_async::TcpConnect;
+
+/// Response sent in place of the server's for an injected server error
+pub const SERVER_ERROR_RESPONSE: &[u8] =
+    b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// Network conditions to simulate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Faults {
+    /// Delay before the response arrives, in milliseconds
+    pub latency_ms: u32,
+    /// Extra delay drawn for each connection, up to this many milliseconds
+    pub jitter_ms: u32,
+    /// Share of connections that fail, in percent
+    pub drop_percent: u8,
+    /// Share of connections answered with a `500`, in percent
+    pub server_error_percent: u8,
+}
+
+impl Faults {
+    /// A perfect network
+    pub const fn none() -> Self {
+        Self {
+            latency_ms: 0,
+            jitter_ms: 0,
+            drop_percent: 0,
+            server_error_percent: 0,
+        }
+    }
+
+    /// Delay responses by `latency_ms`, plus up to `jitter_ms`
+    pub const fn with_latency(mut self, latency_ms: u32, jitter_ms: u32) -> Self {
+        self.latency_ms = latency_ms;
+        self.jitter_ms = jitter_ms;
+        self
+    }
+
+    /// Fail `percent` of the connections
+    pub const fn with_drops(mut self, percent: u8) -> Self {
+        self.drop_percent = percent;
+        self
+    }
+
+    /// Answer `percent` of the connections with a `500`
+    pub const fn with_server_errors(mut self, percent: u8) -> Self {
+        self.server_error_percent = percent;
+        self
+    }
+}
+
+/// What happens to one connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Reaches the server, the response arriving this many milliseconds late
+    Delayed(u32),
+    /// Fails to connect
+    Dropped,
+    /// Answered with [`SERVER_ERROR_RESPONSE`] without reaching the server
+    ServerError,
+}
+
+/// Seeded sequence of faults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultPlan {
+    faults: Faults,
+    state: u32,
+}
+
+impl FaultPlan {
+    pub const fn new(faults: Faults, seed: u32) -> Self {
+        Self {
+            faults,
+            // Xorshift never leaves 0
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    /// Fault of the next connection
+    pub fn next_fault(&mut self) -> Fault {
+        let roll = self.next_u32() % 100;
+        if roll < u32::from(self.faults.drop_percent) {
+            return Fault::Dropped;
+        }
+        if roll < u32::from(self.faults.drop_percent) + u32::from(self.faults.server_error_percent)
+        {
+            return Fault::ServerError;
+        }
+        let jitter = match self.faults.jitter_ms {
+            0 => 0,
+            jitter_ms => self.next_u32() % (jitter_ms + 1),
+        };
+        Fault::Delayed(self.faults.latency_ms.saturating_add(jitter))
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+}
+
+/// Error of a [`FaultyTcp`] connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultError<E> {
+    /// The connection was dropped on purpose
+    Dropped,
+    /// Error of the wrapped connection
+    Io(E),
+}
+
+impl<E: embedded_io_async::Error> embedded_io_async::Error for FaultError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Dropped => ErrorKind::ConnectionRefused,
+            Self::Io(e) => e.kind(),
+        }
+    }
+}
+
+/// [`TcpConnect`] wrapper injecting the faults of a [`FaultPlan`]
+pub struct FaultyTcp<'a, T> {
+    inner: &'a T,
+    plan: Cell<FaultPlan>,
+    injected: Cell<u32>,
+}
+
+impl<'a, T: TcpConnect> FaultyTcp<'a, T> {
+    pub fn new(inner: &'a T, plan: FaultPlan) -> Self {
+        Self {
+            inner,
+            plan: Cell::new(plan),
+            injected: Cell::new(0),
+        }
+    }
+
+    /// Connections dropped or answered with a `500` so far
+    pub fn injected(&self) -> u32 {
+        self.injected.get()
+    }
+}
+
+impl<T: TcpConnect> TcpConnect for FaultyTcp<'_, T> {
+    type Error = FaultError<T::Error>;
+    type Connection<'m>
+        = FaultyConnection<T::Connection<'m>>
+    where
+        Self: 'm;
+
+    async fn connect<'m>(
+        &'m self,
+        remote: SocketAddr,
+    ) -> Result<Self::Connection<'m>, Self::Error> {
+        let mut plan = self.plan.get();
+        let fault = plan.next_fault();
+        self.plan.set(plan);
+
+        match fault {
+            Fault::Delayed(delay_ms) => {
+                let inner = self.inner.connect(remote).await.map_err(FaultError::Io)?;
+                Ok(FaultyConnection::Open { inner, delay_ms })
+            }
+            Fault::Dropped => {
+                self.injected.set(self.injected.get() + 1);
+                Err(FaultError::Dropped)
+            }
+            Fault::ServerError => {
+                self.injected.set(self.injected.get() + 1);
+                Ok(FaultyConnection::ServerError { sent: 0 })
+            }
+        }
+    }
+}
+
+/// Connection opened by [`FaultyTcp`]
+pub enum FaultyConnection<C> {
+    /// Connected to the server
+    Open {
+        inner: C,
+        /// Delay left before the first read
+        delay_ms: u32,
+    },
+    /// Answering [`SERVER_ERROR_RESPONSE`], of which `sent` bytes were read
+    ServerError { sent: usize },
+}
+
+impl<C: ErrorType> ErrorType for FaultyConnection<C> {
+    type Error = FaultError<C::Error>;
+}
+
+impl<C: Read> Read for FaultyConnection<C> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match self {
+            Self::Open { inner, delay_ms } => {
+                // The request goes out on time, the response is late
+                if *delay_ms > 0 {
+                    Timer::after_millis((*delay_ms).into()).await;
+                    *delay_ms = 0;
+                }
+                inner.read(buf).await.map_err(FaultError::Io)
+            }
+            Self::ServerError { sent } => {
+                let rest = &SERVER_ERROR_RESPONSE[*sent..];
+                let len = rest.len().min(buf.len());
+                buf[..len].copy_from_slice(&rest[..len]);
+                *sent += len;
+                Ok(len)
+            }
+        }
+    }
+}
+
+impl<C: Write> Write for FaultyConnection<C> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match self {
+            Self::Open { inner, .. } => inner.write(buf).await.map_err(FaultError::Io),
+            // The request is swallowed, the answer doesn't depend on it
+            Self::ServerError { .. } => Ok(buf.len()),
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        match self {
+            Self::Open { inner, .. } => inner.flush().await.map_err(FaultError::Io),
+            Self::ServerError { .. } => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::{Ipv4Addr, SocketAddrV4};
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    /// Server that is never reached: every fault is injected before it
+    struct Unreachable;
+
+    impl ErrorType for Unreachable {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Unreachable {
+        async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            Err(ErrorKind::NotConnected)
+        }
+    }
+
+    impl Write for Unreachable {
+        async fn write(&mut self, _buf: &[u8]) -> Result<usize, ErrorKind> {
+            Err(ErrorKind::NotConnected)
+        }
+    }
+
+    impl TcpConnect for Unreachable {
+        type Error = ErrorKind;
+        type Connection<'m> = Unreachable;
+
+        async fn connect(&self, _remote: SocketAddr) -> Result<Unreachable, ErrorKind> {
+            Err(ErrorKind::TimedOut)
+        }
+    }
+
+    fn ready<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future not ready"),
+        }
+    }
+
+    const REMOTE: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80));
+
+    #[test]
+    fn test_plan_is_reproducible() {
+        let faults = Faults::none()
+            .with_latency(100, 50)
+            .with_drops(20)
+            .with_server_errors(20);
+        let mut a = FaultPlan::new(faults, 42);
+        let mut b = FaultPlan::new(faults, 42);
+
+        let mut dropped = 0;
+        for _ in 0..1000 {
+            let fault = a.next_fault();
+            assert_eq!(fault, b.next_fault());
+            match fault {
+                Fault::Delayed(delay_ms) => assert!((100..=150).contains(&delay_ms)),
+                Fault::Dropped => dropped += 1,
+                Fault::ServerError => {}
+            }
+        }
+        assert!((150..250).contains(&dropped), "{dropped} drops");
+
+        let mut clean = FaultPlan::new(Faults::none(), 0);
+        assert!((0..100).all(|_| clean.next_fault() == Fault::Delayed(0)));
+    }
+
+    #[test]
+    fn test_injected_server_error() {
+        let tcp = FaultyTcp::new(
+            &Unreachable,
+            FaultPlan::new(Faults::none().with_server_errors(100), 1),
+        );
+        let mut connection = ready(tcp.connect(REMOTE)).unwrap();
+        assert_eq!(ready(connection.write(b"GET / HTTP/1.1\r\n\r\n")), Ok(18));
+
+        // Read in small pieces, like a slow socket
+        let mut response = [0u8; SERVER_ERROR_RESPONSE.len()];
+        let mut len = 0;
+        while len < response.len() {
+            let end = (len + 7).min(response.len());
+            len += ready(connection.read(&mut response[len..end])).unwrap();
+        }
+        assert_eq!(&response[..], SERVER_ERROR_RESPONSE);
+        assert_eq!(ready(connection.read(&mut response)), Ok(0));
+        assert_eq!(tcp.injected(), 1);
+    }
+
+    #[test]
+    fn test_dropped_connection() {
+        let tcp = FaultyTcp::new(
+            &Unreachable,
+            FaultPlan::new(Faults::none().with_drops(100), 1),
+        );
+        assert!(matches!(
+            ready(tcp.connect(REMOTE)),
+            Err(FaultError::Dropped)
+        ));
+
+        // Without faults the wrapped connect runs, and fails here
+        let tcp = FaultyTcp::new(&Unreachable, FaultPlan::new(Faults::none(), 1));
+        assert!(matches!(
+            ready(tcp.connect(REMOTE)),
+            Err(FaultError::Io(ErrorKind::TimedOut))
+        ));
+        assert_eq!(tcp.injected(), 0);
+    }
+}
@@ -2,22 +2,56 @@
 #![doc = "cluster-net: REST API client library for cluster-matrix"]
 #![doc = ""]
 #![doc = "A no_std library for making HTTP requests to a cluster server."]
-#![doc = "Provides read-only access to cluster data via REST API."]
+#![doc = "Fetches cluster data and reports seat statuses via REST API."]
 
 #[cfg(feature = "std")]
 extern crate std;
 
+pub mod auth;
+pub mod cancel;
 pub mod client;
+pub mod device;
 pub mod endpoints;
 pub mod error;
+pub mod mdns;
+pub mod middleware;
+pub mod rate_limit;
+pub mod shared;
+pub mod syslog;
+pub mod validators;
 
+#[cfg(feature = "live-updates")]
+pub mod events;
+#[cfg(feature = "faults")]
+pub mod faults;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "stream-parse")]
+pub mod stream;
 #[cfg(feature = "tls")]
 pub mod tls;
 
 // Re-export commonly used types
-pub use client::Client;
+pub use auth::{Auth, TokenSource};
+pub use cancel::CancelToken;
+pub use client::{Client, Conditional, ETag, Timeouts};
+pub use device::DeviceId;
 pub use error::{Error, Result};
+pub use mdns::MdnsResponder;
+pub use middleware::Middleware;
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use shared::SharedClient;
+pub use syslog::{RemoteLog, Severity};
+pub use validators::Validators;
 
+#[cfg(feature = "live-updates")]
+pub use events::{Event, EventParser};
+#[cfg(feature = "faults")]
+pub use faults::{FaultPlan, Faults, FaultyTcp};
+#[cfg(feature = "metrics")]
+pub use metrics::{Metrics, NetworkStats};
+#[cfg(feature = "stream-parse")]
+pub use stream::LayoutParser;
 #[cfg(feature = "tls")]
 pub use tls::{create_tls_config, create_tls_config_with_psk};
 
@@ -27,5 +61,10 @@ pub const DEFAULT_BUFFER_SIZE: usize = 8192;
 /// Maximum URL length
 pub const MAX_URL_LENGTH: usize = 256;
 
-/// Maximum number of headers in a request
-pub const MAX_HEADERS: usize = 8;
+/// Headers the client adds itself: `Accept`, the credentials,
+/// `If-None-Match` and `If-Modified-Since`
+pub const BUILTIN_HEADERS: usize = 4;
+
+/// Maximum number of headers in a request, the built-in ones and those
+/// added by middleware
+pub const MAX_HEADERS: usize = BUILTIN_HEADERS + middleware::MAX_EXTRA_HEADERS;
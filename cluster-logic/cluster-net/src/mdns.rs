@@ -0,0 +1,436 @@
+//! mDNS responder for finding panels on the LAN
+//!
+//! Each panel answers as `cluster-panel-<device id>.local` and advertises its
+//! diagnostic HTTP endpoint as an `_http._tcp` service, so admins can find it
+//! without tracking DHCP leases. [`MdnsResponder`] only builds and parses
+//! packets; the caller owns the UDP socket bound to [`MDNS_PORT`] and joined
+//! to [`MDNS_MULTICAST_ADDR`].
+
+use crate::device::{DEVICE_ID_LENGTH, DeviceId};
+use crate::error::{Error, Result};
+use heapless::String;
+
+/// UDP port used by mDNS
+pub const MDNS_PORT: u16 = 5353;
+
+/// IPv4 multicast group used by mDNS
+pub const MDNS_MULTICAST_ADDR: [u8; 4] = [224, 0, 0, 251];
+
+/// Prefix of the advertised hostname, followed by the device ID
+pub const HOSTNAME_PREFIX: &str = "cluster-panel-";
+
+/// Maximum length of the advertised hostname (without `.local`)
+pub const MAX_HOSTNAME_LENGTH: usize = HOSTNAME_PREFIX.len() + DEVICE_ID_LENGTH;
+
+/// Service type advertised for the diagnostic endpoints
+pub const HTTP_SERVICE: &str = "_http._tcp.local";
+
+/// Time-to-live of the advertised records in seconds
+pub const RECORD_TTL: u32 = 120;
+
+/// Maximum length of a decoded name in a query
+const MAX_NAME_LENGTH: usize = 128;
+
+/// Maximum number of compression pointers followed in a single name
+const MAX_POINTER_JUMPS: usize = 8;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+
+const CLASS_IN: u16 = 1;
+/// Set on records this host is the only owner of
+const CACHE_FLUSH: u16 = 0x8000;
+
+/// Response flags: QR (response) and AA (authoritative)
+const RESPONSE_FLAGS: u16 = 0x8400;
+
+const HEADER_SIZE: usize = 12;
+
+/// Records included in a response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Answers {
+    a: bool,
+    ptr: bool,
+    srv: bool,
+    txt: bool,
+}
+
+impl Answers {
+    const ALL: Self = Self {
+        a: true,
+        ptr: true,
+        srv: true,
+        txt: true,
+    };
+
+    const fn count(&self) -> u16 {
+        self.a as u16 + self.ptr as u16 + self.srv as u16 + self.txt as u16
+    }
+}
+
+/// Answers mDNS queries for the panel's hostname and HTTP service
+#[derive(Debug, Clone)]
+pub struct MdnsResponder {
+    hostname: String<MAX_HOSTNAME_LENGTH>,
+    address: [u8; 4],
+    port: u16,
+    path: &'static str,
+}
+
+impl MdnsResponder {
+    /// Create a responder for a panel reachable at `address`
+    ///
+    /// `port` and `path` describe the advertised HTTP endpoint
+    /// (e.g. 9100 and "/metrics").
+    pub fn new(device_id: &DeviceId, address: [u8; 4], port: u16, path: &'static str) -> Self {
+        let mut hostname = String::new();
+        // Cannot overflow: sized for the prefix plus a full device ID
+        let _ = hostname.push_str(HOSTNAME_PREFIX);
+        let _ = hostname.push_str(device_id.as_str());
+        Self {
+            hostname,
+            address,
+            port,
+            path,
+        }
+    }
+
+    /// Get the advertised hostname, without the `.local` suffix
+    pub fn hostname(&self) -> &str {
+        self.hostname.as_str()
+    }
+
+    /// Update the advertised address (e.g. after a new DHCP lease)
+    pub const fn set_address(&mut self, address: [u8; 4]) {
+        self.address = address;
+    }
+
+    /// Build the answer to an incoming packet
+    ///
+    /// Returns the length of the response written to `out`, or `None` if the
+    /// packet is not a query for one of our names.
+    pub fn handle_packet(&self, packet: &[u8], out: &mut [u8]) -> Result<Option<usize>> {
+        if packet.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+        let flags = u16::from_be_bytes([packet[2], packet[3]]);
+        if flags & 0x8000 != 0 {
+            // Response from another host
+            return Ok(None);
+        }
+
+        let questions = u16::from_be_bytes([packet[4], packet[5]]);
+        let mut answers = Answers::default();
+        let mut pos = HEADER_SIZE;
+        for _ in 0..questions {
+            let mut name: String<MAX_NAME_LENGTH> = String::new();
+            let Some(next) = read_name(packet, pos, &mut name) else {
+                return Ok(None);
+            };
+            let Some(fixed) = packet.get(next..next + 4) else {
+                return Ok(None);
+            };
+            let qtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+            pos = next + 4;
+
+            self.match_question(&name, qtype, &mut answers);
+        }
+
+        if answers.count() == 0 {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("mDNS query answered with {} records", answers.count());
+
+        self.write_response(answers, out).map(Some)
+    }
+
+    /// Build an unsolicited announcement of every record
+    ///
+    /// Send it once the network is up so caches pick up the panel right away.
+    pub fn announcement(&self, out: &mut [u8]) -> Result<usize> {
+        self.write_response(Answers::ALL, out)
+    }
+
+    fn match_question(&self, name: &str, qtype: u16, answers: &mut Answers) {
+        let any = qtype == TYPE_ANY;
+
+        // "<host>.local"
+        if let Some(host) = strip_suffix_ignore_case(name, ".local")
+            && host.eq_ignore_ascii_case(self.hostname())
+        {
+            answers.a |= any || qtype == TYPE_A;
+        }
+
+        // "_http._tcp.local"
+        if name.eq_ignore_ascii_case(HTTP_SERVICE) && (any || qtype == TYPE_PTR) {
+            *answers = Answers::ALL;
+        }
+
+        // "<host>._http._tcp.local"
+        if let Some(instance) =
+            strip_suffix_ignore_case(name, HTTP_SERVICE).and_then(|rest| rest.strip_suffix('.'))
+            && instance.eq_ignore_ascii_case(self.hostname())
+        {
+            answers.srv |= any || qtype == TYPE_SRV;
+            answers.txt |= any || qtype == TYPE_TXT;
+            answers.a |= answers.srv;
+        }
+    }
+
+    fn write_response(&self, answers: Answers, out: &mut [u8]) -> Result<usize> {
+        let mut w = Writer { buf: out, pos: 0 };
+
+        // Header: id 0, no questions, answers only
+        w.u16(0)?;
+        w.u16(RESPONSE_FLAGS)?;
+        w.u16(0)?;
+        w.u16(answers.count())?;
+        w.u16(0)?;
+        w.u16(0)?;
+
+        if answers.ptr {
+            w.name(&[HTTP_SERVICE])?;
+            w.record_header(TYPE_PTR, CLASS_IN)?;
+            let start = w.begin_rdata()?;
+            w.name(&[self.hostname(), HTTP_SERVICE])?;
+            w.end_rdata(start)?;
+        }
+
+        if answers.srv {
+            w.name(&[self.hostname(), HTTP_SERVICE])?;
+            w.record_header(TYPE_SRV, CLASS_IN | CACHE_FLUSH)?;
+            let start = w.begin_rdata()?;
+            w.u16(0)?; // Priority
+            w.u16(0)?; // Weight
+            w.u16(self.port)?;
+            w.name(&[self.hostname(), "local"])?;
+            w.end_rdata(start)?;
+        }
+
+        if answers.txt {
+            w.name(&[self.hostname(), HTTP_SERVICE])?;
+            w.record_header(TYPE_TXT, CLASS_IN | CACHE_FLUSH)?;
+            let start = w.begin_rdata()?;
+            let entry_len = "path=".len() + self.path.len();
+            w.u8(u8::try_from(entry_len).map_err(|_| Error::BufferTooSmall)?)?;
+            w.bytes(b"path=")?;
+            w.bytes(self.path.as_bytes())?;
+            w.end_rdata(start)?;
+        }
+
+        if answers.a {
+            w.name(&[self.hostname(), "local"])?;
+            w.record_header(TYPE_A, CLASS_IN | CACHE_FLUSH)?;
+            w.u16(4)?;
+            w.bytes(&self.address)?;
+        }
+
+        Ok(w.pos)
+    }
+}
+
+fn strip_suffix_ignore_case<'n>(name: &'n str, suffix: &str) -> Option<&'n str> {
+    let split = name.len().checked_sub(suffix.len())?;
+    if !name.is_char_boundary(split) {
+        return None;
+    }
+    let (head, tail) = name.split_at(split);
+    tail.eq_ignore_ascii_case(suffix).then_some(head)
+}
+
+/// Decode a possibly compressed name starting at `pos`
+///
+/// Returns the position right after the name in the original packet.
+fn read_name(packet: &[u8], mut pos: usize, out: &mut String<MAX_NAME_LENGTH>) -> Option<usize> {
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(pos)?;
+        if len & 0xC0 == 0xC0 {
+            let pointer = usize::from(len & 0x3F) << 8 | usize::from(*packet.get(pos + 1)?);
+            end.get_or_insert(pos + 2);
+            jumps += 1;
+            if jumps > MAX_POINTER_JUMPS {
+                return None;
+            }
+            pos = pointer;
+            continue;
+        }
+        if len == 0 {
+            return Some(end.unwrap_or(pos + 1));
+        }
+
+        let label = packet.get(pos + 1..pos + 1 + usize::from(len))?;
+        if !out.is_empty() {
+            out.push('.').ok()?;
+        }
+        out.push_str(core::str::from_utf8(label).ok()?).ok()?;
+        pos += 1 + usize::from(len);
+    }
+}
+
+/// Bounds-checked packet writer
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, data: &[u8]) -> Result<()> {
+        let end = self.pos + data.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn u8(&mut self, value: u8) -> Result<()> {
+        self.bytes(&[value])
+    }
+
+    fn u16(&mut self, value: u16) -> Result<()> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    fn u32(&mut self, value: u32) -> Result<()> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    /// Write the dot-separated parts as one uncompressed name
+    fn name(&mut self, parts: &[&str]) -> Result<()> {
+        for label in parts.iter().flat_map(|part| part.split('.')) {
+            let len = u8::try_from(label.len()).map_err(|_| Error::BufferTooSmall)?;
+            self.u8(len)?;
+            self.bytes(label.as_bytes())?;
+        }
+        self.u8(0)
+    }
+
+    fn record_header(&mut self, rtype: u16, class: u16) -> Result<()> {
+        self.u16(rtype)?;
+        self.u16(class)?;
+        self.u32(RECORD_TTL)
+    }
+
+    /// Reserve the RDLENGTH field, returning its position
+    fn begin_rdata(&mut self) -> Result<usize> {
+        let start = self.pos;
+        self.u16(0)?;
+        Ok(start)
+    }
+
+    /// Fill in the RDLENGTH field reserved at `start`
+    fn end_rdata(&mut self, start: usize) -> Result<()> {
+        let len = u16::try_from(self.pos - start - 2).map_err(|_| Error::BufferTooSmall)?;
+        self.buf[start..start + 2].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(name: &[&str], qtype: u16) -> ([u8; 128], usize) {
+        let mut buf = [0u8; 128];
+        let mut w = Writer {
+            buf: &mut buf,
+            pos: 0,
+        };
+        w.bytes(&[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+        w.name(name).unwrap();
+        w.u16(qtype).unwrap();
+        w.u16(CLASS_IN).unwrap();
+        let len = w.pos;
+        (buf, len)
+    }
+
+    fn responder() -> MdnsResponder {
+        MdnsResponder::new(
+            &DeviceId::from_unique_id(0xABCD),
+            [192, 168, 1, 42],
+            9100,
+            "/metrics",
+        )
+    }
+
+    #[test]
+    fn test_hostname() {
+        assert_eq!(responder().hostname(), "cluster-panel-000000000000abcd");
+    }
+
+    #[test]
+    fn test_answers_host_query() {
+        let responder = responder();
+        let (packet, len) = query(&["CLUSTER-PANEL-000000000000abcd.local"], TYPE_A);
+        let mut out = [0u8; 512];
+        let written = responder
+            .handle_packet(&packet[..len], &mut out)
+            .unwrap()
+            .unwrap();
+
+        // One answer, ending with the IPv4 address
+        assert_eq!(&out[2..4], &RESPONSE_FLAGS.to_be_bytes());
+        assert_eq!(&out[6..8], &1u16.to_be_bytes());
+        assert_eq!(&out[written - 4..written], &[192, 168, 1, 42]);
+    }
+
+    #[test]
+    fn test_answers_service_browse() {
+        let responder = responder();
+        let (packet, len) = query(&[HTTP_SERVICE], TYPE_PTR);
+        let mut out = [0u8; 512];
+        let written = responder
+            .handle_packet(&packet[..len], &mut out)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&out[6..8], &4u16.to_be_bytes());
+
+        let mut name: String<MAX_NAME_LENGTH> = String::new();
+        let next = read_name(&out[..written], HEADER_SIZE, &mut name).unwrap();
+        assert_eq!(name, HTTP_SERVICE);
+        assert_eq!(&out[next..next + 2], &TYPE_PTR.to_be_bytes());
+
+        // Same records as the announcement
+        let mut announcement = [0u8; 512];
+        let announced = responder.announcement(&mut announcement).unwrap();
+        assert_eq!(out[..written], announcement[..announced]);
+    }
+
+    #[test]
+    fn test_ignores_other_names_and_responses() {
+        let responder = responder();
+        let mut out = [0u8; 512];
+
+        let (packet, len) = query(&["other-host.local"], TYPE_A);
+        assert_eq!(responder.handle_packet(&packet[..len], &mut out), Ok(None));
+
+        let (mut packet, len) = query(&[HTTP_SERVICE], TYPE_PTR);
+        packet[2] = 0x84;
+        assert_eq!(responder.handle_packet(&packet[..len], &mut out), Ok(None));
+    }
+
+    #[test]
+    fn test_read_compressed_name() {
+        // "local" at 12, then "_tcp" + pointer to it at 19
+        let packet = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, b'l', b'o', b'c', b'a', b'l', 0, 4, b'_', b't',
+            b'c', b'p', 0xC0, 12,
+        ];
+        let mut name: String<MAX_NAME_LENGTH> = String::new();
+        assert_eq!(read_name(&packet, 19, &mut name), Some(26));
+        assert_eq!(name, "_tcp.local");
+
+        // Pointer loops are rejected
+        let looping = [0xC0, 0];
+        assert_eq!(read_name(&looping, 0, &mut String::new()), None);
+    }
+}
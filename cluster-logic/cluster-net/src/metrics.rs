@@ -0,0 +1,311 @@
+//! Prometheus metrics endpoint
+//!
+//! Panels expose their health in the Prometheus text format so the existing
+//! monitoring can scrape them directly. [`Metrics`] holds the values,
+//! [`NetworkStats`] collects request counters as a client [`Middleware`], and
+//! [`serve_connection`] answers a single HTTP request on an accepted socket
+//! (e.g. an `embassy_net::tcp::TcpSocket`).
+//!
+//! Only available with the `metrics` feature, as the response buffer costs a
+//! few KiB of RAM.
+
+use crate::error::{Error, Result};
+use crate::middleware::{Middleware, RequestContext, ResponseInfo};
+use cluster_core::models::Layout;
+use core::fmt::{self, Write as _};
+use embedded_io_async::{Read, Write};
+use heapless::String;
+
+/// Path the metrics are served on
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Default port used by Prometheus exporters of this kind
+pub const DEFAULT_METRICS_PORT: u16 = 9100;
+
+/// Size of the rendered metrics body
+pub const METRICS_BODY_SIZE: usize = 2048;
+
+/// Request counters collected from the HTTP client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    /// Requests that got a successful response
+    pub requests_ok: u32,
+    /// Requests that failed (network error or bad status)
+    pub requests_failed: u32,
+    /// Total size of received response bodies
+    pub bytes_received: u64,
+}
+
+impl NetworkStats {
+    /// Create zeroed counters
+    pub const fn new() -> Self {
+        Self {
+            requests_ok: 0,
+            requests_failed: 0,
+            bytes_received: 0,
+        }
+    }
+}
+
+impl Middleware for NetworkStats {
+    fn after_response(&mut self, _ctx: &RequestContext<'_>, info: &ResponseInfo) {
+        match info.error {
+            None => self.requests_ok = self.requests_ok.wrapping_add(1),
+            Some(_) => self.requests_failed = self.requests_failed.wrapping_add(1),
+        }
+        self.bytes_received = self.bytes_received.wrapping_add(info.body_len as u64);
+    }
+}
+
+/// Values exported on the metrics endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Display refresh rate in frames per second
+    pub frames_per_second: u32,
+    /// Hash of the frame on display, e.g. `Hub75::frame_hash`
+    ///
+    /// A hash that stays the same for an hour flags a frozen panel.
+    pub frame_hash: u32,
+    /// Whether the frame on display is all black
+    pub frame_blank: bool,
+    /// Time since boot in seconds
+    pub uptime_seconds: u64,
+    /// Occupancy percentage per floor, in [`Layout::FLOORS`] order
+    pub occupancy: [u8; 6],
+    /// HTTP client counters
+    pub network: NetworkStats,
+}
+
+impl Metrics {
+    /// Create zeroed metrics, usable in a `static`
+    pub const fn new() -> Self {
+        Self {
+            frames_per_second: 0,
+            frame_hash: 0,
+            frame_blank: false,
+            uptime_seconds: 0,
+            occupancy: [0; 6],
+            network: NetworkStats::new(),
+        }
+    }
+
+    /// Refresh the per-floor occupancy from a layout
+    pub fn update_occupancy(&mut self, layout: &Layout) {
+        for (slot, (_, cluster)) in self.occupancy.iter_mut().zip(layout.clusters()) {
+            *slot = cluster.get_stats().occupancy_percentage();
+        }
+    }
+
+    /// Write the metrics in the Prometheus text exposition format
+    pub fn write_prometheus<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write_header(
+            out,
+            "cluster_matrix_frames_per_second",
+            "Display refresh rate",
+            "gauge",
+        )?;
+        writeln!(
+            out,
+            "cluster_matrix_frames_per_second {}",
+            self.frames_per_second
+        )?;
+
+        write_header(
+            out,
+            "cluster_matrix_frame_hash",
+            "Hash of the frame on display",
+            "gauge",
+        )?;
+        writeln!(out, "cluster_matrix_frame_hash {}", self.frame_hash)?;
+
+        write_header(
+            out,
+            "cluster_matrix_frame_blank",
+            "Whether the frame on display is all black",
+            "gauge",
+        )?;
+        writeln!(
+            out,
+            "cluster_matrix_frame_blank {}",
+            u8::from(self.frame_blank)
+        )?;
+
+        write_header(
+            out,
+            "cluster_matrix_uptime_seconds",
+            "Time since boot",
+            "counter",
+        )?;
+        writeln!(out, "cluster_matrix_uptime_seconds {}", self.uptime_seconds)?;
+
+        write_header(
+            out,
+            "cluster_matrix_occupancy_percent",
+            "Seat occupancy per floor",
+            "gauge",
+        )?;
+        for (id, occupancy) in Layout::FLOORS.iter().zip(self.occupancy) {
+            writeln!(
+                out,
+                "cluster_matrix_occupancy_percent{{floor=\"{}\"}} {}",
+                id, occupancy
+            )?;
+        }
+
+        write_header(
+            out,
+            "cluster_matrix_http_requests_total",
+            "HTTP requests made by the panel",
+            "counter",
+        )?;
+        let network = &self.network;
+        for (result, count) in [
+            ("ok", network.requests_ok),
+            ("error", network.requests_failed),
+        ] {
+            writeln!(
+                out,
+                "cluster_matrix_http_requests_total{{result=\"{}\"}} {}",
+                result, count
+            )?;
+        }
+
+        write_header(
+            out,
+            "cluster_matrix_http_received_bytes_total",
+            "Size of received HTTP response bodies",
+            "counter",
+        )?;
+        writeln!(
+            out,
+            "cluster_matrix_http_received_bytes_total {}",
+            network.bytes_received
+        )
+    }
+}
+
+fn write_header<W: fmt::Write>(out: &mut W, name: &str, help: &str, kind: &str) -> fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind)
+}
+
+/// Extract the method and path from the request line of an HTTP request
+fn parse_request_line(request: &[u8]) -> Option<(&str, &str)> {
+    let end = request.windows(2).position(|w| w == b"\r\n")?;
+    let line = core::str::from_utf8(&request[..end]).ok()?;
+    let mut parts = line.split(' ');
+    let method = parts.next()?;
+    let path = parts.next()?;
+    Some((method, path))
+}
+
+/// Answer one HTTP request on an accepted connection
+///
+/// `GET /metrics` gets the rendered metrics, anything else a 404. `scratch`
+/// holds the request line and should be a few hundred bytes. The caller
+/// closes the connection afterwards.
+pub async fn serve_connection<C>(
+    connection: &mut C,
+    metrics: &Metrics,
+    scratch: &mut [u8],
+) -> Result<()>
+where
+    C: Read + Write,
+{
+    // Read until the request line is complete
+    let mut len = 0;
+    let (method, path) = loop {
+        if len == scratch.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        let read = connection
+            .read(&mut scratch[len..])
+            .await
+            .map_err(|_| Error::ConnectionError)?;
+        if read == 0 {
+            return Err(Error::ConnectionError);
+        }
+        len += read;
+
+        if let Some(request) = parse_request_line(&scratch[..len]) {
+            break request;
+        }
+    };
+
+    #[cfg(feature = "defmt")]
+    defmt::debug!("Metrics request: {} {}", method, path);
+
+    let mut body: String<METRICS_BODY_SIZE> = String::new();
+    let status = if method == "GET" && path == METRICS_PATH {
+        metrics
+            .write_prometheus(&mut body)
+            .map_err(|_| Error::BufferTooSmall)?;
+        "200 OK"
+    } else {
+        let _ = body.push_str("Not Found\n");
+        "404 Not Found"
+    };
+
+    let mut head: String<128> = String::new();
+    write!(
+        head,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )
+    .map_err(|_| Error::BufferTooSmall)?;
+
+    connection
+        .write_all(head.as_bytes())
+        .await
+        .map_err(|_| Error::ConnectionError)?;
+    connection
+        .write_all(body.as_bytes())
+        .await
+        .map_err(|_| Error::ConnectionError)?;
+    connection.flush().await.map_err(|_| Error::ConnectionError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line() {
+        assert_eq!(
+            parse_request_line(b"GET /metrics HTTP/1.1\r\nHost: panel\r\n\r\n"),
+            Some(("GET", "/metrics"))
+        );
+        assert_eq!(parse_request_line(b"GET /metr"), None);
+    }
+
+    #[test]
+    fn test_prometheus_format() {
+        let mut metrics = Metrics {
+            frames_per_second: 60,
+            frame_hash: 0xdead_beef,
+            frame_blank: true,
+            occupancy: [10, 20, 30, 40, 50, 60],
+            ..Metrics::default()
+        };
+        metrics.network.after_response(
+            &RequestContext::new(crate::middleware::Method::Get, "/layout", "http://x/layout"),
+            &ResponseInfo {
+                status: Some(200),
+                body_len: 512,
+                error: None,
+            },
+        );
+
+        let mut out: String<METRICS_BODY_SIZE> = String::new();
+        metrics.write_prometheus(&mut out).unwrap();
+
+        assert!(out.contains("# TYPE cluster_matrix_frames_per_second gauge\n"));
+        assert!(out.contains("cluster_matrix_frames_per_second 60\n"));
+        assert!(out.contains("cluster_matrix_frame_hash 3735928559\n"));
+        assert!(out.contains("cluster_matrix_frame_blank 1\n"));
+        assert!(out.contains("cluster_matrix_occupancy_percent{floor=\"f1b\"} 30\n"));
+        assert!(out.contains("cluster_matrix_http_requests_total{result=\"ok\"} 1\n"));
+        assert!(out.contains("cluster_matrix_http_received_bytes_total 512\n"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Request/response middleware hooks
+//!
+//! Middleware lets applications instrument traffic (extra headers, timing,
+//! metrics) without touching the endpoint implementations. The client calls
+//! [`Middleware::before_request`] right before a request is sent and
+//! [`Middleware::after_response`] once the response (or the error) is known.
+
+use crate::error::{Error, Result};
+use heapless::{String, Vec};
+
+/// Maximum length of a header name added by middleware
+pub const MAX_HEADER_NAME_LENGTH: usize = 32;
+
+/// Maximum length of a header value added by middleware
+pub const MAX_HEADER_VALUE_LENGTH: usize = 64;
+
+/// Maximum number of headers added by middleware, `X-Device-Id` included
+///
+/// The headers the client adds itself don't count, see
+/// [`BUILTIN_HEADERS`](crate::BUILTIN_HEADERS).
+pub const MAX_EXTRA_HEADERS: usize = 6;
+
+/// HTTP method of an outgoing request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+}
+
+impl Method {
+    /// Method name as sent on the wire
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+        }
+    }
+}
+
+/// Context of an outgoing request, handed to middleware
+#[derive(Debug)]
+pub struct RequestContext<'r> {
+    /// HTTP method
+    pub method: Method,
+    /// API path (e.g. "/cluster/f0")
+    pub path: &'r str,
+    /// Full request URL
+    pub url: &'r str,
+    headers: Vec<
+        (
+            String<MAX_HEADER_NAME_LENGTH>,
+            String<MAX_HEADER_VALUE_LENGTH>,
+        ),
+        MAX_EXTRA_HEADERS,
+    >,
+}
+
+impl<'r> RequestContext<'r> {
+    /// Create a new request context without extra headers
+    pub fn new(method: Method, path: &'r str, url: &'r str) -> Self {
+        Self {
+            method,
+            path,
+            url,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Add a header to the request
+    ///
+    /// Returns `Error::BufferTooSmall` if the header does not fit.
+    pub fn add_header(&mut self, name: &str, value: &str) -> Result<()> {
+        let name = String::try_from(name).map_err(|_| Error::BufferTooSmall)?;
+        let value = String::try_from(value).map_err(|_| Error::BufferTooSmall)?;
+        self.headers
+            .push((name, value))
+            .map_err(|_| Error::BufferTooSmall)
+    }
+
+    /// Iterate over the headers added by middleware
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+/// Outcome of a request, handed to middleware after the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseInfo {
+    /// HTTP status code, if a response was received
+    pub status: Option<u16>,
+    /// Size of the response body in bytes (0 on error)
+    pub body_len: usize,
+    /// Error returned to the caller, if any
+    pub error: Option<Error>,
+}
+
+impl ResponseInfo {
+    /// Check if the request succeeded
+    pub const fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Hooks called by the client around every request
+///
+/// Both hooks have empty default implementations so middleware only needs to
+/// implement what it uses. Timing is left to the implementation since the
+/// client has no clock: take a timestamp in `before_request` and compare it in
+/// `after_response`.
+pub trait Middleware {
+    /// Called before the request is sent
+    ///
+    /// Returning an error aborts the request; `after_response` is still called
+    /// with that error.
+    fn before_request(&mut self, _ctx: &mut RequestContext<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the response has been read or the request failed
+    fn after_response(&mut self, _ctx: &RequestContext<'_>, _response: &ResponseInfo) {}
+}
+
+/// Chain two middlewares, running `A` first on the way out and last on the way in
+impl<A: Middleware, B: Middleware> Middleware for (A, B) {
+    fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> Result<()> {
+        self.0.before_request(ctx)?;
+        self.1.before_request(ctx)
+    }
+
+    fn after_response(&mut self, ctx: &RequestContext<'_>, response: &ResponseInfo) {
+        self.1.after_response(ctx, response);
+        self.0.after_response(ctx, response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DeviceId;
+
+    impl Middleware for DeviceId {
+        fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> Result<()> {
+            ctx.add_header("X-Device-Id", "matrix-01")
+        }
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        ok: u32,
+        failed: u32,
+    }
+
+    impl Middleware for Counter {
+        fn after_response(&mut self, _ctx: &RequestContext<'_>, response: &ResponseInfo) {
+            if response.is_success() {
+                self.ok += 1;
+            } else {
+                self.failed += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_chained_middleware() {
+        let mut chain = (DeviceId, Counter::default());
+        let mut ctx = RequestContext::new(Method::Get, "/layout", "http://host/layout");
+
+        chain.before_request(&mut ctx).unwrap();
+        assert_eq!(ctx.headers().next(), Some(("X-Device-Id", "matrix-01")));
+
+        let response = ResponseInfo {
+            status: Some(500),
+            body_len: 0,
+            error: Some(Error::InvalidStatus(500)),
+        };
+        chain.after_response(&ctx, &response);
+        assert_eq!(chain.1.failed, 1);
+        assert_eq!(chain.1.ok, 0);
+    }
+
+    #[test]
+    fn test_header_capacity() {
+        let mut ctx = RequestContext::new(Method::Get, "/", "http://host/");
+        for _ in 0..MAX_EXTRA_HEADERS {
+            ctx.add_header("X-A", "b").unwrap();
+        }
+        assert_eq!(ctx.add_header("X-A", "b"), Err(Error::BufferTooSmall));
+    }
+}
@@ -0,0 +1,124 @@
+//! Per-endpoint rate limiting
+//!
+//! [`RateLimiter`] caps how often each endpoint may be hit. It is built into
+//! [`SharedClient`](crate::shared::SharedClient): a request arriving too
+//! early waits for its turn instead of failing, and only successful requests
+//! start a new interval, so a failed request can be retried right away.
+
+/// Minimum interval between two requests to paths starting with `prefix`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Path prefix the limit applies to (e.g. "/cluster/")
+    pub prefix: &'static str,
+    /// Minimum time between two requests in milliseconds
+    pub min_interval_ms: u32,
+}
+
+impl RateLimit {
+    /// Create a new rate limit rule
+    pub const fn new(prefix: &'static str, min_interval_ms: u32) -> Self {
+        Self {
+            prefix,
+            min_interval_ms,
+        }
+    }
+}
+
+/// Rate limiter with one rule per endpoint
+///
+/// The first rule whose prefix matches the request path applies. Paths that
+/// match no rule are never limited. The limiter has no clock of its own:
+/// times are passed in milliseconds, e.g. from
+/// `embassy_time::Instant::now().as_millis()`.
+#[derive(Debug, Clone)]
+pub struct RateLimiter<const N: usize> {
+    rules: [RateLimit; N],
+    last_request_ms: [Option<u64>; N],
+}
+
+impl<const N: usize> RateLimiter<N> {
+    /// Create a rate limiter from a set of rules
+    pub const fn new(rules: [RateLimit; N]) -> Self {
+        Self {
+            rules,
+            last_request_ms: [None; N],
+        }
+    }
+
+    /// Index of the rule applying to `path`
+    fn rule(&self, path: &str) -> Option<usize> {
+        self.rules
+            .iter()
+            .position(|rule| path.starts_with(rule.prefix))
+    }
+
+    /// Time to wait before a request to `path` may be sent at `now_ms`, 0 if
+    /// it may go now
+    pub fn delay_ms(&self, path: &str, now_ms: u64) -> u64 {
+        let Some(index) = self.rule(path) else {
+            return 0;
+        };
+        match self.last_request_ms[index] {
+            Some(last) => {
+                let elapsed = now_ms.saturating_sub(last);
+                u64::from(self.rules[index].min_interval_ms).saturating_sub(elapsed)
+            }
+            None => 0,
+        }
+    }
+
+    /// Record a successful request to `path` at `now_ms`, starting a new
+    /// interval
+    pub fn record(&mut self, path: &str, now_ms: u64) {
+        if let Some(index) = self.rule(path) {
+            self.last_request_ms[index] = Some(now_ms);
+        }
+    }
+
+    /// Forget all recorded requests
+    pub fn reset(&mut self) {
+        self.last_request_ms = [None; N];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_per_endpoint() {
+        let mut limiter = RateLimiter::new([
+            RateLimit::new("/layout", 10_000),
+            RateLimit::new("/cluster/", 1_000),
+        ]);
+
+        assert_eq!(limiter.delay_ms("/layout", 0), 0);
+        limiter.record("/layout", 0);
+        assert_eq!(limiter.delay_ms("/cluster/f0", 100), 0);
+        limiter.record("/cluster/f0", 100);
+
+        assert_eq!(limiter.delay_ms("/layout", 5_000), 5_000);
+        assert_eq!(limiter.delay_ms("/cluster/f2", 500), 600);
+        assert_eq!(limiter.delay_ms("/cluster/f2", 1_100), 0);
+        assert_eq!(limiter.delay_ms("/layout", 10_000), 0);
+
+        // Unmatched paths are never limited
+        limiter.record("/devices/provision", 0);
+        assert_eq!(limiter.delay_ms("/devices/provision", 0), 0);
+
+        limiter.reset();
+        assert_eq!(limiter.delay_ms("/layout", 1), 0);
+    }
+
+    #[test]
+    fn test_only_recorded_requests_count() {
+        let mut limiter = RateLimiter::new([RateLimit::new("/layout", 10_000)]);
+
+        // A request that failed isn't recorded, so its retry isn't delayed
+        assert_eq!(limiter.delay_ms("/layout", 0), 0);
+        assert_eq!(limiter.delay_ms("/layout", 10), 0);
+
+        limiter.record("/layout", 10);
+        assert_eq!(limiter.delay_ms("/layout", 20), 9_990);
+    }
+}
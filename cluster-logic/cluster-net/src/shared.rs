@@ -0,0 +1,238 @@
+//! Client shared between several tasks
+//!
+//! A [`Client`] drives a single connection, so requests from concurrent tasks
+//! (layout polling, telemetry, ...) must not interleave. [`SharedClient`]
+//! queues them on an async mutex so only one request is in flight at a time,
+//! and on top of that:
+//! - holds each request back until its [`RateLimiter`] rule lets it through,
+//!   so a burst of callers is spread out instead of failing
+//! - merges identical GET requests: a caller asking for a path that is being
+//!   fetched gets a copy of that response instead of sending its own
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::rate_limit::RateLimiter;
+use core::cell::Cell;
+use core::future::Future;
+use core::ops::Range;
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{MappedMutexGuard, Mutex, MutexGuard};
+use embassy_time::{Instant, Timer};
+use embedded_nal_async::{Dns, TcpConnect};
+use heapless::String;
+
+/// HTTP client guarded by an async mutex
+///
+/// Use `NoopRawMutex` when all tasks run on the same executor and
+/// `CriticalSectionRawMutex` otherwise. `RULES` is the number of rules of
+/// the rate limiter, none by default. The last GET response is kept in a
+/// `BUF_SIZE` buffer for the callers that asked for it while it was in
+/// flight.
+pub struct SharedClient<
+    'a,
+    M: RawMutex,
+    T: TcpConnect,
+    D: Dns,
+    const BUF_SIZE: usize = 8192,
+    const RULES: usize = 0,
+> {
+    inner: Mutex<M, Inner<'a, T, D, BUF_SIZE, RULES>>,
+    /// Number of requests completed so far
+    completed: BlockingMutex<M, Cell<u64>>,
+}
+
+/// State behind the lock
+struct Inner<'a, T: TcpConnect, D: Dns, const BUF_SIZE: usize, const RULES: usize> {
+    client: Client<'a, T, D, BUF_SIZE>,
+    limiter: RateLimiter<RULES>,
+    /// Body of `last_get`
+    body: [u8; BUF_SIZE],
+    last_get: Option<Fetched>,
+}
+
+/// Last successful GET, whose body is kept in [`Inner::body`]
+struct Fetched {
+    path: String<{ crate::MAX_URL_LENGTH }>,
+    /// Requests completed once it was, itself included
+    completed: u64,
+    body: Range<usize>,
+}
+
+impl<'a, M: RawMutex, T: TcpConnect, D: Dns, const BUF_SIZE: usize>
+    SharedClient<'a, M, T, D, BUF_SIZE>
+{
+    /// Wrap a client so it can be shared between tasks, without rate limits
+    pub fn new(client: Client<'a, T, D, BUF_SIZE>) -> Self {
+        Self::with_rate_limiter(client, RateLimiter::new([]))
+    }
+}
+
+impl<'a, M: RawMutex, T: TcpConnect, D: Dns, const BUF_SIZE: usize, const RULES: usize>
+    SharedClient<'a, M, T, D, BUF_SIZE, RULES>
+{
+    /// Wrap a client so it can be shared between tasks, spacing requests
+    /// out with `limiter`
+    ///
+    /// # Example
+    /// ```no_run
+    /// use cluster_net::client::Client;
+    /// use cluster_net::rate_limit::{RateLimit, RateLimiter};
+    /// use cluster_net::shared::SharedClient;
+    /// use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    ///
+    /// # fn example<'a, T: embedded_nal_async::TcpConnect, D: embedded_nal_async::Dns>(
+    /// #     client: Client<'a, T, D>
+    /// # ) {
+    /// let limiter = RateLimiter::new([
+    ///     RateLimit::new("/layout", 30_000),
+    ///     RateLimit::new("/cluster/", 5_000),
+    /// ]);
+    /// let shared: SharedClient<'_, NoopRawMutex, _, _, 8192, 2> =
+    ///     SharedClient::with_rate_limiter(client, limiter);
+    /// # }
+    /// ```
+    pub fn with_rate_limiter(
+        client: Client<'a, T, D, BUF_SIZE>,
+        limiter: RateLimiter<RULES>,
+    ) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                client,
+                limiter,
+                body: [0; BUF_SIZE],
+                last_get: None,
+            }),
+            completed: BlockingMutex::new(Cell::new(0)),
+        }
+    }
+
+    /// Wait for exclusive access to the client
+    ///
+    /// Hold the guard for the whole request, e.g.
+    /// `Endpoints::get_cluster(&mut *shared.lock().await, id, &mut buffer)`.
+    /// Requests made through the guard skip the rate limiter and aren't
+    /// merged with others.
+    pub async fn lock(&self) -> MappedMutexGuard<'_, M, Client<'a, T, D, BUF_SIZE>> {
+        MutexGuard::map(self.inner.lock().await, |inner| &mut inner.client)
+    }
+
+    /// Perform a GET request once no other request is in flight and the rate
+    /// limiter allows it
+    ///
+    /// If the same path was fetched successfully while this call waited, its
+    /// response is copied into `buffer` instead of sending the request again.
+    pub fn get<'s, 'buf>(
+        &'s self,
+        path: &'s str,
+        buffer: &'buf mut [u8],
+    ) -> impl Future<Output = Result<&'buf [u8]>> + 's
+    where
+        'buf: 's,
+    {
+        // Taken when called rather than when first polled, so a request
+        // already in flight counts as finishing after this one was asked for
+        let asked = self.completed.lock(Cell::get);
+        async move {
+            let mut inner = loop {
+                let inner = self.inner.lock().await;
+                if let Some(fetched) = inner
+                    .last_get
+                    .as_ref()
+                    .filter(|fetched| fetched.completed > asked && fetched.path == path)
+                {
+                    #[cfg(feature = "defmt")]
+                    defmt::debug!("Merged GET {} with the one in flight", path);
+                    return copy_body(&inner.body[fetched.body.clone()], buffer);
+                }
+                if let Some(inner) = self.wait_turn(inner, path).await {
+                    break inner;
+                }
+            };
+
+            let Inner {
+                client,
+                limiter,
+                body,
+                last_get,
+            } = &mut *inner;
+            *last_get = None;
+            let result = client.get_range(path, body).await;
+            let completed = self.complete();
+            let range = result?;
+
+            limiter.record(path, Instant::now().as_millis());
+            *last_get = String::try_from(path).ok().map(|path| Fetched {
+                path,
+                completed,
+                body: range.clone(),
+            });
+            copy_body(&body[range], buffer)
+        }
+    }
+
+    /// Perform a POST request once no other request is in flight and the
+    /// rate limiter allows it
+    pub async fn post<'buf>(
+        &self,
+        path: &str,
+        body: &[u8],
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf [u8]> {
+        let mut inner = loop {
+            let inner = self.inner.lock().await;
+            if let Some(inner) = self.wait_turn(inner, path).await {
+                break inner;
+            }
+        };
+
+        let Inner {
+            client, limiter, ..
+        } = &mut *inner;
+        let result = client.post(path, body, buffer).await;
+        self.complete();
+        let response = result?;
+
+        limiter.record(path, Instant::now().as_millis());
+        Ok(response)
+    }
+
+    /// Keep the lock if the rate limiter lets a request to `path` go now,
+    /// otherwise release it and wait for the end of the interval
+    async fn wait_turn<'g>(
+        &self,
+        inner: MutexGuard<'g, M, Inner<'a, T, D, BUF_SIZE, RULES>>,
+        path: &str,
+    ) -> Option<MutexGuard<'g, M, Inner<'a, T, D, BUF_SIZE, RULES>>> {
+        let delay_ms = inner.limiter.delay_ms(path, Instant::now().as_millis());
+        if delay_ms == 0 {
+            return Some(inner);
+        }
+        drop(inner);
+
+        #[cfg(feature = "defmt")]
+        defmt::debug!("Rate limited: {} waits {}ms", path, delay_ms);
+        Timer::after_millis(delay_ms).await;
+        None
+    }
+
+    /// Count a completed request, returning the new count
+    fn complete(&self) -> u64 {
+        self.completed.lock(|completed| {
+            completed.set(completed.get() + 1);
+            completed.get()
+        })
+    }
+
+    /// Unwrap the inner client
+    pub fn into_inner(self) -> Client<'a, T, D, BUF_SIZE> {
+        self.inner.into_inner().client
+    }
+}
+
+/// Copy a response body into the caller's buffer
+fn copy_body<'buf>(body: &[u8], buffer: &'buf mut [u8]) -> Result<&'buf [u8]> {
+    let buffer = buffer.get_mut(..body.len()).ok_or(Error::BufferTooSmall)?;
+    buffer.copy_from_slice(body);
+    Ok(buffer)
+}
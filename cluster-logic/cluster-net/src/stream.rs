@@ -0,0 +1,541 @@
+//! Incremental parsing of the layout
+//!
+//! The complete layout is the largest response of the API, and
+//! [`from_json`] needs all of it in one buffer. [`LayoutParser`] is fed the
+//! body chunk by chunk as it arrives instead, and fills a [`Layout`] seat by
+//! seat: only one value at a time (a seat, a zone, a message) has to fit in
+//! its [`MAX_VALUE_SIZE`] bytes, so layouts larger than any buffer the
+//! device can spare are still read.
+//!
+//! The parser only follows the structure of the layout object. Each value
+//! is parsed by [`from_json`] on its own, so its errors locate the failing
+//! byte within that value rather than the body.
+
+use crate::error::{Error, Result, from_json};
+use cluster_core::models::{Cluster, Layout, Seat, Zone};
+use cluster_core::types::{AttributeVec, ClusterId, ClusterString, MessageString};
+use heapless::Vec;
+use serde::Deserialize;
+
+/// Maximum size of one value of the layout: a seat, a zone, or a field of
+/// a cluster
+pub const MAX_VALUE_SIZE: usize = 512;
+
+/// Size of the chunks a streamed body is read in
+pub const CHUNK_SIZE: usize = 256;
+
+/// Message of a cluster, parsed like [`Cluster::message`]
+#[derive(Deserialize)]
+struct Message(
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "cluster_core::types::lossy::deserialize")
+    )]
+    MessageString,
+);
+
+/// Name of a cluster, parsed like [`Cluster::name`]
+#[derive(Deserialize)]
+struct Name(
+    #[cfg_attr(
+        feature = "lossy-strings",
+        serde(deserialize_with = "cluster_core::types::lossy::deserialize")
+    )]
+    ClusterString,
+);
+
+/// Field of a cluster
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Message,
+    Attributes,
+    Name,
+    Seats,
+    Zones,
+}
+
+impl Field {
+    const ALL: [Field; 5] = [
+        Field::Message,
+        Field::Attributes,
+        Field::Name,
+        Field::Seats,
+        Field::Zones,
+    ];
+
+    fn from_key(key: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.key() == key)
+    }
+
+    const fn key(self) -> &'static [u8] {
+        match self {
+            Field::Message => b"message",
+            Field::Attributes => b"attributes",
+            Field::Name => b"name",
+            Field::Seats => b"seats",
+            Field::Zones => b"zones",
+        }
+    }
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Every field of a cluster, as seen by [`Field::bit`]
+const ALL_FIELDS: u8 = (1 << Field::ALL.len()) - 1;
+
+/// Every cluster of a layout, by index in [`Layout::FLOORS`]
+const ALL_CLUSTERS: u8 = (1 << Layout::FLOORS.len()) - 1;
+
+/// Object a key belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Layout,
+    Cluster,
+}
+
+/// What to do with a value once it is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    /// Key of the layout or of a cluster
+    Key(Level),
+    /// Message, attributes or name of the cluster being filled
+    Field(Field),
+    /// Seat or zone of the cluster being filled
+    Element(Field),
+    /// Unknown member, dropped without being stored
+    Skip(Level),
+}
+
+/// Position of the parser in the layout object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Expecting the layout object
+    Start,
+    /// Expecting a cluster id or the end of the layout
+    LayoutKey,
+    /// Expecting a `,` or the end of the layout
+    LayoutNext,
+    /// Expecting the object of the cluster being filled
+    ClusterStart,
+    /// Expecting a field name or the end of the cluster
+    ClusterKey,
+    /// Expecting a `,` or the end of the cluster
+    ClusterNext,
+    /// Expecting the `:` after a key, see [`LayoutParser::after_colon`]
+    Colon,
+    /// Expecting the seats or zones array
+    ArrayStart(Field),
+    /// Expecting a seat or zone, or the end of the array
+    Element(Field),
+    /// Expecting a `,` or the end of the array
+    ElementNext(Field),
+    /// Reading a value
+    Value(Target),
+    /// The layout object ended
+    Done,
+}
+
+/// Progress of the value being read
+#[derive(Debug, Default)]
+struct Capture {
+    started: bool,
+    /// Depth of nested objects and arrays
+    depth: u16,
+    in_string: bool,
+    escaped: bool,
+    /// Number or literal, which ends at the byte after it
+    scalar: bool,
+}
+
+/// Outcome of reading one byte of a value
+enum Captured {
+    /// The value continues
+    More,
+    /// The byte ended the value
+    Done,
+    /// The value ended before the byte, which belongs to what follows
+    Before,
+}
+
+/// Push parser filling a [`Layout`] from chunks of its JSON form
+///
+/// Every cluster is cleared when its object starts, and must list all its
+/// fields. Members the layout doesn't know are skipped, like `from_json`
+/// does. On error the layout is left partially filled.
+///
+/// # Example
+/// ```
+/// use cluster_net::stream::LayoutParser;
+/// # fn example(layout: &mut cluster_core::models::Layout, chunks: &[&[u8]]) -> cluster_net::Result<()> {
+/// let mut parser = LayoutParser::new(layout);
+/// for chunk in chunks {
+///     parser.feed(chunk)?;
+/// }
+/// parser.finish()
+/// # }
+/// ```
+pub struct LayoutParser<'l> {
+    layout: &'l mut Layout,
+    state: State,
+    /// State following the `:` of the key just read
+    after_colon: State,
+    capture: Capture,
+    value: Vec<u8, MAX_VALUE_SIZE>,
+    /// Cluster being filled
+    cluster: Option<ClusterId>,
+    /// Fields of the cluster being filled, by [`Field::bit`]
+    fields_seen: u8,
+    /// Clusters filled, by index in [`Layout::FLOORS`]
+    clusters_seen: u8,
+}
+
+impl<'l> LayoutParser<'l> {
+    /// Create a parser filling `layout`
+    pub fn new(layout: &'l mut Layout) -> Self {
+        Self {
+            layout,
+            state: State::Start,
+            after_colon: State::Start,
+            capture: Capture::default(),
+            value: Vec::new(),
+            cluster: None,
+            fields_seen: 0,
+            clusters_seen: 0,
+        }
+    }
+
+    /// Parse the next chunk of the body
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+        chunk.iter().try_for_each(|&byte| self.step(byte))
+    }
+
+    /// Check that the body held a complete layout
+    pub fn finish(self) -> Result<()> {
+        if self.state != State::Done || self.clusters_seen != ALL_CLUSTERS {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Incomplete layout, clusters: {=u8:#b}", self.clusters_seen);
+            return Err(Error::ParseError);
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, byte: u8) -> Result<()> {
+        if let State::Value(target) = self.state {
+            return match self.capture(byte, !matches!(target, Target::Skip(_)))? {
+                Captured::More => Ok(()),
+                Captured::Done => self.complete(target),
+                Captured::Before => {
+                    self.complete(target)?;
+                    self.step(byte)
+                }
+            };
+        }
+        if byte.is_ascii_whitespace() {
+            return Ok(());
+        }
+
+        self.state = match (self.state, byte) {
+            (State::Start, b'{') => State::LayoutKey,
+            (State::LayoutKey, b'"') => self.start_value(Target::Key(Level::Layout), byte)?,
+            (State::LayoutKey | State::LayoutNext, b'}') => State::Done,
+            (State::LayoutNext, b',') => State::LayoutKey,
+            (State::Colon, b':') => self.after_colon,
+            (State::ClusterStart, b'{') => {
+                self.start_cluster();
+                State::ClusterKey
+            }
+            (State::ClusterKey, b'"') => self.start_value(Target::Key(Level::Cluster), byte)?,
+            (State::ClusterKey | State::ClusterNext, b'}') => {
+                self.end_cluster()?;
+                State::LayoutNext
+            }
+            (State::ClusterNext, b',') => State::ClusterKey,
+            (State::ArrayStart(field), b'[') => {
+                self.fields_seen |= field.bit();
+                State::Element(field)
+            }
+            (State::Element(_) | State::ElementNext(_), b']') => State::ClusterNext,
+            (State::Element(field), _) => self.start_value(Target::Element(field), byte)?,
+            (State::ElementNext(field), b',') => State::Element(field),
+            _ => return Err(Error::ParseError),
+        };
+        Ok(())
+    }
+
+    /// Start reading a value at its first byte
+    fn start_value(&mut self, target: Target, byte: u8) -> Result<State> {
+        self.state = State::Value(target);
+        self.step(byte)?;
+        Ok(self.state)
+    }
+
+    /// Read one byte of the current value, storing it if `keep`
+    fn capture(&mut self, byte: u8, keep: bool) -> Result<Captured> {
+        let capture = &mut self.capture;
+        if !capture.started {
+            if byte.is_ascii_whitespace() {
+                return Ok(Captured::More);
+            }
+            capture.started = true;
+            match byte {
+                b'{' | b'[' => capture.depth = 1,
+                b'"' => capture.in_string = true,
+                b'}' | b']' | b',' | b':' => return Err(Error::ParseError),
+                _ => capture.scalar = true,
+            }
+        } else if capture.in_string {
+            if capture.escaped {
+                capture.escaped = false;
+            } else if byte == b'\\' {
+                capture.escaped = true;
+            } else if byte == b'"' {
+                capture.in_string = false;
+            }
+        } else if capture.scalar {
+            if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                return Ok(Captured::Before);
+            }
+        } else {
+            match byte {
+                b'"' => capture.in_string = true,
+                b'{' | b'[' => capture.depth += 1,
+                b'}' | b']' => capture.depth -= 1,
+                _ => {}
+            }
+        }
+
+        if keep {
+            self.value.push(byte).map_err(|_| Error::BufferTooSmall)?;
+        }
+        let capture = &self.capture;
+        Ok(
+            if !capture.scalar && !capture.in_string && capture.depth == 0 {
+                Captured::Done
+            } else {
+                Captured::More
+            },
+        )
+    }
+
+    /// Handle the value just read, and move past it
+    fn complete(&mut self, target: Target) -> Result<()> {
+        self.capture = Capture::default();
+        let result = self.apply(target);
+        self.value.clear();
+        self.state = result?;
+        Ok(())
+    }
+
+    fn apply(&mut self, target: Target) -> Result<State> {
+        let next = match target {
+            Target::Key(Level::Layout) => {
+                let id = core::str::from_utf8(Self::key(&self.value))
+                    .ok()
+                    .and_then(|key| key.parse::<ClusterId>().ok())
+                    .filter(|&id| self.layout.get(id).is_some());
+                self.cluster = id;
+                self.after_colon = match id {
+                    Some(_) => State::ClusterStart,
+                    None => State::Value(Target::Skip(Level::Layout)),
+                };
+                State::Colon
+            }
+            Target::Key(Level::Cluster) => {
+                self.after_colon = match Field::from_key(Self::key(&self.value)) {
+                    Some(field @ (Field::Seats | Field::Zones)) => State::ArrayStart(field),
+                    Some(field) => State::Value(Target::Field(field)),
+                    None => State::Value(Target::Skip(Level::Cluster)),
+                };
+                State::Colon
+            }
+            Target::Field(Field::Message) => {
+                let Message(message) = from_json(&self.value)?;
+                self.cluster_mut()?.message = message;
+                State::ClusterNext
+            }
+            Target::Field(Field::Name) => {
+                let Name(name) = from_json(&self.value)?;
+                self.cluster_mut()?.name = name;
+                State::ClusterNext
+            }
+            Target::Field(_) => {
+                let attributes = from_json::<AttributeVec>(&self.value)?;
+                self.cluster_mut()?.attributes = attributes;
+                State::ClusterNext
+            }
+            Target::Element(Field::Seats) => {
+                let seat = from_json::<Seat>(&self.value)?;
+                push(&mut self.cluster_mut()?.seats, seat)?;
+                State::ElementNext(Field::Seats)
+            }
+            Target::Element(field) => {
+                let zone = from_json::<Zone>(&self.value)?;
+                push(&mut self.cluster_mut()?.zones, zone)?;
+                State::ElementNext(field)
+            }
+            Target::Skip(Level::Layout) => State::LayoutNext,
+            Target::Skip(Level::Cluster) => State::ClusterNext,
+        };
+        if let Target::Field(field) = target {
+            self.fields_seen |= field.bit();
+        }
+        Ok(next)
+    }
+
+    /// Clear the cluster whose object starts
+    fn start_cluster(&mut self) {
+        self.fields_seen = 0;
+        if let Ok(cluster) = self.cluster_mut() {
+            cluster.message.clear();
+            cluster.attributes.clear();
+            cluster.name.clear();
+            cluster.seats.clear();
+            cluster.zones.clear();
+        }
+    }
+
+    /// Check that the cluster whose object ends had all its fields
+    fn end_cluster(&mut self) -> Result<()> {
+        let index = Layout::FLOORS
+            .iter()
+            .position(|&id| Some(id) == self.cluster)
+            .ok_or(Error::ParseError)?;
+        if self.fields_seen != ALL_FIELDS {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Incomplete cluster, fields: {=u8:#b}", self.fields_seen);
+            return Err(Error::ParseError);
+        }
+        self.clusters_seen |= 1 << index;
+        self.cluster = None;
+        Ok(())
+    }
+
+    fn cluster_mut(&mut self) -> Result<&mut Cluster> {
+        self.cluster
+            .and_then(|id| self.layout.get_mut(id))
+            .ok_or(Error::ParseError)
+    }
+
+    /// Key without its quotes
+    fn key(value: &[u8]) -> &[u8] {
+        value
+            .strip_prefix(b"\"")
+            .and_then(|key| key.strip_suffix(b"\""))
+            .unwrap_or(value)
+    }
+}
+
+/// Add a seat or zone to a cluster, failing when it is full
+#[cfg(not(feature = "std"))]
+fn push<T, const N: usize>(items: &mut Vec<T, N>, item: T) -> Result<()> {
+    items.push(item).map_err(|_| Error::ParseError)
+}
+
+/// Add a seat or zone to a cluster
+#[cfg(feature = "std")]
+fn push<T>(items: &mut std::vec::Vec<T>, item: T) -> Result<()> {
+    items.push(item);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cluster_core::types::{Attribute, Status};
+    use cluster_core::{empty_cluster, layout};
+
+    const LAYOUT: &[u8] = br#"{
+        "version": { "id": 3, "tags": ["a}", "b]"] },
+        "f0": {
+            "message": "Back at \"2pm\" {soon}",
+            "attributes": ["silent"],
+            "name": "F0",
+            "seats": [
+                { "id": "f0r1s1", "kind": "mac", "status": "taken", "x": 0, "y": 0 },
+                {"id":"f0r1s2","kind":"dell","status":"free","x":4,"y":0,"label":"AB"}
+            ],
+            "zones": [ { "attributes": ["exam"], "name": "Z1", "x": 2, "y": 3 } ],
+            "updated": 1712345678
+        },
+        "f1": { "message": "", "attributes": [], "name": "F1", "seats": [], "zones": [] },
+        "f1b": { "message": "", "attributes": [], "name": "F1B", "seats": [], "zones": [] },
+        "f2": { "message": "", "attributes": [], "name": "F2", "seats": [], "zones": [] },
+        "f4": { "message": "", "attributes": [], "name": "F4", "seats": [], "zones": [] },
+        "f6": { "message": "", "attributes": [], "name": "F6", "seats": [], "zones": [] }
+    }"#;
+
+    fn empty_layout() -> Layout {
+        layout! { f0: empty_cluster!("F0") }
+    }
+
+    fn parse(layout: &mut Layout, body: &[u8], chunk_size: usize) -> Result<()> {
+        let mut parser = LayoutParser::new(layout);
+        body.chunks(chunk_size)
+            .try_for_each(|chunk| parser.feed(chunk))?;
+        parser.finish()
+    }
+
+    #[test]
+    fn test_parses_in_chunks() {
+        for chunk_size in [1, 7, 64, LAYOUT.len()] {
+            let mut layout = empty_layout();
+            // Left over from a previous layout, cleared by the parser
+            layout.f1 = empty_cluster!("OLD");
+            parse(&mut layout, LAYOUT, chunk_size).unwrap();
+
+            let f0 = &layout.f0;
+            assert_eq!(f0.message.as_str(), r#"Back at \"2pm\" {soon}"#);
+            assert_eq!(f0.attributes.as_slice(), [Attribute::Silent]);
+            assert_eq!(f0.seats.len(), 2);
+            assert_eq!(
+                (f0.seats[0].id.as_str(), f0.seats[0].status),
+                ("f0r1s1", Status::Taken)
+            );
+            assert_eq!(
+                (f0.seats[1].id.as_str(), f0.seats[1].status),
+                ("f0r1s2", Status::Free)
+            );
+            assert_eq!(f0.seats[1].label.as_deref(), Some("AB"));
+            assert_eq!(f0.zones.len(), 1);
+            assert_eq!((f0.zones[0].name.as_str(), f0.zones[0].y), ("Z1", 3));
+            assert_eq!(layout.f1.name.as_str(), "F1");
+            assert_eq!(layout.f6.name.as_str(), "F6");
+        }
+    }
+
+    #[test]
+    fn test_incomplete_layout() {
+        let mut layout = empty_layout();
+        let truncated = &LAYOUT[..LAYOUT.len() / 2];
+        assert_eq!(parse(&mut layout, truncated, 16), Err(Error::ParseError));
+
+        let missing = br#"{"f0": { "message": "", "attributes": [], "name": "F0", "seats": [], "zones": [] }}"#;
+        assert_eq!(parse(&mut layout, missing, 16), Err(Error::ParseError));
+
+        let no_zones = br#"{"f0": { "message": "", "attributes": [], "name": "F0", "seats": [] }"#;
+        assert_eq!(parse(&mut layout, no_zones, 16), Err(Error::ParseError));
+    }
+
+    #[test]
+    fn test_value_errors() {
+        let mut layout = empty_layout();
+        let bad_seat = br#"{"f0": { "seats": [ { "id": "f0r1s1", "kind": "mac", "status": 3, "x": 0, "y": 0 } ] }"#;
+        match parse(&mut layout, bad_seat, 16) {
+            Err(Error::DeserializationError(error)) => {
+                assert_eq!(error.snippet(), "mac\", \"status\": 3, \"x\": 0, \"y\": ")
+            }
+            other => panic!("expected a deserialization error, got {:?}", other),
+        }
+
+        let mut parser = LayoutParser::new(&mut layout);
+        parser.feed(br#"{"f0": { "message": ""#).unwrap();
+        let long = [b'x'; MAX_VALUE_SIZE];
+        assert_eq!(parser.feed(&long), Err(Error::BufferTooSmall));
+
+        let mut parser = LayoutParser::new(&mut layout);
+        parser.feed(LAYOUT).unwrap();
+        assert_eq!(parser.feed(b"]"), Err(Error::ParseError));
+    }
+}
@@ -0,0 +1,180 @@
+//! Remote log streaming over syslog
+//!
+//! Logs are normally only visible through a debug probe. [`RemoteLog`] is a
+//! bounded queue that the logging facade mirrors records into; a network task
+//! drains it and sends each record as an RFC 5424 syslog line (usually over
+//! UDP to port [`SYSLOG_PORT`]) tagged with the device ID and severity.
+//!
+//! Logging never blocks: when the queue is full the record is dropped and
+//! counted.
+
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Channel;
+use heapless::String;
+
+/// Default syslog port (UDP)
+pub const SYSLOG_PORT: u16 = 514;
+
+/// Maximum length of a queued log message
+pub const MAX_MESSAGE_LENGTH: usize = 128;
+
+/// Maximum length of a formatted syslog line
+pub const MAX_LINE_LENGTH: usize = 256;
+
+/// Syslog facility used for panel logs (local0)
+pub const FACILITY_LOCAL0: u8 = 16;
+
+/// Severity levels from RFC 5424
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Severity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Informational = 6,
+    Debug = 7,
+}
+
+/// A queued log record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub severity: Severity,
+    pub message: String<MAX_MESSAGE_LENGTH>,
+}
+
+impl Record {
+    /// Format the record as an RFC 5424 syslog line
+    ///
+    /// `hostname` is typically the device ID. No timestamp is sent (the
+    /// device has no wall clock); the collector stamps records on receipt.
+    pub fn format(
+        &self,
+        facility: u8,
+        hostname: &str,
+        app_name: &str,
+        out: &mut String<MAX_LINE_LENGTH>,
+    ) {
+        out.clear();
+        let priority = u16::from(facility) * 8 + self.severity as u16;
+        let mut out = Truncate(out);
+        let _ = write!(
+            out,
+            "<{}>1 - {} {} - - - {}",
+            priority, hostname, app_name, self.message
+        );
+    }
+}
+
+/// Bounded queue of log records waiting to be sent
+///
+/// Records less severe than `max_severity` are discarded right away.
+pub struct RemoteLog<M: RawMutex, const N: usize> {
+    channel: Channel<M, Record, N>,
+    max_severity: Severity,
+    dropped: AtomicU32,
+}
+
+impl<M: RawMutex, const N: usize> RemoteLog<M, N> {
+    /// Create an empty queue, usable in a `static`
+    pub const fn new(max_severity: Severity) -> Self {
+        Self {
+            channel: Channel::new(),
+            max_severity,
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Queue a record without blocking
+    ///
+    /// Long messages are truncated. Returns `false` if the record was
+    /// dropped because the queue is full.
+    pub fn log(&self, severity: Severity, args: fmt::Arguments<'_>) -> bool {
+        if severity > self.max_severity {
+            return true;
+        }
+
+        let mut message = String::new();
+        let _ = Truncate(&mut message).write_fmt(args);
+
+        if self.channel.try_send(Record { severity, message }).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    /// Wait for the next record to send
+    pub async fn receive(&self) -> Record {
+        self.channel.receive().await
+    }
+
+    /// Get the next record if one is queued
+    pub fn try_receive(&self) -> Option<Record> {
+        self.channel.try_receive().ok()
+    }
+
+    /// Take the number of records dropped since the last call
+    pub fn take_dropped(&self) -> u32 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// Writer that silently truncates once the string is full
+struct Truncate<'s, const N: usize>(&'s mut String<N>);
+
+impl<const N: usize> Write for Truncate<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    #[test]
+    fn test_format_rfc5424() {
+        let log: RemoteLog<NoopRawMutex, 4> = RemoteLog::new(Severity::Debug);
+        assert!(log.log(Severity::Warning, format_args!("poll failed: {}", 503)));
+
+        let mut line = String::new();
+        log.try_receive()
+            .unwrap()
+            .format(FACILITY_LOCAL0, "000000000000abcd", "panel", &mut line);
+        // local0 (16) * 8 + warning (4) = 132
+        assert_eq!(
+            line,
+            "<132>1 - 000000000000abcd panel - - - poll failed: 503"
+        );
+    }
+
+    #[test]
+    fn test_filter_truncate_and_drop() {
+        let log: RemoteLog<NoopRawMutex, 1> = RemoteLog::new(Severity::Informational);
+
+        // Filtered out, not queued
+        assert!(log.log(Severity::Debug, format_args!("noise")));
+        assert!(log.try_receive().is_none());
+
+        let long = [b'x'; 200];
+        let long = core::str::from_utf8(&long).unwrap();
+        assert!(log.log(Severity::Error, format_args!("{}", long)));
+        assert!(!log.log(Severity::Error, format_args!("overflow")));
+        assert_eq!(log.take_dropped(), 1);
+        assert_eq!(log.take_dropped(), 0);
+
+        let record = log.try_receive().unwrap();
+        assert_eq!(record.message.len(), MAX_MESSAGE_LENGTH);
+    }
+}
@@ -0,0 +1,136 @@
+//! Validators for conditional polling
+//!
+//! The `ETag` and `Last-Modified` headers of a response identify the version
+//! of the resource it carried. [`Client::get_cached`](crate::Client::get_cached)
+//! remembers them per path and sends them back as `If-None-Match` and
+//! `If-Modified-Since`, so a resource that didn't change is answered with an
+//! empty `304 Not Modified` instead of being downloaded and parsed again.
+
+use crate::client::ETag;
+use crate::middleware::MAX_HEADER_VALUE_LENGTH;
+use heapless::{String, Vec};
+
+/// Maximum number of paths whose validators are remembered
+pub const MAX_CACHED_PATHS: usize = 4;
+
+/// Maximum length of a path whose validators are remembered
+pub const MAX_CACHED_PATH_LENGTH: usize = 64;
+
+/// Date of the last change of a resource, as sent by the server
+pub type HttpDate = String<MAX_HEADER_VALUE_LENGTH>;
+
+/// Version of a resource, as identified by the server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// `ETag` header of the response
+    pub etag: Option<ETag>,
+    /// `Last-Modified` header of the response
+    pub last_modified: Option<HttpDate>,
+}
+
+impl Validators {
+    /// Check whether the server sent neither header
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Validators of the last response to each polled path
+///
+/// Holds up to [`MAX_CACHED_PATHS`] paths; when full, the path stored first
+/// is forgotten.
+#[derive(Debug, Default)]
+pub struct ValidatorCache {
+    entries: Vec<(String<MAX_CACHED_PATH_LENGTH>, Validators), MAX_CACHED_PATHS>,
+}
+
+impl ValidatorCache {
+    /// Create an empty cache
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Validators of the last response to `path`
+    pub fn get(&self, path: &str) -> Option<&Validators> {
+        self.entries
+            .iter()
+            .find(|(stored, _)| stored.as_str() == path)
+            .map(|(_, validators)| validators)
+    }
+
+    /// Remember the validators of a response to `path`
+    ///
+    /// Empty validators forget the path, as there is nothing to send back.
+    /// Paths longer than [`MAX_CACHED_PATH_LENGTH`] are not remembered.
+    pub fn store(&mut self, path: &str, validators: Validators) {
+        self.forget(path);
+        if validators.is_empty() {
+            return;
+        }
+        let Ok(path) = String::try_from(path) else {
+            return;
+        };
+        if self.entries.is_full() {
+            self.entries.remove(0);
+        }
+        // Cannot fail: a slot was freed above if needed
+        let _ = self.entries.push((path, validators));
+    }
+
+    /// Forget the validators of `path`, so it is downloaded again
+    pub fn forget(&mut self, path: &str) {
+        self.entries.retain(|(stored, _)| stored.as_str() != path);
+    }
+
+    /// Forget every path
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn etag(value: &str) -> Validators {
+        Validators {
+            etag: Some(String::try_from(value).unwrap()),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_store_and_replace() {
+        let mut cache = ValidatorCache::new();
+        assert_eq!(cache.get("/cluster/f0"), None);
+
+        cache.store("/cluster/f0", etag("\"v1\""));
+        cache.store("/cluster/f0", etag("\"v2\""));
+        assert_eq!(cache.get("/cluster/f0"), Some(&etag("\"v2\"")));
+
+        // Nothing to send back: the path is forgotten
+        cache.store("/cluster/f0", Validators::default());
+        assert_eq!(cache.get("/cluster/f0"), None);
+    }
+
+    #[test]
+    fn test_oldest_path_evicted() {
+        let mut cache = ValidatorCache::new();
+        let paths = ["/cluster/f0", "/cluster/f1", "/cluster/f2", "/cluster/f4"];
+        for path in paths {
+            cache.store(path, etag(path));
+        }
+        cache.store("/cluster/f6", etag("f6"));
+
+        assert_eq!(cache.get("/cluster/f0"), None);
+        assert!(paths[1..].iter().all(|path| cache.get(path).is_some()));
+        assert_eq!(cache.get("/cluster/f6"), Some(&etag("f6")));
+
+        cache.forget("/cluster/f1");
+        assert_eq!(cache.get("/cluster/f1"), None);
+        cache.clear();
+        assert_eq!(cache.get("/cluster/f6"), None);
+    }
+}
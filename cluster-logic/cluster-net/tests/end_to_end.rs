@@ -0,0 +1,401 @@
+//! End-to-end test of the data-to-pixels pipeline
+//!
+//! A local HTTP server serves a known layout, the client fetches it over std
+//! sockets and `cluster_core::visualization` renders it to an in-memory
+//! surface, where each seat must show the color of its status.
+
+use cluster_core::visualization::display::visual;
+use cluster_core::visualization::{BackgroundSurface, ClusterRenderer, DEFAULT_LAYOUT};
+use cluster_net::client::{Client, ClientConfig, Conditional};
+use cluster_net::endpoints::Endpoints;
+use cluster_net::middleware::{MAX_EXTRA_HEADERS, Middleware, RequestContext};
+use cluster_net::rate_limit::{RateLimit, RateLimiter};
+use cluster_net::shared::SharedClient;
+use cluster_net::{Auth, DeviceId, Error};
+use embassy_futures::block_on;
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use embedded_nal_async::{AddrType, Dns, TcpConnect};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const LAYOUT: &str = r#"{
+    "f0": {
+        "message": "",
+        "attributes": [],
+        "name": "F0",
+        "seats": [
+            { "id": "f0r1s1", "kind": "mac", "status": "taken", "x": 0, "y": 0 },
+            { "id": "f0r1s2", "kind": "dell", "status": "free", "x": 4, "y": 0 },
+            { "id": "f0r2s1", "kind": "lenovo", "status": "broken", "x": 0, "y": 6 },
+            { "id": "f0r2s2", "kind": "flex", "status": "free", "x": 8, "y": 6 }
+        ],
+        "zones": []
+    },
+    "f1": { "message": "", "attributes": [], "name": "F1", "seats": [], "zones": [] },
+    "f1b": { "message": "", "attributes": [], "name": "F1B", "seats": [], "zones": [] },
+    "f2": { "message": "", "attributes": [], "name": "F2", "seats": [], "zones": [] },
+    "f4": { "message": "", "attributes": [], "name": "F4", "seats": [], "zones": [] },
+    "f6": { "message": "", "attributes": [], "name": "F6", "seats": [], "zones": [] }
+}"#;
+
+/// Serve one response per connection, in order, returning the request
+/// heads received
+fn serve(responses: Vec<String>) -> (SocketAddr, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    (addr, serve_on(listener, responses))
+}
+
+/// Serve one response per connection on `listener`, see [`serve`]
+fn serve_on(listener: TcpListener, responses: Vec<String>) -> JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut heads = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut buf = [0u8; 512];
+            while !head.ends_with(b"\r\n\r\n") {
+                let len = io::Read::read(&mut stream, &mut buf).unwrap();
+                if len == 0 {
+                    break;
+                }
+                head.extend_from_slice(&buf[..len]);
+            }
+
+            io::Write::write_all(&mut stream, response.as_bytes()).unwrap();
+            heads.push(String::from_utf8(head).unwrap());
+        }
+        heads
+    })
+}
+
+/// `200 OK` response with a JSON `body`
+fn ok(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Redirect response to `location`
+fn redirect(status: u16, location: &str) -> String {
+    format!(
+        "HTTP/1.1 {status} Moved\r\nLocation: {location}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    )
+}
+
+/// Client of the server at `addr`
+fn client(addr: SocketAddr) -> Client<'static, StdNetwork, StdNetwork> {
+    let config = ClientConfig::new(&format!("http://{addr}"))
+        .unwrap()
+        .with_timeout(5000);
+    Client::new(config, &StdNetwork, &StdNetwork)
+}
+
+/// Blocking std sockets behind the async traits the client takes
+struct StdNetwork;
+
+struct StdConnection(TcpStream);
+
+impl ErrorType for StdConnection {
+    type Error = ErrorKind;
+}
+
+impl Read for StdConnection {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        io::Read::read(&mut self.0, buf).map_err(|_| ErrorKind::Other)
+    }
+}
+
+impl Write for StdConnection {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        io::Write::write(&mut self.0, buf).map_err(|_| ErrorKind::Other)
+    }
+
+    async fn flush(&mut self) -> Result<(), ErrorKind> {
+        io::Write::flush(&mut self.0).map_err(|_| ErrorKind::Other)
+    }
+}
+
+impl TcpConnect for StdNetwork {
+    type Error = ErrorKind;
+    type Connection<'m> = StdConnection;
+
+    async fn connect(&self, remote: SocketAddr) -> Result<StdConnection, ErrorKind> {
+        TcpStream::connect(remote)
+            .map(StdConnection)
+            .map_err(|_| ErrorKind::ConnectionRefused)
+    }
+}
+
+impl Dns for StdNetwork {
+    type Error = ErrorKind;
+
+    async fn get_host_by_name(
+        &self,
+        host: &str,
+        _addr_type: AddrType,
+    ) -> Result<IpAddr, ErrorKind> {
+        host.parse().map_err(|_| ErrorKind::Unsupported)
+    }
+
+    async fn get_host_by_address(
+        &self,
+        _addr: IpAddr,
+        _result: &mut [u8],
+    ) -> Result<usize, ErrorKind> {
+        Err(ErrorKind::Unsupported)
+    }
+}
+
+/// Stack of the thread running the layout test: a `Layout` and its
+/// deserializer overflow the default test thread stack in debug builds
+const LAYOUT_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+#[test]
+fn test_fetched_layout_renders_seat_colors() {
+    thread::Builder::new()
+        .stack_size(LAYOUT_STACK_SIZE)
+        .spawn(fetched_layout_renders_seat_colors)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn fetched_layout_renders_seat_colors() {
+    let (addr, server) = serve(vec![ok(LAYOUT)]);
+    let mut client = client(addr);
+
+    let mut buffer = vec![0u8; 16384];
+    let layout = block_on(Endpoints::get_layout(&mut client, &mut buffer)).unwrap();
+    assert!(server.join().unwrap()[0].starts_with("GET /layout HTTP/1.1\r\n"));
+    assert_eq!(layout.f0.seats.len(), 4);
+
+    let mut surface = Box::new(BackgroundSurface::new());
+    let Ok(()) = ClusterRenderer::new().render_frame(&mut *surface, &layout, 0);
+
+    // Seats are drawn 1:1 from the top left of the cluster area, 2x2 pixels each
+    let origin = DEFAULT_LAYOUT.cluster_area.top_left;
+    for (x, y, color) in [
+        (0, 0, Rgb565::BLUE),
+        (4, 0, Rgb565::GREEN),
+        (0, 6, Rgb565::RED),
+        (8, 6, Rgb565::CSS_PURPLE),
+    ] {
+        for offset in [Point::zero(), Point::new(1, 1)] {
+            let point = origin + Point::new(x, y) + offset;
+            assert_eq!(surface.pixel(point), Some(color), "seat at ({x}, {y})");
+        }
+    }
+    // Nothing between the seats
+    assert_eq!(
+        surface.pixel(origin + Point::new(2, 0)),
+        Some(visual::BACKGROUND)
+    );
+}
+
+#[test]
+fn test_follows_redirects() {
+    let (addr, server) = serve(vec![
+        redirect(302, "/v2/cluster/f0"),
+        redirect(307, "f1"),
+        ok(r#"{"moved":true}"#),
+    ]);
+    let mut client = client(addr);
+
+    let mut buffer = vec![0u8; 1024];
+    let body = block_on(client.get("/cluster/f0", &mut buffer)).unwrap();
+    assert_eq!(body, br#"{"moved":true}"#);
+    let heads = server.join().unwrap();
+    assert!(heads[0].starts_with("GET /cluster/f0 HTTP/1.1\r\n"));
+    assert!(heads[1].starts_with("GET /v2/cluster/f0 HTTP/1.1\r\n"));
+    assert!(heads[2].starts_with("GET /v2/cluster/f1 HTTP/1.1\r\n"));
+}
+
+#[test]
+fn test_redirect_errors() {
+    let mut buffer = vec![0u8; 1024];
+
+    let (addr, server) = serve(vec![redirect(301, "/cluster/f0")]);
+    let result = block_on(client(addr).get("/cluster/f0", &mut buffer));
+    assert_eq!(result, Err(Error::RedirectLoop));
+    server.join().unwrap();
+
+    let (addr, server) = serve(vec![redirect(301, "https://api.example.com/cluster/f0")]);
+    let result = block_on(client(addr).get("/cluster/f0", &mut buffer));
+    assert_eq!(result, Err(Error::TlsUnavailable));
+    server.join().unwrap();
+
+    let (addr, server) = serve(
+        ["/a", "/b", "/c", "/d"]
+            .map(|to| redirect(302, to))
+            .to_vec(),
+    );
+    let result = block_on(client(addr).get("/cluster/f0", &mut buffer));
+    assert_eq!(result, Err(Error::TooManyRedirects));
+    assert_eq!(server.join().unwrap().len(), 4);
+}
+
+#[test]
+fn test_credentials_sent_and_rejected() {
+    let unauthorized =
+        "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    let (addr, server) = serve(vec![unauthorized.to_string()]);
+    let config = ClientConfig::new(&format!("http://{addr}"))
+        .unwrap()
+        .with_timeout(5000)
+        .with_auth(Auth::bearer("t0k3n").unwrap());
+    let mut client: Client<'_, _, _> = Client::new(config, &StdNetwork, &StdNetwork);
+
+    let mut buffer = vec![0u8; 1024];
+    let result = block_on(client.get("/cluster/f0", &mut buffer));
+    assert_eq!(result, Err(Error::Unauthorized(401)));
+    let heads = server.join().unwrap();
+    assert!(heads[0].contains("\r\nAuthorization: Bearer t0k3n\r\n"));
+}
+
+#[test]
+fn test_headers_only_sent_to_origin_host() {
+    struct DeviceId;
+
+    impl Middleware for DeviceId {
+        fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> cluster_net::Result<()> {
+            ctx.add_header("X-Device-Id", "matrix-01")
+        }
+    }
+
+    // The origin sends the request to another host, which sends it back
+    let origin = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let other = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let (origin_addr, other_addr) = (origin.local_addr().unwrap(), other.local_addr().unwrap());
+    let origin_server = serve_on(
+        origin,
+        vec![
+            redirect(302, &format!("http://{other_addr}/away")),
+            ok("{}"),
+        ],
+    );
+    let other_server = serve_on(
+        other,
+        vec![redirect(302, &format!("http://{origin_addr}/back"))],
+    );
+
+    let config = ClientConfig::new(&format!("http://{origin_addr}"))
+        .unwrap()
+        .with_timeout(5000)
+        .with_auth(Auth::bearer("t0k3n").unwrap());
+    let mut device_id = DeviceId;
+    let mut client: Client<'_, _, _> =
+        Client::new(config, &StdNetwork, &StdNetwork).with_middleware(&mut device_id);
+
+    let mut buffer = vec![0u8; 1024];
+    let body = block_on(client.get("/cluster/f0", &mut buffer)).unwrap();
+    assert_eq!(body, b"{}");
+
+    let origin_heads = origin_server.join().unwrap();
+    let other_heads = other_server.join().unwrap();
+    for head in &origin_heads {
+        assert!(head.contains("\r\nAuthorization: Bearer t0k3n\r\n"));
+        assert!(head.contains("\r\nX-Device-Id: matrix-01\r\n"));
+    }
+    assert!(origin_heads[1].starts_with("GET /back HTTP/1.1\r\n"));
+    assert!(!other_heads[0].contains("Authorization"));
+    assert!(!other_heads[0].contains("X-Device-Id"));
+}
+
+#[test]
+fn test_shared_client_merges_identical_gets() {
+    let (addr, server) = serve(vec![ok(r#"{"n":1}"#), ok(r#"{"n":2}"#)]);
+    let shared: SharedClient<'_, NoopRawMutex, _, _> = SharedClient::new(client(addr));
+
+    let (mut first, mut second) = (vec![0u8; 1024], vec![0u8; 1024]);
+    let (first, second) = block_on(join(
+        shared.get("/layout", &mut first),
+        shared.get("/layout", &mut second),
+    ));
+    assert_eq!(first.unwrap(), br#"{"n":1}"#);
+    assert_eq!(second.unwrap(), br#"{"n":1}"#);
+
+    // Asked for once the first one was done: sent again
+    let mut buffer = vec![0u8; 1024];
+    let third = block_on(shared.get("/layout", &mut buffer)).unwrap();
+    assert_eq!(third, br#"{"n":2}"#);
+    assert_eq!(server.join().unwrap().len(), 2);
+}
+
+#[test]
+fn test_shared_client_waits_for_rate_limit() {
+    let interval = Duration::from_millis(200);
+    let failure =
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    let (addr, server) = serve(vec![failure.to_string(), ok("{}"), ok("{}")]);
+    let limiter = RateLimiter::new([RateLimit::new("/cluster/", interval.as_millis() as u32)]);
+    let shared: SharedClient<'_, NoopRawMutex, _, _, 8192, 1> =
+        SharedClient::with_rate_limiter(client(addr), limiter);
+
+    let mut buffer = vec![0u8; 1024];
+    let start = Instant::now();
+    let result = block_on(shared.get("/cluster/f0", &mut buffer));
+    assert_eq!(result, Err(Error::InvalidStatus(500)));
+
+    // The failed request didn't use up the interval
+    block_on(shared.get("/cluster/f0", &mut buffer)).unwrap();
+    assert!(start.elapsed() < interval);
+
+    // The successful one did: the next request waits for its turn
+    block_on(shared.get("/cluster/f1", &mut buffer)).unwrap();
+    assert!(start.elapsed() >= interval);
+    assert_eq!(server.join().unwrap().len(), 3);
+}
+
+#[test]
+fn test_all_headers_fit_together() {
+    /// Fills the rest of the middleware header budget
+    struct Tracing;
+
+    impl Middleware for Tracing {
+        fn before_request(&mut self, ctx: &mut RequestContext<'_>) -> cluster_net::Result<()> {
+            for index in 1..MAX_EXTRA_HEADERS {
+                ctx.add_header(&format!("X-Trace-{index}"), "on")?;
+            }
+            Ok(())
+        }
+    }
+
+    let validated = "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2026 07:28:00 GMT\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+    let not_modified =
+        "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    let (addr, server) = serve(vec![validated.to_string(), not_modified.to_string()]);
+
+    let config = ClientConfig::new(&format!("http://{addr}"))
+        .unwrap()
+        .with_timeout(5000)
+        .with_auth(Auth::bearer("t0k3n").unwrap());
+    let mut middleware = (DeviceId::from_unique_id(0x0123_4567_89ab_cdef), Tracing);
+    let mut client: Client<'_, _, _> =
+        Client::new(config, &StdNetwork, &StdNetwork).with_middleware(&mut middleware);
+
+    let mut buffer = vec![0u8; 1024];
+    block_on(client.get_cached("/layout", &mut buffer)).unwrap();
+    let result = block_on(client.get_cached("/layout", &mut buffer));
+    assert_eq!(result, Ok(Conditional::NotModified));
+
+    let heads = server.join().unwrap();
+    for header in [
+        "Accept: application/json",
+        "Authorization: Bearer t0k3n",
+        "If-None-Match: \"v1\"",
+        "If-Modified-Since: Wed, 21 Oct 2026 07:28:00 GMT",
+        "X-Device-Id: 0123456789abcdef",
+        "X-Trace-5: on",
+    ] {
+        assert!(heads[1].contains(&format!("\r\n{header}\r\n")), "{header}");
+    }
+}
@@ -1,20 +1,37 @@
 #![no_std]
+//! Bit-banged Hub75 driver over `embedded-hal` output pins
+//!
+//! The panel size is given by the `W` and `H` parameters of [`Hub75`], e.g.
+//! `Hub75<64, 32, ...>`. Panels are dual-scan: rows `y` and `y + H / 2` are
+//! shifted out together, so `H / 2` rows are addressed, up to 32 with the
+//! five address lines.
+//!
+//! Panels can be chained: the `CHAIN` parameter is the number of panels
+//! daisy-chained on the output, each panel feeding its shift registers into
+//! the next. The chain is one display `W * CHAIN` pixels wide, the first `W`
+//! columns on the panel at the far end of the chain: with the input
+//! connectors on the right seen from the front, as usual, `x` grows from
+//! left to right across the whole chain.
+//!
+//! Outdoor panels (P10, P5...) often use fewer addresses than rows per
+//! half, see [`ScanMode`].
 
 use core::convert::Infallible;
+#[cfg(feature = "embassy")]
+use embassy_time::{Duration, Ticker, Timer};
 use embedded_graphics_core::{
+    Pixel,
     draw_target::DrawTarget,
-    geometry::{OriginDimensions, Size},
+    geometry::{OriginDimensions, Point, Size},
     pixelcolor::{Rgb565, RgbColor},
-    Pixel,
 };
 use embedded_hal::{delay::DelayNs, digital::OutputPin};
+pub use matrix_driver::MatrixDriver;
 
-/// Constants for the display dimensions
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 64;
-const ACTIVE_ROWS: usize = DISPLAY_HEIGHT / 2; // Number of rows to address
+/// Most rows the A to E address lines can select
+const MAX_ACTIVE_ROWS: usize = 32;
 
-/// Buffer format for dual scanning 64x64 matrix
+/// Color values shifted out for one column of a dual-scan panel
 /// Each entry represents the color values for both top and bottom pixels
 #[derive(Clone, Copy, Default)]
 pub struct DualPixel {
@@ -26,82 +43,257 @@ pub struct DualPixel {
     pub b2: u8, // Blue for bottom half
 }
 
-/// Complete framebuffer for a 64x64 display
-pub struct FrameBuffer {
-    buffer: [[DualPixel; DISPLAY_WIDTH]; ACTIVE_ROWS],
-    modified: bool,
+/// Complete framebuffer for a chain of `CHAIN` panels of `W`x`H` pixels
+pub struct FrameBuffer<const W: usize, const H: usize, const CHAIN: usize = 1> {
+    /// RGB of every pixel, rows of each panel in shift order
+    ///
+    /// The halves are paired into [`DualPixel`]s when scanning out, as
+    /// `H / 2` can't size an array of a generic panel.
+    buffer: [[[[u8; 3]; W]; CHAIN]; H],
+    /// Bit `y` set when row `y` changed since the last scan-out
+    dirty_rows: u64,
 }
 
-impl Default for FrameBuffer {
+impl<const W: usize, const H: usize, const CHAIN: usize> Default for FrameBuffer<W, H, CHAIN> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl FrameBuffer {
+impl<const W: usize, const H: usize, const CHAIN: usize> FrameBuffer<W, H, CHAIN> {
+    /// Width of the whole chain
+    pub const WIDTH: usize = W * CHAIN;
+    /// Rows addressed, each showing a row of both halves
+    pub const ACTIVE_ROWS: usize = H / 2;
+
+    const VALID_SIZE: () = assert!(
+        W > 0 && H > 0 && H.is_multiple_of(2) && H / 2 <= MAX_ACTIVE_ROWS,
+        "Hub75 panels must have an even height of at most 64 rows"
+    );
+
     /// Create a new, empty framebuffer
     #[must_use]
     pub fn new() -> Self {
+        let () = Self::VALID_SIZE;
         Self {
-            buffer: [[DualPixel::default(); DISPLAY_WIDTH]; ACTIVE_ROWS],
-            modified: true,
+            buffer: [[[[0; 3]; W]; CHAIN]; H],
+            dirty_rows: Self::ALL_ROWS,
         }
     }
 
+    /// Mask of the dirty bits of all rows
+    const ALL_ROWS: u64 = u64::MAX >> (64 - H);
+
     /// Set a single pixel's color
     pub fn set_pixel(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8) {
-        if x >= DISPLAY_WIDTH || y >= DISPLAY_HEIGHT {
+        if x >= Self::WIDTH || y >= H {
             return;
         }
 
-        // Determine if this is in the top or bottom half
-        let row_address = y % ACTIVE_ROWS;
-
-        // Update the appropriate pixel
-        if y < ACTIVE_ROWS {
-            // Top half
-            self.buffer[row_address][x].r1 = r;
-            self.buffer[row_address][x].g1 = g;
-            self.buffer[row_address][x].b1 = b;
-        } else {
-            // Bottom half
-            self.buffer[row_address][x].r2 = r;
-            self.buffer[row_address][x].g2 = g;
-            self.buffer[row_address][x].b2 = b;
+        let pixel = &mut self.buffer[y][x / W][x % W];
+        if *pixel != [r, g, b] {
+            *pixel = [r, g, b];
+            self.dirty_rows |= 1 << y;
         }
+    }
 
-        self.modified = true;
+    /// Pixels of address `row` at column `x` of the chain, in both halves
+    fn dual_pixel(&self, row: usize, x: usize) -> DualPixel {
+        let [r1, g1, b1] = self.buffer[row][x / W][x % W];
+        let [r2, g2, b2] = self.buffer[row + Self::ACTIVE_ROWS][x / W][x % W];
+        DualPixel {
+            r1,
+            g1,
+            b1,
+            r2,
+            g2,
+            b2,
+        }
     }
 
-    /// Clear the framebuffer
+    /// Clear the framebuffer, marking the rows that had lit pixels as changed
     pub fn clear(&mut self) {
+        self.dirty_rows |= self.lit_rows();
         for row in &mut self.buffer {
-            for pixel in row.iter_mut() {
-                *pixel = DualPixel::default();
+            for pixel in row.iter_mut().flatten() {
+                *pixel = [0; 3];
             }
         }
-        self.modified = true;
+    }
+
+    /// Rows with at least one pixel lit, bit `y` for row `y`
+    fn lit_rows(&self) -> u64 {
+        let mut lit = 0;
+        for (y, row) in self.buffer.iter().enumerate() {
+            if row.iter().flatten().any(|pixel| *pixel != [0; 3]) {
+                lit |= 1 << y;
+            }
+        }
+        lit
     }
 
     /// Check if the framebuffer has been modified
     #[must_use]
     pub fn is_modified(&self) -> bool {
-        self.modified
+        self.dirty_rows != 0
+    }
+
+    /// Rows changed since the last reset, bit `y` for row `y`
+    #[must_use]
+    pub fn dirty_rows(&self) -> u64 {
+        self.dirty_rows
+    }
+
+    /// Check if any row lit by `address` changed, in either half
+    #[must_use]
+    pub fn is_address_dirty(&self, scan_mode: ScanMode, address: usize) -> bool {
+        let addresses = scan_mode.addresses(Self::ACTIVE_ROWS);
+        let dirty_halves = self.dirty_rows | (self.dirty_rows >> Self::ACTIVE_ROWS);
+        (address..Self::ACTIVE_ROWS)
+            .step_by(addresses)
+            .any(|row| dirty_halves & (1 << row) != 0)
+    }
+
+    /// Mark every row as changed
+    pub fn mark_modified(&mut self) {
+        self.dirty_rows = Self::ALL_ROWS;
     }
 
-    /// Reset the modified flag
+    /// Reset the modified flag of every row
     pub fn reset_modified(&mut self) {
-        self.modified = false;
+        self.dirty_rows = 0;
     }
 }
 
+/// Wiring of the color channels to the R, G and B inputs of the panel
+///
+/// Each variant names the inputs driven by the red, green and blue channels
+/// of a color, in that order: with [`ColorOrder::Gbr`], red is shifted out
+/// on G, green on B and blue on R.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// Values of the R, G and B inputs for a color
+    #[must_use]
+    pub const fn map(self, [r, g, b]: [u8; 3]) -> [u8; 3] {
+        match self {
+            Self::Rgb => [r, g, b],
+            Self::Rbg => [r, b, g],
+            Self::Grb => [g, r, b],
+            Self::Gbr => [b, r, g],
+            Self::Brg => [g, b, r],
+            Self::Bgr => [b, g, r],
+        }
+    }
+}
+
+/// Row multiplexing of the panel
+///
+/// A panel lights the rows of one address at a time, in each half. With
+/// [`ScanMode::Full`] an address is one row per half (1/32 scan on a 64-row
+/// panel), and a row of the chain is shifted out per address. 1/16 and 1/8
+/// scan panels have fewer addresses, each driving several rows per half:
+/// address `a` lights rows `a`, `a + N`, `a + 2N`... of the half for 1/N
+/// scan, and their pixels are shifted out together, zigzagging between the
+/// rows every 8 columns, lowest row first.
+///
+/// The chain width must be a multiple of [`ScanMode::ZIGZAG_BLOCK`] with
+/// 1/16 and 1/8 scan. A mode with more addresses than rows per half, or
+/// that doesn't divide them, falls back to [`ScanMode::Full`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanMode {
+    /// One address per row of each half
+    #[default]
+    Full,
+    /// 1/16 scan
+    Scan16,
+    /// 1/8 scan
+    Scan8,
+}
+
+impl ScanMode {
+    /// Columns shifted out for one row before switching to the next one
+    /// driven by the same address
+    pub const ZIGZAG_BLOCK: usize = 8;
+
+    /// Number of addresses of a panel with `half_height` rows per half
+    #[must_use]
+    pub const fn addresses(self, half_height: usize) -> usize {
+        let addresses = match self {
+            Self::Full => return half_height,
+            Self::Scan16 => 16,
+            Self::Scan8 => 8,
+        };
+        if addresses <= half_height && half_height.is_multiple_of(addresses) {
+            addresses
+        } else {
+            half_height
+        }
+    }
+
+    /// Row of the half and column of the chain of the `shift`th pixel
+    /// shifted out for `address`
+    #[must_use]
+    pub const fn locate(self, half_height: usize, address: usize, shift: usize) -> (usize, usize) {
+        let addresses = self.addresses(half_height);
+        let rows = half_height / addresses;
+        let block = shift / Self::ZIGZAG_BLOCK;
+        let row = address + (block % rows) * addresses;
+        let col = (block / rows) * Self::ZIGZAG_BLOCK + shift % Self::ZIGZAG_BLOCK;
+        (row, col)
+    }
+}
+
+/// What `Hub75::update` does with the rows that didn't change
+///
+/// The addresses whose rows all stayed the same since the last update can be
+/// skipped, or shown with fewer bit planes, to shorten an update after a
+/// small change on slow MCUs. The skipped rows stay dark during that update,
+/// like the whole panel when nothing changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CleanRows {
+    /// Scan them out like the changed ones
+    #[default]
+    Refresh,
+    /// Don't scan them out
+    Skip,
+    /// Scan out only this many of the most significant bit planes
+    ReducedDepth(u8),
+}
+
 /// Configuration options for the Hub75 driver
 #[derive(Clone, Copy)]
 pub struct Hub75Config {
-    pub pwm_bits: u8,               // Number of bits for PWM (1-8)
-    pub brightness: u8,             // Overall brightness (0-255)
+    pub pwm_bits: u8,   // Number of bits for PWM (1-8), checked by `validate`
+    pub brightness: u8, // Overall brightness (0-255)
     pub use_gamma_correction: bool, // Apply gamma correction to colors
-    pub row_step_time_us: u32,      // Delay between row updates
+    pub row_step_time_us: u32, // Delay between row updates
+    /// Draw into a back buffer and show it on `commit()`
+    ///
+    /// Avoids tearing when drawing between `update()` calls, at the cost of
+    /// a second framebuffer.
+    pub double_buffered: bool,
+    /// Wiring of the panel's color inputs
+    pub color_order: ColorOrder,
+    /// Row multiplexing of the panel
+    pub scan_mode: ScanMode,
+    /// Handling of unchanged rows by `update()`
+    pub clean_rows: CleanRows,
+    /// Alternate the least significant shown bit across frames, for about
+    /// two more bits of depth than `pwm_bits`
+    ///
+    /// Each `update()` then scans out the whole panel, changed or not, and
+    /// should run at 100 Hz or more to keep the alternation invisible.
+    pub dithering: bool,
 }
 
 impl Default for Hub75Config {
@@ -111,10 +303,32 @@ impl Default for Hub75Config {
             brightness: 220,            // High brightness
             use_gamma_correction: true, // Enable gamma correction for better visuals
             row_step_time_us: 1,        // 1µs delay between row transitions
+            double_buffered: false,
+            color_order: ColorOrder::Rgb,
+            scan_mode: ScanMode::Full,
+            clean_rows: CleanRows::Refresh,
+            dithering: false,
+        }
+    }
+}
+
+impl Hub75Config {
+    /// Check the options the driver can't work with
+    pub const fn validate(&self) -> Result<(), ConfigError> {
+        if self.pwm_bits == 0 || self.pwm_bits > 8 {
+            return Err(ConfigError::PwmBits(self.pwm_bits));
         }
+        Ok(())
     }
 }
 
+/// Invalid [`Hub75Config`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `pwm_bits` outside 1..=8, the bit planes of an 8-bit channel
+    PwmBits(u8),
+}
+
 /// Gamma correction lookup table for better color representation
 static GAMMA8: [u8; 256] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
@@ -130,6 +344,34 @@ static GAMMA8: [u8; 256] = [
     223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
 ];
 
+/// Round `value` up on some frames, so the two bits below the `pwm_bits`
+/// shown come through on average over four frames
+///
+/// `pwm_bits` is in 1..=8, see [`Hub75Config::validate`].
+const fn dither(value: u8, pwm_bits: u8, threshold: u8) -> u8 {
+    let dropped = 8u8.saturating_sub(pwm_bits);
+    let fraction = match dropped {
+        0 => return value,
+        1 => (value & 1) << 1,
+        _ => (value >> (dropped - 2)) & 0b11,
+    };
+    if fraction > threshold {
+        // Saturates only when the shown bits are all set already
+        value.saturating_add(1 << dropped)
+    } else {
+        value
+    }
+}
+
+/// Threshold of a pixel for [`dither`] in one of four frames
+///
+/// Neighbouring pixels are out of phase, so the panel as a whole doesn't
+/// flicker.
+const fn dither_threshold(x: usize, y: usize, frame: u8) -> u8 {
+    const PATTERN: [u8; 4] = [0, 2, 3, 1];
+    (PATTERN[(x & 1) | (y & 1) << 1] + frame) & 0b11
+}
+
 /// Generic Hub75 pins structure using static dispatch with shared error type
 pub struct Hub75Pins<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
 where
@@ -192,6 +434,7 @@ where
     OE: OutputPin<Error = E>,
 {
     /// Create new pins structure
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         r1: R1,
         g1: G1,
@@ -228,7 +471,8 @@ where
 
     /// Set the row address pins based on the row number
     pub fn set_row(&mut self, row: usize) -> Result<(), E> {
-        // For 64x64 dual-scan panels:
+        // Dual-scan panels: 32 rows (A to E) for 64 pixels high, fewer
+        // lines are left low on smaller panels
 
         if row & 0x01 != 0 {
             self.a.set_high()?;
@@ -323,8 +567,29 @@ where
 }
 
 /// Main Hub75 driver structure with static dispatch
-pub struct Hub75<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
-where
+///
+/// `W`x`H` is the size of one panel, `CHAIN` the number of panels chained
+/// on the output.
+pub struct Hub75<
+    const W: usize,
+    const H: usize,
+    E,
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E0,
+    CLK,
+    LAT,
+    OE,
+    const CHAIN: usize = 1,
+> where
     E: core::fmt::Debug,
     R1: OutputPin<Error = E>,
     G1: OutputPin<Error = E>,
@@ -341,13 +606,38 @@ where
     LAT: OutputPin<Error = E>,
     OE: OutputPin<Error = E>,
 {
+    #[allow(clippy::type_complexity)]
     pins: Hub75Pins<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>,
+    /// Change it with `set_config`, which checks it
     pub config: Hub75Config,
-    framebuffer: FrameBuffer,
+    /// Front and back buffers; only the front one is used when single-buffered
+    framebuffers: [FrameBuffer<W, H, CHAIN>; 2],
+    /// Index of the buffer scanned out by `update()`
+    front: usize,
+    /// Frame of the dithering cycle, see [`Hub75Config::dithering`]
+    dither_frame: u8,
 }
 
-impl<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
-    Hub75<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
+impl<
+    const W: usize,
+    const H: usize,
+    E,
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E0,
+    CLK,
+    LAT,
+    OE,
+    const CHAIN: usize,
+> Hub75<W, H, E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE, CHAIN>
 where
     E: core::fmt::Debug,
     R1: OutputPin<Error = E>,
@@ -365,111 +655,113 @@ where
     LAT: OutputPin<Error = E>,
     OE: OutputPin<Error = E>,
 {
+    /// Width of the whole chain
+    pub const WIDTH: usize = FrameBuffer::<W, H, CHAIN>::WIDTH;
+
     /// Create a new Hub75 driver with default configuration
+    #[allow(clippy::type_complexity)]
     pub fn new(pins: Hub75Pins<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>) -> Self {
-        Self::new_with_config(pins, Hub75Config::default())
+        Self {
+            pins,
+            config: Hub75Config::default(),
+            framebuffers: [FrameBuffer::new(), FrameBuffer::new()],
+            front: 0,
+            dither_frame: 0,
+        }
     }
 
     /// Create a new Hub75 driver with custom configuration
+    ///
+    /// Fails if the configuration is invalid, see [`Hub75Config::validate`].
+    #[allow(clippy::type_complexity)]
     pub fn new_with_config(
         pins: Hub75Pins<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>,
         config: Hub75Config,
-    ) -> Self {
-        let framebuffer = FrameBuffer::new();
-
-        Self {
-            pins,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self {
             config,
-            framebuffer,
-        }
+            ..Self::new(pins)
+        })
     }
 
     /// Update the configuration
-    pub fn set_config(&mut self, config: Hub75Config) {
+    ///
+    /// Fails and keeps the current one if the new one is invalid.
+    pub fn set_config(&mut self, config: Hub75Config) -> Result<(), ConfigError> {
+        config.validate()?;
         self.config = config;
+        Ok(())
+    }
+
+    /// Buffer written by `set_pixel` and `clear`
+    fn draw_buffer(&mut self) -> &mut FrameBuffer<W, H, CHAIN> {
+        let index = if self.config.double_buffered {
+            1 - self.front
+        } else {
+            self.front
+        };
+        &mut self.framebuffers[index]
+    }
+
+    /// Show the frame drawn since the last commit (double-buffered mode)
+    ///
+    /// Swaps the buffers, so the next `update()` scans out the new frame,
+    /// and clears the new back buffer for the next frame, like the RP2350
+    /// driver. Only the rows that differ from what the panel shows are marked
+    /// as changed, so [`CleanRows`] still applies. Does nothing when
+    /// single-buffered: drawing is visible on the next `update()` already.
+    pub fn commit(&mut self) {
+        if !self.config.double_buffered {
+            return;
+        }
+
+        // Rows of the old front not scanned out yet still differ from the panel
+        let pending = self.framebuffers[self.front].dirty_rows;
+        self.front = 1 - self.front;
+        let front = &mut self.framebuffers[self.front];
+        // The back buffer's dirty rows cover those differing from the front
+        front.dirty_rows |= pending;
+
+        // Blank, the new back buffer differs from the front on its lit rows
+        let lit = front.lit_rows();
+        let back = self.draw_buffer();
+        back.clear();
+        back.dirty_rows = lit;
     }
 
     /// Update the display with the current framebuffer contents
+    ///
+    /// In double-buffered mode this is the front buffer, drawing goes to the
+    /// back buffer until `commit()`.
     pub fn update(&mut self, delay: &mut impl DelayNs) -> Result<(), E> {
         // Only update if the framebuffer has changed
-        if !self.framebuffer.is_modified() {
+        if !self.framebuffers[self.front].is_modified() && !self.config.dithering {
             return Ok(());
         }
 
         // Start with output disabled
         self.pins.set_output_enabled(false)?;
 
-        // Correct PWM bit plane implementation - directly use the bit count
-        let num_bit_planes = self.config.pwm_bits as usize;
-
-        // Process each row
-        for row in 0..ACTIVE_ROWS {
-            // For each bit position in PWM sequence (binary-coded modulation)
-            for bit_plane in 0..num_bit_planes {
-                // Calculate the bit mask for this bit position
-                // MSB (highest bit_plane) has the largest weight and should be displayed longest
-                let bit_position = num_bit_planes - 1 - bit_plane;
-
-                // Shift in the data for this row
-                for col in 0..DISPLAY_WIDTH {
-                    let pixel = self.framebuffer.buffer[row][col];
-
-                    // Apply gamma and brightness in-place
-                    let (mut r1, mut g1, mut b1, mut r2, mut g2, mut b2) =
-                        (pixel.r1, pixel.g1, pixel.b1, pixel.r2, pixel.g2, pixel.b2);
-                    // Apply brightness
-                    let brightness = u16::from(self.config.brightness);
-                    r1 = ((u16::from(r1) * brightness) >> 8) as u8;
-                    g1 = ((u16::from(g1) * brightness) >> 8) as u8;
-                    b1 = ((u16::from(b1) * brightness) >> 8) as u8;
-                    r2 = ((u16::from(r2) * brightness) >> 8) as u8;
-                    g2 = ((u16::from(g2) * brightness) >> 8) as u8;
-                    b2 = ((u16::from(b2) * brightness) >> 8) as u8;
-
-                    if self.config.use_gamma_correction {
-                        r1 = GAMMA8[r1 as usize];
-                        g1 = GAMMA8[g1 as usize];
-                        b1 = GAMMA8[b1 as usize];
-                        r2 = GAMMA8[r2 as usize];
-                        g2 = GAMMA8[g2 as usize];
-                        b2 = GAMMA8[b2 as usize];
-                    }
-
-                    // Bit plane comparison
-                    let mask = 1 << (7 - bit_plane); // MSB first
-                    let r1_active = (r1 & mask) != 0;
-                    let g1_active = (g1 & mask) != 0;
-                    let b1_active = (b1 & mask) != 0;
-
-                    let r2_active = (r2 & mask) != 0;
-                    let g2_active = (g2 & mask) != 0;
-                    let b2_active = (b2 & mask) != 0;
-
-                    // Set the color pins
-                    let dual_pixel = DualPixel {
-                        r1: u8::from(r1_active),
-                        g1: u8::from(g1_active),
-                        b1: u8::from(b1_active),
-                        r2: u8::from(r2_active),
-                        g2: u8::from(g2_active),
-                        b2: u8::from(b2_active),
-                    };
-                    self.pins.set_color_pins(&dual_pixel, 0)?;
-                    self.pins.clock_pulse()?;
+        let pwm_bits = self.config.pwm_bits;
+        for address in 0..self.addresses() {
+            let front = &self.framebuffers[self.front];
+            let bit_planes = if front.is_address_dirty(self.config.scan_mode, address) {
+                pwm_bits
+            } else {
+                match self.config.clean_rows {
+                    CleanRows::Refresh => pwm_bits,
+                    CleanRows::Skip => continue,
+                    CleanRows::ReducedDepth(bits) => bits.min(pwm_bits),
                 }
+            };
 
-                // Latch the data
-                self.pins.latch()?;
-
-                // Set row address
-                self.pins.set_row(row)?;
+            // For each bit position in PWM sequence (binary-coded modulation)
+            for bit_plane in 0..bit_planes as usize {
+                let hold_time = self.load_bit_plane(address, bit_plane)?;
 
-                // Enable output
+                // Enable output for the weight of the bit plane
                 self.pins.set_output_enabled(true)?;
-
-                // Hold proportionally to the bit weight (binary coded modulation)
-                // MSB (bit_position = pwm_bits-1) should be displayed longest
-                let hold_time = (1 << bit_position) * self.config.row_step_time_us;
                 delay.delay_us(hold_time);
 
                 // Disable output before next bit plane
@@ -481,30 +773,156 @@ where
         }
 
         // Mark framebuffer as updated
-        self.framebuffer.reset_modified();
+        self.framebuffers[self.front].reset_modified();
+        self.dither_frame = self.dither_frame.wrapping_add(1);
 
         Ok(())
     }
 
+    /// Keep the panel refreshed from an Embassy task
+    ///
+    /// Scans out the front buffer every `ticker`, whether it changed or not,
+    /// and waits on `embassy_time` timers while each bit plane is shown
+    /// instead of blocking, so other tasks run in the meantime. Hold times
+    /// get rounded to the timer tick, keep `row_step_time_us` well above it.
+    #[cfg(feature = "embassy")]
+    pub async fn refresh_task(&mut self, ticker: Duration) -> Result<Infallible, E> {
+        let mut ticker = Ticker::every(ticker);
+        loop {
+            self.pins.set_output_enabled(false)?;
+            for address in 0..self.addresses() {
+                for bit_plane in 0..self.config.pwm_bits as usize {
+                    let hold_time = self.load_bit_plane(address, bit_plane)?;
+
+                    self.pins.set_output_enabled(true)?;
+                    Timer::after_micros(hold_time.into()).await;
+                    self.pins.set_output_enabled(false)?;
+
+                    // Small delay to prevent ghosting
+                    Timer::after_micros(1).await;
+                }
+            }
+            self.framebuffers[self.front].reset_modified();
+            self.dither_frame = self.dither_frame.wrapping_add(1);
+
+            ticker.next().await;
+        }
+    }
+
+    /// Addresses scanned per frame
+    const fn addresses(&self) -> usize {
+        self.config
+            .scan_mode
+            .addresses(FrameBuffer::<W, H, CHAIN>::ACTIVE_ROWS)
+    }
+
+    /// Shift out and latch one bit plane of the rows lit by `address`
+    ///
+    /// Leaves the output disabled and returns how long the bit plane should
+    /// be shown, in microseconds.
+    fn load_bit_plane(&mut self, address: usize, bit_plane: usize) -> Result<u32, E> {
+        // Calculate the bit mask for this bit position
+        // MSB (highest bit_plane) has the largest weight and should be displayed longest
+        let num_bit_planes = self.config.pwm_bits as usize;
+        let bit_position = num_bit_planes - 1 - bit_plane;
+
+        // Rows of each half lit by one address, all shifted out together
+        let half_height = FrameBuffer::<W, H, CHAIN>::ACTIVE_ROWS;
+        let scan_mode = self.config.scan_mode;
+        let shift_length = Self::WIDTH * (half_height / self.addresses());
+
+        // Shift in the data for the rows of this address on every panel, the
+        // first panel's columns ending up at the end of the chain
+        for shift in 0..shift_length {
+            let (row, col) = scan_mode.locate(half_height, address, shift);
+            let pixel = self.framebuffers[self.front].dual_pixel(row, col);
+
+            // Apply gamma and brightness in-place
+            let (mut r1, mut g1, mut b1, mut r2, mut g2, mut b2) =
+                (pixel.r1, pixel.g1, pixel.b1, pixel.r2, pixel.g2, pixel.b2);
+            // Apply brightness
+            let brightness = u16::from(self.config.brightness);
+            r1 = ((u16::from(r1) * brightness) >> 8) as u8;
+            g1 = ((u16::from(g1) * brightness) >> 8) as u8;
+            b1 = ((u16::from(b1) * brightness) >> 8) as u8;
+            r2 = ((u16::from(r2) * brightness) >> 8) as u8;
+            g2 = ((u16::from(g2) * brightness) >> 8) as u8;
+            b2 = ((u16::from(b2) * brightness) >> 8) as u8;
+
+            if self.config.use_gamma_correction {
+                r1 = GAMMA8[r1 as usize];
+                g1 = GAMMA8[g1 as usize];
+                b1 = GAMMA8[b1 as usize];
+                r2 = GAMMA8[r2 as usize];
+                g2 = GAMMA8[g2 as usize];
+                b2 = GAMMA8[b2 as usize];
+            }
+
+            if self.config.dithering {
+                let pwm_bits = self.config.pwm_bits;
+                let top = dither_threshold(col, row, self.dither_frame);
+                let bottom = dither_threshold(col, row + half_height, self.dither_frame);
+                r1 = dither(r1, pwm_bits, top);
+                g1 = dither(g1, pwm_bits, top);
+                b1 = dither(b1, pwm_bits, top);
+                r2 = dither(r2, pwm_bits, bottom);
+                g2 = dither(g2, pwm_bits, bottom);
+                b2 = dither(b2, pwm_bits, bottom);
+            }
+
+            // Bit plane comparison
+            let mask = 1 << (7 - bit_plane); // MSB first
+            let r1_active = (r1 & mask) != 0;
+            let g1_active = (g1 & mask) != 0;
+            let b1_active = (b1 & mask) != 0;
+
+            let r2_active = (r2 & mask) != 0;
+            let g2_active = (g2 & mask) != 0;
+            let b2_active = (b2 & mask) != 0;
+
+            // Set the color pins
+            let dual_pixel = DualPixel {
+                r1: u8::from(r1_active),
+                g1: u8::from(g1_active),
+                b1: u8::from(b1_active),
+                r2: u8::from(r2_active),
+                g2: u8::from(g2_active),
+                b2: u8::from(b2_active),
+            };
+            self.pins.set_color_pins(&dual_pixel, 0)?;
+            self.pins.clock_pulse()?;
+        }
+
+        // Latch the data
+        self.pins.latch()?;
+
+        // Set row address
+        self.pins.set_row(address)?;
+
+        // Hold proportionally to the bit weight (binary coded modulation)
+        // MSB (bit_position = pwm_bits-1) should be displayed longest
+        Ok((1 << bit_position) * self.config.row_step_time_us)
+    }
+
     /// Set a pixel in the framebuffer
     pub fn set_pixel(&mut self, x: i32, y: i32, color: Rgb565) {
         // Convert Rgb565 to 8-bit linear scale
-        let r_original = color.r() << 3; // 5-bit -> 8-bit
-        let g_original = color.g() << 2; // 6-bit -> 8-bit
-        let b_original = color.b() << 3;
+        let rgb = [
+            color.r() << 3, // 5-bit -> 8-bit
+            color.g() << 2, // 6-bit -> 8-bit
+            color.b() << 3,
+        ];
 
-        // Swap the colors to match the hardware configuration
-        // Based on your description: blue→green, green→red, red→blue
-        let r = b_original; // Red pin receives what should be blue
-        let g = r_original; // Green pin receives what should be red
-        let b = g_original; // Blue pin receives what should be green
+        // Route the channels to the inputs they are wired to
+        let [r, g, b] = self.config.color_order.map(rgb);
 
-        self.framebuffer.set_pixel(x as usize, y as usize, r, g, b);
+        self.draw_buffer()
+            .set_pixel(x as usize, y as usize, r, g, b);
     }
 
-    /// Clear the framebuffer
+    /// Clear the framebuffer (the back buffer in double-buffered mode)
     pub fn clear(&mut self) {
-        self.framebuffer.clear();
+        self.draw_buffer().clear();
     }
 
     /// Draw a test pattern to verify correct row mapping and scanning
@@ -513,7 +931,7 @@ where
         self.clear();
 
         // Draw horizontal color bands
-        for y in 0..DISPLAY_HEIGHT {
+        for y in 0..H {
             let color = match (y / 8) % 8 {
                 0 => Rgb565::RED,
                 1 => Rgb565::GREEN,
@@ -525,35 +943,35 @@ where
                 _ => Rgb565::new(255 >> 3, 128 >> 2, 0), // Orange
             };
 
-            for x in 0..DISPLAY_WIDTH {
+            for x in 0..Self::WIDTH {
                 self.set_pixel(x as i32, y as i32, color);
             }
         }
 
         // Add a diagonal line for visual confirmation
-        for i in 0..DISPLAY_HEIGHT {
+        for i in 0..H {
             self.set_pixel(i as i32, i as i32, Rgb565::WHITE);
             // Draw a thicker line for better visibility
             if i > 0 {
                 self.set_pixel(i as i32 - 1, i as i32, Rgb565::WHITE);
             }
-            if i < DISPLAY_WIDTH - 1 {
+            if i < Self::WIDTH - 1 {
                 self.set_pixel(i as i32 + 1, i as i32, Rgb565::WHITE);
             }
         }
 
         // Draw a grid pattern
-        for i in 0..DISPLAY_HEIGHT {
+        for i in 0..H {
             if i % 8 == 0 {
-                for x in 0..DISPLAY_WIDTH {
+                for x in 0..Self::WIDTH {
                     self.set_pixel(x as i32, i as i32, Rgb565::BLACK);
                 }
             }
         }
 
-        for i in 0..DISPLAY_WIDTH {
+        for i in 0..Self::WIDTH {
             if i % 8 == 0 {
-                for y in 0..DISPLAY_HEIGHT {
+                for y in 0..H {
                     self.set_pixel(i as i32, y as i32, Rgb565::BLACK);
                 }
             }
@@ -564,16 +982,12 @@ where
     pub fn draw_test_gradient(&mut self) {
         self.clear();
 
-        for y in 0..DISPLAY_HEIGHT {
-            for x in 0..DISPLAY_WIDTH {
+        for y in 0..H {
+            for x in 0..Self::WIDTH {
                 self.set_pixel(
                     x as i32,
                     y as i32,
-                    Rgb565::new(
-                        (x * 32 / DISPLAY_WIDTH) as u8,
-                        32,
-                        (y * 32 / DISPLAY_HEIGHT) as u8,
-                    ),
+                    Rgb565::new((x * 32 / Self::WIDTH) as u8, 32, (y * 32 / H) as u8),
                 );
             }
         }
@@ -581,8 +995,26 @@ where
 }
 
 // Implement embedded-graphics interfaces
-impl<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE> OriginDimensions
-    for Hub75<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
+impl<
+    const W: usize,
+    const H: usize,
+    E,
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E0,
+    CLK,
+    LAT,
+    OE,
+    const CHAIN: usize,
+> OriginDimensions for Hub75<W, H, E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE, CHAIN>
 where
     E: core::fmt::Debug,
     R1: OutputPin<Error = E>,
@@ -601,12 +1033,30 @@ where
     OE: OutputPin<Error = E>,
 {
     fn size(&self) -> Size {
-        Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32)
+        Size::new(Self::WIDTH as u32, H as u32)
     }
 }
 
-impl<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE> DrawTarget
-    for Hub75<E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE>
+impl<
+    const W: usize,
+    const H: usize,
+    E,
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E0,
+    CLK,
+    LAT,
+    OE,
+    const CHAIN: usize,
+> DrawTarget for Hub75<W, H, E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE, CHAIN>
 where
     E: core::fmt::Debug,
     R1: OutputPin<Error = E>,
@@ -638,3 +1088,423 @@ where
         Ok(())
     }
 }
+
+impl<
+    const W: usize,
+    const H: usize,
+    E,
+    R1,
+    G1,
+    B1,
+    R2,
+    G2,
+    B2,
+    A,
+    B,
+    C,
+    D,
+    E0,
+    CLK,
+    LAT,
+    OE,
+    const CHAIN: usize,
+> MatrixDriver for Hub75<W, H, E, R1, G1, B1, R2, G2, B2, A, B, C, D, E0, CLK, LAT, OE, CHAIN>
+where
+    E: core::fmt::Debug,
+    R1: OutputPin<Error = E>,
+    G1: OutputPin<Error = E>,
+    B1: OutputPin<Error = E>,
+    R2: OutputPin<Error = E>,
+    G2: OutputPin<Error = E>,
+    B2: OutputPin<Error = E>,
+    A: OutputPin<Error = E>,
+    B: OutputPin<Error = E>,
+    C: OutputPin<Error = E>,
+    D: OutputPin<Error = E>,
+    E0: OutputPin<Error = E>,
+    CLK: OutputPin<Error = E>,
+    LAT: OutputPin<Error = E>,
+    OE: OutputPin<Error = E>,
+{
+    fn set_pixel(&mut self, point: Point, color: Rgb565) {
+        self.set_pixel(point.x, point.y, color);
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn commit(&mut self) {
+        self.commit();
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        self.config.brightness = brightness;
+    }
+
+    fn brightness(&self) -> u8 {
+        self.config.brightness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::digital::ErrorType;
+
+    /// Delay returning right away
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    /// Output pin that ignores what is written to it
+    struct Pin;
+
+    impl ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Pin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    type Pins =
+        Hub75Pins<Infallible, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin>;
+
+    type Panel = Hub75<
+        16,
+        32,
+        Infallible,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+        Pin,
+    >;
+
+    fn pins() -> Pins {
+        Hub75Pins::new(
+            Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin, Pin,
+        )
+    }
+
+    fn panel(config: Hub75Config) -> Panel {
+        Hub75::new_with_config(pins(), config).unwrap()
+    }
+
+    fn double_buffered() -> Panel {
+        panel(Hub75Config {
+            double_buffered: true,
+            ..Hub75Config::default()
+        })
+    }
+
+    #[test]
+    fn test_addresses() {
+        assert_eq!(ScanMode::Full.addresses(32), 32);
+        assert_eq!(ScanMode::Scan16.addresses(32), 16);
+        assert_eq!(ScanMode::Scan8.addresses(32), 8);
+        assert_eq!(ScanMode::Scan8.addresses(16), 8);
+
+        // More addresses than rows, or not dividing them: one row per address
+        assert_eq!(ScanMode::Scan16.addresses(8), 8);
+        assert_eq!(ScanMode::Scan16.addresses(24), 24);
+        assert_eq!(ScanMode::Scan8.addresses(12), 12);
+    }
+
+    #[test]
+    fn test_locate_full_scan() {
+        assert_eq!(ScanMode::Full.locate(32, 0, 0), (0, 0));
+        assert_eq!(ScanMode::Full.locate(32, 5, 10), (5, 10));
+        assert_eq!(ScanMode::Full.locate(32, 31, 63), (31, 63));
+    }
+
+    #[test]
+    fn test_locate_scan16_zigzags_between_rows() {
+        // Address 3 lights rows 3 and 19, 8 columns of each in turn
+        assert_eq!(ScanMode::Scan16.locate(32, 3, 0), (3, 0));
+        assert_eq!(ScanMode::Scan16.locate(32, 3, 7), (3, 7));
+        assert_eq!(ScanMode::Scan16.locate(32, 3, 8), (19, 0));
+        assert_eq!(ScanMode::Scan16.locate(32, 3, 16), (3, 8));
+        assert_eq!(ScanMode::Scan16.locate(32, 3, 27), (19, 11));
+    }
+
+    #[test]
+    fn test_locate_scan8_zigzags_between_rows() {
+        // Address 2 lights rows 2, 10, 18 and 26
+        assert_eq!(ScanMode::Scan8.locate(32, 2, 8), (10, 0));
+        assert_eq!(ScanMode::Scan8.locate(32, 2, 24), (26, 0));
+        assert_eq!(ScanMode::Scan8.locate(32, 2, 33), (2, 9));
+    }
+
+    #[test]
+    fn test_locate_covers_every_pixel_once() {
+        const HALF: usize = 16;
+        const WIDTH: usize = 32;
+
+        for mode in [ScanMode::Full, ScanMode::Scan16, ScanMode::Scan8] {
+            let addresses = mode.addresses(HALF);
+            let mut seen = [[false; WIDTH]; HALF];
+            for address in 0..addresses {
+                for shift in 0..WIDTH * (HALF / addresses) {
+                    let (row, col) = mode.locate(HALF, address, shift);
+                    assert!(!seen[row][col], "{mode:?} shifts ({row}, {col}) twice");
+                    seen[row][col] = true;
+                }
+            }
+            assert!(seen.iter().flatten().all(|&seen| seen), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_dirty_rows() {
+        let mut framebuffer = FrameBuffer::<16, 32>::new();
+        assert_eq!(framebuffer.dirty_rows(), u64::from(u32::MAX));
+
+        framebuffer.reset_modified();
+        assert!(!framebuffer.is_modified());
+
+        // Same color, or outside the panel: nothing changes
+        framebuffer.set_pixel(3, 5, 0, 0, 0);
+        framebuffer.set_pixel(16, 5, 255, 0, 0);
+        framebuffer.set_pixel(3, 32, 255, 0, 0);
+        assert!(!framebuffer.is_modified());
+
+        framebuffer.set_pixel(3, 5, 255, 0, 0);
+        framebuffer.set_pixel(0, 20, 0, 255, 0);
+        assert_eq!(framebuffer.dirty_rows(), (1 << 5) | (1 << 20));
+
+        // Clearing only changes the lit rows
+        framebuffer.reset_modified();
+        framebuffer.clear();
+        assert_eq!(framebuffer.dirty_rows(), (1 << 5) | (1 << 20));
+
+        framebuffer.reset_modified();
+        framebuffer.clear();
+        assert!(!framebuffer.is_modified());
+    }
+
+    #[test]
+    fn test_dirty_rows_of_a_chain() {
+        let mut framebuffer = FrameBuffer::<16, 32, 2>::new();
+        framebuffer.reset_modified();
+
+        // On the second panel of the chain
+        framebuffer.set_pixel(20, 7, 0, 0, 255);
+        assert_eq!(framebuffer.dirty_rows(), 1 << 7);
+    }
+
+    #[test]
+    fn test_is_address_dirty() {
+        let mut framebuffer = FrameBuffer::<16, 32>::new();
+        framebuffer.reset_modified();
+
+        // Row 4 of the bottom half
+        framebuffer.set_pixel(0, 20, 255, 255, 255);
+        assert!(framebuffer.is_address_dirty(ScanMode::Full, 4));
+        assert!(!framebuffer.is_address_dirty(ScanMode::Full, 3));
+
+        // 1/8 scan: address 4 lights rows 4 and 12 of each half
+        framebuffer.reset_modified();
+        framebuffer.set_pixel(0, 28, 255, 255, 255);
+        assert!(framebuffer.is_address_dirty(ScanMode::Scan8, 4));
+        assert!(!framebuffer.is_address_dirty(ScanMode::Scan8, 0));
+    }
+
+    #[test]
+    fn test_color_order_map() {
+        let color = [10, 20, 30];
+        let orders = [
+            (ColorOrder::Rgb, "RGB"),
+            (ColorOrder::Rbg, "RBG"),
+            (ColorOrder::Grb, "GRB"),
+            (ColorOrder::Gbr, "GBR"),
+            (ColorOrder::Brg, "BRG"),
+            (ColorOrder::Bgr, "BGR"),
+        ];
+
+        // The name lists the inputs driven by red, green and blue
+        for (order, inputs) in orders {
+            let mapped = order.map(color);
+            for (channel, input) in inputs.bytes().enumerate() {
+                let index = b"RGB".iter().position(|&i| i == input).unwrap();
+                assert_eq!(mapped[index], color[channel], "{order:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_dither_rounds_up_on_some_frames() {
+        // 6 bits shown: the two dropped bits are the fraction
+        let value = 0b0000_0010;
+        let shown: [u8; 4] = core::array::from_fn(|threshold| dither(value, 6, threshold as u8));
+        assert_eq!(shown, [0b0110, 0b0110, 0b0010, 0b0010]);
+
+        // 7 bits shown: the single dropped bit counts as half
+        assert_eq!(dither(1, 7, 0), 3);
+        assert_eq!(dither(1, 7, 2), 1);
+        assert_eq!(dither(0, 7, 0), 0);
+    }
+
+    #[test]
+    fn test_dither_keeps_full_depth_and_saturates() {
+        assert_eq!(dither(0b1010_1011, 8, 0), 0b1010_1011);
+        assert_eq!(dither(255, 6, 0), 255);
+    }
+
+    #[test]
+    fn test_dither_threshold_out_of_phase() {
+        // Each pixel of a 2x2 block gets its own threshold...
+        let mut block: [u8; 4] = core::array::from_fn(|i| dither_threshold(i & 1, i >> 1, 0));
+        block.sort_unstable();
+        assert_eq!(block, [0, 1, 2, 3]);
+
+        // ...and each pixel goes through all of them over four frames
+        let mut frames: [u8; 4] = core::array::from_fn(|frame| dither_threshold(5, 2, frame as u8));
+        frames.sort_unstable();
+        assert_eq!(frames, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_commit_shows_back_buffer() {
+        let mut panel = double_buffered();
+        panel.framebuffers[panel.front].reset_modified();
+        panel.set_pixel(1, 2, Rgb565::WHITE);
+
+        // Not shown until committed
+        assert_eq!(panel.framebuffers[panel.front].buffer[2][0][1], [0; 3]);
+
+        panel.commit();
+        assert_eq!(
+            panel.framebuffers[panel.front].buffer[2][0][1],
+            [248, 252, 248]
+        );
+        assert!(panel.framebuffers[panel.front].is_modified());
+
+        // The new back buffer starts blank
+        let back = &panel.framebuffers[1 - panel.front];
+        assert!(back.buffer.iter().flatten().flatten().all(|&p| p == [0; 3]));
+    }
+
+    #[test]
+    fn test_commit_single_buffered_does_nothing() {
+        let mut panel = panel(Hub75Config::default());
+        panel.set_pixel(1, 2, Rgb565::WHITE);
+        panel.commit();
+
+        assert_eq!(panel.front, 0);
+        assert_eq!(panel.framebuffers[0].buffer[2][0][1], [248, 252, 248]);
+    }
+
+    #[test]
+    fn test_rejects_pwm_bits_out_of_range() {
+        for pwm_bits in [0, 9, 255] {
+            let config = Hub75Config {
+                pwm_bits,
+                ..Hub75Config::default()
+            };
+            assert_eq!(
+                Panel::new_with_config(pins(), config).err(),
+                Some(ConfigError::PwmBits(pwm_bits))
+            );
+        }
+
+        let mut panel = panel(Hub75Config::default());
+        let config = Hub75Config {
+            pwm_bits: 0,
+            ..Hub75Config::default()
+        };
+        assert_eq!(panel.set_config(config), Err(ConfigError::PwmBits(0)));
+        assert_eq!(panel.config.pwm_bits, Hub75Config::default().pwm_bits);
+    }
+
+    #[test]
+    fn test_dithered_update_with_any_valid_pwm_bits() {
+        for pwm_bits in 1..=8 {
+            let mut panel = panel(Hub75Config {
+                pwm_bits,
+                dithering: true,
+                use_gamma_correction: false,
+                ..Hub75Config::default()
+            });
+            for x in 0..16 {
+                panel.set_pixel(x, 0, Rgb565::WHITE);
+                panel.set_pixel(x, 1, Rgb565::new(x as u8, 0, 31 - x as u8));
+            }
+            for _ in 0..4 {
+                panel.update(&mut NoDelay).unwrap();
+            }
+        }
+    }
+
+    /// Double-buffered panel with both buffers blank and shown
+    fn steady_double_buffered() -> Panel {
+        let mut panel = double_buffered();
+        panel.commit();
+        panel.update(&mut NoDelay).unwrap();
+        panel.commit();
+        panel.update(&mut NoDelay).unwrap();
+        assert!(!panel.framebuffers[panel.front].is_modified());
+        panel
+    }
+
+    #[test]
+    fn test_commit_carries_dirty_rows() {
+        let mut panel = steady_double_buffered();
+
+        // Nothing drawn: nothing to scan out
+        panel.commit();
+        assert!(!panel.framebuffers[panel.front].is_modified());
+
+        panel.set_pixel(1, 5, Rgb565::WHITE);
+        panel.set_pixel(1, 21, Rgb565::RED);
+        panel.commit();
+        assert_eq!(
+            panel.framebuffers[panel.front].dirty_rows(),
+            (1 << 5) | (1 << 21)
+        );
+        panel.update(&mut NoDelay).unwrap();
+
+        // The rows lit in the frame shown go dark
+        panel.commit();
+        assert_eq!(
+            panel.framebuffers[panel.front].dirty_rows(),
+            (1 << 5) | (1 << 21)
+        );
+        panel.update(&mut NoDelay).unwrap();
+
+        panel.commit();
+        assert!(!panel.framebuffers[panel.front].is_modified());
+    }
+
+    #[test]
+    fn test_commit_keeps_rows_not_scanned_out() {
+        let mut panel = steady_double_buffered();
+        panel.set_pixel(1, 3, Rgb565::WHITE);
+        panel.commit();
+        panel.update(&mut NoDelay).unwrap();
+
+        // Two blank frames committed without a scan-out in between: the
+        // panel still shows row 3
+        panel.commit();
+        panel.commit();
+        assert_eq!(panel.framebuffers[panel.front].dirty_rows(), 1 << 3);
+    }
+}
@@ -0,0 +1,37 @@
+//! `esp-hal` outputs for the pins
+
+use crate::{Hub75, Hub75EspPins, SamePins};
+use core::convert::Infallible;
+use esp_hal::gpio::{AnyPin, Level, Output, OutputConfig};
+
+/// Hub75 pins driven as `esp-hal` outputs
+pub type EspPins<'d> = SamePins<Output<'d>>;
+
+/// Bit-banged driver of a chain of `CHAIN` `W`x`H` panels on `esp-hal` outputs
+pub type Hub75Esp32<'d, const W: usize, const H: usize, const CHAIN: usize = 1> = Hub75<
+    W,
+    H,
+    Infallible,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    Output<'d>,
+    CHAIN,
+>;
+
+impl<'d> Hub75EspPins<AnyPin<'d>> {
+    /// Configure the pins as outputs, with the panel blanked
+    pub fn into_outputs(self) -> EspPins<'d> {
+        self.map(|pin, high| Output::new(pin, Level::from(high), OutputConfig::default()))
+    }
+}
@@ -0,0 +1,206 @@
+#![no_std]
+//! Hub75 driver for the ESP32-S3 over `esp-hal` GPIOs
+//!
+//! The bit-banged `hub75-driver` on `esp-hal` outputs: the framebuffer,
+//! BCM refresh, scan modes and dithering are shared with it, this crate
+//! only wires the pins. The driver implements [`MatrixDriver`], so the
+//! test patterns, animations and self-test of `graphics-common` draw on it
+//! as on the RP2350.
+//!
+//! ```ignore
+//! let peripherals = esp_hal::init(esp_hal::Config::default());
+//! let pins = Hub75EspPins {
+//!     r1: peripherals.GPIO4.into(),
+//!     // ...
+//!     oe: peripherals.GPIO21.into(),
+//! };
+//! let mut display: Hub75Esp32<64, 64> =
+//!     Hub75Esp32::new_with_config(pins.into_outputs(), Hub75Config::default()).unwrap();
+//!
+//! let mut delay = Delay::new();
+//! loop {
+//!     draw_animation_frame(&mut display, frame)?;
+//!     display.commit();
+//!     display.update(&mut delay).unwrap();
+//! }
+//! ```
+//!
+//! Every pin is written on its own, so a refresh is far slower than with
+//! the PIO and DMA of the RP2350: fine for a 64x64 panel at 4 to 6 PWM
+//! bits, not for long chains, see [`timing`]. The `esp-hal` outputs only
+//! build for Xtensa (see rust-toolchain.toml); the pin and timing mapping
+//! build and are tested on the host with the rest of the workspace.
+
+#[cfg(target_arch = "xtensa")]
+mod esp;
+pub mod timing;
+
+#[cfg(target_arch = "xtensa")]
+pub use esp::{EspPins, Hub75Esp32};
+pub use hub75_driver::{
+    CleanRows, ColorOrder, ConfigError, Hub75, Hub75Config, Hub75Pins, ScanMode,
+};
+pub use matrix_driver::MatrixDriver;
+
+use embedded_hal::digital::OutputPin;
+
+/// Every Hub75 signal on the same output type `O`
+pub type SamePins<O> = Hub75Pins<
+    <O as embedded_hal::digital::ErrorType>::Error,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+    O,
+>;
+
+/// GPIOs wired to the Hub75 connector
+///
+/// Any output-capable GPIO works. Panels with fewer than 32 row addresses
+/// ignore the upper address lines, which still need a pin each.
+pub struct Hub75EspPins<P> {
+    pub r1: P,
+    pub g1: P,
+    pub b1: P,
+    pub r2: P,
+    pub g2: P,
+    pub b2: P,
+    pub a: P,
+    pub b: P,
+    pub c: P,
+    pub d: P,
+    pub e: P,
+    pub clk: P,
+    pub lat: P,
+    pub oe: P,
+}
+
+impl<P> Hub75EspPins<P> {
+    /// Make an output of each pin with `output(pin, high)`, with the panel
+    /// blanked: every line starts low but OE, which is active low
+    pub fn map<O: OutputPin>(self, mut output: impl FnMut(P, bool) -> O) -> SamePins<O> {
+        Hub75Pins::new(
+            output(self.r1, false),
+            output(self.g1, false),
+            output(self.b1, false),
+            output(self.r2, false),
+            output(self.g2, false),
+            output(self.b2, false),
+            output(self.a, false),
+            output(self.b, false),
+            output(self.c, false),
+            output(self.d, false),
+            output(self.e, false),
+            output(self.clk, false),
+            output(self.lat, false),
+            output(self.oe, true),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use embedded_hal::digital::ErrorType;
+    use std::vec::Vec;
+
+    /// Writes to the outputs, in order
+    type Log = RefCell<Vec<(&'static str, bool)>>;
+
+    /// Output logging its writes under the name of its pin
+    struct LoggedPin<'a> {
+        name: &'static str,
+        log: &'a Log,
+    }
+
+    impl ErrorType for LoggedPin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for LoggedPin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.log.borrow_mut().push((self.name, false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.log.borrow_mut().push((self.name, true));
+            Ok(())
+        }
+    }
+
+    /// Pins named after their signal
+    const NAMED: Hub75EspPins<&str> = Hub75EspPins {
+        r1: "r1",
+        g1: "g1",
+        b1: "b1",
+        r2: "r2",
+        g2: "g2",
+        b2: "b2",
+        a: "a",
+        b: "b",
+        c: "c",
+        d: "d",
+        e: "e",
+        clk: "clk",
+        lat: "lat",
+        oe: "oe",
+    };
+
+    #[test]
+    fn test_outputs_start_blanked() {
+        let log = Log::default();
+        let mut initial = Vec::new();
+        NAMED.map(|name, high| {
+            initial.push((name, high));
+            LoggedPin { name, log: &log }
+        });
+
+        assert_eq!(initial.len(), 14);
+        for (name, high) in initial {
+            assert_eq!(high, name == "oe", "{name}");
+        }
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_pins_drive_their_signal() {
+        let log = Log::default();
+        let mut pins = NAMED.map(|name, _| LoggedPin { name, log: &log });
+
+        pins.set_output_enabled(true).unwrap();
+        pins.clock_pulse().unwrap();
+        pins.latch().unwrap();
+        assert_eq!(
+            log.take(),
+            [
+                ("oe", false),
+                ("clk", true),
+                ("clk", false),
+                ("lat", true),
+                ("lat", false)
+            ]
+        );
+
+        pins.set_row(0b10101).unwrap();
+        let high: Vec<_> = log
+            .take()
+            .into_iter()
+            .filter_map(|(name, high)| high.then_some(name))
+            .collect();
+        assert_eq!(high, ["a", "c", "e"]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Refresh rate of the bit-banged driver
+//!
+//! `Hub75::update` writes each pin on its own: for every row address and bit
+//! plane it shifts in a row of the chain (six color writes and a clock
+//! pulse per column), latches, sets the address, then shows the plane for
+//! its BCM weight times `row_step_time_us`. Pin writes dominate on wide
+//! chains, the hold times on deep PWM. [`refresh_hz`] adds both up for a
+//! panel and config, and [`max_pwm_bits`] picks the deepest PWM that keeps
+//! a target rate.
+
+use crate::Hub75Config;
+
+/// Rough cost of one `esp-hal` pin write on an ESP32-S3 at 240 MHz
+pub const PIN_WRITE_NS: u32 = 40;
+
+/// Pin writes per column shifted in: six colors and a clock pulse
+const WRITES_PER_COLUMN: u32 = 8;
+
+/// Pin writes per bit plane besides the columns: latch pulse, five address
+/// lines, and OE on and off
+const WRITES_PER_PLANE: u32 = 9;
+
+/// Blanking after each bit plane, against ghosting
+const BLANKING_US: u32 = 1;
+
+/// Full refreshes per second of a chain of `CHAIN` `W`x`H` panels
+///
+/// A `pwm_bits` of 0, which the driver rejects, counts as 1.
+pub const fn refresh_hz<const W: usize, const H: usize, const CHAIN: usize>(
+    config: &Hub75Config,
+) -> u32 {
+    let half_height = H / 2;
+    let addresses = config.scan_mode.addresses(half_height);
+    let columns = (W * CHAIN * (half_height / addresses)) as u32;
+    let shift_ns = (columns * WRITES_PER_COLUMN + WRITES_PER_PLANE) * PIN_WRITE_NS;
+
+    let planes = if config.pwm_bits == 0 {
+        1
+    } else {
+        config.pwm_bits
+    };
+    let mut address_ns = 0;
+    let mut plane = 0;
+    while plane < planes {
+        let hold_us = (1 << plane) * config.row_step_time_us + BLANKING_US;
+        address_ns += shift_ns + hold_us * 1000;
+        plane += 1;
+    }
+    1_000_000_000 / (address_ns * addresses as u32)
+}
+
+/// Deepest PWM, from 1 to 8 bits, refreshing at `min_hz` or more
+///
+/// `None` if even 1 bit is too slow.
+pub const fn max_pwm_bits<const W: usize, const H: usize, const CHAIN: usize>(
+    config: &Hub75Config,
+    min_hz: u32,
+) -> Option<u8> {
+    let mut config = *config;
+    let mut bits = 8;
+    while bits > 0 {
+        config.pwm_bits = bits;
+        if refresh_hz::<W, H, CHAIN>(&config) >= min_hz {
+            return Some(bits);
+        }
+        bits -= 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScanMode;
+
+    /// Rate at which a panel stops visibly flickering
+    const FLICKER_FREE_HZ: u32 = 100;
+
+    fn config(pwm_bits: u8) -> Hub75Config {
+        Hub75Config {
+            pwm_bits,
+            ..Hub75Config::default()
+        }
+    }
+
+    #[test]
+    fn test_single_panel_refreshes_fast_enough() {
+        // 4 to 6 bits on a 64x64 panel, as documented
+        for bits in 4..=6 {
+            assert!(refresh_hz::<64, 64, 1>(&config(bits)) >= FLICKER_FREE_HZ);
+        }
+        assert_eq!(
+            max_pwm_bits::<64, 64, 1>(&config(6), FLICKER_FREE_HZ),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn test_long_chain_is_too_slow() {
+        assert!(refresh_hz::<64, 64, 4>(&config(4)) < FLICKER_FREE_HZ);
+        // Down to a single bit, too few for the occupancy colors
+        assert_eq!(
+            max_pwm_bits::<128, 64, 4>(&config(6), FLICKER_FREE_HZ),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_deeper_pwm_and_longer_holds_are_slower() {
+        let shallow = refresh_hz::<64, 32, 1>(&config(4));
+        assert!(refresh_hz::<64, 32, 1>(&config(8)) < shallow);
+
+        let slow = Hub75Config {
+            row_step_time_us: 4,
+            ..config(4)
+        };
+        assert!(refresh_hz::<64, 32, 1>(&slow) < shallow);
+    }
+
+    #[test]
+    fn test_no_planes_counts_as_one() {
+        assert_eq!(
+            refresh_hz::<64, 64, 1>(&config(0)),
+            refresh_hz::<64, 64, 1>(&config(1))
+        );
+    }
+
+    #[test]
+    fn test_scan_mode_shifts_more_columns_per_address() {
+        // Same pixels either way, but fewer addresses means fewer hold times
+        let full = config(6);
+        let scan8 = Hub75Config {
+            scan_mode: ScanMode::Scan8,
+            ..full
+        };
+        assert!(refresh_hz::<64, 32, 1>(&scan8) > refresh_hz::<64, 32, 1>(&full));
+    }
+}
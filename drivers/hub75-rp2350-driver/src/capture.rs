@@ -0,0 +1,89 @@
+//! Capture of the words fed to the data state machine
+//!
+//! With the `frame-capture` feature, every committed frame has its first
+//! [`CAPTURE_WORDS`] words copied here, exactly as DMA channel 0 reads them
+//! (little-endian words of the active buffer). That is every bit plane of
+//! the first row (more when fewer planes are scanned), enough to check the
+//! plane packing without a logic analyzer on the data pins. Print it with
+//! [`FrameCapture::hex_dump`].
+
+use crate::config::{COLOR_BITS, DISPLAY_WIDTH, FRAME_SIZE};
+use core::fmt;
+
+/// Words captured per frame: all bit planes of the first row
+pub const CAPTURE_WORDS: usize = DISPLAY_WIDTH * COLOR_BITS / 4;
+
+/// Words per line of the hex dump
+const WORDS_PER_LINE: usize = 8;
+
+const _: () = assert!(CAPTURE_WORDS * 4 <= FRAME_SIZE);
+
+/// First words of the last committed frame
+pub struct FrameCapture {
+    words: [u32; CAPTURE_WORDS],
+    /// Frames captured so far
+    frames: u32,
+}
+
+impl Default for FrameCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCapture {
+    /// Empty capture, filled by the first commit
+    pub const fn new() -> Self {
+        Self {
+            words: [0; CAPTURE_WORDS],
+            frames: 0,
+        }
+    }
+
+    /// Copy the first words of a frame handed to the DMA
+    pub(crate) fn record(&mut self, frame: &[u8; FRAME_SIZE]) {
+        for (word, bytes) in self.words.iter_mut().zip(frame.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        self.frames = self.frames.wrapping_add(1);
+    }
+
+    /// Captured words, in transfer order
+    pub const fn words(&self) -> &[u32; CAPTURE_WORDS] {
+        &self.words
+    }
+
+    /// Number of frames captured, to tell whether the capture changed
+    pub const fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Lines of the hex dump, each with its byte offset and 8 words
+    ///
+    /// Every line can be sent on its own, e.g. one log message per line.
+    pub fn hex_dump(&self) -> impl Iterator<Item = HexLine<'_>> {
+        self.words
+            .chunks(WORDS_PER_LINE)
+            .enumerate()
+            .map(|(line, words)| HexLine {
+                offset: line * WORDS_PER_LINE * 4,
+                words,
+            })
+    }
+}
+
+/// Line of a [`FrameCapture`] hex dump, e.g. `0020: 00000000 09090909 ...`
+pub struct HexLine<'a> {
+    offset: usize,
+    words: &'a [u32],
+}
+
+impl fmt::Display for HexLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}:", self.offset)?;
+        for word in self.words {
+            write!(f, " {word:08x}")?;
+        }
+        Ok(())
+    }
+}
@@ -12,6 +12,59 @@ pub const DISPLAY_HEIGHT: usize = if cfg!(feature = "size_64x32") { 32 } else {
 /// Number of rows that need to be addressed (dual-scan panels use half)
 pub const ACTIVE_ROWS: usize = DISPLAY_HEIGHT / 2; // 32 rows (requires 5 address bits)
 
+/// Address lines driven, enough to address `ACTIVE_ROWS` rows
+pub const ADDRESS_LINES: u8 = ACTIVE_ROWS.trailing_zeros() as u8;
+
+/// Scan geometry of the panel: how many rows it addresses
+///
+/// A panel with `n` address lines lights rows `y` and `y + 2^n` together:
+/// it is "1/2^n scan" and `2 * 2^n` rows high, e.g. a 64x32 panel with
+/// lines A-D is 1/16 scan. The driver is built for `ACTIVE_ROWS` addresses
+/// and can scan fewer, for a panel with fewer address lines than the size
+/// feature expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PanelGeometry {
+    address_lines: u8,
+}
+
+impl PanelGeometry {
+    /// Every address line the driver has, the geometry of the size feature
+    pub const DEFAULT: Self = Self {
+        address_lines: ADDRESS_LINES,
+    };
+
+    /// Geometry of a panel with `lines` address lines, `None` if the driver
+    /// can't scan it (no lines, or more than `ADDRESS_LINES`)
+    pub const fn from_address_lines(lines: u8) -> Option<Self> {
+        if lines == 0 || lines > ADDRESS_LINES {
+            return None;
+        }
+        Some(Self {
+            address_lines: lines,
+        })
+    }
+
+    pub const fn address_lines(self) -> u8 {
+        self.address_lines
+    }
+
+    /// Rows addressed, which is also the scan factor (16 for 1/16 scan)
+    pub const fn scan_rows(self) -> usize {
+        1 << self.address_lines
+    }
+
+    /// Rows of the panel, two per address
+    pub const fn height(self) -> usize {
+        2 * self.scan_rows()
+    }
+}
+
+impl Default for PanelGeometry {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Color depth in bits (affects refresh rate vs color quality trade-off)
 pub const COLOR_BITS: usize = 8;
 
@@ -31,6 +84,102 @@ pub const fn compute_bcm_delays() -> [u32; COLOR_BITS] {
     delays
 }
 
+/// Compute BCM delays for the low-power mode, keeping the `planes` most
+/// significant bit planes
+///
+/// Dropped planes get a zero delay (shown for a single cycle) and the kept
+/// ones are shifted down, so output-enable time (and LED current) is divided
+/// by `2^(COLOR_BITS - planes)`.
+pub const fn compute_low_power_delays(planes: usize) -> [u32; COLOR_BITS] {
+    let planes = if planes == 0 {
+        1
+    } else if planes > COLOR_BITS {
+        COLOR_BITS
+    } else {
+        planes
+    };
+    let dropped = COLOR_BITS - planes;
+
+    let mut delays = [0u32; COLOR_BITS];
+    let mut i = dropped;
+    while i < COLOR_BITS {
+        delays[i] = (1 << (i - dropped)) - 1;
+        i += 1;
+    }
+    delays
+}
+
+/// Scale BCM delays by a global brightness level (255 keeps them as is)
+///
+/// Each plane is lit for `delay + 1` cycles of the OE state machine, and
+/// that on-time is what gets scaled. It can't go below one cycle, so at low
+/// levels the least significant planes end up with the same weight and
+/// level 0 is dim rather than black.
+pub const fn scale_delays(delays: [u32; COLOR_BITS], level: u8) -> [u32; COLOR_BITS] {
+    let mut scaled = [0u32; COLOR_BITS];
+    let mut i = 0;
+    while i < COLOR_BITS {
+        let on_time = (delays[i] + 1) * level as u32;
+        scaled[i] = ((on_time + 127) / 255).saturating_sub(1);
+        i += 1;
+    }
+    scaled
+}
+
+/// Fewest BCM planes kept by the adaptive plane count
+pub const MIN_ADAPTIVE_PLANES: usize = 4;
+
+/// BCM planes worth scanning at a global brightness level
+///
+/// Scaled by `level`, plane `b` is lit for `2^b * level / 255` cycles, and
+/// [`scale_delays`] rounds anything shorter than a cycle up to one: such a
+/// plane only skews the darkest shades while costing a full shift of its
+/// rows. They are dropped, down to [`MIN_ADAPTIVE_PLANES`]: 8 planes at full
+/// brightness, 7 from 128, 6 from 64, 5 from 32 and 4 below.
+pub const fn adaptive_planes(level: u8) -> usize {
+    let mut planes = COLOR_BITS;
+    while planes > MIN_ADAPTIVE_PLANES && (1u32 << (COLOR_BITS - planes)) * (level as u32) < 255 {
+        planes -= 1;
+    }
+    planes
+}
+
+/// Move the delays of the `planes` most significant planes to the front of
+/// the table, where the OE DMA reads them when only those planes are scanned
+pub const fn scanned_delays(delays: [u32; COLOR_BITS], planes: usize) -> [u32; COLOR_BITS] {
+    let dropped = COLOR_BITS - planes;
+    let mut scanned = [0u32; COLOR_BITS];
+    let mut i = 0;
+    while i < planes {
+        scanned[i] = delays[i + dropped];
+        i += 1;
+    }
+    scanned
+}
+
+/// Settings for the low-power refresh mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct LowPowerConfig {
+    /// Number of BCM planes kept (1 to COLOR_BITS)
+    pub planes: u8,
+    /// Factor applied to the PIO clock dividers (1 keeps the normal rate)
+    pub clock_slowdown: u8,
+}
+
+impl LowPowerConfig {
+    /// 4 planes at a quarter of the refresh rate: a dim but readable image
+    pub const DEFAULT: Self = Self {
+        planes: 4,
+        clock_slowdown: 4,
+    };
+}
+
+impl Default for LowPowerConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// PIO clock dividers for different state machines
 pub mod pio_clocks {
     use fixed_macro::__fixed::types::U24F8;
@@ -0,0 +1,82 @@
+//! Fades applied at the LUT stage
+//!
+//! `set_brightness` scales colors before gamma correction: dark channels
+//! drop to zero at different points, so colors drift and turn muddy halfway
+//! through a fade. A fade scales the gamma-corrected values instead, i.e.
+//! the LED duty cycles, by the same factor on every channel, which keeps
+//! hues while dimming. The fade level is squared into that factor, a cheap
+//! approximation of the eye's response, so a linear ramp looks even.
+
+/// End point of a timed fade
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum FadeTarget {
+    /// Fade out to a black panel
+    Black,
+    /// Fade in to the drawn frame at full level
+    Frame,
+}
+
+impl FadeTarget {
+    pub const fn level(self) -> u8 {
+        match self {
+            Self::Black => 0,
+            Self::Frame => u8::MAX,
+        }
+    }
+}
+
+/// Factor applied to gamma-corrected values at full level
+pub(crate) const FADE_FULL: u32 = u8::MAX as u32 * u8::MAX as u32;
+
+/// Linear-light factor of a fade level, out of [`FADE_FULL`]
+pub(crate) const fn fade_factor(level: u8) -> u32 {
+    level as u32 * level as u32
+}
+
+/// Scale a gamma-corrected value by a fade factor, rounding to nearest
+pub(crate) const fn apply_fade(value: u8, factor: u32) -> u8 {
+    ((value as u32 * factor + FADE_FULL / 2) / FADE_FULL) as u8
+}
+
+/// [`apply_fade`] for the 10-bit values of [`crate::lut::GAMMA10`]
+pub(crate) const fn apply_fade_fine(value: u16, factor: u32) -> u16 {
+    ((value as u32 * factor + FADE_FULL / 2) / FADE_FULL) as u16
+}
+
+/// Fade level ramp over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Fade {
+    from: u8,
+    to: u8,
+    start_ms: u64,
+    duration_ms: u32,
+}
+
+impl Fade {
+    /// Ramp from level `from` to `target` over `duration_ms`
+    pub const fn new(from: u8, target: FadeTarget, start_ms: u64, duration_ms: u32) -> Self {
+        Self {
+            from,
+            to: target.level(),
+            start_ms,
+            duration_ms,
+        }
+    }
+
+    /// Fade level at `now_ms`
+    pub const fn level(&self, now_ms: u64) -> u8 {
+        let elapsed = now_ms.saturating_sub(self.start_ms);
+        if self.duration_ms == 0 || elapsed >= self.duration_ms as u64 {
+            return self.to;
+        }
+
+        let from = self.from as i64;
+        let delta = self.to as i64 - from;
+        (from + delta * elapsed as i64 / self.duration_ms as i64) as u8
+    }
+
+    /// Check if the ramp reached its target
+    pub const fn is_done(&self, now_ms: u64) -> bool {
+        self.level(now_ms) == self.to
+    }
+}
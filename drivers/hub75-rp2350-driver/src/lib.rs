@@ -60,12 +60,21 @@ compile_error!("Cannot enable both size_64x32 and size_128x128");
 #[cfg(all(feature = "size_64x64", feature = "size_128x128"))]
 compile_error!("Cannot enable both size_64x64 and size_128x128");
 
+#[cfg(feature = "frame-capture")]
+pub mod capture;
 pub mod config;
 pub mod dma;
+pub mod fade;
+pub mod loopback;
 pub mod lut;
 pub mod memory;
 pub mod pio;
+#[cfg(feature = "frame-recording")]
+pub mod recording;
+pub mod vsync;
 
+#[cfg(feature = "frame-capture")]
+pub use capture::FrameCapture;
 pub use config::*;
 use core::convert::Infallible;
 use defmt::info;
@@ -80,12 +89,19 @@ use embedded_graphics_core::{
     geometry::{OriginDimensions, Size},
     pixelcolor::Rgb565,
 };
-pub use memory::DisplayMemory;
+pub use fade::{Fade, FadeTarget};
+pub use loopback::ChainLoopback;
+pub use matrix_driver::MatrixDriver;
+pub use memory::{BLANK_FRAME_HASH, DisplayMemory};
 pub use pio::Hub75StateMachines;
+#[cfg(feature = "frame-recording")]
+pub use recording::{RECORDED_HEIGHT, RECORDED_PIXELS, RECORDED_WIDTH};
+pub use vsync::VsyncInterruptHandler;
 
-// Bind PIO interrupts
+// Bind PIO interrupts, and the DMA one signalling the end of a refresh
 bind_interrupts!(struct Irqs {
     PIO0_IRQ_0 => InterruptHandler<PIO0>;
+    DMA_IRQ_1 => VsyncInterruptHandler;
 });
 
 /// High-performance Hub75 LED matrix driver
@@ -97,7 +113,7 @@ bind_interrupts!(struct Irqs {
 /// - Binary Color Modulation provides smooth color gradients
 pub struct Hub75<'d> {
     /// PIO state machines for Hub75 control
-    _state_machines: Hub75StateMachines<'d>,
+    state_machines: Hub75StateMachines<'d>,
 
     /// DMA channels (stored but consumed during setup)
     #[allow(dead_code)]
@@ -114,6 +130,51 @@ pub struct Hub75<'d> {
 
     /// Global brightness control (0-255)
     brightness: u8,
+
+    /// Brightness applied to the BCM delays (0-255)
+    global_brightness: u8,
+
+    /// Fade level applied after gamma correction (0-255)
+    fade: u8,
+
+    /// Fade in progress, started by `fade_to`
+    fade_ramp: Option<Fade>,
+
+    /// Temporal dithering, set with `set_dithering`
+    dithering: bool,
+
+    /// Frame of the dithering cycle, advanced on each commit
+    dither_frame: u8,
+
+    /// Active low-power settings, if any
+    low_power: Option<LowPowerConfig>,
+
+    /// Bit planes dropped at low global brightness, see `set_adaptive_planes`
+    adaptive_planes: bool,
+
+    /// Refresh stopped by `pause_refresh`
+    paused: bool,
+
+    /// Panel geometry set with `set_geometry`
+    geometry: PanelGeometry,
+
+    /// Consecutive commits dropped because nothing changed
+    unchanged_commits: u32,
+
+    /// Idle detection set with `set_idle_callback`
+    idle: Option<IdleDetection>,
+
+    /// First words of the frame handed to the DMA
+    #[cfg(feature = "frame-capture")]
+    capture: FrameCapture,
+}
+
+/// Callback fired when the display content stops or starts changing
+#[derive(Clone, Copy)]
+struct IdleDetection {
+    after_commits: u32,
+    callback: fn(bool),
+    idle: bool,
 }
 
 impl<'d> Hub75<'d> {
@@ -177,13 +238,26 @@ impl<'d> Hub75<'d> {
 
         // Create driver instance
         let driver = Self {
-            _state_machines: state_machines,
+            state_machines,
             dma_fb: dma_channels.0,
             dma_fb_loop: dma_channels.1,
             dma_oe: dma_channels.2,
             dma_oe_loop: dma_channels.3,
             memory,
             brightness: 255, // Full brightness by default
+            global_brightness: u8::MAX,
+            fade: u8::MAX,
+            fade_ramp: None,
+            dithering: false,
+            dither_frame: 0,
+            low_power: None,
+            adaptive_planes: false,
+            paused: false,
+            geometry: PanelGeometry::DEFAULT,
+            unchanged_commits: 0,
+            idle: None,
+            #[cfg(feature = "frame-capture")]
+            capture: FrameCapture::new(),
         };
 
         info!("Initializing Hub75 DMA channels...");
@@ -200,15 +274,93 @@ impl<'d> Hub75<'d> {
     /// * `y` - Y coordinate (0 to 63)
     /// * `color` - RGB565 color value
     pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb565) {
-        self.memory.set_pixel(x, y, color, self.brightness);
+        self.memory.set_pixel(
+            x,
+            y,
+            color,
+            self.brightness,
+            fade::fade_factor(self.fade),
+            self.dithering.then_some(self.dither_frame),
+        );
     }
 
     /// Commit the current drawing buffer (non-blocking)
     ///
     /// This swaps the double buffers, making the drawn frame visible
-    /// and providing a fresh buffer for the next frame.
-    pub fn commit(&mut self) {
-        self.memory.commit();
+    /// and providing a fresh buffer for the next frame. A frame identical
+    /// to the one on display is dropped (the buffers are compared by hash),
+    /// so redrawing static content costs no swap. Returns `true` if the
+    /// displayed frame changed.
+    pub fn commit(&mut self) -> bool {
+        let changed = self.memory.commit();
+        self.dither_frame = self.dither_frame.wrapping_add(1);
+        #[cfg(feature = "frame-capture")]
+        if changed {
+            self.capture.record(self.memory.get_active_buffer());
+        }
+        #[cfg(feature = "frame-recording")]
+        self.memory.drawn.commit(changed);
+        self.unchanged_commits = if changed {
+            0
+        } else {
+            self.unchanged_commits.saturating_add(1)
+        };
+
+        if let Some(detection) = &mut self.idle {
+            let idle = self.unchanged_commits >= detection.after_commits;
+            if idle != detection.idle {
+                detection.idle = idle;
+                (detection.callback)(idle);
+            }
+        }
+        changed
+    }
+
+    /// First words of the last committed frame, as read by the DMA
+    #[cfg(feature = "frame-capture")]
+    pub const fn capture(&self) -> &FrameCapture {
+        &self.capture
+    }
+
+    /// Pixels of the frame on display as drawn, row-major RGB565
+    ///
+    /// `RECORDED_WIDTH` x `RECORDED_HEIGHT`, see [`recording`].
+    #[cfg(feature = "frame-recording")]
+    pub const fn shown_frame(&self) -> &[u16; RECORDED_PIXELS] {
+        self.memory.drawn.shown()
+    }
+
+    /// Hash of the frame on display
+    ///
+    /// Changes with every committed frame that differs from the previous
+    /// one, so fleet monitoring can spot a panel stuck on the same frame or
+    /// showing [`BLANK_FRAME_HASH`]. Fades and brightness are applied as
+    /// pixels are drawn and change it too.
+    pub const fn frame_hash(&self) -> u32 {
+        self.memory.active_hash()
+    }
+
+    /// Check whether the frame on display is all black
+    pub const fn is_blank(&self) -> bool {
+        self.frame_hash() == BLANK_FRAME_HASH
+    }
+
+    /// Number of consecutive commits dropped because nothing changed
+    pub const fn unchanged_commits(&self) -> u32 {
+        self.unchanged_commits
+    }
+
+    /// Call `callback(true)` once `after_commits` consecutive commits were
+    /// dropped, and `callback(false)` on the next change
+    ///
+    /// Lets a power manager slow the render loop down or dim the panel while
+    /// the content is static. `None` disables idle detection.
+    pub fn set_idle_callback(&mut self, detection: Option<(u32, fn(bool))>) {
+        self.idle = detection.map(|(after_commits, callback)| IdleDetection {
+            after_commits: after_commits.max(1),
+            callback,
+            idle: false,
+        });
     }
 
     /// Clear the drawing buffer
@@ -217,6 +369,8 @@ impl<'d> Hub75<'d> {
     /// Call `commit()` to make the cleared display visible.
     pub fn clear(&mut self) {
         self.memory.clear();
+        #[cfg(feature = "frame-recording")]
+        self.memory.drawn.clear();
     }
 
     /// Get mutable access to the internal draw buffer
@@ -238,7 +392,8 @@ impl<'d> Hub75<'d> {
     /// Set overall brightness (0-255)
     ///
     /// This affects all subsequently drawn pixels.
-    /// Existing pixels in the buffer are not affected.
+    /// Existing pixels in the buffer are not affected; see
+    /// `set_global_brightness` to dim what is already displayed.
     pub const fn set_brightness(&mut self, brightness: u8) {
         self.brightness = brightness;
     }
@@ -248,6 +403,260 @@ impl<'d> Hub75<'d> {
         self.brightness
     }
 
+    /// Set the brightness of the whole panel (0-255), including the frame
+    /// already displayed
+    ///
+    /// Scales how long the output is enabled for each BCM plane, applying
+    /// from the next refresh without redrawing. Combines with
+    /// `set_brightness`, which dims the pixel data itself. See
+    /// [`scale_delays`] for the limits at low levels, and
+    /// `set_adaptive_planes` to skip the planes they affect.
+    pub fn set_global_brightness(&mut self, level: u8) {
+        self.global_brightness = level;
+        self.update_delays();
+    }
+
+    /// Get the brightness set with `set_global_brightness`
+    pub const fn global_brightness(&self) -> u8 {
+        self.global_brightness
+    }
+
+    /// Set the fade level (0 is black, 255 leaves colors untouched)
+    ///
+    /// Unlike `set_brightness`, the fade is applied after gamma correction,
+    /// keeping hues while dimming; see [`fade`]. Like the brightness, it
+    /// affects subsequently drawn pixels. Cancels a fade started by
+    /// `fade_to`.
+    pub const fn set_fade(&mut self, level: u8) {
+        self.fade = level;
+        self.fade_ramp = None;
+    }
+
+    /// Get the current fade level
+    pub const fn fade_level(&self) -> u8 {
+        self.fade
+    }
+
+    /// Fade from the current level to `target` over `duration_ms`
+    ///
+    /// Call `update_fade` before drawing each frame to follow the ramp.
+    pub const fn fade_to(&mut self, target: FadeTarget, duration_ms: u32, now_ms: u64) {
+        self.fade_ramp = Some(Fade::new(self.fade, target, now_ms, duration_ms));
+    }
+
+    /// Advance the fade started by `fade_to`
+    ///
+    /// Returns `true` while the fade is in progress.
+    pub fn update_fade(&mut self, now_ms: u64) -> bool {
+        let Some(ramp) = self.fade_ramp else {
+            return false;
+        };
+        self.fade = ramp.level(now_ms);
+        if ramp.is_done(now_ms) {
+            self.fade_ramp = None;
+        }
+        self.fade_ramp.is_some()
+    }
+
+    /// Check if a fade started by `fade_to` is in progress
+    pub const fn is_fading(&self) -> bool {
+        self.fade_ramp.is_some()
+    }
+
+    /// Enable temporal dithering, for about two more bits of depth in the
+    /// dark levels where gamma correction bands
+    ///
+    /// Gamma correction is done at 10 bits and the two bits that don't fit
+    /// in the BCM planes alternate the lowest plane across commits, see
+    /// [`lut::GAMMA10`]. It is baked in when drawing, so it needs a redraw
+    /// every frame at a steady rate, and those frames then rarely come out
+    /// identical: commits are seldom dropped and idle detection rarely
+    /// fires.
+    pub const fn set_dithering(&mut self, enabled: bool) {
+        self.dithering = enabled;
+    }
+
+    /// Check if temporal dithering is enabled
+    pub const fn is_dithering(&self) -> bool {
+        self.dithering
+    }
+
+    /// Enter or leave the low-power refresh mode
+    ///
+    /// Low-power mode keeps only the most significant BCM planes (cutting
+    /// output-enable time) and slows the PIO clocks down. It applies from
+    /// the next refresh, without restarting the display. `None` restores
+    /// normal refresh.
+    pub fn set_low_power(&mut self, config: Option<LowPowerConfig>) {
+        match config {
+            Some(config) => {
+                self.state_machines
+                    .set_clock_slowdown(config.clock_slowdown);
+                info!(
+                    "Low-power refresh: {} planes, clock / {}",
+                    config.planes, config.clock_slowdown
+                );
+            }
+            None => {
+                self.state_machines.set_clock_slowdown(1);
+                info!("Normal refresh");
+            }
+        }
+        self.low_power = config;
+        self.update_delays();
+    }
+
+    /// Write the BCM delays of the refresh mode, scaled by the global
+    /// brightness, to the table the OE DMA reads
+    ///
+    /// The number of scanned planes follows the brightness when
+    /// `set_adaptive_planes` is enabled.
+    fn update_delays(&mut self) {
+        let delays = match self.low_power {
+            Some(config) => compute_low_power_delays(config.planes as usize),
+            None => compute_bcm_delays(),
+        };
+        let planes = if self.adaptive_planes {
+            adaptive_planes(self.global_brightness)
+        } else {
+            COLOR_BITS
+        };
+        let delays = scanned_delays(scale_delays(delays, self.global_brightness), planes);
+
+        if planes == self.memory.planes() {
+            self.memory.delays = delays;
+            return;
+        }
+
+        let paused = self.paused;
+        self.pause_refresh();
+        self.memory.delays = delays;
+        self.memory.set_planes(planes);
+        self.state_machines.set_planes(planes);
+        if !paused {
+            self.resume_refresh();
+        }
+        info!("Scanning {} bit planes", planes);
+    }
+
+    /// Drop the least significant BCM planes at low global brightness
+    ///
+    /// Below full brightness, the lowest planes can be lit for less than a
+    /// cycle, which is rounded up to one; see [`adaptive_planes`] for
+    /// the planes kept at each level. Skipping them shortens every row,
+    /// raising the refresh rate and cutting the time spent shifting data.
+    /// Changing the number of planes restarts refresh with both buffers
+    /// cleared, so the frame must be drawn again, like after
+    /// `set_geometry`.
+    pub fn set_adaptive_planes(&mut self, enabled: bool) {
+        self.adaptive_planes = enabled;
+        self.update_delays();
+    }
+
+    /// Check if the plane count follows the global brightness
+    pub const fn is_adaptive_planes(&self) -> bool {
+        self.adaptive_planes
+    }
+
+    /// Bit planes currently scanned for each row
+    pub const fn scanned_planes(&self) -> usize {
+        self.memory.planes()
+    }
+
+    /// Get the active low-power settings
+    pub const fn low_power(&self) -> Option<LowPowerConfig> {
+        self.low_power
+    }
+
+    /// Stop refreshing the panel, leaving it blank
+    ///
+    /// The state machines are halted with the output disabled and the DMA
+    /// chain is aborted, so neither runs until `resume_refresh`. Drawing
+    /// and `commit` keep working while paused.
+    pub fn pause_refresh(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        self.state_machines.halt();
+        Self::abort_dma();
+        self.paused = true;
+    }
+
+    /// Restart refresh after `pause_refresh`
+    ///
+    /// Scanning starts again from the first row of the active buffer.
+    pub fn resume_refresh(&mut self) {
+        if !self.paused {
+            return;
+        }
+
+        self.state_machines.restart();
+        self.setup_dma();
+        self.paused = false;
+    }
+
+    /// Check if refresh is paused
+    pub const fn is_refresh_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Scan a panel with a different geometry than the size feature's
+    ///
+    /// Refresh is stopped while the row SM and DMA are set up for the new
+    /// number of rows, and both buffers are cleared. Pixels below the
+    /// panel height are dropped from then on.
+    pub fn set_geometry(&mut self, geometry: PanelGeometry) {
+        let paused = self.paused;
+        self.pause_refresh();
+        self.memory.set_scan_rows(geometry.scan_rows());
+        self.state_machines.set_scan_rows(geometry.scan_rows());
+        self.geometry = geometry;
+        if !paused {
+            self.resume_refresh();
+        }
+        info!(
+            "Panel geometry: {} address lines, 1/{} scan",
+            geometry.address_lines(),
+            geometry.scan_rows()
+        );
+    }
+
+    /// Get the scanned panel geometry
+    pub const fn geometry(&self) -> PanelGeometry {
+        self.geometry
+    }
+
+    /// Wait until the panel finishes its current refresh
+    ///
+    /// Wakes up on the DMA interrupt fired each time the whole frame has
+    /// been streamed, so drawing and `commit` can be paced on the panel
+    /// refresh instead of a timer. Returns at once while refresh is paused.
+    pub async fn wait_for_vsync(&self) {
+        if self.paused {
+            return;
+        }
+        vsync::wait_for_frame().await;
+    }
+
+    /// Number of refreshes completed since the driver started, wrapping
+    pub fn refresh_count(&self) -> u32 {
+        vsync::frame_count()
+    }
+
+    /// Sample the chain output, looped back to three consecutive GPIOs
+    ///
+    /// See [`loopback`]. Uses the spare state machine of PIO0, so this
+    /// returns `None` after the first call.
+    pub fn chain_loopback(
+        &mut self,
+        r_pin: Peri<'d, impl PioPin>,
+        g_pin: Peri<'d, impl PioPin>,
+        b_pin: Peri<'d, impl PioPin>,
+    ) -> Option<ChainLoopback<'d>> {
+        self.state_machines.take_loopback(r_pin, g_pin, b_pin)
+    }
+
     /// Draw a test pattern for verification
     ///
     /// Creates a colorful test pattern to verify correct operation:
@@ -294,6 +703,18 @@ impl<'d> Hub75<'d> {
         }
     }
 
+    /// Stop the chained DMA channels
+    fn abort_dma() {
+        let dma = embassy_rp::pac::DMA;
+
+        // Disable first so the loop channels can't retrigger an aborted channel
+        for ch in 0..4 {
+            dma.ch(ch).ctrl_trig().modify(|w| w.set_en(false));
+        }
+        dma.chan_abort().write(|w| w.set_chan_abort(0b1111));
+        while dma.chan_abort().read().chan_abort() != 0 {}
+    }
+
     /// Setup DMA channels (CRITICAL: matches original exactly)
     fn setup_dma(&self) {
         use embassy_rp::pac::dma::regs::{ChTransCount, CtrlTrig};
@@ -324,7 +745,7 @@ impl<'d> Hub75<'d> {
         dma.ch(0).read_addr().write_value(self.memory.fb_ptr as u32);
         dma.ch(0)
             .trans_count()
-            .write_value(ChTransCount((FRAME_SIZE / 4) as u32));
+            .write_value(ChTransCount((self.memory.scanned_size() / 4) as u32));
         dma.ch(0).write_addr().write_value(data_fifo_addr);
 
         let mut ch1_ctrl = CtrlTrig(0);
@@ -333,7 +754,7 @@ impl<'d> Hub75<'d> {
         ch1_ctrl.set_data_size(DataSize::SIZE_WORD);
         ch1_ctrl.set_treq_sel(TreqSel::PERMANENT);
         ch1_ctrl.set_chain_to(0);
-        ch1_ctrl.set_irq_quiet(true);
+        ch1_ctrl.set_irq_quiet(false); // Ends a refresh, see `vsync`
         ch1_ctrl.set_en(false); // Don't enable yet
         // Channel 1: Reset channel 0's read address
         dma.ch(1).al1_ctrl().write_value(ch1_ctrl.0);
@@ -365,7 +786,7 @@ impl<'d> Hub75<'d> {
         dma.ch(2).write_addr().write_value(oe_fifo_addr);
         dma.ch(2)
             .trans_count()
-            .write_value(ChTransCount(COLOR_BITS as u32));
+            .write_value(ChTransCount(self.memory.planes() as u32));
 
         // Channel 3: Reset channel 2's read address
         let mut ch3_ctrl = CtrlTrig(0);
@@ -388,6 +809,8 @@ impl<'d> Hub75<'d> {
             .write_value(dma.ch(2).read_addr().as_ptr() as u32);
         dma.ch(3).trans_count().write_value(ChTransCount(1));
 
+        vsync::enable();
+
         // Enable all channels
         dma.ch(1).ctrl_trig().modify(|w| w.set_en(true));
         dma.ch(3).ctrl_trig().modify(|w| w.set_en(true));
@@ -413,6 +836,8 @@ impl<'d> DrawTarget for Hub75<'d> {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for Pixel(mut point, color) in pixels {
+            #[cfg(feature = "frame-recording")]
+            self.memory.drawn.set_pixel(point, color);
             #[cfg(feature = "size_128x128")]
             {
                 if point.x >= 128 || point.y >= 128 || point.y < 0 || point.x < 0 {
@@ -426,6 +851,25 @@ impl<'d> DrawTarget for Hub75<'d> {
     }
 }
 
+impl<'d> MatrixDriver for Hub75<'d> {
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn commit(&mut self) {
+        self.commit();
+    }
+
+    /// Applies to the pixels drawn afterwards, see `Hub75::set_brightness`
+    fn set_brightness(&mut self, brightness: u8) {
+        self.set_brightness(brightness);
+    }
+
+    fn brightness(&self) -> u8 {
+        self.brightness
+    }
+}
+
 const fn coord_transfer(point: &mut Point) {
     if point.y < 64 {
         point.x += 128
@@ -0,0 +1,101 @@
+//! Chain output loopback for the daisy-chain integrity check
+//!
+//! With a jumper from the output connector of the last panel back to three
+//! consecutive GPIOs, the spare state machine of PIO0 samples the data that
+//! went through the whole chain: the R2, G2 and B2 lines, which carry the
+//! bottom half and thus the last pixel row. It samples as the pixel clock
+//! falls, when the shift registers of the panels have settled.
+//!
+//! The checksum row to draw and the check of the samples are in
+//! `graphics_common::diagnostics::chain`:
+//!
+//! ```ignore
+//! let mut loopback = display.chain_loopback(p.PIN_26, p.PIN_27, p.PIN_28).unwrap();
+//! draw_chain_frame(&mut display, counter)?;
+//! display.commit();
+//! loopback.capture(p.DMA_CH4.reborrow(), &mut words).await;
+//! let outcome = chain::verify(samples(&words)).outcome(counter);
+//! ```
+
+use crate::config::FRAME_SIZE;
+use embassy_rp::Peri;
+use embassy_rp::dma::Channel;
+use embassy_rp::peripherals::PIO0;
+use embassy_rp::pio::program::pio_asm;
+use embassy_rp::pio::{
+    Common, Config, Direction, FifoJoin, Pin, ShiftConfig, ShiftDirection, StateMachine,
+};
+
+/// Samples packed in each captured word, 3 bits each
+pub const SAMPLES_PER_WORD: usize = 10;
+
+/// Words holding the samples of a whole refresh
+///
+/// A capture at least this long contains every row of the frame, the
+/// checksum row included.
+pub const REFRESH_WORDS: usize = FRAME_SIZE.div_ceil(SAMPLES_PER_WORD);
+
+/// Sampler of the chain output, from `Hub75::chain_loopback`
+pub struct ChainLoopback<'d> {
+    sm: StateMachine<'d, PIO0, 3>,
+}
+
+impl<'d> ChainLoopback<'d> {
+    pub(crate) fn new(
+        common: &mut Common<'d, PIO0>,
+        mut sm: StateMachine<'d, PIO0, 3>,
+        return_pins: &[Pin<'d, PIO0>; 3],
+        clk_pin: &Pin<'d, PIO0>,
+    ) -> Self {
+        let program = pio_asm!(
+            ".wrap_target",
+            "wait_high:",
+            "jmp pin wait_low", // Wait for the pixel clock to rise
+            "jmp wait_high",
+            "wait_low:",
+            "jmp pin wait_low", // Then to fall
+            "in pins, 3",       // Sample R2, G2 and B2
+            ".wrap",
+        );
+        let installed = common.load_program(&program.program);
+
+        let pin_refs = [&return_pins[0], &return_pins[1], &return_pins[2]];
+        let mut cfg = Config::default();
+        cfg.use_program(&installed, &[]);
+        cfg.set_in_pins(&pin_refs);
+        cfg.set_jmp_pin(clk_pin);
+        cfg.fifo_join = FifoJoin::RxOnly;
+        cfg.shift_in = ShiftConfig {
+            auto_fill: true,
+            threshold: (3 * SAMPLES_PER_WORD) as u8,
+            direction: ShiftDirection::Left,
+        };
+        // Full speed, to follow the data SM's clock
+        sm.set_config(&cfg);
+        sm.set_pin_dirs(Direction::In, &pin_refs);
+
+        Self { sm }
+    }
+
+    /// Fill `words` with samples of the chain output
+    ///
+    /// The refresh must be running, sampling follows its pixel clock. Use
+    /// [`samples`] to unpack the words, and at least [`REFRESH_WORDS`] of
+    /// them to see the whole frame.
+    pub async fn capture(&mut self, dma: Peri<'_, impl Channel>, words: &mut [u32]) {
+        self.sm.clear_fifos();
+        self.sm.restart();
+        self.sm.set_enable(true);
+        self.sm.rx().dma_pull(dma, words, false).await;
+        self.sm.set_enable(false);
+    }
+}
+
+/// Unpack captured words into samples, oldest first, R2 in bit 0
+pub fn samples(words: &[u32]) -> impl Iterator<Item = u8> + '_ {
+    words.iter().flat_map(|&word| {
+        (0..SAMPLES_PER_WORD)
+            .rev()
+            .map(move |i| (word >> (3 * i)) as u8 & 0b111)
+    })
+}
@@ -1,7 +1,10 @@
 //! Display memory management with double buffering
 
 use crate::config::*;
-use crate::lut::GAMMA8;
+use crate::fade::{apply_fade, apply_fade_fine};
+use crate::lut::{GAMMA8, GAMMA10, dither, dither_threshold};
+#[cfg(feature = "frame-recording")]
+use crate::recording::DrawnFrames;
 use core::mem::MaybeUninit;
 use embedded_graphics_core::pixelcolor::Rgb565;
 use embedded_graphics_core::prelude::RgbColor;
@@ -10,6 +13,8 @@ use embedded_graphics_core::prelude::RgbColor;
 ///
 /// The memory layout is optimized for the PIO+DMA scanning pattern:
 /// - Data is arranged as \[row]\[bit_plane]\[column]
+/// - Only the most significant `planes` bit planes are stored, see
+///   [`crate::Hub75::set_adaptive_planes`]
 /// - Each byte contains packed RGB data for 2 pixels (top/bottom half)
 /// - Double buffering allows drawing while previous frame displays
 pub struct DisplayMemory {
@@ -30,6 +35,19 @@ pub struct DisplayMemory {
 
     /// Which buffer is currently active (false = fb0, true = fb1)
     current_buffer: bool,
+
+    /// Hash of the active buffer, to skip commits of identical frames
+    active_hash: u32,
+
+    /// Rows addressed by the panel, see [`PanelGeometry`]
+    scan_rows: usize,
+
+    /// Most significant bit planes stored and scanned
+    planes: usize,
+
+    /// Frames as drawn, for the frame recorder
+    #[cfg(feature = "frame-recording")]
+    pub drawn: DrawnFrames,
 }
 
 impl Default for DisplayMemory {
@@ -70,6 +88,14 @@ impl DisplayMemory {
                 core::ptr::null_mut(),
             );
             core::ptr::write(core::ptr::addr_of_mut!((*ptr).current_buffer), false);
+            core::ptr::write(
+                core::ptr::addr_of_mut!((*ptr).active_hash),
+                frame_hash(&(*ptr).fb0),
+            );
+            core::ptr::write(core::ptr::addr_of_mut!((*ptr).scan_rows), ACTIVE_ROWS);
+            core::ptr::write(core::ptr::addr_of_mut!((*ptr).planes), COLOR_BITS);
+            #[cfg(feature = "frame-recording")]
+            core::ptr::write(core::ptr::addr_of_mut!((*ptr).drawn), DrawnFrames::new());
 
             memory.assume_init()
         }
@@ -81,11 +107,65 @@ impl DisplayMemory {
         self.delay_ptr = self.delays.as_mut_ptr();
     }
 
+    /// Rows addressed by the panel
+    pub const fn scan_rows(&self) -> usize {
+        self.scan_rows
+    }
+
+    /// Map pixels for a panel addressing `rows` rows
+    ///
+    /// Only the first `rows` rows of each buffer are scanned, so the DMA
+    /// must be set up again for the new frame length.
+    pub(crate) fn set_scan_rows(&mut self, rows: usize) {
+        self.scan_rows = rows.clamp(1, ACTIVE_ROWS);
+        self.clear_buffers();
+    }
+
+    /// Bit planes stored for each row
+    pub const fn planes(&self) -> usize {
+        self.planes
+    }
+
+    /// Keep only the `planes` most significant bit planes of each color
+    ///
+    /// Rows get shorter, so like [`Self::set_scan_rows`] the DMA must be set
+    /// up again for the new frame length.
+    pub(crate) fn set_planes(&mut self, planes: usize) {
+        self.planes = planes.clamp(1, COLOR_BITS);
+        self.clear_buffers();
+    }
+
+    /// Blank both buffers after a layout change
+    fn clear_buffers(&mut self) {
+        self.fb0.fill(0);
+        self.fb1.fill(0);
+        self.active_hash = BLANK_FRAME_HASH;
+    }
+
+    /// Bytes of a buffer streamed for each refresh
+    pub const fn scanned_size(&self) -> usize {
+        self.scan_rows * self.planes * DISPLAY_WIDTH
+    }
+
+    /// Hash of the frame on display, see [`BLANK_FRAME_HASH`]
+    pub const fn active_hash(&self) -> u32 {
+        self.active_hash
+    }
+
     /// Commit the drawn buffer and make it active for display
     ///
     /// This swaps the buffers so the newly drawn frame becomes visible
-    /// while the old frame buffer becomes available for drawing
-    pub fn commit(&mut self) {
+    /// while the old frame buffer becomes available for drawing. A frame
+    /// identical to the one on display is dropped instead, leaving the
+    /// active buffer untouched. Returns `true` if the buffers were swapped.
+    pub fn commit(&mut self) -> bool {
+        let hash = frame_hash(self.get_draw_buffer());
+        if hash == self.active_hash {
+            self.get_draw_buffer().fill(0);
+            return false;
+        }
+        self.active_hash = hash;
+
         // Switch buffers
         self.current_buffer = !self.current_buffer;
 
@@ -98,6 +178,7 @@ impl DisplayMemory {
 
         // Clear the new draw buffer for next frame
         self.get_draw_buffer().fill(0);
+        true
     }
 
     /// Get the currently inactive buffer for drawing
@@ -109,6 +190,15 @@ impl DisplayMemory {
         }
     }
 
+    /// Get the buffer currently scanned out by the DMA
+    pub const fn get_active_buffer(&self) -> &[u8; FRAME_SIZE] {
+        if self.current_buffer {
+            &self.fb1
+        } else {
+            &self.fb0
+        }
+    }
+
     /// Get mutable access to the draw buffer for direct writes
     ///
     /// This provides low-level access to the internal framebuffer.
@@ -124,16 +214,27 @@ impl DisplayMemory {
     ///
     /// # Arguments
     /// * `x` - X coordinate (0 to DISPLAY_WIDTH-1)
-    /// * `y` - Y coordinate (0 to DISPLAY_HEIGHT-1)
+    /// * `y` - Y coordinate (0 to twice the scanned rows, at most DISPLAY_HEIGHT-1)
     /// * `color` - RGB565 color value
     /// * `brightness` - Global brightness multiplier (0-255)
-    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb565, brightness: u8) {
-        if x >= DISPLAY_WIDTH || y >= DISPLAY_HEIGHT {
+    /// * `fade` - Factor applied after gamma correction, see [`crate::fade`]
+    /// * `dither_frame` - Frame of the temporal dithering cycle, `None` to
+    ///   show the gamma-corrected values as they are
+    pub fn set_pixel(
+        &mut self,
+        x: usize,
+        y: usize,
+        color: Rgb565,
+        brightness: u8,
+        fade: u32,
+        dither_frame: Option<u8>,
+    ) {
+        if x >= DISPLAY_WIDTH || y >= DISPLAY_HEIGHT || y >= 2 * self.scan_rows {
             return;
         }
 
         // Half of the screen
-        let h = y > (DISPLAY_HEIGHT / 2) - 1;
+        let h = y >= self.scan_rows;
         let shift = if h { 3 } else { 0 };
 
         let mut c_r: u16;
@@ -154,19 +255,30 @@ impl DisplayMemory {
             c_r = (((color.b() << 3) as f32) * (brightness as f32 / 255f32)) as u16;
         }
 
-        let base_idx = x + ((y % (DISPLAY_HEIGHT / 2)) * DISPLAY_WIDTH * COLOR_BITS);
+        let base_idx = x + ((y % self.scan_rows) * DISPLAY_WIDTH * self.planes);
 
-        c_r = GAMMA8[c_r as usize] as u16;
-        c_g = GAMMA8[c_g as usize] as u16;
-        c_b = GAMMA8[c_b as usize] as u16;
+        let correct = |c: u16| -> u16 {
+            match dither_frame {
+                Some(frame) => {
+                    let fine = apply_fade_fine(GAMMA10[c as usize], fade);
+                    dither(fine, dither_threshold(x, y, frame)).into()
+                }
+                None => apply_fade(GAMMA8[c as usize], fade).into(),
+            }
+        };
+        c_r = correct(c_r);
+        c_g = correct(c_g);
+        c_b = correct(c_b);
 
-        for b in 0..COLOR_BITS {
+        let dropped = COLOR_BITS - self.planes;
+        for plane in 0..self.planes {
             // Extract the n-th bit of each component of the color and pack them
+            let b = plane + dropped;
             let cr = (c_r >> b) & 0b1;
             let cg = (c_g >> b) & 0b1;
             let cb = (c_b >> b) & 0b1;
             let packed_rgb = (cb << 2 | cg << 1 | cr) as u8;
-            let idx = base_idx + b * DISPLAY_WIDTH;
+            let idx = base_idx + plane * DISPLAY_WIDTH;
 
             // Use current_buffer flag instead of pointer comparison
             let draw_buffer = if self.current_buffer {
@@ -209,3 +321,25 @@ impl DisplayMemory {
 // Safety: DisplayMemory contains only plain data and atomic operations
 unsafe impl Send for DisplayMemory {}
 unsafe impl Sync for DisplayMemory {}
+
+/// FNV-1a offset basis and prime
+const FNV_OFFSET: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// Hash of an all-black frame
+///
+/// Black pixels are stored as zeros, which leave the hash untouched but for
+/// the multiplication by the prime, once per word.
+pub const BLANK_FRAME_HASH: u32 =
+    FNV_OFFSET.wrapping_mul(FNV_PRIME.wrapping_pow((FRAME_SIZE / 4) as u32));
+
+/// FNV-1a hash of a frame, a word at a time
+///
+/// Only used to tell frames apart, so hashing words instead of bytes is fine
+/// and four times cheaper on the full frame.
+fn frame_hash(frame: &[u8; FRAME_SIZE]) -> u32 {
+    frame.chunks_exact(4).fold(FNV_OFFSET, |hash, word| {
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        (hash ^ word).wrapping_mul(FNV_PRIME)
+    })
+}
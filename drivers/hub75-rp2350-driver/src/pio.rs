@@ -1,11 +1,13 @@
 //! PIO state machine programs and configuration for Hub75 scanning
 
 use crate::config::*;
+use crate::loopback::ChainLoopback;
 use defmt::error;
 use embassy_rp::Peri;
 use embassy_rp::pio::program::pio_asm;
 use embassy_rp::pio::{
-    Config, Direction, FifoJoin::TxOnly, Pio, PioPin, ShiftConfig, ShiftDirection, StateMachine,
+    Common, Config, Direction, FifoJoin::TxOnly, Pio, PioPin, ShiftConfig, ShiftDirection,
+    StateMachine,
 };
 
 /// PIO state machines for Hub75 control
@@ -18,8 +20,22 @@ pub struct Hub75StateMachines<'d> {
     pub data_sm: StateMachine<'d, embassy_rp::peripherals::PIO0, 0>,
     pub row_sm: StateMachine<'d, embassy_rp::peripherals::PIO0, 1>,
     pub oe_sm: StateMachine<'d, embassy_rp::peripherals::PIO0, 2>,
+    /// Program start addresses, used to restart the programs from the top
+    origins: [u8; 3],
+    /// Kept to load the chain loopback program later
+    common: Common<'d, embassy_rp::peripherals::PIO0>,
+    clk_pin: embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>,
+    /// Spare state machine, until taken by the chain loopback
+    spare_sm: Option<StateMachine<'d, embassy_rp::peripherals::PIO0, 3>>,
+    /// Rows scanned by the row SM, pushed when the programs start
+    scan_rows: usize,
+    /// Bit planes scanned per row, pushed along with `scan_rows`
+    planes: usize,
 }
 
+/// `nop side 1` (`mov y, y` with the single side-set bit high)
+const NOP_SIDE_1: u16 = 0xB042;
+
 impl<'d> Hub75StateMachines<'d> {
     /// Initialize all three state machines with their programs
     #[allow(clippy::too_many_arguments)]
@@ -46,6 +62,7 @@ impl<'d> Hub75StateMachines<'d> {
             mut sm0,
             mut sm1,
             mut sm2,
+            sm3,
             ..
         } = Pio::new(pio, crate::Irqs);
 
@@ -79,18 +96,24 @@ impl<'d> Hub75StateMachines<'d> {
         // - IRQ 7: OE SM signals row SM that timing is complete
 
         // Setup Data State Machine (SM0)
-        Self::setup_data_sm(&mut common, &mut sm0, &data_pins, &clk_pio_pin);
+        let data_origin = Self::setup_data_sm(&mut common, &mut sm0, &data_pins, &clk_pio_pin);
 
         // Setup Row State Machine (SM1)
-        Self::setup_row_sm(&mut common, &mut sm1, &addr_pins, &lat_pio_pin);
+        let row_origin = Self::setup_row_sm(&mut common, &mut sm1, &addr_pins, &lat_pio_pin);
 
         // Setup Output Enable State Machine (SM2)
-        Self::setup_oe_sm(&mut common, &mut sm2, &oe_pio_pin);
+        let oe_origin = Self::setup_oe_sm(&mut common, &mut sm2, &oe_pio_pin);
 
         Self {
             data_sm: sm0,
             row_sm: sm1,
             oe_sm: sm2,
+            origins: [data_origin, row_origin, oe_origin],
+            common,
+            clk_pin: clk_pio_pin,
+            spare_sm: Some(sm3),
+            scan_rows: ACTIVE_ROWS,
+            planes: COLOR_BITS,
         }
     }
 
@@ -106,7 +129,7 @@ impl<'d> Hub75StateMachines<'d> {
         sm: &mut StateMachine<'d, embassy_rp::peripherals::PIO0, 0>,
         data_pins: &[embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>; 6],
         clk_pin: &embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>,
-    ) {
+    ) -> u8 {
         let data_program = pio_asm!(
             ".side_set 1",
             "out isr, 32    side 0b0", // Get width-1 and store in ISR
@@ -150,7 +173,12 @@ impl<'d> Hub75StateMachines<'d> {
         sm.set_pin_dirs(Direction::Out, &data_pin_refs);
         sm.set_pin_dirs(Direction::Out, &[clk_pin]);
 
-        // Send display width-1 to data SM
+        Self::push_data_params(sm);
+        data_installed.origin
+    }
+
+    /// Send display width-1 to data SM
+    fn push_data_params(sm: &mut StateMachine<'d, embassy_rp::peripherals::PIO0, 0>) {
         if !sm.tx().try_push((DISPLAY_WIDTH - 1) as u32) {
             error!("Failed to push display width to data SM");
         }
@@ -167,7 +195,7 @@ impl<'d> Hub75StateMachines<'d> {
         sm: &mut StateMachine<'d, embassy_rp::peripherals::PIO0, 1>,
         addr_pins: &[embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>; 5],
         lat_pin: &embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>,
-    ) {
+    ) -> u8 {
         let row_program = pio_asm!(
             ".side_set 1",
             "pull           side 0b0", // Pull active_rows-1
@@ -212,12 +240,21 @@ impl<'d> Hub75StateMachines<'d> {
         sm.set_pin_dirs(Direction::Out, &addr_pin_refs);
         sm.set_pin_dirs(Direction::Out, &[lat_pin]);
 
-        // Send parameters to row SM
-        if !sm.tx().try_push((ACTIVE_ROWS - 1) as u32) {
+        Self::push_row_params(sm, ACTIVE_ROWS, COLOR_BITS);
+        row_installed.origin
+    }
+
+    /// Send parameters to row SM
+    fn push_row_params(
+        sm: &mut StateMachine<'d, embassy_rp::peripherals::PIO0, 1>,
+        scan_rows: usize,
+        planes: usize,
+    ) {
+        if !sm.tx().try_push((scan_rows - 1) as u32) {
             error!("Failed to push active rows to row SM");
         }
 
-        if !sm.tx().try_push((COLOR_BITS - 1) as u32) {
+        if !sm.tx().try_push((planes - 1) as u32) {
             error!("Failed to push color bits to row SM");
         }
     }
@@ -232,7 +269,7 @@ impl<'d> Hub75StateMachines<'d> {
         common: &mut embassy_rp::pio::Common<'d, embassy_rp::peripherals::PIO0>,
         sm: &mut StateMachine<'d, embassy_rp::peripherals::PIO0, 2>,
         oe_pin: &embassy_rp::pio::Pin<'d, embassy_rp::peripherals::PIO0>,
-    ) {
+    ) -> u8 {
         let oe_program = pio_asm!(
             ".side_set 1",
             ".wrap_target",
@@ -260,6 +297,32 @@ impl<'d> Hub75StateMachines<'d> {
 
         // Configure pin direction
         sm.set_pin_dirs(Direction::Out, &[oe_pin]);
+
+        oe_installed.origin
+    }
+
+    /// Set the spare state machine up to sample the chain output
+    ///
+    /// The red, green and blue return pins must be consecutive GPIOs.
+    /// Returns `None` once the state machine was taken.
+    pub(crate) fn take_loopback(
+        &mut self,
+        r_pin: Peri<'d, impl PioPin>,
+        g_pin: Peri<'d, impl PioPin>,
+        b_pin: Peri<'d, impl PioPin>,
+    ) -> Option<ChainLoopback<'d>> {
+        let sm = self.spare_sm.take()?;
+        let pins = [
+            self.common.make_pio_pin(r_pin),
+            self.common.make_pio_pin(g_pin),
+            self.common.make_pio_pin(b_pin),
+        ];
+        Some(ChainLoopback::new(
+            &mut self.common,
+            sm,
+            &pins,
+            &self.clk_pin,
+        ))
     }
 
     /// Start all state machines
@@ -275,4 +338,67 @@ impl<'d> Hub75StateMachines<'d> {
         self.row_sm.set_enable(false);
         self.oe_sm.set_enable(false);
     }
+
+    /// Slow all state machines down by `factor` (1 restores the normal rate)
+    pub fn set_clock_slowdown(&mut self, factor: u8) {
+        let factor = u32::from(factor.max(1));
+        self.data_sm
+            .set_clock_divider(pio_clocks::DATA_SM_CLOCK_DIV.saturating_mul_int(factor));
+        self.row_sm
+            .set_clock_divider(pio_clocks::ROW_SM_CLOCK_DIV.saturating_mul_int(factor));
+        self.oe_sm
+            .set_clock_divider(pio_clocks::OE_SM_CLOCK_DIV.saturating_mul_int(factor));
+
+        self.data_sm.clkdiv_restart();
+        self.row_sm.clkdiv_restart();
+        self.oe_sm.clkdiv_restart();
+    }
+
+    /// Scan `rows` rows from the next [`restart`](Self::restart)
+    pub fn set_scan_rows(&mut self, rows: usize) {
+        self.scan_rows = rows.clamp(1, ACTIVE_ROWS);
+    }
+
+    /// Scan `planes` bit planes per row from the next [`restart`](Self::restart)
+    pub fn set_planes(&mut self, planes: usize) {
+        self.planes = planes.clamp(1, COLOR_BITS);
+    }
+
+    /// Stop all state machines with the output disabled
+    ///
+    /// The OE SM can be stopped in the middle of a BCM delay with the
+    /// output enabled, which would leave one row lit.
+    pub fn halt(&mut self) {
+        self.stop();
+        // SAFETY: the state machine is stopped, the instruction only drives OE high
+        unsafe { self.oe_sm.exec_instr(NOP_SIDE_1) };
+    }
+
+    /// Restart the programs from the top after [`halt`](Self::halt)
+    ///
+    /// FIFOs and the inter-SM IRQ flags are cleared and the start-up
+    /// parameters pushed again. DMA must be set up again before data flows.
+    pub fn restart(&mut self) {
+        // Clear IRQ 4-7 used for handshaking between the state machines
+        embassy_rp::pac::PIO0.irq().write(|w| w.set_irq(0xF0));
+
+        let [data_origin, row_origin, oe_origin] = self.origins;
+
+        self.data_sm.clear_fifos();
+        self.data_sm.restart();
+        // SAFETY: jumping to the start of our own program on a stopped SM
+        unsafe { self.data_sm.exec_jmp(data_origin) };
+        Self::push_data_params(&mut self.data_sm);
+
+        self.row_sm.clear_fifos();
+        self.row_sm.restart();
+        unsafe { self.row_sm.exec_jmp(row_origin) };
+        Self::push_row_params(&mut self.row_sm, self.scan_rows, self.planes);
+
+        self.oe_sm.clear_fifos();
+        self.oe_sm.restart();
+        unsafe { self.oe_sm.exec_jmp(oe_origin) };
+
+        self.start();
+    }
 }
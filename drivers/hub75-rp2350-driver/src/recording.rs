@@ -0,0 +1,73 @@
+//! Copy of the frames as drawn, for the frame recorder
+//!
+//! With the `frame-recording` feature, pixels drawn through
+//! `embedded-graphics` are also kept in RGB565, before gamma correction,
+//! brightness and fade, and in the coordinates they were drawn at (128x128
+//! for the chained 128x128 layout). The copy follows the double buffering:
+//! [`DrawnFrames::shown`] is the frame on display, which the firmware hands
+//! to `cluster_core::recording::FrameRing` after each commit that changed
+//! it.
+
+use crate::config::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::pixelcolor::raw::RawU16;
+use embedded_graphics_core::prelude::{Point, RawData};
+
+/// Width of the recorded frames
+pub const RECORDED_WIDTH: usize = if cfg!(feature = "size_128x128") {
+    128
+} else {
+    DISPLAY_WIDTH
+};
+
+/// Height of the recorded frames
+pub const RECORDED_HEIGHT: usize = RECORDED_PIXELS / RECORDED_WIDTH;
+
+/// Pixels of a recorded frame
+pub const RECORDED_PIXELS: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
+
+/// Frame being drawn and frame on display, row-major RGB565
+pub struct DrawnFrames {
+    frames: [[u16; RECORDED_PIXELS]; 2],
+    /// Index of the frame on display
+    shown: usize,
+}
+
+impl Default for DrawnFrames {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawnFrames {
+    pub const fn new() -> Self {
+        Self {
+            frames: [[0; RECORDED_PIXELS]; 2],
+            shown: 0,
+        }
+    }
+
+    pub(crate) fn set_pixel(&mut self, point: Point, color: Rgb565) {
+        let (x, y) = (point.x as usize, point.y as usize);
+        if point.x >= 0 && point.y >= 0 && x < RECORDED_WIDTH && y < RECORDED_HEIGHT {
+            self.frames[1 - self.shown][y * RECORDED_WIDTH + x] = RawU16::from(color).into_inner();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.frames[1 - self.shown].fill(0);
+    }
+
+    /// Follow a commit, which showed the drawn frame if `changed`
+    pub(crate) fn commit(&mut self, changed: bool) {
+        if changed {
+            self.shown = 1 - self.shown;
+        }
+        self.clear();
+    }
+
+    /// Frame on display
+    pub const fn shown(&self) -> &[u16; RECORDED_PIXELS] {
+        &self.frames[self.shown]
+    }
+}
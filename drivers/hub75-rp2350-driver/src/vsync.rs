@@ -0,0 +1,70 @@
+//! Frame-complete notification
+//!
+//! DMA channel 0 streams a whole frame to the data state machine, then
+//! channel 1 reloads its read address and triggers it again. Channel 1
+//! completing thus marks the end of each refresh of the panel, which raises
+//! `DMA_IRQ_1` (Embassy's own DMA driver uses `DMA_IRQ_0`). The handler
+//! counts refreshes and wakes the task waiting in `Hub75::wait_for_vsync`;
+//! a single task can wait at a time.
+
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Poll;
+use embassy_rp::interrupt::typelevel::{DMA_IRQ_1, Handler};
+use embassy_rp::interrupt::{self, InterruptExt};
+use embassy_sync::waitqueue::AtomicWaker;
+
+/// DMA channel whose completion ends a refresh
+const FRAME_CHANNEL_MASK: u32 = 1 << 1;
+
+/// Refreshes completed since the driver started
+static FRAMES: AtomicU32 = AtomicU32::new(0);
+
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Handler of `DMA_IRQ_1`, bound by the driver
+pub struct VsyncInterruptHandler;
+
+impl Handler<DMA_IRQ_1> for VsyncInterruptHandler {
+    unsafe fn on_interrupt() {
+        let dma = embassy_rp::pac::DMA;
+        if dma.ints(1).read() & FRAME_CHANNEL_MASK != 0 {
+            dma.ints(1).write_value(FRAME_CHANNEL_MASK);
+            FRAMES.fetch_add(1, Ordering::Release);
+            WAKER.wake();
+        }
+    }
+}
+
+/// Route the frame channel's completion to `DMA_IRQ_1`
+///
+/// The channel must be set up with `irq_quiet` cleared.
+pub(crate) fn enable() {
+    let dma = embassy_rp::pac::DMA;
+    dma.inte(0).modify(|mask| *mask &= !FRAME_CHANNEL_MASK);
+    dma.ints(1).write_value(FRAME_CHANNEL_MASK);
+    dma.inte(1).modify(|mask| *mask |= FRAME_CHANNEL_MASK);
+
+    interrupt::DMA_IRQ_1.unpend();
+    // SAFETY: the handler only touches the frame channel and atomics
+    unsafe { interrupt::DMA_IRQ_1.enable() };
+}
+
+/// Number of refreshes completed so far, wrapping
+pub(crate) fn frame_count() -> u32 {
+    FRAMES.load(Ordering::Acquire)
+}
+
+/// Wait for the end of the refresh in progress
+pub(crate) async fn wait_for_frame() {
+    let start = frame_count();
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
+        if frame_count() == start {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+}
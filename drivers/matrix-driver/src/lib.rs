@@ -0,0 +1,42 @@
+#![no_std]
+//! Interface shared by the LED matrix drivers
+//!
+//! Firmware, test patterns and the self-test take a [`MatrixDriver`]
+//! instead of a concrete driver, so they run on the bit-banged
+//! `hub75-driver` as well as on the PIO-based `hub75-rp2350-driver`. A port
+//! to a new MCU only has to implement this trait.
+//!
+//! Drawing goes through `embedded-graphics`, which every driver supports;
+//! the trait adds what the drivers have in common around it. Keeping the
+//! panel refreshed stays driver-specific: the PIO driver refreshes on its
+//! own, the bit-banged one needs its `update()` loop.
+
+use core::convert::Infallible;
+use embedded_graphics_core::{
+    Pixel,
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point},
+    pixelcolor::Rgb565,
+};
+
+/// LED matrix the firmware draws on
+///
+/// Drawing can't fail: pixels outside the panel are ignored.
+pub trait MatrixDriver: DrawTarget<Color = Rgb565, Error = Infallible> + OriginDimensions {
+    /// Set a pixel of the frame being drawn
+    fn set_pixel(&mut self, point: Point, color: Rgb565) {
+        let Ok(()) = self.draw_iter([Pixel(point, color)]);
+    }
+
+    /// Clear the frame being drawn to black
+    fn clear(&mut self);
+
+    /// Show the frame drawn since the last commit
+    fn commit(&mut self);
+
+    /// Set the overall brightness (0-255)
+    fn set_brightness(&mut self, brightness: u8);
+
+    /// Get the overall brightness
+    fn brightness(&self) -> u8;
+}
@@ -0,0 +1,320 @@
+//! Installation self-test screens
+//!
+//! The firmware chains a few checks (display, input, network, flash,
+//! plugins) and records each [`Outcome`] in a [`Report`]. This module holds
+//! the parts that don't depend on hardware: the full-screen test patterns
+//! and the pass/fail summary screen. [`chain`] checks the data going
+//! through daisy-chained panels, and [`geometry`] finds out how many rows
+//! the panel addresses.
+
+pub mod chain;
+pub mod geometry;
+
+use crate::i18n::{Locale, MessageId};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::Text,
+};
+
+/// One step of the self-test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    Display,
+    Input,
+    Network,
+    Flash,
+    Plugins,
+}
+
+impl Check {
+    /// Every check, in the order they run
+    pub const ALL: [Self; 5] = [
+        Self::Display,
+        Self::Input,
+        Self::Network,
+        Self::Flash,
+        Self::Plugins,
+    ];
+
+    /// Name of the check, in English for the logs
+    pub const fn name(self) -> &'static str {
+        self.message().text(Locale::En)
+    }
+
+    /// Name of the check shown on the summary screen
+    pub const fn message(self) -> MessageId {
+        match self {
+            Self::Display => MessageId::CheckDisplay,
+            Self::Input => MessageId::CheckInput,
+            Self::Network => MessageId::CheckNetwork,
+            Self::Flash => MessageId::CheckFlash,
+            Self::Plugins => MessageId::CheckPlugins,
+        }
+    }
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Result of a check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Not run yet
+    Pending,
+    Passed,
+    /// Failed, with a short reason for the logs
+    Failed(&'static str),
+    /// Not applicable to this build or install
+    Skipped(&'static str),
+}
+
+impl Outcome {
+    /// Short label, in English for the logs
+    pub const fn label(self) -> &'static str {
+        self.message().text(Locale::En)
+    }
+
+    /// Short label shown on the summary screen
+    pub const fn message(self) -> MessageId {
+        match self {
+            Self::Pending => MessageId::OutcomePending,
+            Self::Passed => MessageId::OutcomePassed,
+            Self::Failed(_) => MessageId::OutcomeFailed,
+            Self::Skipped(_) => MessageId::OutcomeSkipped,
+        }
+    }
+
+    /// Reason of a failed or skipped check
+    pub const fn reason(self) -> Option<&'static str> {
+        match self {
+            Self::Failed(reason) | Self::Skipped(reason) => Some(reason),
+            Self::Pending | Self::Passed => None,
+        }
+    }
+
+    const fn color(self) -> Rgb565 {
+        match self {
+            Self::Pending => Rgb565::CSS_GRAY,
+            Self::Passed => Rgb565::GREEN,
+            Self::Failed(_) => Rgb565::RED,
+            Self::Skipped(_) => Rgb565::YELLOW,
+        }
+    }
+}
+
+/// Outcome of every check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Report {
+    outcomes: [Outcome; Check::ALL.len()],
+}
+
+impl Report {
+    /// Report with every check pending
+    pub const fn new() -> Self {
+        Self {
+            outcomes: [Outcome::Pending; Check::ALL.len()],
+        }
+    }
+
+    pub const fn set(&mut self, check: Check, outcome: Outcome) {
+        self.outcomes[check.index()] = outcome;
+    }
+
+    pub const fn get(&self, check: Check) -> Outcome {
+        self.outcomes[check.index()]
+    }
+
+    /// Iterate over the checks and their outcome, in run order
+    pub fn iter(&self) -> impl Iterator<Item = (Check, Outcome)> + '_ {
+        Check::ALL.iter().map(|&check| (check, self.get(check)))
+    }
+
+    /// Number of failed checks
+    pub fn failures(&self) -> usize {
+        self.iter()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Failed(_)))
+            .count()
+    }
+
+    /// Check if every check ran and none failed
+    pub fn passed(&self) -> bool {
+        self.iter()
+            .all(|(_, outcome)| matches!(outcome, Outcome::Passed | Outcome::Skipped(_)))
+    }
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Full-screen patterns for checking the panel by eye
+///
+/// Solid colors reveal dead channels, the gradient reveals missing color
+/// depth planes and the grid reveals swapped rows or address lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestPattern {
+    Red,
+    Green,
+    Blue,
+    White,
+    Gradient,
+    Grid,
+}
+
+impl TestPattern {
+    pub const ALL: [Self; 6] = [
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::White,
+        Self::Gradient,
+        Self::Grid,
+    ];
+
+    /// Draw the pattern over the whole display
+    pub fn draw<D>(self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let area = display.bounding_box();
+        match self {
+            Self::Red => display.clear(Rgb565::RED),
+            Self::Green => display.clear(Rgb565::GREEN),
+            Self::Blue => display.clear(Rgb565::BLUE),
+            Self::White => display.clear(Rgb565::WHITE),
+            Self::Gradient => {
+                // Red, green and blue ramps stacked, plus a gray ramp
+                let width = area.size.width.max(1);
+                let band = area.size.height / 4;
+                let colors = area.points().map(|point| {
+                    let level = (point.x - area.top_left.x) as u32 * 256 / width;
+                    let level = level.min(255) as u8;
+                    match (point.y - area.top_left.y) as u32 / band.max(1) {
+                        0 => Rgb565::new(level >> 3, 0, 0),
+                        1 => Rgb565::new(0, level >> 2, 0),
+                        2 => Rgb565::new(0, 0, level >> 3),
+                        _ => Rgb565::new(level >> 3, level >> 2, level >> 3),
+                    }
+                });
+                display.fill_contiguous(&area, colors)
+            }
+            Self::Grid => {
+                display.clear(Rgb565::BLACK)?;
+                let style = PrimitiveStyle::with_stroke(Rgb565::WHITE, 1);
+                let bottom_right = area.bottom_right().unwrap_or(area.top_left);
+                for x in (area.top_left.x..=bottom_right.x).step_by(8) {
+                    Line::new(
+                        Point::new(x, area.top_left.y),
+                        Point::new(x, bottom_right.y),
+                    )
+                    .into_styled(style)
+                    .draw(display)?;
+                }
+                for y in (area.top_left.y..=bottom_right.y).step_by(8) {
+                    Line::new(
+                        Point::new(area.top_left.x, y),
+                        Point::new(bottom_right.x, y),
+                    )
+                    .into_styled(style)
+                    .draw(display)?;
+                }
+                // Border, to spot missing edge rows and columns
+                area.into_styled(PrimitiveStyle::with_stroke(Rgb565::RED, 1))
+                    .draw(display)
+            }
+        }
+    }
+}
+
+/// Draw a prompt screen, e.g. "Press the button" during the input check
+pub fn draw_prompt<D>(display: &mut D, title: &str, prompt: &str) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    display.clear(Rgb565::BLACK)?;
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    Text::new(title, Point::new(2, 10), style).draw(display)?;
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::YELLOW);
+    Text::new(prompt, Point::new(2, 30), style).draw(display)?;
+    Ok(())
+}
+
+/// Draw the pass/fail summary of a report
+pub fn draw_summary<D>(display: &mut D, report: &Report, locale: Locale) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    display.clear(Rgb565::BLACK)?;
+    let width = display.bounding_box().size.width;
+
+    let title = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let text = MessageId::SelfTest.text(locale);
+    Text::new(text, Point::new(2, 10), title).draw(display)?;
+
+    for (row, (check, outcome)) in report.iter().enumerate() {
+        let y = 26 + row as i32 * 12;
+        let name = check.message().text(locale);
+        Text::new(name, Point::new(2, y), title).draw(display)?;
+        let label = outcome.message().text(locale);
+        let x = width as i32 - 2 - (label.chars().count() as i32 * 6);
+        let style = MonoTextStyle::new(&FONT_6X10, outcome.color());
+        Text::new(label, Point::new(x, y), style).draw(display)?;
+    }
+
+    // Verdict banner at the bottom
+    let (verdict, color) = if report.passed() {
+        (MessageId::VerdictPassed, Rgb565::GREEN)
+    } else if report.failures() > 0 {
+        (MessageId::VerdictFailed, Rgb565::RED)
+    } else {
+        (MessageId::VerdictIncomplete, Rgb565::YELLOW)
+    };
+    let height = display.bounding_box().size.height;
+    let banner = Rectangle::new(Point::new(0, height as i32 - 14), Size::new(width, 14));
+    banner
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(display)?;
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::BLACK);
+    let verdict = verdict.text(locale);
+    Text::new(verdict, Point::new(2, height as i32 - 4), style).draw(display)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    #[test]
+    fn test_report_verdict() {
+        let mut report = Report::new();
+        assert!(!report.passed());
+
+        for check in Check::ALL {
+            report.set(check, Outcome::Passed);
+        }
+        report.set(Check::Network, Outcome::Skipped("no network"));
+        assert!(report.passed());
+        assert_eq!(report.failures(), 0);
+
+        report.set(Check::Flash, Outcome::Failed("corrupted"));
+        assert!(!report.passed());
+        assert_eq!(report.failures(), 1);
+        assert_eq!(report.get(Check::Flash).reason(), Some("corrupted"));
+    }
+
+    #[test]
+    fn test_patterns_stay_on_screen() {
+        for pattern in TestPattern::ALL {
+            let mut display = MockDisplay::<Rgb565>::new();
+            display.set_allow_overdraw(true);
+            pattern.draw(&mut display).unwrap();
+        }
+    }
+}
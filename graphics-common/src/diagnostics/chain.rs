@@ -0,0 +1,232 @@
+//! Daisy-chain data integrity check
+//!
+//! A marginal ribbon cable between chained panels shows up as sparkles:
+//! bits flipped on their way down the chain. With a loopback jumper from
+//! the output connector of the last panel back to the controller, the data
+//! that went through every panel can be compared with what was sent.
+//!
+//! [`draw_chain_frame`] blanks the display and fills the last pixel row
+//! with checksum blocks of [`BLOCK_BITS`] pixels, one bit per pixel, white
+//! for 1 and black for 0 so each bit survives gamma correction and is the
+//! same in every BCM plane. A block holds:
+//!
+//! ```text
+//! [sync: 0xB5][frame counter: u32][crc: u16 of the counter][!sync]
+//! ```
+//!
+//! all most significant bit first. The driver samples the red, green and
+//! blue lines of the chain output on each pixel clock, and [`verify`] looks
+//! for the blocks in those samples. As the row is black elsewhere, any
+//! block whose sync markers are found but whose CRC doesn't match, or whose
+//! color lines disagree, is a corrupted transfer.
+
+use super::Outcome;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+
+/// Pixels, and bits, of one checksum block
+pub const BLOCK_BITS: usize = 64;
+
+const SYNC: u8 = 0xB5;
+
+/// All three color lines of a sample high
+const SAMPLE_HIGH: u8 = 0b111;
+
+/// Frames the returned data may lag behind, the driver double buffering
+const MAX_LAG: u32 = 2;
+
+/// CRC-16/CCITT-FALSE
+pub const fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= (data[i] as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Checksum block of a frame, first bit in the most significant position
+pub const fn encode_block(counter: u32) -> u64 {
+    let crc = crc16(&counter.to_be_bytes());
+    ((SYNC as u64) << 56) | ((counter as u64) << 24) | ((crc as u64) << 8) | !SYNC as u64
+}
+
+/// Frame counter of a block, if its markers and CRC are intact
+pub const fn decode_block(block: u64) -> Option<u32> {
+    if !has_markers(block) {
+        return None;
+    }
+    let counter = (block >> 24) as u32;
+    if (block >> 8) as u16 == crc16(&counter.to_be_bytes()) {
+        Some(counter)
+    } else {
+        None
+    }
+}
+
+const fn has_markers(block: u64) -> bool {
+    (block >> 56) as u8 == SYNC && block as u8 == !SYNC
+}
+
+/// Draw the check frame: black, with checksum blocks along the last row
+pub fn draw_chain_frame<D>(display: &mut D, counter: u32) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    display.clear(Rgb565::BLACK)?;
+    let area = display.bounding_box();
+    let Some(bottom_right) = area.bottom_right() else {
+        return Ok(());
+    };
+
+    let block = encode_block(counter);
+    let pixels = (area.top_left.x..=bottom_right.x).map(|x| {
+        let bit = (x - area.top_left.x) as usize % BLOCK_BITS;
+        let color = if block >> (BLOCK_BITS - 1 - bit) & 1 != 0 {
+            Rgb565::WHITE
+        } else {
+            Rgb565::BLACK
+        };
+        Pixel(Point::new(x, bottom_right.y), color)
+    });
+    display.draw_iter(pixels)
+}
+
+/// Blocks found in the samples of one capture
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChainCapture {
+    /// Blocks received intact
+    pub valid: u32,
+    /// Blocks with their sync markers but a bad CRC or disagreeing lines
+    pub corrupted: u32,
+    /// Counter of the last intact block
+    pub latest: Option<u32>,
+    /// Whether any line of the chain output was ever high
+    pub signal: bool,
+}
+
+impl ChainCapture {
+    /// Outcome of the capture of a frame drawn with `counter`
+    pub fn outcome(&self, counter: u32) -> Outcome {
+        if !self.signal {
+            Outcome::Failed("no loopback signal")
+        } else if self.corrupted > 0 {
+            Outcome::Failed("chain data corrupted")
+        } else {
+            match self.latest {
+                Some(latest) if counter.wrapping_sub(latest) <= MAX_LAG => Outcome::Passed,
+                Some(_) => Outcome::Failed("chain data stale"),
+                None => Outcome::Failed("no checksum block"),
+            }
+        }
+    }
+}
+
+/// Look for checksum blocks in the samples of the chain output
+///
+/// Each sample holds the red, green and blue lines in its three low bits,
+/// one sample per pixel clock. A pixel counts as 1 when at least two lines
+/// are high.
+pub fn verify(samples: impl IntoIterator<Item = u8>) -> ChainCapture {
+    let mut capture = ChainCapture::default();
+    // Last BLOCK_BITS bits, and which of them had disagreeing lines
+    let mut window: u64 = 0;
+    let mut mixed: u64 = 0;
+    let mut seen = 0;
+
+    for sample in samples {
+        let sample = sample & SAMPLE_HIGH;
+        capture.signal |= sample != 0;
+        window = window << 1 | u64::from(sample.count_ones() >= 2);
+        mixed = mixed << 1 | u64::from(sample != 0 && sample != SAMPLE_HIGH);
+        seen += 1;
+        if seen < BLOCK_BITS || !has_markers(window) {
+            continue;
+        }
+
+        match decode_block(window) {
+            Some(counter) if mixed == 0 => {
+                capture.valid += 1;
+                capture.latest = Some(counter);
+            }
+            _ => capture.corrupted += 1,
+        }
+    }
+    capture
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    /// Samples of one row as it leaves the chain
+    fn row_samples(counter: u32, width: usize) -> impl Iterator<Item = u8> {
+        let block = encode_block(counter);
+        (0..width).map(move |x| {
+            let bit = block >> (BLOCK_BITS - 1 - x % BLOCK_BITS) & 1;
+            if bit != 0 { SAMPLE_HIGH } else { 0 }
+        })
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        for counter in [0, 1, 0x1234_5678, u32::MAX] {
+            assert_eq!(decode_block(encode_block(counter)), Some(counter));
+        }
+        // Check value of CRC-16/CCITT-FALSE
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(decode_block(encode_block(42) ^ 1 << 30), None);
+    }
+
+    #[test]
+    fn test_intact_chain_passes() {
+        let samples = core::iter::repeat_n(0, 100).chain(row_samples(41, 128));
+        let capture = verify(samples);
+        assert_eq!((capture.valid, capture.corrupted), (2, 0));
+        assert_eq!(capture.outcome(42), Outcome::Passed);
+        assert_eq!(capture.outcome(50), Outcome::Failed("chain data stale"));
+    }
+
+    #[test]
+    fn test_flipped_bits_are_caught() {
+        // A bit of the counter flipped on every line
+        let flipped = row_samples(7, 64)
+            .enumerate()
+            .map(|(i, s)| if i == 20 { s ^ SAMPLE_HIGH } else { s });
+        let capture = verify(flipped);
+        assert_eq!((capture.valid, capture.corrupted), (0, 1));
+        assert_eq!(capture.outcome(7), Outcome::Failed("chain data corrupted"));
+
+        // A single line dropping out, still read as a 1 by the majority
+        let dropped = row_samples(7, 64)
+            .enumerate()
+            .map(|(i, s)| if i == 0 { s & 0b011 } else { s });
+        assert_eq!(verify(dropped).corrupted, 1);
+
+        let silent = verify(core::iter::repeat_n(0, 256));
+        assert_eq!(silent.outcome(0), Outcome::Failed("no loopback signal"));
+    }
+
+    #[test]
+    fn test_frame_fills_last_row() {
+        let mut display = MockDisplay::<Rgb565>::new();
+        display.set_allow_overdraw(true);
+        draw_chain_frame(&mut display, 3).unwrap();
+        let block = encode_block(3);
+        for x in 0..64 {
+            let white = display.get_pixel(Point::new(x, 63)) == Some(Rgb565::WHITE);
+            assert_eq!(white, block >> (63 - x) & 1 != 0);
+        }
+        assert_eq!(display.get_pixel(Point::new(0, 0)), Some(Rgb565::BLACK));
+    }
+}
@@ -0,0 +1,145 @@
+//! Guided probe of the panel scan geometry
+//!
+//! Panels of the same width come in different heights and scan rates: a
+//! 64x32 panel is usually 1/16 scan with four address lines (A-D), a 64x64
+//! one 1/32 scan with five (A-E). The driver drives every address line it
+//! has, and a panel ignores those it doesn't decode, so with `n` lines
+//! decoded, row `2^n` lands back on row 0.
+//!
+//! [`GeometryProbe`] uses that to count the lines with the installer's
+//! help. For each address line, highest first, it draws row 0 in green and
+//! row `2^line` in red, and asks whether two separate lines are lit. A panel
+//! that doesn't decode the line shows a single yellow line, red over green.
+//! The first line answered with two lines is the highest one the panel
+//! decodes.
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle},
+};
+
+/// Row lit in every probe step, to compare the probed row against
+const REFERENCE_COLOR: Rgb565 = Rgb565::GREEN;
+/// Row addressed through the probed line
+const PROBE_COLOR: Rgb565 = Rgb565::RED;
+
+/// Questions to deduce the address lines of a panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeometryProbe {
+    /// Address line asked about, from 0 for A
+    line: Option<u8>,
+    /// Address lines found
+    found: Option<u8>,
+}
+
+impl GeometryProbe {
+    /// Probe a panel with at most `max_address_lines` lines, those the
+    /// driver drives
+    ///
+    /// Rows are drawn in the driver's default geometry, so the display must
+    /// scan every address line while probing.
+    pub const fn new(max_address_lines: u8) -> Self {
+        Self {
+            line: max_address_lines.checked_sub(1),
+            found: None,
+        }
+    }
+
+    /// Address line of the current question, `None` once the probe is over
+    pub const fn line(&self) -> Option<u8> {
+        self.line
+    }
+
+    /// Draw the rows of the current question
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        display.clear(Rgb565::BLACK)?;
+        let Some(line) = self.line else {
+            return Ok(());
+        };
+
+        let right = display.bounding_box().size.width as i32 - 1;
+        let row = |y: i32, color| {
+            Line::new(Point::new(0, y), Point::new(right, y))
+                .into_styled(PrimitiveStyle::with_stroke(color, 1))
+        };
+        row(0, REFERENCE_COLOR).draw(display)?;
+        row(1 << line, PROBE_COLOR).draw(display)
+    }
+
+    /// Record the answer to the current question
+    ///
+    /// `two_lines` is whether the red and green rows were lit separately.
+    pub const fn answer(&mut self, two_lines: bool) {
+        let Some(line) = self.line else {
+            return;
+        };
+        if two_lines {
+            self.found = Some(line + 1);
+            self.line = None;
+        } else {
+            self.line = line.checked_sub(1);
+        }
+    }
+
+    /// Check whether every question was answered
+    pub const fn is_done(&self) -> bool {
+        self.line.is_none()
+    }
+
+    /// Address lines of the panel, once the probe is over
+    ///
+    /// `None` while questions remain, or if no line showed two rows: the
+    /// panel didn't light as expected and its geometry is unknown.
+    pub const fn address_lines(&self) -> Option<u8> {
+        self.found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    /// Answer as a panel decoding `lines` address lines would
+    fn run(max: u8, lines: u8) -> GeometryProbe {
+        let mut probe = GeometryProbe::new(max);
+        while let Some(line) = probe.line() {
+            probe.answer(line < lines);
+        }
+        probe
+    }
+
+    #[test]
+    fn test_deduces_the_address_lines() {
+        assert_eq!(run(5, 5).address_lines(), Some(5));
+        assert_eq!(run(5, 4).address_lines(), Some(4));
+        assert_eq!(run(4, 3).address_lines(), Some(3));
+
+        // A panel lighting a single line for every question
+        let probe = run(5, 0);
+        assert!(probe.is_done());
+        assert_eq!(probe.address_lines(), None);
+
+        let probe = GeometryProbe::new(0);
+        assert!(probe.is_done());
+        assert_eq!(probe.address_lines(), None);
+    }
+
+    #[test]
+    fn test_draws_the_reference_and_probed_rows() {
+        let mut probe = GeometryProbe::new(5);
+        probe.answer(false);
+        assert_eq!(probe.line(), Some(3));
+
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        probe.draw(&mut display).unwrap();
+        assert_eq!(display.get_pixel(Point::new(10, 0)), Some(REFERENCE_COLOR));
+        assert_eq!(display.get_pixel(Point::new(10, 8)), Some(PROBE_COLOR));
+        assert_eq!(display.get_pixel(Point::new(10, 4)), Some(Rgb565::BLACK));
+    }
+}
@@ -0,0 +1,155 @@
+//! Localized on-screen strings
+//!
+//! Every string drawn on the panel is a [`MessageId`] with one translation
+//! per [`Locale`]. The table is declared with `messages!`, which requires a
+//! translation for every locale of every message, so a missing one fails the
+//! build rather than showing up on site.
+//!
+//! Messages may contain `{}` placeholders, filled in order by
+//! [`write_message`]. Translations use ISO 8859-1 characters only, so draw
+//! them with a font from `mono_font::iso_8859_1`.
+
+use core::fmt::{self, Display, Write};
+
+/// Language of the on-screen strings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Self; 2] = [Self::En, Self::Fr];
+}
+
+// Declare `MessageId` and its translations; the `en` and `fr` fields are
+// required by the pattern and each locale gets an exhaustive match
+macro_rules! messages {
+    ($($(#[$meta:meta])* $id:ident { en: $en:literal, fr: $fr:literal $(,)? }),+ $(,)?) => {
+        /// String shown on the panel
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum MessageId {
+            $($(#[$meta])* $id,)+
+        }
+
+        impl MessageId {
+            /// Every message, in declaration order
+            pub const ALL: &[Self] = &[$(Self::$id,)+];
+
+            /// Translation of the message, placeholders included
+            pub const fn text(self, locale: Locale) -> &'static str {
+                match locale {
+                    Locale::En => match self {
+                        $(Self::$id => $en,)+
+                    },
+                    Locale::Fr => match self {
+                        $(Self::$id => $fr,)+
+                    },
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    /// Title of the history page, with the cluster name
+    HistoryTitle { en: "{} history", fr: "Historique {}" },
+    /// Footer of the history page, with the graph scale and the occupancy
+    HistoryFooter { en: "max {}% now {}%", fr: "max {}% act. {}%" },
+    /// Occupancy alert banner, with the cluster name and the occupancy
+    AlertFull { en: "{} FULL {}%", fr: "{} PLEIN {}%" },
+    /// Header of a closed cluster without a message of its own
+    ClusterClosed { en: "Cluster closed", fr: "Cluster fermé" },
+    /// Header of a cluster booked for an exam, without a message of its own
+    ClusterExam { en: "Exam in progress", fr: "Examen en cours" },
+    SelfTest { en: "Self-test", fr: "Autotest" },
+    InputTest { en: "Input test", fr: "Test bouton" },
+    PressButton { en: "Press the button", fr: "Appuyez sur le bouton" },
+    CheckDisplay { en: "Display", fr: "Affichage" },
+    CheckInput { en: "Input", fr: "Bouton" },
+    CheckNetwork { en: "Network", fr: "Réseau" },
+    CheckFlash { en: "Flash", fr: "Flash" },
+    CheckPlugins { en: "Plugins", fr: "Plugins" },
+    SafeMode { en: "Safe mode", fr: "Mode sans échec" },
+    ResetConfig { en: "Press: reset config", fr: "Appui: réinit. config" },
+    ConfirmReset { en: "Press again to reset", fr: "Réappuyez: réinit." },
+    ConfigReset { en: "Config reset", fr: "Config réinitialisée" },
+    PowerCycle { en: "Power cycle to exit", fr: "Éteindre pour quitter" },
+    /// Title of the menu opened with the menu button
+    Menu { en: "Menu", fr: "Menu" },
+    MenuBrightness { en: "Brightness", fr: "Luminosité" },
+    MenuTheme { en: "Theme", fr: "Thème" },
+    /// Floor shown at boot, see `Preferences::default_floor`
+    MenuFloor { en: "Floor", fr: "Étage" },
+    MenuClose { en: "Close", fr: "Fermer" },
+    OutcomePending { en: "...", fr: "..." },
+    OutcomePassed { en: "PASS", fr: "OK" },
+    OutcomeFailed { en: "FAIL", fr: "ÉCHEC" },
+    OutcomeSkipped { en: "SKIP", fr: "IGNORÉ" },
+    VerdictPassed { en: "ALL PASSED", fr: "TOUT OK" },
+    VerdictFailed { en: "FAILED", fr: "ÉCHEC" },
+    VerdictIncomplete { en: "INCOMPLETE", fr: "INCOMPLET" },
+}
+
+/// Write a message, replacing each `{}` with the next argument
+///
+/// Placeholders without an argument are dropped, extra arguments ignored.
+pub fn write_message<W: Write>(
+    out: &mut W,
+    id: MessageId,
+    locale: Locale,
+    args: &[&dyn Display],
+) -> fmt::Result {
+    let mut args = args.iter();
+    let mut parts = id.text(locale).split("{}");
+    if let Some(first) = parts.next() {
+        out.write_str(first)?;
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            write!(out, "{arg}")?;
+        }
+        out.write_str(part)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::String;
+
+    #[test]
+    fn test_write_message() {
+        let mut out: String<32> = String::new();
+        write_message(&mut out, MessageId::AlertFull, Locale::En, &[&"F1", &97]).unwrap();
+        assert_eq!(out.as_str(), "F1 FULL 97%");
+
+        out.clear();
+        write_message(&mut out, MessageId::HistoryTitle, Locale::Fr, &[&"F2"]).unwrap();
+        assert_eq!(out.as_str(), "Historique F2");
+
+        out.clear();
+        write_message(&mut out, MessageId::HistoryFooter, Locale::En, &[&40]).unwrap();
+        assert_eq!(out.as_str(), "max 40% now %");
+    }
+
+    #[test]
+    fn test_translations_fit_the_font() {
+        for &id in MessageId::ALL {
+            for locale in Locale::ALL {
+                let text = id.text(locale);
+                assert!(!text.is_empty(), "{id:?} is empty in {locale:?}");
+                // ISO 8859-1 fonts have no glyph past U+00FF
+                assert!(
+                    text.chars().all(|c| u32::from(c) <= 0xFF),
+                    "{id:?} in {locale:?} has a character the fonts can't draw"
+                );
+                // Same placeholders in every translation
+                let placeholders = text.matches("{}").count();
+                assert_eq!(placeholders, id.text(Locale::En).matches("{}").count());
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
 #![no_std]
 
-#[cfg(feature = "std")]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
 pub mod animations;
+pub mod diagnostics;
+pub mod i18n;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub mod utilities;
@@ -0,0 +1,152 @@
+//! In-memory draw target for tests
+//!
+//! [`TestCanvas`] records what is drawn on it, so visualization and widget
+//! tests can check pixels without the simulator or a fixed-size display.
+//!
+//! ```
+//! use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+//! use graphics_common::test_support::TestCanvas;
+//!
+//! let mut canvas = TestCanvas::new(Size::new(64, 32));
+//! let square = Rectangle::new(Point::new(2, 2), Size::new(4, 4));
+//! let Ok(()) = square.into_styled(PrimitiveStyle::with_fill(Rgb565::RED)).draw(&mut canvas);
+//!
+//! assert_eq!(canvas.pixel_at(Point::new(3, 3)), Some(Rgb565::RED));
+//! assert_eq!(canvas.count_color(Rgb565::RED), 16);
+//! ```
+
+use core::convert::Infallible;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use std::vec;
+use std::vec::Vec;
+
+/// FNV-1a offset basis and prime, as used by the panel driver
+const FNV_OFFSET: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// Canvas of any size recording the color of each pixel
+///
+/// Pixels drawn outside the canvas are dropped, like a display does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCanvas {
+    size: Size,
+    pixels: Vec<Rgb565>,
+}
+
+impl TestCanvas {
+    /// Black canvas of the given size
+    pub fn new(size: Size) -> Self {
+        Self::with_background(size, Rgb565::BLACK)
+    }
+
+    /// Canvas of the given size filled with `color`
+    pub fn with_background(size: Size, color: Rgb565) -> Self {
+        Self {
+            size,
+            pixels: vec![color; (size.width * size.height) as usize],
+        }
+    }
+
+    /// Color of the pixel at `point`, `None` outside the canvas
+    pub fn pixel_at(&self, point: Point) -> Option<Rgb565> {
+        self.index(point).map(|index| self.pixels[index])
+    }
+
+    /// Number of pixels of the given color
+    pub fn count_color(&self, color: Rgb565) -> usize {
+        self.pixels.iter().filter(|&&pixel| pixel == color).count()
+    }
+
+    /// FNV-1a hash of the pixels of `area`, row by row
+    ///
+    /// Compares a region against a known rendering without spelling out its
+    /// pixels. Only the part of `area` on the canvas is hashed.
+    pub fn region_hash(&self, area: &Rectangle) -> u32 {
+        area.intersection(&self.bounding_box())
+            .points()
+            .filter_map(|point| self.pixel_at(point))
+            .flat_map(|color| color.into_storage().to_le_bytes())
+            .fold(FNV_OFFSET, |hash, byte| {
+                (hash ^ u32::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Every pixel, row by row
+    pub fn pixels(&self) -> &[Rgb565] {
+        &self.pixels
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        self.bounding_box()
+            .contains(point)
+            .then(|| point.y as usize * self.size.width as usize + point.x as usize)
+    }
+}
+
+impl OriginDimensions for TestCanvas {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl DrawTarget for TestCanvas {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<Rgb565>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(index) = self.index(point) {
+                self.pixels[index] = color;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Rgb565) -> Result<(), Infallible> {
+        self.pixels.fill(color);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_bounds_pixels_dropped() {
+        let mut canvas = TestCanvas::new(Size::new(4, 2));
+        let Ok(()) = canvas.draw_iter([
+            Pixel(Point::new(3, 1), Rgb565::RED),
+            Pixel(Point::new(4, 0), Rgb565::RED),
+            Pixel(Point::new(-1, 0), Rgb565::RED),
+        ]);
+
+        assert_eq!(canvas.pixel_at(Point::new(3, 1)), Some(Rgb565::RED));
+        assert_eq!(canvas.pixel_at(Point::new(4, 0)), None);
+        assert_eq!(canvas.count_color(Rgb565::RED), 1);
+        assert_eq!(canvas.count_color(Rgb565::BLACK), 7);
+    }
+
+    #[test]
+    fn test_region_hash() {
+        let mut canvas = TestCanvas::new(Size::new(8, 8));
+        let left = Rectangle::new(Point::zero(), Size::new(4, 8));
+        let right = Rectangle::new(Point::new(4, 0), Size::new(4, 8));
+        assert_eq!(canvas.region_hash(&left), canvas.region_hash(&right));
+
+        let Ok(()) = Pixel(Point::new(5, 5), Rgb565::GREEN).draw(&mut canvas);
+        assert_ne!(canvas.region_hash(&left), canvas.region_hash(&right));
+
+        // Only the part on the canvas counts
+        let overhanging = Rectangle::new(Point::new(-4, 0), Size::new(8, 8));
+        assert_eq!(canvas.region_hash(&overhanging), canvas.region_hash(&left));
+
+        let Ok(()) = canvas.clear(Rgb565::BLACK);
+        assert_eq!(canvas, TestCanvas::new(Size::new(8, 8)));
+    }
+}
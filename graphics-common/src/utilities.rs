@@ -1 +1,7 @@
+pub mod average;
+pub mod brightness;
 pub mod color;
+pub mod hysteresis;
+pub mod image;
+pub mod scroll;
+pub mod tiled;
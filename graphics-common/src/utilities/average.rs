@@ -0,0 +1,128 @@
+//! Moving average over a fixed window
+//!
+//! [`MovingAverage`] keeps the last `N` samples in place, so it works on
+//! integers and floats alike without allocating. Used to smooth noisy
+//! readings such as light levels, temperatures or request latencies.
+
+use core::ops::{Add, Div};
+
+/// Average of the last `N` samples
+///
+/// Until `N` samples were pushed, the average covers the samples seen so far.
+/// The sum is computed in `T`, so pick a type wide enough for `N` samples.
+#[derive(Clone, Copy, Debug)]
+pub struct MovingAverage<T, const N: usize> {
+    samples: [T; N],
+    len: usize,
+    next: usize,
+}
+
+impl<T, const N: usize> MovingAverage<T, N>
+where
+    T: Copy + Default + Add<Output = T> + Div<Output = T> + From<u16>,
+{
+    /// Create an empty average
+    pub fn new() -> Self {
+        const {
+            assert!(
+                N > 0 && N <= u16::MAX as usize,
+                "window must hold 1 to 65535 samples"
+            )
+        };
+        Self {
+            samples: [T::default(); N],
+            len: 0,
+            next: 0,
+        }
+    }
+
+    /// Add a sample, dropping the oldest one once the window is full
+    pub fn push(&mut self, sample: T) {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Add a sample and get the new average
+    pub fn update(&mut self, sample: T) -> T {
+        self.push(sample);
+        self.average().unwrap_or(sample)
+    }
+
+    /// Average of the samples in the window, if any
+    pub fn average(&self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let sum = self.samples[..self.len]
+            .iter()
+            .fold(T::default(), |sum, &sample| sum + sample);
+        Some(sum / T::from(self.len as u16))
+    }
+
+    /// Number of samples in the window
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if no sample was pushed since creation or the last reset
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if the window holds `N` samples
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Drop every sample
+    pub const fn reset(&mut self) {
+        self.len = 0;
+        self.next = 0;
+    }
+}
+
+impl<T, const N: usize> Default for MovingAverage<T, N>
+where
+    T: Copy + Default + Add<Output = T> + Div<Output = T> + From<u16>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_then_rolling_window() {
+        let mut average = MovingAverage::<u32, 4>::new();
+        assert_eq!(average.average(), None);
+
+        assert_eq!(average.update(10), 10);
+        assert_eq!(average.update(20), 15);
+        assert!(!average.is_full());
+
+        average.push(30);
+        average.push(40);
+        assert!(average.is_full());
+        assert_eq!(average.average(), Some(25));
+
+        // 10 drops out of the window
+        assert_eq!(average.update(50), 35);
+        assert_eq!(average.len(), 4);
+    }
+
+    #[test]
+    fn test_float_and_reset() {
+        let mut average = MovingAverage::<f32, 3>::default();
+        average.push(1.0);
+        average.push(2.0);
+        assert_eq!(average.average(), Some(1.5));
+
+        average.reset();
+        assert!(average.is_empty());
+        assert_eq!(average.update(-4.0), -4.0);
+    }
+}
@@ -0,0 +1,237 @@
+//! Brightness ramps for sleep and wake transitions
+//!
+//! Switching the panel off at quiet hours is jarring, so [`BrightnessRamp`]
+//! fades the global brightness down to zero when going to sleep and back up
+//! when waking. Times are plain milliseconds from any monotonic clock.
+
+/// Daily quiet hours window, in minutes since midnight
+///
+/// The window may wrap past midnight (e.g. 22:00 to 07:00).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    /// Start of the quiet period (minutes since midnight)
+    pub sleep_at: u16,
+    /// End of the quiet period (minutes since midnight)
+    pub wake_at: u16,
+}
+
+impl QuietHours {
+    /// Minutes in a day
+    pub const MINUTES_PER_DAY: u16 = 24 * 60;
+
+    /// Create a quiet hours window from `hh:mm` boundaries
+    pub const fn new(sleep_hour: u8, sleep_minute: u8, wake_hour: u8, wake_minute: u8) -> Self {
+        Self {
+            sleep_at: sleep_hour as u16 * 60 + sleep_minute as u16,
+            wake_at: wake_hour as u16 * 60 + wake_minute as u16,
+        }
+    }
+
+    /// Check if `minute_of_day` falls within the quiet period
+    pub const fn is_quiet(&self, minute_of_day: u16) -> bool {
+        let minute = minute_of_day % Self::MINUTES_PER_DAY;
+        if self.sleep_at <= self.wake_at {
+            minute >= self.sleep_at && minute < self.wake_at
+        } else {
+            minute >= self.sleep_at || minute < self.wake_at
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Awake,
+    FadingOut { start_ms: u64, from: u8 },
+    Asleep,
+    FadingIn { start_ms: u64, from: u8 },
+}
+
+/// Brightness controller fading between sleep and wake
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrightnessRamp {
+    awake_level: u8,
+    fade_out_ms: u32,
+    fade_in_ms: u32,
+    phase: Phase,
+    /// Quiet state seen by the last [`apply_schedule`](Self::apply_schedule)
+    scheduled_quiet: Option<bool>,
+}
+
+impl BrightnessRamp {
+    /// Create an awake controller
+    ///
+    /// `awake_level` is the brightness outside quiet hours, `fade_out_ms` and
+    /// `fade_in_ms` the duration of the sleep and wake transitions.
+    pub const fn new(awake_level: u8, fade_out_ms: u32, fade_in_ms: u32) -> Self {
+        Self {
+            awake_level,
+            fade_out_ms,
+            fade_in_ms,
+            phase: Phase::Awake,
+            scheduled_quiet: None,
+        }
+    }
+
+    /// Change the brightness used while awake
+    pub const fn set_awake_level(&mut self, level: u8) {
+        self.awake_level = level;
+    }
+
+    /// Start fading to zero
+    ///
+    /// The fade starts from the current level, so interrupting a wake ramp
+    /// does not jump.
+    pub fn sleep(&mut self, now_ms: u64) {
+        if matches!(self.phase, Phase::Asleep | Phase::FadingOut { .. }) {
+            return;
+        }
+        self.phase = Phase::FadingOut {
+            start_ms: now_ms,
+            from: self.level(now_ms),
+        };
+    }
+
+    /// Start ramping up to the awake level
+    pub fn wake(&mut self, now_ms: u64) {
+        if matches!(self.phase, Phase::Awake | Phase::FadingIn { .. }) {
+            return;
+        }
+        self.phase = Phase::FadingIn {
+            start_ms: now_ms,
+            from: self.level(now_ms),
+        };
+    }
+
+    /// Wake up at full brightness immediately (e.g. on a button press)
+    pub const fn wake_now(&mut self) {
+        self.phase = Phase::Awake;
+    }
+
+    /// Follow a quiet hours schedule, starting a fade on each boundary
+    ///
+    /// Meant to be called periodically. Only boundary crossings act, so a
+    /// manual [`wake_now`](Self::wake_now) during quiet hours holds until the
+    /// next quiet period starts.
+    pub fn apply_schedule(&mut self, quiet: &QuietHours, minute_of_day: u16, now_ms: u64) {
+        let is_quiet = quiet.is_quiet(minute_of_day);
+        if self.scheduled_quiet == Some(is_quiet) {
+            return;
+        }
+        self.scheduled_quiet = Some(is_quiet);
+
+        if is_quiet {
+            self.sleep(now_ms);
+        } else {
+            self.wake(now_ms);
+        }
+    }
+
+    /// Check if the panel is fully off
+    pub fn is_asleep(&mut self, now_ms: u64) -> bool {
+        self.level(now_ms);
+        self.phase == Phase::Asleep
+    }
+
+    /// Get the brightness to use at `now_ms`
+    ///
+    /// Completed fades settle into the awake or asleep state.
+    pub fn level(&mut self, now_ms: u64) -> u8 {
+        match self.phase {
+            Phase::Awake => self.awake_level,
+            Phase::Asleep => 0,
+            Phase::FadingOut { start_ms, from } => {
+                let level = Self::interpolate(from, 0, start_ms, self.fade_out_ms, now_ms);
+                if level == 0 {
+                    self.phase = Phase::Asleep;
+                }
+                level
+            }
+            Phase::FadingIn { start_ms, from } => {
+                let level =
+                    Self::interpolate(from, self.awake_level, start_ms, self.fade_in_ms, now_ms);
+                if level == self.awake_level {
+                    self.phase = Phase::Awake;
+                }
+                level
+            }
+        }
+    }
+
+    fn interpolate(from: u8, to: u8, start_ms: u64, duration_ms: u32, now_ms: u64) -> u8 {
+        let elapsed = now_ms.saturating_sub(start_ms);
+        if duration_ms == 0 || elapsed >= u64::from(duration_ms) {
+            return to;
+        }
+
+        let from = i64::from(from);
+        let delta = i64::from(to) - from;
+        (from + delta * elapsed as i64 / i64::from(duration_ms)) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_hours_wrap_midnight() {
+        let quiet = QuietHours::new(22, 0, 7, 30);
+        assert!(quiet.is_quiet(23 * 60));
+        assert!(quiet.is_quiet(3 * 60));
+        assert!(!quiet.is_quiet(7 * 60 + 30));
+        assert!(!quiet.is_quiet(12 * 60));
+
+        let daytime = QuietHours::new(12, 0, 13, 0);
+        assert!(daytime.is_quiet(12 * 60 + 15));
+        assert!(!daytime.is_quiet(23 * 60));
+    }
+
+    #[test]
+    fn test_fade_out_and_in() {
+        let mut ramp = BrightnessRamp::new(200, 1_000, 2_000);
+        assert_eq!(ramp.level(0), 200);
+
+        ramp.sleep(1_000);
+        assert_eq!(ramp.level(1_500), 100);
+        assert!(!ramp.is_asleep(1_999));
+        assert_eq!(ramp.level(2_000), 0);
+        assert!(ramp.is_asleep(2_000));
+
+        ramp.wake(10_000);
+        assert_eq!(ramp.level(11_000), 100);
+        assert_eq!(ramp.level(12_000), 200);
+    }
+
+    #[test]
+    fn test_interrupted_fade_and_wake_now() {
+        let mut ramp = BrightnessRamp::new(200, 1_000, 1_000);
+        ramp.sleep(0);
+        assert_eq!(ramp.level(250), 150);
+
+        // Waking mid-fade ramps up from the current level
+        ramp.wake(250);
+        assert_eq!(ramp.level(250), 150);
+        assert_eq!(ramp.level(750), 175);
+
+        ramp.sleep(2_000);
+        ramp.wake_now();
+        assert_eq!(ramp.level(2_100), 200);
+    }
+
+    #[test]
+    fn test_apply_schedule() {
+        let quiet = QuietHours::new(22, 0, 7, 0);
+        let mut ramp = BrightnessRamp::new(255, 0, 0);
+        ramp.apply_schedule(&quiet, 22 * 60, 0);
+        assert!(ramp.is_asleep(0));
+
+        // A manual wake is not undone until the next quiet period
+        ramp.wake_now();
+        ramp.apply_schedule(&quiet, 23 * 60, 1);
+        assert_eq!(ramp.level(1), 255);
+
+        ramp.apply_schedule(&quiet, 7 * 60, 2);
+        ramp.apply_schedule(&quiet, 22 * 60, 3);
+        assert!(ramp.is_asleep(3));
+    }
+}
@@ -0,0 +1,87 @@
+//! Two-threshold switch
+//!
+//! A single threshold makes a state flap when the input hovers around it.
+//! [`Hysteresis`] switches on at one threshold and only switches back off
+//! past a second one, e.g. to throttle above 70 °C and resume below 65 °C.
+
+/// Boolean state driven by a value and two thresholds
+///
+/// The state turns on once the value reaches `on` and turns off once it
+/// drops below `off`. With `on` below `off` the comparison is inverted: the
+/// state turns on at or below `on` and off above `off`, for inputs where low
+/// values are the trigger (e.g. dimming in the dark).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hysteresis<T> {
+    on: T,
+    off: T,
+    active: bool,
+}
+
+impl<T: Copy + PartialOrd> Hysteresis<T> {
+    /// Create an inactive switch
+    pub const fn new(on: T, off: T) -> Self {
+        Self {
+            on,
+            off,
+            active: false,
+        }
+    }
+
+    /// Feed a new value, returning the updated state
+    pub fn update(&mut self, value: T) -> bool {
+        self.active = self.next_state(value);
+        self.active
+    }
+
+    /// Feed a new value, returning the new state only if it changed
+    pub fn update_changed(&mut self, value: T) -> Option<bool> {
+        let was_active = self.active;
+        (self.update(value) != was_active).then_some(self.active)
+    }
+
+    /// State the switch would take for `value`, without updating it
+    pub fn next_state(&self, value: T) -> bool {
+        let rising = self.on >= self.off;
+        match (self.active, rising) {
+            (false, true) => value >= self.on,
+            (true, true) => value >= self.off,
+            (false, false) => value <= self.on,
+            (true, false) => value <= self.off,
+        }
+    }
+
+    /// Current state
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Force the state, e.g. to start active after a reboot
+    pub const fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rising_thresholds() {
+        let mut throttle = Hysteresis::new(70, 65);
+        assert!(!throttle.update(69));
+        assert!(throttle.update(70));
+        assert!(throttle.update(65));
+        assert!(!throttle.update(64));
+        assert!(!throttle.update(69));
+    }
+
+    #[test]
+    fn test_falling_thresholds_and_changes() {
+        let mut dark = Hysteresis::new(10.0_f32, 20.0);
+        assert_eq!(dark.update_changed(15.0), None);
+        assert_eq!(dark.update_changed(10.0), Some(true));
+        assert_eq!(dark.update_changed(18.0), None);
+        assert_eq!(dark.update_changed(20.5), Some(false));
+        assert!(!dark.is_active());
+    }
+}
@@ -0,0 +1,236 @@
+//! Images downloaded from the server
+//!
+//! Assets such as event banners come as raw RGB565 or as 8-bit indexed
+//! color, which halves their size for the flat colors they are mostly made
+//! of. An asset starts with a header, all numbers little-endian:
+//!
+//! ```text
+//! [format: u8][reserved: u8][width: u16][height: u16][palette length: u16]
+//! [palette: RGB565 u16 each][pixels: row by row]
+//! ```
+//!
+//! Pixels are an RGB565 `u16` each with [`Format::Rgb565`] (no palette), and
+//! a palette index byte each with [`Format::Indexed`]. An [`AssetImage`] is
+//! drawn with `embedded_graphics::image::Image`:
+//!
+//! ```
+//! # use embedded_graphics::{image::Image, mock_display::MockDisplay, pixelcolor::Rgb565, prelude::*};
+//! # use graphics_common::utilities::image::AssetImage;
+//! // 2x1 indexed image: red then blue
+//! let asset = [1, 0, 2, 0, 1, 0, 2, 0, 0x00, 0xF8, 0x1F, 0x00, 0, 1];
+//! let image = AssetImage::parse(&asset).unwrap();
+//! let mut display = MockDisplay::<Rgb565>::new();
+//! Image::new(&image, Point::new(3, 4)).draw(&mut display).unwrap();
+//! assert_eq!(display.get_pixel(Point::new(4, 4)), Some(Rgb565::BLUE));
+//! ```
+
+use embedded_graphics::{
+    image::ImageDrawable,
+    pixelcolor::{Rgb565, raw::RawU16},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+/// Size of the asset header in bytes
+pub const HEADER_SIZE: usize = 8;
+
+/// Pixel format of an asset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Two bytes per pixel
+    Rgb565,
+    /// One palette index per pixel
+    Indexed,
+}
+
+/// Errors that can occur while reading an asset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageError {
+    /// Unknown pixel format
+    UnknownFormat,
+    /// Fewer bytes than the header announces
+    Truncated,
+    /// A pixel refers to a color past the end of the palette
+    BadIndex,
+}
+
+/// Image over the bytes of an asset
+#[derive(Clone, Copy, Debug)]
+pub struct AssetImage<'a> {
+    format: Format,
+    size: Size,
+    palette: &'a [u8],
+    pixels: &'a [u8],
+}
+
+impl<'a> AssetImage<'a> {
+    /// Check an asset and read its header
+    pub fn parse(data: &'a [u8]) -> Result<Self, ImageError> {
+        let header = data.get(..HEADER_SIZE).ok_or(ImageError::Truncated)?;
+        let read_u16 = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let format = match header[0] {
+            0 => Format::Rgb565,
+            1 => Format::Indexed,
+            _ => return Err(ImageError::UnknownFormat),
+        };
+        let width = read_u16(2);
+        let height = read_u16(4);
+        let palette_len = usize::from(read_u16(6));
+
+        let (palette, pixels) = data[HEADER_SIZE..]
+            .split_at_checked(palette_len * 2)
+            .ok_or(ImageError::Truncated)?;
+        let pixel_count = usize::from(width) * usize::from(height);
+        let pixels = match format {
+            Format::Rgb565 => pixels.get(..pixel_count * 2),
+            Format::Indexed => pixels.get(..pixel_count),
+        }
+        .ok_or(ImageError::Truncated)?;
+
+        if format == Format::Indexed && pixels.iter().any(|&i| usize::from(i) >= palette_len) {
+            return Err(ImageError::BadIndex);
+        }
+
+        Ok(Self {
+            format,
+            size: Size::new(width.into(), height.into()),
+            palette,
+            pixels,
+        })
+    }
+
+    /// Pixel format of the asset
+    pub const fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Color of the pixel at index `i` in row-major order
+    fn color(&self, i: usize) -> Rgb565 {
+        let (bytes, offset) = match self.format {
+            Format::Rgb565 => (self.pixels, i * 2),
+            Format::Indexed => (self.palette, usize::from(self.pixels[i]) * 2),
+        };
+        RawU16::new(u16::from_le_bytes([bytes[offset], bytes[offset + 1]])).into()
+    }
+}
+
+impl OriginDimensions for AssetImage<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl ImageDrawable for AssetImage<'_> {
+    type Color = Rgb565;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let pixel_count = self.size.width as usize * self.size.height as usize;
+        target.fill_contiguous(
+            &self.bounding_box(),
+            (0..pixel_count).map(|i| self.color(i)),
+        )
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let area = self.bounding_box().intersection(area);
+        let width = self.size.width as usize;
+        target.fill_contiguous(
+            &Rectangle::new(Point::zero(), area.size),
+            area.points()
+                .map(|point| self.color(point.y as usize * width + point.x as usize)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::image::{Image, ImageRaw};
+    use embedded_graphics::mock_display::MockDisplay;
+    use embedded_graphics::pixelcolor::raw::LittleEndian;
+
+    /// 3x2 RGB565 asset and its pixels
+    fn rgb565() -> ([u8; HEADER_SIZE + 12], [u8; 12]) {
+        let mut pixels = [0; 12];
+        let colors = [
+            Rgb565::RED,
+            Rgb565::GREEN,
+            Rgb565::BLUE,
+            Rgb565::WHITE,
+            Rgb565::YELLOW,
+            Rgb565::CYAN,
+        ];
+        for (bytes, color) in pixels.as_chunks_mut::<2>().0.iter_mut().zip(colors) {
+            *bytes = RawU16::from(color).into_inner().to_le_bytes();
+        }
+
+        let mut asset = [0; HEADER_SIZE + 12];
+        asset[..HEADER_SIZE].copy_from_slice(&[0, 0, 3, 0, 2, 0, 0, 0]);
+        asset[HEADER_SIZE..].copy_from_slice(&pixels);
+        (asset, pixels)
+    }
+
+    #[test]
+    fn test_rgb565_matches_raw_image() {
+        let (asset, pixels) = rgb565();
+        let image = AssetImage::parse(&asset).unwrap();
+        assert_eq!(image.size(), Size::new(3, 2));
+
+        let mut drawn = MockDisplay::<Rgb565>::new();
+        Image::new(&image, Point::new(1, 2))
+            .draw(&mut drawn)
+            .unwrap();
+
+        let raw = ImageRaw::<Rgb565, LittleEndian>::new(&pixels, 3);
+        let mut expected = MockDisplay::<Rgb565>::new();
+        Image::new(&raw, Point::new(1, 2))
+            .draw(&mut expected)
+            .unwrap();
+
+        drawn.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_sub_image() {
+        let (asset, pixels) = rgb565();
+        let image = AssetImage::parse(&asset).unwrap();
+        let area = Rectangle::new(Point::new(1, 0), Size::new(2, 2));
+
+        let mut drawn = MockDisplay::<Rgb565>::new();
+        Image::new(&image.sub_image(&area), Point::zero())
+            .draw(&mut drawn)
+            .unwrap();
+
+        let raw = ImageRaw::<Rgb565, LittleEndian>::new(&pixels, 3);
+        let mut expected = MockDisplay::<Rgb565>::new();
+        Image::new(&raw.sub_image(&area), Point::zero())
+            .draw(&mut expected)
+            .unwrap();
+
+        drawn.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_invalid_assets() {
+        let (asset, _) = rgb565();
+        assert_eq!(
+            AssetImage::parse(&asset[..asset.len() - 1]).err(),
+            Some(ImageError::Truncated)
+        );
+        assert_eq!(
+            AssetImage::parse(&[7, 0, 1, 0, 1, 0, 0, 0, 0, 0]).err(),
+            Some(ImageError::UnknownFormat)
+        );
+        // Index 1 with a single color palette
+        assert_eq!(
+            AssetImage::parse(&[1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 1]).err(),
+            Some(ImageError::BadIndex)
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Subpixel scrolling by temporal dithering
+//!
+//! Text moving slower than a pixel per frame only steps every few frames,
+//! unevenly unless the speed divides the frame rate, which judders on the
+//! LED matrix. [`SubpixelScroll`] keeps the position in 1/256 pixel and,
+//! between two pixels, shows the farther one on a share of the frames equal
+//! to the fraction: at 10.25 pixels the text is at 11 about one frame in
+//! four and at 10 otherwise. At 60 fps the eye averages the frames and the
+//! text glides.
+
+/// Steps of a pixel in positions and speeds
+pub const SUBPIXELS: u32 = 256;
+
+/// Spreads the dither thresholds of consecutive frames over 0..256, about
+/// 256 divided by the golden ratio so no speed lines up with the pattern
+const THRESHOLD_STEP: u32 = 159;
+
+/// Position of text wrapping around a loop of `period` pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubpixelScroll {
+    /// Subpixels per frame
+    speed: u32,
+    /// Loop length in subpixels, never 0
+    period: u32,
+}
+
+impl SubpixelScroll {
+    /// Scroll `speed` 256ths of a pixel per frame around `period` pixels
+    pub const fn new(speed: u32, period: u32) -> Self {
+        let period = if period == 0 { 1 } else { period };
+        Self {
+            speed,
+            period: period.saturating_mul(SUBPIXELS),
+        }
+    }
+
+    /// Exact position at `frame`, in 256ths of a pixel
+    pub const fn position(&self, frame: u32) -> u32 {
+        ((frame as u64 * self.speed as u64) % self.period as u64) as u32
+    }
+
+    /// Pixel offset to draw at `frame`, in `0..period`
+    ///
+    /// The pixel before or after [`position`](Self::position), so the
+    /// average over a few frames is the exact position.
+    pub const fn offset(&self, frame: u32) -> u32 {
+        let threshold = frame.wrapping_mul(THRESHOLD_STEP) % SUBPIXELS;
+        let dithered = self.position(frame) + threshold;
+        (dithered % self.period) / SUBPIXELS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whole_pixel_speeds_are_not_dithered() {
+        let scroll = SubpixelScroll::new(2 * SUBPIXELS, 10);
+        let offsets: [u32; 6] = core::array::from_fn(|frame| scroll.offset(frame as u32));
+        assert_eq!(offsets, [0, 2, 4, 6, 8, 0]);
+    }
+
+    #[test]
+    fn test_fraction_sets_the_share_of_farther_frames() {
+        // A tenth of a pixel per frame: the text is one pixel ahead of the
+        // exact position's pixel on a share of frames equal to the fraction
+        let scroll = SubpixelScroll::new(SUBPIXELS / 10, 1000);
+        let (mut ahead, mut expected) = (0, 0);
+        for frame in 0..2560 {
+            let offset = scroll.offset(frame);
+            let exact = scroll.position(frame) / SUBPIXELS;
+            assert!(offset == exact || offset == exact + 1);
+            ahead += (offset > exact) as u32 * SUBPIXELS;
+            expected += scroll.position(frame) % SUBPIXELS;
+        }
+        let error = ahead.abs_diff(expected);
+        assert!(error < expected / 20, "{ahead} vs {expected}");
+    }
+
+    #[test]
+    fn test_wraps_around_the_period() {
+        let scroll = SubpixelScroll::new(SUBPIXELS / 2, 4);
+        assert_eq!(scroll.position(8), 0);
+        assert!((0..1000).all(|frame| scroll.offset(frame) < 4));
+        assert_eq!(SubpixelScroll::new(SUBPIXELS, 0).offset(5), 0);
+    }
+}
@@ -0,0 +1,227 @@
+//! One logical canvas over chained panels
+//!
+//! Chained panels form one long physical display: four 64x64 panels are a
+//! 256x64 strip to the driver, whatever their arrangement on the wall. A
+//! [`TiledCanvas`] hides the chain: each [`Tile`] maps an area of the logical
+//! canvas to the position of its panel in the chain, with the rotation the
+//! panel is mounted with, and drawing goes through the tiles to the driver.
+//!
+//! ```
+//! # use embedded_graphics::{prelude::*, primitives::Rectangle};
+//! # use graphics_common::utilities::tiled::Tile;
+//! // 128x128 made of two 128x64 rows, the top row at the end of the chain
+//! const TILES: [Tile; 2] = [
+//!     Tile::new(Rectangle::new(Point::new(0, 0), Size::new(128, 64)), Point::new(128, 0)),
+//!     Tile::new(Rectangle::new(Point::new(0, 64), Size::new(128, 64)), Point::new(0, 0)),
+//! ];
+//! ```
+
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+
+/// Clockwise rotation of a mounted panel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+/// Area of the logical canvas shown by one panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    /// Logical area
+    pub area: Rectangle,
+    /// Top-left corner of the panel in the chain
+    pub offset: Point,
+    pub rotation: Rotation,
+}
+
+impl Tile {
+    /// Tile showing `area` on the panel at `offset`, without rotation
+    pub const fn new(area: Rectangle, offset: Point) -> Self {
+        Self {
+            area,
+            offset,
+            rotation: Rotation::Normal,
+        }
+    }
+
+    /// Set the rotation the panel is mounted with
+    pub const fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Physical position of a logical point, if the tile shows it
+    pub fn map(&self, point: Point) -> Option<Point> {
+        if !self.area.contains(point) {
+            return None;
+        }
+        let local = point - self.area.top_left;
+        Some(self.offset + self.rotate(local))
+    }
+
+    /// Position of a point of the area once the panel is rotated
+    fn rotate(&self, local: Point) -> Point {
+        let width = self.area.size.width as i32;
+        let height = self.area.size.height as i32;
+        match self.rotation {
+            Rotation::Normal => local,
+            Rotation::Rotate90 => Point::new(height - 1 - local.y, local.x),
+            Rotation::Rotate180 => Point::new(width - 1 - local.x, height - 1 - local.y),
+            Rotation::Rotate270 => Point::new(local.y, width - 1 - local.x),
+        }
+    }
+
+    /// Physical rectangle of a logical one inside the tile
+    fn map_rect(&self, rect: &Rectangle) -> Option<Rectangle> {
+        let rect = self.area.intersection(rect);
+        let bottom_right = rect.bottom_right()?;
+        let a = self.map(rect.top_left)?;
+        let b = self.map(bottom_right)?;
+        Some(Rectangle::with_corners(a, b))
+    }
+}
+
+/// Draw target mapping a logical canvas onto chained panels
+///
+/// The canvas spans the bounding box of the tiles. Points outside every
+/// tile are dropped; where tiles overlap, the first one wins.
+pub struct TiledCanvas<'a, D> {
+    target: &'a mut D,
+    tiles: &'a [Tile],
+}
+
+impl<'a, D> TiledCanvas<'a, D> {
+    pub const fn new(target: &'a mut D, tiles: &'a [Tile]) -> Self {
+        Self { target, tiles }
+    }
+
+    /// The underlying chain
+    pub fn target_mut(&mut self) -> &mut D {
+        self.target
+    }
+
+    /// Physical position of a logical point
+    pub fn map(&self, point: Point) -> Option<Point> {
+        self.tiles.iter().find_map(|tile| tile.map(point))
+    }
+}
+
+impl<D> OriginDimensions for TiledCanvas<'_, D> {
+    fn size(&self) -> Size {
+        let (width, height) = self.tiles.iter().fold((0, 0), |(width, height), tile| {
+            let end = tile.area.top_left + tile.area.size;
+            (width.max(end.x), height.max(end.y))
+        });
+        Size::new(width.max(0) as u32, height.max(0) as u32)
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for TiledCanvas<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let tiles = self.tiles;
+        self.target
+            .draw_iter(pixels.into_iter().filter_map(|Pixel(point, color)| {
+                let point = tiles.iter().find_map(|tile| tile.map(point))?;
+                Some(Pixel(point, color))
+            }))
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        // A rectangle stays a rectangle on each tile, so fill tile by tile;
+        // overlapping tiles may be filled twice with the same color
+        for tile in self.tiles {
+            if let Some(rect) = tile.map_rect(area) {
+                self.target.fill_solid(&rect, color)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestCanvas;
+    use embedded_graphics::pixelcolor::Rgb565;
+    use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+
+    /// 256x64 chain recording the drawn pixels
+    fn chain() -> TestCanvas {
+        TestCanvas::new(Size::new(256, 64))
+    }
+
+    /// 128x128 as wired on the cluster panel: top half at the end of the chain
+    const TILES: [Tile; 2] = [
+        Tile::new(
+            Rectangle::new(Point::new(0, 0), Size::new(128, 64)),
+            Point::new(128, 0),
+        ),
+        Tile::new(
+            Rectangle::new(Point::new(0, 64), Size::new(128, 64)),
+            Point::new(0, 0),
+        ),
+    ];
+
+    #[test]
+    fn test_pixels_follow_the_chain() {
+        let mut chain = chain();
+        let mut canvas = TiledCanvas::new(&mut chain, &TILES);
+        assert_eq!(canvas.size(), Size::new(128, 128));
+
+        let Ok(()) = Pixel(Point::new(3, 5), Rgb565::RED).draw(&mut canvas);
+        let Ok(()) = Pixel(Point::new(3, 69), Rgb565::GREEN).draw(&mut canvas);
+        let Ok(()) = Pixel(Point::new(200, 5), Rgb565::BLUE).draw(&mut canvas);
+
+        assert_eq!(chain.pixel_at(Point::new(131, 5)), Some(Rgb565::RED));
+        assert_eq!(chain.pixel_at(Point::new(3, 5)), Some(Rgb565::GREEN));
+        assert_eq!(chain.count_color(Rgb565::BLUE), 0);
+    }
+
+    #[test]
+    fn test_fill_matches_pixel_drawing() {
+        let tiles = [TILES[0].with_rotation(Rotation::Rotate180), TILES[1]];
+        let circle =
+            Circle::new(Point::new(40, 30), 60).into_styled(PrimitiveStyle::with_fill(Rgb565::RED));
+        let rect = Rectangle::new(Point::new(-10, 50), Size::new(90, 30));
+
+        // Filled through fill_solid
+        let mut filled = chain();
+        let mut canvas = TiledCanvas::new(&mut filled, &tiles);
+        let Ok(()) = circle.draw(&mut canvas);
+        let Ok(()) = canvas.fill_solid(&rect, Rgb565::GREEN);
+
+        // Same shapes pixel by pixel
+        let mut drawn = chain();
+        let mut canvas = TiledCanvas::new(&mut drawn, &tiles);
+        let Ok(()) = circle.draw(&mut canvas);
+        let Ok(()) = canvas.draw_iter(rect.points().map(|point| Pixel(point, Rgb565::GREEN)));
+
+        assert!(filled == drawn);
+    }
+
+    #[test]
+    fn test_rotations() {
+        let area = Rectangle::new(Point::new(0, 0), Size::new(4, 2));
+        let corner = Point::new(3, 0);
+        let rotated = |rotation| {
+            Tile::new(area, Point::zero())
+                .with_rotation(rotation)
+                .map(corner)
+        };
+
+        assert_eq!(rotated(Rotation::Normal), Some(Point::new(3, 0)));
+        assert_eq!(rotated(Rotation::Rotate90), Some(Point::new(1, 3)));
+        assert_eq!(rotated(Rotation::Rotate180), Some(Point::new(0, 1)));
+        assert_eq!(rotated(Rotation::Rotate270), Some(Point::new(0, 0)));
+    }
+}
@@ -1,29 +1,95 @@
 //! Test the C plugin loading system on real hardware
 //! This binary loads the embedded plasma plugin and runs it on the LED matrix
+//!
+//! Plugin storage is kept in the last flash sector, reserved in memory.x, so
+//! saved values survive a reboot. Plugin tones play on a piezo buzzer between
+//! PIN_15 and GND.
+//!
+//! Plugin updates run under a budget: a plugin whose update runs late is
+//! stopped by the host, and one stuck in its update is stopped by the
+//! watchdog resetting the board. Either way an error screen names it, and
+//! after a reset the plugin is not loaded again. Storage changes not yet
+//! persisted are lost in a reset.
+//!
+//! Plugins read their cluster data from the sample layout of the
+//! `cluster_sim_hard` test.
+//!
+//! With the `usb-loader` feature, plugins uploaded over USB replace the main
+//! plugin as they arrive.
 
 #![no_std]
 #![no_main]
 
-use basic_panel::{CORE1_STACK, DISPLAY_MEMORY, DmaChannels, EXECUTOR1, Hub75Pins};
+use basic_panel::piezo::Piezo;
+use basic_panel::plugin_watchdog;
+use basic_panel::{
+    CORE1_STACK, DISPLAY_MEMORY, DmaChannels, EXECUTOR1, Hub75Pins, LAYOUT, helpers,
+};
 use core::ptr::addr_of_mut;
 use defmt::{info, unwrap, warn};
 use embassy_executor::{Executor, Spawner};
+use embassy_rp::flash::{Blocking, ERASE_SIZE, Flash};
 use embassy_rp::multicore::spawn_core1;
 use embassy_rp::peripherals::*;
-use embassy_rp::{Peri, gpio};
-use embassy_time::{Duration, Timer};
+use embassy_rp::{Peri, gpio, pwm};
+use embassy_sync::rwlock::RwLock;
+use embassy_time::{Duration, Instant, Timer};
 use hub75_rp2350_driver::{
     COLOR_BITS, DISPLAY_HEIGHT, DISPLAY_WIDTH, DisplayMemory, Hub75, lut::GAMMA8,
 };
-use plugin_host::PluginRuntime;
+use plugin_api::DisplayInfo;
+use plugin_api::permissions::{self, PLUGIN_PERM_SOUND, PLUGIN_PERM_STORAGE};
+use plugin_api::storage::STORAGE_SIZE;
+use plugin_host::{PluginRuntime, Slot, Viewport};
 use {defmt_rtt as _, panic_probe as _};
 
+/// Permissions this device grants to plugins that request them
+const GRANTED_PERMISSIONS: u32 = PLUGIN_PERM_STORAGE | PLUGIN_PERM_SOUND;
+
+/// Total flash size, must match memory.x plus the storage sector
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Offset of the plugin storage sector from the start of flash
+const STORAGE_OFFSET: u32 = (FLASH_SIZE - STORAGE_SIZE) as u32;
+
+/// Frames between checks for storage changes to persist (~10 s)
+///
+/// Plugins may save on every change, this bounds the flash erases.
+const STORAGE_PERSIST_FRAMES: u32 = 600;
+
+const _: () = assert!(STORAGE_SIZE % ERASE_SIZE == 0);
+
+/// Width of the panels, the 128x128 display being two 128x64 panels chained
+const PANEL_WIDTH: usize = if DISPLAY_WIDTH > 128 {
+    128
+} else {
+    DISPLAY_WIDTH
+};
+
+/// Panels as the PIO driver shows them to plugins
+const DISPLAY_INFO: DisplayInfo = DisplayInfo {
+    // Measured refresh of the PIO driver at 8 bit planes
+    refresh_hz: 2100,
+    color_depth: COLOR_BITS as u32,
+    panel_width: PANEL_WIDTH as u32,
+    panel_height: DISPLAY_HEIGHT as u32,
+    chain_length: (DISPLAY_WIDTH / PANEL_WIDTH) as u32,
+};
+
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     let p = embassy_rp::init(Default::default());
 
     info!("Plugin Test Starting!");
 
+    let hung = plugin_watchdog::init(p.WATCHDOG);
+    if let Some(slot) = hung {
+        warn!(
+            "Reset by the watchdog: the {} plugin hung in its update",
+            slot
+        );
+    }
+
     // Spawn Core 1 to handle led blinking
     let led = gpio::Output::new(p.PIN_25, gpio::Level::Low);
     spawn_core1(
@@ -65,11 +131,35 @@ async fn main(spawner: Spawner) {
     };
 
     // Core 0 handles Hub75 matrix with plugins
-    spawner.spawn(unwrap!(matrix_task(p.PIO0, dma_channels, pins)));
+    // Piezo on PIN_15, output B of PWM slice 7
+    let piezo = Piezo::new(pwm::Pwm::new_output_b(
+        p.PWM_SLICE7,
+        p.PIN_15,
+        pwm::Config::default(),
+    ));
+
+    #[cfg(feature = "usb-loader")]
+    basic_panel::usb_loader::start(&spawner, p.USB);
+
+    spawner.spawn(unwrap!(matrix_task(
+        p.PIO0,
+        dma_channels,
+        pins,
+        p.FLASH,
+        piezo,
+        hung
+    )));
 }
 
 #[embassy_executor::task]
-async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins: Hub75Pins) {
+async fn matrix_task(
+    pio: Peri<'static, PIO0>,
+    dma_channels: DmaChannels,
+    pins: Hub75Pins,
+    flash: Peri<'static, FLASH>,
+    mut piezo: Piezo<'static>,
+    hung: Option<Slot>,
+) {
     info!("Starting Hub75 LED matrix with plugin system");
 
     // Create the LED matrix driver with PIO + DMA
@@ -104,14 +194,52 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
 
     // Initialize the plugin runtime
     let runtime = PluginRuntime::init();
+    runtime.set_granted_permissions(GRANTED_PERMISSIONS);
+    runtime.set_clock(|| Instant::now().as_micros());
+    runtime.set_watchdog(plugin_watchdog::HOOKS);
+    runtime.set_display_info(DISPLAY_INFO);
+    match helpers::create_sample_layout() {
+        Ok(layout) => runtime.set_layout(LAYOUT.init(RwLock::new(layout))),
+        Err(e) => warn!("Failed to create sample cluster layout: {}", e),
+    }
     info!("Plugin runtime initialized");
 
+    // Restore what plugins saved before the last reboot
+    let mut flash = Flash::<_, Blocking, FLASH_SIZE>::new_blocking(flash);
+    let mut image = [0u8; STORAGE_SIZE];
+    match flash.blocking_read(STORAGE_OFFSET, &mut image) {
+        Ok(()) if runtime.load_storage(&image) => info!("Plugin storage loaded from flash"),
+        Ok(()) => info!("No plugin storage in flash"),
+        Err(_) => warn!("Failed to read plugin storage"),
+    }
+
     // List available plugins
     let plugin_list = plugin_host::get_plugin_list();
     info!("Available plugins: {}", plugin_list.len());
 
-    for (name, bytes) in plugin_list {
-        info!("  - {} ({} bytes)", name, bytes.len());
+    for plugin in plugin_host::plugin_registry() {
+        info!(
+            "  - {} \"{}\" ({} bytes, API v{}{})",
+            plugin.id,
+            plugin.name,
+            plugin.bytes.len(),
+            plugin.api_version,
+            if plugin.icon_offset.is_some() {
+                ", icon"
+            } else {
+                ""
+            }
+        );
+        if !plugin.supports_host() {
+            warn!("      does not support this host");
+        }
+        let requested = plugin.permissions;
+        for permission in permissions::names(requested) {
+            info!("      requests {}", permission);
+        }
+        for permission in permissions::names(requested & !GRANTED_PERMISSIONS) {
+            warn!("      {} will be denied", permission);
+        }
     }
 
     // Find and load the quadrant plugin
@@ -132,14 +260,36 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
     let (plugin_name, plugin_bytes) = plugin_to_load;
     info!("Loading plugin: {}", plugin_name);
 
-    match runtime.load_plugin(plugin_bytes) {
-        Ok(()) => {
-            info!("Plugin loaded successfully!");
+    if hung == Some(Slot::Main) {
+        // Loading it again would hang again, keep the error screen instead
+        warn!("Not loading {}, it hung before the reset", plugin_name);
+        runtime.show_fault(plugin_name);
+    } else {
+        // Embedded plugins stay in flash, where segmented ones read their assets
+        match runtime.load_flash_plugin(plugin_bytes) {
+            Ok(()) => {
+                info!("Plugin loaded successfully!");
+            }
+            Err(e) => {
+                warn!("Failed to load plugin: {:?}", e);
+                loop {
+                    Timer::after(Duration::from_secs(1)).await;
+                }
+            }
         }
-        Err(e) => {
-            warn!("Failed to load plugin: {:?}", e);
-            loop {
-                Timer::after(Duration::from_secs(1)).await;
+    }
+
+    // Run a second plugin picture-in-picture in the bottom-right corner
+    if let Some((pip_name, pip_bytes)) = plugin_list.iter().find(|(name, _)| name != plugin_name) {
+        if hung == Some(Slot::Pip) {
+            warn!(
+                "Not loading PiP plugin {}, it hung before the reset",
+                pip_name
+            );
+        } else {
+            info!("Loading PiP plugin: {}", pip_name);
+            if let Err(e) = runtime.load_pip_plugin(pip_bytes, Viewport::bottom_right(48, 48)) {
+                warn!("Failed to load PiP plugin: {:?}", e);
             }
         }
     }
@@ -164,11 +314,30 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
             info!("Plugin FPS: {}", fps);
         }
 
+        #[cfg(feature = "usb-loader")]
+        match basic_panel::usb_loader::LOADER.poll(runtime) {
+            Some(Ok(())) => info!("Loaded plugin uploaded over USB"),
+            Some(Err(e)) => warn!("Failed to load plugin uploaded over USB: {}", e),
+            None => {}
+        }
+
         // Run the plugin's update function
         let update_start = embassy_time::Instant::now();
         runtime.update(0); // No input for now
         let update_time = update_start.elapsed();
 
+        // The PiP plugin going away leaves the main one on screen
+        if let Some(fault) = runtime.take_fault()
+            && fault.slot == Slot::Main
+        {
+            runtime.show_fault(fault.name);
+        }
+
+        if let Some(tone) = runtime.take_tone() {
+            piezo.play(tone.freq_hz, tone.duration_ms);
+        }
+        piezo.poll();
+
         // Copy the plugin's framebuffer to the display
         // The plugin renders to a 128x128 buffer, we need to copy it to the display
         let copy_start = embassy_time::Instant::now();
@@ -189,6 +358,12 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
             );
         }
 
+        if frame_counter.is_multiple_of(STORAGE_PERSIST_FRAMES)
+            && let Some(image) = runtime.storage_to_persist()
+        {
+            persist_storage(&mut flash, image);
+        }
+
         // Increment frame counter
         frame_counter = frame_counter.wrapping_add(1);
 
@@ -197,6 +372,20 @@ async fn matrix_task(pio: Peri<'static, PIO0>, dma_channels: DmaChannels, pins:
     }
 }
 
+/// Write the plugin storage image to its flash sector
+///
+/// The display keeps scanning from RAM while flash is erased, only the plugin
+/// loop stalls.
+fn persist_storage(flash: &mut Flash<'_, FLASH, Blocking, FLASH_SIZE>, image: &[u8; STORAGE_SIZE]) {
+    let result = flash
+        .blocking_erase(STORAGE_OFFSET, STORAGE_OFFSET + STORAGE_SIZE as u32)
+        .and_then(|()| flash.blocking_write(STORAGE_OFFSET, image));
+    match result {
+        Ok(()) => info!("Plugin storage persisted to flash"),
+        Err(_) => warn!("Failed to persist plugin storage"),
+    }
+}
+
 /// Copy the plugin's framebuffer to the display using optimized direct buffer writes
 /// Plugin renders to 128x128, driver transforms coords to 256x64 physical layout
 fn copy_framebuffer_to_display(plugin_fb: &plugin_api::FrameBuffer, display: &mut Hub75) {
@@ -26,6 +26,10 @@ pub static SELECTED_CLUSTER: StaticCell<Channel<CriticalSectionRawMutex, Cluster
     StaticCell::new();
 
 pub mod helpers;
+pub mod piezo;
+pub mod plugin_watchdog;
+#[cfg(feature = "usb-loader")]
+pub mod usb_loader;
 
 pub struct Hub75Pins {
     // RGB data pins
@@ -0,0 +1,70 @@
+//! Piezo buzzer driven by a PWM slice, for the plugins' `tone_fn`
+
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::pwm::{Config, Pwm};
+use embassy_time::{Duration, Instant};
+
+/// Lowest and highest frequencies played, about the audible range
+const MIN_FREQ_HZ: u32 = 20;
+const MAX_FREQ_HZ: u32 = 20_000;
+
+/// Square wave on a PWM output, with a duration
+///
+/// The pin and slice are picked by whoever creates the [`Pwm`]; both
+/// channels get the same duty cycle, so either output of the slice works.
+pub struct Piezo<'d> {
+    pwm: Pwm<'d>,
+    /// When the current tone ends, `None` while silent
+    until: Option<Instant>,
+}
+
+impl<'d> Piezo<'d> {
+    pub fn new(mut pwm: Pwm<'d>) -> Self {
+        pwm.set_config(&Self::silence());
+        Self { pwm, until: None }
+    }
+
+    /// Play `freq_hz` for `duration_ms`, replacing the current tone
+    ///
+    /// 0 Hz stops the tone, other frequencies are clamped to 20 Hz..20 kHz.
+    pub fn play(&mut self, freq_hz: u32, duration_ms: u32) {
+        if freq_hz == 0 || duration_ms == 0 {
+            self.stop();
+            return;
+        }
+
+        // Smallest integer divider that keeps the period within 16 bits
+        let freq_hz = freq_hz.clamp(MIN_FREQ_HZ, MAX_FREQ_HZ);
+        let clock = clk_sys_freq();
+        let divider = (clock / (freq_hz * 65_536) + 1).min(u32::from(u8::MAX));
+        let top = (clock / (divider * freq_hz)).clamp(2, 65_536) - 1;
+
+        let mut config = Config::default();
+        config.divider = (divider as u8).into();
+        config.top = top as u16;
+        config.compare_a = config.top / 2;
+        config.compare_b = config.top / 2;
+        self.pwm.set_config(&config);
+        self.until = Some(Instant::now() + Duration::from_millis(duration_ms.into()));
+    }
+
+    /// Stop the tone once its duration is over, to call every frame
+    pub fn poll(&mut self) {
+        if self.until.is_some_and(|until| Instant::now() >= until) {
+            self.stop();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.pwm.set_config(&Self::silence());
+        self.until = None;
+    }
+
+    /// Output held low
+    fn silence() -> Config {
+        let mut config = Config::default();
+        config.compare_a = 0;
+        config.compare_b = 0;
+        config
+    }
+}
@@ -0,0 +1,75 @@
+//! Hardware watchdog around plugin updates, for the host's `UpdateWatchdog`
+//!
+//! The plugin host only stops updates that return late. One that never
+//! returns keeps the core, so [`HOOKS`] start the RP2350 watchdog before
+//! each update and stop it after: a plugin stuck in its update resets the
+//! board. The slot being updated is kept in a watchdog scratch register,
+//! which survives that reset, for [`init`] to tell which plugin hung.
+
+use core::cell::RefCell;
+use embassy_rp::Peri;
+use embassy_rp::peripherals::WATCHDOG;
+use embassy_rp::watchdog::{ResetReason, Watchdog};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::Duration;
+use plugin_host::{Slot, UpdateWatchdog};
+
+/// Hooks to pass to `PluginRuntime::set_watchdog`
+pub const HOOKS: UpdateWatchdog = UpdateWatchdog { arm, disarm };
+
+/// Scratch register holding the slot being updated
+const SCRATCH_INDEX: usize = 0;
+
+/// Scratch value while a slot is updated, with the slot in the low bit
+const ARMED_MAGIC: u32 = 0x5744_4700; // "WDG\0"
+
+static WATCHDOG: Mutex<CriticalSectionRawMutex, RefCell<Option<Watchdog>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Take the watchdog, returning the slot whose plugin hung if it reset
+/// the board
+pub fn init(watchdog: Peri<'static, WATCHDOG>) -> Option<Slot> {
+    let mut watchdog = Watchdog::new(watchdog);
+    // Halting on a breakpoint inside a plugin shouldn't reset the board
+    watchdog.pause_on_debug(true);
+
+    let marker = watchdog.get_scratch(SCRATCH_INDEX);
+    watchdog.set_scratch(SCRATCH_INDEX, 0);
+    let hung = match watchdog.reset_reason() {
+        Some(ResetReason::TimedOut) if marker == ARMED_MAGIC => Some(Slot::Main),
+        Some(ResetReason::TimedOut) if marker == ARMED_MAGIC | 1 => Some(Slot::Pip),
+        _ => None,
+    };
+
+    WATCHDOG.lock(|cell| cell.replace(Some(watchdog)));
+    hung
+}
+
+/// Start the watchdog for twice the budget, so updates that merely run late
+/// are stopped by the host without a reset
+fn arm(slot: Slot, budget_us: u32) {
+    with_watchdog(|watchdog| {
+        let marker = match slot {
+            Slot::Main => ARMED_MAGIC,
+            Slot::Pip => ARMED_MAGIC | 1,
+        };
+        watchdog.set_scratch(SCRATCH_INDEX, marker);
+        watchdog.start(Duration::from_micros(2 * u64::from(budget_us)));
+    });
+}
+
+fn disarm() {
+    with_watchdog(|watchdog| {
+        watchdog.stop();
+        watchdog.set_scratch(SCRATCH_INDEX, 0);
+    });
+}
+
+fn with_watchdog(f: impl FnOnce(&mut Watchdog)) {
+    WATCHDOG.lock(|cell| {
+        if let Some(watchdog) = cell.borrow_mut().as_mut() {
+            f(watchdog);
+        }
+    });
+}
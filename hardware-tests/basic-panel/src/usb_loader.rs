@@ -0,0 +1,103 @@
+//! Plugin upload over USB
+//!
+//! The board enumerates as a USB CDC ACM serial port and takes plugin
+//! binaries written to it (`sim upload-plugin /dev/ttyACM0 plugin.bin`), in
+//! the upload frames of `cluster_core::framing`. The render loop polls
+//! [`LOADER`] between updates and swaps the main plugin for each upload, so
+//! a plugin can be tried without reflashing the firmware.
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_rp::{Peri, bind_interrupts};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::{Builder, Config, UsbDevice};
+use plugin_host::loader::{UploadReceiver, UsbLoader};
+use static_cell::StaticCell;
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+type UsbDriver = Driver<'static, USB>;
+
+/// Full-speed bulk packet size
+const MAX_PACKET_SIZE: u16 = 64;
+
+/// Test VID/PID from pid.codes, to be replaced before shipping devices
+const USB_VID: u16 = 0x1209;
+const USB_PID: u16 = 0x0002;
+
+/// Uploads for the render loop to load, see [`UsbLoader::poll`]
+pub static LOADER: UsbLoader = UsbLoader::new();
+
+/// Start the USB device and the upload receiver
+pub fn start(spawner: &Spawner, usb: Peri<'static, USB>) {
+    let driver = Driver::new(usb, Irqs);
+
+    let mut config = Config::new(USB_VID, USB_PID);
+    config.manufacturer = Some("42");
+    config.product = Some("Cluster matrix plugin loader");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static CDC_STATE: StaticCell<State> = StaticCell::new();
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(&mut builder, CDC_STATE.init(State::new()), MAX_PACKET_SIZE);
+    let usb = builder.build();
+
+    spawner.spawn(usb_task(usb).unwrap());
+    spawner.spawn(receive_task(class, LOADER.receiver()).unwrap());
+}
+
+#[embassy_executor::task]
+async fn usb_task(mut usb: UsbDevice<'static, UsbDriver>) -> ! {
+    usb.run().await
+}
+
+#[embassy_executor::task]
+async fn receive_task(
+    mut class: CdcAcmClass<'static, UsbDriver>,
+    mut receiver: UploadReceiver,
+) -> ! {
+    let mut packet = [0; MAX_PACKET_SIZE as usize];
+
+    loop {
+        class.wait_connection().await;
+        info!("Plugin loader host connected");
+
+        'connected: while let Ok(len) = class.read_packet(&mut packet).await {
+            for &byte in &packet[..len] {
+                let Some(reply) = receiver.push(byte).await else {
+                    continue;
+                };
+                for chunk in reply.chunks(MAX_PACKET_SIZE as usize) {
+                    if class.write_packet(chunk).await.is_err() {
+                        break 'connected;
+                    }
+                }
+                // A reply of a full packet needs a short one to end the transfer
+                if reply.len().is_multiple_of(MAX_PACKET_SIZE as usize)
+                    && class.write_packet(&[]).await.is_err()
+                {
+                    break 'connected;
+                }
+            }
+        }
+
+        info!("Plugin loader host disconnected");
+        receiver.reset();
+    }
+}
@@ -0,0 +1,48 @@
+//! Logging facade
+//!
+//! `log_info!`, `log_warn!` and `log_error!` log through defmt as usual and,
+//! with the `remote-log` feature, also queue the message in [`REMOTE_LOG`]
+//! so `syslog_task` can mirror it to a central server.
+//!
+//! The format string is used by both defmt and `core::fmt`, so arguments
+//! must implement `defmt::Format` as well as `Display`/`Debug`.
+
+#[cfg(feature = "remote-log")]
+use cluster_net::syslog::{RemoteLog, Severity};
+#[cfg(feature = "remote-log")]
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+
+/// Records waiting to be sent to the syslog server
+#[cfg(feature = "remote-log")]
+pub static REMOTE_LOG: RemoteLog<CriticalSectionRawMutex, 16> =
+    RemoteLog::new(Severity::Informational);
+
+#[cfg(feature = "remote-log")]
+macro_rules! log_at {
+    ($level:ident, $severity:ident, $($arg:tt)*) => {{
+        defmt::$level!($($arg)*);
+        $crate::log::REMOTE_LOG.log(
+            cluster_net::syslog::Severity::$severity,
+            format_args!($($arg)*),
+        );
+    }};
+}
+
+#[cfg(not(feature = "remote-log"))]
+macro_rules! log_at {
+    ($level:ident, $severity:ident, $($arg:tt)*) => {
+        defmt::$level!($($arg)*)
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => { log_at!(info, Informational, $($arg)*) };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => { log_at!(warn, Warning, $($arg)*) };
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => { log_at!(error, Error, $($arg)*) };
+}
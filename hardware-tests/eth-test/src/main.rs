@@ -11,11 +11,22 @@
 #![no_main]
 
 mod compat;
+#[macro_use]
+mod log;
 
 use crate::compat::StackAdapter;
 use cluster_core::types::ClusterId;
+use cluster_net::DeviceId;
 use cluster_net::client::{Client, ClientConfig};
-use cluster_net::endpoints::Endpoints;
+use cluster_net::endpoints::{Endpoints, Polled};
+#[cfg(feature = "metrics")]
+use cluster_net::mdns::{MDNS_MULTICAST_ADDR, MDNS_PORT, MdnsResponder};
+#[cfg(feature = "metrics")]
+use cluster_net::metrics::{DEFAULT_METRICS_PORT, METRICS_PATH, Metrics, serve_connection};
+#[cfg(feature = "remote-log")]
+use cluster_net::syslog::{FACILITY_LOCAL0, MAX_LINE_LENGTH, Record, SYSLOG_PORT, Severity};
+#[cfg(feature = "remote-log")]
+use core::fmt::Write as _;
 use defmt::*;
 use embassy_executor::Spawner;
 use embassy_futures::yield_now;
@@ -26,14 +37,30 @@ use embassy_rp::clocks::RoscRng;
 use embassy_rp::gpio::{Input, Level, Output, Pull};
 use embassy_rp::peripherals::SPI0;
 use embassy_rp::spi::{Async, Config as SpiConfig, Spi};
+#[cfg(feature = "metrics")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
 use embassy_time::{Delay, Timer};
 use embedded_hal_bus::spi::ExclusiveDevice;
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
+#[cfg(feature = "metrics")]
+use {embassy_net::tcp::TcpSocket, embassy_time::Duration};
+#[cfg(any(feature = "metrics", feature = "remote-log"))]
+use {
+    embassy_net::udp::{PacketMetadata, UdpSocket},
+    embassy_net::{IpAddress, IpEndpoint, Ipv4Address},
+};
 
 // Test configuration
 const TEST_SERVER_URL: &str = "http://example.com"; // Replace with your test server
 const TEST_INTERVAL_SECS: u64 = 30;
+const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+#[cfg(feature = "remote-log")]
+const SYSLOG_SERVER: [u8; 4] = [192, 168, 1, 10]; // Replace with your syslog collector
+
+/// Values served on `/metrics`, updated by the polling loop
+#[cfg(feature = "metrics")]
+static METRICS: Mutex<CriticalSectionRawMutex, Metrics> = Mutex::new(Metrics::new());
 
 #[embassy_executor::task]
 async fn ethernet_task(
@@ -53,6 +80,129 @@ async fn net_task(mut runner: embassy_net::Runner<'static, Device<'static>>) ->
     runner.run().await
 }
 
+/// Serve Prometheus metrics, one connection at a time
+#[cfg(feature = "metrics")]
+#[embassy_executor::task]
+async fn metrics_task(stack: Stack<'static>) -> ! {
+    let mut rx_buffer = [0u8; 512];
+    let mut tx_buffer = [0u8; 1024];
+    let mut scratch = [0u8; 256];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(5)));
+
+        if let Err(e) = socket.accept(DEFAULT_METRICS_PORT).await {
+            warn!("Metrics accept failed: {:?}", e);
+            continue;
+        }
+
+        let snapshot = *METRICS.lock().await;
+        if let Err(e) = serve_connection(&mut socket, &snapshot, &mut scratch).await {
+            warn!("Metrics request failed: {:?}", e);
+        }
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+/// Answer mDNS queries so the metrics endpoint can be found by name
+#[cfg(feature = "metrics")]
+#[embassy_executor::task]
+async fn mdns_task(stack: Stack<'static>, responder: MdnsResponder) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0u8; 512];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0u8; 512];
+    let mut packet = [0u8; 512];
+    let mut response = [0u8; 512];
+
+    let group = Ipv4Address::from(MDNS_MULTICAST_ADDR);
+    if let Err(e) = stack.join_multicast_group(group) {
+        warn!("Failed to join mDNS group: {:?}", e);
+    }
+
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    unwrap!(socket.bind(MDNS_PORT));
+    let destination = IpEndpoint::new(IpAddress::Ipv4(group), MDNS_PORT);
+
+    info!("Advertising {}.local", responder.hostname());
+    match responder.announcement(&mut response) {
+        Ok(len) => {
+            if let Err(e) = socket.send_to(&response[..len], destination).await {
+                warn!("mDNS announcement failed: {:?}", e);
+            }
+        }
+        Err(e) => warn!("Failed to build mDNS announcement: {:?}", e),
+    }
+
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut packet).await else {
+            continue;
+        };
+        match responder.handle_packet(&packet[..len], &mut response) {
+            Ok(Some(len)) => {
+                if let Err(e) = socket.send_to(&response[..len], destination).await {
+                    warn!("mDNS response failed: {:?}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to build mDNS response: {:?}", e),
+        }
+    }
+}
+
+/// Mirror queued log records to the syslog server
+#[cfg(feature = "remote-log")]
+#[embassy_executor::task]
+async fn syslog_task(stack: Stack<'static>, device_id: DeviceId) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0u8; 64];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0u8; 1024];
+    let mut line: heapless::String<MAX_LINE_LENGTH> = heapless::String::new();
+
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    unwrap!(socket.bind(0));
+    let server = IpEndpoint::new(
+        IpAddress::Ipv4(Ipv4Address::from(SYSLOG_SERVER)),
+        SYSLOG_PORT,
+    );
+
+    loop {
+        let record = log::REMOTE_LOG.receive().await;
+
+        let dropped = log::REMOTE_LOG.take_dropped();
+        if dropped > 0 {
+            let mut notice = Record {
+                severity: Severity::Warning,
+                message: heapless::String::new(),
+            };
+            let _ = write!(notice.message, "{} log records dropped", dropped);
+            notice.format(FACILITY_LOCAL0, device_id.as_str(), "eth-test", &mut line);
+            let _ = socket.send_to(line.as_bytes(), server).await;
+        }
+
+        record.format(FACILITY_LOCAL0, device_id.as_str(), "eth-test", &mut line);
+        if let Err(e) = socket.send_to(line.as_bytes(), server).await {
+            // Plain defmt here, a remote record would loop back into the queue
+            warn!("Syslog send failed: {:?}", e);
+        }
+    }
+}
+
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     info!("Starting cluster-net hardware test on RP2350 + W6100");
@@ -90,7 +240,8 @@ async fn main(spawner: Spawner) {
 
     // Init network stack with DHCP
     info!("Initializing network stack...");
-    static RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
+    // Extra sockets for the metrics server, mDNS and syslog
+    static RESOURCES: StaticCell<StackResources<6>> = StaticCell::new();
     let (stack, runner) = embassy_net::new(
         device,
         embassy_net::Config::dhcpv4(Default::default()),
@@ -112,9 +263,39 @@ async fn main(spawner: Spawner) {
     // Wait a bit for network to stabilize
     Timer::after_secs(2).await;
 
+    // Device identity from the RP2350 unique chip ID
+    let device_id = match embassy_rp::otp::get_chipid() {
+        Ok(chip_id) => DeviceId::from_unique_id(chip_id),
+        Err(_) => {
+            error!("Failed to read chip ID from OTP");
+            DeviceId::from_unique_id(0)
+        }
+    };
+    #[cfg(feature = "remote-log")]
+    spawner.spawn(unwrap!(syslog_task(stack, device_id.clone())));
+    log_info!("Device ID: {}", device_id);
+
+    #[cfg(feature = "metrics")]
+    {
+        spawner.spawn(unwrap!(metrics_task(stack)));
+        info!("Serving metrics on port {}", DEFAULT_METRICS_PORT);
+
+        let responder = MdnsResponder::new(
+            &device_id,
+            cfg.address.address().octets(),
+            DEFAULT_METRICS_PORT,
+            METRICS_PATH,
+        );
+        spawner.spawn(unwrap!(mdns_task(stack, responder)));
+    }
+
+    // Provisioning handshake, on every run to exercise the endpoint; the
+    // app only runs it until the ID is stored, see its network.rs
+    provision_device(stack, &device_id).await;
+
     // Run HTTP tests
     info!("Starting HTTP tests...");
-    test_http_client(stack).await;
+    test_http_client(stack, &device_id).await;
 
     // Optional: Run HTTPS tests if TLS feature is enabled
     #[cfg(feature = "tls")]
@@ -131,9 +312,9 @@ async fn main(spawner: Spawner) {
     loop {
         Timer::after_secs(TEST_INTERVAL_SECS).await;
 
-        match poll_cluster_data(stack).await {
+        match poll_cluster_data(stack, &device_id).await {
             Ok(()) => info!("Poll successful"),
-            Err(e) => error!("Poll failed: {:?}", e),
+            Err(e) => log_error!("Poll failed: {:?}", e),
         }
     }
 }
@@ -148,8 +329,34 @@ async fn wait_for_config(stack: Stack<'static>) -> embassy_net::StaticConfigV4 {
     }
 }
 
+/// Announce the device to the server so it can be associated with a location
+async fn provision_device(stack: Stack<'static>, device_id: &DeviceId) {
+    info!("=== Provisioning ===");
+
+    let Ok(config) = ClientConfig::new(TEST_SERVER_URL) else {
+        error!("Failed to create client config (URL too long?)");
+        return;
+    };
+
+    let adapter = StackAdapter::new(&stack);
+    let mut client: Client<StackAdapter, StackAdapter> = Client::new(config, &adapter, &adapter);
+
+    let mut buffer = [0u8; 512];
+    match Endpoints::provision(&mut client, device_id, FIRMWARE_VERSION, &mut buffer).await {
+        Ok(provisioning) => {
+            info!("✓ Provisioned at {}", provisioning.location.as_str());
+            if let Some(cluster) = provisioning.cluster {
+                info!("  Assigned cluster: {}", Display2Format(&cluster));
+            }
+        }
+        Err(e) => {
+            error!("✗ Provisioning failed: {:?}", e);
+        }
+    }
+}
+
 /// Test HTTP client functionality
-async fn test_http_client(stack: Stack<'static>) {
+async fn test_http_client(stack: Stack<'static>, device_id: &DeviceId) {
     info!("=== HTTP Client Test ===");
 
     // Create client configuration
@@ -164,8 +371,10 @@ async fn test_http_client(stack: Stack<'static>) {
     // Create compatibility adapter for embassy-net stack
     let adapter = StackAdapter::new(&stack);
 
-    // Create HTTP client using the adapter
-    let mut client: Client<StackAdapter, StackAdapter> = Client::new(config, &adapter, &adapter);
+    // Create HTTP client using the adapter, tagging every request with the device ID
+    let mut device_id = device_id.clone();
+    let mut client: Client<StackAdapter, StackAdapter> =
+        Client::new(config, &adapter, &adapter).with_middleware(&mut device_id);
 
     // Test 1: Fetch cluster F0
     info!("Test 1: Fetching cluster F0...");
@@ -265,17 +474,37 @@ async fn test_https_client(stack: Stack<'static>) {
 }
 
 /// Poll cluster data periodically
-async fn poll_cluster_data(stack: Stack<'static>) -> Result<(), ()> {
+async fn poll_cluster_data(stack: Stack<'static>, device_id: &DeviceId) -> Result<(), ()> {
     let config = ClientConfig::new(TEST_SERVER_URL).map_err(|_| ())?;
     let adapter = StackAdapter::new(&stack);
-    let mut client: Client<StackAdapter, StackAdapter> = Client::new(config, &adapter, &adapter);
-
+    #[cfg(feature = "metrics")]
+    let mut middleware = (device_id.clone(), METRICS.lock().await.network);
+    #[cfg(not(feature = "metrics"))]
+    let mut middleware = device_id.clone();
     let mut buffer = [0u8; 8192];
-    let cluster = Endpoints::poll_cluster(&mut client, ClusterId::F0, &mut buffer)
-        .await
-        .map_err(|_| ())?;
+    // A new client has no validators to send, so every poll is a full download
+    let result = {
+        let mut client: Client<StackAdapter, StackAdapter> =
+            Client::new(config, &adapter, &adapter).with_middleware(&mut middleware);
+        Endpoints::poll_cluster(&mut client, ClusterId::F0, &mut buffer).await
+    };
 
-    info!(
+    #[cfg(feature = "metrics")]
+    {
+        let mut metrics = METRICS.lock().await;
+        metrics.network = middleware.1;
+        metrics.uptime_seconds = embassy_time::Instant::now().as_secs();
+        if let Ok(Polled::Modified(cluster)) = &result {
+            metrics.occupancy[0] = cluster.occupancy_percentage();
+        }
+    }
+
+    let Polled::Modified(cluster) = result.map_err(|_| ())? else {
+        log_info!("Cluster F0 not modified");
+        return Ok(());
+    };
+
+    log_info!(
         "Cluster F0 update: {} seats, {}% occupied",
         cluster.seats.len(),
         cluster.occupancy_percentage()
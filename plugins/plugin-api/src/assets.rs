@@ -0,0 +1,175 @@
+//! Asset segments of plugins larger than the load buffer
+//!
+//! Hosts copy plugins into a RAM load buffer of a fixed size, which code,
+//! data and `.bss` must fit. Plugins with large assets (sprites, levels,
+//! music) use a segmented binary instead:
+//!
+//! ```text
+//! [code segment][asset table][asset data]
+//! ```
+//!
+//! The code segment is the linked plugin, header first, and
+//! [`PluginHeader::code_size`](crate::PluginHeader::code_size) is its
+//! length: only it is copied to RAM. The asset table that follows is
+//! `[ASSET_MAGIC u32][count u32]` then `count` entries of
+//! `[offset u32][len u32]`, little-endian, offsets counted from the start of
+//! the table. The table and the data stay where the binary is, in flash, and
+//! the plugin reads an asset a slice at a time with `read_asset_fn`.
+//!
+//! `cargo xtask plugin-pack` appends the assets to a built plugin.
+
+/// Offset of [`PluginHeader::code_size`](crate::PluginHeader::code_size) in
+/// a plugin binary, for packagers running on hosts with wider pointers
+pub const CODE_SIZE_OFFSET: usize = 88;
+
+// Plugins are built for 32-bit targets, where the header has this layout
+#[cfg(target_pointer_width = "32")]
+const _: () = assert!(CODE_SIZE_OFFSET == core::mem::offset_of!(crate::PluginHeader, code_size));
+
+/// First word of an asset table, "ASST"
+pub const ASSET_MAGIC: u32 = 0x5453_5341;
+
+/// Bytes before the entries of an asset table: magic and count
+pub const ASSET_TABLE_HEADER_SIZE: usize = 8;
+
+/// Bytes of an asset table entry: offset and length
+pub const ASSET_ENTRY_SIZE: usize = 8;
+
+/// `read_asset_fn` result when the plugin has no such asset
+pub const ASSET_NOT_FOUND: i32 = -1;
+
+/// Asset table of a segmented binary, checked when parsed
+#[derive(Clone, Copy, Debug)]
+pub struct AssetTable<'a> {
+    /// From the table to the end of the binary
+    bytes: &'a [u8],
+    count: usize,
+}
+
+impl<'a> AssetTable<'a> {
+    /// Table at the start of `bytes`, what follows the code segment
+    ///
+    /// `None` without a valid table, or if an asset lies past the end of
+    /// `bytes`.
+    #[must_use]
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if word(bytes, 0)? != ASSET_MAGIC {
+            return None;
+        }
+        let count = word(bytes, 4)? as usize;
+        let table = Self { bytes, count };
+        let entries = count.checked_mul(ASSET_ENTRY_SIZE)?;
+        if ASSET_TABLE_HEADER_SIZE + entries > bytes.len() {
+            return None;
+        }
+        (0..count)
+            .all(|index| table.get(index).is_some())
+            .then_some(table)
+    }
+
+    /// Number of assets
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.count
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Bytes of the asset at `index`
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.count {
+            return None;
+        }
+        let entry = ASSET_TABLE_HEADER_SIZE + index * ASSET_ENTRY_SIZE;
+        let offset = word(self.bytes, entry)? as usize;
+        let len = word(self.bytes, entry + 4)? as usize;
+        self.bytes.get(offset..offset.checked_add(len)?)
+    }
+
+    /// Copy the asset at `index` from `offset` into `buf`, as
+    /// `read_asset_fn` does
+    ///
+    /// Returns the length of the asset, of which only what fits in `buf` is
+    /// copied, or [`ASSET_NOT_FOUND`].
+    pub fn read(&self, index: usize, offset: usize, buf: &mut [u8]) -> i32 {
+        let Some(asset) = self.get(index) else {
+            return ASSET_NOT_FOUND;
+        };
+        let from = asset.get(offset..).unwrap_or_default();
+        let len = from.len().min(buf.len());
+        buf[..len].copy_from_slice(&from[..len]);
+        asset.len() as i32
+    }
+}
+
+/// Asset table and data for `assets`, to append to a code segment
+#[cfg(feature = "std")]
+#[must_use]
+pub fn build_table(assets: &[&[u8]]) -> std::vec::Vec<u8> {
+    let mut table = std::vec::Vec::new();
+    table.extend_from_slice(&ASSET_MAGIC.to_le_bytes());
+    table.extend_from_slice(&(assets.len() as u32).to_le_bytes());
+    let mut offset = ASSET_TABLE_HEADER_SIZE + assets.len() * ASSET_ENTRY_SIZE;
+    for asset in assets {
+        table.extend_from_slice(&(offset as u32).to_le_bytes());
+        table.extend_from_slice(&(asset.len() as u32).to_le_bytes());
+        offset += asset.len();
+    }
+    for asset in assets {
+        table.extend_from_slice(asset);
+    }
+    table
+}
+
+fn word(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Table of the assets `b"abc"` and `b"hello"`
+    const TABLE: [u8; 32] = [
+        0x41, 0x53, 0x53, 0x54, 2, 0, 0, 0, // magic, count
+        24, 0, 0, 0, 3, 0, 0, 0, // "abc"
+        27, 0, 0, 0, 5, 0, 0, 0, // "hello"
+        b'a', b'b', b'c', b'h', b'e', b'l', b'l', b'o',
+    ];
+
+    #[test]
+    fn test_read_assets() {
+        let table = AssetTable::parse(&TABLE).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(1), Some(&b"hello"[..]));
+
+        let mut buf = [0; 4];
+        assert_eq!(table.read(1, 2, &mut buf), 5);
+        assert_eq!(&buf[..3], b"llo");
+        assert_eq!(table.read(0, 0, &mut []), 3);
+        assert_eq!(table.read(0, 10, &mut buf), 3);
+        assert_eq!(table.read(2, 0, &mut buf), ASSET_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        assert!(AssetTable::parse(&TABLE[..31]).is_none());
+        assert!(AssetTable::parse(&TABLE[4..]).is_none());
+        assert!(AssetTable::parse(&[]).is_none());
+
+        let mut huge = TABLE;
+        huge[4] = 0xFF;
+        assert!(AssetTable::parse(&huge).is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_build_table() {
+        assert_eq!(build_table(&[b"abc", b"hello"]), TABLE);
+    }
+}
@@ -0,0 +1,146 @@
+//! Cluster occupancy data for plugins
+//!
+//! Hosts with a layout share it through [`DataContext`]: clusters are
+//! indexed from 0 to [`CLUSTER_COUNT`], from the lowest floor up, and the
+//! seats of each cluster from 0 to its seat count. Every call of a frame sees
+//! the same data; it changes between frames when the host gets an update.
+//! A host without a layout reports no seats.
+
+/// Clusters of a layout, `CLUSTER_*` indexes
+pub const CLUSTER_COUNT: u32 = 6;
+pub const CLUSTER_F0: u32 = 0;
+pub const CLUSTER_F1: u32 = 1;
+pub const CLUSTER_F1B: u32 = 2;
+pub const CLUSTER_F2: u32 = 3;
+pub const CLUSTER_F4: u32 = 4;
+pub const CLUSTER_F6: u32 = 5;
+
+/// Values of [`SeatInfo::status`]
+pub const SEAT_STATUS_FREE: u8 = 0;
+pub const SEAT_STATUS_TAKEN: u8 = 1;
+pub const SEAT_STATUS_REPORTED: u8 = 2;
+pub const SEAT_STATUS_BROKEN: u8 = 3;
+
+/// Values of [`SeatInfo::kind`]
+pub const SEAT_KIND_MAC: u8 = 0;
+pub const SEAT_KIND_LENOVO: u8 = 1;
+pub const SEAT_KIND_DELL: u8 = 2;
+pub const SEAT_KIND_FLEX: u8 = 3;
+
+/// Flags of `get_cluster_attr_mask_fn`
+pub const CLUSTER_ATTR_PISCINE: u32 = 1 << 0;
+pub const CLUSTER_ATTR_EXAM: u32 = 1 << 1;
+pub const CLUSTER_ATTR_SILENT: u32 = 1 << 2;
+pub const CLUSTER_ATTR_EVENT: u32 = 1 << 3;
+pub const CLUSTER_ATTR_CLOSED: u32 = 1 << 4;
+
+/// Result of `get_seat_fn` for a cluster or seat index out of range
+pub const DATA_NOT_FOUND: i32 = -1;
+
+/// A seat of a cluster, as copied by `get_seat_fn`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SeatInfo {
+    /// Position in the cluster's seat grid
+    pub x: u16,
+    pub y: u16,
+    /// `SEAT_STATUS_*`
+    pub status: u8,
+    /// `SEAT_KIND_*`
+    pub kind: u8,
+}
+
+/// Cluster data functions (C function pointers)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DataContext {
+    /// Number of seats of `cluster`, 0 for an unknown cluster
+    pub get_cluster_seat_count_fn: unsafe extern "C" fn(cluster: u32) -> u32,
+    /// Copy seat `index` of `cluster` into `out`, returning 0 or
+    /// `DATA_NOT_FOUND`
+    pub get_seat_fn: unsafe extern "C" fn(cluster: u32, index: u32, out: *mut SeatInfo) -> i32,
+    /// `CLUSTER_ATTR_*` flags of `cluster`, 0 for an unknown cluster
+    pub get_cluster_attr_mask_fn: unsafe extern "C" fn(cluster: u32) -> u32,
+}
+
+impl DataContext {
+    /// Number of seats of `cluster`, a `CLUSTER_*` index
+    #[must_use]
+    pub fn cluster_seat_count(&self, cluster: u32) -> u32 {
+        unsafe { (self.get_cluster_seat_count_fn)(cluster) }
+    }
+
+    /// Seat `index` of `cluster`, `None` if either is out of range
+    #[must_use]
+    pub fn seat(&self, cluster: u32, index: u32) -> Option<SeatInfo> {
+        let mut seat = SeatInfo::default();
+        let code = unsafe { (self.get_seat_fn)(cluster, index, &mut seat) };
+        (code == 0).then_some(seat)
+    }
+
+    /// Seats of `cluster`, in the host's order
+    pub fn seats(&self, cluster: u32) -> impl Iterator<Item = SeatInfo> + '_ {
+        (0..self.cluster_seat_count(cluster)).filter_map(move |index| self.seat(cluster, index))
+    }
+
+    /// `CLUSTER_ATTR_*` flags of `cluster`
+    #[must_use]
+    pub fn cluster_attr_mask(&self, cluster: u32) -> u32 {
+        unsafe { (self.get_cluster_attr_mask_fn)(cluster) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEATS: [SeatInfo; 2] = [
+        SeatInfo {
+            x: 1,
+            y: 2,
+            status: SEAT_STATUS_TAKEN,
+            kind: SEAT_KIND_MAC,
+        },
+        SeatInfo {
+            x: 3,
+            y: 2,
+            status: SEAT_STATUS_FREE,
+            kind: SEAT_KIND_DELL,
+        },
+    ];
+
+    unsafe extern "C" fn seat_count(cluster: u32) -> u32 {
+        if cluster == CLUSTER_F1 { 2 } else { 0 }
+    }
+
+    unsafe extern "C" fn seat(cluster: u32, index: u32, out: *mut SeatInfo) -> i32 {
+        match SEATS.get(index as usize) {
+            Some(seat) if cluster == CLUSTER_F1 => {
+                unsafe { out.write(*seat) };
+                0
+            }
+            _ => DATA_NOT_FOUND,
+        }
+    }
+
+    unsafe extern "C" fn attr_mask(_cluster: u32) -> u32 {
+        CLUSTER_ATTR_EXAM
+    }
+
+    #[test]
+    fn test_wrappers_copy_the_host_seats() {
+        let data = DataContext {
+            get_cluster_seat_count_fn: seat_count,
+            get_seat_fn: seat,
+            get_cluster_attr_mask_fn: attr_mask,
+        };
+
+        assert_eq!(data.seat(CLUSTER_F1, 1), Some(SEATS[1]));
+        assert_eq!(data.seat(CLUSTER_F1, 2), None);
+        assert_eq!(data.seat(CLUSTER_F0, 0), None);
+        assert!(data.seats(CLUSTER_F1).eq(SEATS));
+        assert_eq!(data.seats(CLUSTER_F6).count(), 0);
+        assert_eq!(data.cluster_attr_mask(CLUSTER_F1), CLUSTER_ATTR_EXAM);
+    }
+}
@@ -0,0 +1,191 @@
+//! Built-in bitmap font of the text callbacks
+//!
+//! A 5x7 font covering printable ASCII, from the public domain X11 `5x7`
+//! font (the same glyphs as embedded-graphics' `FONT_5X7`). Characters are
+//! one pixel apart, so each takes [`GLYPH_ADVANCE`] columns. Other bytes
+//! are drawn as `?`, once per UTF-8 character.
+
+/// Width of a glyph in pixels
+pub const GLYPH_WIDTH: i32 = 5;
+/// Height of a glyph in pixels, and of a line of text
+pub const GLYPH_HEIGHT: i32 = 7;
+/// Columns from one character to the next
+pub const GLYPH_ADVANCE: i32 = GLYPH_WIDTH + 1;
+
+/// Longest text the hosts draw or measure, in bytes
+///
+/// Longer text is cut, which bounds the work of a call.
+pub const MAX_TEXT_LEN: usize = 1024;
+
+const FIRST_CHAR: u8 = b' ';
+const REPLACEMENT: u8 = b'?';
+
+/// Rows of each glyph from `' '` to `'~'`, leftmost pixel in bit 4
+#[rustfmt::skip]
+const GLYPHS: [[u8; GLYPH_HEIGHT as usize]; 95] = [
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // ' '
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100, 0b00000], // '!'
+    [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000], // '"'
+    [0b00000, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b00000], // '#'
+    [0b00000, 0b01110, 0b10100, 0b01110, 0b00101, 0b01110, 0b00000], // '$'
+    [0b10000, 0b10010, 0b00100, 0b01000, 0b10010, 0b00010, 0b00000], // '%'
+    [0b00000, 0b01000, 0b10100, 0b01000, 0b10100, 0b01010, 0b00000], // '&'
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000], // "'"
+    [0b00100, 0b01000, 0b01000, 0b01000, 0b01000, 0b00100, 0b00000], // '('
+    [0b01000, 0b00100, 0b00100, 0b00100, 0b00100, 0b01000, 0b00000], // ')'
+    [0b00000, 0b01010, 0b00100, 0b01110, 0b00100, 0b01010, 0b00000], // '*'
+    [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000], // '+'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00110, 0b00100, 0b01000], // ','
+    [0b00000, 0b00000, 0b00000, 0b11110, 0b00000, 0b00000, 0b00000], // '-'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100, 0b00000], // '.'
+    [0b00000, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000, 0b00000], // '/'
+    [0b00100, 0b01010, 0b01010, 0b01010, 0b01010, 0b00100, 0b00000], // '0'
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // '1'
+    [0b01100, 0b10010, 0b00010, 0b00100, 0b01000, 0b11110, 0b00000], // '2'
+    [0b11110, 0b00010, 0b01100, 0b00010, 0b10010, 0b01100, 0b00000], // '3'
+    [0b00100, 0b01100, 0b10100, 0b11110, 0b00100, 0b00100, 0b00000], // '4'
+    [0b11110, 0b10000, 0b11100, 0b00010, 0b10010, 0b01100, 0b00000], // '5'
+    [0b01100, 0b10000, 0b11100, 0b10010, 0b10010, 0b01100, 0b00000], // '6'
+    [0b11110, 0b00010, 0b00100, 0b00100, 0b01000, 0b01000, 0b00000], // '7'
+    [0b01100, 0b10010, 0b01100, 0b10010, 0b10010, 0b01100, 0b00000], // '8'
+    [0b01100, 0b10010, 0b10010, 0b01110, 0b00010, 0b01100, 0b00000], // '9'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000], // ':'
+    [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01000, 0b10000], // ';'
+    [0b00000, 0b00010, 0b00100, 0b01000, 0b00100, 0b00010, 0b00000], // '<'
+    [0b00000, 0b00000, 0b11110, 0b00000, 0b11110, 0b00000, 0b00000], // '='
+    [0b00000, 0b01000, 0b00100, 0b00010, 0b00100, 0b01000, 0b00000], // '>'
+    [0b00100, 0b01010, 0b00010, 0b00100, 0b00000, 0b00100, 0b00000], // '?'
+    [0b01100, 0b10010, 0b10110, 0b10110, 0b10000, 0b01100, 0b00000], // '@'
+    [0b01100, 0b10010, 0b10010, 0b11110, 0b10010, 0b10010, 0b00000], // 'A'
+    [0b11100, 0b10010, 0b11100, 0b10010, 0b10010, 0b11100, 0b00000], // 'B'
+    [0b01100, 0b10010, 0b10000, 0b10000, 0b10010, 0b01100, 0b00000], // 'C'
+    [0b11100, 0b10010, 0b10010, 0b10010, 0b10010, 0b11100, 0b00000], // 'D'
+    [0b11110, 0b10000, 0b11100, 0b10000, 0b10000, 0b11110, 0b00000], // 'E'
+    [0b11110, 0b10000, 0b11100, 0b10000, 0b10000, 0b10000, 0b00000], // 'F'
+    [0b01100, 0b10010, 0b10000, 0b10110, 0b10010, 0b01110, 0b00000], // 'G'
+    [0b10010, 0b10010, 0b11110, 0b10010, 0b10010, 0b10010, 0b00000], // 'H'
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // 'I'
+    [0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100, 0b00000], // 'J'
+    [0b10010, 0b10100, 0b11000, 0b11000, 0b10100, 0b10010, 0b00000], // 'K'
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11110, 0b00000], // 'L'
+    [0b10010, 0b11110, 0b11110, 0b10010, 0b10010, 0b10010, 0b00000], // 'M'
+    [0b10010, 0b11010, 0b11010, 0b10110, 0b10110, 0b10010, 0b00000], // 'N'
+    [0b01100, 0b10010, 0b10010, 0b10010, 0b10010, 0b01100, 0b00000], // 'O'
+    [0b11100, 0b10010, 0b10010, 0b11100, 0b10000, 0b10000, 0b00000], // 'P'
+    [0b01100, 0b10010, 0b10010, 0b10010, 0b11010, 0b01100, 0b00010], // 'Q'
+    [0b11100, 0b10010, 0b10010, 0b11100, 0b10100, 0b10010, 0b00000], // 'R'
+    [0b01100, 0b10010, 0b01000, 0b00100, 0b10010, 0b01100, 0b00000], // 'S'
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000], // 'T'
+    [0b10010, 0b10010, 0b10010, 0b10010, 0b10010, 0b01100, 0b00000], // 'U'
+    [0b10010, 0b10010, 0b10010, 0b10010, 0b01100, 0b01100, 0b00000], // 'V'
+    [0b10010, 0b10010, 0b10010, 0b11110, 0b11110, 0b10010, 0b00000], // 'W'
+    [0b10010, 0b10010, 0b01100, 0b01100, 0b10010, 0b10010, 0b00000], // 'X'
+    [0b01010, 0b01010, 0b01010, 0b00100, 0b00100, 0b00100, 0b00000], // 'Y'
+    [0b11110, 0b00010, 0b00100, 0b01000, 0b10000, 0b11110, 0b00000], // 'Z'
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110, 0b00000], // '['
+    [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00000, 0b00000], // '\\'
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110, 0b00000], // ']'
+    [0b00100, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // '^'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11110, 0b00000], // '_'
+    [0b01000, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // '`'
+    [0b00000, 0b00000, 0b01110, 0b10010, 0b10110, 0b01010, 0b00000], // 'a'
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b10010, 0b11100, 0b00000], // 'b'
+    [0b00000, 0b00000, 0b01100, 0b10000, 0b10000, 0b01100, 0b00000], // 'c'
+    [0b00010, 0b00010, 0b01110, 0b10010, 0b10010, 0b01110, 0b00000], // 'd'
+    [0b00000, 0b00000, 0b01100, 0b10110, 0b11000, 0b01100, 0b00000], // 'e'
+    [0b00100, 0b01010, 0b01000, 0b11100, 0b01000, 0b01000, 0b00000], // 'f'
+    [0b00000, 0b00000, 0b01110, 0b10010, 0b01100, 0b10000, 0b01110], // 'g'
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b10010, 0b10010, 0b00000], // 'h'
+    [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b01110, 0b00000], // 'i'
+    [0b00010, 0b00000, 0b00010, 0b00010, 0b00010, 0b01010, 0b00100], // 'j'
+    [0b10000, 0b10000, 0b10100, 0b11000, 0b10100, 0b10010, 0b00000], // 'k'
+    [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110, 0b00000], // 'l'
+    [0b00000, 0b00000, 0b10100, 0b11110, 0b10010, 0b10010, 0b00000], // 'm'
+    [0b00000, 0b00000, 0b11100, 0b10010, 0b10010, 0b10010, 0b00000], // 'n'
+    [0b00000, 0b00000, 0b01100, 0b10010, 0b10010, 0b01100, 0b00000], // 'o'
+    [0b00000, 0b00000, 0b11100, 0b10010, 0b10010, 0b11100, 0b10000], // 'p'
+    [0b00000, 0b00000, 0b01110, 0b10010, 0b10010, 0b01110, 0b00010], // 'q'
+    [0b00000, 0b00000, 0b11100, 0b10010, 0b10000, 0b10000, 0b00000], // 'r'
+    [0b00000, 0b00000, 0b01110, 0b11000, 0b00110, 0b11100, 0b00000], // 's'
+    [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b00110, 0b00000], // 't'
+    [0b00000, 0b00000, 0b10010, 0b10010, 0b10010, 0b01110, 0b00000], // 'u'
+    [0b00000, 0b00000, 0b01010, 0b01010, 0b01010, 0b00100, 0b00000], // 'v'
+    [0b00000, 0b00000, 0b10010, 0b10010, 0b11110, 0b11110, 0b00000], // 'w'
+    [0b00000, 0b00000, 0b10010, 0b01100, 0b01100, 0b10010, 0b00000], // 'x'
+    [0b00000, 0b00000, 0b10010, 0b10010, 0b01010, 0b00100, 0b01000], // 'y'
+    [0b00000, 0b00000, 0b11110, 0b00100, 0b01000, 0b11110, 0b00000], // 'z'
+    [0b00010, 0b00100, 0b01100, 0b00100, 0b00100, 0b00010, 0b00000], // '{'
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000], // '|'
+    [0b01000, 0b00100, 0b00110, 0b00100, 0b00100, 0b01000, 0b00000], // '}'
+    [0b01010, 0b10100, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // '~'
+];
+
+/// Rows of the glyph of a byte, leftmost pixel in bit 4
+#[must_use]
+pub const fn glyph(byte: u8) -> &'static [u8; GLYPH_HEIGHT as usize] {
+    let byte = if byte.is_ascii_graphic() || byte == b' ' {
+        byte
+    } else {
+        REPLACEMENT
+    };
+    &GLYPHS[(byte - FIRST_CHAR) as usize]
+}
+
+/// Bytes of `text` that start a character, i.e. all but UTF-8 continuations
+pub fn chars(text: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    text[..text.len().min(MAX_TEXT_LEN)]
+        .iter()
+        .copied()
+        .filter(|&byte| byte & 0xC0 != 0x80)
+}
+
+/// Width of `text` in pixels, without spacing after the last character
+#[must_use]
+pub fn text_width(text: &[u8]) -> i32 {
+    match chars(text).count() as i32 {
+        0 => 0,
+        count => count * GLYPH_ADVANCE - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyph_lookup() {
+        assert_eq!(glyph(b' '), &[0; GLYPH_HEIGHT as usize]);
+        assert_eq!(
+            glyph(b'A'),
+            &[
+                0b01100, 0b10010, 0b10010, 0b11110, 0b10010, 0b10010, 0b00000
+            ]
+        );
+        assert_eq!(glyph(b'~'), &GLYPHS[GLYPHS.len() - 1]);
+        // Every glyph fits in GLYPH_WIDTH columns
+        for byte in b' '..=b'~' {
+            assert!(glyph(byte).iter().all(|&row| row >> GLYPH_WIDTH == 0));
+        }
+    }
+
+    #[test]
+    fn test_missing_glyphs_are_replaced() {
+        let replacement = glyph(b'?');
+        for byte in [0, b'\n', 0x7F, 0x80, 0xC3, 0xFF] {
+            assert_eq!(glyph(byte), replacement, "byte {byte:#x}");
+        }
+    }
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(text_width(b""), 0);
+        assert_eq!(text_width(b"A"), GLYPH_WIDTH);
+        assert_eq!(text_width(b"Hi!"), 3 * GLYPH_ADVANCE - 1);
+        // "é" is two bytes but one character
+        assert_eq!(text_width("é".as_bytes()), GLYPH_WIDTH);
+        assert_eq!(text_width("né".as_bytes()), 2 * GLYPH_ADVANCE - 1);
+        // Text past MAX_TEXT_LEN isn't measured
+        let long = [b'x'; MAX_TEXT_LEN + 10];
+        assert_eq!(text_width(&long), MAX_TEXT_LEN as i32 * GLYPH_ADVANCE - 1);
+    }
+}
@@ -0,0 +1,292 @@
+//! Button events on top of the raw input bitmask
+//!
+//! Plugins get the buttons held during each frame as a bitmask of the
+//! `INPUT_*` constants. A host that needs more, like a menu opened with
+//! START+SELECT or "hold B to leave the plugin", feeds the same bitmask
+//! every frame to an [`InputProcessor`], which turns it into
+//! [`InputEvent`]s:
+//!
+//! - [`InputEvent::Pressed`] and [`InputEvent::Released`] on each edge
+//! - [`InputEvent::LongPress`] once a button is held for
+//!   [`InputTiming::long_press_ms`]
+//! - [`InputEvent::Repeat`] every [`InputTiming::repeat_interval_ms`] after
+//!   [`InputTiming::repeat_delay_ms`], for scrolling through lists
+//! - [`InputEvent::Combo`] when the last button of a registered combo is
+//!   pressed while the others are held
+//!
+//! The buttons of a combo don't long-press or repeat until released, so a
+//! combo held down is not also taken for one of its buttons. The raw mask
+//! still goes to the plugins unchanged.
+//!
+//! ```
+//! # use plugin_api::input::{InputEvent, InputProcessor, InputTiming};
+//! # use plugin_api::{INPUT_SELECT, INPUT_START};
+//! const MENU: u32 = INPUT_START | INPUT_SELECT;
+//! let mut input = InputProcessor::new(InputTiming::DEFAULT, &[MENU]);
+//!
+//! input.update(INPUT_START, 0);
+//! let events = input.update(MENU, 16);
+//! assert!(events.contains(InputEvent::Combo(MENU)));
+//! ```
+
+/// Number of buttons, one per bit from `INPUT_UP` to `INPUT_SELECT`
+const BUTTON_COUNT: usize = 8;
+
+/// Most events produced by one update
+///
+/// Each button changes edge at most once and long-presses or repeats at
+/// most once per update, and combos come on top.
+const MAX_EVENTS: usize = 2 * BUTTON_COUNT + 8;
+
+/// Button event, buttons being `INPUT_*` bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InputEvent {
+    Pressed(u32),
+    Released(u32),
+    /// Held for the long-press time, sent once per press
+    LongPress(u32),
+    /// Still held, sent periodically after the repeat delay
+    Repeat(u32),
+    /// All the buttons of this mask held, the last one just pressed
+    Combo(u32),
+}
+
+/// Delays of the long-press and repeat events, in milliseconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InputTiming {
+    pub long_press_ms: u32,
+    pub repeat_delay_ms: u32,
+    pub repeat_interval_ms: u32,
+}
+
+impl InputTiming {
+    pub const DEFAULT: Self = Self {
+        long_press_ms: 800,
+        repeat_delay_ms: 400,
+        repeat_interval_ms: 100,
+    };
+}
+
+impl Default for InputTiming {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Events of one update, in the order they happened
+#[derive(Clone, Copy, Debug)]
+pub struct InputEvents {
+    events: [Option<InputEvent>; MAX_EVENTS],
+    len: usize,
+}
+
+impl InputEvents {
+    const fn new() -> Self {
+        Self {
+            events: [None; MAX_EVENTS],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: InputEvent) {
+        if let Some(slot) = self.events.get_mut(self.len) {
+            *slot = Some(event);
+            self.len += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn contains(&self, event: InputEvent) -> bool {
+        self.iter().any(|e| e == event)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = InputEvent> + '_ {
+        self.events[..self.len].iter().flatten().copied()
+    }
+}
+
+/// Tracks the raw bitmask across frames to produce [`InputEvent`]s
+#[derive(Clone, Debug)]
+pub struct InputProcessor<'a> {
+    timing: InputTiming,
+    combos: &'a [u32],
+    held: u32,
+    /// Buttons that already long-pressed during this press
+    long_pressed: u32,
+    /// Buttons of a triggered combo, ignored until released
+    consumed: u32,
+    pressed_at: [u64; BUTTON_COUNT],
+    next_repeat: [u64; BUTTON_COUNT],
+}
+
+impl<'a> InputProcessor<'a> {
+    /// Processor detecting the given combos, each a mask of two buttons or more
+    #[must_use]
+    pub const fn new(timing: InputTiming, combos: &'a [u32]) -> Self {
+        Self {
+            timing,
+            combos,
+            held: 0,
+            long_pressed: 0,
+            consumed: 0,
+            pressed_at: [0; BUTTON_COUNT],
+            next_repeat: [0; BUTTON_COUNT],
+        }
+    }
+
+    /// Buttons held as of the last update, as passed to plugins
+    #[must_use]
+    pub const fn held(&self) -> u32 {
+        self.held
+    }
+
+    /// Feed the buttons held at `now_ms` and get what changed
+    pub fn update(&mut self, raw: u32, now_ms: u64) -> InputEvents {
+        let mut events = InputEvents::new();
+        let pressed = raw & !self.held;
+        let released = self.held & !raw;
+
+        for bit in 0..BUTTON_COUNT {
+            let button = 1 << bit;
+            if pressed & button != 0 {
+                self.pressed_at[bit] = now_ms;
+                self.next_repeat[bit] = now_ms + u64::from(self.timing.repeat_delay_ms);
+                events.push(InputEvent::Pressed(button));
+            } else if released & button != 0 {
+                self.long_pressed &= !button;
+                self.consumed &= !button;
+                events.push(InputEvent::Released(button));
+            }
+        }
+
+        for &combo in self.combos {
+            if raw & combo == combo && pressed & combo != 0 {
+                self.consumed |= combo;
+                events.push(InputEvent::Combo(combo));
+            }
+        }
+
+        for bit in 0..BUTTON_COUNT {
+            let button = 1 << bit;
+            if raw & button == 0 || self.consumed & button != 0 {
+                continue;
+            }
+
+            let held_for = now_ms.saturating_sub(self.pressed_at[bit]);
+            if self.long_pressed & button == 0 && held_for >= u64::from(self.timing.long_press_ms) {
+                self.long_pressed |= button;
+                events.push(InputEvent::LongPress(button));
+            } else if now_ms >= self.next_repeat[bit] {
+                // Skip the repeats missed by a slow frame
+                let interval = u64::from(self.timing.repeat_interval_ms.max(1));
+                let missed = (now_ms - self.next_repeat[bit]) / interval;
+                self.next_repeat[bit] += (missed + 1) * interval;
+                events.push(InputEvent::Repeat(button));
+            }
+        }
+
+        self.held = raw;
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{INPUT_A, INPUT_B, INPUT_SELECT, INPUT_START, INPUT_UP, InputState, Inputs};
+
+    const MENU: u32 = INPUT_START | INPUT_SELECT;
+
+    #[test]
+    fn test_edges() {
+        let mut input = InputProcessor::new(InputTiming::DEFAULT, &[]);
+        assert!(input.update(0, 0).is_empty());
+
+        let events = input.update(INPUT_A | INPUT_UP, 16);
+        assert!(events.contains(InputEvent::Pressed(INPUT_A)));
+        assert!(events.contains(InputEvent::Pressed(INPUT_UP)));
+        assert_eq!(events.iter().count(), 2);
+        assert!(input.update(INPUT_A | INPUT_UP, 32).is_empty());
+
+        let events = input.update(INPUT_UP, 48);
+        assert_eq!(events.iter().next(), Some(InputEvent::Released(INPUT_A)));
+        assert_eq!(input.held(), INPUT_UP);
+    }
+
+    #[test]
+    fn test_long_press_and_repeat() {
+        let timing = InputTiming {
+            long_press_ms: 1000,
+            repeat_delay_ms: 300,
+            repeat_interval_ms: 100,
+        };
+        let mut input = InputProcessor::new(timing, &[]);
+        let mut sends = |now_ms, event| input.update(INPUT_B, now_ms).contains(event);
+        let repeat = InputEvent::Repeat(INPUT_B);
+        let long_press = InputEvent::LongPress(INPUT_B);
+
+        assert!(!sends(0, repeat));
+        assert!(!sends(299, repeat));
+        assert!(sends(300, repeat));
+        // A slow frame repeats once, then catches up with the period
+        assert!(sends(650, repeat));
+        assert!(!sends(699, repeat));
+        assert!(sends(700, repeat));
+
+        assert!(sends(1000, long_press));
+        assert!(!sends(2000, long_press));
+
+        // Released and pressed again
+        assert!(
+            input
+                .update(0, 2100)
+                .contains(InputEvent::Released(INPUT_B))
+        );
+        input.update(INPUT_B, 2200);
+        assert!(input.update(INPUT_B, 3200).contains(long_press));
+    }
+
+    #[test]
+    fn test_combo_consumes_its_buttons() {
+        let mut input = InputProcessor::new(InputTiming::DEFAULT, &[MENU]);
+        input.update(INPUT_SELECT, 0);
+        let events = input.update(MENU | INPUT_A, 10);
+        assert!(events.contains(InputEvent::Combo(MENU)));
+        assert!(events.contains(InputEvent::Pressed(INPUT_START)));
+
+        // Only A, not part of the combo, long-presses
+        let events = input.update(MENU | INPUT_A, 5000);
+        assert!(events.iter().eq([InputEvent::LongPress(INPUT_A)]));
+
+        // Holding the combo doesn't trigger it again, pressing it anew does
+        assert!(!input.update(MENU, 5100).contains(InputEvent::Combo(MENU)));
+        input.update(INPUT_START, 5200);
+        assert!(input.update(MENU, 5300).contains(InputEvent::Combo(MENU)));
+    }
+
+    #[test]
+    fn test_state_keeps_the_button_bitmask() {
+        let state = InputState {
+            buttons: INPUT_A | INPUT_UP,
+            axis_x: -300,
+            axis_y: 12,
+            rotary_delta: -2,
+        };
+        let inputs = Inputs::from_state(state);
+        assert!(inputs.a() && inputs.up() && !inputs.b());
+        assert_eq!(inputs.raw(), INPUT_A | INPUT_UP);
+        assert_eq!(inputs.axes(), (-300, 12));
+        assert_eq!(inputs.rotary_delta(), -2);
+
+        // Hosts with buttons only
+        assert_eq!(InputState::from(INPUT_B), Inputs::from_raw(INPUT_B).state());
+        assert_eq!(Inputs::from_raw(INPUT_B).axes(), (0, 0));
+    }
+}
@@ -5,14 +5,44 @@ extern crate std;
 
 use core::cell::UnsafeCell;
 
+pub mod assets;
+pub mod data;
+pub mod font;
+pub mod input;
+pub mod permissions;
+pub mod raster;
+pub mod storage;
+
 /// Display dimensions
 pub const DISPLAY_WIDTH: usize = 128;
 pub const DISPLAY_HEIGHT: usize = 128;
 pub const FRAMEBUFFER_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
 
+/// Milliseconds per frame when `millis()` is derived from the frame counter
+pub const FRAME_TIME_MS: u32 = 16;
+
+/// Time the host lets a plugin's `update` take by default, in microseconds
+///
+/// A plugin going over it is stopped, see the host's update watchdog.
+pub const UPDATE_BUDGET_US: u32 = 30_000;
+
 /// Plugin magic number and version
 pub const PLUGIN_MAGIC: u32 = 0x504C5547; // "PLUG" in hex
-pub const PLUGIN_API_VERSION: u32 = 1;
+pub const PLUGIN_API_VERSION: u32 = 2;
+
+/// Capability flags of [`PluginHeader::capabilities`]
+///
+/// The plugin implements `pause` and `resume`.
+pub const PLUGIN_CAP_PAUSE: u32 = 1 << 0;
+
+/// Requirement flags of [`PluginHeader::requirements`]
+///
+/// The plugin does not work without storage, granted by the host.
+pub const PLUGIN_REQ_STORAGE: u32 = 1 << 0;
+/// The plugin needs network data from the host, the cluster data of [`data`].
+pub const PLUGIN_REQ_NETWORK: u32 = 1 << 1;
+/// The plugin draws at twice the panel resolution.
+pub const PLUGIN_REQ_DOUBLE_RES: u32 = 1 << 2;
 
 // ============================================================================
 // Core C-ABI Structures
@@ -32,6 +62,8 @@ pub struct PluginAPI {
     pub gfx: *const GraphicsContext,
     /// System utilities
     pub sys: *const SystemContext,
+    /// Cluster data
+    pub data: *const data::DataContext,
 }
 
 /// Direct framebuffer access structure
@@ -58,6 +90,8 @@ pub struct GraphicsContext {
     pub draw_line_fn: unsafe extern "C" fn(x0: i32, y0: i32, x1: i32, y1: i32, color: u16),
     pub draw_circle_fn: unsafe extern "C" fn(cx: i32, cy: i32, radius: i32, color: u16),
     pub blit_fn: unsafe extern "C" fn(x: i32, y: i32, w: i32, h: i32, data: *const u16),
+    pub draw_text_fn: unsafe extern "C" fn(x: i32, y: i32, text: *const u8, len: u32, color: u16),
+    pub text_width_fn: unsafe extern "C" fn(text: *const u8, len: u32) -> i32,
 }
 
 /// System utilities (C function pointers and color constants)
@@ -75,9 +109,90 @@ pub struct SystemContext {
     pub color_yellow: u16,
     pub color_cyan: u16,
     pub color_magenta: u16,
+    /// `random()` and `millis()` only depend on the frame counter: the same
+    /// frame always gives the same image, e.g. for golden-image tests
+    pub deterministic: bool,
+    /// Seed of `random()`, for plugins with their own generator to reuse
+    pub seed: u32,
+    /// Copy the value of `key` into `buf`, returning the length of the value
+    /// (which may exceed `buf_len`), `STORAGE_NOT_FOUND` or `PLUGIN_ERR_DENIED`
+    pub storage_read_fn:
+        unsafe extern "C" fn(key: *const u8, key_len: u32, buf: *mut u8, buf_len: u32) -> i32,
+    /// Save `len` bytes of `data` under `key`, returning 0, a
+    /// `STORAGE_ERR_*` code or `PLUGIN_ERR_DENIED`
+    pub storage_write_fn:
+        unsafe extern "C" fn(key: *const u8, key_len: u32, data: *const u8, len: u32) -> i32,
+    /// Beep at `freq_hz` for `duration_ms`, replacing the current tone;
+    /// 0 Hz stops it. Returns 0 or `PLUGIN_ERR_DENIED`
+    pub tone_fn: unsafe extern "C" fn(freq_hz: u32, duration_ms: u32) -> i32,
+    /// Copy the hardware the frames are shown on into `out`
+    pub display_info_fn: unsafe extern "C" fn(out: *mut DisplayInfo),
+    /// Copy asset `asset` from byte `offset` into `buf`, returning the
+    /// length of the asset (which may exceed `buf_len`) or `ASSET_NOT_FOUND`
+    pub read_asset_fn:
+        unsafe extern "C" fn(asset: u32, offset: u32, buf: *mut u8, buf_len: u32) -> i32,
+}
+
+/// Hardware the frames are shown on, as copied by `display_info_fn`
+///
+/// Plugins always draw into a `DISPLAY_WIDTH` x `DISPLAY_HEIGHT`
+/// framebuffer, which the host maps onto its panels. A slow refresh rate,
+/// e.g. a bit-banged driver, blurs fast motion and makes dim colors flicker;
+/// a low color depth bands gradients.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisplayInfo {
+    /// Full refreshes of the panels per second, 0 if unknown
+    pub refresh_hz: u32,
+    /// Bits per color channel the panels show, 0 if unknown
+    pub color_depth: u32,
+    /// Size of one panel, in pixels
+    pub panel_width: u32,
+    pub panel_height: u32,
+    /// Panels daisy-chained to make up the display
+    pub chain_length: u32,
+}
+
+impl DisplayInfo {
+    /// One panel of the framebuffer's size, as reported by hosts that don't
+    /// know their hardware
+    pub const UNKNOWN: Self = Self {
+        refresh_hz: 0,
+        color_depth: 0,
+        panel_width: DISPLAY_WIDTH as u32,
+        panel_height: DISPLAY_HEIGHT as u32,
+        chain_length: 1,
+    };
+}
+
+impl Default for DisplayInfo {
+    fn default() -> Self {
+        Self::UNKNOWN
+    }
+}
+
+/// Side of the square [`PluginIcon`], in pixels
+pub const PLUGIN_ICON_SIZE: usize = 16;
+
+/// Pixels of a [`PluginIcon`]
+pub const PLUGIN_ICON_PIXELS: usize = PLUGIN_ICON_SIZE * PLUGIN_ICON_SIZE;
+
+/// Picture shown next to the plugin's name in menus
+///
+/// Pixels are RGB565, row by row. Menus read it from the binary without
+/// loading the plugin, so it must be a constant.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginIcon {
+    pub pixels: [u16; PLUGIN_ICON_PIXELS],
 }
 
 /// Plugin header placed at start of binary
+///
+/// The optional entry points are only called when their capability flag is
+/// set, so plugins can leave them null; the other fields are checked by the
+/// host before `init`, with 0 meaning "not declared".
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct PluginHeader {
@@ -85,8 +200,97 @@ pub struct PluginHeader {
     pub api_version: u32,
     pub name: [u8; 32],
     pub init: unsafe extern "C" fn(api: *const PluginAPI) -> i32,
-    pub update: unsafe extern "C" fn(api: *const PluginAPI, inputs: u32),
+    pub update: unsafe extern "C" fn(api: *const PluginAPI, input: *const InputState),
     pub cleanup: unsafe extern "C" fn(),
+    /// `PLUGIN_CAP_*` flags of the optional entry points implemented
+    pub capabilities: u32,
+    /// Called when the plugin stops being updated, e.g. under the menu
+    pub pause: Option<unsafe extern "C" fn()>,
+    /// Called before the first update after a pause
+    pub resume: Option<unsafe extern "C" fn()>,
+    /// `PLUGIN_PERM_*` flags of the host functions the plugin uses
+    pub permissions: u32,
+    /// Bytes of zero-initialized memory (`.bss`) the plugin needs after its
+    /// binary; hosts zero the whole load buffer when it is 0
+    pub bss_size: u32,
+    /// Oldest host API version the plugin works with
+    pub min_host_version: u32,
+    /// Newest host API version the plugin works with
+    pub max_host_version: u32,
+    /// `PLUGIN_REQ_*` flags of what the host must provide
+    pub requirements: u32,
+    /// Icon for menus, null for none
+    pub icon: Option<&'static PluginIcon>,
+    /// Bytes of the binary loaded into RAM, header included, the rest being
+    /// [`assets`]; 0 loads the whole binary. Set by the packager
+    pub code_size: u32,
+}
+
+impl PluginHeader {
+    /// Header with the mandatory fields set and nothing declared
+    ///
+    /// The name is cut to 31 bytes, leaving room for a terminating zero.
+    #[must_use]
+    pub const fn new(
+        name: &str,
+        init: unsafe extern "C" fn(api: *const PluginAPI) -> i32,
+        update: unsafe extern "C" fn(api: *const PluginAPI, input: *const InputState),
+        cleanup: unsafe extern "C" fn(),
+    ) -> Self {
+        let mut name_arr = [0u8; 32];
+        let name_bytes = name.as_bytes();
+        let len = if name_bytes.len() < 32 {
+            name_bytes.len()
+        } else {
+            31
+        };
+        let mut i = 0;
+        while i < len {
+            name_arr[i] = name_bytes[i];
+            i += 1;
+        }
+
+        Self {
+            magic: PLUGIN_MAGIC,
+            api_version: PLUGIN_API_VERSION,
+            name: name_arr,
+            init,
+            update,
+            cleanup,
+            capabilities: 0,
+            pause: None,
+            resume: None,
+            permissions: 0,
+            bss_size: 0,
+            min_host_version: 0,
+            max_host_version: 0,
+            requirements: 0,
+            icon: None,
+            code_size: 0,
+        }
+    }
+
+    /// Check that a host of API version `host_version` is in the range the
+    /// plugin declared
+    #[must_use]
+    pub const fn supports_host(&self, host_version: u32) -> bool {
+        host_version >= self.min_host_version
+            && (self.max_host_version == 0 || host_version <= self.max_host_version)
+    }
+
+    /// Entry point called on pause, if the plugin has one
+    #[must_use]
+    pub fn pause_fn(&self) -> Option<unsafe extern "C" fn()> {
+        self.pause
+            .filter(|_| self.capabilities & PLUGIN_CAP_PAUSE != 0)
+    }
+
+    /// Entry point called on resume, if the plugin has one
+    #[must_use]
+    pub fn resume_fn(&self) -> Option<unsafe extern "C" fn()> {
+        self.resume
+            .filter(|_| self.capabilities & PLUGIN_CAP_PAUSE != 0)
+    }
 }
 
 // ============================================================================
@@ -102,63 +306,121 @@ pub const INPUT_B: u32 = 1 << 5;
 pub const INPUT_START: u32 = 1 << 6;
 pub const INPUT_SELECT: u32 = 1 << 7;
 
+/// Full deflection of [`InputState::axis_x`] and [`InputState::axis_y`]
+pub const INPUT_AXIS_MAX: i16 = i16::MAX;
+
+/// Inputs of a frame, as passed to `update`
+///
+/// Hosts without an analog stick or a rotary encoder leave those fields at
+/// 0, so plugins can read them unconditionally.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InputState {
+    /// `INPUT_*` flags of the buttons held
+    pub buttons: u32,
+    /// Stick position from `-INPUT_AXIS_MAX` (left) to `INPUT_AXIS_MAX`
+    pub axis_x: i16,
+    /// Stick position from `-INPUT_AXIS_MAX` (up) to `INPUT_AXIS_MAX`
+    pub axis_y: i16,
+    /// Encoder detents turned since the last frame, positive clockwise
+    pub rotary_delta: i32,
+}
+
+impl From<u32> for InputState {
+    fn from(buttons: u32) -> Self {
+        Self {
+            buttons,
+            ..Self::default()
+        }
+    }
+}
+
 // ============================================================================
 // Rust-Safe Wrappers
 // ============================================================================
 
 /// Type-safe input wrapper for Rust plugins
 #[derive(Clone, Copy, Debug, Default)]
-pub struct Inputs(u32);
+pub struct Inputs(InputState);
 
 impl Inputs {
     #[must_use]
     pub const fn from_raw(raw: u32) -> Self {
-        Self(raw)
+        Self(InputState {
+            buttons: raw,
+            axis_x: 0,
+            axis_y: 0,
+            rotary_delta: 0,
+        })
+    }
+
+    #[must_use]
+    pub const fn from_state(state: InputState) -> Self {
+        Self(state)
     }
 
+    /// `INPUT_*` flags of the buttons held
     #[must_use]
     pub const fn raw(self) -> u32 {
+        self.0.buttons
+    }
+
+    #[must_use]
+    pub const fn state(self) -> InputState {
         self.0
     }
 
+    /// Stick position, `-INPUT_AXIS_MAX` to `INPUT_AXIS_MAX` on each axis
+    #[must_use]
+    pub const fn axes(self) -> (i16, i16) {
+        (self.0.axis_x, self.0.axis_y)
+    }
+
+    /// Encoder detents turned since the last frame, positive clockwise
+    #[must_use]
+    pub const fn rotary_delta(self) -> i32 {
+        self.0.rotary_delta
+    }
+
     #[must_use]
     pub const fn up(self) -> bool {
-        self.0 & INPUT_UP != 0
+        self.0.buttons & INPUT_UP != 0
     }
 
     #[must_use]
     pub const fn down(self) -> bool {
-        self.0 & INPUT_DOWN != 0
+        self.0.buttons & INPUT_DOWN != 0
     }
 
     #[must_use]
     pub const fn left(self) -> bool {
-        self.0 & INPUT_LEFT != 0
+        self.0.buttons & INPUT_LEFT != 0
     }
 
     #[must_use]
     pub const fn right(self) -> bool {
-        self.0 & INPUT_RIGHT != 0
+        self.0.buttons & INPUT_RIGHT != 0
     }
 
     #[must_use]
     pub const fn a(self) -> bool {
-        self.0 & INPUT_A != 0
+        self.0.buttons & INPUT_A != 0
     }
 
     #[must_use]
     pub const fn b(self) -> bool {
-        self.0 & INPUT_B != 0
+        self.0.buttons & INPUT_B != 0
     }
 
     #[must_use]
     pub const fn start(self) -> bool {
-        self.0 & INPUT_START != 0
+        self.0.buttons & INPUT_START != 0
     }
 
     #[must_use]
     pub const fn select(self) -> bool {
-        self.0 & INPUT_SELECT != 0
+        self.0.buttons & INPUT_SELECT != 0
     }
 }
 
@@ -187,6 +449,13 @@ impl PluginAPI {
         // SAFETY: Plugin runtime guarantees pointer validity during callbacks
         unsafe { &*self.sys }
     }
+
+    /// Get reference to cluster data context.
+    #[must_use]
+    pub fn data(&self) -> &data::DataContext {
+        // SAFETY: Plugin runtime guarantees pointer validity during callbacks
+        unsafe { &*self.data }
+    }
 }
 
 impl GraphicsContext {
@@ -216,9 +485,32 @@ impl GraphicsContext {
         unsafe { (self.draw_circle_fn)(cx, cy, radius, color) }
     }
 
+    /// Copy a row-major `w` x `h` image to (`x`, `y`)
+    ///
+    /// Nothing is drawn if `data` holds fewer than `w * h` pixels, since the
+    /// host would read past its end.
     pub fn blit(&self, x: i32, y: i32, w: i32, h: i32, data: &[u16]) {
+        let pixels = i64::from(w) * i64::from(h);
+        if w <= 0 || h <= 0 || pixels > data.len() as i64 {
+            return;
+        }
         unsafe { (self.blit_fn)(x, y, w, h, data.as_ptr()) }
     }
+
+    /// Draw `text` in the built-in 5x7 font, top-left corner at (`x`, `y`)
+    ///
+    /// See [`font`] for the characters it covers.
+    pub fn draw_text(&self, x: i32, y: i32, text: &str, color: u16) {
+        let len = text.len().min(font::MAX_TEXT_LEN) as u32;
+        unsafe { (self.draw_text_fn)(x, y, text.as_ptr(), len, color) }
+    }
+
+    /// Width of `text` in pixels as drawn by [`draw_text`](Self::draw_text)
+    #[must_use]
+    pub fn text_width(&self, text: &str) -> i32 {
+        let len = text.len().min(font::MAX_TEXT_LEN) as u32;
+        unsafe { (self.text_width_fn)(text.as_ptr(), len) }
+    }
 }
 
 impl SystemContext {
@@ -237,6 +529,92 @@ impl SystemContext {
         unsafe { (self.rgb_fn)(r, g, b) }
     }
 
+    #[must_use]
+    pub const fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    #[must_use]
+    pub const fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Copy the value saved under `key` into `buf`
+    ///
+    /// Returns the length of the value, of which only what fits in `buf` is
+    /// copied. Keys are private to each plugin, and need the
+    /// [`PLUGIN_PERM_STORAGE`](permissions::PLUGIN_PERM_STORAGE) permission.
+    pub fn storage_read(&self, key: &str, buf: &mut [u8]) -> Result<usize, storage::StorageError> {
+        let len = unsafe {
+            (self.storage_read_fn)(
+                key.as_ptr(),
+                key.len() as u32,
+                buf.as_mut_ptr(),
+                buf.len() as u32,
+            )
+        };
+        match storage::StorageError::from_code(len) {
+            Some(error) => Err(error),
+            None => Ok(len as usize),
+        }
+    }
+
+    /// Save `value` under `key`, kept across reboots
+    ///
+    /// Keys are at most [`storage::MAX_KEY_LEN`] bytes and values
+    /// [`storage::MAX_VALUE_LEN`].
+    /// Hosts write to flash in the background, so a plugin can save on
+    /// every change without wearing it out.
+    pub fn storage_write(&self, key: &str, value: &[u8]) -> Result<(), storage::StorageError> {
+        let code = unsafe {
+            (self.storage_write_fn)(
+                key.as_ptr(),
+                key.len() as u32,
+                value.as_ptr(),
+                value.len() as u32,
+            )
+        };
+        match storage::StorageError::from_code(code) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Beep at `freq_hz` for `duration_ms`, replacing the tone playing
+    ///
+    /// A frequency of 0 stops the tone. Hosts start it after the current
+    /// frame, and those without a speaker ignore it. Returns `false` if the
+    /// host did not grant [`PLUGIN_PERM_SOUND`](permissions::PLUGIN_PERM_SOUND).
+    pub fn tone(&self, freq_hz: u32, duration_ms: u32) -> bool {
+        unsafe { (self.tone_fn)(freq_hz, duration_ms) != permissions::PLUGIN_ERR_DENIED }
+    }
+
+    /// Hardware the frames are shown on, to adapt effects to it
+    #[must_use]
+    pub fn display_info(&self) -> DisplayInfo {
+        let mut info = DisplayInfo::UNKNOWN;
+        unsafe { (self.display_info_fn)(&mut info) };
+        info
+    }
+
+    /// Copy asset `asset` from byte `offset` into `buf`
+    ///
+    /// Returns the length of the asset, of which only what fits in `buf` is
+    /// copied, `None` if the plugin has no such asset. Assets are read from
+    /// flash, so plugins read them a slice at a time rather than keeping
+    /// them in RAM.
+    pub fn read_asset(&self, asset: u32, offset: u32, buf: &mut [u8]) -> Option<usize> {
+        let len =
+            unsafe { (self.read_asset_fn)(asset, offset, buf.as_mut_ptr(), buf.len() as u32) };
+        (len != assets::ASSET_NOT_FOUND).then_some(len as usize)
+    }
+
+    /// Length of asset `asset`, `None` if the plugin has no such asset
+    #[must_use]
+    pub fn asset_len(&self, asset: u32) -> Option<usize> {
+        self.read_asset(asset, 0, &mut [])
+    }
+
     #[must_use]
     pub const fn red(&self) -> u16 {
         self.color_red
@@ -383,6 +761,13 @@ pub trait PluginImpl {
 
     /// Clean up any resources when the plugin is unloaded
     fn cleanup(&mut self);
+
+    /// Stop updates for a while, e.g. while the menu is shown over the
+    /// plugin. Games should halt their logic and timers here.
+    fn pause(&mut self) {}
+
+    /// Updates are about to start again after `pause`
+    fn resume(&mut self) {}
 }
 
 // ============================================================================
@@ -411,9 +796,21 @@ pub trait PluginImpl {
 ///
 /// plugin_main!(MyPlugin, "my_plugin");
 /// ```
+///
+/// Other [`PluginHeader`] fields follow the name, e.g. the permissions of
+/// gated host functions and the manifest the host checks before `init`:
+/// ```ignore
+/// plugin_main!(
+///     Snake,
+///     "snake",
+///     permissions: PLUGIN_PERM_STORAGE | PLUGIN_PERM_SOUND,
+///     requirements: PLUGIN_REQ_STORAGE,
+///     bss_size: 4096,
+/// );
+/// ```
 #[macro_export]
 macro_rules! plugin_main {
-    ($plugin_type:ty, $name:expr) => {
+    ($plugin_type:ty, $name:expr $(, $field:ident: $value:expr)* $(,)?) => {
         // Compile-time check that the type implements PluginImpl
         const _: () = {
             fn _assert_plugin_impl<T: $crate::PluginImpl>() {}
@@ -428,26 +825,11 @@ macro_rules! plugin_main {
         #[used]
         #[unsafe(no_mangle)]
         pub static PLUGIN_HEADER: $crate::PluginHeader = $crate::PluginHeader {
-            magic: $crate::PLUGIN_MAGIC,
-            api_version: $crate::PLUGIN_API_VERSION,
-            name: {
-                let mut name_arr = [0u8; 32];
-                let name_bytes = $name.as_bytes();
-                let len = if name_bytes.len() < 32 {
-                    name_bytes.len()
-                } else {
-                    31
-                };
-                let mut i = 0;
-                while i < len {
-                    name_arr[i] = name_bytes[i];
-                    i += 1;
-                }
-                name_arr
-            },
-            init: __plugin_init,
-            update: __plugin_update,
-            cleanup: __plugin_cleanup,
+            capabilities: $crate::PLUGIN_CAP_PAUSE,
+            pause: Some(__plugin_pause),
+            resume: Some(__plugin_resume),
+            $($field: $value,)*
+            ..$crate::PluginHeader::new($name, __plugin_init, __plugin_update, __plugin_cleanup)
         };
 
         #[unsafe(no_mangle)]
@@ -463,11 +845,15 @@ macro_rules! plugin_main {
         }
 
         #[unsafe(no_mangle)]
-        extern "C" fn __plugin_update(api: *const $crate::PluginAPI, inputs: u32) {
-            // SAFETY: API pointer valid during callback, single-threaded execution
+        extern "C" fn __plugin_update(
+            api: *const $crate::PluginAPI,
+            input: *const $crate::InputState,
+        ) {
+            // SAFETY: API and input pointers valid during callback,
+            // single-threaded execution
             unsafe {
                 let api_mut = &mut *(api as *mut $crate::PluginAPI);
-                let inputs = $crate::Inputs::from_raw(inputs);
+                let inputs = $crate::Inputs::from_state(input.as_ref().copied().unwrap_or_default());
                 if let Some(plugin) = PLUGIN_INSTANCE.get_mut() {
                     plugin.update(api_mut, inputs);
                 }
@@ -483,6 +869,26 @@ macro_rules! plugin_main {
                 }
             }
         }
+
+        #[unsafe(no_mangle)]
+        extern "C" fn __plugin_pause() {
+            // SAFETY: Single-threaded execution
+            unsafe {
+                if let Some(plugin) = PLUGIN_INSTANCE.get_mut() {
+                    plugin.pause();
+                }
+            }
+        }
+
+        #[unsafe(no_mangle)]
+        extern "C" fn __plugin_resume() {
+            // SAFETY: Single-threaded execution
+            unsafe {
+                if let Some(plugin) = PLUGIN_INSTANCE.get_mut() {
+                    plugin.resume();
+                }
+            }
+        }
     };
 }
 
@@ -492,8 +898,10 @@ macro_rules! plugin_main {
 
 pub mod prelude {
     pub use crate::{
-        DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAMEBUFFER_SIZE, FrameBuffer, GraphicsContext, INPUT_A,
-        INPUT_B, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_SELECT, INPUT_START, INPUT_UP, Inputs,
-        PluginAPI, PluginImpl, SystemContext, plugin_main,
+        DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_TIME_MS, FRAMEBUFFER_SIZE, FrameBuffer,
+        GraphicsContext, INPUT_A, INPUT_B, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_SELECT,
+        INPUT_START, INPUT_UP, InputState, Inputs, PLUGIN_ICON_PIXELS, PLUGIN_ICON_SIZE, PluginAPI,
+        PluginIcon, PluginImpl, SystemContext, data::DataContext, data::SeatInfo, plugin_main,
+        storage::StorageError,
     };
 }
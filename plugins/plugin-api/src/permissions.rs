@@ -0,0 +1,44 @@
+//! Permissions gating the host functions beyond drawing
+//!
+//! A plugin lists the permissions it needs in
+//! [`PluginHeader::permissions`](crate::PluginHeader::permissions), and the
+//! host grants a subset according to its own policy. Functions whose
+//! permission was not granted return [`PLUGIN_ERR_DENIED`] without doing
+//! anything, so plugins can tell a denied call from a failed one.
+
+/// Read and write the plugin storage (`storage_read_fn`, `storage_write_fn`)
+pub const PLUGIN_PERM_STORAGE: u32 = 1 << 0;
+/// Play tones (`tone_fn`)
+pub const PLUGIN_PERM_SOUND: u32 = 1 << 1;
+/// Every permission known to this API version
+pub const PLUGIN_PERM_ALL: u32 = PLUGIN_PERM_STORAGE | PLUGIN_PERM_SOUND;
+
+/// Result of a call whose permission the host did not grant
+pub const PLUGIN_ERR_DENIED: i32 = -16;
+
+const NAMES: [(u32, &str); 2] = [
+    (PLUGIN_PERM_STORAGE, "storage"),
+    (PLUGIN_PERM_SOUND, "sound"),
+];
+
+/// Names of the permissions in `permissions`, e.g. for a menu to list
+pub fn names(permissions: u32) -> impl Iterator<Item = &'static str> {
+    NAMES
+        .into_iter()
+        .filter(move |(flag, _)| permissions & flag != 0)
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_are_named() {
+        assert!(names(0).next().is_none());
+        let mut all = names(PLUGIN_PERM_ALL | 1 << 31);
+        assert_eq!(all.next(), Some("storage"));
+        assert_eq!(all.next(), Some("sound"));
+        assert_eq!(all.next(), None);
+    }
+}
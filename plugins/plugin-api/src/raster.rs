@@ -0,0 +1,549 @@
+//! Panic-free drawing primitives on a [`FrameBuffer`]
+//!
+//! The plugin hosts implement the [`GraphicsContext`] callbacks with these, so
+//! a plugin can pass any coordinates, sizes or radii without overflowing the
+//! host's arithmetic or indexing outside the framebuffer. Everything is
+//! clipped to the view (`width` x `height`, capped to the display) and the
+//! intermediate math is done in `i64`, where no `i32` argument can overflow.
+//!
+//! Work is bounded by the view too: lines are clipped before they are
+//! rasterized and circles that can't touch the view are skipped, so extreme
+//! arguments don't stall the frame loop either.
+//!
+//! [`GraphicsContext`]: crate::GraphicsContext
+
+use core::ops::Range;
+
+use crate::font::{self, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FrameBuffer};
+
+/// Largest width or height of a blit
+pub const MAX_BLIT_SIZE: i32 = 1024;
+
+/// Largest circle radius that is drawn
+///
+/// Bigger circles crossing the view would take too long to rasterize, so
+/// they are skipped.
+pub const MAX_CIRCLE_RADIUS: i32 = 1 << 14;
+
+/// Distance around the view lines are clipped to before rasterizing
+///
+/// Clipping to a band around the view rather than to its edges keeps the
+/// rounding of the clipped endpoints from changing the visible pixels.
+const LINE_GUARD: i64 = 1024;
+
+/// Clip the `w` x `h` rectangle at (`x`, `y`) to a `width` x `height` view
+///
+/// Returns the column and row ranges to fill, or `None` when nothing is
+/// visible. Negative sizes are empty.
+#[must_use]
+pub fn clip_rect(
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    width: usize,
+    height: usize,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let columns = clip_span(x, w, width)?;
+    let rows = clip_span(y, h, height)?;
+    Some((columns, rows))
+}
+
+/// Clip `len` pixels from `start` to `0..limit`
+fn clip_span(start: i32, len: i32, limit: usize) -> Option<Range<usize>> {
+    let limit = limit as i64;
+    let first = i64::from(start).clamp(0, limit);
+    let end = (i64::from(start) + i64::from(len)).clamp(0, limit);
+    (first < end).then_some(first as usize..end as usize)
+}
+
+/// Clip the segment from (`x0`, `y0`) to (`x1`, `y1`) to a band of
+/// [`LINE_GUARD`] pixels around a `width` x `height` view
+///
+/// Returns the clipped endpoints, or `None` when the segment misses the band.
+/// Segments already inside it are returned unchanged.
+fn clip_line([x0, y0, x1, y1]: [i64; 4], width: usize, height: usize) -> Option<[i64; 4]> {
+    let (min_x, max_x) = (-LINE_GUARD, width as i64 + LINE_GUARD);
+    let (min_y, max_y) = (-LINE_GUARD, height as i64 + LINE_GUARD);
+    let inside = |x: i64, y: i64| (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y);
+    if inside(x0, y0) && inside(x1, y1) {
+        return Some([x0, y0, x1, y1]);
+    }
+
+    // Liang-Barsky; the i32 inputs are exact in f64
+    let (dx, dy) = ((x1 - x0) as f64, (y1 - y0) as f64);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    let edges = [
+        (-dx, (x0 - min_x) as f64),
+        (dx, (max_x - x0) as f64),
+        (-dy, (y0 - min_y) as f64),
+        (dy, (max_y - y0) as f64),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let at = |start: i64, delta: f64, t: f64| start + round(delta * t);
+    Some([
+        at(x0, dx, t0),
+        at(y0, dy, t0),
+        at(x0, dx, t1),
+        at(y0, dy, t1),
+    ])
+}
+
+/// Round half away from zero, which `core` doesn't provide for floats
+fn round(value: f64) -> i64 {
+    if value < 0.0 {
+        (value - 0.5) as i64
+    } else {
+        (value + 0.5) as i64
+    }
+}
+
+impl FrameBuffer {
+    /// Size of the view drawing is clipped to
+    ///
+    /// `width` and `height` are writable by plugins, so they are capped to
+    /// the display before being used for indexing.
+    fn view(&self) -> (usize, usize) {
+        (
+            (self.width as usize).min(DISPLAY_WIDTH),
+            (self.height as usize).min(DISPLAY_HEIGHT),
+        )
+    }
+
+    /// Index of a pixel in the view, or `None` when it's clipped
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let (width, height) = self.view();
+        let visible = (0..width as i64).contains(&x) && (0..height as i64).contains(&y);
+        visible.then(|| y as usize * DISPLAY_WIDTH + x as usize)
+    }
+
+    /// Set a pixel of the view, returning `false` if it was clipped
+    pub fn checked_set_pixel(&mut self, x: i32, y: i32, color: u16) -> bool {
+        self.plot(i64::from(x), i64::from(y), color)
+    }
+
+    /// Get a pixel of the view, or `None` if it's outside
+    #[must_use]
+    pub fn checked_get_pixel(&self, x: i32, y: i32) -> Option<u16> {
+        let index = self.index(i64::from(x), i64::from(y))?;
+        Some(self.pixels[index])
+    }
+
+    fn plot(&mut self, x: i64, y: i64, color: u16) -> bool {
+        match self.index(x, y) {
+            Some(index) => {
+                self.pixels[index] = color;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fill the view
+    pub fn fill(&mut self, color: u16) {
+        let (width, height) = self.view();
+        self.fill_rect(0, 0, width as i32, height as i32, color);
+    }
+
+    /// Fill a rectangle, clipped to the view
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: u16) {
+        let (width, height) = self.view();
+        let Some((columns, rows)) = clip_rect(x, y, w, h, width, height) else {
+            return;
+        };
+        for row in rows {
+            let start = row * DISPLAY_WIDTH;
+            self.pixels[start + columns.start..start + columns.end].fill(color);
+        }
+    }
+
+    /// Draw a line with Bresenham's algorithm, clipped to the view
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u16) {
+        let (width, height) = self.view();
+        let endpoints = [x0, y0, x1, y1].map(i64::from);
+        let Some([x0, y0, x1, y1]) = clip_line(endpoints, width, height) else {
+            return;
+        };
+
+        let (mut x, mut y) = (x0, y0);
+        let dx = (x1 - x0).abs();
+        let dy = (y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx - dy;
+
+        loop {
+            self.plot(x, y, color);
+
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 > -dy {
+                err -= dy;
+                x += sx;
+            }
+            if e2 < dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draw a circle outline with the midpoint algorithm, clipped to the view
+    ///
+    /// Negative radii and radii above [`MAX_CIRCLE_RADIUS`] draw nothing.
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u16) {
+        if !(0..=MAX_CIRCLE_RADIUS).contains(&radius) {
+            return;
+        }
+        let (width, height) = self.view();
+        let (cx, cy, radius) = (i64::from(cx), i64::from(cy), i64::from(radius));
+        // Skip circles whose bounding box misses the view
+        if cx + radius < 0
+            || cy + radius < 0
+            || cx - radius >= width as i64
+            || cy - radius >= height as i64
+        {
+            return;
+        }
+
+        let mut x = radius;
+        let mut y = 0;
+        let mut decision = 1 - radius;
+
+        while x >= y {
+            self.plot(cx + x, cy + y, color);
+            self.plot(cx - x, cy + y, color);
+            self.plot(cx + x, cy - y, color);
+            self.plot(cx - x, cy - y, color);
+            self.plot(cx + y, cy + x, color);
+            self.plot(cx - y, cy + x, color);
+            self.plot(cx + y, cy - x, color);
+            self.plot(cx - y, cy - x, color);
+
+            y += 1;
+
+            if decision <= 0 {
+                decision += 2 * y + 1;
+            } else {
+                x -= 1;
+                decision += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Copy a row-major `w` x `h` image to (`x`, `y`), clipped to the view
+    ///
+    /// Returns `false` without drawing if the size is not in
+    /// `1..=MAX_BLIT_SIZE` or `data` holds fewer than `w * h` pixels.
+    pub fn blit(&mut self, x: i32, y: i32, w: i32, h: i32, data: &[u16]) -> bool {
+        let valid = 1..=MAX_BLIT_SIZE;
+        if !valid.contains(&w) || !valid.contains(&h) || data.len() < (w * h) as usize {
+            return false;
+        }
+
+        let (width, height) = self.view();
+        let Some((columns, rows)) = clip_rect(x, y, w, h, width, height) else {
+            return true;
+        };
+        // Offsets of the visible part in the source image
+        let src_x = (columns.start as i64 - i64::from(x)) as usize;
+        let src_y = (rows.start as i64 - i64::from(y)) as usize;
+        let stride = w as usize;
+
+        for (i, row) in rows.enumerate() {
+            let src = (src_y + i) * stride + src_x;
+            let dst = row * DISPLAY_WIDTH;
+            self.pixels[dst + columns.start..dst + columns.end]
+                .copy_from_slice(&data[src..src + columns.len()]);
+        }
+        true
+    }
+
+    /// Draw a line of text with its top-left corner at (`x`, `y`), clipped
+    /// to the view
+    ///
+    /// Only the glyph pixels are set, the background is left as is. Text
+    /// past [`font::MAX_TEXT_LEN`] bytes is cut.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &[u8], color: u16) {
+        let (width, height) = self.view();
+        let (x, y) = (i64::from(x), i64::from(y));
+        if y + i64::from(GLYPH_HEIGHT) <= 0 || y >= height as i64 {
+            return;
+        }
+
+        let mut left = x;
+        for byte in font::chars(text) {
+            if left >= width as i64 {
+                break;
+            }
+            if left + i64::from(GLYPH_WIDTH) > 0 {
+                for (row, bits) in (y..).zip(font::glyph(byte)) {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits >> (GLYPH_WIDTH - 1 - column) & 1 != 0 {
+                            self.plot(left + i64::from(column), row, color);
+                        }
+                    }
+                }
+            }
+            left += i64::from(GLYPH_ADVANCE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FRAMEBUFFER_SIZE;
+
+    const WHITE: u16 = 0xFFFF;
+
+    /// Coordinates around every boundary the arithmetic could trip on
+    const EXTREMES: [i32; 15] = [
+        i32::MIN,
+        i32::MIN + 1,
+        -(1 << 30),
+        -65536,
+        -1025,
+        -128,
+        -1,
+        0,
+        1,
+        64,
+        127,
+        128,
+        65536,
+        i32::MAX - 1,
+        i32::MAX,
+    ];
+
+    fn framebuffer(width: u32, height: u32) -> FrameBuffer {
+        FrameBuffer {
+            pixels: [0; FRAMEBUFFER_SIZE],
+            width,
+            height,
+            frame_counter: 0,
+        }
+    }
+
+    fn lit(fb: &FrameBuffer) -> usize {
+        fb.pixels.iter().filter(|&&pixel| pixel != 0).count()
+    }
+
+    /// Small deterministic generator, so failures are reproducible
+    struct Xorshift(u32);
+
+    impl Xorshift {
+        fn next(&mut self) -> i32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            // Mix in the extremes, which random values rarely hit
+            if self.0.is_multiple_of(4) {
+                EXTREMES[(self.0 >> 8) as usize % EXTREMES.len()]
+            } else {
+                self.0 as i32
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rect_clips_to_view() {
+        let mut fb = framebuffer(128, 128);
+        fb.fill_rect(-10, -10, 20, 20, WHITE);
+        assert_eq!(lit(&fb), 100);
+
+        let mut fb = framebuffer(128, 128);
+        fb.fill_rect(i32::MAX, i32::MAX, i32::MAX, i32::MAX, WHITE);
+        fb.fill_rect(0, 0, -5, 10, WHITE);
+        assert_eq!(lit(&fb), 0);
+
+        fb.fill_rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX, WHITE);
+        assert_eq!(lit(&fb), 0);
+        fb.fill_rect(i32::MIN, 0, i32::MAX, 1, WHITE);
+        assert_eq!(lit(&fb), 0);
+        fb.fill_rect(-1, -1, i32::MAX, i32::MAX, WHITE);
+        assert_eq!(lit(&fb), FRAMEBUFFER_SIZE);
+    }
+
+    #[test]
+    fn drawing_stays_in_a_smaller_view() {
+        // Picture-in-picture view with the full display stride
+        let mut fb = framebuffer(32, 16);
+        fb.fill_rect(0, 0, 128, 128, WHITE);
+        fb.draw_line(-50, 8, 500, 8, WHITE);
+        fb.draw_circle(16, 8, 40, WHITE);
+        assert_eq!(lit(&fb), 32 * 16);
+        assert!(!fb.checked_set_pixel(32, 0, WHITE));
+        assert_eq!(fb.checked_get_pixel(0, 16), None);
+    }
+
+    #[test]
+    fn oversized_view_is_capped_to_the_display() {
+        let mut fb = framebuffer(u32::MAX, 1000);
+        assert!(fb.checked_set_pixel(127, 127, WHITE));
+        assert!(!fb.checked_set_pixel(128, 0, WHITE));
+        fb.fill(WHITE);
+        assert_eq!(lit(&fb), FRAMEBUFFER_SIZE);
+    }
+
+    #[test]
+    fn clipped_lines_keep_their_visible_pixels() {
+        let mut direct = framebuffer(128, 128);
+        direct.draw_line(-1000, -500, 1000, 500, WHITE);
+
+        // Same line, extended far past the guard band on both ends
+        let mut clipped = framebuffer(128, 128);
+        clipped.draw_line(-1_000_000, -500_000, 1_000_000, 500_000, WHITE);
+
+        assert!(lit(&direct) > 0);
+        assert_eq!(direct.pixels, clipped.pixels);
+    }
+
+    #[test]
+    fn lines_across_the_whole_range() {
+        let mut fb = framebuffer(128, 128);
+        fb.draw_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, WHITE);
+        assert_eq!(fb.checked_get_pixel(0, 0), Some(WHITE));
+        assert_eq!(fb.checked_get_pixel(127, 127), Some(WHITE));
+
+        let mut fb = framebuffer(128, 128);
+        fb.draw_line(i32::MIN, 5, i32::MAX, 5, WHITE);
+        assert_eq!(lit(&fb), 128);
+
+        let mut fb = framebuffer(128, 128);
+        fb.draw_line(i32::MIN, i32::MIN, i32::MIN, i32::MAX, WHITE);
+        assert_eq!(lit(&fb), 0);
+    }
+
+    #[test]
+    fn circle_radius_limits() {
+        let mut fb = framebuffer(128, 128);
+        fb.draw_circle(64, 64, -1, WHITE);
+        fb.draw_circle(64, 64, i32::MAX, WHITE);
+        fb.draw_circle(i32::MAX, i32::MIN, MAX_CIRCLE_RADIUS, WHITE);
+        assert_eq!(lit(&fb), 0);
+
+        fb.draw_circle(64, 64, 0, WHITE);
+        assert_eq!(lit(&fb), 1);
+        // Outline crossing the view from a far-away center
+        fb.draw_circle(-MAX_CIRCLE_RADIUS + 64, 64, MAX_CIRCLE_RADIUS, WHITE);
+        assert!(lit(&fb) > 1);
+    }
+
+    #[test]
+    fn blit_validates_and_clips() {
+        let image = [WHITE; 16];
+        let mut fb = framebuffer(128, 128);
+        assert!(!fb.blit(0, 0, 4, 5, &image));
+        assert!(!fb.blit(0, 0, 0, 4, &image));
+        assert!(!fb.blit(0, 0, MAX_BLIT_SIZE + 1, 1, &image));
+        assert_eq!(lit(&fb), 0);
+
+        assert!(fb.blit(-2, 126, 4, 4, &image));
+        assert_eq!(lit(&fb), 4);
+        assert!(fb.blit(i32::MAX, i32::MIN, 4, 4, &image));
+        assert_eq!(lit(&fb), 4);
+
+        let gradient: [u16; 16] = core::array::from_fn(|i| i as u16 + 1);
+        let mut fb = framebuffer(128, 128);
+        assert!(fb.blit(-1, -2, 4, 4, &gradient));
+        assert_eq!(fb.checked_get_pixel(0, 0), Some(10));
+        assert_eq!(fb.checked_get_pixel(2, 1), Some(16));
+    }
+
+    #[test]
+    fn text_is_drawn_and_clipped() {
+        let mut fb = framebuffer(128, 128);
+        fb.draw_text(0, 0, b"I", WHITE);
+        // Vertical bar of the I, its serifs, and nothing past the glyph
+        assert_eq!(fb.checked_get_pixel(2, 3), Some(WHITE));
+        assert_eq!(fb.checked_get_pixel(1, 0), Some(WHITE));
+        assert_eq!(fb.checked_get_pixel(5, 3), Some(0));
+        let one = lit(&fb);
+
+        let mut fb = framebuffer(128, 128);
+        fb.draw_text(-6, 0, b"II", WHITE);
+        assert_eq!(lit(&fb), one);
+        fb.draw_text(i32::MAX, i32::MIN, b"II", WHITE);
+        fb.draw_text(126, 126, b"II", WHITE);
+        assert!(lit(&fb) > one);
+
+        // One replacement glyph per character
+        let mut question = framebuffer(128, 128);
+        question.draw_text(0, 0, b"?", WHITE);
+        let mut accent = framebuffer(128, 128);
+        accent.draw_text(0, 0, "\u{e9}".as_bytes(), WHITE);
+        assert_eq!(question.pixels, accent.pixels);
+    }
+
+    #[test]
+    fn text_width_counts_characters() {
+        assert_eq!(font::text_width(b""), 0);
+        assert_eq!(font::text_width(b"A"), GLYPH_WIDTH);
+        assert_eq!(
+            font::text_width("42\u{e9}".as_bytes()),
+            3 * GLYPH_ADVANCE - 1
+        );
+        let long = [b'x'; font::MAX_TEXT_LEN + 10];
+        assert_eq!(
+            font::text_width(&long),
+            font::MAX_TEXT_LEN as i32 * GLYPH_ADVANCE - 1
+        );
+    }
+
+    #[test]
+    fn fuzz_extreme_arguments() {
+        let mut rng = Xorshift(0x2545_F491);
+        let image = [WHITE; 64];
+        let mut fb = framebuffer(128, 128);
+
+        for _ in 0..2000 {
+            let [a, b, c, d] = [rng.next(), rng.next(), rng.next(), rng.next()];
+            fb.checked_set_pixel(a, b, WHITE);
+            let _ = fb.checked_get_pixel(c, d);
+            fb.fill_rect(a, b, c, d, WHITE);
+            fb.draw_line(a, b, c, d, WHITE);
+            fb.draw_circle(a, b, c, WHITE);
+            fb.draw_circle(a, b, c.rem_euclid(MAX_CIRCLE_RADIUS + 1), WHITE);
+            fb.blit(a, b, c.rem_euclid(9), d.rem_euclid(9), &image);
+            fb.blit(a, b, c, d, &image);
+            fb.draw_text(a, b, b"fuzz", WHITE);
+        }
+    }
+
+    #[test]
+    fn fuzz_every_extreme_combination() {
+        let mut fb = framebuffer(128, 128);
+        for &a in &EXTREMES {
+            for &b in &EXTREMES {
+                for &c in &EXTREMES {
+                    fb.fill_rect(a, b, c, a, WHITE);
+                    fb.draw_line(a, b, c, b, WHITE);
+                    fb.draw_line(a, c, b, a, WHITE);
+                    fb.draw_circle(a, b, c, WHITE);
+                }
+            }
+        }
+    }
+}
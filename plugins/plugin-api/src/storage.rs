@@ -0,0 +1,342 @@
+//! Key-value store behind the storage callbacks
+//!
+//! Hosts keep one [`Storage`] image shared by all plugins, small enough to
+//! live in a single flash sector. Each plugin sees its own keys: entries are
+//! namespaced by the plugin name from its header, so two plugins can both
+//! save a `"highscore"`.
+//!
+//! The image is a magic number followed by records packed back to back,
+//! `[namespace len u8][key len u8][value len u16 LE][namespace][key][value]`,
+//! and erased bytes (`0xFF`) after the last record. Replacing a value moves
+//! its record to the end, so the image never needs more than one write per
+//! change. Persisting the image is up to the host, see
+//! [`take_dirty`](Storage::take_dirty).
+
+use crate::permissions::PLUGIN_ERR_DENIED;
+
+/// Size of the storage image, one RP2350 flash sector
+pub const STORAGE_SIZE: usize = 4096;
+/// Longest key, in bytes
+pub const MAX_KEY_LEN: usize = 32;
+/// Longest value, in bytes
+pub const MAX_VALUE_LEN: usize = 256;
+
+/// `storage_read_fn` result when the key has no value
+pub const STORAGE_NOT_FOUND: i32 = -1;
+/// `storage_write_fn` result when the key is empty or too long
+pub const STORAGE_ERR_INVALID_KEY: i32 = -2;
+/// `storage_write_fn` result when the value is longer than `MAX_VALUE_LEN`
+pub const STORAGE_ERR_TOO_LARGE: i32 = -3;
+/// `storage_write_fn` result when the image has no room left for the value
+pub const STORAGE_ERR_FULL: i32 = -4;
+
+const STORAGE_MAGIC: u32 = 0x504B_5631; // "PKV1"
+const MAGIC_SIZE: usize = 4;
+const RECORD_HEADER_SIZE: usize = 4;
+/// Namespaces are plugin names, as long as `PluginHeader::name`
+const MAX_NAMESPACE_LEN: usize = 32;
+const ERASED: u8 = 0xFF;
+
+/// Why a storage call failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StorageError {
+    /// The key has no value
+    NotFound,
+    InvalidKey,
+    TooLarge,
+    Full,
+    /// The host did not grant `PLUGIN_PERM_STORAGE`
+    Denied,
+}
+
+impl StorageError {
+    /// Result code of the storage functions for this error
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::NotFound => STORAGE_NOT_FOUND,
+            Self::InvalidKey => STORAGE_ERR_INVALID_KEY,
+            Self::TooLarge => STORAGE_ERR_TOO_LARGE,
+            Self::Full => STORAGE_ERR_FULL,
+            Self::Denied => PLUGIN_ERR_DENIED,
+        }
+    }
+
+    /// Error of a storage function result code, `None` for success
+    #[must_use]
+    pub const fn from_code(code: i32) -> Option<Self> {
+        match code {
+            0.. => None,
+            STORAGE_NOT_FOUND => Some(Self::NotFound),
+            PLUGIN_ERR_DENIED => Some(Self::Denied),
+            STORAGE_ERR_TOO_LARGE => Some(Self::TooLarge),
+            STORAGE_ERR_FULL => Some(Self::Full),
+            _ => Some(Self::InvalidKey),
+        }
+    }
+}
+
+/// Location of a record in the image
+struct Record {
+    start: usize,
+    value: usize,
+    end: usize,
+}
+
+/// Storage image shared by the plugins of a host
+pub struct Storage {
+    image: [u8; STORAGE_SIZE],
+    /// End of the last record
+    used: usize,
+    /// Changed since the last `take_dirty`
+    dirty: bool,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage {
+    /// Empty storage
+    pub const fn new() -> Self {
+        let mut image = [ERASED; STORAGE_SIZE];
+        let magic = STORAGE_MAGIC.to_le_bytes();
+        image[0] = magic[0];
+        image[1] = magic[1];
+        image[2] = magic[2];
+        image[3] = magic[3];
+        Self {
+            image,
+            used: MAGIC_SIZE,
+            dirty: false,
+        }
+    }
+
+    /// Replace the content with an image read back from flash
+    ///
+    /// An image without the magic number, e.g. erased flash, loads as empty
+    /// storage and `false` is returned. Records are kept up to the first one
+    /// that is cut or malformed.
+    pub fn load(&mut self, image: &[u8]) -> bool {
+        *self = Self::new();
+        if image.get(..MAGIC_SIZE) != Some(&STORAGE_MAGIC.to_le_bytes()[..]) {
+            return false;
+        }
+
+        let len = image.len().min(STORAGE_SIZE);
+        self.image[..len].copy_from_slice(&image[..len]);
+        while let Some(record) = self.record_at(self.used) {
+            self.used = record.end;
+        }
+        self.image[self.used..].fill(ERASED);
+        true
+    }
+
+    /// Copy the value of `key` into `buf`
+    ///
+    /// Returns the length of the value, which may be more than `buf` holds;
+    /// only the start of the value is copied then.
+    pub fn read(&self, namespace: &[u8], key: &[u8], buf: &mut [u8]) -> Option<usize> {
+        let record = self.find(namespace, key)?;
+        let value = &self.image[record.value..record.end];
+        let len = value.len().min(buf.len());
+        buf[..len].copy_from_slice(&value[..len]);
+        Some(value.len())
+    }
+
+    /// Set the value of `key`, replacing the previous one
+    ///
+    /// Writing the value a key already has leaves the storage clean.
+    pub fn write(
+        &mut self,
+        namespace: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), StorageError> {
+        if namespace.len() > MAX_NAMESPACE_LEN || key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(StorageError::InvalidKey);
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(StorageError::TooLarge);
+        }
+
+        let old = self.find(namespace, key);
+        if let Some(old) = &old
+            && self.image[old.value..old.end] == *value
+        {
+            return Ok(());
+        }
+
+        let size = RECORD_HEADER_SIZE + namespace.len() + key.len() + value.len();
+        let freed = old.as_ref().map_or(0, |old| old.end - old.start);
+        if self.used - freed + size > STORAGE_SIZE {
+            return Err(StorageError::Full);
+        }
+
+        if let Some(old) = old {
+            self.image.copy_within(old.end..self.used, old.start);
+            self.used -= freed;
+        }
+
+        let record = &mut self.image[self.used..self.used + size];
+        record[0] = namespace.len() as u8;
+        record[1] = key.len() as u8;
+        record[2..4].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        let (names, data) = record[RECORD_HEADER_SIZE..].split_at_mut(namespace.len());
+        names.copy_from_slice(namespace);
+        data[..key.len()].copy_from_slice(key);
+        data[key.len()..].copy_from_slice(value);
+        self.used += size;
+        self.image[self.used..].fill(ERASED);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Whole image, as it should be written to flash
+    pub const fn image(&self) -> &[u8; STORAGE_SIZE] {
+        &self.image
+    }
+
+    /// Bytes used by the records
+    pub const fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Check whether the image changed since the last call
+    ///
+    /// Hosts persist the image when this returns `true`, at a pace that
+    /// spares the flash rather than after every write.
+    pub fn take_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.dirty)
+    }
+
+    fn find(&self, namespace: &[u8], key: &[u8]) -> Option<Record> {
+        let mut offset = MAGIC_SIZE;
+        while offset < self.used {
+            let record = self.record_at(offset)?;
+            let names = record.start + RECORD_HEADER_SIZE;
+            let keys = names + usize::from(self.image[record.start]);
+            if self.image[names..keys] == *namespace && self.image[keys..record.value] == *key {
+                return Some(record);
+            }
+            offset = record.end;
+        }
+        None
+    }
+
+    /// Record starting at `start`, if a valid one does
+    fn record_at(&self, start: usize) -> Option<Record> {
+        let header = self.image.get(start..start + RECORD_HEADER_SIZE)?;
+        let namespace_len = usize::from(header[0]);
+        let key_len = usize::from(header[1]);
+        let value_len = usize::from(u16::from_le_bytes([header[2], header[3]]));
+        if namespace_len > MAX_NAMESPACE_LEN
+            || key_len == 0
+            || key_len > MAX_KEY_LEN
+            || value_len > MAX_VALUE_LEN
+        {
+            return None;
+        }
+
+        let value = start + RECORD_HEADER_SIZE + namespace_len + key_len;
+        let end = value + value_len;
+        (end <= STORAGE_SIZE).then_some(Record { start, value, end })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_namespaced_and_replaced() {
+        let mut storage = Storage::new();
+        let mut buf = [0; 8];
+        assert_eq!(storage.read(b"snake", b"highscore", &mut buf), None);
+
+        storage.write(b"snake", b"highscore", &[1, 2]).unwrap();
+        storage.write(b"tetris", b"highscore", &[3]).unwrap();
+        storage.write(b"snake", b"highscore", &[4, 5, 6]).unwrap();
+        assert!(storage.take_dirty());
+
+        assert_eq!(storage.read(b"snake", b"highscore", &mut buf), Some(3));
+        assert_eq!(buf[..3], [4, 5, 6]);
+        assert_eq!(storage.read(b"tetris", b"highscore", &mut buf), Some(1));
+        assert_eq!(buf[0], 3);
+
+        // Same value again, nothing to persist
+        storage.write(b"tetris", b"highscore", &[3]).unwrap();
+        assert!(!storage.take_dirty());
+
+        // Short buffers get the start of the value
+        let mut short = [0; 2];
+        assert_eq!(storage.read(b"snake", b"highscore", &mut short), Some(3));
+        assert_eq!(short, [4, 5]);
+    }
+
+    #[test]
+    fn invalid_writes_are_rejected() {
+        let mut storage = Storage::new();
+        assert_eq!(
+            storage.write(b"p", b"", &[1]),
+            Err(StorageError::InvalidKey)
+        );
+        assert_eq!(
+            storage.write(b"p", &[b'k'; MAX_KEY_LEN + 1], &[1]),
+            Err(StorageError::InvalidKey)
+        );
+        assert_eq!(
+            storage.write(b"p", b"k", &[0; MAX_VALUE_LEN + 1]),
+            Err(StorageError::TooLarge)
+        );
+        assert!(!storage.take_dirty());
+
+        // Fill the image, replacing a value must still fit
+        let value = [7; MAX_VALUE_LEN];
+        let mut key = 0u8;
+        while storage.write(b"p", &[b'k', key], &value).is_ok() {
+            key += 1;
+        }
+        assert_eq!(storage.write(b"p", b"x", &value), Err(StorageError::Full));
+        storage
+            .write(b"p", &[b'k', 0], &[1; MAX_VALUE_LEN])
+            .unwrap();
+        assert_eq!(
+            StorageError::from_code(StorageError::Full.code()),
+            Some(StorageError::Full)
+        );
+        assert_eq!(StorageError::from_code(0), None);
+        for error in [StorageError::NotFound, StorageError::Denied] {
+            assert_eq!(StorageError::from_code(error.code()), Some(error));
+        }
+    }
+
+    #[test]
+    fn images_load_back() {
+        let mut storage = Storage::new();
+        storage.write(b"snake", b"a", &[1]).unwrap();
+        storage.write(b"snake", b"b", &[2, 2]).unwrap();
+
+        let mut loaded = Storage::new();
+        assert!(loaded.load(storage.image()));
+        assert_eq!(loaded.used(), storage.used());
+        let mut buf = [0; 2];
+        assert_eq!(loaded.read(b"snake", b"b", &mut buf), Some(2));
+
+        // Erased flash
+        assert!(!loaded.load(&[ERASED; STORAGE_SIZE]));
+        assert_eq!(loaded.read(b"snake", b"a", &mut buf), None);
+
+        // A corrupted record ends the store, earlier ones are kept
+        let mut image = *storage.image();
+        image[MAGIC_SIZE + RECORD_HEADER_SIZE + 7] = 0xEE;
+        assert!(loaded.load(&image));
+        assert_eq!(loaded.read(b"snake", b"a", &mut buf), Some(1));
+        assert_eq!(loaded.read(b"snake", b"b", &mut buf), None);
+        loaded.write(b"snake", b"c", &[3]).unwrap();
+        assert_eq!(loaded.read(b"snake", b"c", &mut buf), Some(1));
+    }
+}
@@ -17,10 +17,40 @@
 
 #define FRAMEBUFFER_SIZE (DISPLAY_WIDTH * DISPLAY_HEIGHT)
 
+// Milliseconds per frame when `millis()` is derived from the frame counter
+#define FRAME_TIME_MS 16
+
+// Time the host lets a plugin's `update` take by default, in microseconds
+//
+// A plugin going over it is stopped, see the host's update watchdog.
+#define UPDATE_BUDGET_US 30000
+
 // Plugin magic number and version
 #define PLUGIN_MAGIC 1347179847
 
-#define PLUGIN_API_VERSION 1
+#define PLUGIN_API_VERSION 2
+
+// Capability flags of [`PluginHeader::capabilities`]
+//
+// The plugin implements `pause` and `resume`.
+#define PLUGIN_CAP_PAUSE (1 << 0)
+
+// Requirement flags of [`PluginHeader::requirements`]
+//
+// The plugin does not work without storage, granted by the host.
+#define PLUGIN_REQ_STORAGE (1 << 0)
+
+// The plugin needs network data from the host, the cluster data of [`data`].
+#define PLUGIN_REQ_NETWORK (1 << 1)
+
+// The plugin draws at twice the panel resolution.
+#define PLUGIN_REQ_DOUBLE_RES (1 << 2)
+
+// Side of the square [`PluginIcon`], in pixels
+#define PLUGIN_ICON_SIZE 16
+
+// Pixels of a [`PluginIcon`]
+#define PLUGIN_ICON_PIXELS (PLUGIN_ICON_SIZE * PLUGIN_ICON_SIZE)
 
 #define INPUT_UP (1 << 0)
 
@@ -38,6 +68,128 @@
 
 #define INPUT_SELECT (1 << 7)
 
+// Full deflection of [`InputState::axis_x`] and [`InputState::axis_y`]
+#define INPUT_AXIS_MAX INT16_MAX
+
+// Offset of [`PluginHeader::code_size`](crate::PluginHeader::code_size) in
+// a plugin binary, for packagers running on hosts with wider pointers
+#define CODE_SIZE_OFFSET 88
+
+// First word of an asset table, "ASST"
+#define ASSET_MAGIC 1414746945
+
+// Bytes before the entries of an asset table: magic and count
+#define ASSET_TABLE_HEADER_SIZE 8
+
+// Bytes of an asset table entry: offset and length
+#define ASSET_ENTRY_SIZE 8
+
+// `read_asset_fn` result when the plugin has no such asset
+#define ASSET_NOT_FOUND -1
+
+// Clusters of a layout, `CLUSTER_*` indexes
+#define CLUSTER_COUNT 6
+
+#define CLUSTER_F0 0
+
+#define CLUSTER_F1 1
+
+#define CLUSTER_F1B 2
+
+#define CLUSTER_F2 3
+
+#define CLUSTER_F4 4
+
+#define CLUSTER_F6 5
+
+// Values of [`SeatInfo::status`]
+#define SEAT_STATUS_FREE 0
+
+#define SEAT_STATUS_TAKEN 1
+
+#define SEAT_STATUS_REPORTED 2
+
+#define SEAT_STATUS_BROKEN 3
+
+// Values of [`SeatInfo::kind`]
+#define SEAT_KIND_MAC 0
+
+#define SEAT_KIND_LENOVO 1
+
+#define SEAT_KIND_DELL 2
+
+#define SEAT_KIND_FLEX 3
+
+// Flags of `get_cluster_attr_mask_fn`
+#define CLUSTER_ATTR_PISCINE (1 << 0)
+
+#define CLUSTER_ATTR_EXAM (1 << 1)
+
+#define CLUSTER_ATTR_SILENT (1 << 2)
+
+#define CLUSTER_ATTR_EVENT (1 << 3)
+
+#define CLUSTER_ATTR_CLOSED (1 << 4)
+
+// Result of `get_seat_fn` for a cluster or seat index out of range
+#define DATA_NOT_FOUND -1
+
+// Width of a glyph in pixels
+#define GLYPH_WIDTH 5
+
+// Height of a glyph in pixels, and of a line of text
+#define GLYPH_HEIGHT 7
+
+// Columns from one character to the next
+#define GLYPH_ADVANCE (GLYPH_WIDTH + 1)
+
+// Longest text the hosts draw or measure, in bytes
+//
+// Longer text is cut, which bounds the work of a call.
+#define MAX_TEXT_LEN 1024
+
+// Read and write the plugin storage (`storage_read_fn`, `storage_write_fn`)
+#define PLUGIN_PERM_STORAGE (1 << 0)
+
+// Play tones (`tone_fn`)
+#define PLUGIN_PERM_SOUND (1 << 1)
+
+// Every permission known to this API version
+#define PLUGIN_PERM_ALL (PLUGIN_PERM_STORAGE | PLUGIN_PERM_SOUND)
+
+// Result of a call whose permission the host did not grant
+#define PLUGIN_ERR_DENIED -16
+
+// Largest width or height of a blit
+#define MAX_BLIT_SIZE 1024
+
+// Largest circle radius that is drawn
+//
+// Bigger circles crossing the view would take too long to rasterize, so
+// they are skipped.
+#define MAX_CIRCLE_RADIUS (1 << 14)
+
+// Size of the storage image, one RP2350 flash sector
+#define STORAGE_SIZE 4096
+
+// Longest key, in bytes
+#define MAX_KEY_LEN 32
+
+// Longest value, in bytes
+#define MAX_VALUE_LEN 256
+
+// `storage_read_fn` result when the key has no value
+#define STORAGE_NOT_FOUND -1
+
+// `storage_write_fn` result when the key is empty or too long
+#define STORAGE_ERR_INVALID_KEY -2
+
+// `storage_write_fn` result when the value is longer than `MAX_VALUE_LEN`
+#define STORAGE_ERR_TOO_LARGE -3
+
+// `storage_write_fn` result when the image has no room left for the value
+#define STORAGE_ERR_FULL -4
+
 // Direct framebuffer access structure
 typedef struct FrameBuffer {
   // Raw pixel data in RGB565 format
@@ -59,8 +211,31 @@ typedef struct GraphicsContext {
   void (*draw_line_fn)(int32_t x0, int32_t y0, int32_t x1, int32_t y1, uint16_t color);
   void (*draw_circle_fn)(int32_t cx, int32_t cy, int32_t radius, uint16_t color);
   void (*blit_fn)(int32_t x, int32_t y, int32_t w, int32_t h, const uint16_t *data);
+  void (*draw_text_fn)(int32_t x, int32_t y, const uint8_t *text, uint32_t len, uint16_t color);
+  int32_t (*text_width_fn)(const uint8_t *text, uint32_t len);
 } GraphicsContext;
 
+// Hardware the frames are shown on, as copied by `display_info_fn`
+//
+// Plugins always draw into a `DISPLAY_WIDTH` x `DISPLAY_HEIGHT`
+// framebuffer, which the host maps onto its panels. A slow refresh rate,
+// e.g. a bit-banged driver, blurs fast motion and makes dim colors flicker;
+// a low color depth bands gradients.
+typedef struct DisplayInfo {
+  // Full refreshes of the panels per second, 0 if unknown
+  uint32_t refresh_hz;
+  // Bits per color channel the panels show, 0 if unknown
+  uint32_t color_depth;
+  // Size of one panel, in pixels
+  uint32_t panel_width;
+  uint32_t panel_height;
+  // Panels daisy-chained to make up the display
+  uint32_t chain_length;
+} DisplayInfo;
+// One panel of the framebuffer's size, as reported by hosts that don't
+// know their hardware
+#define DisplayInfo_UNKNOWN (DisplayInfo){ .refresh_hz = 0, .color_depth = 0, .panel_width = (uint32_t)DISPLAY_WIDTH, .panel_height = (uint32_t)DISPLAY_HEIGHT, .chain_length = 1 }
+
 // System utilities (C function pointers and color constants)
 typedef struct SystemContext {
   uint32_t (*random_fn)(void);
@@ -74,8 +249,52 @@ typedef struct SystemContext {
   uint16_t color_yellow;
   uint16_t color_cyan;
   uint16_t color_magenta;
+  // `random()` and `millis()` only depend on the frame counter: the same
+  // frame always gives the same image, e.g. for golden-image tests
+  bool deterministic;
+  // Seed of `random()`, for plugins with their own generator to reuse
+  uint32_t seed;
+  // Copy the value of `key` into `buf`, returning the length of the value
+  // (which may exceed `buf_len`), `STORAGE_NOT_FOUND` or `PLUGIN_ERR_DENIED`
+  int32_t (*storage_read_fn)(const uint8_t *key, uint32_t key_len, uint8_t *buf, uint32_t buf_len);
+  // Save `len` bytes of `data` under `key`, returning 0, a
+  // `STORAGE_ERR_*` code or `PLUGIN_ERR_DENIED`
+  int32_t (*storage_write_fn)(const uint8_t *key,
+                              uint32_t key_len,
+                              const uint8_t *data,
+                              uint32_t len);
+  // Beep at `freq_hz` for `duration_ms`, replacing the current tone;
+  // 0 Hz stops it. Returns 0 or `PLUGIN_ERR_DENIED`
+  int32_t (*tone_fn)(uint32_t freq_hz, uint32_t duration_ms);
+  // Copy the hardware the frames are shown on into `out`
+  void (*display_info_fn)(struct DisplayInfo *out);
+  // Copy asset `asset` from byte `offset` into `buf`, returning the
+  // length of the asset (which may exceed `buf_len`) or `ASSET_NOT_FOUND`
+  int32_t (*read_asset_fn)(uint32_t asset, uint32_t offset, uint8_t *buf, uint32_t buf_len);
 } SystemContext;
 
+// A seat of a cluster, as copied by `get_seat_fn`
+typedef struct SeatInfo {
+  // Position in the cluster's seat grid
+  uint16_t x;
+  uint16_t y;
+  // `SEAT_STATUS_*`
+  uint8_t status;
+  // `SEAT_KIND_*`
+  uint8_t kind;
+} SeatInfo;
+
+// Cluster data functions (C function pointers)
+typedef struct DataContext {
+  // Number of seats of `cluster`, 0 for an unknown cluster
+  uint32_t (*get_cluster_seat_count_fn)(uint32_t cluster);
+  // Copy seat `index` of `cluster` into `out`, returning 0 or
+  // `DATA_NOT_FOUND`
+  int32_t (*get_seat_fn)(uint32_t cluster, uint32_t index, struct SeatInfo *out);
+  // `CLUSTER_ATTR_*` flags of `cluster`, 0 for an unknown cluster
+  uint32_t (*get_cluster_attr_mask_fn)(uint32_t cluster);
+} DataContext;
+
 // Main API structure passed to plugins.
 //
 // This struct contains raw pointers to the runtime-provided contexts.
@@ -88,16 +307,69 @@ typedef struct PluginAPI {
   const struct GraphicsContext *gfx;
   // System utilities
   const struct SystemContext *sys;
+  // Cluster data
+  const struct DataContext *data;
 } PluginAPI;
 
+// Picture shown next to the plugin's name in menus
+//
+// Pixels are RGB565, row by row. Menus read it from the binary without
+// loading the plugin, so it must be a constant.
+typedef struct PluginIcon {
+  uint16_t pixels[PLUGIN_ICON_PIXELS];
+} PluginIcon;
+
+// Inputs of a frame, as passed to `update`
+//
+// Hosts without an analog stick or a rotary encoder leave those fields at
+// 0, so plugins can read them unconditionally.
+typedef struct InputState {
+  // `INPUT_*` flags of the buttons held
+  uint32_t buttons;
+  // Stick position from `-INPUT_AXIS_MAX` (left) to `INPUT_AXIS_MAX`
+  int16_t axis_x;
+  // Stick position from `-INPUT_AXIS_MAX` (up) to `INPUT_AXIS_MAX`
+  int16_t axis_y;
+  // Encoder detents turned since the last frame, positive clockwise
+  int32_t rotary_delta;
+} InputState;
+
 // Plugin header placed at start of binary
+//
+// The optional entry points are only called when their capability flag is
+// set, so plugins can leave them null; the other fields are checked by the
+// host before `init`, with 0 meaning "not declared".
 typedef struct PluginHeader {
   uint32_t magic;
   uint32_t api_version;
   uint8_t name[32];
   int32_t (*init)(const struct PluginAPI *api);
-  void (*update)(const struct PluginAPI *api, uint32_t inputs);
+  void (*update)(const struct PluginAPI *api, const struct InputState *input);
   void (*cleanup)(void);
+  // `PLUGIN_CAP_*` flags of the optional entry points implemented
+  uint32_t capabilities;
+  // Called when the plugin stops being updated, e.g. under the menu
+  void (*pause)(void);
+  // Called before the first update after a pause
+  void (*resume)(void);
+  // `PLUGIN_PERM_*` flags of the host functions the plugin uses
+  uint32_t permissions;
+  // Bytes of zero-initialized memory (`.bss`) the plugin needs after its
+  // binary; hosts zero the whole load buffer when it is 0
+  uint32_t bss_size;
+  // Oldest host API version the plugin works with
+  uint32_t min_host_version;
+  // Newest host API version the plugin works with
+  uint32_t max_host_version;
+  // `PLUGIN_REQ_*` flags of what the host must provide
+  uint32_t requirements;
+  // Icon for menus, null for none
+  const struct PluginIcon *icon;
+  // Bytes of the binary loaded into RAM, header included, the rest being
+  // [`assets`]; 0 loads the whole binary. Set by the packager
+  uint32_t code_size;
 } PluginHeader;
 
+
+
 #endif  /* PLUGIN_API_H */
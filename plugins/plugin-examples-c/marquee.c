@@ -0,0 +1,93 @@
+// plugins/plugin-examples-c/marquee.c
+// Marquee plugin - scrolling text over a bouncing rectangle
+//
+// A template for C plugins: it only uses the generated plugin_api.h, draws
+// through the host's gfx functions and derives its motion from the frame
+// counter, so it looks the same on the panel and in the simulator.
+#include <stdint.h>
+#include "plugin_api.h"
+#include "plugin_helpers.h"
+
+static const char MESSAGE[] = "HELLO FROM C - PLUGIN_API.H";
+#define MESSAGE_LEN ((uint32_t)(sizeof(MESSAGE) - 1))
+
+// Text baseline row and scrolling speed
+#define TEXT_Y 100
+#define TEXT_SPEED 1
+
+// Rectangle size and speed, in pixels per frame
+#define RECT_W 24
+#define RECT_H 16
+#define RECT_DX 2
+#define RECT_DY 1
+
+static int32_t text_width;
+static int32_t rect_x, rect_y;
+static int32_t rect_dx, rect_dy;
+static uint32_t last_frame;
+
+int32_t marquee_init(const PluginAPI* api) {
+    text_width = api->gfx->text_width_fn((const uint8_t*)MESSAGE, MESSAGE_LEN);
+    rect_x = 0;
+    rect_y = 0;
+    rect_dx = RECT_DX;
+    rect_dy = RECT_DY;
+    last_frame = api->framebuffer->frame_counter;
+    return 0; // Success
+}
+
+// Move the rectangle one step, bouncing off the top of the text
+static void step_rect(void) {
+    rect_x += rect_dx;
+    rect_y += rect_dy;
+    if (rect_x <= 0 || rect_x + RECT_W >= DISPLAY_WIDTH) {
+        rect_dx = -rect_dx;
+    }
+    if (rect_y <= 0 || rect_y + RECT_H >= TEXT_Y - 4) {
+        rect_dy = -rect_dy;
+    }
+}
+
+void marquee_update(const PluginAPI* api, const InputState* input) {
+    const GraphicsContext* gfx = api->gfx;
+    uint32_t frame = api->framebuffer->frame_counter;
+
+    // A freezes the rectangle, to show input handling
+    if (!(input->buttons & INPUT_A)) {
+        for (uint32_t i = last_frame; i != frame; i++) {
+            step_rect();
+        }
+    }
+    last_frame = frame;
+
+    gfx->clear_fn(api->sys->color_black);
+
+    // Rectangle with a one pixel outline
+    gfx->fill_rect_fn(rect_x, rect_y, RECT_W, RECT_H, RGB565(0, 96, 255));
+    gfx->draw_line_fn(rect_x, rect_y, rect_x + RECT_W - 1, rect_y, api->sys->color_white);
+    gfx->draw_line_fn(rect_x, rect_y + RECT_H - 1, rect_x + RECT_W - 1, rect_y + RECT_H - 1,
+                      api->sys->color_white);
+
+    // Text enters on the right and wraps around once it left on the left
+    int32_t span = DISPLAY_WIDTH + text_width;
+    int32_t x = DISPLAY_WIDTH - (int32_t)((frame * TEXT_SPEED) % (uint32_t)span);
+    gfx->draw_line_fn(0, TEXT_Y - 3, DISPLAY_WIDTH - 1, TEXT_Y - 3, api->sys->color_yellow);
+    gfx->draw_text_fn(x, TEXT_Y, (const uint8_t*)MESSAGE, MESSAGE_LEN, api->sys->color_yellow);
+    gfx->draw_line_fn(0, TEXT_Y + GLYPH_HEIGHT + 2, DISPLAY_WIDTH - 1, TEXT_Y + GLYPH_HEIGHT + 2,
+                      api->sys->color_yellow);
+}
+
+void marquee_cleanup(void) {
+    // Nothing to clean up
+}
+
+// Export the plugin header
+__attribute__((section(".plugin_header")))
+const PluginHeader PLUGIN_HEADER = {
+    .magic = PLUGIN_MAGIC,
+    .api_version = PLUGIN_API_VERSION,
+    .name = "Marquee",
+    .init = marquee_init,
+    .update = marquee_update,
+    .cleanup = marquee_cleanup,
+};
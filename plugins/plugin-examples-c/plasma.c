@@ -29,7 +29,7 @@ int32_t plasma_init(const PluginAPI* plugin_api) {
     return 0; // Success
 }
 
-void plasma_update(const PluginAPI* plugin_api, uint32_t inputs) {
+void plasma_update(const PluginAPI* plugin_api, const InputState* input) {
     FrameBuffer* fb = api->framebuffer;
 
     // DIRECT BUFFER ACCESS for maximum performance
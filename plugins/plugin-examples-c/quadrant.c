@@ -28,7 +28,7 @@ int32_t quadrant_init(const PluginAPI* plugin_api) {
     return 0; // Success
 }
 
-void quadrant_update(const PluginAPI* plugin_api, uint32_t inputs) {
+void quadrant_update(const PluginAPI* plugin_api, const InputState* input) {
     FrameBuffer* fb = api->framebuffer;
 
     // Clear to black
@@ -14,8 +14,25 @@ pub struct BouncingBallPlugin {
     radius: i32,
 }
 
+/// Menu icon: the ball, in its starting color
+static ICON: PluginIcon = {
+    const BALL: u16 = 0x9332;
+    let mut pixels = [0; PLUGIN_ICON_PIXELS];
+    let mut i = 0;
+    while i < PLUGIN_ICON_PIXELS {
+        // Distance from the center, in half pixels
+        let dx = (2 * (i % PLUGIN_ICON_SIZE)) as i32 - 15;
+        let dy = (2 * (i / PLUGIN_ICON_SIZE)) as i32 - 15;
+        if dx * dx + dy * dy <= 13 * 13 {
+            pixels[i] = BALL;
+        }
+        i += 1;
+    }
+    PluginIcon { pixels }
+};
+
 // Generate C ABI functions for the plugin
-plugin_main!(BouncingBallPlugin, "bouncing_ball");
+plugin_main!(BouncingBallPlugin, "bouncing_ball", icon: Some(&ICON));
 
 impl PluginImpl for BouncingBallPlugin {
     fn new() -> Self {
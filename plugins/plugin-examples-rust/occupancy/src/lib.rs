@@ -0,0 +1,76 @@
+//! Cluster occupancy plugin
+//!
+//! Shows how full each cluster is, one bar per floor, from the cluster
+//! data of the host. Closed clusters are drawn in red.
+
+#![cfg_attr(not(feature = "simulator"), no_std)]
+
+use plugin_api::data::{CLUSTER_ATTR_CLOSED, CLUSTER_COUNT, SEAT_STATUS_TAKEN};
+use plugin_api::prelude::*;
+use plugin_api::PLUGIN_REQ_NETWORK;
+
+const NAMES: [&str; CLUSTER_COUNT as usize] = ["F0", "F1", "F1B", "F2", "F4", "F6"];
+
+const BAR_X: i32 = 24;
+const BAR_WIDTH: i32 = 100;
+const ROW_HEIGHT: i32 = 20;
+const BAR_HEIGHT: i32 = 12;
+
+pub struct OccupancyPlugin;
+
+plugin_main!(
+    OccupancyPlugin,
+    "occupancy",
+    requirements: PLUGIN_REQ_NETWORK,
+);
+
+impl PluginImpl for OccupancyPlugin {
+    fn new() -> Self {
+        Self
+    }
+
+    fn init(&mut self, _api: &mut PluginAPI) -> i32 {
+        0 // Success
+    }
+
+    fn update(&mut self, api: &mut PluginAPI, _inputs: Inputs) {
+        let gfx = api.gfx();
+        let sys = api.sys();
+        let data = api.data();
+
+        gfx.clear(sys.black());
+        for (cluster, name) in (0..CLUSTER_COUNT).zip(NAMES) {
+            let y = 4 + cluster as i32 * ROW_HEIGHT;
+            gfx.draw_text(2, y + 3, name, sys.white());
+
+            let total = data.cluster_seat_count(cluster);
+            let taken = data
+                .seats(cluster)
+                .filter(|seat| seat.status == SEAT_STATUS_TAKEN)
+                .count() as i32;
+            let fill = if total == 0 {
+                0
+            } else {
+                taken * BAR_WIDTH / total as i32
+            };
+            let color = if data.cluster_attr_mask(cluster) & CLUSTER_ATTR_CLOSED != 0 {
+                sys.red()
+            } else {
+                sys.green()
+            };
+
+            gfx.fill_rect(BAR_X, y, BAR_WIDTH, BAR_HEIGHT, sys.rgb(32, 32, 32));
+            gfx.fill_rect(BAR_X, y, fill, BAR_HEIGHT, color);
+        }
+    }
+
+    fn cleanup(&mut self) {
+        // Nothing to clean up
+    }
+}
+
+impl Default for OccupancyPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -332,6 +332,10 @@ fn generate_empty_plugin_list(out_dir: &Path) {
         pub fn get_plugin_list() -> &'static [(&'static str, &'static [u8])] {
             &[]
         }
+
+        pub fn plugin_registry() -> &'static [PluginInfo] {
+            &[]
+        }
     "#;
     std::fs::write(out_dir.join("plugin_includes.rs"), code).unwrap();
 }
@@ -357,10 +361,93 @@ fn generate_plugin_includes(out_dir: &Path, plugins: &[String]) {
             plugin.to_uppercase().replace('-', "_")
         ));
     }
+    code.push_str("    ]\n}\n\n");
+
+    // Registry of the plugins whose header could be read
+    code.push_str("pub fn plugin_registry() -> &'static [PluginInfo] {\n    &[\n");
+    for plugin in plugins {
+        let bin_file = out_dir.join(format!("{}.bin", plugin));
+        let Some(header) = std::fs::read(&bin_file)
+            .ok()
+            .and_then(|bytes| read_header(&bytes))
+        else {
+            println!(
+                "cargo:warning=Plugin {} has no valid header, left out of the registry",
+                plugin
+            );
+            continue;
+        };
+        code.push_str(&format!(
+            "        PluginInfo {{\n            id: {:?},\n            name: {:?},\n            api_version: {},\n            min_host_version: {},\n            max_host_version: {},\n            capabilities: {:#x},\n            permissions: {:#x},\n            requirements: {:#x},\n            icon_offset: {:?},\n            bytes: plugins::{}_BYTES,\n        }},\n",
+            plugin,
+            header.name,
+            header.api_version,
+            header.min_host_version,
+            header.max_host_version,
+            header.capabilities,
+            header.permissions,
+            header.requirements,
+            header.icon_offset,
+            plugin.to_uppercase().replace('-', "_")
+        ));
+    }
     code.push_str("    ]\n}\n");
     std::fs::write(out_dir.join("plugin_includes.rs"), code).unwrap();
 }
 
+/// Offsets of the `PluginHeader` fields on the 32-bit plugin target
+const HEADER_NAME: usize = 8;
+const HEADER_CAPABILITIES: usize = 52;
+const HEADER_PERMISSIONS: usize = 64;
+const HEADER_MIN_HOST_VERSION: usize = 72;
+const HEADER_MAX_HOST_VERSION: usize = 76;
+const HEADER_REQUIREMENTS: usize = 80;
+const HEADER_ICON: usize = 84;
+
+/// Bytes of a `PluginIcon`: 16x16 RGB565 pixels
+const ICON_BYTES: usize = 16 * 16 * 2;
+
+/// Header fields of a plugin binary, for the registry
+struct HeaderInfo {
+    name: String,
+    api_version: u32,
+    min_host_version: u32,
+    max_host_version: u32,
+    capabilities: u32,
+    permissions: u32,
+    requirements: u32,
+    icon_offset: Option<usize>,
+}
+
+/// Read the header at the start of a plugin binary, `None` if it has none
+fn read_header(bytes: &[u8]) -> Option<HeaderInfo> {
+    let word = |offset: usize| {
+        let bytes = bytes.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    // PLUGIN_MAGIC, "PLUG"
+    if word(0)? != 0x504C5547 {
+        return None;
+    }
+    let api_version = word(4)?;
+
+    let name = bytes.get(HEADER_NAME..HEADER_NAME + 32)?;
+    let len = name.iter().position(|&byte| byte == 0).unwrap_or(31);
+    let icon_offset = Some(word(HEADER_ICON)? as usize)
+        .filter(|&offset| offset != 0 && offset + ICON_BYTES <= bytes.len());
+
+    Some(HeaderInfo {
+        name: String::from_utf8_lossy(&name[..len]).into_owned(),
+        api_version,
+        min_host_version: word(HEADER_MIN_HOST_VERSION)?,
+        max_host_version: word(HEADER_MAX_HOST_VERSION)?,
+        capabilities: word(HEADER_CAPABILITIES)?,
+        permissions: word(HEADER_PERMISSIONS)?,
+        requirements: word(HEADER_REQUIREMENTS)?,
+        icon_offset,
+    })
+}
+
 const DEFAULT_LINKER_SCRIPT: &str = r#"
 MEMORY {
     PLUGIN : ORIGIN = 0x00000000, LENGTH = 64K
@@ -0,0 +1,82 @@
+//! Cluster data of the firmware's layout, for the plugins' `DataContext`
+
+use crate::PluginRuntime;
+use cluster_core::models::{Cluster, Layout};
+use cluster_core::types::{Attribute, Kind, Status};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::rwlock::RwLock;
+use plugin_api::InputState;
+use plugin_api::data::*;
+
+impl PluginRuntime {
+    /// Share the layout the firmware tasks keep up to date with plugins
+    ///
+    /// [`update`](Self::update) holds a read lock on it for the frame, so
+    /// every call of the frame sees the same data. While a task writes it,
+    /// plugins see no seats for that frame rather than the update waiting.
+    pub fn set_layout(&mut self, layout: &'static RwLock<CriticalSectionRawMutex, Layout>) {
+        self.layout = Some(layout);
+    }
+
+    /// Run one frame with `layout` as the cluster data, e.g. when the caller
+    /// already holds it
+    pub fn update_with_layout(&mut self, input: impl Into<InputState>, layout: Option<&Layout>) {
+        self.frame_layout = layout.map(|layout| layout as *const Layout);
+        self.run_frame(input.into());
+        self.frame_layout = None;
+    }
+
+    /// Check whether plugins get cluster data, for `PLUGIN_REQ_NETWORK`
+    pub(crate) fn has_layout(&self) -> bool {
+        self.layout.is_some()
+    }
+
+    fn cluster(&self, cluster: u32) -> Option<&Cluster> {
+        // SAFETY: only set for the duration of `update_with_layout`, which
+        // borrows the layout
+        let layout = unsafe { self.frame_layout?.as_ref()? };
+        let id = *Layout::FLOORS.get(cluster as usize)?;
+        layout.get(id)
+    }
+
+    pub(crate) fn cluster_seat_count(&self, cluster: u32) -> u32 {
+        self.cluster(cluster)
+            .map_or(0, |cluster| cluster.seats.len() as u32)
+    }
+
+    pub(crate) fn cluster_seat(&self, cluster: u32, index: u32) -> Option<SeatInfo> {
+        let seat = self.cluster(cluster)?.seats.get(index as usize)?;
+        Some(SeatInfo {
+            x: u16::try_from(seat.x).unwrap_or(u16::MAX),
+            y: u16::try_from(seat.y).unwrap_or(u16::MAX),
+            status: match seat.status {
+                Status::Free => SEAT_STATUS_FREE,
+                Status::Taken => SEAT_STATUS_TAKEN,
+                Status::Reported => SEAT_STATUS_REPORTED,
+                Status::Broken => SEAT_STATUS_BROKEN,
+            },
+            kind: match seat.kind {
+                Kind::Mac => SEAT_KIND_MAC,
+                Kind::Lenovo => SEAT_KIND_LENOVO,
+                Kind::Dell => SEAT_KIND_DELL,
+                Kind::Flex => SEAT_KIND_FLEX,
+            },
+        })
+    }
+
+    pub(crate) fn cluster_attr_mask(&self, cluster: u32) -> u32 {
+        let Some(cluster) = self.cluster(cluster) else {
+            return 0;
+        };
+        cluster
+            .attributes
+            .iter()
+            .fold(0, |mask, attribute| match attribute {
+                Attribute::Piscine => mask | CLUSTER_ATTR_PISCINE,
+                Attribute::Exam => mask | CLUSTER_ATTR_EXAM,
+                Attribute::Silent => mask | CLUSTER_ATTR_SILENT,
+                Attribute::Event => mask | CLUSTER_ATTR_EVENT,
+                Attribute::Closed => mask | CLUSTER_ATTR_CLOSED,
+            })
+    }
+}
@@ -1,48 +1,273 @@
 #![no_std]
 
-use core::mem::size_of;
-use core::ptr::{addr_of, addr_of_mut};
+use core::mem::{offset_of, size_of};
+use core::ptr::addr_of_mut;
+use plugin_api::assets::{ASSET_NOT_FOUND, AssetTable};
+use plugin_api::data::{DATA_NOT_FOUND, DataContext, SeatInfo};
+use plugin_api::permissions::{PLUGIN_ERR_DENIED, PLUGIN_PERM_SOUND, PLUGIN_PERM_STORAGE};
+use plugin_api::storage::{self, STORAGE_SIZE, Storage};
 use plugin_api::*;
+use plugin_api::{font, raster};
 use static_cell::StaticCell;
 
 include!(concat!(env!("OUT_DIR"), "/plugin_includes.rs"));
 
+pub use registry::PluginInfo;
+
+#[cfg(feature = "cluster-data")]
+mod data;
+#[cfg(feature = "usb-loader")]
+pub mod loader;
+mod registry;
+#[cfg(feature = "renderer")]
+mod view;
+
 static PLUGIN_RUNTIME: StaticCell<PluginRuntime> = StaticCell::new();
 
-// 64KB RAM buffer for plugin code (must be 4-byte aligned for ARM execution)
+const MAIN_LOAD_BUFFER_SIZE: usize = 65536;
+// Picture-in-picture plugins are small widgets (clock, status), code + .bss must fit
+const PIP_LOAD_BUFFER_SIZE: usize = 16384;
+
+// RAM buffers for plugin code (must be 4-byte aligned for ARM execution)
 #[repr(align(4))]
-struct AlignedBuffer([u8; 65536]);
+struct AlignedBuffer<const N: usize>([u8; N]);
+
+#[unsafe(link_section = ".bss")]
+static mut PLUGIN_LOAD_BUFFER: AlignedBuffer<MAIN_LOAD_BUFFER_SIZE> =
+    AlignedBuffer([0; MAIN_LOAD_BUFFER_SIZE]);
 
 #[unsafe(link_section = ".bss")]
-static mut PLUGIN_LOAD_BUFFER: AlignedBuffer = AlignedBuffer([0; 65536]);
+static mut PIP_LOAD_BUFFER: AlignedBuffer<PIP_LOAD_BUFFER_SIZE> =
+    AlignedBuffer([0; PIP_LOAD_BUFFER_SIZE]);
+
+/// Plugin instance a call applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Slot {
+    /// Full-screen plugin
+    Main,
+    /// Picture-in-picture plugin drawn over a corner of the main one
+    Pip,
+}
+
+/// Screen area given to the picture-in-picture plugin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Viewport of the given size in the bottom-right corner of the display
+    pub const fn bottom_right(width: u32, height: u32) -> Self {
+        Self::new(
+            (DISPLAY_WIDTH as u32).saturating_sub(width),
+            (DISPLAY_HEIGHT as u32).saturating_sub(height),
+            width,
+            height,
+        )
+    }
+
+    /// Check that the viewport is non-empty and fully on screen
+    pub const fn is_valid(&self) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x.saturating_add(self.width) <= DISPLAY_WIDTH as u32
+            && self.y.saturating_add(self.height) <= DISPLAY_HEIGHT as u32
+    }
+}
+
+/// Tone requested by a plugin through `tone_fn`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tone {
+    /// Frequency, 0 to stop the tone playing
+    pub freq_hz: u32,
+    pub duration_ms: u32,
+}
+
+/// Plugin the host stopped for going over its update budget
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PluginFault {
+    pub slot: Slot,
+    /// Name from the plugin's header
+    pub name: &'static str,
+    /// Time its last update took, in microseconds
+    pub elapsed_us: u64,
+}
+
+/// Hooks arming a hardware timer around each plugin update
+///
+/// The host's clock only catches updates that return late. An update that
+/// never returns is left to the firmware: `arm` starts a timer, e.g. the
+/// watchdog, that stops the plugin if `disarm` isn't called within the
+/// budget.
+#[derive(Clone, Copy)]
+pub struct UpdateWatchdog {
+    /// Called before the update of the plugin in the slot, with the budget
+    /// in microseconds
+    pub arm: fn(Slot, u32),
+    /// Called once the update returned
+    pub disarm: fn(),
+}
+
+/// Length of the code segment of a plugin binary, the whole binary unless
+/// the packager appended assets
+///
+/// A binary that isn't a valid plugin is left whole, for the loader to
+/// report what is wrong with it.
+fn code_len(plugin_bytes: &[u8]) -> usize {
+    let word = |offset: usize| {
+        let bytes = plugin_bytes.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let declared = || {
+        if word(0)? != PLUGIN_MAGIC || word(4)? != PLUGIN_API_VERSION {
+            return None;
+        }
+        Some(word(offset_of!(PluginHeader, code_size))? as usize)
+    };
+    match declared() {
+        Some(len) if len >= size_of::<PluginHeader>() && len <= plugin_bytes.len() => len,
+        _ => plugin_bytes.len(),
+    }
+}
+
+/// `PLUGIN_REQ_*` flags this host can meet, storage if it is also granted
+/// and network data if the firmware shares its layout
+pub const HOST_REQUIREMENTS: u32 = PLUGIN_REQ_STORAGE | PLUGIN_REQ_NETWORK;
+
+/// Permissions a plugin binary asks for, e.g. for a menu to show before
+/// loading it
+///
+/// `None` if `plugin_bytes` is not a plugin of this API version.
+pub fn requested_permissions(plugin_bytes: &[u8]) -> Option<u32> {
+    let word = |offset: usize| {
+        let bytes = plugin_bytes.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if word(0)? != PLUGIN_MAGIC || word(4)? != PLUGIN_API_VERSION {
+        return None;
+    }
+    word(offset_of!(PluginHeader, permissions))
+}
 
 struct LoadedPlugin {
-    header: &'static PluginHeader,
-    #[allow(dead_code)]
+    /// Header with the entry points relocated to the load buffer
+    header: PluginHeader,
+}
+
+/// Framebuffer view, API table and loaded plugin for one slot
+///
+/// The framebuffer always has the full 128-pixel stride; its `width` and
+/// `height` are the size of the view, and drawing calls are clipped to them.
+struct PluginSlot {
+    framebuffer: FrameBuffer,
+    api: PluginAPI,
+    plugin: Option<LoadedPlugin>,
+    /// Name of the plugin loaded last, the namespace of its storage keys
+    ///
+    /// Kept after unloading, for `cleanup` to save its state.
     name: &'static str,
+    /// `PLUGIN_PERM_*` flags requested by the plugin loaded last
+    permissions: u32,
+    /// Updates are suspended, see [`PluginRuntime::pause`]
+    paused: bool,
+    /// Asset segments of the plugin loaded last, read in place
+    assets: Option<AssetTable<'static>>,
+}
+
+impl PluginSlot {
+    const fn new(width: u32, height: u32) -> Self {
+        Self {
+            framebuffer: FrameBuffer {
+                pixels: [0; FRAMEBUFFER_SIZE],
+                width,
+                height,
+                frame_counter: 0,
+            },
+            api: PluginAPI {
+                framebuffer: core::ptr::null_mut(),
+                gfx: core::ptr::null(),
+                sys: core::ptr::null(),
+                data: core::ptr::null(),
+            },
+            plugin: None,
+            name: "",
+            permissions: 0,
+            paused: false,
+            assets: None,
+        }
+    }
 }
 
 pub struct PluginRuntime {
-    framebuffer: FrameBuffer,
+    main: PluginSlot,
+    pip: PluginSlot,
+    pip_viewport: Viewport,
     graphics_ctx: GraphicsContext,
     system_ctx: SystemContext,
-    api: PluginAPI,
-    current_plugin: Option<LoadedPlugin>,
+    data_ctx: DataContext,
+    /// Slot whose framebuffer the drawing callbacks target
+    active: Slot,
+    /// Slot that receives inputs
+    input_focus: Slot,
+    /// Key-value store of the storage callbacks
+    storage: Storage,
+    /// Last tone requested, until the firmware takes it
+    tone: Option<Tone>,
+    /// Hardware reported by `display_info_fn`, see [`PluginRuntime::set_display_info`]
+    display_info: DisplayInfo,
+    /// `PLUGIN_PERM_*` flags the device policy grants
+    granted: u32,
+    /// Microsecond clock timing plugin updates, see [`PluginRuntime::set_clock`]
+    clock: Option<fn() -> u64>,
+    watchdog: Option<UpdateWatchdog>,
+    update_budget_us: u32,
+    /// Plugin stopped last, until the firmware takes it
+    fault: Option<PluginFault>,
+    /// Layout shared by the firmware, see [`PluginRuntime::set_layout`]
+    #[cfg(feature = "cluster-data")]
+    layout: Option<
+        &'static embassy_sync::rwlock::RwLock<
+            embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+            cluster_core::models::Layout,
+        >,
+    >,
+    /// Layout the data callbacks read during a frame
+    #[cfg(feature = "cluster-data")]
+    frame_layout: Option<*const cluster_core::models::Layout>,
 }
 
 // Global pointer for callbacks
 static mut RUNTIME_PTR: Option<*mut PluginRuntime> = None;
 
+/// Seed of `sys_random` until [`PluginRuntime::set_seed`]
+pub const DEFAULT_SEED: u32 = 0xDEADBEEF;
+
+/// State of the `sys_random` generator
+static mut RNG_STATE: u32 = DEFAULT_SEED;
+
 impl PluginRuntime {
     /// Initialize the global plugin runtime
     pub fn init() -> &'static mut Self {
         let runtime = PLUGIN_RUNTIME.init(Self {
-            framebuffer: FrameBuffer {
-                pixels: [0; FRAMEBUFFER_SIZE],
-                width: DISPLAY_WIDTH as u32,
-                height: DISPLAY_HEIGHT as u32,
-                frame_counter: 0,
-            },
+            main: PluginSlot::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
+            pip: PluginSlot::new(0, 0),
+            pip_viewport: Viewport::new(0, 0, 0, 0),
             graphics_ctx: GraphicsContext {
                 set_pixel_fn: gfx_set_pixel,
                 get_pixel_fn: gfx_get_pixel,
@@ -51,6 +276,8 @@ impl PluginRuntime {
                 draw_line_fn: gfx_draw_line,
                 draw_circle_fn: gfx_draw_circle,
                 blit_fn: gfx_blit,
+                draw_text_fn: gfx_draw_text,
+                text_width_fn: gfx_text_width,
             },
             system_ctx: SystemContext {
                 random_fn: sys_random,
@@ -64,18 +291,42 @@ impl PluginRuntime {
                 color_yellow: 0xFFE0,
                 color_cyan: 0x07FF,
                 color_magenta: 0xF81F,
+                // Plugin time is the frame counter, random() a seeded LCG
+                deterministic: true,
+                seed: DEFAULT_SEED,
+                storage_read_fn: sys_storage_read,
+                storage_write_fn: sys_storage_write,
+                tone_fn: sys_tone,
+                display_info_fn: sys_display_info,
+                read_asset_fn: sys_read_asset,
             },
-            api: PluginAPI {
-                framebuffer: core::ptr::null_mut(),
-                gfx: core::ptr::null(),
-                sys: core::ptr::null(),
+            data_ctx: DataContext {
+                get_cluster_seat_count_fn: data_cluster_seat_count,
+                get_seat_fn: data_seat,
+                get_cluster_attr_mask_fn: data_cluster_attr_mask,
             },
-            current_plugin: None,
+            active: Slot::Main,
+            input_focus: Slot::Main,
+            storage: Storage::new(),
+            tone: None,
+            display_info: DisplayInfo::UNKNOWN,
+            granted: 0,
+            clock: None,
+            watchdog: None,
+            update_budget_us: UPDATE_BUDGET_US,
+            fault: None,
+            #[cfg(feature = "cluster-data")]
+            layout: None,
+            #[cfg(feature = "cluster-data")]
+            frame_layout: None,
         });
 
-        runtime.api.framebuffer = &mut runtime.framebuffer as *mut _;
-        runtime.api.gfx = &runtime.graphics_ctx as *const _;
-        runtime.api.sys = &runtime.system_ctx as *const _;
+        for slot in [&mut runtime.main, &mut runtime.pip] {
+            slot.api.framebuffer = &mut slot.framebuffer as *mut _;
+            slot.api.gfx = &runtime.graphics_ctx as *const _;
+            slot.api.sys = &runtime.system_ctx as *const _;
+            slot.api.data = &runtime.data_ctx as *const _;
+        }
 
         unsafe {
             RUNTIME_PTR = Some(runtime as *mut _);
@@ -84,29 +335,213 @@ impl PluginRuntime {
         runtime
     }
 
-    pub fn load_plugin(&mut self, plugin_bytes: &'static [u8]) -> Result<(), &'static str> {
+    /// Restart the sequence of `random()` from `seed`
+    ///
+    /// With `millis()` derived from the frame counter, a plugin loaded after
+    /// this gives the same frames for the same inputs on every run.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.system_ctx.seed = seed;
+        unsafe {
+            RNG_STATE = seed;
+        }
+    }
+
+    /// Set the `PLUGIN_PERM_*` flags the device grants to plugins
+    ///
+    /// Nothing is granted until the firmware sets its policy. A plugin may
+    /// use a gated function if it requested the permission and the policy
+    /// grants it; other calls return `PLUGIN_ERR_DENIED`.
+    pub fn set_granted_permissions(&mut self, granted: u32) {
+        self.granted = granted;
+    }
+
+    pub fn granted_permissions(&self) -> u32 {
+        self.granted
+    }
+
+    /// Permissions requested by the plugin in `slot`, if one is loaded
+    pub fn plugin_permissions(&self, slot: Slot) -> Option<u32> {
+        let slot = self.slot(slot);
+        slot.plugin.as_ref().map(|_| slot.permissions)
+    }
+
+    /// Restore the plugins' storage from the image last persisted
+    ///
+    /// Returns `false` if `image` holds no storage, e.g. erased flash, in
+    /// which case the storage starts empty.
+    pub fn load_storage(&mut self, image: &[u8]) -> bool {
+        self.storage.load(image)
+    }
+
+    /// Storage image to persist, if plugins changed it since the last call
+    ///
+    /// The host has no flash access: the firmware polls this and writes the
+    /// image to its storage sector, so it decides how often flash is erased.
+    pub fn storage_to_persist(&mut self) -> Option<&[u8; STORAGE_SIZE]> {
+        self.storage.take_dirty().then(|| self.storage.image())
+    }
+
+    /// Tone the plugins asked for since the last call
+    ///
+    /// Like storage, sound needs hardware the host doesn't drive: the
+    /// firmware takes the tone after each update and plays it on its
+    /// speaker, if it has one. Only the last request of a frame is kept.
+    pub fn take_tone(&mut self) -> Option<Tone> {
+        self.tone.take()
+    }
+
+    /// Describe the panels the firmware drives, for plugins to adapt their
+    /// effects
+    ///
+    /// Until then plugins get [`DisplayInfo::UNKNOWN`].
+    pub fn set_display_info(&mut self, info: DisplayInfo) {
+        self.display_info = info;
+    }
+
+    pub fn display_info(&self) -> DisplayInfo {
+        self.display_info
+    }
+
+    /// Time plugin updates with `now_us`, a clock in microseconds
+    ///
+    /// A plugin whose update takes longer than the budget is unloaded
+    /// without its `cleanup`, and reported by [`take_fault`](Self::take_fault).
+    /// Without a clock updates are not timed.
+    pub fn set_clock(&mut self, now_us: fn() -> u64) {
+        self.clock = Some(now_us);
+    }
+
+    /// Arm a hardware timer around each plugin update, for updates that
+    /// never return
+    pub fn set_watchdog(&mut self, watchdog: UpdateWatchdog) {
+        self.watchdog = Some(watchdog);
+    }
+
+    /// Set the time a plugin update may take, [`UPDATE_BUDGET_US`] by default
+    pub fn set_update_budget(&mut self, budget_us: u32) {
+        self.update_budget_us = budget_us;
+    }
+
+    pub fn update_budget(&self) -> u32 {
+        self.update_budget_us
+    }
+
+    /// Plugin stopped for going over its budget since the last call
+    ///
+    /// The firmware polls this after each update, to tell the user with
+    /// [`show_fault`](Self::show_fault) rather than leave the last frame of
+    /// a stopped plugin on screen.
+    pub fn take_fault(&mut self) -> Option<PluginFault> {
+        self.fault.take()
+    }
+
+    /// Draw an error screen naming the stopped plugin `name`
+    ///
+    /// Also meant for plugins the firmware stopped itself, e.g. after a
+    /// watchdog reset. The screen stays until a plugin draws over it.
+    pub fn show_fault(&mut self, name: &str) {
+        let framebuffer = &mut self.main.framebuffer;
+        framebuffer.fill(self.system_ctx.color_black);
+        let lines: [(&[u8], u16); 3] = [
+            (b"PLUGIN STOPPED", self.system_ctx.color_red),
+            (name.as_bytes(), self.system_ctx.color_white),
+            (b"TOO SLOW", self.system_ctx.color_white),
+        ];
+        let top = (DISPLAY_HEIGHT as i32 - 3 * 2 * font::GLYPH_HEIGHT) / 2;
+        for (i, (text, color)) in lines.into_iter().enumerate() {
+            let x = (DISPLAY_WIDTH as i32 - font::text_width(text)) / 2;
+            let y = top + i as i32 * 2 * font::GLYPH_HEIGHT;
+            framebuffer.draw_text(x.max(0), y, text, color);
+        }
+    }
+
+    /// Load the full-screen plugin
+    ///
+    /// The binary is copied, so it may be a buffer reused afterwards. That
+    /// rules out segmented binaries, whose assets are read from the binary:
+    /// load those with [`load_flash_plugin`](Self::load_flash_plugin).
+    pub fn load_plugin(&mut self, plugin_bytes: &[u8]) -> Result<(), &'static str> {
+        self.load_into(Slot::Main, plugin_bytes, None)
+    }
+
+    /// Load the full-screen plugin from a binary that stays in place, e.g.
+    /// in flash
+    ///
+    /// Only the code segment of a segmented binary is copied to RAM: its
+    /// assets are read from `plugin_bytes` when the plugin asks for them, so
+    /// the binary may be larger than the load buffer.
+    pub fn load_flash_plugin(&mut self, plugin_bytes: &'static [u8]) -> Result<(), &'static str> {
+        self.load_into(Slot::Main, plugin_bytes, Some(plugin_bytes))
+    }
+
+    /// Load a second plugin that renders into `viewport` over the main one
+    ///
+    /// The plugin sees a framebuffer of the viewport's size with its origin
+    /// at the top-left corner of the viewport. It gets no input until
+    /// [`set_input_focus`](Self::set_input_focus) routes input to it.
+    pub fn load_pip_plugin(
+        &mut self,
+        plugin_bytes: &[u8],
+        viewport: Viewport,
+    ) -> Result<(), &'static str> {
+        if !viewport.is_valid() {
+            return Err("PiP viewport outside display");
+        }
+
+        self.unload_pip_plugin();
+        self.pip_viewport = viewport;
+        self.pip.framebuffer.width = viewport.width;
+        self.pip.framebuffer.height = viewport.height;
+        self.pip.framebuffer.frame_counter = 0;
+        self.load_into(Slot::Pip, plugin_bytes, None)
+    }
+
+    /// Load `plugin_bytes` into `slot`, reading assets from `flash`, the
+    /// same binary left in place
+    fn load_into(
+        &mut self,
+        slot: Slot,
+        plugin_bytes: &[u8],
+        flash: Option<&'static [u8]>,
+    ) -> Result<(), &'static str> {
         if plugin_bytes.len() < size_of::<PluginHeader>() {
             return Err("Plugin binary too small");
         }
 
-        const BUFFER_SIZE: usize = 65536;
-        if plugin_bytes.len() > BUFFER_SIZE {
+        // SAFETY: only a pointer is taken, the buffers are written below
+        let (buffer_ptr, buffer_size) = unsafe {
+            match slot {
+                Slot::Main => (
+                    addr_of_mut!(PLUGIN_LOAD_BUFFER.0).cast::<u8>(),
+                    MAIN_LOAD_BUFFER_SIZE,
+                ),
+                Slot::Pip => (
+                    addr_of_mut!(PIP_LOAD_BUFFER.0).cast::<u8>(),
+                    PIP_LOAD_BUFFER_SIZE,
+                ),
+            }
+        };
+
+        // Only the code segment of a segmented binary is loaded, its assets
+        // are read where they are
+        let (plugin_bytes, assets) = plugin_bytes.split_at(code_len(plugin_bytes));
+        let assets = match (assets.is_empty(), flash) {
+            (true, _) => None,
+            (false, None) => return Err("Plugin assets need a binary in flash"),
+            (false, Some(flash)) => Some(
+                AssetTable::parse(&flash[plugin_bytes.len()..])
+                    .ok_or("Invalid plugin asset table")?,
+            ),
+        };
+        if plugin_bytes.len() > buffer_size {
             return Err("Plugin too large for load buffer");
         }
 
         // Copy from flash to RAM and relocate (plugins are linked at 0x00000000)
         unsafe {
-            let buffer_ptr = addr_of_mut!(PLUGIN_LOAD_BUFFER.0).cast::<u8>();
-
             core::ptr::copy_nonoverlapping(plugin_bytes.as_ptr(), buffer_ptr, plugin_bytes.len());
 
-            // Zero remaining buffer space for .bss section (uninitialized data)
-            // This ensures all static/global variables are properly zeroed regardless of actual BSS size
-            let bss_start = plugin_bytes.len();
-            let remaining_size = BUFFER_SIZE - bss_start;
-            core::ptr::write_bytes(buffer_ptr.add(bss_start), 0, remaining_size);
-
-            let header = &*(addr_of!(PLUGIN_LOAD_BUFFER.0).cast::<PluginHeader>());
+            let header = *(buffer_ptr.cast_const().cast::<PluginHeader>());
 
             if header.magic != PLUGIN_MAGIC {
                 return Err("Invalid plugin magic number");
@@ -116,8 +551,22 @@ impl PluginRuntime {
                 return Err("Plugin API version mismatch");
             }
 
+            self.check_manifest(&header)?;
+
+            // Zero the .bss the plugin declared, or all the remaining buffer
+            // space if it did not, so static variables start zeroed
+            let bss_start = plugin_bytes.len();
+            let bss_size = match header.bss_size {
+                0 => buffer_size - bss_start,
+                declared => declared as usize,
+            };
+            if bss_size > buffer_size - bss_start {
+                return Err("Plugin .bss too large for load buffer");
+            }
+            core::ptr::write_bytes(buffer_ptr.add(bss_start), 0, bss_size);
+
             // Relocate function pointers from 0x00000000 to buffer address
-            let base_addr = addr_of!(PLUGIN_LOAD_BUFFER.0).cast::<u8>() as usize;
+            let base_addr = buffer_ptr as usize;
 
             // ARM Thumb bit (bit 0) must be preserved during relocation
             let init_offset = header.init as usize;
@@ -145,6 +594,22 @@ impl PluginRuntime {
                 );
             }
 
+            let relocate_optional = |entry: Option<unsafe extern "C" fn()>| {
+                entry.map(|entry| {
+                    core::mem::transmute::<usize, unsafe extern "C" fn()>(
+                        base_addr + entry as usize,
+                    )
+                })
+            };
+
+            // The icon is only read by menus, from the binary, but keep the
+            // header coherent if it points inside the plugin
+            let icon = header.icon.and_then(|icon| {
+                let offset = icon as *const PluginIcon as usize;
+                (offset + size_of::<PluginIcon>() <= plugin_bytes.len())
+                    .then(|| &*((base_addr + offset) as *const PluginIcon))
+            });
+
             let relocated_header = PluginHeader {
                 magic: header.magic,
                 api_version: header.api_version,
@@ -152,19 +617,25 @@ impl PluginRuntime {
                 init: core::mem::transmute::<usize, unsafe extern "C" fn(*const PluginAPI) -> i32>(
                     base_addr + init_offset,
                 ),
-                update: core::mem::transmute::<usize, unsafe extern "C" fn(*const PluginAPI, u32)>(
-                    base_addr + update_offset,
-                ),
+                update: core::mem::transmute::<
+                    usize,
+                    unsafe extern "C" fn(*const PluginAPI, *const InputState),
+                >(base_addr + update_offset),
                 cleanup: core::mem::transmute::<usize, unsafe extern "C" fn()>(
                     base_addr + cleanup_offset,
                 ),
+                capabilities: header.capabilities,
+                pause: relocate_optional(header.pause),
+                resume: relocate_optional(header.resume),
+                permissions: header.permissions,
+                bss_size: header.bss_size,
+                min_host_version: header.min_host_version,
+                max_host_version: header.max_host_version,
+                requirements: header.requirements,
+                icon,
+                code_size: header.code_size,
             };
 
-            core::ptr::write(
-                addr_of_mut!(PLUGIN_LOAD_BUFFER.0).cast::<PluginHeader>(),
-                relocated_header,
-            );
-
             // Sync caches for executable code
             #[cfg(target_arch = "arm")]
             {
@@ -172,12 +643,35 @@ impl PluginRuntime {
                 core::arch::asm!("isb");
             }
 
-            let final_header = &*(addr_of!(PLUGIN_LOAD_BUFFER.0).cast::<PluginHeader>());
+            #[cfg(feature = "defmt")]
+            defmt::debug!(
+                "Calling plugin init at {:#x}",
+                relocated_header.init as usize
+            );
 
             #[cfg(feature = "defmt")]
-            defmt::debug!("Calling plugin init at {:#x}", final_header.init as usize);
+            defmt::debug!(
+                "Plugin permissions: requested {:#x}, granted {:#x}",
+                header.permissions,
+                header.permissions & self.granted
+            );
 
-            let result = (final_header.init)(&self.api as *const _);
+            // The name lives in the load buffer, which stays in place until
+            // the next load into this slot. It and the permissions are needed
+            // from `init` on, for the plugin to read its storage.
+            self.slot_mut(slot).permissions = header.permissions;
+            self.slot_mut(slot).assets = assets;
+            let name_bytes = &(*buffer_ptr.cast_const().cast::<PluginHeader>()).name;
+            self.slot_mut(slot).name = {
+                let mut len = 0;
+                while len < 32 && name_bytes[len] != 0 {
+                    len += 1;
+                }
+                core::str::from_utf8(&name_bytes[..len]).unwrap_or("invalid string")
+            };
+
+            self.active = slot;
+            let result = (relocated_header.init)(&self.slot(slot).api as *const _);
 
             #[cfg(feature = "defmt")]
             defmt::debug!("Plugin init returned: {}", result);
@@ -186,115 +680,317 @@ impl PluginRuntime {
                 return Err("Plugin initialization failed");
             }
 
-            let name = {
-                let mut len = 0;
-                while len < 32 && final_header.name[len] != 0 {
-                    len += 1;
-                }
-                core::str::from_utf8(&final_header.name[..len]).unwrap_or("invalid string")
-            };
-
-            self.current_plugin = Some(LoadedPlugin {
-                header: final_header,
-                name,
+            let loaded = self.slot_mut(slot);
+            loaded.plugin = Some(LoadedPlugin {
+                header: relocated_header,
             });
+            loaded.paused = false;
         }
 
         Ok(())
     }
 
-    pub fn update(&mut self, inputs: u32) {
-        if let Some(plugin) = &self.current_plugin {
-            unsafe {
-                (plugin.header.update)(&self.api as *const _, inputs);
-            }
-            self.framebuffer.frame_counter = self.framebuffer.frame_counter.wrapping_add(1);
+    /// Run one frame of the loaded plugins
+    ///
+    /// The main plugin runs first, then the picture-in-picture plugin, whose
+    /// view is copied over the main framebuffer. `input` goes to the slot
+    /// that has the input focus; the other slot sees no buttons pressed and
+    /// the stick centered. A bare `u32` is the button bitmask.
+    pub fn update(&mut self, input: impl Into<InputState>) {
+        let input = input.into();
+        #[cfg(feature = "cluster-data")]
+        if let Some(lock) = self.layout {
+            let layout = lock.try_read().ok();
+            self.update_with_layout(input, layout.as_deref());
+            return;
+        }
+        self.run_frame(input);
+    }
+
+    fn run_frame(&mut self, input: InputState) {
+        let idle = InputState::default();
+        let (main_input, pip_input) = match self.input_focus {
+            Slot::Main => (input, idle),
+            Slot::Pip => (idle, input),
+        };
+
+        self.update_slot(Slot::Main, main_input);
+        if self.pip.plugin.is_some() {
+            self.update_slot(Slot::Pip, pip_input);
+            self.composite_pip();
+        }
+        self.active = Slot::Main;
+    }
+
+    fn update_slot(&mut self, slot: Slot, input: InputState) {
+        self.active = slot;
+        let (clock, watchdog, budget) = (self.clock, self.watchdog, self.update_budget_us);
+        let plugin_slot = self.slot_mut(slot);
+        if plugin_slot.paused {
+            return;
+        }
+        let Some(plugin) = &plugin_slot.plugin else {
+            return;
+        };
+
+        if let Some(watchdog) = watchdog {
+            (watchdog.arm)(slot, budget);
+        }
+        let start = clock.map(|now| now());
+        let api = &plugin_slot.api as *const PluginAPI;
+        unsafe {
+            (plugin.header.update)(api, &input);
+        }
+        let elapsed_us = clock
+            .zip(start)
+            .map(|(now, start)| now().saturating_sub(start));
+        if let Some(watchdog) = watchdog {
+            (watchdog.disarm)();
+        }
+        plugin_slot.framebuffer.frame_counter =
+            plugin_slot.framebuffer.frame_counter.wrapping_add(1);
+
+        if let Some(elapsed_us) = elapsed_us.filter(|&elapsed| elapsed > budget as u64) {
+            self.stop_faulted(slot, elapsed_us);
+        }
+    }
+
+    /// Unload the plugin in `slot` after it went over its budget
+    ///
+    /// Its `cleanup` isn't called, as it may be as slow as its update.
+    fn stop_faulted(&mut self, slot: Slot, elapsed_us: u64) {
+        let plugin_slot = self.slot_mut(slot);
+        plugin_slot.plugin = None;
+        let fault = PluginFault {
+            slot,
+            name: plugin_slot.name,
+            elapsed_us,
+        };
+        #[cfg(feature = "defmt")]
+        defmt::warn!(
+            "Stopped plugin {}: update took {}us",
+            fault.name,
+            elapsed_us
+        );
+        self.fault = Some(fault);
+        if slot == Slot::Pip && self.input_focus == Slot::Pip {
+            self.input_focus = Slot::Main;
         }
     }
 
+    /// Stop updating the plugin in `slot`, e.g. while the menu covers it
+    ///
+    /// Its framebuffer keeps the last frame and its frame counter, which
+    /// `millis()` is derived from for the main slot, stops. Plugins with
+    /// the `PLUGIN_CAP_PAUSE` capability are told through their `pause`
+    /// entry point.
+    pub fn pause(&mut self, slot: Slot) {
+        let Some(entry) = self.pause_entry(slot, true) else {
+            return;
+        };
+        self.active = slot;
+        unsafe {
+            entry();
+        }
+        self.active = Slot::Main;
+    }
+
+    /// Start updating the plugin in `slot` again after [`pause`](Self::pause)
+    pub fn resume(&mut self, slot: Slot) {
+        let Some(entry) = self.pause_entry(slot, false) else {
+            return;
+        };
+        self.active = slot;
+        unsafe {
+            entry();
+        }
+        self.active = Slot::Main;
+    }
+
+    pub fn is_paused(&self, slot: Slot) -> bool {
+        self.slot(slot).paused
+    }
+
+    /// Switch the paused state of `slot`, returning the plugin's entry point
+    /// to call if it has one and the state changed
+    fn pause_entry(&mut self, slot: Slot, paused: bool) -> Option<unsafe extern "C" fn()> {
+        let slot = self.slot_mut(slot);
+        let plugin = slot.plugin.as_ref()?;
+        if slot.paused == paused {
+            return None;
+        }
+        slot.paused = paused;
+        if paused {
+            plugin.header.pause_fn()
+        } else {
+            plugin.header.resume_fn()
+        }
+    }
+
+    /// Copy the picture-in-picture view into the main framebuffer
+    fn composite_pip(&mut self) {
+        let viewport = self.pip_viewport;
+        let width = viewport.width as usize;
+        for row in 0..viewport.height as usize {
+            let src = row * DISPLAY_WIDTH;
+            let dst = (viewport.y as usize + row) * DISPLAY_WIDTH + viewport.x as usize;
+            self.main.framebuffer.pixels[dst..dst + width]
+                .copy_from_slice(&self.pip.framebuffer.pixels[src..src + width]);
+        }
+    }
+
+    /// Composited output of all loaded plugins
     pub fn framebuffer(&self) -> &FrameBuffer {
-        &self.framebuffer
+        &self.main.framebuffer
+    }
+
+    /// Route inputs to the given slot
+    pub fn set_input_focus(&mut self, slot: Slot) {
+        self.input_focus = slot;
+    }
+
+    pub fn input_focus(&self) -> Slot {
+        self.input_focus
+    }
+
+    /// Screen area of the picture-in-picture plugin, if one is loaded
+    pub fn pip_viewport(&self) -> Option<Viewport> {
+        self.pip.plugin.as_ref().map(|_| self.pip_viewport)
     }
 
     pub fn unload_plugin(&mut self) {
-        if let Some(plugin) = self.current_plugin.take() {
+        if let Some(plugin) = self.main.plugin.take() {
             unsafe {
                 (plugin.header.cleanup)();
             }
         }
     }
+
+    pub fn unload_pip_plugin(&mut self) {
+        if let Some(plugin) = self.pip.plugin.take() {
+            // Storage calls from `cleanup` go to the PiP plugin's keys
+            self.active = Slot::Pip;
+            unsafe {
+                (plugin.header.cleanup)();
+            }
+            self.active = Slot::Main;
+        }
+        if self.input_focus == Slot::Pip {
+            self.input_focus = Slot::Main;
+        }
+    }
+
+    fn slot(&self, slot: Slot) -> &PluginSlot {
+        match slot {
+            Slot::Main => &self.main,
+            Slot::Pip => &self.pip,
+        }
+    }
+
+    fn slot_mut(&mut self, slot: Slot) -> &mut PluginSlot {
+        match slot {
+            Slot::Main => &mut self.main,
+            Slot::Pip => &mut self.pip,
+        }
+    }
+
+    /// Check the manifest of a plugin header before calling its `init`
+    fn check_manifest(&self, header: &PluginHeader) -> Result<(), &'static str> {
+        if !header.supports_host(PLUGIN_API_VERSION) {
+            return Err("Plugin does not support this host API version");
+        }
+
+        let mut met = HOST_REQUIREMENTS;
+        if !self.has_layout() {
+            met &= !PLUGIN_REQ_NETWORK;
+        }
+        if header.permissions & self.granted & PLUGIN_PERM_STORAGE == 0 {
+            met &= !PLUGIN_REQ_STORAGE;
+        }
+        let missing = header.requirements & !met;
+        if missing & PLUGIN_REQ_STORAGE != 0 {
+            Err("Plugin needs storage, which is not granted")
+        } else if missing & PLUGIN_REQ_NETWORK != 0 {
+            Err("Plugin needs network data, which this host lacks")
+        } else if missing & PLUGIN_REQ_DOUBLE_RES != 0 {
+            Err("Plugin needs a double-resolution framebuffer, which this host lacks")
+        } else if missing != 0 {
+            Err("Plugin needs an unknown host feature")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that the plugin making a call may use `permission`
+    fn allowed(&self, permission: u32) -> bool {
+        let allowed = self.slot(self.active).permissions & self.granted & permission != 0;
+        if !allowed {
+            #[cfg(feature = "defmt")]
+            defmt::warn!(
+                "Plugin {} denied permission {:#x}",
+                self.slot(self.active).name,
+                permission
+            );
+        }
+        allowed
+    }
+
+    /// Framebuffer view the drawing callbacks currently target
+    #[cfg(not(feature = "cluster-data"))]
+    fn has_layout(&self) -> bool {
+        false
+    }
+
+    // Without a layout plugins see clusters without seats
+    #[cfg(not(feature = "cluster-data"))]
+    fn cluster_seat_count(&self, _cluster: u32) -> u32 {
+        0
+    }
+
+    #[cfg(not(feature = "cluster-data"))]
+    fn cluster_seat(&self, _cluster: u32, _index: u32) -> Option<SeatInfo> {
+        None
+    }
+
+    #[cfg(not(feature = "cluster-data"))]
+    fn cluster_attr_mask(&self, _cluster: u32) -> u32 {
+        0
+    }
+
+    fn target(&mut self) -> &mut FrameBuffer {
+        let active = self.active;
+        &mut self.slot_mut(active).framebuffer
+    }
 }
 
-// Graphics functions with bounds checking
+// Graphics functions, clipped to the active view (the whole display, or the
+// PiP viewport) by the panic-free primitives in `plugin_api::raster`
 fn set_pixel(runtime: &mut PluginRuntime, x: i32, y: i32, color: u16) {
-    if x >= 0 && x < DISPLAY_WIDTH as i32 && y >= 0 && y < DISPLAY_HEIGHT as i32 {
-        let idx = (y as usize) * DISPLAY_WIDTH + (x as usize);
-        runtime.framebuffer.pixels[idx] = color;
-    } else {
+    if !runtime.target().checked_set_pixel(x, y, color) {
         #[cfg(feature = "defmt")]
         defmt::trace!("set_pixel out of bounds: ({}, {})", x, y);
     }
 }
 
-fn get_pixel(runtime: &PluginRuntime, x: i32, y: i32) -> u16 {
-    if x >= 0 && x < DISPLAY_WIDTH as i32 && y >= 0 && y < DISPLAY_HEIGHT as i32 {
-        let idx = (y as usize) * DISPLAY_WIDTH + (x as usize);
-        runtime.framebuffer.pixels[idx]
-    } else {
+fn get_pixel(runtime: &mut PluginRuntime, x: i32, y: i32) -> u16 {
+    let pixel = runtime.target().checked_get_pixel(x, y);
+    if pixel.is_none() {
         #[cfg(feature = "defmt")]
         defmt::trace!("get_pixel out of bounds: ({}, {})", x, y);
-        0
     }
+    pixel.unwrap_or(0)
 }
 
 fn clear(runtime: &mut PluginRuntime, color: u16) {
-    runtime.framebuffer.pixels.fill(color);
+    runtime.target().pixels.fill(color);
 }
 
 fn fill_rect(runtime: &mut PluginRuntime, x: i32, y: i32, w: i32, h: i32, color: u16) {
-    let x_start = x.max(0) as usize;
-    let y_start = y.max(0) as usize;
-    let x_end = ((x + w).min(DISPLAY_WIDTH as i32) as usize).min(DISPLAY_WIDTH);
-    let y_end = ((y + h).min(DISPLAY_HEIGHT as i32) as usize).min(DISPLAY_HEIGHT);
-
-    if x_start >= x_end || y_start >= y_end {
-        return;
-    }
-
-    for py in y_start..y_end {
-        for px in x_start..x_end {
-            runtime.framebuffer.pixels[py * DISPLAY_WIDTH + px] = color;
-        }
-    }
+    runtime.target().fill_rect(x, y, w, h, color);
 }
 
 fn draw_line(runtime: &mut PluginRuntime, x0: i32, y0: i32, x1: i32, y1: i32, color: u16) {
-    let mut x = x0;
-    let mut y = y0;
-
-    let dx = (x1 - x0).abs();
-    let dy = (y1 - y0).abs();
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
-    let mut err = dx - dy;
-
-    loop {
-        set_pixel(runtime, x, y, color);
-
-        if x == x1 && y == y1 {
-            break;
-        }
-
-        let e2 = 2 * err;
-        if e2 > -dy {
-            err -= dy;
-            x += sx;
-        }
-        if e2 < dx {
-            err += dx;
-            y += sy;
-        }
-    }
+    runtime.target().draw_line(x0, y0, x1, y1, color);
 }
 
 fn draw_circle(runtime: &mut PluginRuntime, cx: i32, cy: i32, radius: i32, color: u16) {
@@ -303,30 +999,7 @@ fn draw_circle(runtime: &mut PluginRuntime, cx: i32, cy: i32, radius: i32, color
         defmt::warn!("draw_circle: negative radius {}", radius);
         return;
     }
-
-    let mut x = radius;
-    let mut y = 0;
-    let mut decision = 1 - radius;
-
-    while x >= y {
-        set_pixel(runtime, cx + x, cy + y, color);
-        set_pixel(runtime, cx - x, cy + y, color);
-        set_pixel(runtime, cx + x, cy - y, color);
-        set_pixel(runtime, cx - x, cy - y, color);
-        set_pixel(runtime, cx + y, cy + x, color);
-        set_pixel(runtime, cx - y, cy + x, color);
-        set_pixel(runtime, cx + y, cy - x, color);
-        set_pixel(runtime, cx - y, cy - x, color);
-
-        y += 1;
-
-        if decision <= 0 {
-            decision += 2 * y + 1;
-        } else {
-            x -= 1;
-            decision += 2 * (y - x) + 1;
-        }
-    }
+    runtime.target().draw_circle(cx, cy, radius, color);
 }
 
 fn blit(runtime: &mut PluginRuntime, x: i32, y: i32, w: i32, h: i32, data: *const u16) -> bool {
@@ -336,28 +1009,80 @@ fn blit(runtime: &mut PluginRuntime, x: i32, y: i32, w: i32, h: i32, data: *cons
         return false;
     }
 
-    if w <= 0 || h <= 0 || w > 1024 || h > 1024 {
+    if w <= 0 || h <= 0 || w > raster::MAX_BLIT_SIZE || h > raster::MAX_BLIT_SIZE {
         #[cfg(feature = "defmt")]
         defmt::warn!("blit: invalid dimensions {}x{}", w, h);
         return false;
     }
 
-    unsafe {
-        for dy in 0..h {
-            for dx in 0..w {
-                let px = x + dx;
-                let py = y + dy;
-
-                if px >= 0 && px < DISPLAY_WIDTH as i32 && py >= 0 && py < DISPLAY_HEIGHT as i32 {
-                    let src_idx = (dy * w + dx) as usize;
-                    let dst_idx = (py as usize) * DISPLAY_WIDTH + (px as usize);
-                    runtime.framebuffer.pixels[dst_idx] = *data.add(src_idx);
-                }
-            }
-        }
+    // SAFETY: the API requires `data` to hold `w * h` pixels, which the
+    // plugin-side `GraphicsContext::blit` checks
+    let data = unsafe { core::slice::from_raw_parts(data, (w * h) as usize) };
+    runtime.target().blit(x, y, w, h, data)
+}
+
+/// Text passed to the text callbacks, cut to `font::MAX_TEXT_LEN` bytes
+///
+/// # Safety
+///
+/// `text` must be null or point to `len` readable bytes.
+unsafe fn text_bytes<'a>(text: *const u8, len: u32) -> Option<&'a [u8]> {
+    if text.is_null() {
+        #[cfg(feature = "defmt")]
+        defmt::warn!("text: null pointer");
+        return None;
     }
+    let len = (len as usize).min(font::MAX_TEXT_LEN);
+    // SAFETY: guaranteed by the caller
+    Some(unsafe { core::slice::from_raw_parts(text, len) })
+}
 
-    true
+fn draw_text(runtime: &mut PluginRuntime, x: i32, y: i32, text: &[u8], color: u16) {
+    runtime.target().draw_text(x, y, text, color);
+}
+
+/// Bytes passed to the storage callbacks, `None` if more than `max`
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+unsafe fn storage_bytes<'a>(data: *const u8, len: u32, max: usize) -> Option<&'a [u8]> {
+    let len = len as usize;
+    if len > max {
+        return None;
+    }
+    if data.is_null() {
+        return (len == 0).then_some(&[]);
+    }
+    // SAFETY: guaranteed by the caller
+    Some(unsafe { core::slice::from_raw_parts(data, len) })
+}
+
+/// Storage keys are namespaced by the name of the calling plugin
+fn storage_read(runtime: &mut PluginRuntime, key: &[u8], buf: &mut [u8]) -> i32 {
+    if !runtime.allowed(PLUGIN_PERM_STORAGE) {
+        return PLUGIN_ERR_DENIED;
+    }
+    let namespace = runtime.slot(runtime.active).name.as_bytes();
+    runtime
+        .storage
+        .read(namespace, key, buf)
+        .map_or(storage::STORAGE_NOT_FOUND, |len| len as i32)
+}
+
+fn storage_write(runtime: &mut PluginRuntime, key: &[u8], value: &[u8]) -> i32 {
+    if !runtime.allowed(PLUGIN_PERM_STORAGE) {
+        return PLUGIN_ERR_DENIED;
+    }
+    let namespace = runtime.slot(runtime.active).name.as_bytes();
+    match runtime.storage.write(namespace, key, value) {
+        Ok(()) => 0,
+        Err(error) => {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("storage_write: {}", error);
+            error.code()
+        }
+    }
 }
 
 // C API wrappers
@@ -370,7 +1095,7 @@ unsafe extern "C" fn gfx_set_pixel(x: i32, y: i32, color: u16) {
 }
 
 unsafe extern "C" fn gfx_get_pixel(x: i32, y: i32) -> u16 {
-    unsafe { RUNTIME_PTR.map_or(0, |runtime| get_pixel(&*runtime, x, y)) }
+    unsafe { RUNTIME_PTR.map_or(0, |runtime| get_pixel(&mut *runtime, x, y)) }
 }
 
 unsafe extern "C" fn gfx_clear(color: u16) {
@@ -413,23 +1138,149 @@ unsafe extern "C" fn gfx_blit(x: i32, y: i32, w: i32, h: i32, data: *const u16)
     }
 }
 
+unsafe extern "C" fn gfx_draw_text(x: i32, y: i32, text: *const u8, len: u32, color: u16) {
+    unsafe {
+        if let (Some(runtime), Some(text)) = (RUNTIME_PTR, text_bytes(text, len)) {
+            draw_text(&mut *runtime, x, y, text, color);
+        }
+    }
+}
+
+unsafe extern "C" fn gfx_text_width(text: *const u8, len: u32) -> i32 {
+    unsafe { text_bytes(text, len).map_or(0, font::text_width) }
+}
+
+// Cluster data
+unsafe extern "C" fn data_cluster_seat_count(cluster: u32) -> u32 {
+    unsafe { RUNTIME_PTR.map_or(0, |runtime| (*runtime).cluster_seat_count(cluster)) }
+}
+
+unsafe extern "C" fn data_seat(cluster: u32, index: u32, out: *mut SeatInfo) -> i32 {
+    unsafe {
+        let Some(runtime) = RUNTIME_PTR else {
+            return DATA_NOT_FOUND;
+        };
+        match (*runtime).cluster_seat(cluster, index) {
+            Some(seat) if !out.is_null() => {
+                out.write_unaligned(seat);
+                0
+            }
+            _ => DATA_NOT_FOUND,
+        }
+    }
+}
+
+unsafe extern "C" fn data_cluster_attr_mask(cluster: u32) -> u32 {
+    unsafe { RUNTIME_PTR.map_or(0, |runtime| (*runtime).cluster_attr_mask(cluster)) }
+}
+
 // System utilities
 unsafe extern "C" fn sys_random() -> u32 {
-    static mut SEED: u32 = 0xDEADBEEF;
     unsafe {
-        SEED = SEED.wrapping_mul(1103515245).wrapping_add(12345);
-        SEED
+        RNG_STATE = RNG_STATE.wrapping_mul(1103515245).wrapping_add(12345);
+        RNG_STATE
     }
 }
 
 unsafe extern "C" fn sys_millis() -> u32 {
     unsafe {
         RUNTIME_PTR.map_or(0, |runtime| {
-            (*runtime).framebuffer.frame_counter.saturating_mul(16)
+            (*runtime)
+                .main
+                .framebuffer
+                .frame_counter
+                .saturating_mul(FRAME_TIME_MS)
         })
     }
 }
 
+unsafe extern "C" fn sys_storage_read(
+    key: *const u8,
+    key_len: u32,
+    buf: *mut u8,
+    buf_len: u32,
+) -> i32 {
+    unsafe {
+        let (Some(runtime), Some(key)) = (
+            RUNTIME_PTR,
+            storage_bytes(key, key_len, storage::MAX_KEY_LEN),
+        ) else {
+            return storage::STORAGE_NOT_FOUND;
+        };
+        // Values are never longer, so neither is what gets copied
+        let buf_len = (buf_len as usize).min(storage::MAX_VALUE_LEN);
+        let buf = if buf.is_null() {
+            &mut []
+        } else {
+            core::slice::from_raw_parts_mut(buf, buf_len)
+        };
+        storage_read(&mut *runtime, key, buf)
+    }
+}
+
+unsafe extern "C" fn sys_storage_write(
+    key: *const u8,
+    key_len: u32,
+    data: *const u8,
+    len: u32,
+) -> i32 {
+    unsafe {
+        let Some(runtime) = RUNTIME_PTR else {
+            return storage::STORAGE_ERR_FULL;
+        };
+        let Some(key) = storage_bytes(key, key_len, storage::MAX_KEY_LEN) else {
+            return storage::STORAGE_ERR_INVALID_KEY;
+        };
+        let Some(value) = storage_bytes(data, len, storage::MAX_VALUE_LEN) else {
+            return storage::STORAGE_ERR_TOO_LARGE;
+        };
+        storage_write(&mut *runtime, key, value)
+    }
+}
+
+unsafe extern "C" fn sys_tone(freq_hz: u32, duration_ms: u32) -> i32 {
+    unsafe {
+        let Some(runtime) = RUNTIME_PTR else {
+            return PLUGIN_ERR_DENIED;
+        };
+        let runtime = &mut *runtime;
+        if !runtime.allowed(PLUGIN_PERM_SOUND) {
+            return PLUGIN_ERR_DENIED;
+        }
+        runtime.tone = Some(Tone {
+            freq_hz,
+            duration_ms,
+        });
+        0
+    }
+}
+
+unsafe extern "C" fn sys_display_info(out: *mut DisplayInfo) {
+    unsafe {
+        if let (Some(runtime), false) = (RUNTIME_PTR, out.is_null()) {
+            out.write((*runtime).display_info);
+        }
+    }
+}
+
+unsafe extern "C" fn sys_read_asset(asset: u32, offset: u32, buf: *mut u8, buf_len: u32) -> i32 {
+    unsafe {
+        let Some(runtime) = RUNTIME_PTR else {
+            return ASSET_NOT_FOUND;
+        };
+        let runtime = &*runtime;
+        let Some(assets) = runtime.slot(runtime.active).assets else {
+            return ASSET_NOT_FOUND;
+        };
+        let buf = if buf.is_null() {
+            &mut []
+        } else {
+            core::slice::from_raw_parts_mut(buf, buf_len as usize)
+        };
+        assets.read(asset as usize, offset as usize, buf)
+    }
+}
+
 unsafe extern "C" fn sys_rgb(r: u8, g: u8, b: u8) -> u16 {
     ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | ((b as u16 & 0xF8) >> 3)
 }
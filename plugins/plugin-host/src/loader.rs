@@ -0,0 +1,457 @@
+//! Plugin upload over a serial link, to try a plugin without reflashing
+//!
+//! The firmware's USB task feeds what it reads from its CDC ACM port to an
+//! [`UploadReceiver`], which decodes the upload frames of
+//! `cluster_core::framing` and stages the binary. The render loop calls
+//! [`UsbLoader::poll`] between updates, which swaps the main plugin for the
+//! staged one. The staging buffer goes back and forth between the two, so
+//! neither waits on a lock while the other copies or initializes a plugin.
+
+use crate::{MAIN_LOAD_BUFFER_SIZE, PluginRuntime};
+use cluster_core::framing::{
+    self, Crc32, Frame, FrameDecoder, KIND_PLUGIN_BEGIN, KIND_PLUGIN_CHUNK, KIND_PLUGIN_END,
+    KIND_PLUGIN_STATUS, PLUGIN_CHUNK_SIZE,
+};
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use plugin_api::{PLUGIN_API_VERSION, PLUGIN_MAGIC};
+
+type Staging = &'static mut [u8; MAIN_LOAD_BUFFER_SIZE];
+
+/// Outcome of an upload, as sent back in the status frame
+pub type UploadResult = Result<(), &'static str>;
+
+/// Longest status message sent back
+const MAX_MESSAGE_LEN: usize = 64;
+
+/// Size of the largest status frame
+pub const REPLY_SIZE: usize = framing::frame_size(1 + MAX_MESSAGE_LEN);
+
+/// Bytes before the data in a chunk payload: its offset
+const CHUNK_OFFSET_SIZE: usize = 4;
+
+static mut UPLOAD_BUFFER: [u8; MAIN_LOAD_BUFFER_SIZE] = [0; MAIN_LOAD_BUFFER_SIZE];
+static UPLOAD_BUFFER_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Hand-over of uploaded plugins from the USB task to the render loop
+pub struct UsbLoader {
+    /// Staged binary and its size, waiting for the render loop
+    uploaded: Signal<CriticalSectionRawMutex, (Staging, usize)>,
+    /// Staging buffer given back, with the outcome of the load
+    loaded: Signal<CriticalSectionRawMutex, (Staging, UploadResult)>,
+}
+
+impl UsbLoader {
+    pub const fn new() -> Self {
+        Self {
+            uploaded: Signal::new(),
+            loaded: Signal::new(),
+        }
+    }
+
+    /// Receiver for the USB task, staging uploads in a buffer of the
+    /// main load buffer's size
+    ///
+    /// # Panics
+    /// If called more than once, as there is a single staging buffer.
+    pub fn receiver(&'static self) -> UploadReceiver {
+        assert!(
+            !UPLOAD_BUFFER_TAKEN.swap(true, Ordering::AcqRel),
+            "plugin upload receiver already taken"
+        );
+        // SAFETY: handed out once, checked above
+        let staging = unsafe { &mut *addr_of_mut!(UPLOAD_BUFFER) };
+        UploadReceiver {
+            loader: self,
+            staging: Some(staging),
+            decoder: FrameDecoder::new(),
+            upload: None,
+            reply: [0; REPLY_SIZE],
+            sequence: 0,
+        }
+    }
+
+    /// Swap the main plugin for the one uploaded since the last call, if
+    /// any, returning the outcome of loading it
+    ///
+    /// The old plugin is cleaned up first. If the new one fails to load, the
+    /// main slot stays empty until the next upload.
+    pub fn poll(&self, runtime: &mut PluginRuntime) -> Option<UploadResult> {
+        let (staging, size) = self.uploaded.try_take()?;
+        runtime.unload_plugin();
+        let result = runtime.load_plugin(&staging[..size]);
+        self.loaded.signal((staging, result));
+        Some(result)
+    }
+}
+
+impl Default for UsbLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Upload in progress
+struct Upload {
+    size: usize,
+    crc: u32,
+    received: usize,
+    received_crc: Crc32,
+}
+
+/// What a frame leads to
+enum Step {
+    Continue,
+    Reply(UploadResult),
+    Complete(usize),
+}
+
+/// Decoder of the upload frames read by the USB task
+pub struct UploadReceiver {
+    loader: &'static UsbLoader,
+    /// `None` only while the render loop loads from it
+    staging: Option<Staging>,
+    decoder: FrameDecoder<{ CHUNK_OFFSET_SIZE + PLUGIN_CHUNK_SIZE }>,
+    upload: Option<Upload>,
+    reply: [u8; REPLY_SIZE],
+    sequence: u8,
+}
+
+impl UploadReceiver {
+    /// Drop the upload in progress, e.g. when the sender disconnects
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+        self.upload = None;
+    }
+
+    /// Feed the next byte read from the serial link
+    ///
+    /// Returns the status frame to write back after the end of an upload
+    /// or a rejected frame. At the end of an upload this waits for the
+    /// render loop to load the plugin.
+    pub async fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        let result = match self.receive(byte)? {
+            Step::Continue => return None,
+            Step::Reply(result) => result,
+            Step::Complete(size) => {
+                let staging = self.staging.take()?;
+                self.loader.uploaded.signal((staging, size));
+                let (staging, result) = self.loader.loaded.wait().await;
+                self.staging = Some(staging);
+                result
+            }
+        };
+        Some(self.encode_reply(result))
+    }
+
+    /// Decode `byte`, returning what the frame it completes leads to
+    fn receive(&mut self, byte: u8) -> Option<Step> {
+        let staging = self.staging.as_deref_mut()?;
+        Some(match self.decoder.push(byte)? {
+            Ok(frame) => step(&mut self.upload, staging, frame),
+            Err(_) if self.upload.is_some() => {
+                self.upload = None;
+                Step::Reply(Err("Corrupted frame, upload dropped"))
+            }
+            Err(_) => Step::Continue,
+        })
+    }
+
+    fn encode_reply(&mut self, result: UploadResult) -> &[u8] {
+        let (status, message) = match result {
+            Ok(()) => (0, "Plugin loaded"),
+            Err(message) => (1, message),
+        };
+        let message = &message.as_bytes()[..message.len().min(MAX_MESSAGE_LEN)];
+        let mut payload = [0; 1 + MAX_MESSAGE_LEN];
+        payload[0] = status;
+        payload[1..=message.len()].copy_from_slice(message);
+
+        let sequence = self.sequence;
+        self.sequence = sequence.wrapping_add(1);
+        let len = framing::encode(
+            KIND_PLUGIN_STATUS,
+            sequence,
+            &payload[..=message.len()],
+            &mut self.reply,
+        )
+        .unwrap_or(0);
+        &self.reply[..len]
+    }
+}
+
+fn word(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn step(upload: &mut Option<Upload>, staging: &mut [u8], frame: Frame<'_>) -> Step {
+    match frame.kind {
+        KIND_PLUGIN_BEGIN => {
+            *upload = None;
+            let (Some(size), Some(crc)) = (word(frame.payload, 0), word(frame.payload, 4)) else {
+                return Step::Reply(Err("Malformed upload start"));
+            };
+            let size = size as usize;
+            if size > staging.len() {
+                return Step::Reply(Err("Plugin too large for load buffer"));
+            }
+            *upload = Some(Upload {
+                size,
+                crc,
+                received: 0,
+                received_crc: Crc32::new(),
+            });
+            Step::Continue
+        }
+        KIND_PLUGIN_CHUNK => {
+            // The rest of an upload already rejected, the end reports it
+            let Some(progress) = upload.as_mut() else {
+                return Step::Continue;
+            };
+            let offset = word(frame.payload, 0);
+            let data = frame.payload.get(CHUNK_OFFSET_SIZE..).unwrap_or_default();
+            let end = progress.received + data.len();
+            if offset != Some(progress.received as u32) || end > progress.size {
+                *upload = None;
+                return Step::Reply(Err("Chunk out of order, upload dropped"));
+            }
+            staging[progress.received..end].copy_from_slice(data);
+            progress.received_crc.update(data);
+            progress.received = end;
+            Step::Continue
+        }
+        KIND_PLUGIN_END => {
+            let Some(progress) = upload.take() else {
+                return Step::Reply(Err("End outside an upload"));
+            };
+            if progress.received != progress.size {
+                return Step::Reply(Err("Upload incomplete"));
+            }
+            if progress.received_crc.finish() != progress.crc {
+                return Step::Reply(Err("Upload checksum mismatch"));
+            }
+            // The runtime checks the whole header again when loading
+            let bytes = &staging[..progress.size];
+            if word(bytes, 0) != Some(PLUGIN_MAGIC) {
+                return Step::Reply(Err("Invalid plugin magic number"));
+            }
+            if word(bytes, 4) != Some(PLUGIN_API_VERSION) {
+                return Step::Reply(Err("Plugin API version mismatch"));
+            }
+            Step::Complete(progress.size)
+        }
+        // Other traffic, e.g. display frames, is not for the loader
+        _ => Step::Continue,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use cluster_core::framing::{KIND_DISPLAY_FRAME, crc32};
+    use std::boxed::Box;
+
+    const IMAGE_SIZE: usize = 2 * PLUGIN_CHUNK_SIZE + 500;
+
+    /// Receiver with a staging buffer of its own, for the render loop of
+    /// no test to load from
+    fn receiver() -> UploadReceiver {
+        static LOADER: UsbLoader = UsbLoader::new();
+        UploadReceiver {
+            loader: &LOADER,
+            staging: Some(Box::leak(Box::new([0; MAIN_LOAD_BUFFER_SIZE]))),
+            decoder: FrameDecoder::new(),
+            upload: None,
+            reply: [0; REPLY_SIZE],
+            sequence: 0,
+        }
+    }
+
+    /// Plugin binary spanning several chunks, with a valid magic and version
+    fn image() -> [u8; IMAGE_SIZE] {
+        let mut image = [0; IMAGE_SIZE];
+        for (i, byte) in image.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        image[..4].copy_from_slice(&PLUGIN_MAGIC.to_le_bytes());
+        image[4..8].copy_from_slice(&PLUGIN_API_VERSION.to_le_bytes());
+        image
+    }
+
+    /// Feed `bytes`, returning what the last frame they complete leads to
+    fn feed(receiver: &mut UploadReceiver, bytes: &[u8]) -> Option<Step> {
+        bytes
+            .iter()
+            .fold(None, |last, &byte| receiver.receive(byte).or(last))
+    }
+
+    /// Send a frame of `kind`, returning what the receiver made of it
+    fn send(receiver: &mut UploadReceiver, kind: u8, payload: &[u8]) -> Option<Step> {
+        let mut frame = [0; framing::frame_size(CHUNK_OFFSET_SIZE + PLUGIN_CHUNK_SIZE)];
+        let len = framing::encode(kind, 0, payload, &mut frame).unwrap();
+        feed(receiver, &frame[..len])
+    }
+
+    fn begin(receiver: &mut UploadReceiver, size: usize, crc: u32) -> Option<Step> {
+        let mut payload = [0; 8];
+        payload[..4].copy_from_slice(&(size as u32).to_le_bytes());
+        payload[4..].copy_from_slice(&crc.to_le_bytes());
+        send(receiver, KIND_PLUGIN_BEGIN, &payload)
+    }
+
+    fn chunk(receiver: &mut UploadReceiver, offset: usize, data: &[u8]) -> Option<Step> {
+        let mut payload = [0; CHUNK_OFFSET_SIZE + PLUGIN_CHUNK_SIZE];
+        payload[..CHUNK_OFFSET_SIZE].copy_from_slice(&(offset as u32).to_le_bytes());
+        payload[CHUNK_OFFSET_SIZE..][..data.len()].copy_from_slice(data);
+        send(
+            receiver,
+            KIND_PLUGIN_CHUNK,
+            &payload[..CHUNK_OFFSET_SIZE + data.len()],
+        )
+    }
+
+    /// Upload the chunks of `data` announced as `size` bytes of checksum
+    /// `crc`, returning what the end frame leads to
+    fn upload(receiver: &mut UploadReceiver, data: &[u8], size: usize, crc: u32) -> Step {
+        assert!(matches!(begin(receiver, size, crc), Some(Step::Continue)));
+        for (i, data) in data.chunks(PLUGIN_CHUNK_SIZE).enumerate() {
+            let step = chunk(receiver, i * PLUGIN_CHUNK_SIZE, data);
+            assert!(matches!(step, Some(Step::Continue)));
+        }
+        send(receiver, KIND_PLUGIN_END, &[]).unwrap()
+    }
+
+    fn rejection(step: Option<Step>) -> Option<&'static str> {
+        match step? {
+            Step::Reply(Err(message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_upload_is_staged() {
+        let mut receiver = receiver();
+        let image = image();
+
+        let step = upload(&mut receiver, &image, IMAGE_SIZE, crc32(&image));
+        assert!(matches!(step, Step::Complete(IMAGE_SIZE)));
+        assert_eq!(receiver.staging.as_ref().unwrap()[..IMAGE_SIZE], image);
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut receiver = receiver();
+
+        // Frames for the display, and garbage between frames, are skipped
+        assert!(matches!(
+            send(&mut receiver, KIND_DISPLAY_FRAME, b"pixels"),
+            Some(Step::Continue)
+        ));
+        assert!(feed(&mut receiver, &[0x00, 0xA5, 0xFF]).is_none());
+
+        // A frame corrupted outside an upload is dropped silently, inside
+        // one it drops the upload
+        let mut frame = [0; framing::frame_size(4)];
+        framing::encode(KIND_PLUGIN_CHUNK, 0, &[0; 4], &mut frame).unwrap();
+        frame[framing::HEADER_SIZE] ^= 1;
+        assert!(matches!(feed(&mut receiver, &frame), Some(Step::Continue)));
+        begin(&mut receiver, IMAGE_SIZE, 0);
+        assert_eq!(
+            rejection(feed(&mut receiver, &frame)),
+            Some("Corrupted frame, upload dropped")
+        );
+        assert!(receiver.upload.is_none());
+
+        // Replies are status frames, numbered in sequence
+        let mut decoder = FrameDecoder::<{ 1 + MAX_MESSAGE_LEN }>::new();
+        for sequence in 0..2 {
+            let reply = receiver.encode_reply(Err("Upload incomplete")).to_vec();
+            let (last, bytes) = reply.split_last().unwrap();
+            assert!(bytes.iter().all(|&byte| decoder.push(byte).is_none()));
+            let frame = decoder.push(*last).unwrap().unwrap();
+            assert_eq!(frame.kind, KIND_PLUGIN_STATUS);
+            assert_eq!(frame.sequence, sequence);
+            assert_eq!(frame.payload, b"\x01Upload incomplete");
+        }
+    }
+
+    #[test]
+    fn test_truncated_upload_is_rejected() {
+        let mut receiver = receiver();
+        let image = image();
+
+        let step = upload(
+            &mut receiver,
+            &image[..2 * PLUGIN_CHUNK_SIZE],
+            IMAGE_SIZE,
+            crc32(&image),
+        );
+        assert_eq!(rejection(Some(step)), Some("Upload incomplete"));
+        assert_eq!(
+            rejection(send(&mut receiver, KIND_PLUGIN_END, &[])),
+            Some("End outside an upload")
+        );
+
+        // A chunk skipping ahead drops the upload too
+        begin(&mut receiver, IMAGE_SIZE, crc32(&image));
+        assert_eq!(
+            rejection(chunk(
+                &mut receiver,
+                PLUGIN_CHUNK_SIZE,
+                &image[..PLUGIN_CHUNK_SIZE]
+            )),
+            Some("Chunk out of order, upload dropped")
+        );
+    }
+
+    #[test]
+    fn test_bad_checksum_is_rejected() {
+        let mut receiver = receiver();
+        let image = image();
+
+        let step = upload(&mut receiver, &image, IMAGE_SIZE, crc32(&image) ^ 1);
+        assert_eq!(rejection(Some(step)), Some("Upload checksum mismatch"));
+
+        let mut not_a_plugin = image;
+        not_a_plugin[0] ^= 1;
+        let step = upload(
+            &mut receiver,
+            &not_a_plugin,
+            IMAGE_SIZE,
+            crc32(&not_a_plugin),
+        );
+        assert_eq!(rejection(Some(step)), Some("Invalid plugin magic number"));
+    }
+
+    #[test]
+    fn test_oversized_image_is_rejected() {
+        let mut receiver = receiver();
+        let image = image();
+
+        assert_eq!(
+            rejection(begin(&mut receiver, MAIN_LOAD_BUFFER_SIZE + 1, 0)),
+            Some("Plugin too large for load buffer")
+        );
+        // The rest of the upload is ignored, and its end reported
+        assert!(matches!(
+            chunk(&mut receiver, 0, &image[..PLUGIN_CHUNK_SIZE]),
+            Some(Step::Continue)
+        ));
+        assert_eq!(
+            rejection(send(&mut receiver, KIND_PLUGIN_END, &[])),
+            Some("End outside an upload")
+        );
+
+        // Chunks past the announced size are refused
+        begin(&mut receiver, PLUGIN_CHUNK_SIZE - 1, crc32(&image));
+        assert_eq!(
+            rejection(chunk(&mut receiver, 0, &image[..PLUGIN_CHUNK_SIZE])),
+            Some("Chunk out of order, upload dropped")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Metadata of the plugins built into the firmware
+//!
+//! The build script reads the header of each plugin it embeds, so a menu
+//! can list the plugins with their names and icons, and grey out the ones
+//! this host can't run, without loading any of them.
+
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::pixelcolor::raw::RawU16;
+use plugin_api::{PLUGIN_API_VERSION, PluginIcon};
+
+/// A plugin of [`plugin_registry`](crate::plugin_registry), as its header
+/// declares it
+#[derive(Clone, Copy, Debug)]
+pub struct PluginInfo {
+    /// Source the plugin was built from, as in `get_plugin_list`
+    pub id: &'static str,
+    /// Name from the header
+    pub name: &'static str,
+    pub api_version: u32,
+    pub min_host_version: u32,
+    pub max_host_version: u32,
+    /// `PLUGIN_CAP_*` flags
+    pub capabilities: u32,
+    /// `PLUGIN_PERM_*` flags the plugin asks for
+    pub permissions: u32,
+    /// `PLUGIN_REQ_*` flags
+    pub requirements: u32,
+    /// Offset of the plugin's [`PluginIcon`] in `bytes`
+    pub icon_offset: Option<usize>,
+    /// The binary, for [`PluginRuntime::load_plugin`](crate::PluginRuntime::load_plugin)
+    pub bytes: &'static [u8],
+}
+
+impl PluginInfo {
+    /// Check that this host's API version is one the plugin works with
+    ///
+    /// Loading can still fail on requirements the firmware doesn't meet,
+    /// e.g. storage it doesn't grant.
+    pub fn supports_host(&self) -> bool {
+        self.api_version == PLUGIN_API_VERSION
+            && PLUGIN_API_VERSION >= self.min_host_version
+            && (self.max_host_version == 0 || PLUGIN_API_VERSION <= self.max_host_version)
+    }
+
+    /// Icon pixels, row by row, if the plugin has one
+    pub fn icon(&self) -> Option<impl Iterator<Item = Rgb565> + 'static> {
+        let offset = self.icon_offset?;
+        let bytes = self.bytes.get(offset..offset + size_of::<PluginIcon>())?;
+        let (pixels, _) = bytes.as_chunks::<2>();
+        Some(
+            pixels
+                .iter()
+                .map(|&pixel| RawU16::new(u16::from_le_bytes(pixel)).into()),
+        )
+    }
+}
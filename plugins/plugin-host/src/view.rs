@@ -0,0 +1,23 @@
+//! Plugins as a full-screen view of the firmware main loop
+
+use crate::PluginRuntime;
+use cluster_core::visualization::{RenderCtx, Renderer};
+use embedded_graphics_core::pixelcolor::Rgb565;
+use embedded_graphics_core::pixelcolor::raw::RawU16;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+use plugin_api::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Step the loaded plugins with the frame's inputs and show their output
+impl<D: DrawTarget<Color = Rgb565>> Renderer<D> for PluginRuntime {
+    fn render(&mut self, target: &mut D, ctx: &RenderCtx<'_>) -> Result<(), D::Error> {
+        self.update_with_layout(ctx.inputs, ctx.layout);
+
+        let area = Rectangle::new(
+            Point::zero(),
+            Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
+        );
+        let pixels = self.framebuffer().pixels.iter();
+        target.fill_contiguous(&area, pixels.map(|&raw| Rgb565::from(RawU16::new(raw))))
+    }
+}
@@ -0,0 +1,214 @@
+//! Repository maintenance tasks, run with `cargo xtask <task>`
+//!
+//! Tasks:
+//! - `schema [dir]`: write the layout JSON schemas to `dir` (default
+//!   `schema/` at the root of the repository)
+//! - `c-plugins [dir]`: build the C plugin examples against the generated
+//!   `plugin_api.h` as shared libraries in `dir` (default
+//!   `target/c-plugins`), with `$CC` or `cc`, warnings as errors
+//! - `plugin-pack <plugin.bin> <out.bin> [asset...]`: append asset files to
+//!   a plugin binary built for the RP2350, making a segmented binary whose
+//!   assets stay in flash
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::{env, fs, io};
+
+use cluster_core::schema::SCHEMAS;
+use plugin_api::assets::{CODE_SIZE_OFFSET, build_table};
+use plugin_api::{PLUGIN_API_VERSION, PLUGIN_MAGIC};
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("schema") => {
+            let dir = args
+                .next()
+                .map_or_else(|| workspace_root().join("schema"), PathBuf::from);
+            write_schemas(&dir)
+        }
+        Some("c-plugins") => {
+            let dir = args.next().map_or_else(
+                || workspace_root().join("target").join("c-plugins"),
+                PathBuf::from,
+            );
+            build_c_plugins(&dir)
+        }
+        Some("plugin-pack") => match (args.next(), args.next()) {
+            (Some(plugin), Some(out)) => {
+                let assets: Vec<PathBuf> = args.map(PathBuf::from).collect();
+                pack_plugin(Path::new(&plugin), Path::new(&out), &assets)
+            }
+            _ => {
+                eprintln!("Usage: cargo xtask plugin-pack <plugin.bin> <out.bin> [asset...]");
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => {
+            eprintln!("Usage: cargo xtask schema [dir]");
+            eprintln!("       cargo xtask c-plugins [dir]");
+            eprintln!("       cargo xtask plugin-pack <plugin.bin> <out.bin> [asset...]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask is inside the workspace")
+        .to_path_buf()
+}
+
+fn write_schemas(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for &(file, schema) in SCHEMAS {
+        let mut json = serde_json::to_string_pretty(&schema()).map_err(io::Error::other)?;
+        json.push('\n');
+
+        let path = dir.join(file);
+        fs::write(&path, json)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Compile every `.c` file of `plugins/plugin-examples-c` for the host
+///
+/// The firmware builds the same sources for the RP2350 in plugin-host's
+/// build script; this checks them with the host compiler, which doesn't
+/// need an ARM toolchain, and produces libraries the simulator can load.
+fn build_c_plugins(dir: &Path) -> io::Result<()> {
+    let src_dir = workspace_root().join("plugins").join("plugin-examples-c");
+    let include = src_dir.join("common");
+    if !include.join("plugin_api.h").exists() {
+        return Err(io::Error::other(
+            "plugin_api.h is missing, build plugin-api to generate it",
+        ));
+    }
+
+    let mut sources: Vec<PathBuf> = fs::read_dir(&src_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect();
+    sources.sort();
+
+    let (prefix, extension) = if cfg!(target_os = "windows") {
+        ("", "dll")
+    } else if cfg!(target_os = "macos") {
+        ("lib", "dylib")
+    } else {
+        ("lib", "so")
+    };
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    fs::create_dir_all(dir)?;
+
+    for source in &sources {
+        let name = source.file_stem().unwrap_or_default().to_string_lossy();
+        let library = dir.join(format!("{prefix}{name}.{extension}"));
+        let mut command = Command::new(&cc);
+        command
+            .args(["-std=c11", "-Wall", "-Werror", "-O2", "-shared", "-fPIC"])
+            .arg("-I")
+            .arg(&include)
+            .arg(source)
+            .arg("-o")
+            .arg(&library);
+        if cfg!(target_os = "macos") {
+            command.args(["-undefined", "dynamic_lookup"]);
+        }
+
+        let status = command.status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{cc} failed on {}",
+                source.display()
+            )));
+        }
+        println!("Built {}", library.display());
+    }
+    Ok(())
+}
+
+/// Size of plugin-host's main load buffer, which the code segment, data and
+/// `.bss` must fit
+const LOAD_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Split a plugin into the code segment loaded to RAM and asset segments
+/// read from flash
+///
+/// Assets are numbered in the order given. A binary packed before is
+/// repacked with the new assets.
+fn pack_plugin(plugin: &Path, out: &Path, assets: &[PathBuf]) -> io::Result<()> {
+    let mut code = fs::read(plugin)?;
+    let word = |bytes: &[u8], offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+    };
+    if word(&code, 0) != Some(PLUGIN_MAGIC) {
+        return Err(io::Error::other(format!(
+            "{} is not a plugin binary",
+            plugin.display()
+        )));
+    }
+    let api_version = word(&code, 4).unwrap_or_default();
+    if api_version != PLUGIN_API_VERSION {
+        return Err(io::Error::other(format!(
+            "{} is built for API version {api_version}, not {PLUGIN_API_VERSION}",
+            plugin.display()
+        )));
+    }
+    let Some(packed) = word(&code, CODE_SIZE_OFFSET) else {
+        return Err(io::Error::other("plugin header is truncated"));
+    };
+    if packed != 0 {
+        // Packed before: the header must fit the code segment, and the code
+        // segment the binary
+        let packed = packed as usize;
+        if packed < CODE_SIZE_OFFSET + 4 || packed > code.len() {
+            return Err(io::Error::other(format!(
+                "{} has a {packed} byte code segment, shorter than its header or longer \
+                 than the {} byte binary",
+                plugin.display(),
+                code.len()
+            )));
+        }
+        code.truncate(packed);
+    }
+    if code.len() > LOAD_BUFFER_SIZE {
+        return Err(io::Error::other(format!(
+            "code segment is {} bytes, over the {LOAD_BUFFER_SIZE} byte load buffer",
+            code.len()
+        )));
+    }
+
+    let assets = assets
+        .iter()
+        .map(fs::read)
+        .collect::<io::Result<Vec<_>>>()?;
+    let assets: Vec<&[u8]> = assets.iter().map(Vec::as_slice).collect();
+    // Binaries without assets stay whole, as plain plugins
+    let code_len = code.len();
+    let code_size = if assets.is_empty() { 0 } else { code_len };
+    code[CODE_SIZE_OFFSET..CODE_SIZE_OFFSET + 4].copy_from_slice(&(code_size as u32).to_le_bytes());
+    if !assets.is_empty() {
+        code.extend(build_table(&assets));
+    }
+    fs::write(out, &code)?;
+    println!(
+        "Wrote {}: {code_len} bytes of code, {} assets, {} bytes in all",
+        out.display(),
+        assets.len(),
+        code.len()
+    );
+    Ok(())
+}